@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use chumsky::Parser;
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use dargo::{
+    ParseOptions,
+    dargo::queries,
+    emit::ir::join_ir,
+    parse::{lexer::lex_parser, source_map},
+    parse_src_file, typecheck,
+};
+
+// the pipeline always pulls in std, so these need an installed std (~/.duck/std)
+fn fixtures() -> Vec<(&'static str, String)> {
+    let read = |path: &str| std::fs::read_to_string(path).expect("couldn't read bench fixture");
+
+    let large = (0..2000)
+        .map(|i| {
+            format!(
+                "fn f{i}(x: Int) -> Int {{\n    let y: Int = x * {i} + 1;\n    if (y > 10) {{ return y - 1; }}\n    return y;\n}}\n"
+            )
+        })
+        .chain(std::iter::once("fn main() {}\n".to_string()))
+        .collect::<String>();
+
+    vec![
+        ("small", read("tests/valid_programs/hello_world.duck")),
+        ("medium", read("tests/valid_programs/methods.duck")),
+        ("large", large),
+    ]
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let fixtures = fixtures()
+        .into_iter()
+        .map(|(name, src)| {
+            let file_name = format!("{name}.duck");
+            let file_id = source_map::add_file(&file_name, src.as_str());
+            (name, file_name, file_id, src)
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("lex");
+    for (name, _, file_id, src) in &fixtures {
+        group.bench_with_input(BenchmarkId::from_parameter(name), src, |b, src| {
+            b.iter(|| {
+                lex_parser(*file_id)
+                    .parse(src.as_str())
+                    .into_result()
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("parse");
+    for (name, file_name, file_id, src) in &fixtures {
+        let tokens = dargo::lex(*file_id, src);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &tokens, |b, tokens| {
+            b.iter_batched(
+                // the asts (and the tokens of std) would come out of the queries after the
+                // first iteration, which would only measure the cache lookup
+                || {
+                    queries::ASTS.lock().unwrap().clear();
+                    queries::TOKENS.lock().unwrap().clear();
+                    tokens.clone()
+                },
+                |tokens| {
+                    parse_src_file(
+                        Path::new(file_name),
+                        *file_id,
+                        src,
+                        tokens,
+                        &ParseOptions::default(),
+                    )
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("typecheck");
+    for (name, file_name, file_id, src) in &fixtures {
+        let tokens = dargo::lex(*file_id, src);
+        let ast = parse_src_file(
+            Path::new(file_name),
+            *file_id,
+            src,
+            tokens,
+            &ParseOptions::default(),
+        );
+        group.bench_with_input(BenchmarkId::from_parameter(name), &ast, |b, ast| {
+            b.iter_batched(
+                || ast.clone(),
+                |mut ast| typecheck(&mut ast),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("emit");
+    for (name, file_name, file_id, src) in &fixtures {
+        let tokens = dargo::lex(*file_id, src);
+        let mut ast = parse_src_file(
+            Path::new(file_name),
+            *file_id,
+            src,
+            tokens,
+            &ParseOptions::default(),
+        );
+        let type_env = typecheck(&mut ast);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &ast, |b, ast| {
+            b.iter_batched(
+                || (ast.clone(), type_env.clone()),
+                |(ast, mut type_env)| join_ir(&ast.emit("main".into(), &mut type_env)),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
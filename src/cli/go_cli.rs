@@ -53,14 +53,19 @@ pub fn format(go_source_file: &Path) -> Result<(), (String, GoCliErrKind)> {
 pub fn build(
     compile_output_target: &Path,
     go_output_file: &Path,
+    target_os: Option<&str>,
 ) -> Result<(), (String, GoCliErrKind)> {
-    let cmd_result = Command::new("go")
-        .args([
-            OsString::from("build"),
-            OsString::from("-o"),
-            compile_output_target.as_os_str().to_owned(),
-            go_output_file.as_os_str().to_owned(),
-        ])
+    let mut command = Command::new("go");
+    command.args([
+        OsString::from("build"),
+        OsString::from("-o"),
+        compile_output_target.as_os_str().to_owned(),
+        go_output_file.as_os_str().to_owned(),
+    ]);
+    if let Some(target_os) = target_os {
+        command.env("GOOS", target_os);
+    }
+    let cmd_result = command
         .spawn()
         .map_err(|err| {
             (
@@ -121,7 +121,6 @@ pub fn build(build_args: &BuildArgs) -> Result<BuildOutput, (String, BuildErrKin
             #[cfg(not(target_os = "windows"))]
             let result = symlink(absolute_src_dir, target_dir);
 
-
             result.map_err(|err| {
                 (
                     format!("{}{} error creating symlink - {err}", Tag::IO, Tag::Err,),
@@ -141,6 +140,10 @@ pub fn build(build_args: &BuildArgs) -> Result<BuildOutput, (String, BuildErrKin
         file: copy_target_clone,
         output_name: build_args.output_name.clone(),
         optimize_go: build_args.optimize_go,
+        features: build_args.features.clone(),
+        target_os: build_args.target_os.clone(),
+        with_tests: false,
+        module_dir: None,
     })
     .map_err(|err| {
         (
@@ -0,0 +1,180 @@
+use std::{io::ErrorKind as IOErrKind, path::Path};
+
+use crate::{
+    dargo::cli::CheckArgs,
+    lex_partial,
+    parse::{parse_report, source_file_parser::SourceFile, source_map},
+    parse_partial,
+    semantics::ident_mangler::unmangle,
+    tags::Tag,
+};
+
+#[derive(Debug)]
+pub enum CheckErrKind {
+    IOErr(IOErrKind),
+    ParseErrors,
+}
+
+// the items of a file by kind, a broken item is skipped by the parser and so is missing here
+fn outline(src_file: &SourceFile) -> Vec<(&'static str, Vec<String>)> {
+    let names = |names: Vec<String>| {
+        names
+            .into_iter()
+            .map(|name| unmangle(&name).join("::"))
+            .collect::<Vec<_>>()
+    };
+
+    vec![
+        (
+            "fn",
+            names(
+                src_file
+                    .function_definitions
+                    .iter()
+                    .map(|def| def.name.clone())
+                    .collect(),
+            ),
+        ),
+        (
+            "type",
+            names(
+                src_file
+                    .type_definitions
+                    .iter()
+                    .map(|def| def.name.clone())
+                    .collect(),
+            ),
+        ),
+        (
+            "struct",
+            names(
+                src_file
+                    .struct_definitions
+                    .iter()
+                    .map(|def| def.name.clone())
+                    .collect(),
+            ),
+        ),
+        (
+            "const",
+            names(
+                src_file
+                    .const_definitions
+                    .iter()
+                    .map(|def| def.name.clone())
+                    .collect(),
+            ),
+        ),
+        (
+            "let",
+            names(
+                src_file
+                    .global_variables
+                    .iter()
+                    .map(|def| def.name.clone())
+                    .collect(),
+            ),
+        ),
+        (
+            "mod",
+            src_file
+                .sub_modules
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect(),
+        ),
+        (
+            "test",
+            src_file
+                .test_cases
+                .iter()
+                .map(|test_case| format!("{:?}", test_case.name))
+                .collect(),
+        ),
+    ]
+}
+
+// lexes and parses a file without compiling it. every error is reported instead of only the
+// ones up to the first broken phase, and the items that were still parsed are listed
+pub fn check(check_args: &CheckArgs) -> Result<(), (String, CheckErrKind)> {
+    let src_file_contents = source_map::read_source(&check_args.file).map_err(|err| {
+        (
+            format!(
+                "{}{} couldn't read file '{}'",
+                Tag::IO,
+                Tag::Err,
+                check_args.file.display()
+            ),
+            CheckErrKind::IOErr(err.kind()),
+        )
+    })?;
+
+    let src_file_id = source_map::add_file(&check_args.file.to_string_lossy(), src_file_contents);
+    let src_file_contents = source_map::file_contents(src_file_id);
+    let file_name = source_map::file_name(src_file_id);
+
+    let (tokens, lex_errors) = lex_partial(src_file_id, &src_file_contents);
+    let module_dir = check_args.file.parent().unwrap_or(Path::new(""));
+    let (src_file, parse_errors) =
+        parse_partial(module_dir, src_file_id, &src_file_contents, &tokens);
+
+    for err in &lex_errors {
+        parse_report(&file_name, err, &src_file_contents);
+    }
+    // the errors of the declared modules are shown in their own files
+    for err in &parse_errors {
+        let context = err.span().context;
+        parse_report(&context.file_name(), err, &context.file_contents());
+    }
+
+    for (kind, names) in outline(&src_file) {
+        if !names.is_empty() {
+            println!("{kind} {}", names.join(", "));
+        }
+    }
+
+    let error_count = lex_errors.len() + parse_errors.len();
+    if error_count > 0 {
+        return Err((
+            format!("{} {error_count} errors in {file_name}", Tag::Err),
+            CheckErrKind::ParseErrors,
+        ));
+    }
+
+    println!("{}{} {file_name} has no errors", Tag::Dargo, Tag::Check);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broken_items_are_left_out() {
+        let src = "fn a() {} fn b( {} struct S = { x: Int }; \
+                   #[derive(Clone)] struct T = {}; macro m { $x } fn c() {}";
+        let file_id = source_map::add_test_file("check_test", src);
+        let (tokens, lex_errors) = lex_partial(file_id, src);
+        let (src_file, parse_errors) = parse_partial(Path::new(""), file_id, src, &tokens);
+
+        assert_eq!(lex_errors.len(), 1, "{lex_errors:?}");
+        assert!(
+            lex_errors[0]
+                .reason()
+                .to_string()
+                .contains("expected a rule"),
+            "{lex_errors:?}"
+        );
+        assert!(
+            parse_errors
+                .iter()
+                .any(|err| err.reason().to_string().contains("can't derive Clone")),
+            "{parse_errors:?}"
+        );
+        assert!(parse_errors.len() >= 2, "{parse_errors:?}");
+
+        let items = outline(&src_file);
+        assert_eq!(items[0], ("fn", vec!["a".to_string(), "c".to_string()]));
+        assert_eq!(items[2], ("struct", vec!["S".to_string(), "T".to_string()]));
+    }
+}
@@ -2,12 +2,14 @@ use clap::{Parser as CliParser, Subcommand};
 use std::path::PathBuf;
 
 use crate::{
-    dargo::{self, compile::CompileErrKind, init::InitErrKind, run::RunErrKind},
+    dargo::{self, compile::CompileErrKind, init::InitErrKind, run::RunErrKind, test::TestErrKind},
+    parse::attribute_parser::GO_TARGET_OSES,
     tags::Tag,
 };
 
 use super::{
     build::{self, BuildErrKind},
+    check::CheckErrKind,
     clean::CleanErrKind,
 };
 
@@ -23,10 +25,12 @@ pub struct DargoCliParser {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     Build(BuildArgs),
+    Check(CheckArgs),
     Compile(CompileArgs),
     Init(InitArgs),
     Clean,
     Run(RunArgs),
+    Test(TestArgs),
 }
 
 #[derive(clap::Args, Debug)]
@@ -37,6 +41,17 @@ pub struct BuildArgs {
     pub output_name: Option<String>,
     #[arg(long, short = 'G')]
     pub optimize_go: bool,
+    #[arg(long, value_delimiter = ',')]
+    pub features: Vec<String>,
+    // cross compiles for another os, this is the GOOS of the go compiler
+    #[arg(long, value_parser = GO_TARGET_OSES)]
+    pub target_os: Option<String>,
+}
+
+// only lexes and parses the file and reports every error in it, for editors and other tooling
+#[derive(clap::Args, Debug)]
+pub struct CheckArgs {
+    pub file: PathBuf,
 }
 
 #[derive(clap::Args, Debug)]
@@ -46,6 +61,15 @@ pub struct CompileArgs {
     pub output_name: Option<String>,
     #[arg(long, short = 'G')]
     pub optimize_go: bool,
+    #[arg(long, value_delimiter = ',')]
+    pub features: Vec<String>,
+    #[arg(long, value_parser = GO_TARGET_OSES)]
+    pub target_os: Option<String>,
+    #[arg(skip)]
+    pub with_tests: bool,
+    // the doc tests are compiled from .dargo, but their modules are next to the documented file
+    #[arg(skip)]
+    pub module_dir: Option<PathBuf>,
 }
 
 #[derive(clap::Args, Debug)]
@@ -53,6 +77,17 @@ pub struct RunArgs {
     pub file: Option<PathBuf>,
     #[arg(long, short = 'G')]
     pub optimize_go: bool,
+    #[arg(long, value_delimiter = ',')]
+    pub features: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TestArgs {
+    pub file: PathBuf,
+    #[arg(long, short = 'G')]
+    pub optimize_go: bool,
+    #[arg(long, value_delimiter = ',')]
+    pub features: Vec<String>,
 }
 
 #[derive(clap::Args, Debug)]
@@ -69,8 +104,10 @@ pub enum CliErrKind {
     Init(InitErrKind),
     Compile(CompileErrKind),
     Build(BuildErrKind),
+    Check(CheckErrKind),
     Clean(CleanErrKind),
     Run(RunErrKind),
+    Test(TestErrKind),
 }
 
 pub fn run_cli() -> Result<(), (String, CliErrKind)> {
@@ -84,6 +121,10 @@ pub fn run_cli() -> Result<(), (String, CliErrKind)> {
                 )
             })?;
         }
+        Commands::Check(check_args) => {
+            dargo::check::check(&check_args)
+                .map_err(|err| (format!("{}{}", Tag::Dargo, err.0), CliErrKind::Check(err.1)))?;
+        }
         Commands::Compile(compile_args) => {
             dargo::compile::compile(compile_args).map_err(|err| {
                 (
@@ -112,6 +153,14 @@ pub fn run_cli() -> Result<(), (String, CliErrKind)> {
                 )
             })?;
         }
+        Commands::Test(test_args) => {
+            dargo::test::test(&test_args).map_err(|err| {
+                (
+                    format!("{}{}{}", Tag::Dargo, Tag::Test, err.0,),
+                    CliErrKind::Test(err.1),
+                )
+            })?;
+        }
     }
 
     Ok(())
@@ -1,14 +1,16 @@
 use colored::Colorize;
 use lazy_static::lazy_static;
-use std::{ffi::OsString, fs, path::PathBuf};
+use std::{ffi::OsString, path::PathBuf};
 
 use crate::{
-    DARGO_DOT_DIR,
+    DARGO_DOT_DIR, ParseOptions,
     cli::go_cli::{self, GoCliErrKind},
-    dargo::cli::CompileArgs,
+    dargo::{cli::CompileArgs, loader::project_features, queries},
     emit::ir::join_ir,
     go_fixup::remove_unused_imports::cleanup_go_source,
-    lex, parse_src_file,
+    lex,
+    parse::{lexer::escape_go_idents, source_map},
+    parse_src_file,
     tags::Tag,
     typecheck, write_in_duck_dotdir,
 };
@@ -19,6 +21,7 @@ pub enum CompileErrKind {
     TargetPathIsDirectory,
     FileNotFound,
     CannotReadFile,
+    UnknownFeature,
     GoCli(GoCliErrKind),
 }
 
@@ -31,6 +34,8 @@ pub struct CompileOutput {
 }
 
 pub fn compile(compile_args: CompileArgs) -> Result<CompileOutput, (String, CompileErrKind)> {
+    let features = project_features(&compile_args.features)
+        .map_err(|err| (err, CompileErrKind::UnknownFeature))?;
     let src_file: PathBuf = compile_args.file;
     let binary_output_name: Option<String> = compile_args.output_name;
 
@@ -64,7 +69,7 @@ pub fn compile(compile_args: CompileArgs) -> Result<CompileOutput, (String, Comp
         return Err((message, CompileErrKind::TargetPathIsDirectory));
     }
 
-    let src_file_name: &'static str = src_file
+    let src_file_name: &str = src_file
         .file_name()
         .ok_or_else(|| {
             (
@@ -86,31 +91,41 @@ pub fn compile(compile_args: CompileArgs) -> Result<CompileOutput, (String, Comp
                 ),
                 CompileErrKind::CorruptedFileName,
             )
-        })?
-        .to_string()
-        .leak();
+        })?;
 
-    let src_file_file_contents: &'static str = fs::read_to_string(&src_file)
-        .map_err(|err| {
-            (
-                format!(
-                    "{}{} couldn't read file '{}'. msg='{}'",
-                    *COMPILE_TAG,
-                    Tag::Err,
-                    src_file_name.bright_blue(),
-                    err.to_string().bright_red()
-                ),
-                CompileErrKind::CannotReadFile,
-            )
-        })?
-        .to_string()
-        .leak();
-
-    let tokens = lex(src_file_name, src_file_file_contents);
-    let mut src_file_ast = parse_src_file(&src_file, src_file_name, src_file_file_contents, tokens);
-    let mut type_env = typecheck(&mut src_file_ast);
-    let mut go_code = join_ir(&src_file_ast.emit("main".into(), &mut type_env));
-    go_code = cleanup_go_source(&go_code, true);
+    let src_file_file_contents = source_map::read_source(&src_file).map_err(|err| {
+        (
+            format!(
+                "{}{} couldn't read file '{}'. msg='{}'",
+                *COMPILE_TAG,
+                Tag::Err,
+                src_file_name.bright_blue(),
+                err.to_string().bright_red()
+            ),
+            CompileErrKind::CannotReadFile,
+        )
+    })?;
+
+    let src_file_id = source_map::add_file_as(&src_file, src_file_name, src_file_file_contents);
+    let src_file_file_contents = source_map::file_contents(src_file_id);
+    let tokens = lex(src_file_id, &src_file_file_contents);
+    let src_file_ast = parse_src_file(
+        &src_file,
+        src_file_id,
+        &src_file_file_contents,
+        tokens,
+        &ParseOptions {
+            with_tests: compile_args.with_tests,
+            features,
+            target_os: compile_args.target_os.clone(),
+            module_dir: compile_args.module_dir.clone(),
+        },
+    );
+    let go_code = queries::go_code(src_file_id, src_file_ast, |mut src_file_ast| {
+        let mut type_env = queries::typed(src_file_id, &mut src_file_ast, typecheck);
+        let go_code = join_ir(&src_file_ast.emit("main".into(), &mut type_env));
+        cleanup_go_source(&escape_go_idents(&go_code), true)
+    });
     // go_code = remove_unused_imports(&go_code)
 
     let go_output_file = write_in_duck_dotdir(format!("{src_file_name}.gen.go").as_str(), &go_code);
@@ -129,7 +144,12 @@ pub fn compile(compile_args: CompileArgs) -> Result<CompileOutput, (String, Comp
         target_file
     };
 
-    go_cli::build(&compile_output_target, &go_output_file).map_err(|err| {
+    go_cli::build(
+        &compile_output_target,
+        &go_output_file,
+        compile_args.target_os.as_deref(),
+    )
+    .map_err(|err| {
         (
             format!("{}{}", *COMPILE_TAG, err.0),
             CompileErrKind::GoCli(err.1),
@@ -0,0 +1,403 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::DARGO_DOT_DIR;
+use crate::dargo::cli::{CompileArgs, TestArgs};
+use crate::dargo::compile::compile;
+use crate::lex_with_comments;
+use crate::parse::{
+    SS, Spanned,
+    lexer::Token,
+    source_file_parser::{ModuleSource, directory_module_entries, find_module},
+    source_map,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocTest {
+    pub source: String,
+    // from the opening to the closing fence
+    pub span: SS,
+    // the inline modules of the file the documented item is declared in
+    pub module_path: Vec<String>,
+}
+
+// the path of inline modules every token is in, `module a { module b { .. } }`
+fn enclosing_modules(tokens: &[Spanned<Token>]) -> Vec<Vec<String>> {
+    let mut module_path = Vec::new();
+    // the brace depth at which each module of module_path was opened
+    let mut module_depths = Vec::new();
+    let mut depth = 0;
+    let mut enclosing = Vec::with_capacity(tokens.len());
+
+    for (i, (token, _)) in tokens.iter().enumerate() {
+        match token {
+            Token::ControlChar('{') => {
+                depth += 1;
+                if let [.., (Token::Module, _), (Token::Ident(name), _)] = &tokens[..i] {
+                    module_path.push(name.to_string());
+                    module_depths.push(depth);
+                }
+            }
+            Token::ControlChar('}') => {
+                if module_depths.last() == Some(&depth) {
+                    module_path.pop();
+                    module_depths.pop();
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        enclosing.push(module_path.clone());
+    }
+
+    enclosing
+}
+
+// the modules declared with `module name;`, their source is in another file
+pub fn external_modules(tokens: &[Spanned<Token>]) -> Vec<Vec<String>> {
+    let enclosing = enclosing_modules(tokens);
+    tokens
+        .windows(3)
+        .enumerate()
+        .filter_map(|(i, window)| match window {
+            [
+                (Token::Module, _),
+                (Token::Ident(name), _),
+                (Token::ControlChar(';'), _),
+            ] => {
+                let mut path = enclosing[i].clone();
+                path.push(name.to_string());
+                Some(path)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// collects every ``` or ```duck fenced block of the doc comments, blocks tagged with another
+// language (```go, ```text, ..) are only documentation
+pub fn extract_doc_tests(tokens: &[Spanned<Token>]) -> Vec<DocTest> {
+    let enclosing = enclosing_modules(tokens);
+    let mut doc_tests = Vec::new();
+    // (is duck, lines, span of the opening fence)
+    let mut open_block: Option<(bool, Vec<&str>, SS)> = None;
+
+    for (i, (token, span)) in tokens.iter().enumerate() {
+        let Token::DocComment(line) = token else {
+            // a code block can't outlive its doc comment
+            open_block = None;
+            continue;
+        };
+
+        match open_block.as_mut() {
+            None => {
+                if let Some(lang) = line.strip_prefix("```") {
+                    let lang = lang.trim();
+                    open_block = Some((lang.is_empty() || lang == "duck", Vec::new(), *span));
+                }
+            }
+            Some((is_duck, lines, start)) => {
+                if line.starts_with("```") {
+                    if *is_duck {
+                        doc_tests.push(DocTest {
+                            source: lines.join("\n"),
+                            span: SS {
+                                start: start.start,
+                                end: span.end,
+                                context: span.context,
+                            },
+                            module_path: enclosing[i].clone(),
+                        });
+                    }
+                    open_block = None;
+                } else {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+
+    doc_tests
+}
+
+// like rustdoc, an example sees the pub items of the module it documents, the documented file is
+// included as the module `crate_name`. examples without a main are wrapped in one, their use
+// statements stay on the top level
+pub fn doc_test_program(source: &str, crate_name: &str, module_path: &[String]) -> String {
+    let prelude = format!(
+        "module {crate_name};\nuse {}::*;\n",
+        std::iter::once(crate_name)
+            .chain(module_path.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join("::")
+    );
+
+    if source.contains("fn main(") {
+        return format!("{prelude}{source}");
+    }
+
+    let (use_statements, body): (Vec<&str>, Vec<&str>) = source
+        .lines()
+        .partition(|line| line.trim_start().starts_with("use "));
+
+    format!(
+        "{prelude}{}\nfn main() {{\n{}\n}}\n",
+        use_statements.join("\n"),
+        body.join("\n")
+    )
+}
+
+fn is_ident(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+// the files of the module `name` declared in a file of dir, found like the parser does. returns
+// each file with the directory its own module declarations are looked up in. the directories in
+// a directory module only add their use statements to it, so their doc tests aren't run
+fn module_files(name: &str, dir: &Path) -> Vec<(PathBuf, PathBuf)> {
+    match find_module(name, dir) {
+        Some(ModuleSource::File(file, sub_module_dir)) => vec![(file, sub_module_dir)],
+        Some(ModuleSource::Directory(module_dir)) => directory_module_entries(&module_dir)
+            .into_iter()
+            .filter(|path| path.is_file())
+            .map(|path| (path, module_dir.clone()))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+// the doc tests of a file and of the files of all its sub modules, each with the module path of
+// the documented item
+fn collect_doc_tests(
+    file: &Path,
+    sub_module_dir: &Path,
+    module_path: Vec<String>,
+) -> std::io::Result<Vec<DocTest>> {
+    let contents = source_map::read_source(file)?;
+    let file_id = source_map::add_file(&file.to_string_lossy(), contents);
+    let tokens = lex_with_comments(file_id, &source_map::file_contents(file_id));
+
+    let mut doc_tests = extract_doc_tests(&tokens);
+    for doc_test in &mut doc_tests {
+        doc_test
+            .module_path
+            .splice(0..0, module_path.iter().cloned());
+    }
+
+    for external_module in external_modules(&tokens) {
+        let (name, inline_path) = external_module.split_last().unwrap();
+        let mut sub_module_path = module_path.clone();
+        sub_module_path.extend(inline_path.iter().cloned());
+        sub_module_path.push(name.clone());
+
+        for (sub_file, sub_dir) in module_files(name, sub_module_dir) {
+            doc_tests.extend(collect_doc_tests(
+                &sub_file,
+                &sub_dir,
+                sub_module_path.clone(),
+            )?);
+        }
+    }
+
+    Ok(doc_tests)
+}
+
+// compiles and runs every doc test of the given file and its sub modules, returns the locations
+// of the failed ones
+pub fn run_doc_tests(test_args: &TestArgs) -> std::io::Result<(usize, Vec<String>)> {
+    let dir = test_args
+        .file
+        .parent()
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    let crate_name = test_args
+        .file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if !is_ident(&crate_name) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "the doc tests include {} as the module {crate_name}, which is no identifier",
+                test_args.file.display()
+            ),
+        ));
+    }
+
+    let doc_tests = collect_doc_tests(&test_args.file, &dir, Vec::new())?;
+    let mut failed = Vec::new();
+
+    for (i, doc_test) in doc_tests.iter().enumerate() {
+        let location = doc_test.span.context.location(doc_test.span.start);
+        // the program is kept out of the user's directory, its modules are still looked up next to
+        // the documented file, so that `module crate_name;` finds it and its sub modules
+        let program_file = DARGO_DOT_DIR.join(format!("{crate_name}_doc_test_{i}.duck"));
+        std::fs::write(
+            &program_file,
+            doc_test_program(&doc_test.source, &crate_name, &doc_test.module_path),
+        )?;
+
+        // the compiler reports errors by panicking, a broken example shouldn't stop the others
+        let compile_result = panic::catch_unwind(AssertUnwindSafe(|| {
+            compile(CompileArgs {
+                file: program_file.clone(),
+                output_name: Some(format!("duck_doc_test_{i}")),
+                optimize_go: test_args.optimize_go,
+                features: test_args.features.clone(),
+                target_os: None,
+                with_tests: false,
+                module_dir: Some(dir.clone()),
+            })
+        }));
+        std::fs::remove_file(&program_file)?;
+
+        let passed = match compile_result {
+            Ok(Ok(compile_output)) => Command::new(compile_output.binary_path.canonicalize()?)
+                .status()?
+                .success(),
+            _ => false,
+        };
+
+        println!(
+            "doc test {location} ... {}",
+            if passed { "ok" } else { "FAILED" }
+        );
+
+        if !passed {
+            failed.push(location);
+        }
+    }
+
+    Ok((doc_tests.len(), failed))
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use crate::parse::{lexer::lex_parser, source_map::FileId};
+
+    use super::*;
+
+    #[test]
+    fn test_extract_doc_tests() {
+        let test_cases = vec![
+            ("/// no code here\nfn f() {}", vec![]),
+            (
+                "/// adds\n/// ```\n/// let x = 1;\n/// x + 1;\n/// ```\nfn f() {}",
+                vec!["let x = 1;\nx + 1;"],
+            ),
+            ("/// ```duck\n/// f();\n/// ```", vec!["f();"]),
+            ("/// ```go\n/// fmt.Println()\n/// ```", vec![]),
+            (
+                "/// ```\n/// a();\n/// ```\n/// ```\n/// b();\n/// ```",
+                vec!["a();", "b();"],
+            ),
+            ("/// ```\n/// a();\nfn f() {}\n/// ```", vec![]),
+            ("// ```\n// a();\n// ```", vec![]),
+        ];
+
+        for (src, expected) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let got = extract_doc_tests(&tokens)
+                .into_iter()
+                .map(|doc_test| doc_test.source)
+                .collect::<Vec<_>>();
+            assert_eq!(got, expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_doc_test_program() {
+        let test_cases = vec![
+            ("fn main() {}", "fn main() {}"),
+            ("f();", "\nfn main() {\nf();\n}\n"),
+            (
+                "use std;\nstd::io::println(\"x\");",
+                "use std;\nfn main() {\nstd::io::println(\"x\");\n}\n",
+            ),
+        ];
+
+        for (src, expected) in test_cases {
+            assert_eq!(
+                doc_test_program(src, "lib", &[]),
+                format!("module lib;\nuse lib::*;\n{expected}"),
+                "{src}"
+            );
+        }
+
+        assert_eq!(
+            doc_test_program("f();", "lib", &["a".into(), "b".into()]),
+            "module lib;\nuse lib::a::b::*;\n\nfn main() {\nf();\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_module_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::write(dir.join("single.duck"), "").unwrap();
+        std::fs::create_dir_all(dir.join("multi/nested")).unwrap();
+        std::fs::write(dir.join("multi/b.duck"), "").unwrap();
+        std::fs::write(dir.join("multi/a.duck"), "").unwrap();
+        std::fs::write(dir.join("multi/notes.txt"), "").unwrap();
+
+        assert_eq!(
+            module_files("single", dir),
+            vec![(dir.join("single.duck"), dir.join("single"))]
+        );
+        assert_eq!(
+            module_files("multi", dir),
+            vec![
+                (dir.join("multi/a.duck"), dir.join("multi")),
+                (dir.join("multi/b.duck"), dir.join("multi")),
+            ]
+        );
+        assert!(module_files("missing", dir).is_empty());
+    }
+
+    #[test]
+    fn test_doc_test_module_paths() {
+        let src = "/// ```\n/// top();\n/// ```\nfn top() {}\n\
+                   module a {\n\
+                       module ext;\n\
+                       /// ```\n/// in_a();\n/// ```\nfn in_a() {}\n\
+                       module b { fn f() { if true {} } }\n\
+                   }\n\
+                   module top_ext;\n\
+                   /// ```\n/// after();\n/// ```\nfn after() {}";
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+
+        let paths = extract_doc_tests(&tokens)
+            .into_iter()
+            .map(|doc_test| (doc_test.source, doc_test.module_path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                ("top();".to_string(), vec![]),
+                ("in_a();".to_string(), vec!["a".to_string()]),
+                ("after();".to_string(), vec![]),
+            ]
+        );
+
+        assert_eq!(
+            external_modules(&tokens),
+            vec![
+                vec!["a".to_string(), "ext".to_string()],
+                vec!["top_ext".to_string()],
+            ]
+        );
+    }
+}
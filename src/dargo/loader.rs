@@ -27,6 +27,9 @@ pub struct ProjectConfig {
     #[serde(rename = "bin", default)]
     pub binaries: Vec<BinaryConfig>,
     pub dependencies: Option<HashMap<String, Dependency>>,
+    // feature name -> features it enables, "default" is enabled unless it's missing
+    #[serde(default)]
+    pub features: HashMap<String, Vec<String>>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -73,6 +76,56 @@ pub fn load_dargo_config(
     return Ok(project_config);
 }
 
+// the requested features, the default features and every feature they enable
+pub fn resolve_features(
+    config: &ProjectConfig,
+    requested: &[String],
+) -> Result<Vec<String>, String> {
+    let mut enabled: Vec<String> = Vec::new();
+    let mut pending = requested.to_vec();
+    if config.features.contains_key("default") {
+        pending.push("default".to_string());
+    }
+
+    while let Some(feature) = pending.pop() {
+        if enabled.contains(&feature) {
+            continue;
+        }
+
+        let Some(implied) = config.features.get(&feature) else {
+            return Err(format!(
+                "{} unknown feature {}, features have to be declared in the [features] section of dargo.toml",
+                Tag::Err,
+                feature.bright_blue(),
+            ));
+        };
+
+        pending.extend(implied.iter().cloned());
+        enabled.push(feature);
+    }
+
+    enabled.sort();
+    Ok(enabled)
+}
+
+// the features of a compile resolved against the dargo.toml of the current directory, compile,
+// run, test and build all go through here. a lone file without a project has no features
+pub fn project_features(requested: &[String]) -> Result<Vec<String>, String> {
+    if !Path::new("dargo.toml").exists() {
+        return match requested.first() {
+            None => Ok(Vec::new()),
+            Some(feature) => Err(format!(
+                "{} unknown feature {}, there's no dargo.toml to declare features in",
+                Tag::Err,
+                feature.bright_blue(),
+            )),
+        };
+    }
+
+    let config = load_dargo_config(None).map_err(|err| err.0)?;
+    resolve_features(&config, requested)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,6 +197,35 @@ mod tests {
         fs::remove_file(file_path).unwrap();
     }
 
+    #[test]
+    fn test_resolve_features() {
+        let toml_content = r#"
+            name="My Project"
+
+            [features]
+            default = ["json"]
+            json = []
+            http = ["json", "tls"]
+            tls = []
+            "#;
+        let file_path = create_temp_file("features_dargo.toml", toml_content);
+        let config = load_dargo_config(Some(file_path.clone())).unwrap();
+        fs::remove_file(file_path).unwrap();
+
+        let test_cases = vec![
+            (vec![], vec!["default", "json"]),
+            (vec!["tls"], vec!["default", "json", "tls"]),
+            (vec!["http"], vec!["default", "http", "json", "tls"]),
+        ];
+
+        for (requested, expected) in test_cases {
+            let requested = requested.into_iter().map(String::from).collect::<Vec<_>>();
+            assert_eq!(resolve_features(&config, &requested).unwrap(), expected);
+        }
+
+        assert!(resolve_features(&config, &["unknown".to_string()]).is_err());
+    }
+
     #[test]
     fn test_load_project_env_missing_file() {
         let file_path = PathBuf::from("non_existent_dargo.toml");
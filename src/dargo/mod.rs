@@ -1,7 +1,11 @@
 pub mod build;
+pub mod check;
 pub mod clean;
 pub mod cli;
 pub mod compile;
+pub mod doc_test;
 pub mod init;
 pub mod loader;
+pub mod queries;
 pub mod run;
+pub mod test;
@@ -0,0 +1,244 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use lazy_static::lazy_static;
+
+use crate::{
+    parse::{
+        Spanned,
+        lexer::Token,
+        source_file_parser::{ModuleErrors, SourceFile},
+        source_map::FileId,
+    },
+    semantics::type_resolve::TypeEnv,
+};
+
+pub struct Query<K, V> {
+    memo: HashMap<K, (u64, V)>,
+}
+
+impl<K, V> Default for Query<K, V> {
+    fn default() -> Self {
+        Self {
+            memo: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Query<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &K, input_hash: u64) -> Option<V> {
+        self.memo
+            .get(key)
+            .filter(|(memo_hash, _)| *memo_hash == input_hash)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn insert(&mut self, key: K, input_hash: u64, value: V) {
+        self.memo.insert(key, (input_hash, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.memo.clear();
+    }
+}
+
+pub fn input_hash(input: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    input.hash(&mut hasher);
+    hasher.finish()
+}
+
+// the ast doesn't implement Hash, its debug output is hashed instead. there are no maps in it, so
+// the same ast always has the same output
+pub fn debug_hash(input: &impl fmt::Debug) -> u64 {
+    struct HashWriter(DefaultHasher);
+
+    impl Write for HashWriter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0.write(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    let mut writer = HashWriter(DefaultHasher::new());
+    write!(writer, "{input:?}").unwrap();
+    writer.0.finish()
+}
+
+// the modification time and size of a file or directory, None if there's nothing at the path.
+// adding or removing a file changes the time of its directory
+pub type Stamp = Option<(SystemTime, u64)>;
+
+pub fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// an ast with the stamps of the module paths it was read from at the time it was parsed
+#[derive(Clone)]
+pub struct ParsedFile {
+    module_stamps: Vec<(PathBuf, Stamp)>,
+    parsed: (SourceFile, ModuleErrors),
+}
+
+// ast(file), parse is only called if the file or one of its modules changed. the file itself is
+// compared by its contents, its modules only by their stamps, so an unchanged module isn't read
+// again. the lock isn't held while parsing, the modules of the file are queried from inside of
+// parse. the errors are kept with the ast, they're reported by whoever parses the file that
+// declares the module
+pub fn ast(
+    file_id: FileId,
+    contents: &str,
+    parse: impl FnOnce() -> (SourceFile, ModuleErrors),
+) -> (SourceFile, ModuleErrors) {
+    let contents_hash = input_hash(&contents);
+    if let Some(parsed_file) = ASTS.lock().unwrap().get(&file_id, contents_hash)
+        && parsed_file
+            .module_stamps
+            .iter()
+            .all(|(path, module_stamp)| stamp(path) == *module_stamp)
+    {
+        return parsed_file.parsed;
+    }
+
+    let parsed = parse();
+    let module_stamps = parsed
+        .0
+        .all_module_paths()
+        .into_iter()
+        .map(|path| (path.to_path_buf(), stamp(path)))
+        .collect();
+    ASTS.lock().unwrap().insert(
+        file_id,
+        contents_hash,
+        ParsedFile {
+            module_stamps,
+            parsed: parsed.clone(),
+        },
+    );
+    parsed
+}
+
+// typed(file), the type resolution of a file merged with its modules and std. the items share
+// one TypeEnv, a generic function is instantiated by whichever item uses it first, so the merged
+// file is resolved as a whole once any of its items changed
+pub fn typed(
+    file_id: FileId,
+    source_file: &mut SourceFile,
+    typecheck: impl FnOnce(&mut SourceFile) -> TypeEnv,
+) -> TypeEnv {
+    let input_hash = debug_hash(source_file);
+    if let Some((typed_file, type_env)) = TYPED.lock().unwrap().get(&file_id, input_hash) {
+        *source_file = typed_file;
+        return type_env;
+    }
+
+    let type_env = typecheck(source_file);
+    TYPED
+        .lock()
+        .unwrap()
+        .insert(file_id, input_hash, (source_file.clone(), type_env.clone()));
+    type_env
+}
+
+// go_code(file), the go source of a merged file. it's keyed by the ast before the type
+// resolution, so an unchanged file skips typed(file) too
+pub fn go_code(
+    file_id: FileId,
+    source_file: SourceFile,
+    emit: impl FnOnce(SourceFile) -> String,
+) -> String {
+    let input_hash = debug_hash(&source_file);
+    if let Some(go_code) = GO_CODE.lock().unwrap().get(&file_id, input_hash) {
+        return go_code;
+    }
+
+    let go_code = emit(source_file);
+    GO_CODE
+        .lock()
+        .unwrap()
+        .insert(file_id, input_hash, go_code.clone());
+    go_code
+}
+
+lazy_static! {
+    pub static ref TOKENS: Mutex<Query<FileId, Vec<Spanned<Token>>>> = Mutex::new(Query::new());
+    pub static ref ASTS: Mutex<Query<FileId, ParsedFile>> = Mutex::new(Query::new());
+    pub static ref TYPED: Mutex<Query<FileId, (SourceFile, TypeEnv)>> = Mutex::new(Query::new());
+    pub static ref GO_CODE: Mutex<Query<FileId, String>> = Mutex::new(Query::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::source_map;
+
+    use super::*;
+
+    #[test]
+    fn test_query_invalidates_on_changed_input() {
+        let mut query = Query::<&str, i32>::new();
+
+        query.insert("a.duck", input_hash(&"fn main() {}"), 1);
+        assert_eq!(query.get(&"a.duck", input_hash(&"fn main() {}")), Some(1));
+        assert_eq!(query.get(&"a.duck", input_hash(&"fn main() { 1 }")), None);
+        assert_eq!(query.get(&"b.duck", input_hash(&"fn main() {}")), None);
+    }
+
+    #[test]
+    fn test_ast_is_parsed_again_when_a_module_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let module_file = dir.path().join("nested.duck");
+        let file_id = source_map::add_test_file("queries_test", "module nested;");
+
+        let parses = std::cell::Cell::new(0);
+        let parse = || {
+            parses.set(parses.get() + 1);
+            let source_file = SourceFile {
+                module_paths: vec![module_file.clone()],
+                ..Default::default()
+            };
+            (source_file, Vec::new())
+        };
+
+        ast(file_id, "module nested;", parse);
+        ast(file_id, "module nested;", parse);
+        assert_eq!(parses.get(), 1);
+
+        fs::write(&module_file, "fn a() {}").unwrap();
+        ast(file_id, "module nested;", parse);
+        assert_eq!(parses.get(), 2);
+
+        fs::write(&module_file, "fn a() { 1 }").unwrap();
+        ast(file_id, "module nested;", parse);
+        assert_eq!(parses.get(), 3);
+
+        ast(file_id, "module other;", parse);
+        assert_eq!(parses.get(), 4);
+    }
+
+    #[test]
+    fn test_debug_hash() {
+        let a = SourceFile {
+            c_declarations: vec!["int a();".to_string()],
+            ..Default::default()
+        };
+        let b = SourceFile {
+            c_declarations: vec!["int b();".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(debug_hash(&a), debug_hash(&a.clone()));
+        assert_ne!(debug_hash(&a), debug_hash(&b));
+    }
+}
@@ -29,6 +29,10 @@ pub fn run(run_args: &RunArgs) -> Result<(), (String, RunErrKind)> {
             file: run_args_file.clone(),
             output_name: None,
             optimize_go: run_args.optimize_go,
+            features: run_args.features.clone(),
+            target_os: None,
+            with_tests: false,
+            module_dir: None,
         })
         .map_err(|err| {
             (
@@ -77,6 +81,8 @@ pub fn run(run_args: &RunArgs) -> Result<(), (String, RunErrKind)> {
     let build_result = build(&crate::dargo::cli::BuildArgs {
         output_name: None,
         optimize_go: run_args.optimize_go,
+        features: run_args.features.clone(),
+        target_os: None,
     })
     .map_err(|err| {
         (
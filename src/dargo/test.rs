@@ -0,0 +1,98 @@
+use std::io::ErrorKind as IOErrKind;
+use std::process::Command;
+
+use crate::dargo::cli::{CompileArgs, TestArgs};
+use crate::dargo::compile::{CompileErrKind, compile};
+use crate::dargo::doc_test::run_doc_tests;
+use crate::tags::Tag;
+
+#[derive(Debug)]
+pub enum TestErrKind {
+    CompileErr(CompileErrKind),
+    IOErr(IOErrKind),
+    TestFailed,
+}
+
+pub fn test(test_args: &TestArgs) -> Result<(), (String, TestErrKind)> {
+    let compile_result = compile(CompileArgs {
+        file: test_args.file.clone(),
+        output_name: Some("duck_test".to_string()),
+        optimize_go: test_args.optimize_go,
+        features: test_args.features.clone(),
+        target_os: None,
+        with_tests: true,
+        module_dir: None,
+    })
+    .map_err(|err| {
+        (
+            format!(
+                "{}{} couldn't compile the tests\n{}",
+                Tag::Build,
+                Tag::Err,
+                err.0
+            ),
+            TestErrKind::CompileErr(err.1),
+        )
+    })?;
+
+    let full_path_name = compile_result.binary_path.canonicalize().map_err(|err| {
+        (
+            format!(
+                "{}{} couldn't canonicalize path name of just compiled test binary",
+                Tag::IO,
+                Tag::Err
+            ),
+            TestErrKind::IOErr(err.kind()),
+        )
+    })?;
+
+    let output = Command::new(full_path_name).output().map_err(|err| {
+        (
+            format!("{}{} couldn't spawn test process", Tag::IO, Tag::Err),
+            TestErrKind::IOErr(err.kind()),
+        )
+    })?;
+
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+
+    if !output.status.success() {
+        return Err((
+            format!(
+                "{} tests of {} failed",
+                Tag::Err,
+                test_args.file.to_string_lossy()
+            ),
+            TestErrKind::TestFailed,
+        ));
+    }
+
+    let (doc_test_count, failed_doc_tests) = run_doc_tests(test_args).map_err(|err| {
+        (
+            format!("{}{} couldn't run the doc tests", Tag::IO, Tag::Err),
+            TestErrKind::IOErr(err.kind()),
+        )
+    })?;
+
+    if !failed_doc_tests.is_empty() {
+        return Err((
+            format!(
+                "{} {} of {doc_test_count} doc tests failed:\n{}",
+                Tag::Err,
+                failed_doc_tests.len(),
+                failed_doc_tests.join("\n")
+            ),
+            TestErrKind::TestFailed,
+        ));
+    }
+
+    println!(
+        "{}{}{} All tests of {} passed",
+        Tag::Dargo,
+        Tag::Test,
+        Tag::Check,
+        test_args.file.to_string_lossy()
+    );
+
+    Ok(())
+}
@@ -24,14 +24,16 @@ impl FunctionDefintion {
             && self.name != "main"
             && !self.return_type.as_ref().unwrap().0.is_unit()
         {
-            emitted_body.push(IrInstruction::InlineGo(format!(
-                "return *new({})",
-                self.return_type
-                    .as_ref()
-                    .unwrap()
-                    .0
-                    .as_go_type_annotation(type_env)
-            )));
+            let return_type = &self.return_type.as_ref().unwrap().0;
+            let zero_values = match return_type.multi_return_types(type_env) {
+                Some(types) => types
+                    .iter()
+                    .map(|type_expr| format!("*new({})", type_expr.as_go_type_annotation(type_env)))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                None => format!("*new({})", return_type.as_go_type_annotation(type_env)),
+            };
+            emitted_body.push(IrInstruction::InlineGo(format!("return {zero_values}")));
         }
 
         // TODO mvmo - 03.07.2025: this should check if the last is without a semicolon
@@ -1,6 +1,12 @@
-use crate::emit::{
-    types::{primitive_conc_type_name, primitive_native_type_name, string_to_byte_string},
-    value::{Case, IrInstruction, IrValue},
+use crate::{
+    emit::{
+        types::{
+            escape_char_for_go, escape_string_for_go, primitive_conc_type_name,
+            primitive_native_type_name, string_to_byte_string,
+        },
+        value::{Case, IrInstruction, IrValue},
+    },
+    parse::type_parser::TypeExpr,
 };
 
 impl IrInstruction {
@@ -34,7 +40,7 @@ impl IrInstruction {
                     } else {
                         let binding_str = if let Some(identifier) = &case.identifier_binding {
                             format!(
-                                "var {} {} = {}.({})\n_={}\n",
+                                "var {} {} = any({}).({})\n_={}\n",
                                 identifier, case.type_name, actual, case.type_name, identifier,
                             )
                         } else {
@@ -55,7 +61,7 @@ impl IrInstruction {
                 }
 
                 format!(
-                    "switch {}.(type) {{\n{}\n}}",
+                    "switch any({}).(type) {{\n{}\n}}",
                     against.emit_as_go(),
                     type_cases
                         .iter()
@@ -76,6 +82,20 @@ impl IrInstruction {
                     primitive_native_type_name(type_expr),
                 )
             }
+            IrInstruction::Mul(r, v1, v2, TypeExpr::String) => {
+                // repeats the string, a negative count gives the empty string
+                format!(
+                    "{r} = ConcDuckString {{ value: func(s string, n int) string {{
+                        res := \"\"
+                        for i := 0; i < n; i++ {{
+                            res += s
+                        }}
+                        return res
+                    }}({}.as_dgo_string(), {}.as_dgo_int()) }}",
+                    v1.emit_as_go(),
+                    v2.emit_as_go(),
+                )
+            }
             IrInstruction::Mul(r, v1, v2, type_expr) => {
                 // TODO: check if this is correct
                 format!(
@@ -97,6 +117,14 @@ impl IrInstruction {
                     primitive_native_type_name(type_expr),
                 )
             }
+            IrInstruction::Negate(r, v, type_expr) => {
+                format!(
+                    "{r} = {} {{ -{}.as_dgo_{}() }}",
+                    primitive_conc_type_name(type_expr),
+                    v.emit_as_go(),
+                    primitive_native_type_name(type_expr),
+                )
+            }
             IrInstruction::Div(r, v1, v2, type_expr) => {
                 format!(
                     "{r} = {} {{ {}.as_dgo_{}() / {}.as_dgo_{}() }}",
@@ -117,8 +145,16 @@ impl IrInstruction {
                     primitive_native_type_name(type_expr),
                 )
             }
+            IrInstruction::BitAnd(r, v1, v2) => emit_bit_op(r, v1, "&", v2),
+            IrInstruction::BitOr(r, v1, v2) => emit_bit_op(r, v1, "|", v2),
+            IrInstruction::BitXor(r, v1, v2) => emit_bit_op(r, v1, "^", v2),
+            IrInstruction::ShiftLeft(r, v1, v2) => emit_bit_op(r, v1, "<<", v2),
+            IrInstruction::ShiftRight(r, v1, v2) => emit_bit_op(r, v1, ">>", v2),
             IrInstruction::Continue => "continue".to_string(),
             IrInstruction::Break => "break".to_string(),
+            IrInstruction::BreakLabel(label) => format!("break {label}"),
+            IrInstruction::ContinueLabel(label) => format!("continue {label}"),
+            IrInstruction::Label(label) => format!("{label}:"),
             IrInstruction::Return(o) => format!(
                 "return {}",
                 o.as_ref()
@@ -200,6 +236,29 @@ impl IrInstruction {
             IrInstruction::Block(block_instr) => {
                 format!("{{\n{}\n}}", join_ir(block_instr))
             }
+            IrInstruction::Defer(body) => format!("defer func() {{\n{}\n}}()", join_ir(body)),
+            IrInstruction::Spawn(body) => format!("go func() {{\n{}\n}}()", join_ir(body)),
+            IrInstruction::Recover(body, handler) => format!(
+                "func() {{\ndefer func() {{\n\
+                 if duck_panic := recover(); duck_panic != nil {{\n{}\n}}\n\
+                 }}()\n{}\n}}()",
+                join_ir(handler),
+                join_ir(body)
+            ),
+            IrInstruction::Send(channel, value) => {
+                format!("{} <- {}", channel.emit_as_go(), value.emit_as_go())
+            }
+            IrInstruction::Select(cases, default) => {
+                let mut select = String::from("select {\n");
+                for (header, body) in cases {
+                    select.push_str(&format!("case {header}:\n{}\n", join_ir(body)));
+                }
+                if let Some(body) = default {
+                    select.push_str(&format!("default:\n{}\n", join_ir(body)));
+                }
+                select.push('}');
+                select
+            }
             IrInstruction::FunCall(r, t, p) => {
                 format!(
                     "{}{}({})",
@@ -237,6 +296,20 @@ impl IrInstruction {
             IrInstruction::Loop(v) => {
                 format!("for {{\n{}\n}}", join_ir(v))
             }
+            IrInstruction::ForRange(ident, target, body) => {
+                format!(
+                    "for _, {ident} := range {} {{\n_ = {ident}\n{}\n}}",
+                    target.emit_as_go(),
+                    join_ir(body)
+                )
+            }
+            IrInstruction::ForReceive(ident, channel, body) => {
+                format!(
+                    "for {ident} := range {} {{\n_ = {ident}\n{}\n}}",
+                    channel.emit_as_go(),
+                    join_ir(body)
+                )
+            }
             IrInstruction::InlineGo(t) => t.to_string(),
             IrInstruction::GoImports(imports) => {
                 format!(
@@ -248,6 +321,19 @@ impl IrInstruction {
                         .join("\n")
                 )
             }
+            IrInstruction::CgoImport(c_declarations) => {
+                // no header is included, a prototype of the user could clash with the one of
+                // the header. C.free of the string params only needs its own prototype
+                let mut lines = vec!["void free(void*);"];
+                for c_declaration in c_declarations {
+                    if !lines.contains(&c_declaration.as_str()) {
+                        lines.push(c_declaration);
+                    }
+                }
+                format!("/*\n{}\n*/\nimport \"C\"", lines.join("\n"))
+            }
+            IrInstruction::ConstDef(name, value) => format!("const {name} = {value}"),
+            IrInstruction::GlobalVarDef(name, ty) => format!("var {name} {ty}"),
             IrInstruction::FunDef(name, receiver, params, return_type, body) => {
                 format!(
                     "func {} {name}({}) {} {{\n{}\n}}",
@@ -328,6 +414,14 @@ impl IrInstruction {
     }
 }
 
+fn emit_bit_op(r: &str, v1: &IrValue, op: &str, v2: &IrValue) -> String {
+    format!(
+        "{r} = ConcDuckInt {{ value: {}.as_dgo_int() {op} {}.as_dgo_int() }}",
+        v1.emit_as_go(),
+        v2.emit_as_go(),
+    )
+}
+
 pub fn join_ir(v: &[IrInstruction]) -> String {
     v.iter()
         .map(IrInstruction::emit_as_go)
@@ -343,6 +437,24 @@ impl IrValue {
             IrValue::ArrayAccess(target, idx) => {
                 format!("{}[{}.as_dgo_int()]", target.emit_as_go(), idx.emit_as_go())
             }
+            IrValue::Slice(target, start, end, is_string) => {
+                let bound = |bound: &Option<Box<IrValue>>| {
+                    bound
+                        .as_ref()
+                        .map(|bound| bound.emit_as_go())
+                        .unwrap_or_default()
+                };
+                if *is_string {
+                    format!(
+                        "ConcDuckString {{ value: {}.as_dgo_string()[{}:{}] }}",
+                        target.emit_as_go(),
+                        bound(start),
+                        bound(end)
+                    )
+                } else {
+                    format!("{}[{}:{}]", target.emit_as_go(), bound(start), bound(end))
+                }
+            }
             IrValue::Array(arr_type, contents) => format!(
                 "{arr_type}{{{}}}",
                 contents
@@ -351,19 +463,34 @@ impl IrValue {
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
+            IrValue::Map(map_type, entries) => format!(
+                "{map_type}{{{}}}",
+                entries
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key.emit_as_go(), value.emit_as_go()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            IrValue::MapAccess(target, key) => {
+                format!("{}[{}]", target.emit_as_go(), key.emit_as_go())
+            }
+            IrValue::Receive(channel) => format!("<-{}", channel.emit_as_go()),
             IrValue::Bool(b) => format!("ConcDuckBool {{ value: {b} }}"),
             IrValue::Int(i) => format!("ConcDuckInt {{ value: {i} }}"),
             IrValue::Float(f) => format!("ConcDuckFloat {{ value: {f} }}"),
-            IrValue::Char(c) => format!("ConcDuckChar {{ value: '{c}' }}"),
+            IrValue::Char(c) => format!("ConcDuckChar {{ value: '{}' }}", escape_char_for_go(*c)),
             IrValue::String(s, is_const) => {
                 if *is_const {
                     format!(
                         "ConstString_{} {{ \"{}\" }}",
                         string_to_byte_string(s),
-                        s.replace("\n", "\\n")
+                        escape_string_for_go(s)
                     )
                 } else {
-                    format!("ConcDuckString {{ value: \"{}\" }}", s.replace("\n", "\\n"))
+                    format!(
+                        "ConcDuckString {{ value: \"{}\" }}",
+                        escape_string_for_go(s)
+                    )
                 }
             }
             IrValue::Var(v) => v.to_string(),
@@ -419,11 +546,24 @@ impl IrValue {
                         .join(", ")
                 )
             }
+            IrValue::Multi(values) => values
+                .iter()
+                .map(IrValue::emit_as_go)
+                .collect::<Vec<_>>()
+                .join(", "),
             IrValue::Nil => "nil".to_string(),
             IrValue::BoolNegate(o) => format!(
                 "ConcDuckBool {{ value: !{}.as_dgo_bool() }}",
                 o.emit_as_go()
             ),
+            // go's bitwise complement is the unary ^
+            IrValue::BitNegate(o) => {
+                format!("ConcDuckInt {{ value: ^{}.as_dgo_int() }}", o.emit_as_go())
+            }
+            IrValue::Sprintf(verb, o) => format!(
+                "ConcDuckString {{ value: fmt.Sprintf(\"{verb}\", {}) }}",
+                o.emit_as_go()
+            ),
             IrValue::Lambda(params, return_type, body) => format!(
                 "func({}) {} {{\n{}\n}} ",
                 params
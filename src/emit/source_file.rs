@@ -1,14 +1,31 @@
 use std::collections::HashSet;
 
+use rayon::iter::{IntoParallelRefIterator, ParallelExtend, ParallelIterator};
+
 use crate::{
     emit::{
-        types::emit_type_definitions,
+        types::{emit_type_definitions, escape_char_for_go, escape_string_for_go},
         value::{IrInstruction, ToIr},
     },
-    parse::{source_file_parser::SourceFile, use_statement_parser::UseStatement},
+    parse::{
+        const_parser::ConstDefinition, source_file_parser::SourceFile,
+        use_statement_parser::UseStatement, value_parser::ValueExpr,
+    },
     semantics::type_resolve::TypeEnv,
 };
 
+fn emit_const_definition(const_def: &ConstDefinition) -> IrInstruction {
+    let value = match &const_def.value_expr.0 {
+        ValueExpr::Int(i) => i.to_string(),
+        ValueExpr::Float(f) => f.to_string(),
+        ValueExpr::Bool(b) => b.to_string(),
+        ValueExpr::Char(c) => format!("'{}'", escape_char_for_go(*c)),
+        ValueExpr::String(s, _) => format!("\"{}\"", escape_string_for_go(s)),
+        _ => panic!("compiler error: consts should be evaluated to literals by now"),
+    };
+    IrInstruction::ConstDef(const_def.name.clone(), value)
+}
+
 impl SourceFile {
     pub fn emit(self, pkg_name: String, type_env: &mut TypeEnv) -> Vec<IrInstruction> {
         let mut to_ir = ToIr::default();
@@ -28,26 +45,67 @@ impl SourceFile {
 
         instructions.push(IrInstruction::GoImports(go_imports));
 
-        let mut emitted = HashSet::new();
+        if !self.c_declarations.is_empty() {
+            instructions.push(IrInstruction::CgoImport(self.c_declarations));
+        }
 
-        for function_definition in self.function_definitions {
-            // generic functions shouldn't be emitted, as they have incomplete type information
-            if function_definition.generics.is_some() {
-                continue;
-            }
+        for const_def in &self.const_definitions {
+            instructions.push(emit_const_definition(const_def));
+        }
 
-            if emitted.insert(function_definition.name.clone()) {
-                let mut fn_instr = function_definition.emit(None, type_env, &mut to_ir);
+        // go runs init before main, so the globals are set in the order they're declared in
+        if !self.global_variables.is_empty() {
+            let mut init_body = Vec::new();
+            for global in &self.global_variables {
+                let (type_expr, _) = global
+                    .type_expr
+                    .as_ref()
+                    .expect("compiler error: the types of globals should be resolved by now");
+                instructions.push(IrInstruction::GlobalVarDef(
+                    global.name.clone(),
+                    type_expr.as_go_type_annotation(type_env),
+                ));
 
-                if function_definition.name.as_str() == "main" {
-                    let IrInstruction::FunDef(_, _, _, _, _body) = &mut fn_instr else {
-                        panic!("how")
-                    };
+                let (instr, res) = global
+                    .initializer
+                    .0
+                    .direct_or_with_instr(type_env, &mut to_ir);
+                init_body.extend(instr);
+                if let Some(res) = res {
+                    init_body.push(IrInstruction::VarAssignment(global.name.clone(), res));
                 }
-                instructions.push(fn_instr);
             }
+            instructions.push(IrInstruction::FunDef(
+                "init".to_string(),
+                None,
+                vec![],
+                None,
+                init_body,
+            ));
         }
 
+        let mut emitted = HashSet::new();
+        // generic functions shouldn't be emitted, as they have incomplete type information
+        let function_definitions = self
+            .function_definitions
+            .into_iter()
+            .filter(|function_definition| {
+                function_definition.generics.is_none()
+                    && emitted.insert(function_definition.name.clone())
+            })
+            .collect::<Vec<_>>();
+
+        // the functions only read the resolved types, so they're emitted in parallel. every
+        // worker gets its own copy of the env and every function its own temporaries, which keeps
+        // the emitted code the same no matter how the functions are split up
+        let type_env_ref = &*type_env;
+        instructions.par_extend(function_definitions.par_iter().map_init(
+            || type_env_ref.clone(),
+            |type_env, function_definition| {
+                function_definition.emit(None, type_env, &mut ToIr::default())
+            },
+        ));
+
         instructions.push(IrInstruction::StructDef(
             "RenderCall".to_string(),
             vec![
@@ -101,6 +159,152 @@ impl SourceFile {
             )],
         ));
 
+        instructions.push(IrInstruction::FunDef(
+            "duck_range".to_string(),
+            None,
+            vec![
+                ("start".to_string(), "int".to_string()),
+                ("end".to_string(), "int".to_string()),
+                ("inclusive".to_string(), "bool".to_string()),
+            ],
+            Some("[]DuckInt".to_string()),
+            vec![IrInstruction::InlineGo(
+                r#"
+	if inclusive {
+		end++
+	}
+	res := []DuckInt{}
+	for i := start; i < end; i++ {
+		res = append(res, ConcDuckInt{value: i})
+	}
+	return res
+                "#
+                .to_string(),
+            )],
+        ));
+
+        // what panic, todo and assert panic with, so that uncaught panics print the location
+        instructions.push(IrInstruction::StructDef(
+            "DuckPanic".to_string(),
+            vec![
+                ("Message".to_string(), "string".to_string()),
+                ("Location".to_string(), "string".to_string()),
+            ],
+        ));
+
+        instructions.push(IrInstruction::FunDef(
+            "Error".to_string(),
+            Some(("self".to_string(), "DuckPanic".to_string())),
+            vec![],
+            Some("string".to_string()),
+            vec![IrInstruction::InlineGo(
+                "return \"panic at \" + self.Location + \": \" + self.Message".to_string(),
+            )],
+        ));
+
+        // the message a recover block gets, go's own panics like an index out of range too
+        instructions.push(IrInstruction::FunDef(
+            "duck_panic_message".to_string(),
+            None,
+            vec![("recovered".to_string(), "any".to_string())],
+            Some("string".to_string()),
+            vec![IrInstruction::InlineGo(
+                r#"
+	switch recovered := recovered.(type) {
+	case DuckPanic:
+		return recovered.Message
+	case error:
+		return recovered.Error()
+	default:
+		return fmt.Sprint(recovered)
+	}
+                "#
+                .to_string(),
+            )],
+        ));
+
+        // x as Int for floats, nan is 0 and everything outside of the range of an int is its
+        // smallest or largest value
+        instructions.push(IrInstruction::FunDef(
+            "duck_float_to_int".to_string(),
+            None,
+            vec![("f".to_string(), "float32".to_string())],
+            Some("int".to_string()),
+            vec![IrInstruction::InlineGo(
+                r#"
+	switch {
+	case f != f:
+		return 0
+	case float64(f) >= 9223372036854775807.0:
+		return int(^uint(0) >> 1)
+	case float64(f) <= -9223372036854775808.0:
+		return -int(^uint(0)>>1) - 1
+	}
+	return int(f)
+                "#
+                .to_string(),
+            )],
+        ));
+
+        instructions.push(IrInstruction::FunDef(
+            "duck_bool_to_int".to_string(),
+            None,
+            vec![("b".to_string(), "bool".to_string())],
+            Some("int".to_string()),
+            vec![IrInstruction::InlineGo(
+                "if b {\n\t\treturn 1\n\t}\n\treturn 0".to_string(),
+            )],
+        ));
+
+        // x as Char panics like an index out of range if the int isn't a unicode code point
+        instructions.push(IrInstruction::FunDef(
+            "duck_int_to_char".to_string(),
+            None,
+            vec![
+                ("i".to_string(), "int".to_string()),
+                ("location".to_string(), "string".to_string()),
+            ],
+            Some("rune".to_string()),
+            vec![IrInstruction::InlineGo(
+                r#"
+	if i < 0 || i > 0x10FFFF || (i >= 0xD800 && i <= 0xDFFF) {
+		panic(DuckPanic{Message: fmt.Sprintf("%d is not a char", i), Location: location})
+	}
+	return rune(i)
+                "#
+                .to_string(),
+            )],
+        ));
+
+        // the name typeof gives a value whose type isn't known at compile time, %T finds the go
+        // type with reflection
+        instructions.push(IrInstruction::FunDef(
+            "duck_type_name".to_string(),
+            None,
+            vec![("value".to_string(), "any".to_string())],
+            Some("string".to_string()),
+            vec![IrInstruction::InlineGo(
+                r#"
+	if value == nil {
+		return "nil"
+	}
+	name := strings.ReplaceAll(strings.TrimLeft(fmt.Sprintf("%T", value), "*"), "main.", "")
+	switch {
+	case strings.HasPrefix(name, "ConcDuck"):
+		return strings.TrimPrefix(name, "ConcDuck")
+	case strings.HasPrefix(name, "ConstString"):
+		return "String"
+	case strings.HasPrefix(name, "ConstInt"):
+		return "Int"
+	case strings.HasPrefix(name, "ConstBool"):
+		return "Bool"
+	}
+	return name
+                "#
+                .to_string(),
+            )],
+        ));
+
         instructions.push(IrInstruction::FunDef(
             "emit_go_to_js".to_string(),
             None,
@@ -3,7 +3,7 @@ use crate::{
     parse::{
         Field,
         struct_parser::StructDefinition,
-        type_parser::{Duck, TypeExpr},
+        type_parser::{DUCK_REST, Duck, TypeExpr},
     },
     semantics::type_resolve::TypeEnv,
 };
@@ -22,6 +22,31 @@ pub fn primitive_native_type_name<'a>(primitive_type_expr: &TypeExpr) -> &'a str
     }
 }
 
+// translates an f-string format spec into a go verb, <8.2 => %-8.2f and 04x => %04x
+pub fn format_spec_verb(spec: &str, type_expr: &TypeExpr) -> String {
+    let mut verb = String::from("%");
+    let spec = match spec.strip_prefix('<') {
+        Some(spec) => {
+            verb.push('-');
+            spec
+        }
+        None => spec.strip_prefix('>').unwrap_or(spec),
+    };
+
+    verb.push_str(spec);
+    if !spec.ends_with(['x', 'X', 'o', 'b', 'e']) {
+        verb.push(match type_expr {
+            TypeExpr::Float => 'f',
+            TypeExpr::Int => 'd',
+            TypeExpr::Char => 'c',
+            TypeExpr::Bool => 't',
+            _ => 's',
+        });
+    }
+
+    verb
+}
+
 pub fn escape_string_for_go(input_str: &str) -> String {
     let mut out = String::new();
     for c in input_str.chars() {
@@ -31,6 +56,15 @@ pub fn escape_string_for_go(input_str: &str) -> String {
                 out.push_str("\\n");
                 continue;
             }
+            '\r' => {
+                out.push_str("\\r");
+                continue;
+            }
+            // go doesn't allow a nul or any other control char in its source
+            c if c.is_control() && c != '\t' => {
+                out.push_str(&format!("\\u{:04x}", c as u32));
+                continue;
+            }
             _ => {}
         }
         out.push(c);
@@ -38,6 +72,15 @@ pub fn escape_string_for_go(input_str: &str) -> String {
     out
 }
 
+// the contents of a go rune literal, which escapes ' instead of "
+pub fn escape_char_for_go(c: char) -> String {
+    match c {
+        '\'' => "\\'".to_string(),
+        '"' => "\"".to_string(),
+        _ => escape_string_for_go(&c.to_string()),
+    }
+}
+
 pub fn string_to_byte_string(input_str: &str) -> String {
     input_str
         .chars()
@@ -45,6 +88,15 @@ pub fn string_to_byte_string(input_str: &str) -> String {
         .collect()
 }
 
+// go type names can't contain a minus
+fn const_int_type_name(int: i32) -> &'static str {
+    let name = match int {
+        int if int < 0 => format!("ConstInt_neg{}", int.unsigned_abs()),
+        int => format!("ConstInt_{int}"),
+    };
+    Box::leak(Box::new(name))
+}
+
 pub fn primitive_conc_type_name<'a>(primitive_type_expr: &TypeExpr) -> &'a str {
     match primitive_type_expr {
         TypeExpr::String => "ConcDuckString",
@@ -52,7 +104,7 @@ pub fn primitive_conc_type_name<'a>(primitive_type_expr: &TypeExpr) -> &'a str {
         TypeExpr::Float => "ConcDuckFloat",
         TypeExpr::Bool => "ConcDuckBool",
         TypeExpr::Char => "ConcDuckChar",
-        TypeExpr::ConstInt(int) => Box::leak(Box::new(format!("ConstInt_{int}"))),
+        TypeExpr::ConstInt(int) => const_int_type_name(*int),
         TypeExpr::ConstString(str) => Box::leak(Box::new(format!(
             "ConstString_{}",
             string_to_byte_string(str)
@@ -69,7 +121,7 @@ pub fn primitive_type_name(primitive_type_expr: &TypeExpr) -> &'static str {
         TypeExpr::Float => "DuckFloat",
         TypeExpr::Bool => "DuckBool",
         TypeExpr::Char => "DuckChar",
-        TypeExpr::ConstInt(int) => Box::leak(Box::new(format!("ConstInt_{int}"))),
+        TypeExpr::ConstInt(int) => const_int_type_name(*int),
         TypeExpr::ConstString(str) => Box::leak(Box::new(format!(
             "ConstString_{}",
             string_to_byte_string(str)
@@ -89,6 +141,9 @@ pub fn emit_type_definitions(type_env: &mut TypeEnv, to_ir: &mut ToIr) -> Vec<Ir
         to_ir: &mut ToIr,
     ) -> Vec<IrInstruction> {
         return match type_expr {
+            TypeExpr::Duck(duck) if duck.fields.iter().any(|field| field.name == DUCK_REST) => {
+                panic!("compiler error: the ..rest of a duck should be spread or reported by now")
+            }
             TypeExpr::Duck(duck) => duck
                 .fields
                 .iter()
@@ -143,6 +198,8 @@ pub fn emit_type_definitions(type_env: &mut TypeEnv, to_ir: &mut ToIr) -> Vec<Ir
                     fields,
                     methods,
                     generics,
+                    doc_comments: _,
+                    attributes: Vec::new(),
                 } = type_env.get_struct_def(s.as_str()).clone();
 
                 if generics.is_some() {
@@ -218,8 +275,7 @@ pub fn emit_type_definitions(type_env: &mut TypeEnv, to_ir: &mut ToIr) -> Vec<Ir
                         method
                             .return_type
                             .as_ref()
-                            .filter(|x| !x.0.is_unit())
-                            .map(|(type_expr, _)| type_expr.as_go_type_annotation(type_env))
+                            .map(|(type_expr, _)| type_expr.as_go_return_type(type_env))
                             .unwrap_or_default()
                     );
 
@@ -246,7 +302,7 @@ pub fn emit_type_definitions(type_env: &mut TypeEnv, to_ir: &mut ToIr) -> Vec<Ir
                                             })
                                             .collect::<Vec<_>>(),
                                         method.return_type.as_ref().map(|return_type| {
-                                            return_type.0.as_go_type_annotation(type_env)
+                                            return_type.0.as_go_return_type(type_env)
                                         }),
                                         vec![IrInstruction::InlineGo(format!(
                                             "{} self.{}({})",
@@ -341,7 +397,7 @@ pub fn emit_type_definitions(type_env: &mut TypeEnv, to_ir: &mut ToIr) -> Vec<Ir
         .flat_map(|primitive_type_expr| {
             if primitive_type_expr.is_literal() {
                 let ir_value = IrValue::Imm(match primitive_type_expr.clone() {
-                    TypeExpr::ConstString(value) => format!("\"{}\"", value.replace("\n", "\\n")),
+                    TypeExpr::ConstString(value) => format!("\"{}\"", escape_string_for_go(&value)),
                     TypeExpr::ConstInt(int_value) => format!("{int_value}"),
                     TypeExpr::ConstBool(bool_value) => format!("{bool_value}"),
                     _ => unreachable!(),
@@ -446,6 +502,8 @@ pub fn emit_type_definitions(type_env: &mut TypeEnv, to_ir: &mut ToIr) -> Vec<Ir
                         fields,
                         methods: _,
                         generics: _,
+                        doc_comments: _,
+                        attributes: Vec::new(),
                     } = type_env.get_struct_def(struct_name.as_str()).clone();
 
                     IrInstruction::StructDef(
@@ -456,6 +514,7 @@ pub fn emit_type_definitions(type_env: &mut TypeEnv, to_ir: &mut ToIr) -> Vec<Ir
                                 |Field {
                                      name,
                                      type_expr: (type_expr, _),
+                                     ..
                                  }| {
                                     (name.clone(), type_expr.as_go_type_annotation(type_env))
                                 },
@@ -471,6 +530,7 @@ pub fn emit_type_definitions(type_env: &mut TypeEnv, to_ir: &mut ToIr) -> Vec<Ir
                             |Field {
                                  name,
                                  type_expr: (type_expr, _),
+                                 ..
                              }| {
                                 (name.clone(), type_expr.as_go_type_annotation(type_env))
                             },
@@ -500,7 +560,15 @@ impl TypeExpr {
             TypeExpr::Alias(def) => def.type_expression.0.as_go_type_annotation(type_env),
             TypeExpr::RawTypeName(..) => panic!(),
             TypeExpr::Array(t) => format!("[]{}", t.0.as_go_type_annotation(type_env)),
-            TypeExpr::Any => "interface{}".to_string(),
+            TypeExpr::Map(key, value) => format!(
+                "map[{}]{}",
+                primitive_native_type_name(&key.0),
+                value.0.as_go_type_annotation(type_env)
+            ),
+            TypeExpr::Chan(element) => {
+                format!("chan {}", element.0.as_go_type_annotation(type_env))
+            }
+            TypeExpr::Any | TypeExpr::Never => "interface{}".to_string(),
             TypeExpr::ConstInt(i) => primitive_type_name(&TypeExpr::ConstInt(*i)).to_string(),
             TypeExpr::ConstBool(b) => primitive_type_name(&TypeExpr::ConstBool(*b)).to_string(),
             TypeExpr::Tag(..) => self.as_clean_go_type_name(type_env),
@@ -528,13 +596,9 @@ impl TypeExpr {
                 "func({}) {}",
                 params
                     .iter()
-                    .map(|(name, type_expr)| match name {
-                        Some(name) =>
-                            format!("{name} {}", type_expr.0.as_go_type_annotation(type_env)),
-                        None => type_expr.0.as_go_type_annotation(type_env),
-                    })
+                    .map(|(_, type_expr)| type_expr.0.as_go_type_annotation(type_env))
                     .collect::<Vec<_>>()
-                    .join(","),
+                    .join(", "),
                 return_type
                     .as_ref()
                     .map(|x| x.0.as_go_return_type(type_env))
@@ -576,7 +640,8 @@ impl TypeExpr {
                 primitive_type_name(&TypeExpr::ConstString(str.clone())).to_string()
             }
             TypeExpr::Array(t) => format!("[]{}", t.0.as_go_concrete_annotation(type_env)),
-            TypeExpr::Any => "interface{}".to_string(),
+            TypeExpr::Map(..) | TypeExpr::Chan(..) => self.as_go_type_annotation(type_env),
+            TypeExpr::Any | TypeExpr::Never => "interface{}".to_string(),
             TypeExpr::Bool => "ConcDuckBool".to_string(),
             TypeExpr::Int => "ConcDuckInt".to_string(),
             TypeExpr::Float => "ConcDuckFloat".to_string(),
@@ -593,18 +658,13 @@ impl TypeExpr {
                 "func({}) {}",
                 params
                     .iter()
-                    .map(|(name, type_expr)| match name {
-                        Some(name) =>
-                            format!("{name} {}", type_expr.0.as_go_type_annotation(type_env)),
-                        None => type_expr.0.as_go_type_annotation(type_env),
-                    })
+                    .map(|(_, type_expr)| type_expr.0.as_go_type_annotation(type_env))
                     .collect::<Vec<_>>()
-                    .join(","),
+                    .join(", "),
                 return_type
-                    .clone()
-                    .map_or("".to_string(), |return_type| return_type
-                        .0
-                        .as_go_type_annotation(type_env))
+                    .as_ref()
+                    .map(|x| x.0.as_go_return_type(type_env))
+                    .unwrap_or_default(),
             ),
             TypeExpr::Duck(Duck { fields }) => format!(
                 "Duck_{}",
@@ -660,7 +720,16 @@ impl TypeExpr {
                 primitive_type_name(&TypeExpr::ConstString(str.clone())).to_string()
             }
             TypeExpr::Array(t) => format!("Array_{}", t.0.as_clean_go_type_name(type_env)),
+            TypeExpr::Map(key, value) => format!(
+                "Map_{}_{}",
+                key.0.as_clean_go_type_name(type_env),
+                value.0.as_clean_go_type_name(type_env)
+            ),
+            TypeExpr::Chan(element) => {
+                format!("Chan_{}", element.0.as_clean_go_type_name(type_env))
+            }
             TypeExpr::Any => "Any".to_string(),
+            TypeExpr::Never => "Never".to_string(),
             TypeExpr::Bool => "DuckBool".to_string(),
             TypeExpr::Int => "DuckInt".to_string(),
             TypeExpr::Float => "DuckFloat".to_string(),
@@ -676,11 +745,7 @@ impl TypeExpr {
                 "Fun_From_{}{}",
                 params
                     .iter()
-                    .map(|(name, type_expr)| format!(
-                        "{}_{}",
-                        name.clone().unwrap_or_else(|| "".to_string()),
-                        type_expr.0.type_id(type_env)
-                    ))
+                    .map(|(_, type_expr)| type_expr.0.type_id(type_env))
                     .collect::<Vec<_>>()
                     .join("_"),
                 return_type
@@ -740,7 +805,16 @@ impl TypeExpr {
                 primitive_type_name(&TypeExpr::ConstString(str.clone())).to_string()
             }
             TypeExpr::Array(t) => format!("Array_{}", t.0.as_clean_go_type_name(type_env)),
+            TypeExpr::Map(key, value) => format!(
+                "Map_{}_{}",
+                key.0.as_clean_go_type_name(type_env),
+                value.0.as_clean_go_type_name(type_env)
+            ),
+            TypeExpr::Chan(element) => {
+                format!("Chan_{}", element.0.as_clean_go_type_name(type_env))
+            }
             TypeExpr::Any => "Any".to_string(),
+            TypeExpr::Never => "Never".to_string(),
             TypeExpr::Bool => "DuckBool".to_string(),
             TypeExpr::Int => "DuckInt".to_string(),
             TypeExpr::Float => "DuckFloat".to_string(),
@@ -758,11 +832,7 @@ impl TypeExpr {
                 "Fun_From_{}{}",
                 params
                     .iter()
-                    .map(|(name, type_expr)| format!(
-                        "{}_{}",
-                        name.clone().unwrap_or_else(|| "".to_string()),
-                        type_expr.0.as_clean_go_type_name(type_env)
-                    ))
+                    .map(|(_, type_expr)| type_expr.0.as_clean_go_type_name(type_env))
                     .collect::<Vec<_>>()
                     .join("_"),
                 return_type
@@ -1,20 +1,51 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::{
-    emit::types::escape_string_for_go,
+    emit::types::{
+        escape_string_for_go, format_spec_verb, primitive_conc_type_name,
+        primitive_native_type_name,
+    },
     parse::{
+        Field, Spanned,
         duckx_component_parser::find_client_components,
         function_parser::LambdaFunctionExpr,
         struct_parser::StructDefinition,
         type_parser::{Duck, TypeExpr},
-        value_parser::{Declaration, ValFmtStringContents, ValHtmlStringContents, ValueExpr},
+        value_parser::{
+            Declaration, MatchArm, ValFmtStringContents, ValHtmlStringContents, ValueExpr,
+        },
+    },
+    semantics::{
+        ident_mangler::{mangle, unmangle},
+        type_resolve::{ARRAY_METHODS, CHAN_METHODS, MAP_METHODS, TypeEnv},
     },
-    semantics::{ident_mangler::mangle, type_resolve::TypeEnv},
 };
 
+// a duck case matches every value that has its fields, not only duck literals
+fn type_switch_case(type_expr: &TypeExpr, type_env: &mut TypeEnv) -> String {
+    match type_env.try_resolve_type_expr(type_expr) {
+        duck @ TypeExpr::Duck(..) => duck.as_go_type_annotation(type_env),
+        _ => type_expr.as_go_concrete_annotation(type_env),
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ToIr {
     pub var_counter: usize,
+    // result variables of the loop expressions around the current expression
+    pub loop_results: Vec<IrRes>,
+    // the labeled loops around the current expression
+    pub loop_labels: Vec<LoopLabel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoopLabel {
+    pub name: String,
+    pub go_label: String,
+    // the index of the loop's result variable, if it's a loop expression
+    pub result_index: usize,
+    // go doesn't allow labels that aren't used
+    pub used: bool,
 }
 
 /// Expression further down should use this
@@ -35,7 +66,13 @@ pub enum IrInstruction {
     Add(IrRes, IrValue, IrValue, TypeExpr),
     Mul(IrRes, IrValue, IrValue, TypeExpr),
     Sub(IrRes, IrValue, IrValue, TypeExpr),
+    Negate(IrRes, IrValue, TypeExpr),
     Mod(IrRes, IrValue, IrValue, TypeExpr),
+    BitAnd(IrRes, IrValue, IrValue),
+    BitOr(IrRes, IrValue, IrValue),
+    BitXor(IrRes, IrValue, IrValue),
+    ShiftLeft(IrRes, IrValue, IrValue),
+    ShiftRight(IrRes, IrValue, IrValue),
     Div(IrRes, IrValue, IrValue, TypeExpr),
     Equals(IrRes, IrValue, IrValue, TypeExpr),
     NotEquals(IrRes, IrValue, IrValue, TypeExpr),
@@ -47,15 +84,44 @@ pub enum IrInstruction {
     Or(IrRes, IrValue, IrValue, TypeExpr),
     Break,
     Continue,
+    // break label and continue label, they leave the loop the go label names
+    BreakLabel(String),
+    ContinueLabel(String),
+    // label:, names the loop that follows it
+    Label(String),
     Return(Option<IrValue>),
     InlineGo(String),
     If(IrValue, Vec<IrInstruction>, Option<Vec<IrInstruction>>),
     Loop(Vec<IrInstruction>),
+    // for _, ident := range target
+    ForRange(String, IrValue, Vec<IrInstruction>),
+    // for ident := range channel, receives until the channel is closed
+    ForReceive(String, IrValue, Vec<IrInstruction>),
     Block(Vec<IrInstruction>),
+    // defer func() { body }(), the body runs when the surrounding function returns
+    Defer(Vec<IrInstruction>),
+    // go func() { body }(), the body runs in a new goroutine
+    Spawn(Vec<IrInstruction>),
+    // func() { defer func() { if recover() != nil { handler } }(); body }(), the handler sees
+    // the recovered value as duck_panic
+    Recover(Vec<IrInstruction>, Vec<IrInstruction>),
+    // channel <- value
+    Send(IrValue, IrValue),
+    // select { case header: body ... default: body }
+    Select(
+        Vec<(String, Vec<IrInstruction>)>,
+        Option<Vec<IrInstruction>>,
+    ),
 
     // Top-Level Statements
     GoPackage(String),
     GoImports(Vec<(Option<String>, String)>),
+    // the declarations of the preamble, the import of "C" has to follow it directly
+    CgoImport(Vec<String>),
+    // const name = value, the value is an untyped go literal
+    ConstDef(Identifier, String),
+    // var name type at package level, it's set by an init function
+    GlobalVarDef(Identifier, String),
     FunDef(
         String,                   // Name
         Option<(String, String)>, // Receiver
@@ -88,6 +154,8 @@ pub enum IrValue {
     Bool(bool),
     Char(char),
     Array(String, Vec<IrValue>),
+    // the keys are native go values
+    Map(String, Vec<(IrValue, IrValue)>),
     Lambda(
         Vec<(String, String)>, // params
         Option<String>,        // return type
@@ -99,15 +167,38 @@ pub enum IrValue {
     Tag(String),
     Var(String),
     BoolNegate(Box<IrValue>),
+    BitNegate(Box<IrValue>),
+    // fmt.Sprintf with a single verb, the value has to be a native go value
+    Sprintf(String, Box<IrValue>),
     FieldAccess(Box<IrValue>, String),
     MethodCall(Box<IrValue>, String, Vec<IrValue>),
     ArrayAccess(Box<IrValue>, Box<IrValue>),
+    // target[start:end] with native go ints as bounds, strings are sliced as their go string
+    Slice(
+        Box<IrValue>,
+        Option<Box<IrValue>>,
+        Option<Box<IrValue>>,
+        bool,
+    ),
+    MapAccess(Box<IrValue>, Box<IrValue>),
+    // <-channel
+    Receive(Box<IrValue>),
     Imm(String),
     Pointer(Box<IrValue>),
+    // the values of a go multiple return
+    Multi(Vec<IrValue>),
     Nil,
 }
 
 impl IrValue {
+    pub fn multi_from_tuple(tuple: IrValue, len: usize) -> Self {
+        IrValue::Multi(
+            (0..len)
+                .map(|i| IrValue::FieldAccess(Box::new(tuple.clone()), format!("field_{i}")))
+                .collect(),
+        )
+    }
+
     pub fn empty_tuple() -> Self {
         Self::Tuple(
             TypeExpr::from_value_expr(&ValueExpr::Tuple(vec![]), &mut TypeEnv::default())
@@ -133,6 +224,22 @@ pub fn as_var(s: impl Into<String>) -> IrValue {
     IrValue::Var(s.into())
 }
 
+// go compares interfaces by their dynamic type, so maps are keyed by the native go value
+fn native_map_key(key: IrValue, key_type: &TypeExpr) -> IrValue {
+    let accessor = format!("as_dgo_{}", primitive_native_type_name(key_type));
+    IrValue::MethodCall(Box::new(key), accessor, vec![])
+}
+
+// the go index expression for target[idx], idx has to be emitted already
+fn index_suffix(target: &ValueExpr, idx: IrValue, type_env: &mut TypeEnv) -> String {
+    match TypeExpr::from_value_expr_resolved_type_name(target, type_env) {
+        TypeExpr::Map(key_type, _) => {
+            format!("[{}]", native_map_key(idx, &key_type.0).emit_as_go())
+        }
+        _ => format!("[{}.as_dgo_int()]", idx.emit_as_go()),
+    }
+}
+
 fn walk_access(
     obj: ValueExpr,
     type_env: &mut TypeEnv,
@@ -156,11 +263,11 @@ fn walk_access(
                     .rev()
                     .for_each(|x| res_instr.push_front(x));
                 if let Some(res) = res {
-                    let IrValue::Var(res) = res else {
+                    let IrValue::Var(_) = res else {
                         panic!("need var");
                     };
 
-                    s.push_front(format!("[{res}.as_dgo_int()]"));
+                    s.push_front(index_suffix(&next_obj.0, res, type_env));
                 } else {
                     return (res_instr.into(), None);
                 }
@@ -194,6 +301,7 @@ fn walk_access(
                             .find(|x| x.name == field_name)
                             .expect("Field doesn't exist");
                         if found_field.type_expr.0.is_array()
+                            || found_field.type_expr.0.is_map()
                             || found_field.type_expr.0.is_duck()
                             || found_field.type_expr.0.is_struct()
                             || found_field.type_expr.0.is_fun()
@@ -217,12 +325,373 @@ fn walk_access(
     )
 }
 
+// assigns the emitted value to a variable, a field or an element of an array
+fn emit_assignment(
+    target: &ValueExpr,
+    a_res: IrValue,
+    type_env: &mut TypeEnv,
+    env: &mut ToIr,
+) -> (Vec<IrInstruction>, Option<IrValue>) {
+    let mut res = Vec::new();
+
+    if let ValueExpr::FieldAccess {
+        target_obj,
+        field_name,
+    } = target
+    {
+        let (walk_instr, walk_res) = walk_access(target_obj.0.clone(), type_env, env);
+        res.extend(walk_instr);
+        let target_res = match walk_res {
+            Some(s) => s,
+            None => return (res, None),
+        };
+
+        let target_ty = TypeExpr::from_value_expr(&target_obj.0, type_env);
+        match target_ty {
+            TypeExpr::Duck(_) => {
+                res.push(IrInstruction::FunCall(
+                    None,
+                    IrValue::Var(format!("{target_res}.Set{field_name}")),
+                    vec![a_res],
+                ));
+            }
+            TypeExpr::Tuple(_) => {
+                res.push(IrInstruction::VarAssignment(
+                    format!("{target_res}.field_{field_name}"),
+                    a_res,
+                ));
+            }
+            TypeExpr::Struct(_) => {
+                res.push(IrInstruction::VarAssignment(
+                    format!("{target_res}.{field_name}"),
+                    a_res,
+                ));
+            }
+            _ => panic!("can't set field on non object"),
+        }
+    } else if let ValueExpr::ArrayAccess(target, idx) = target {
+        //todo(@Apfelfrosch) handle indices of type ! properly (do it in rest of emit too)
+        let (idx_instr, Some(IrValue::Var(idx_res))) = idx.0.emit(type_env, env) else {
+            panic!("no var: {idx:?}")
+        };
+
+        res.extend(idx_instr);
+
+        let (walk_instr, walk_res) = walk_access(target.0.clone(), type_env, env);
+        res.extend(walk_instr);
+        let target_res = match walk_res {
+            Some(s) => s,
+            None => return (res, None),
+        };
+
+        let index = index_suffix(&target.0, IrValue::Var(idx_res), type_env);
+        res.push(IrInstruction::VarAssignment(
+            format!("{target_res}{index}"),
+            a_res,
+        ));
+    } else {
+        let (target_instr, Some(IrValue::Var(target_res))) = target.emit(type_env, env) else {
+            panic!("can only assign var")
+        };
+
+        res.extend(target_instr);
+        res.push(IrInstruction::VarAssignment(target_res, a_res));
+    }
+
+    (res, Some(IrValue::empty_tuple()))
+}
+
+fn is_builtin_method(target_obj: &ValueExpr, method_name: &str, type_env: &mut TypeEnv) -> bool {
+    match TypeExpr::from_value_expr_resolved_type_name(target_obj, type_env) {
+        TypeExpr::Array(..) => ARRAY_METHODS.contains(&method_name),
+        TypeExpr::Map(..) => MAP_METHODS.contains(&method_name),
+        TypeExpr::Chan(..) => CHAN_METHODS.contains(&method_name),
+        _ => false,
+    }
+}
+
+// the methods of arrays, maps and channels are emitted inline instead of as go method calls
+fn emit_builtin_method_call(
+    target_obj: &ValueExpr,
+    method_name: &str,
+    params: &[Spanned<ValueExpr>],
+    type_env: &mut TypeEnv,
+    env: &mut ToIr,
+) -> (Vec<IrInstruction>, Option<IrValue>) {
+    let target_type = TypeExpr::from_value_expr_resolved_type_name(target_obj, type_env);
+    let (mut instr, target_res) = target_obj.direct_or_with_instr(type_env, env);
+    let Some(target_res) = target_res else {
+        return (instr, None);
+    };
+
+    let mut param_res = None;
+    if let Some(param) = params.first() {
+        let (param_instr, res) = param.0.direct_or_with_instr(type_env, env);
+        instr.extend(param_instr);
+        if res.is_none() {
+            return (instr, None);
+        }
+        param_res = res;
+    }
+
+    match (method_name, &target_type, param_res) {
+        ("len", _, _) => {
+            let res = env.new_var();
+            instr.push(IrInstruction::VarDecl(
+                res.clone(),
+                TypeExpr::Int.as_go_type_annotation(type_env),
+            ));
+            instr.push(IrInstruction::VarAssignment(
+                res.clone(),
+                IrValue::Imm(format!(
+                    "ConcDuckInt {{ value: len({}) }}",
+                    target_res.emit_as_go()
+                )),
+            ));
+            (instr, Some(IrValue::Var(res)))
+        }
+        // push appends to the slice and writes it back to where the array came from
+        ("push", TypeExpr::Array(..), Some(elem)) => {
+            let appended = IrValue::Imm(format!(
+                "append({}, {})",
+                target_res.emit_as_go(),
+                elem.emit_as_go()
+            ));
+            let (assign_instr, assign_res) = emit_assignment(target_obj, appended, type_env, env);
+            instr.extend(assign_instr);
+            (instr, assign_res)
+        }
+        ("has", TypeExpr::Map(key_type, _), Some(key)) => {
+            let res = env.new_var();
+            let found = env.new_var();
+            instr.push(IrInstruction::VarDecl(
+                res.clone(),
+                TypeExpr::Bool.as_go_type_annotation(type_env),
+            ));
+            instr.push(IrInstruction::VarDecl(found.clone(), "bool".to_string()));
+            instr.push(IrInstruction::VarAssignment(
+                format!("_, {found}"),
+                IrValue::MapAccess(
+                    Box::new(target_res),
+                    Box::new(native_map_key(key, &key_type.0)),
+                ),
+            ));
+            instr.push(IrInstruction::VarAssignment(
+                res.clone(),
+                IrValue::Imm(format!("ConcDuckBool {{ value: {found} }}")),
+            ));
+            (instr, Some(IrValue::Var(res)))
+        }
+        ("remove", TypeExpr::Map(key_type, _), Some(key)) => {
+            instr.push(IrInstruction::FunCall(
+                None,
+                IrValue::Var("delete".to_string()),
+                vec![target_res, native_map_key(key, &key_type.0)],
+            ));
+            (instr, Some(IrValue::empty_tuple()))
+        }
+        ("close", TypeExpr::Chan(..), _) => {
+            instr.push(IrInstruction::FunCall(
+                None,
+                IrValue::Var("close".to_string()),
+                vec![target_res],
+            ));
+            (instr, Some(IrValue::empty_tuple()))
+        }
+        _ => panic!("compiler error: {method_name} isn't a builtin method of {target_type}"),
+    }
+}
+
+// the fields a duck or struct value can be read from with its getters
+fn readable_fields(type_expr: &TypeExpr, type_env: &mut TypeEnv) -> Option<Vec<Field>> {
+    match type_expr {
+        TypeExpr::Duck(Duck { fields }) => Some(fields.clone()),
+        TypeExpr::Struct(name) => {
+            let struct_def = type_env.get_struct_def(name);
+            struct_def
+                .generics
+                .is_none()
+                .then(|| struct_def.fields.clone())
+        }
+        _ => None,
+    }
+}
+
+fn has_same_go_type(lhs: &TypeExpr, rhs: &TypeExpr, type_env: &mut TypeEnv) -> bool {
+    lhs.as_go_type_annotation(type_env) == rhs.as_go_type_annotation(type_env)
+}
+
+// go accepts a value for a duck interface if the getters of its fields return exactly the types
+// the interface asks for. so a wider duck fits as it is, but not if one of its fields is a wider
+// duck itself or an array of them
+fn fits_as_is(required: &TypeExpr, given: &TypeExpr, type_env: &mut TypeEnv) -> bool {
+    let required = type_env.try_resolve_type_expr(required);
+    let given = type_env.try_resolve_type_expr(given);
+    if has_same_go_type(&required, &given, type_env) {
+        return true;
+    }
+
+    let (TypeExpr::Duck(Duck { fields }), Some(given_fields)) =
+        (&required, readable_fields(&given, type_env))
+    else {
+        return false;
+    };
+    fields.iter().all(|field| {
+        given_fields
+            .iter()
+            .find(|given_field| given_field.name == field.name)
+            .is_some_and(|given_field| {
+                has_same_go_type(&field.type_expr.0, &given_field.type_expr.0, type_env)
+            })
+    })
+}
+
+// whether adapt_to_type can copy the value into the required shape, only ducks of data fields
+// and arrays of them can be copied
+fn is_adaptable(required: &TypeExpr, given: &TypeExpr, type_env: &mut TypeEnv) -> bool {
+    if fits_as_is(required, given, type_env) {
+        return true;
+    }
+
+    match (
+        type_env.try_resolve_type_expr(required),
+        type_env.try_resolve_type_expr(given),
+    ) {
+        (TypeExpr::Array(required_element), TypeExpr::Array(given_element)) => {
+            is_adaptable(&required_element.0, &given_element.0, type_env)
+        }
+        (TypeExpr::Duck(Duck { fields }), given) => {
+            let Some(given_fields) = readable_fields(&given, type_env) else {
+                return false;
+            };
+            fields.iter().all(|field| {
+                !field.type_expr.0.is_fun()
+                    && match given_fields
+                        .iter()
+                        .find(|given_field| given_field.name == field.name)
+                    {
+                        Some(given_field) => {
+                            is_adaptable(&field.type_expr.0, &given_field.type_expr.0, type_env)
+                        }
+                        None => type_env
+                            .try_resolve_type_expr(&field.type_expr.0)
+                            .is_optional(),
+                    }
+            })
+        }
+        _ => false,
+    }
+}
+
+// copies a value that doesn't fit as it is into a new duck or array of the required type, the
+// fields are read with the getters of the given value and optional fields it lacks are .none.
+// the copy doesn't alias the value anymore: a callee that changes a field of an adapted
+// argument changes the copy only, while a value that fits as it is stays shared
+fn adapt_to_type(
+    value: IrValue,
+    required: &TypeExpr,
+    given: &TypeExpr,
+    type_env: &mut TypeEnv,
+    env: &mut ToIr,
+) -> (Vec<IrInstruction>, IrValue) {
+    if fits_as_is(required, given, type_env) {
+        return (Vec::new(), value);
+    }
+
+    let required = type_env.try_resolve_type_expr(required);
+    let given = type_env.try_resolve_type_expr(given);
+    let res = env.new_var();
+    let mut instr = vec![IrInstruction::VarDecl(
+        res.clone(),
+        required.as_go_type_annotation(type_env),
+    )];
+
+    match (&required, &given) {
+        (TypeExpr::Array(required_element), TypeExpr::Array(given_element)) => {
+            let element = env.new_var();
+            let (mut body, adapted) = adapt_to_type(
+                IrValue::Var(element.clone()),
+                &required_element.0,
+                &given_element.0,
+                type_env,
+                env,
+            );
+            body.push(IrInstruction::VarAssignment(
+                res.clone(),
+                IrValue::Imm(format!("append({res}, {})", adapted.emit_as_go())),
+            ));
+            instr.push(IrInstruction::ForRange(element, value, body));
+        }
+        (TypeExpr::Duck(Duck { fields }), given) => {
+            let given_fields = readable_fields(given, type_env)
+                .expect("compiler error: only adaptable values are adapted");
+            let mut field_values = Vec::new();
+            for field in fields {
+                let Some(given_field) = given_fields
+                    .iter()
+                    .find(|given_field| given_field.name == field.name)
+                else {
+                    // an optional field the value doesn't have is .none in the copy
+                    let none = TypeExpr::Tag("none".to_string()).as_clean_go_type_name(type_env);
+                    field_values.push((field.name.clone(), IrValue::Tag(none)));
+                    continue;
+                };
+                let getter = IrValue::MethodCall(
+                    Box::new(value.clone()),
+                    format!("Get{}", field.name),
+                    vec![],
+                );
+                let (field_instr, field_value) = adapt_to_type(
+                    getter,
+                    &field.type_expr.0,
+                    &given_field.type_expr.0,
+                    type_env,
+                    env,
+                );
+                instr.extend(field_instr);
+                field_values.push((field.name.clone(), field_value));
+            }
+            instr.push(IrInstruction::VarAssignment(
+                res.clone(),
+                IrValue::Duck(required.as_clean_go_type_name(type_env), field_values),
+            ));
+        }
+        _ => panic!("compiler error: a {given} can't be adapted to a {required}"),
+    }
+
+    (instr, IrValue::Var(res))
+}
+
+// a duck or array that go can't take as it is gets copied into the required shape
+fn adapt_value(
+    value: IrValue,
+    required: &TypeExpr,
+    value_expr: &ValueExpr,
+    type_env: &mut TypeEnv,
+    env: &mut ToIr,
+) -> (Vec<IrInstruction>, IrValue) {
+    if !matches!(
+        type_env.try_resolve_type_expr(required),
+        TypeExpr::Duck(..) | TypeExpr::Array(..)
+    ) {
+        return (Vec::new(), value);
+    }
+
+    let given = TypeExpr::from_value_expr(value_expr, type_env);
+    if !is_adaptable(required, &given, type_env) {
+        return (Vec::new(), value);
+    }
+    adapt_to_type(value, required, &given, type_env, env)
+}
+
 impl ValueExpr {
     pub fn direct_emit(&self, type_env: &mut TypeEnv, env: &mut ToIr) -> Option<IrValue> {
         match self {
             ValueExpr::Bool(b) => Some(IrValue::Bool(*b)),
             ValueExpr::Char(c) => Some(IrValue::Char(*c)),
-            ValueExpr::Int(i) => Some(IrValue::Int(*i)),
+            ValueExpr::Int(i) => Some(IrValue::Int(
+                i64::try_from(*i).expect("the type checker only lets ints through that fit"),
+            )),
             ValueExpr::Float(f) => Some(IrValue::Float(*f)),
             ValueExpr::String(s, is_const) => Some(IrValue::String(s.clone(), *is_const)),
             ValueExpr::Lambda(b) => {
@@ -237,14 +706,21 @@ impl ValueExpr {
                     rparams.push((p.0.clone(), p.1.0.as_go_type_annotation(type_env)));
                 }
 
+                let multi_return_types = return_type
+                    .as_ref()
+                    .and_then(|(x, _)| x.multi_return_types(type_env));
                 let return_type = return_type
                     .as_ref()
-                    .map(|(x, _)| x.as_go_type_annotation(type_env));
+                    .map(|(x, _)| x.as_go_return_type(type_env));
 
                 let (mut b_instr, b_res) = value_expr.0.emit(type_env, env);
                 if return_type.is_some()
                     && let Some(b_res) = b_res
                 {
+                    let b_res = match multi_return_types {
+                        Some(types) => IrValue::multi_from_tuple(b_res, types.len()),
+                        None => b_res,
+                    };
                     b_instr.push(IrInstruction::Return(b_res.into()));
                 }
                 Some(IrValue::Lambda(rparams, return_type, b_instr))
@@ -867,7 +1343,7 @@ impl ValueExpr {
                         ValFmtStringContents::String(s) => {
                             concat_params.push(IrValue::String(s.to_owned(), false))
                         }
-                        ValFmtStringContents::Expr(expr) => {
+                        ValFmtStringContents::Expr(expr, spec) => {
                             template.push_str("%s");
                             let (param_instr, param_res) =
                                 expr.0.direct_or_with_instr(type_env, env);
@@ -876,7 +1352,21 @@ impl ValueExpr {
                                 return (instr, None);
                             }
 
-                            concat_params.push(param_res.unwrap());
+                            let param_res = param_res.unwrap();
+                            let Some(spec) = spec else {
+                                concat_params.push(param_res);
+                                continue;
+                            };
+
+                            let type_expr = TypeExpr::from_value_expr(&expr.0, type_env).unconst();
+                            concat_params.push(IrValue::Sprintf(
+                                format_spec_verb(spec, &type_expr),
+                                Box::new(IrValue::MethodCall(
+                                    Box::new(param_res),
+                                    format!("as_dgo_{}", primitive_native_type_name(&type_expr)),
+                                    vec![],
+                                )),
+                            ));
                         }
                     }
                 }
@@ -914,10 +1404,26 @@ impl ValueExpr {
                     ));
                 }
 
-                let mut cases = Vec::new();
-                for arm in arms {
-                    let type_name = arm.type_case.0.as_go_concrete_annotation(type_env);
+                // a type switch can't fall through to the next arm if the condition of an arm
+                // doesn't hold, so then every arm gets its own switch that only runs as long as
+                // no arm before it matched
+                let has_conditions = arms
+                    .iter()
+                    .chain(else_arm.as_deref())
+                    .any(|arm| arm.condition.is_some());
+                let matched_var_name = env.new_var();
+                if has_conditions {
+                    instructions.push(IrInstruction::VarDecl(
+                        matched_var_name.clone(),
+                        TypeExpr::Bool.as_go_type_annotation(type_env),
+                    ));
+                    instructions.push(IrInstruction::VarAssignment(
+                        matched_var_name.clone(),
+                        IrValue::Bool(false),
+                    ));
+                }
 
+                let match_arm_instrs = |arm: &MatchArm, type_env: &mut TypeEnv, env: &mut ToIr| {
                     let (mut arm_instrs, arm_res) =
                         arm.value_expr.0.direct_or_with_instr(type_env, env);
                     if !result_type.is_unit()
@@ -926,32 +1432,65 @@ impl ValueExpr {
                         arm_instrs.push(IrInstruction::VarAssignment(result_var_name.clone(), res));
                     }
 
+                    if !has_conditions {
+                        return arm_instrs;
+                    }
+
+                    arm_instrs.insert(
+                        0,
+                        IrInstruction::VarAssignment(matched_var_name.clone(), IrValue::Bool(true)),
+                    );
+                    let Some(condition) = &arm.condition else {
+                        return arm_instrs;
+                    };
+
+                    let (mut condition_instrs, condition_res) =
+                        condition.0.direct_or_with_instr(type_env, env);
+                    if let Some(condition_res) = condition_res {
+                        condition_instrs.push(IrInstruction::If(condition_res, arm_instrs, None));
+                    }
+                    condition_instrs
+                };
+
+                let mut cases = Vec::new();
+                for arm in arms {
+                    let type_name = type_switch_case(&arm.type_case.0, type_env);
+
                     cases.push(Case {
                         type_name,
-                        instrs: arm_instrs,
+                        instrs: match_arm_instrs(arm, type_env, env),
                         identifier_binding: arm.identifier_binding.clone(),
                     });
                 }
 
-                if let Some(arm) = else_arm {
-                    let _type_name = arm.type_case.0.as_clean_go_type_name(type_env);
-
-                    let (mut arm_instrs, arm_res) =
-                        arm.value_expr.0.direct_or_with_instr(type_env, env);
-                    if !result_type.is_unit()
-                        && let Some(res) = arm_res
-                    {
-                        arm_instrs.push(IrInstruction::VarAssignment(result_var_name.clone(), res));
-                    }
-
+                if let Some(arm) = else_arm.as_deref() {
+                    // an else arm with a type binds the value as that type, the type resolution
+                    // narrows x that way where a condition says x is T
+                    let type_name = match &arm.type_case.0 {
+                        TypeExpr::Any => "__else".to_string(),
+                        type_case => type_switch_case(type_case, type_env),
+                    };
                     cases.push(Case {
-                        type_name: "__else".to_string(),
-                        instrs: arm_instrs,
+                        type_name,
+                        instrs: match_arm_instrs(arm, type_env, env),
                         identifier_binding: arm.identifier_binding.clone(),
                     });
                 }
 
-                instructions.push(IrInstruction::SwitchType(match_on_value, cases));
+                if has_conditions {
+                    for case in cases {
+                        instructions.push(IrInstruction::If(
+                            IrValue::BoolNegate(IrValue::Var(matched_var_name.clone()).into()),
+                            vec![IrInstruction::SwitchType(
+                                match_on_value.clone(),
+                                vec![case],
+                            )],
+                            None,
+                        ));
+                    }
+                } else {
+                    instructions.push(IrInstruction::SwitchType(match_on_value, cases));
+                }
                 (
                     instructions,
                     if result_type.is_unit() {
@@ -986,6 +1525,29 @@ impl ValueExpr {
                 let res_var_name = env.new_var();
 
                 res_instr.push(IrInstruction::VarDecl(res_var_name.clone(), res_type));
+
+                if let TypeExpr::Map(key_type, _) =
+                    TypeExpr::from_value_expr_resolved_type_name(&target.0, type_env)
+                {
+                    // reading a key that isn't in the map panics like an index out of range
+                    let found_var_name = env.new_var();
+                    res_instr.push(IrInstruction::VarDecl(
+                        found_var_name.clone(),
+                        "bool".to_string(),
+                    ));
+                    res_instr.push(IrInstruction::VarAssignment(
+                        format!("{res_var_name}, {found_var_name}"),
+                        IrValue::MapAccess(
+                            target_res.unwrap().into(),
+                            native_map_key(idx_res.unwrap(), &key_type.0).into(),
+                        ),
+                    ));
+                    res_instr.push(IrInstruction::InlineGo(format!(
+                        "if !{found_var_name} {{\npanic(\"key not found in map\")\n}}"
+                    )));
+                    return (res_instr, Some(IrValue::Var(res_var_name)));
+                }
+
                 res_instr.push(IrInstruction::VarAssignment(
                     res_var_name.clone(),
                     IrValue::ArrayAccess(target_res.unwrap().into(), idx_res.unwrap().into()),
@@ -993,18 +1555,74 @@ impl ValueExpr {
 
                 (res_instr, Some(IrValue::Var(res_var_name)))
             }
-            ValueExpr::Array(_, exprs) => {
+            ValueExpr::Slice {
+                target,
+                start,
+                end,
+                inclusive,
+            } => {
+                let (mut res_instr, target_res) = target.0.direct_or_with_instr(type_env, env);
+                let Some(target_res) = target_res else {
+                    return (res_instr, None);
+                };
+
+                let mut bounds = [None, None];
+                for (bound, bound_ir) in [start, end].into_iter().zip(&mut bounds) {
+                    let Some(bound) = bound else {
+                        continue;
+                    };
+                    let (bound_instr, bound_res) = bound.0.direct_or_with_instr(type_env, env);
+                    res_instr.extend(bound_instr);
+                    let Some(bound_res) = bound_res else {
+                        return (res_instr, None);
+                    };
+                    *bound_ir = Some(Box::new(IrValue::MethodCall(
+                        bound_res.into(),
+                        "as_dgo_int".into(),
+                        vec![],
+                    )));
+                }
+                let [start_res, end_res] = bounds;
+                // xs[a..=b] is xs[a:b + 1] in go
+                let end_res = if *inclusive {
+                    end_res
+                        .map(|end_res| IrValue::Imm(format!("{} + 1", end_res.emit_as_go())).into())
+                } else {
+                    end_res
+                };
+
+                let res_type = TypeExpr::from_value_expr(self, type_env);
+                let res_var_name = env.new_var();
+                res_instr.push(IrInstruction::VarDecl(
+                    res_var_name.clone(),
+                    res_type.as_go_type_annotation(type_env),
+                ));
+                res_instr.push(IrInstruction::VarAssignment(
+                    res_var_name.clone(),
+                    IrValue::Slice(target_res.into(), start_res, end_res, res_type.is_string()),
+                ));
+
+                (res_instr, Some(IrValue::Var(res_var_name)))
+            }
+            ValueExpr::Array(content_type, exprs) => {
                 let mut total_instr = Vec::new();
                 let mut array_contents = Vec::new();
 
                 for expr in exprs {
                     let (expr_instr, expr_res) = expr.0.direct_or_with_instr(type_env, env);
                     total_instr.extend(expr_instr);
-                    if let Some(expr_res) = expr_res {
-                        array_contents.push(expr_res);
-                    } else {
+                    let Some(mut expr_res) = expr_res else {
                         return (total_instr, None);
+                    };
+
+                    // .T[..] and the values of a variadic param are copied like arguments
+                    if let Some(content_type) = content_type {
+                        let (adapter_instr, adapted) =
+                            adapt_value(expr_res, &content_type.0, &expr.0, type_env, env);
+                        total_instr.extend(adapter_instr);
+                        expr_res = adapted;
                     }
+                    array_contents.push(expr_res);
                 }
 
                 let arr_type = TypeExpr::from_value_expr(self, type_env);
@@ -1023,6 +1641,43 @@ impl ValueExpr {
 
                 (total_instr, Some(IrValue::Var(res_var_name)))
             }
+            ValueExpr::Map(_, entries) => {
+                let map_type = TypeExpr::from_value_expr(self, type_env);
+                let TypeExpr::Map(key_type, _) = &map_type else {
+                    panic!("compiler error: a map literal has to be of a map type")
+                };
+
+                let mut total_instr = Vec::new();
+                let mut map_contents = Vec::new();
+
+                for (key, value) in entries {
+                    let (key_instr, key_res) = key.0.direct_or_with_instr(type_env, env);
+                    total_instr.extend(key_instr);
+                    let Some(key_res) = key_res else {
+                        return (total_instr, None);
+                    };
+
+                    let (value_instr, value_res) = value.0.direct_or_with_instr(type_env, env);
+                    total_instr.extend(value_instr);
+                    let Some(value_res) = value_res else {
+                        return (total_instr, None);
+                    };
+
+                    map_contents.push((native_map_key(key_res, &key_type.0), value_res));
+                }
+
+                let map_type = map_type.as_go_type_annotation(type_env);
+                let res_var_name = env.new_var();
+                total_instr.extend([
+                    IrInstruction::VarDecl(res_var_name.clone(), map_type.clone()),
+                    IrInstruction::VarAssignment(
+                        res_var_name.clone(),
+                        IrValue::Map(map_type, map_contents),
+                    ),
+                ]);
+
+                (total_instr, Some(IrValue::Var(res_var_name)))
+            }
             ValueExpr::VarDecl(b) => {
                 let Declaration {
                     name,
@@ -1030,17 +1685,20 @@ impl ValueExpr {
                     initializer,
                 } = &b.0;
 
-                let type_expression = type_expr
+                let type_expr = &type_expr
                     .as_ref()
                     .expect("compiler error: i expect that the type should be replaced by now")
-                    .0
-                    .as_go_type_annotation(type_env);
+                    .0;
+                let type_expression = type_expr.as_go_type_annotation(type_env);
 
                 let mut v = Vec::new();
                 v.push(IrInstruction::VarDecl(name.clone(), type_expression));
                 let (init_r, inti_r_res) = initializer.0.direct_or_with_instr(type_env, env);
                 v.extend(init_r);
                 if let Some(init_r_res) = inti_r_res {
+                    let (adapter_instr, init_r_res) =
+                        adapt_value(init_r_res, type_expr, &initializer.0, type_env, env);
+                    v.extend(adapter_instr);
                     v.push(IrInstruction::VarAssignment(name.clone(), init_r_res));
                 }
                 (v, Some(IrValue::empty_tuple()))
@@ -1062,6 +1720,82 @@ impl ValueExpr {
 
                 (vec![IrInstruction::Loop(cond_instr)], None)
             }
+            ValueExpr::For {
+                ident,
+                target,
+                body,
+            } => {
+                let (mut target_instr, target_res) = target.0.direct_or_with_instr(type_env, env);
+                let Some(target_res) = target_res else {
+                    return (target_instr, None);
+                };
+
+                // the entries of a map are collected into (key, value) tuples before the loop
+                let target_res = if let TypeExpr::Map(key_type, value_type) =
+                    TypeExpr::from_value_expr_resolved_type_name(&target.0, type_env)
+                {
+                    let entry_type = TypeExpr::Tuple(vec![*key_type.clone(), *value_type])
+                        .as_go_type_annotation(type_env);
+                    let entries_var_name = env.new_var();
+                    let entry = IrValue::Tuple(
+                        entry_type.clone(),
+                        vec![
+                            IrValue::Imm(format!(
+                                "{} {{ value: key }}",
+                                primitive_conc_type_name(&key_type.0)
+                            )),
+                            IrValue::Var("value".to_string()),
+                        ],
+                    );
+                    target_instr.push(IrInstruction::VarDecl(
+                        entries_var_name.clone(),
+                        format!("[]{entry_type}"),
+                    ));
+                    target_instr.push(IrInstruction::InlineGo(format!(
+                        "for key, value := range {} {{\n{entries_var_name} = \
+                         append({entries_var_name}, {})\n}}",
+                        target_res.emit_as_go(),
+                        entry.emit_as_go()
+                    )));
+                    IrValue::Var(entries_var_name)
+                } else {
+                    target_res
+                };
+
+                let (body, _) = body.0.direct_or_with_instr(type_env, env);
+                if let TypeExpr::Chan(..) =
+                    TypeExpr::from_value_expr_resolved_type_name(&target.0, type_env)
+                {
+                    target_instr.push(IrInstruction::ForReceive(ident.clone(), target_res, body));
+                } else {
+                    target_instr.push(IrInstruction::ForRange(ident.clone(), target_res, body));
+                }
+
+                (target_instr, None)
+            }
+            ValueExpr::Loop { body, .. } => {
+                let res_type = TypeExpr::from_value_expr(self, type_env);
+                let res_var_name = env.new_var();
+
+                let mut i = Vec::new();
+                if !res_type.is_unit() {
+                    i.push(IrInstruction::VarDecl(
+                        res_var_name.clone(),
+                        res_type.as_go_type_annotation(type_env),
+                    ));
+                }
+
+                env.loop_results.push(res_var_name.clone());
+                let (body, _) = body.0.direct_or_with_instr(type_env, env);
+                env.loop_results.pop();
+                i.push(IrInstruction::Loop(body));
+
+                if res_type.is_unit() {
+                    (i, None)
+                } else {
+                    (i, as_rvar(res_var_name))
+                }
+            }
             ValueExpr::If {
                 condition,
                 then,
@@ -1106,81 +1840,12 @@ impl ValueExpr {
             }
             ValueExpr::VarAssign(b) => {
                 let assign = &b.0;
-                let (i, res) = assign.value_expr.0.direct_or_with_instr(type_env, env);
+                let (mut i, res) = assign.value_expr.0.direct_or_with_instr(type_env, env);
                 if let Some(a_res) = res {
-                    let target = &assign.target.0;
-                    let mut res = Vec::new();
-
-                    res.extend(i);
-
-                    if let ValueExpr::FieldAccess {
-                        target_obj,
-                        field_name,
-                    } = target
-                    {
-                        let (walk_instr, walk_res) =
-                            walk_access(target_obj.0.clone(), type_env, env);
-                        res.extend(walk_instr);
-                        let target_res = match walk_res {
-                            Some(s) => s,
-                            None => return (res, None),
-                        };
-
-                        let target_ty = TypeExpr::from_value_expr(&target_obj.0, type_env);
-                        match target_ty {
-                            TypeExpr::Duck(_) => {
-                                res.push(IrInstruction::FunCall(
-                                    None,
-                                    IrValue::Var(format!("{target_res}.Set{field_name}")),
-                                    vec![a_res],
-                                ));
-                            }
-                            TypeExpr::Tuple(_) => {
-                                res.push(IrInstruction::VarAssignment(
-                                    format!("{target_res}.field_{field_name}"),
-                                    a_res,
-                                ));
-                            }
-                            TypeExpr::Struct(_) => {
-                                res.push(IrInstruction::VarAssignment(
-                                    format!("{target_res}.{field_name}"),
-                                    a_res,
-                                ));
-                            }
-                            _ => panic!("can't set field on non object"),
-                        }
-                    } else if let ValueExpr::ArrayAccess(target, idx) = target {
-                        //todo(@Apfelfrosch) handle indices of type ! properly (do it in rest of emit too)
-                        let (idx_instr, Some(IrValue::Var(idx_res))) = idx.0.emit(type_env, env)
-                        else {
-                            panic!("no var: {idx:?}")
-                        };
-
-                        res.extend(idx_instr);
-
-                        let (walk_instr, walk_res) = walk_access(target.0.clone(), type_env, env);
-                        res.extend(walk_instr);
-                        let target_res = match walk_res {
-                            Some(s) => s,
-                            None => return (res, None),
-                        };
-
-                        res.push(IrInstruction::VarAssignment(
-                            format!("{target_res}[{idx_res}.as_dgo_int()]"),
-                            a_res,
-                        ));
-                    } else {
-                        let (target_instr, Some(IrValue::Var(target_res))) =
-                            target.emit(type_env, env)
-                        else {
-                            panic!("can only assign var")
-                        };
-
-                        res.extend(target_instr);
-                        res.push(IrInstruction::VarAssignment(target_res, a_res));
-                    }
-
-                    (res, Some(IrValue::empty_tuple()))
+                    let (assign_instr, assign_res) =
+                        emit_assignment(&assign.target.0, a_res, type_env, env);
+                    i.extend(assign_instr);
+                    (i, assign_res)
                 } else {
                     (i, Some(IrValue::empty_tuple()))
                 }
@@ -1199,7 +1864,10 @@ impl ValueExpr {
                     return (ir, None);
                 }
 
-                let type_expr = TypeExpr::from_value_expr(&v1.0, type_env);
+                let type_expr = match TypeExpr::from_value_expr(&v1.0, type_env) {
+                    type_expr if type_expr.is_string() => TypeExpr::String,
+                    type_expr => type_expr,
+                };
 
                 let var = env.new_var();
                 ir.push(IrInstruction::VarDecl(
@@ -1230,7 +1898,10 @@ impl ValueExpr {
                     return (ir, None);
                 }
 
-                let type_expr = TypeExpr::from_value_expr(&v1.0, type_env);
+                let type_expr = match TypeExpr::from_value_expr(&v1.0, type_env) {
+                    type_expr if type_expr.is_string() => TypeExpr::String,
+                    type_expr => type_expr,
+                };
 
                 let var = env.new_var();
                 ir.push(IrInstruction::VarDecl(
@@ -1268,6 +1939,8 @@ impl ValueExpr {
                             IrInstruction::Return(..)
                                 | IrInstruction::Break
                                 | IrInstruction::Continue
+                                | IrInstruction::BreakLabel(..)
+                                | IrInstruction::ContinueLabel(..)
                         ) {
                             res_var = None;
                             return (res_instr, res_var);
@@ -1323,6 +1996,220 @@ impl ValueExpr {
 
                 (res, as_rvar(res_var))
             }
+            ValueExpr::Comptime(..) => {
+                panic!("compiler error: comptime blocks should be evaluated by now")
+            }
+            ValueExpr::OptionalFieldAccess { .. }
+            | ValueExpr::Coalesce(..)
+            | ValueExpr::Propagate(..) => {
+                panic!("compiler error: ?., ?? and ? should be turned into a match by now")
+            }
+            ValueExpr::Spread(..) => {
+                panic!("compiler error: a spread should be unpacked into its call by now")
+            }
+            ValueExpr::Update(..) => {
+                panic!("compiler error: ..base should be spelled out by now")
+            }
+            ValueExpr::BitAnd(lhs, rhs)
+            | ValueExpr::BitOr(lhs, rhs)
+            | ValueExpr::BitXor(lhs, rhs)
+            | ValueExpr::ShiftLeft(lhs, rhs)
+            | ValueExpr::ShiftRight(lhs, rhs) => {
+                let mut ir = Vec::new();
+
+                let (v1_instr, v1_res) = lhs.0.direct_or_with_instr(type_env, env);
+                ir.extend(v1_instr);
+                if v1_res.is_none() {
+                    return (ir, None);
+                }
+                let (v2_instr, v2_res) = rhs.0.direct_or_with_instr(type_env, env);
+                ir.extend(v2_instr);
+                if v2_res.is_none() {
+                    return (ir, None);
+                }
+
+                let var = env.new_var();
+                ir.push(IrInstruction::VarDecl(var.clone(), "DuckInt".into()));
+
+                let (v1, v2) = (v1_res.unwrap(), v2_res.unwrap());
+                ir.push(match self {
+                    ValueExpr::BitAnd(..) => IrInstruction::BitAnd(var.clone(), v1, v2),
+                    ValueExpr::BitOr(..) => IrInstruction::BitOr(var.clone(), v1, v2),
+                    ValueExpr::BitXor(..) => IrInstruction::BitXor(var.clone(), v1, v2),
+                    ValueExpr::ShiftLeft(..) => IrInstruction::ShiftLeft(var.clone(), v1, v2),
+                    _ => IrInstruction::ShiftRight(var.clone(), v1, v2),
+                });
+
+                (ir, as_rvar(var))
+            }
+            ValueExpr::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let mut ir = Vec::new();
+
+                let (start_instr, start_res) = start.0.direct_or_with_instr(type_env, env);
+                ir.extend(start_instr);
+                let Some(start_res) = start_res else {
+                    return (ir, None);
+                };
+                let (end_instr, end_res) = end.0.direct_or_with_instr(type_env, env);
+                ir.extend(end_instr);
+                let Some(end_res) = end_res else {
+                    return (ir, None);
+                };
+
+                // ranges are materialized by the duck_range runtime helper
+                let var = env.new_var();
+                ir.push(IrInstruction::VarDecl(var.clone(), "[]DuckInt".into()));
+                ir.push(IrInstruction::FunCall(
+                    Some(var.clone()),
+                    IrValue::Imm("duck_range".into()),
+                    vec![
+                        IrValue::MethodCall(start_res.into(), "as_dgo_int".into(), vec![]),
+                        IrValue::MethodCall(end_res.into(), "as_dgo_int".into(), vec![]),
+                        IrValue::Imm(inclusive.to_string()),
+                    ],
+                ));
+
+                (ir, as_rvar(var))
+            }
+            ValueExpr::BitNegate(expr) => {
+                let (mut instr, e_res_var) = expr.0.direct_or_with_instr(type_env, env);
+                if let Some(e_res_var) = e_res_var {
+                    let res = env.new_var();
+                    instr.push(IrInstruction::VarDecl(res.clone(), "DuckInt".into()));
+                    instr.push(IrInstruction::VarAssignment(
+                        res.clone(),
+                        IrValue::BitNegate(e_res_var.into()),
+                    ));
+                    (instr, as_rvar(res))
+                } else {
+                    (instr, None)
+                }
+            }
+            ValueExpr::TypeOf(expr) => {
+                let (mut instr, e_res_var) = expr.0.direct_or_with_instr(type_env, env);
+                let Some(e_res_var) = e_res_var else {
+                    return (instr, None);
+                };
+
+                let res = env.new_var();
+                instr.push(IrInstruction::VarDecl(
+                    res.clone(),
+                    TypeExpr::String.as_go_type_annotation(type_env),
+                ));
+                // the variants of a union are told apart with a type switch, go values and any
+                // only have the name of their go type
+                let reflected_name = || {
+                    IrInstruction::VarAssignment(
+                        res.clone(),
+                        IrValue::Imm(format!(
+                            "ConcDuckString {{ value: duck_type_name({}) }}",
+                            e_res_var.emit_as_go()
+                        )),
+                    )
+                };
+                let type_name = |type_expr: &TypeExpr| {
+                    let name = match type_expr.unconst() {
+                        TypeExpr::Struct(name) => unmangle(&name).join("::"),
+                        type_expr => type_expr.as_clean_user_faced_type_name(),
+                    };
+                    IrInstruction::VarAssignment(res.clone(), IrValue::String(name, false))
+                };
+
+                match TypeExpr::from_value_expr_resolved_type_name(&expr.0, type_env) {
+                    TypeExpr::Or(variants) => {
+                        let mut cases = variants
+                            .iter()
+                            .map(|(variant, _)| Case {
+                                type_name: type_switch_case(variant, type_env),
+                                instrs: vec![type_name(&type_env.try_resolve_type_expr(variant))],
+                                identifier_binding: None,
+                            })
+                            .collect::<Vec<_>>();
+                        cases.push(Case {
+                            type_name: "__else".to_string(),
+                            instrs: vec![reflected_name()],
+                            identifier_binding: None,
+                        });
+                        instr.push(IrInstruction::SwitchType(e_res_var, cases));
+                    }
+                    TypeExpr::Any | TypeExpr::Go(..) => instr.push(reflected_name()),
+                    type_expr => {
+                        instr.push(IrInstruction::InlineGo(format!(
+                            "_ = {}",
+                            e_res_var.emit_as_go()
+                        )));
+                        instr.push(type_name(&type_expr));
+                    }
+                }
+                (instr, as_rvar(res))
+            }
+            ValueExpr::Cast(expr, target) => {
+                let (mut instr, e_res_var) = expr.0.direct_or_with_instr(type_env, env);
+                let Some(e_res_var) = e_res_var else {
+                    return (instr, None);
+                };
+
+                let source = TypeExpr::from_value_expr_resolved_type_name(&expr.0, type_env);
+                let target_type = type_env.try_resolve_type_expr(&target.0);
+                let value = e_res_var.emit_as_go();
+                // floats saturate at the bounds of an int, ints that aren't a char panic
+                let converted = match (source.unconst(), &target_type) {
+                    (TypeExpr::Int, TypeExpr::Float) => {
+                        format!("ConcDuckFloat {{ value: float32({value}.as_dgo_int()) }}")
+                    }
+                    (TypeExpr::Float, TypeExpr::Int) => format!(
+                        "ConcDuckInt {{ value: duck_float_to_int({value}.as_dgo_float32()) }}"
+                    ),
+                    (TypeExpr::Char, TypeExpr::Int) => {
+                        format!("ConcDuckInt {{ value: int({value}.as_dgo_rune()) }}")
+                    }
+                    (TypeExpr::Bool, TypeExpr::Int) => {
+                        format!("ConcDuckInt {{ value: duck_bool_to_int({value}.as_dgo_bool()) }}")
+                    }
+                    (TypeExpr::Int, TypeExpr::Char) => format!(
+                        "ConcDuckChar {{ value: duck_int_to_char({value}.as_dgo_int(), \"{}\") }}",
+                        escape_string_for_go(&expr.1.context.location(expr.1.start))
+                    ),
+                    (source, target_type) if source == *target_type && source.is_primitive() => {
+                        format!(
+                            "{} {{ value: {value}.as_dgo_{}() }}",
+                            primitive_conc_type_name(target_type),
+                            primitive_native_type_name(target_type)
+                        )
+                    }
+                    _ => value,
+                };
+
+                let res = env.new_var();
+                instr.push(IrInstruction::VarDecl(
+                    res.clone(),
+                    target.0.as_go_type_annotation(type_env),
+                ));
+                instr.push(IrInstruction::VarAssignment(
+                    res.clone(),
+                    IrValue::Imm(converted),
+                ));
+                (instr, as_rvar(res))
+            }
+            ValueExpr::Negate(expr) => {
+                let (mut instr, e_res_var) = expr.0.direct_or_with_instr(type_env, env);
+                if let Some(e_res_var) = e_res_var {
+                    let type_expr = TypeExpr::from_value_expr(&expr.0, type_env).unconst();
+                    let res = env.new_var();
+                    instr.push(IrInstruction::VarDecl(
+                        res.clone(),
+                        type_expr.as_go_type_annotation(type_env),
+                    ));
+                    instr.push(IrInstruction::Negate(res.clone(), e_res_var, type_expr));
+                    (instr, as_rvar(res))
+                } else {
+                    (instr, None)
+                }
+            }
             ValueExpr::BoolNegate(expr) => {
                 let (mut instr, e_res_var) = expr.0.direct_or_with_instr(type_env, env);
                 if let Some(e_res_var) = e_res_var {
@@ -1337,12 +2224,326 @@ impl ValueExpr {
                     (instr, None)
                 }
             }
-            ValueExpr::Break => (vec![IrInstruction::Break], None),
+            ValueExpr::Break(Some(expr)) => {
+                let (mut instr, res) = expr.0.direct_or_with_instr(type_env, env);
+                let Some(res) = res else {
+                    return (instr, None);
+                };
+
+                if !TypeExpr::from_value_expr(&expr.0, type_env).is_unit() {
+                    let loop_res = env
+                        .loop_results
+                        .last()
+                        .expect("compiler error: break with a value outside of loop")
+                        .clone();
+                    instr.push(IrInstruction::VarAssignment(loop_res, res));
+                }
+                instr.push(IrInstruction::Break);
+                (instr, None)
+            }
+            ValueExpr::Break(None) => (vec![IrInstruction::Break], None),
             ValueExpr::Continue => (vec![IrInstruction::Continue], None),
+            ValueExpr::Labeled(label, labeled) => {
+                if !matches!(labeled.0, ValueExpr::Break(..) | ValueExpr::Continue) {
+                    let go_label = env.new_var();
+                    env.loop_labels.push(LoopLabel {
+                        name: label.clone(),
+                        go_label: go_label.clone(),
+                        result_index: env.loop_results.len(),
+                        used: false,
+                    });
+                    let (mut instr, res) = labeled.0.direct_or_with_instr(type_env, env);
+                    let loop_label = env
+                        .loop_labels
+                        .pop()
+                        .expect("compiler error: the label of this loop was pushed above");
+
+                    // the loop is the last instruction, everything before it prepares its target
+                    if loop_label.used
+                        && let Some(loop_index) = instr.len().checked_sub(1)
+                    {
+                        instr.insert(loop_index, IrInstruction::Label(go_label));
+                    }
+                    return (instr, res);
+                }
+
+                let loop_label = env
+                    .loop_labels
+                    .iter_mut()
+                    .rev()
+                    .find(|loop_label| loop_label.name == *label)
+                    .expect("compiler error: the label should be checked by now");
+                loop_label.used = true;
+                let go_label = loop_label.go_label.clone();
+                let result_index = loop_label.result_index;
+
+                match &labeled.0 {
+                    ValueExpr::Break(Some(value_expr)) => {
+                        let (mut instr, res) = value_expr.0.direct_or_with_instr(type_env, env);
+                        let Some(res) = res else {
+                            return (instr, None);
+                        };
+                        if !TypeExpr::from_value_expr(&value_expr.0, type_env).is_unit() {
+                            let loop_res = env.loop_results[result_index].clone();
+                            instr.push(IrInstruction::VarAssignment(loop_res, res));
+                        }
+                        instr.push(IrInstruction::BreakLabel(go_label));
+                        (instr, None)
+                    }
+                    ValueExpr::Break(None) => (vec![IrInstruction::BreakLabel(go_label)], None),
+                    _ => (vec![IrInstruction::ContinueLabel(go_label)], None),
+                }
+            }
+            ValueExpr::Defer(call) | ValueExpr::Spawn(call) => {
+                let ValueExpr::FunctionCall {
+                    target,
+                    params,
+                    type_params,
+                } = &call.0
+                else {
+                    panic!("compiler error: only function calls can be deferred or spawned")
+                };
+
+                // like in go the params are evaluated right away, so they're copied into
+                // variables that the call uses later
+                let mut instr = Vec::new();
+                let mut captured_params = Vec::new();
+                for (param, span) in params {
+                    let (p_instr, p_res) = param.direct_or_with_instr(type_env, env);
+                    instr.extend(p_instr);
+                    let Some(p_res) = p_res else {
+                        return (instr, None);
+                    };
+
+                    let type_expr = TypeExpr::from_value_expr(param, type_env);
+                    let captured = env.new_var();
+                    instr.push(IrInstruction::VarDecl(
+                        captured.clone(),
+                        type_expr.as_go_type_annotation(type_env),
+                    ));
+                    instr.push(IrInstruction::VarAssignment(captured.clone(), p_res));
+                    captured_params
+                        .push((ValueExpr::Variable(false, captured, Some(type_expr)), *span));
+                }
+
+                let later_call = ValueExpr::FunctionCall {
+                    target: target.clone(),
+                    params: captured_params,
+                    type_params: type_params.clone(),
+                };
+                let (call_instr, _) = later_call.emit(type_env, env);
+                instr.push(match self {
+                    ValueExpr::Defer(..) => IrInstruction::Defer(call_instr),
+                    _ => IrInstruction::Spawn(call_instr),
+                });
+                (instr, Some(IrValue::empty_tuple()))
+            }
+            ValueExpr::MakeChan {
+                element_type,
+                capacity,
+            } => {
+                let chan_type =
+                    TypeExpr::Chan(element_type.clone().into()).as_go_type_annotation(type_env);
+
+                let mut instr = Vec::new();
+                let make = match capacity {
+                    Some(capacity) => {
+                        let (capacity_instr, capacity_res) =
+                            capacity.0.direct_or_with_instr(type_env, env);
+                        instr.extend(capacity_instr);
+                        let Some(capacity_res) = capacity_res else {
+                            return (instr, None);
+                        };
+                        format!(
+                            "make({chan_type}, {}.as_dgo_int())",
+                            capacity_res.emit_as_go()
+                        )
+                    }
+                    None => format!("make({chan_type})"),
+                };
+
+                let res = env.new_var();
+                instr.push(IrInstruction::VarDecl(res.clone(), chan_type));
+                instr.push(IrInstruction::VarAssignment(
+                    res.clone(),
+                    IrValue::Imm(make),
+                ));
+                (instr, Some(IrValue::Var(res)))
+            }
+            ValueExpr::Send(channel, value) => {
+                let (mut instr, channel_res) = channel.0.direct_or_with_instr(type_env, env);
+                let Some(channel_res) = channel_res else {
+                    return (instr, None);
+                };
+
+                let (value_instr, value_res) = value.0.direct_or_with_instr(type_env, env);
+                instr.extend(value_instr);
+                let Some(value_res) = value_res else {
+                    return (instr, None);
+                };
+
+                instr.push(IrInstruction::Send(channel_res, value_res));
+                (instr, Some(IrValue::empty_tuple()))
+            }
+            ValueExpr::Receive(channel) => {
+                let (mut instr, channel_res) = channel.0.direct_or_with_instr(type_env, env);
+                let Some(channel_res) = channel_res else {
+                    return (instr, None);
+                };
+
+                let res = env.new_var();
+                instr.push(IrInstruction::VarDecl(
+                    res.clone(),
+                    TypeExpr::from_value_expr(self, type_env).as_go_type_annotation(type_env),
+                ));
+                instr.push(IrInstruction::VarAssignment(
+                    res.clone(),
+                    IrValue::Receive(channel_res.into()),
+                ));
+                (instr, Some(IrValue::Var(res)))
+            }
+            ValueExpr::Select { arms, else_arm } => {
+                // the go select only records which arm is ready, the arms run after it so that
+                // a break or continue in them still refers to the surrounding loop
+                let mut instr = Vec::new();
+                let chosen = env.new_var();
+                instr.push(IrInstruction::VarDecl(chosen.clone(), "int".to_string()));
+                let choose = |i: usize| {
+                    vec![IrInstruction::VarAssignment(
+                        chosen.clone(),
+                        IrValue::Imm(i.to_string()),
+                    )]
+                };
+
+                let mut cases = Vec::new();
+                let mut received = Vec::new();
+                for (i, arm) in arms.iter().enumerate() {
+                    let header = match &arm.case.0 {
+                        ValueExpr::Receive(channel) => {
+                            let (channel_instr, channel_res) =
+                                channel.0.direct_or_with_instr(type_env, env);
+                            instr.extend(channel_instr);
+                            let Some(channel_res) = channel_res else {
+                                return (instr, None);
+                            };
+
+                            match &arm.binding {
+                                Some(binding) => {
+                                    let received_type =
+                                        TypeExpr::from_value_expr(&arm.case.0, type_env)
+                                            .as_go_type_annotation(type_env);
+                                    let received_var = env.new_var();
+                                    instr.push(IrInstruction::VarDecl(
+                                        received_var.clone(),
+                                        received_type.clone(),
+                                    ));
+                                    received.push(Some((
+                                        binding.clone(),
+                                        received_var.clone(),
+                                        received_type,
+                                    )));
+                                    format!("{received_var} = <-{}", channel_res.emit_as_go())
+                                }
+                                None => {
+                                    received.push(None);
+                                    format!("<-{}", channel_res.emit_as_go())
+                                }
+                            }
+                        }
+                        ValueExpr::Send(channel, value) => {
+                            let (channel_instr, channel_res) =
+                                channel.0.direct_or_with_instr(type_env, env);
+                            instr.extend(channel_instr);
+                            let Some(channel_res) = channel_res else {
+                                return (instr, None);
+                            };
+
+                            let (value_instr, value_res) =
+                                value.0.direct_or_with_instr(type_env, env);
+                            instr.extend(value_instr);
+                            let Some(value_res) = value_res else {
+                                return (instr, None);
+                            };
+
+                            received.push(None);
+                            format!("{} <- {}", channel_res.emit_as_go(), value_res.emit_as_go())
+                        }
+                        _ => panic!("compiler error: a select arm can only send or receive"),
+                    };
+                    cases.push((header, choose(i)));
+                }
+
+                instr.push(IrInstruction::Select(
+                    cases,
+                    else_arm.as_ref().map(|_| choose(arms.len())),
+                ));
+
+                let mut arm_bodies = Vec::new();
+                for (arm, received) in arms.iter().zip(received) {
+                    let mut body = Vec::new();
+                    if let Some((binding, received_var, received_type)) = received {
+                        body.push(IrInstruction::VarDecl(binding.clone(), received_type));
+                        body.push(IrInstruction::VarAssignment(
+                            binding,
+                            IrValue::Var(received_var),
+                        ));
+                    }
+                    let (arm_instr, _) = arm.value_expr.0.direct_or_with_instr(type_env, env);
+                    body.extend(arm_instr);
+                    arm_bodies.push(body);
+                }
+
+                let mut chain = else_arm
+                    .as_ref()
+                    .map(|else_arm| else_arm.0.direct_or_with_instr(type_env, env).0);
+                for (i, body) in arm_bodies.into_iter().enumerate().rev() {
+                    chain = Some(vec![IrInstruction::If(
+                        IrValue::Imm(format!("ConcDuckBool {{ value: {chosen} == {i} }}")),
+                        body,
+                        chain,
+                    )]);
+                }
+                instr.extend(chain.unwrap_or_default());
+
+                (instr, Some(IrValue::empty_tuple()))
+            }
+            ValueExpr::Recover {
+                body,
+                binding,
+                handler,
+            } => {
+                let (body_instr, _) = body.0.direct_or_with_instr(type_env, env);
+
+                let mut handler_instr = vec![
+                    IrInstruction::VarDecl(
+                        binding.clone(),
+                        TypeExpr::String.as_go_type_annotation(type_env),
+                    ),
+                    IrInstruction::VarAssignment(
+                        binding.clone(),
+                        IrValue::Imm(
+                            "ConcDuckString { value: duck_panic_message(duck_panic) }".to_string(),
+                        ),
+                    ),
+                    IrInstruction::InlineGo(format!("_ = {binding}")),
+                ];
+                handler_instr.extend(handler.0.direct_or_with_instr(type_env, env).0);
+
+                (
+                    vec![IrInstruction::Recover(body_instr, handler_instr)],
+                    Some(IrValue::empty_tuple()),
+                )
+            }
             ValueExpr::Return(expr) => {
                 if let Some(expr) = expr {
                     let (expr, _) = &**expr;
                     let (mut instr, res) = expr.direct_or_with_instr(type_env, env);
+                    let res = match TypeExpr::from_value_expr(expr, type_env)
+                        .multi_return_types(type_env)
+                    {
+                        Some(types) => res.map(|res| IrValue::multi_from_tuple(res, types.len())),
+                        None => res,
+                    };
                     instr.push(IrInstruction::Return(res));
                     (instr, None)
                 } else {
@@ -1354,6 +2555,21 @@ impl ValueExpr {
                 params,
                 type_params: _,
             } => {
+                if let ValueExpr::FieldAccess {
+                    target_obj,
+                    field_name,
+                } = &v_target.0
+                    && is_builtin_method(&target_obj.0, field_name, type_env)
+                {
+                    return emit_builtin_method_call(
+                        &target_obj.0,
+                        field_name,
+                        params,
+                        type_env,
+                        env,
+                    );
+                }
+
                 // todo: type_params
 
                 let mut res = v_target.0.direct_emit(type_env, env);
@@ -1369,24 +2585,62 @@ impl ValueExpr {
                 }
                 let call_target = res.unwrap();
 
+                let TypeExpr::Fun(param_types, return_type) =
+                    TypeExpr::from_value_expr(&v_target.0, type_env)
+                else {
+                    panic!("can only call function")
+                };
+
                 let mut v_p_res = Vec::new();
-                for (param, _) in params {
+                for (i, (param, _)) in params.iter().enumerate() {
                     let (p_instr, p_res) = param.direct_or_with_instr(type_env, env);
                     instr.extend(p_instr);
-                    if let Some(p_res) = p_res {
-                        v_p_res.push(p_res);
-                    } else {
+                    let Some(mut p_res) = p_res else {
                         return (instr, None);
+                    };
+
+                    // the values of a variadic param are one array literal by now, so only values
+                    // past the known params are passed as they are
+                    if let Some((_, (param_type, _))) = param_types.get(i) {
+                        let (adapter_instr, adapted) =
+                            adapt_value(p_res, param_type, param, type_env, env);
+                        instr.extend(adapter_instr);
+                        p_res = adapted;
                     }
+                    v_p_res.push(p_res);
                 }
 
-                let TypeExpr::Fun(_, return_type) =
-                    TypeExpr::from_value_expr(&v_target.0, type_env)
-                else {
-                    panic!("can only call function")
-                };
+                if let Some(return_type) = &return_type
+                    && let Some(types) = return_type.0.multi_return_types(type_env)
+                {
+                    // receive the multiple return values one by one and pack them into a tuple
+                    let mut results = Vec::new();
+                    for type_expr in &types {
+                        let result = env.new_var();
+                        instr.push(IrInstruction::VarDecl(
+                            result.clone(),
+                            type_expr.as_go_type_annotation(type_env),
+                        ));
+                        results.push(result);
+                    }
+                    instr.push(IrInstruction::FunCall(
+                        Some(results.join(", ")),
+                        call_target,
+                        v_p_res,
+                    ));
 
-                if let Some(return_type) = return_type {
+                    let res = env.new_var();
+                    let tuple_type = return_type.0.as_go_type_annotation(type_env);
+                    instr.push(IrInstruction::VarDecl(res.clone(), tuple_type.clone()));
+                    let results = results.into_iter().map(IrValue::Var).collect();
+                    instr.push(IrInstruction::VarAssignment(
+                        res.clone(),
+                        IrValue::Tuple(tuple_type, results),
+                    ));
+                    (instr, Some(IrValue::Var(res)))
+                } else if let Some(return_type) = return_type
+                    && return_type.0 != TypeExpr::Never
+                {
                     let res = env.new_var();
                     instr.push(IrInstruction::VarDecl(
                         res.clone(),
@@ -1404,6 +2658,22 @@ impl ValueExpr {
                 }
             }
             ValueExpr::RawVariable(_, p) => (vec![], as_rvar(mangle(p))),
+            ValueExpr::Variable(_, x, _) if type_env.const_names.contains(x) => {
+                // go consts are untyped, so a use has to wrap it into the duck value of its type
+                let type_expr = TypeExpr::from_value_expr(self, type_env);
+                let res = env.new_var();
+                let instr = vec![
+                    IrInstruction::VarDecl(res.clone(), type_expr.as_go_type_annotation(type_env)),
+                    IrInstruction::VarAssignment(
+                        res.clone(),
+                        IrValue::Imm(format!(
+                            "{} {{ value: {x} }}",
+                            primitive_conc_type_name(&type_expr)
+                        )),
+                    ),
+                ];
+                (instr, as_rvar(res))
+            }
             ValueExpr::Variable(_, x, _) => (vec![], as_rvar(x.to_owned())),
             ValueExpr::Equals(v1, v2) => {
                 let mut ir = Vec::new();
@@ -1681,6 +2951,8 @@ impl ValueExpr {
                                 fields,
                                 methods: _,
                                 generics: _,
+                                doc_comments: _,
+                                attributes: Vec::new(),
                             } = type_env.get_struct_def(struct_name.as_str());
                             let f = fields
                                 .iter()
@@ -1810,6 +3082,7 @@ mod tests {
         parse::{
             lexer::lex_parser,
             make_input,
+            source_map::FileId,
             type_parser::TypeExpr,
             value_parser::{empty_range, value_expr_parser},
         },
@@ -1865,6 +3138,71 @@ mod tests {
                     ),
                 ],
             ),
+            (
+                "1 << 2",
+                vec![
+                    decl("var_0", "DuckInt"),
+                    IrInstruction::ShiftLeft("var_0".into(), IrValue::Int(1), IrValue::Int(2)),
+                ],
+            ),
+            (
+                "6 & 3",
+                vec![
+                    decl("var_0", "DuckInt"),
+                    IrInstruction::BitAnd("var_0".into(), IrValue::Int(6), IrValue::Int(3)),
+                ],
+            ),
+            (
+                "~1",
+                vec![
+                    decl("var_0", "DuckInt"),
+                    IrInstruction::VarAssignment(
+                        "var_0".into(),
+                        IrValue::BitNegate(IrValue::Int(1).into()),
+                    ),
+                ],
+            ),
+            (
+                "0..=3",
+                vec![
+                    decl("var_0", "[]DuckInt"),
+                    IrInstruction::FunCall(
+                        Some("var_0".into()),
+                        IrValue::Imm("duck_range".into()),
+                        vec![
+                            IrValue::MethodCall(
+                                IrValue::Int(0).into(),
+                                "as_dgo_int".into(),
+                                vec![],
+                            ),
+                            IrValue::MethodCall(
+                                IrValue::Int(3).into(),
+                                "as_dgo_int".into(),
+                                vec![],
+                            ),
+                            IrValue::Imm("true".into()),
+                        ],
+                    ),
+                ],
+            ),
+            (
+                "f\"{3:<04}\"",
+                vec![
+                    decl("var_0", "DuckString"),
+                    IrInstruction::StringConcat(
+                        "var_0".into(),
+                        vec![IrValue::Sprintf(
+                            "%-04d".into(),
+                            IrValue::MethodCall(
+                                IrValue::Int(3).into(),
+                                "as_dgo_int".into(),
+                                vec![],
+                            )
+                            .into(),
+                        )],
+                    ),
+                ],
+            ),
             ("(true, break, 2)", vec![IrInstruction::Break]),
             ("(true, return, 2)", vec![IrInstruction::Return(None)]),
             ("(true, continue, 3)", vec![IrInstruction::Continue]),
@@ -1915,6 +3253,17 @@ mod tests {
                     ),
                 ],
             ),
+            (
+                "for x in [1] { 2; }",
+                vec![
+                    IrInstruction::VarDecl("var_0".into(), "[]DuckInt".into()),
+                    IrInstruction::VarAssignment(
+                        "var_0".into(),
+                        IrValue::Array("[]DuckInt".into(), vec![IrValue::Int(1)]),
+                    ),
+                    IrInstruction::ForRange("x".into(), IrValue::Var("var_0".into()), vec![]),
+                ],
+            ),
             (
                 "{ x: 123 }",
                 vec![
@@ -1969,10 +3318,32 @@ mod tests {
                     ),
                 ],
             ),
+            (
+                "chan<Int>()",
+                vec![
+                    decl("var_0", "chan DuckInt"),
+                    IrInstruction::VarAssignment(
+                        "var_0".into(),
+                        IrValue::Imm("make(chan DuckInt)".into()),
+                    ),
+                ],
+            ),
+            (
+                "chan<Int>(4)",
+                vec![
+                    decl("var_0", "chan DuckInt"),
+                    IrInstruction::VarAssignment(
+                        "var_0".into(),
+                        IrValue::Imm(
+                            "make(chan DuckInt, ConcDuckInt { value: 4 }.as_dgo_int())".into(),
+                        ),
+                    ),
+                ],
+            ),
         ];
 
         for (src, exp) in test_cases {
-            let lexed = lex_parser("test", src).parse(src).unwrap();
+            let lexed = lex_parser(FileId::EMPTY).parse(src).unwrap();
             let parsed = value_expr_parser(make_input)
                 .parse(make_input(empty_range(), &lexed))
                 .unwrap()
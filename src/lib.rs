@@ -0,0 +1,701 @@
+#![feature(impl_trait_in_bindings)]
+#![allow(
+    clippy::needless_return,
+    clippy::match_like_matches_macro,
+    clippy::only_used_in_recursion,
+    clippy::large_enum_variant
+)]
+
+use std::{
+    env,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chumsky::{Parser, error::Rich};
+use parse::{Spanned, lexer::Token, source_file_parser::SourceFile};
+use tags::Tag;
+
+use crate::{
+    dargo::queries,
+    parse::{
+        Context, SS,
+        attribute_parser::host_target_os,
+        doc_comment_parser::strip_comments,
+        function_parser::LambdaFunctionExpr,
+        lexer::{end_statements_at_line_breaks, lex_parser, push_eof, warn_statement_starts},
+        macro_expander, make_input, module_parse_failures, parse_failures, parse_report,
+        source_file_parser::{ModuleErrors, prefetch_modules, source_file_parser},
+        source_map::{self, FileId},
+        trivia::{TokenWithTrivia, attach_trivia},
+        type_parser::{Duck, TypeExpr},
+        use_statement_parser::UseStatement,
+        value_parser::{
+            Assignment, Declaration, ValFmtStringContents, ValHtmlStringContents, ValueExpr,
+        },
+    },
+    semantics::{
+        const_eval, recursive_types,
+        type_resolve::{self, TypeEnv},
+        visibility,
+    },
+};
+
+use lazy_static::lazy_static;
+
+pub mod cli;
+pub mod dargo;
+pub mod emit;
+pub mod fixup;
+pub mod go_fixup;
+pub mod parse;
+pub mod semantics;
+pub mod tags;
+
+lazy_static! {
+    static ref DUCK_STD_PATH: PathBuf = {
+        env::home_dir()
+            .map(|mut path| {
+                path.push(".duck");
+                path.push("std");
+                path.push("std.duck");
+                path
+            })
+            .expect("couldn't get pathbuf for std lib")
+    };
+    static ref DARGO_DOT_DIR: PathBuf = {
+        fn require_sub_dir(str: &str) {
+            let Ok(current_dir) = env::current_dir() else {
+                println!("{}{} coulnd't read current dir", Tag::Dargo, Tag::Err,);
+                panic!()
+            };
+
+            let required_dir = {
+                let mut current_dir_clone = current_dir.clone();
+                current_dir_clone.push(str);
+                current_dir_clone
+            };
+
+            if required_dir.exists() {
+                return;
+            }
+
+            if let Err(err) = fs::create_dir(required_dir.clone()) {
+                println!(
+                    "{}{} Couldn't create {} dot dir in current directory. - {err}",
+                    Tag::Dargo,
+                    Tag::Err,
+                    required_dir.to_string_lossy()
+                );
+            }
+        }
+
+        let duck_dir = Path::new(".dargo");
+
+        require_sub_dir(".dargo");
+        require_sub_dir(".dargo/git");
+        require_sub_dir(".dargo/project");
+
+        return duck_dir.to_path_buf();
+    };
+}
+
+// todo(@Mvmo): code doc generation, using doc comments
+//      we already have the function lex_with_comments, right below this issue.
+//      now the only thing we have to do is lex the code with comments and then generate some kind of report, maybe in json, which can be interpreted by some docs generator.
+//      or maybe just generate the html docs directly
+// the lexer skips what it couldn't read, so every lexer error is shown before giving up
+fn report_lex_errors(file_id: FileId, file_contents: &str, lex_errors: &[Rich<'_, char>]) {
+    for e in lex_errors {
+        parse_report(
+            &source_map::file_name(file_id),
+            &lex_error(file_id, e),
+            file_contents,
+        );
+    }
+
+    if !lex_errors.is_empty() {
+        panic!("{} lexer errors", lex_errors.len());
+    }
+}
+
+// a lexer error as an error of the token parsers, so that both are reported the same way
+fn lex_error(file_id: FileId, e: &Rich<'_, char>) -> Rich<'static, Token, SS> {
+    Rich::custom(
+        SS {
+            start: e.span().start as u32,
+            end: e.span().end as u32,
+            context: Context { file_id },
+        },
+        format!("{}{} {}", Tag::Lexer, Tag::Err, e.reason()),
+    )
+}
+
+fn lex_with_comments(file_id: FileId, file_contents: &str) -> Vec<Spanned<Token>> {
+    let (lex, lex_errors) = lex_parser(file_id)
+        .parse(file_contents)
+        .into_output_errors();
+
+    report_lex_errors(file_id, file_contents, &lex_errors);
+
+    lex.unwrap()
+}
+
+// keeps comments and whitespace around the tokens for tooling like a formatter, the tokens
+// can't be parsed as they are, use lex for that
+pub fn lex_with_trivia(file_id: FileId, file_contents: &str) -> Vec<TokenWithTrivia> {
+    attach_trivia(
+        file_id,
+        &lex_with_comments(file_id, file_contents),
+        file_contents,
+    )
+}
+
+pub fn lex(file_id: FileId, file_contents: &str) -> Vec<Spanned<Token>> {
+    let contents_hash = queries::input_hash(&file_contents);
+    if let Some(tokens) = queries::TOKENS.lock().unwrap().get(&file_id, contents_hash) {
+        return tokens;
+    }
+
+    let (tokens, errors) = lex_partial(file_id, file_contents);
+    parse_failures(&source_map::file_name(file_id), &errors, file_contents);
+
+    queries::TOKENS
+        .lock()
+        .unwrap()
+        .insert(file_id, contents_hash, tokens.clone());
+
+    tokens
+}
+
+// the lexer, the macro expansion and the statement ends, with the errors returned next to the
+// tokens that could be read. lex reports them, tooling that keeps going with a broken file and
+// the modules, whose errors are reported with the file that declares them, use this directly
+pub fn lex_partial(
+    file_id: FileId,
+    file_contents: &str,
+) -> (Vec<Spanned<Token>>, Vec<Rich<'static, Token, SS>>) {
+    let (lex, lex_errors) = lex_parser(file_id)
+        .parse(file_contents)
+        .into_output_errors();
+
+    let mut errors = lex_errors
+        .iter()
+        .map(|e| lex_error(file_id, e))
+        .collect::<Vec<_>>();
+
+    let (tokens, macro_errors) =
+        macro_expander::expand_macros(strip_comments(&lex.unwrap_or_default()));
+    errors.extend(macro_errors);
+    let (mut tokens, statement_starts) = end_statements_at_line_breaks(tokens, file_contents);
+    warn_statement_starts(&statement_starts);
+    push_eof(&mut tokens, file_id, file_contents.len());
+
+    (tokens, errors)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    // compiles the test blocks into a binary whose main runs all of them
+    pub with_tests: bool,
+    // enabled features, items behind a #[cfg(feature = "..")] of any other feature are dropped
+    pub features: Vec<String>,
+    // the GOOS that #[cfg(target_os = "..")] is checked against, the os of the compiler if none
+    pub target_os: Option<String>,
+    // where the modules of the file are looked up, the directory of the file if none
+    pub module_dir: Option<PathBuf>,
+}
+
+// broken items are skipped, so tooling still gets the rest of the file next to the errors. the
+// modules the file declares are looked up in module_dir
+pub fn parse_partial<'src>(
+    module_dir: &Path,
+    src_file_id: FileId,
+    src_file_file_contents: &str,
+    tokens: &'src [Spanned<Token>],
+) -> (SourceFile, Vec<Rich<'src, Token, SS>>) {
+    prefetch_modules(tokens, module_dir);
+    let (src_file, parse_errors) = source_file_parser(module_dir.to_path_buf(), make_input)
+        .parse(make_input(
+            SS {
+                start: 0,
+                end: src_file_file_contents.len() as u32,
+                context: Context {
+                    file_id: src_file_id,
+                },
+            },
+            tokens,
+        ))
+        .into_output_errors();
+
+    (src_file.unwrap_or_default(), parse_errors)
+}
+
+pub fn parse_src_file(
+    src_file: &Path,
+    src_file_id: FileId,
+    src_file_file_contents: &str,
+    tokens: Vec<Spanned<Token>>,
+    options: &ParseOptions,
+) -> SourceFile {
+    if !DUCK_STD_PATH.exists() {
+        println!(
+            "{}{}{} Standard library not found",
+            Tag::Dargo,
+            Tag::Err,
+            Tag::Build,
+        );
+        std::process::exit(0);
+    }
+
+    let target_os = options.target_os.as_deref().unwrap_or(host_target_os());
+    let module_dir = options
+        .module_dir
+        .as_deref()
+        .or(src_file.parent())
+        .unwrap_or(Path::new(""));
+    // std and the main file don't depend on each other until they are merged, only the wildcard
+    // imports of the main file have to look into the modules of std. their errors are reported
+    // together once both are parsed
+    let ((std_module, std_errors), (mut src_file, parse_errors)) =
+        rayon::join(parse_std_file, || {
+            queries::ast(src_file_id, src_file_file_contents, || {
+                let (src_file, parse_errors) =
+                    parse_partial(module_dir, src_file_id, src_file_file_contents, &tokens);
+                (
+                    src_file,
+                    parse_errors.into_iter().map(Rich::into_owned).collect(),
+                )
+            })
+        });
+    module_parse_failures(&[std_errors, parse_errors].concat());
+
+    let (std_module, ()) = rayon::join(
+        || prepare_std_module(std_module, target_os),
+        || {
+            src_file.apply_cfg(&options.features, target_os);
+            if options.with_tests {
+                let tests = src_file.tests_into_functions();
+                src_file.add_test_runner(&tests);
+            }
+            const_eval::evaluate_comptime(&mut src_file);
+        },
+    );
+    src_file.resolve_wildcard_imports(Some(&std_module));
+    let (std_src_file, mut result) = rayon::join(
+        || flatten_std(std_module),
+        || src_file.flatten(&vec![], true),
+    );
+
+    // TODO: do this for all dependencies
+    for s in &std_src_file.function_definitions {
+        result.function_definitions.push(s.clone());
+    }
+    for s in &std_src_file.type_definitions {
+        result.type_definitions.push(s.clone());
+    }
+    for s in &std_src_file.struct_definitions {
+        result.struct_definitions.push(s.clone());
+    }
+    for s in &std_src_file.const_definitions {
+        result.const_definitions.push(s.clone());
+    }
+    for s in &std_src_file.global_variables {
+        result.global_variables.push(s.clone());
+    }
+    for s in &std_src_file.use_statements {
+        if let UseStatement::Go(..) = s {
+            result.push_use(s);
+        }
+    }
+    result
+        .c_declarations
+        .extend(std_src_file.c_declarations.iter().cloned());
+    for s in &std_src_file.tsx_components {
+        result.tsx_components.push(s.clone());
+    }
+    for s in &std_src_file.duckx_components {
+        result.duckx_components.push(s.clone());
+    }
+    for s in &std_src_file.trait_implementations {
+        result.trait_implementations.push(s.clone());
+    }
+    for s in &std_src_file.private_items {
+        result.private_items.push(s.clone());
+    }
+    for s in &std_src_file.re_exports {
+        result.re_exports.push(s.clone());
+    }
+
+    result
+}
+
+fn parse_std_file() -> (SourceFile, ModuleErrors) {
+    let file_text = source_map::read_source(&DUCK_STD_PATH).unwrap();
+    let std_file_id = source_map::add_file_as(&DUCK_STD_PATH, "std.duck", file_text);
+    let file_text = source_map::file_contents(std_file_id);
+    let std_dir = {
+        let mut buf = DUCK_STD_PATH.to_path_buf();
+        buf.pop();
+        buf
+    };
+    queries::ast(std_file_id, &file_text, || {
+        let lex = lex(std_file_id, &file_text);
+        prefetch_modules(&lex, &std_dir);
+        let (std_src_file, parse_errors) = source_file_parser(std_dir.clone(), make_input)
+            .parse(make_input(
+                SS {
+                    start: 0,
+                    end: file_text.len() as u32,
+                    context: Context {
+                        file_id: std_file_id,
+                    },
+                },
+                lex.as_slice(),
+            ))
+            .into_output_errors();
+        (
+            std_src_file.unwrap_or_default(),
+            parse_errors.into_iter().map(Rich::into_owned).collect(),
+        )
+    })
+}
+
+fn prepare_std_module(mut std_src_file: SourceFile, target_os: &str) -> SourceFile {
+    // the features are the ones of the project, std only has platform specific items
+    std_src_file.apply_cfg(&[], target_os);
+    const_eval::evaluate_comptime(&mut std_src_file);
+    std_src_file.resolve_wildcard_imports(None);
+    std_src_file
+}
+
+fn flatten_std(std_module: SourceFile) -> SourceFile {
+    let mut std_src_file = std_module.flatten(&vec!["std".to_string()], false);
+
+    for func in std_src_file.function_definitions.iter_mut() {
+        if let Some(params) = &mut func.params {
+            for (_, p) in params {
+                typename_reset_global(&mut p.0);
+            }
+        }
+
+        if let Some(ret) = &mut func.return_type {
+            typename_reset_global(&mut ret.0);
+        }
+
+        for default in &mut func.param_defaults {
+            typename_reset_global_value_expr(&mut default.0);
+        }
+        typename_reset_global_value_expr(&mut func.value_expr.0);
+    }
+
+    fn typename_reset_global(t: &mut TypeExpr) {
+        match t {
+            TypeExpr::TypeName(global, _, type_params) => {
+                type_params
+                    .iter_mut()
+                    .flat_map(|x| x.iter_mut().map(|x| &mut x.0))
+                    .for_each(typename_reset_global);
+                *global = false;
+            }
+            TypeExpr::Array(t) | TypeExpr::Chan(t) => typename_reset_global(&mut t.0),
+            TypeExpr::Map(key, value) => {
+                typename_reset_global(&mut key.0);
+                typename_reset_global(&mut value.0);
+            }
+            TypeExpr::Duck(Duck { fields }) => {
+                for field in fields {
+                    typename_reset_global(&mut field.type_expr.0);
+                }
+            }
+            TypeExpr::Tuple(fields) => {
+                for field in fields {
+                    typename_reset_global(&mut field.0);
+                }
+            }
+            TypeExpr::Fun(params, ret) => {
+                for (_, p) in params {
+                    typename_reset_global(&mut p.0);
+                }
+
+                if let Some(ret) = ret {
+                    typename_reset_global(&mut ret.0);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn typename_reset_global_value_expr(type_expr: &mut ValueExpr) {
+        match type_expr {
+            ValueExpr::HtmlString(contents) => {
+                for c in contents {
+                    if let ValHtmlStringContents::Expr(e) = c {
+                        typename_reset_global_value_expr(&mut e.0);
+                    }
+                }
+            }
+            ValueExpr::Select { arms, else_arm } => {
+                for arm in arms {
+                    typename_reset_global_value_expr(&mut arm.case.0);
+                    typename_reset_global_value_expr(&mut arm.value_expr.0);
+                }
+                if let Some(else_arm) = else_arm {
+                    typename_reset_global_value_expr(&mut else_arm.0);
+                }
+            }
+            ValueExpr::Recover { body, handler, .. } => {
+                typename_reset_global_value_expr(&mut body.0);
+                typename_reset_global_value_expr(&mut handler.0);
+            }
+            ValueExpr::Match {
+                value_expr,
+                arms,
+                else_arm,
+            } => {
+                typename_reset_global_value_expr(&mut value_expr.0);
+                for arm in arms {
+                    if let Some(condition) = &mut arm.condition {
+                        typename_reset_global_value_expr(&mut condition.0);
+                    }
+                    typename_reset_global_value_expr(&mut arm.value_expr.0);
+                    typename_reset_global(&mut arm.type_case.0);
+                }
+                if let Some(else_arm) = else_arm {
+                    if let Some(condition) = &mut else_arm.condition {
+                        typename_reset_global_value_expr(&mut condition.0);
+                    }
+                    typename_reset_global_value_expr(&mut else_arm.value_expr.0);
+                    typename_reset_global(&mut else_arm.type_case.0);
+                }
+            }
+            ValueExpr::Block(exprs) => {
+                for expr in exprs {
+                    typename_reset_global_value_expr(&mut expr.0);
+                }
+            }
+            ValueExpr::Add(l, r)
+            | ValueExpr::Mul(l, r)
+            | ValueExpr::Sub(l, r)
+            | ValueExpr::Div(l, r)
+            | ValueExpr::Mod(l, r)
+            | ValueExpr::BitAnd(l, r)
+            | ValueExpr::BitOr(l, r)
+            | ValueExpr::BitXor(l, r)
+            | ValueExpr::ShiftLeft(l, r)
+            | ValueExpr::ShiftRight(l, r)
+            | ValueExpr::Range {
+                start: l, end: r, ..
+            }
+            | ValueExpr::Equals(l, r)
+            | ValueExpr::NotEquals(l, r)
+            | ValueExpr::LessThan(l, r)
+            | ValueExpr::LessThanOrEquals(l, r)
+            | ValueExpr::GreaterThan(l, r)
+            | ValueExpr::GreaterThanOrEquals(l, r)
+            | ValueExpr::And(l, r)
+            | ValueExpr::Or(l, r)
+            | ValueExpr::Update(l, r)
+            | ValueExpr::Coalesce(l, r) => {
+                typename_reset_global_value_expr(&mut l.0);
+                typename_reset_global_value_expr(&mut r.0);
+            }
+            ValueExpr::Lambda(l) => {
+                let LambdaFunctionExpr {
+                    params,
+                    return_type,
+                    value_expr,
+                } = &mut **l;
+                for (_, p) in params {
+                    typename_reset_global(&mut p.0);
+                }
+
+                if let Some(return_type) = return_type {
+                    typename_reset_global(&mut return_type.0);
+                }
+
+                typename_reset_global_value_expr(&mut value_expr.0);
+            }
+            ValueExpr::ArrayAccess(target, idx) | ValueExpr::Send(target, idx) => {
+                typename_reset_global_value_expr(&mut target.0);
+                typename_reset_global_value_expr(&mut idx.0);
+            }
+            ValueExpr::Slice {
+                target, start, end, ..
+            } => {
+                typename_reset_global_value_expr(&mut target.0);
+                for bound in [start, end].into_iter().flatten() {
+                    typename_reset_global_value_expr(&mut bound.0);
+                }
+            }
+            ValueExpr::FunctionCall {
+                target,
+                params,
+                type_params: _,
+            } => {
+                // todo: type_params
+                for p in params {
+                    typename_reset_global_value_expr(&mut p.0);
+                }
+                typename_reset_global_value_expr(&mut target.0);
+            }
+            ValueExpr::FieldAccess {
+                target_obj,
+                field_name: _,
+            }
+            | ValueExpr::OptionalFieldAccess {
+                target_obj,
+                field_name: _,
+            } => {
+                typename_reset_global_value_expr(&mut target_obj.0);
+            }
+            ValueExpr::Array(ty, exprs) => {
+                if let Some(ty) = ty {
+                    typename_reset_global(&mut ty.0);
+                }
+
+                for expr in exprs {
+                    typename_reset_global_value_expr(&mut expr.0);
+                }
+            }
+            ValueExpr::Map(ty, entries) => {
+                if let Some(ty) = ty {
+                    typename_reset_global(&mut ty.0);
+                }
+
+                for (key, value) in entries {
+                    typename_reset_global_value_expr(&mut key.0);
+                    typename_reset_global_value_expr(&mut value.0);
+                }
+            }
+            ValueExpr::BoolNegate(expr)
+            | ValueExpr::BitNegate(expr)
+            | ValueExpr::TypeOf(expr)
+            | ValueExpr::Negate(expr)
+            | ValueExpr::Return(Some(expr))
+            | ValueExpr::Break(Some(expr))
+            | ValueExpr::Loop { body: expr, .. }
+            | ValueExpr::Comptime(expr)
+            | ValueExpr::Defer(expr)
+            | ValueExpr::Spawn(expr)
+            | ValueExpr::Receive(expr)
+            | ValueExpr::Spread(expr)
+            | ValueExpr::Labeled(_, expr)
+            | ValueExpr::Propagate(expr) => {
+                typename_reset_global_value_expr(&mut expr.0);
+            }
+            ValueExpr::MakeChan {
+                element_type,
+                capacity,
+            } => {
+                typename_reset_global(&mut element_type.0);
+                if let Some(capacity) = capacity {
+                    typename_reset_global_value_expr(&mut capacity.0);
+                }
+            }
+            ValueExpr::Cast(value_expr, type_expr) => {
+                typename_reset_global_value_expr(&mut value_expr.0);
+                typename_reset_global(&mut type_expr.0);
+            }
+            ValueExpr::FormattedString(content) => {
+                for c in content {
+                    if let ValFmtStringContents::Expr(e, _) = c {
+                        typename_reset_global_value_expr(&mut e.0);
+                    }
+                }
+            }
+            ValueExpr::If {
+                condition,
+                then,
+                r#else,
+            } => {
+                typename_reset_global_value_expr(&mut condition.0);
+                typename_reset_global_value_expr(&mut then.0);
+                if let Some(r#else) = r#else {
+                    typename_reset_global_value_expr(&mut r#else.0);
+                }
+            }
+            ValueExpr::While { condition, body }
+            | ValueExpr::For {
+                target: condition,
+                body,
+                ..
+            } => {
+                typename_reset_global_value_expr(&mut condition.0);
+                typename_reset_global_value_expr(&mut body.0);
+            }
+            ValueExpr::VarDecl(b) => {
+                let Declaration {
+                    name: _,
+                    type_expr,
+                    initializer,
+                } = &mut b.0;
+
+                if let Some(type_expr) = type_expr.as_mut() {
+                    typename_reset_global(&mut type_expr.0);
+                }
+
+                typename_reset_global_value_expr(&mut initializer.0);
+            }
+            ValueExpr::VarAssign(b) => {
+                let Assignment { target, value_expr } = &mut b.0;
+                typename_reset_global_value_expr(&mut target.0);
+                typename_reset_global_value_expr(&mut value_expr.0);
+            }
+            ValueExpr::Tuple(fields) => {
+                for field in fields {
+                    typename_reset_global_value_expr(&mut field.0);
+                }
+            }
+            ValueExpr::Duck(fields) => {
+                for field in fields {
+                    typename_reset_global_value_expr(&mut field.1.0);
+                }
+            }
+            ValueExpr::Struct { fields, .. } => {
+                for field in fields {
+                    typename_reset_global_value_expr(&mut field.1.0);
+                }
+            }
+            ValueExpr::Break(None)
+            | ValueExpr::Char(..)
+            | ValueExpr::Continue
+            | ValueExpr::Float(..)
+            | ValueExpr::String(..)
+            | ValueExpr::Int(..)
+            | ValueExpr::Bool(..)
+            | ValueExpr::Variable(..)
+            | ValueExpr::RawVariable(..)
+            | ValueExpr::Tag(..)
+            | ValueExpr::Return(..)
+            | ValueExpr::InlineGo(..) => {}
+        }
+    }
+
+    std_src_file
+}
+
+pub fn typecheck(src_file_ast: &mut SourceFile) -> TypeEnv {
+    let mut type_env = TypeEnv::default();
+    visibility::check_item_visibility(src_file_ast);
+    recursive_types::check_recursive_types(src_file_ast);
+    type_resolve::typeresolve_source_file(src_file_ast, &mut type_env);
+    visibility::check_field_visibility(src_file_ast, &mut type_env);
+
+    type_env
+}
+
+fn write_in_duck_dotdir(file_name: &str, content: &str) -> PathBuf {
+    let target_file = {
+        let mut target_file_path = DARGO_DOT_DIR.clone();
+        target_file_path.push(file_name);
+        target_file_path
+    };
+
+    let mut file = File::create(target_file.clone()).expect("couldn't create file in duck dot dir"); // TODO error handling
+    file.write_all(content.as_bytes())
+        .expect("couldn't write file in duck dot dir"); // TODO error handling
+
+    target_file
+}
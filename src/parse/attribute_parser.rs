@@ -0,0 +1,259 @@
+use chumsky::{input::BorrowInput, prelude::*};
+
+use crate::parse::{SS, Spanned, lexer::Token, warning};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<Spanned<String>>,
+    // key = "value" args, like feature = "http" in cfg
+    pub key_values: Vec<(String, Spanned<String>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cfg {
+    Feature(String),
+    // named like GOOS, since that's what the go compiler is told to build for
+    TargetOs(String),
+}
+
+pub const GO_TARGET_OSES: [&str; 15] = [
+    "aix",
+    "android",
+    "darwin",
+    "dragonfly",
+    "freebsd",
+    "illumos",
+    "ios",
+    "js",
+    "linux",
+    "netbsd",
+    "openbsd",
+    "plan9",
+    "solaris",
+    "wasip1",
+    "windows",
+];
+
+// the target os if none is given, the one the compiler runs on
+pub fn host_target_os() -> &'static str {
+    match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    }
+}
+
+impl Cfg {
+    pub fn is_enabled(&self, features: &[String], target_os: &str) -> bool {
+        match self {
+            Cfg::Feature(feature) => features.contains(feature),
+            Cfg::TargetOs(os) => os == target_os,
+        }
+    }
+}
+
+pub fn attribute_parser<'src, I>()
+-> impl Parser<'src, I, Spanned<Attribute>, extra::Err<Rich<'src, Token, SS>>> + Clone + 'src
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+{
+    // '#' '[' <identifier> ( '(' (<identifier> ( '=' <string> )?), ... ')' )? ']'
+    let args = select_ref! { Token::Ident(identifier) => identifier.to_string() }
+        .map_with(|arg, e| (arg, e.span()))
+        .then(
+            just(Token::ControlChar('='))
+                .ignore_then(select_ref! { Token::ConstString(value) => value.to_string() })
+                .map_with(|value, e| (value, e.span()))
+                .or_not(),
+        )
+        .separated_by(just(Token::ControlChar(',')))
+        .allow_trailing()
+        .collect::<Vec<_>>()
+        .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')')))
+        .or_not();
+
+    just(Token::ControlChar('#'))
+        .ignore_then(
+            select_ref! { Token::Ident(identifier) => identifier.to_string() }
+                .then(args)
+                .delimited_by(just(Token::ControlChar('[')), just(Token::ControlChar(']'))),
+        )
+        .map(|(name, args)| {
+            let mut attribute = Attribute {
+                name,
+                args: Vec::new(),
+                key_values: Vec::new(),
+            };
+
+            for (arg, value) in args.unwrap_or_default() {
+                match value {
+                    Some(value) => attribute.key_values.push((arg.0, value)),
+                    None => attribute.args.push(arg),
+                }
+            }
+
+            attribute
+        })
+        .map_with(|attribute, e| (attribute, e.span()))
+}
+
+// attributes are the extension point of declarations, one the compiler doesn't know for an item is
+// ignored with a warning instead of an error
+pub fn warn_unknown_attributes(attributes: &[Spanned<Attribute>], known: &[&str], item: &str) {
+    for (attribute, span) in attributes {
+        if !known.contains(&attribute.name.as_str()) {
+            warning(
+                &span.context.file_name(),
+                &format!("unknown attribute {}", attribute.name),
+                (
+                    format!("{item} has no attribute like this, so it's ignored"),
+                    *span,
+                ),
+                &span.context.file_contents(),
+            );
+        }
+    }
+}
+
+// only #[cfg(...)] attributes, every other attribute is left for the item it's attached to
+pub fn cfg_attribute_parser<'src, I>()
+-> impl Parser<'src, I, Spanned<Cfg>, extra::Err<Rich<'src, Token, SS>>> + Clone + 'src
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+{
+    attribute_parser()
+        .filter(|(attribute, _)| attribute.name == "cfg")
+        .try_map(|attribute, _| cfg_from_attribute(attribute))
+}
+
+pub fn cfg_from_attribute<'src>(
+    (attribute, span): Spanned<Attribute>,
+) -> Result<Spanned<Cfg>, Rich<'src, Token, SS>> {
+    match (&attribute.args[..], &attribute.key_values[..]) {
+        ([], [(key, (feature, _))]) if key == "feature" => {
+            Ok((Cfg::Feature(feature.clone()), span))
+        }
+        ([], [(key, (os, os_span))]) if key == "target_os" => {
+            if !GO_TARGET_OSES.contains(&os.as_str()) {
+                return Err(Rich::custom(
+                    *os_span,
+                    format!(
+                        "unknown target_os {os}, expected one of {}",
+                        GO_TARGET_OSES.join(", ")
+                    ),
+                ));
+            }
+            Ok((Cfg::TargetOs(os.clone()), span))
+        }
+        _ => Err(Rich::custom(
+            span,
+            "invalid cfg attribute, expected #[cfg(feature = \"...\")] or \
+             #[cfg(target_os = \"...\")]",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_map::FileId, value_parser::empty_range,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_attribute_parser() {
+        let test_cases = vec![
+            ("#[inline]", "inline", vec![]),
+            ("#[derive()]", "derive", vec![]),
+            ("#[derive(Show)]", "derive", vec!["Show"]),
+            (
+                "#[derive(Show, Eq, Hash, Json,)]",
+                "derive",
+                vec!["Show", "Eq", "Hash", "Json"],
+            ),
+            ("#[cfg(feature = \"http\")]", "cfg", vec![]),
+        ];
+
+        for (src, expected_name, expected_args) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let (attribute, _) = attribute_parser()
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            assert_eq!(attribute.name, expected_name, "{src}");
+            assert_eq!(
+                attribute.args.into_iter().map(|x| x.0).collect::<Vec<_>>(),
+                expected_args,
+                "{src}"
+            );
+        }
+
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("#[cfg(feature = \"http\")]")
+            .into_result()
+            .unwrap();
+        let (cfg, _) = cfg_attribute_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+        assert_eq!(cfg, Cfg::Feature("http".to_string()));
+        assert!(cfg.is_enabled(&["http".to_string()], "linux"));
+        assert!(!cfg.is_enabled(&[], "linux"));
+
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("#[cfg(target_os = \"windows\")]")
+            .into_result()
+            .unwrap();
+        let (cfg, _) = cfg_attribute_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+        assert_eq!(cfg, Cfg::TargetOs("windows".to_string()));
+        assert!(cfg.is_enabled(&[], "windows"));
+        assert!(!cfg.is_enabled(&["windows".to_string()], "linux"));
+
+        for invalid in [
+            "#derive",
+            "#[]",
+            "#[derive(1)]",
+            "[derive]",
+            "#[cfg(feature = http)]",
+            "#[cfg(target_os = linux)]",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                attribute_parser()
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_target_os() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("#[cfg(target_os = \"macos\")]")
+            .into_result()
+            .unwrap();
+        let errors = cfg_attribute_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .reason()
+                .to_string()
+                .contains("unknown target_os macos"),
+            "{errors:?}"
+        );
+    }
+}
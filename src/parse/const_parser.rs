@@ -0,0 +1,129 @@
+use chumsky::{
+    input::{BorrowInput, SliceInput},
+    prelude::*,
+};
+
+use crate::parse::{
+    SS, Spanned,
+    doc_comment_parser::doc_comments_parser,
+    lexer::Token,
+    type_parser::{TypeExpr, type_expression_parser},
+    value_parser::{ValueExpr, value_expr_parser},
+};
+
+// const MAX: Int = 1024 * 64;
+// the value is evaluated at compile time, afterwards it's a literal and the type is always set
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstDefinition {
+    pub name: String,
+    pub type_expr: Option<Spanned<TypeExpr>>,
+    pub value_expr: Spanned<ValueExpr>,
+    pub doc_comments: Vec<String>,
+    pub span: SS,
+}
+
+pub fn const_definition_parser<'src, I, M>(
+    make_input: M,
+) -> impl Parser<'src, I, ConstDefinition, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>
+        + SliceInput<'src, Slice = &'src [Spanned<Token>]>,
+    M: Fn(SS, &'src [Spanned<Token>]) -> I + Clone + 'static,
+{
+    doc_comments_parser()
+        .then_ignore(just(Token::Const))
+        .then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+        .then(
+            just(Token::ControlChar(':'))
+                .ignore_then(type_expression_parser())
+                .or_not(),
+        )
+        .then_ignore(just(Token::ControlChar('=')))
+        .then(value_expr_parser(make_input))
+        .then_ignore(just(Token::ControlChar(';')))
+        .map_with(
+            |(((doc_comments, name), type_expr), value_expr), e| ConstDefinition {
+                name,
+                type_expr,
+                value_expr,
+                doc_comments,
+                span: e.span(),
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        lexer::lex_parser,
+        make_input,
+        source_map::FileId,
+        value_parser::{empty_range, value_expr_into_empty_range},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_const_definition_parser() {
+        let test_cases = vec![
+            ("const MAX = 1024;", "MAX", None, ValueExpr::Int(1024)),
+            (
+                "const MAX: Int = 1024 * 64;",
+                "MAX",
+                Some(TypeExpr::Int),
+                ValueExpr::Mul(
+                    Box::new(ValueExpr::Int(1024).into_empty_span()),
+                    Box::new(ValueExpr::Int(64).into_empty_span()),
+                ),
+            ),
+            (
+                "const GREETING: String = \"hello\" + \" world\";",
+                "GREETING",
+                Some(TypeExpr::String),
+                ValueExpr::Add(
+                    Box::new(ValueExpr::String("hello".into(), true).into_empty_span()),
+                    Box::new(ValueExpr::String(" world".into(), true).into_empty_span()),
+                ),
+            ),
+        ];
+
+        for (src, expected_name, expected_type, expected_value) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut const_definition = const_definition_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut const_definition.value_expr);
+            assert_eq!(const_definition.name, expected_name, "{src}");
+            assert_eq!(
+                const_definition.type_expr.map(|(type_expr, _)| type_expr),
+                expected_type,
+                "{src}"
+            );
+            assert_eq!(const_definition.value_expr.0, expected_value, "{src}");
+        }
+
+        for invalid in [
+            "const MAX = 1024",
+            "const MAX;",
+            "const MAX: Int;",
+            "const = 1;",
+            "const max 1;",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                const_definition_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use chumsky::error::Rich;
+
+use crate::parse::{
+    Field, SS, Spanned,
+    attribute_parser::Attribute,
+    function_parser::FunctionDefintion,
+    lexer::Token,
+    struct_parser::StructDefinition,
+    type_parser::TypeExpr,
+    value_parser::{Declaration, ValueExpr, empty_range},
+};
+
+// every derived method is a thin duck wrapper around a go block, the go code relies on
+// emit_go_to_js from the runtime for the per field formatting, except for to_json
+fn go_method(
+    name: &str,
+    params: Vec<(String, Spanned<TypeExpr>)>,
+    return_type: TypeExpr,
+    initial_value: ValueExpr,
+    go_code: String,
+) -> FunctionDefintion {
+    FunctionDefintion {
+        name: name.to_string(),
+        return_type: Some(return_type.clone().into_empty_span()),
+        params: Some(params),
+        value_expr: ValueExpr::Block(vec![
+            ValueExpr::VarDecl(
+                (
+                    Declaration {
+                        name: "res".to_string(),
+                        type_expr: Some(return_type.into_empty_span()),
+                        initializer: initial_value.into_empty_span(),
+                    },
+                    empty_range(),
+                )
+                    .into(),
+            )
+            .into_empty_span(),
+            ValueExpr::InlineGo(go_code).into_empty_span(),
+            ValueExpr::Return(Some(
+                ValueExpr::RawVariable(false, vec!["res".to_string()])
+                    .into_empty_span()
+                    .into(),
+            ))
+            .into_empty_span(),
+        ])
+        .into_empty_span(),
+        generics: None,
+        doc_comments: Vec::new(),
+        attributes: Vec::new(),
+        param_defaults: Vec::new(),
+        variadic: false,
+    }
+}
+
+fn show(struct_def: &StructDefinition, path: &[String]) -> FunctionDefintion {
+    let name = path.join("::");
+
+    let fields = struct_def
+        .fields
+        .iter()
+        .map(|field| format!("\"{0}: \" + emit_go_to_js(self.{0})", field.name))
+        .collect::<Vec<_>>()
+        .join(" + \", \" + ");
+
+    let go_code = if fields.is_empty() {
+        format!("res = ConcDuckString {{ value: \"{name} {{}}\" }}")
+    } else {
+        format!("res = ConcDuckString {{ value: \"{name} {{ \" + {fields} + \" }}\" }}")
+    };
+
+    go_method(
+        "show",
+        vec![],
+        TypeExpr::String,
+        ValueExpr::String(String::new(), true),
+        go_code,
+    )
+}
+
+// go's == on the duck interfaces would compare boxes, so primitive fields are unwrapped and
+// compared by value. everything else is compared with reflect.DeepEqual, field by field
+fn field_eq(field: &Field) -> String {
+    let native = match &field.type_expr.0 {
+        TypeExpr::Int => "int",
+        TypeExpr::Float => "float32",
+        TypeExpr::Bool => "bool",
+        TypeExpr::Char => "rune",
+        TypeExpr::String => "string",
+        _ => return format!("reflect.DeepEqual(self.{0}, other.{0})", field.name),
+    };
+
+    format!(
+        "self.{0}.as_dgo_{native}() == other.{0}.as_dgo_{native}()",
+        field.name
+    )
+}
+
+fn eq(struct_def: &StructDefinition, path: &[String]) -> FunctionDefintion {
+    let self_type = TypeExpr::RawTypeName(
+        false,
+        path.to_vec(),
+        struct_def.generics.as_ref().map(|generics| {
+            generics
+                .iter()
+                .map(|(generic, span)| {
+                    (
+                        TypeExpr::RawTypeName(false, vec![generic.name.clone()], None),
+                        *span,
+                    )
+                })
+                .collect()
+        }),
+    );
+
+    let fields = struct_def
+        .fields
+        .iter()
+        .map(field_eq)
+        .collect::<Vec<_>>()
+        .join(" && ");
+
+    let fields = if fields.is_empty() {
+        "true".to_string()
+    } else {
+        fields
+    };
+
+    go_method(
+        "eq",
+        vec![("other".to_string(), self_type.into_empty_span())],
+        TypeExpr::Bool,
+        ValueExpr::Bool(false),
+        format!("res = ConcDuckBool {{ value: {fields} }}"),
+    )
+}
+
+fn hash() -> FunctionDefintion {
+    // fnv-1a, inlined so that the generated code doesn't need any extra imports
+    go_method(
+        "hash",
+        vec![],
+        TypeExpr::Int,
+        ValueExpr::Int(0),
+        r#"
+        h := uint64(14695981039346656037)
+        for _, c := range []byte(emit_go_to_js(self)) {
+            h ^= uint64(c)
+            h *= 1099511628211
+        }
+        res = ConcDuckInt { value: int(h) }
+        "#
+        .to_string(),
+    )
+}
+
+// the duck values are unwrapped to go values and marshalled by encoding/json, which escapes the
+// strings. a nested struct with a derived to_json is embedded as it is
+fn json(struct_def: &StructDefinition) -> FunctionDefintion {
+    let fields = struct_def
+        .fields
+        .iter()
+        .map(|field| format!("json_field(\"{0}\", self.{0})", field.name))
+        .collect::<Vec<_>>()
+        .join(" + \",\" + ");
+
+    let fields = if fields.is_empty() {
+        "\"\"".to_string()
+    } else {
+        fields
+    };
+
+    go_method(
+        "to_json",
+        vec![],
+        TypeExpr::String,
+        ValueExpr::String(String::new(), true),
+        format!(
+            r#"
+        var json_value func(field any) any
+        json_value = func(field any) any {{
+            switch field := field.(type) {{
+            case interface{{ to_json() DuckString }}:
+                return json.RawMessage(field.to_json().as_dgo_string())
+            case interface{{ as_dgo_string() string }}:
+                return field.as_dgo_string()
+            case interface{{ as_dgo_int() int }}:
+                return field.as_dgo_int()
+            case interface{{ as_dgo_float32() float32 }}:
+                return field.as_dgo_float32()
+            case interface{{ as_dgo_bool() bool }}:
+                return field.as_dgo_bool()
+            case interface{{ as_dgo_rune() rune }}:
+                return string(field.as_dgo_rune())
+            }}
+            v := reflect.ValueOf(field)
+            if v.Kind() == reflect.Array || v.Kind() == reflect.Slice {{
+                values := make([]any, v.Len())
+                for i := range values {{
+                    values[i] = json_value(v.Index(i).Interface())
+                }}
+                return values
+            }}
+            return field
+        }}
+        json_field := func(name string, field any) string {{
+            key, _ := json.Marshal(name)
+            value, err := json.Marshal(json_value(field))
+            if err != nil {{
+                panic(err)
+            }}
+            return string(key) + ":" + string(value)
+        }}
+        res = ConcDuckString {{ value: "{{" + {fields} + "}}" }}
+        "#
+        ),
+    )
+}
+
+// a file with a struct or enum that derives Json has to import encoding/json
+pub fn derives_json(attributes: &[Spanned<Attribute>]) -> bool {
+    attributes
+        .iter()
+        .filter(|(attribute, _)| attribute.name == "derive")
+        .flat_map(|(attribute, _)| &attribute.args)
+        .any(|(derive, _)| derive == "Json")
+}
+
+// path is the name of the struct as it's written in duck, Name for a struct and Enum::Variant
+// for the struct of an enum variant
+pub fn derive_methods<'src>(
+    struct_def: &StructDefinition,
+    path: &[String],
+    derives: &[Spanned<String>],
+) -> Result<Vec<FunctionDefintion>, Rich<'src, Token, SS>> {
+    let mut methods = Vec::new();
+    for (derive, span) in derives {
+        let method = match derive.as_str() {
+            "Show" => show(struct_def, path),
+            "Eq" => eq(struct_def, path),
+            "Hash" => hash(),
+            "Json" => json(struct_def),
+            _ => {
+                return Err(Rich::custom(
+                    *span,
+                    format!("can't derive {derive}, only Show, Eq, Hash and Json can be derived"),
+                ));
+            }
+        };
+
+        if struct_def.methods.iter().any(|m| m.name == method.name) {
+            return Err(Rich::custom(
+                *span,
+                format!(
+                    "{} already has a method called {}, so {derive} can't be derived",
+                    struct_def.name, method.name
+                ),
+            ));
+        }
+
+        if methods
+            .iter()
+            .any(|m: &FunctionDefintion| m.name == method.name)
+        {
+            return Err(Rich::custom(
+                *span,
+                format!("{derive} is derived more than once"),
+            ));
+        }
+
+        methods.push(method);
+    }
+
+    Ok(methods)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_methods() {
+        let struct_def = StructDefinition {
+            name: "Point".to_string(),
+            fields: vec![
+                Field::new("x".to_string(), TypeExpr::Int.into_empty_span()),
+                Field::new("y".to_string(), TypeExpr::Int.into_empty_span()),
+            ],
+            methods: vec![],
+            generics: None,
+            doc_comments: Vec::new(),
+            attributes: Vec::new(),
+        };
+
+        let test_cases = vec![
+            (vec!["Show"], vec!["show"]),
+            (vec!["Eq", "Hash"], vec!["eq", "hash"]),
+            (
+                vec!["Show", "Eq", "Hash", "Json"],
+                vec!["show", "eq", "hash", "to_json"],
+            ),
+        ];
+
+        for (derives, expected) in test_cases {
+            let derives = derives
+                .into_iter()
+                .map(|x| (x.to_string(), empty_range()))
+                .collect::<Vec<_>>();
+
+            let methods = derive_methods(&struct_def, &["Point".to_string()], &derives).unwrap();
+            assert_eq!(
+                methods.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        let show = show(&struct_def, &["Point".to_string()]);
+        let ValueExpr::Block(exprs) = &show.value_expr.0 else {
+            panic!("derived methods have to be blocks")
+        };
+        assert_eq!(
+            exprs[1].0,
+            ValueExpr::InlineGo(
+                "res = ConcDuckString { value: \"Point { \" + \"x: \" + emit_go_to_js(self.x) + \", \" + \"y: \" + emit_go_to_js(self.y) + \" }\" }".to_string()
+            )
+        );
+
+        let eq = eq(&struct_def, &["Point".to_string()]);
+        let ValueExpr::Block(exprs) = &eq.value_expr.0 else {
+            panic!("derived methods have to be blocks")
+        };
+        assert_eq!(
+            exprs[1].0,
+            ValueExpr::InlineGo(
+                "res = ConcDuckBool { value: self.x.as_dgo_int() == other.x.as_dgo_int() && self.y.as_dgo_int() == other.y.as_dgo_int() }".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_derive_json() {
+        let struct_def = StructDefinition {
+            name: "Point".to_string(),
+            fields: vec![
+                Field::new("x".to_string(), TypeExpr::Int.into_empty_span()),
+                Field::new("name".to_string(), TypeExpr::String.into_empty_span()),
+            ],
+            methods: vec![],
+            generics: None,
+            doc_comments: Vec::new(),
+            attributes: Vec::new(),
+        };
+
+        let to_json = json(&struct_def);
+        let ValueExpr::Block(exprs) = &to_json.value_expr.0 else {
+            panic!("derived methods have to be blocks")
+        };
+        let ValueExpr::InlineGo(go_code) = &exprs[1].0 else {
+            panic!("expected inline go, got {:?}", exprs[1].0)
+        };
+        assert!(go_code.contains("json.Marshal(json_value(field))"));
+        assert!(go_code.contains(
+            "res = ConcDuckString { value: \"{\" + json_field(\"x\", self.x) + \",\" + json_field(\"name\", self.name) + \"}\" }"
+        ));
+
+        let derive = |args: &[&str]| {
+            (
+                Attribute {
+                    name: "derive".to_string(),
+                    args: args
+                        .iter()
+                        .map(|arg| (arg.to_string(), empty_range()))
+                        .collect(),
+                    key_values: Vec::new(),
+                },
+                empty_range(),
+            )
+        };
+        assert!(derives_json(&[derive(&["Eq", "Json"])]));
+        assert!(!derives_json(&[derive(&["Eq", "Show"])]));
+        assert!(!derives_json(&[]));
+    }
+
+    #[test]
+    fn test_derive_unknown() {
+        let struct_def = StructDefinition {
+            name: "Point".to_string(),
+            fields: vec![],
+            methods: vec![],
+            generics: None,
+            doc_comments: Vec::new(),
+            attributes: Vec::new(),
+        };
+
+        let err = derive_methods(
+            &struct_def,
+            &["Point".to_string()],
+            &[("Clone".to_string(), empty_range())],
+        )
+        .unwrap_err();
+        assert!(err.reason().to_string().contains("can't derive Clone"));
+
+        let err = derive_methods(
+            &struct_def,
+            &["Point".to_string()],
+            &[
+                ("Show".to_string(), empty_range()),
+                ("Show".to_string(), empty_range()),
+            ],
+        )
+        .unwrap_err();
+        assert!(
+            err.reason()
+                .to_string()
+                .contains("Show is derived more than once")
+        );
+    }
+}
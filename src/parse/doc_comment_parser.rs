@@ -0,0 +1,130 @@
+use chumsky::{input::BorrowInput, prelude::*};
+
+use crate::parse::{SS, Spanned, lexer::Token};
+
+// the /// and /** */ comments right in front of a fn, struct, type or const declaration,
+// one entry per comment
+pub fn doc_comments_parser<'src, I>()
+-> impl Parser<'src, I, Vec<String>, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+{
+    select_ref! { Token::DocComment(comment) => comment.to_string() }
+        .repeated()
+        .collect::<Vec<_>>()
+}
+
+// drops all comments except for the doc comments that can be attached to a declaration, any
+// other doc comment would end up in places where the parsers don't expect one
+pub fn strip_comments(tokens: &[Spanned<Token>]) -> Vec<Spanned<Token>> {
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(i, (token, _))| match token {
+            Token::Comment(..) => false,
+            Token::DocComment(..) => is_attachable_doc_comment(&tokens[i + 1..]),
+            _ => true,
+        })
+        .map(|(_, token)| token.clone())
+        .collect()
+}
+
+fn is_attachable_doc_comment(following_tokens: &[Spanned<Token>]) -> bool {
+    let mut following_tokens = following_tokens
+        .iter()
+        .map(|(token, _)| token)
+        .filter(|token| !matches!(token, Token::Comment(..) | Token::DocComment(..)));
+
+    match following_tokens.next() {
+        // fn( is a lambda
+        Some(Token::Function) => matches!(following_tokens.next(), Some(Token::Ident(..))),
+        Some(Token::Sus | Token::Struct | Token::Type | Token::Const | Token::ControlChar('#')) => {
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_map::FileId, value_parser::empty_range,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_is_attachable_doc_comment() {
+        let test_cases = vec![
+            ("fn f() {}", true),
+            ("sus fn f() {}", true),
+            ("struct S = {};", true),
+            ("type T = Int;", true),
+            ("const MAX = 1;", true),
+            ("#[derive(Clone)] struct S = {};", true),
+            ("// between\nfn f() {}", true),
+            ("fn(x: Int) {}", false),
+            ("let x = 1;", false),
+            ("x: Int", false),
+            ("use std;", false),
+            ("", false),
+        ];
+
+        for (src, expected) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            assert_eq!(is_attachable_doc_comment(&tokens), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let test_cases = vec![
+            ("// c\nfn f() {}", vec![Token::Function]),
+            (
+                "/// d\nfn f() {}",
+                vec![Token::DocComment("d".into()), Token::Function],
+            ),
+            ("/// d\nlet x = 1;", vec![Token::Let]),
+            (
+                "/* c */ /** d */ type",
+                vec![Token::DocComment("d".into()), Token::Type],
+            ),
+        ];
+
+        for (src, expected) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let stripped = strip_comments(&tokens)
+                .into_iter()
+                .map(|(token, _)| token)
+                .take(expected.len())
+                .collect::<Vec<_>>();
+            assert_eq!(stripped, expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_doc_comments_parser() {
+        let src = "/// first\n/** second */\n// not a doc comment\n/// third";
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .unwrap()
+            .into_iter()
+            .filter(|(token, _)| !matches!(token, Token::Comment(..)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            doc_comments_parser()
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .unwrap(),
+            vec!["first", "second", "third"]
+        );
+    }
+}
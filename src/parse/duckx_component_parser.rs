@@ -1,6 +1,9 @@
 use std::collections::HashSet;
 
-use chumsky::{input::BorrowInput, prelude::*};
+use chumsky::{
+    input::{BorrowInput, SliceInput},
+    prelude::*,
+};
 use tree_sitter::{Node, Parser as TSParser};
 
 use crate::{
@@ -106,16 +109,17 @@ pub fn duckx_component_parser<'src, I, M>(
     make_input: M,
 ) -> impl Parser<'src, I, DuckxComponent, extra::Err<Rich<'src, Token, SS>>> + Clone
 where
-    I: BorrowInput<'src, Token = Token, Span = SS>,
+    I: BorrowInput<'src, Token = Token, Span = SS>
+        + SliceInput<'src, Slice = &'src [Spanned<Token>]>,
     M: Fn(SS, &'src [Spanned<Token>]) -> I + Clone + 'static,
 {
     // component Name {
     //   %javascript source
     // }
     just(Token::Template)
-        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.clone() })
+        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
         .then(
-            just(Token::Ident("props".to_string()))
+            just(Token::Ident("props".into()))
                 .ignore_then(just(Token::ControlChar(':')))
                 .ignore_then(type_expression_parser())
                 .or_not()
@@ -141,6 +145,7 @@ mod tests {
     use crate::parse::{
         lexer::lex_parser,
         make_input,
+        source_map::FileId,
         value_parser::{empty_range, value_expr_into_empty_range},
     };
 
@@ -159,7 +164,7 @@ mod tests {
 
         for (src, expected_ast) in src_and_expected_ast {
             println!("lexing {src}");
-            let lexer_parse_result = lex_parser("test", "").parse(src);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(src);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -199,7 +204,7 @@ mod tests {
         for invalid_component_statement in invalid_component_statements {
             println!("lexing {invalid_component_statement}");
 
-            let lexer_parse_result = lex_parser("test", "").parse(invalid_component_statement);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(invalid_component_statement);
 
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
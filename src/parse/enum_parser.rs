@@ -0,0 +1,320 @@
+use chumsky::{input::BorrowInput, prelude::*};
+
+use crate::{
+    parse::{
+        Field, SS, Spanned,
+        attribute_parser::{Attribute, warn_unknown_attributes},
+        derive::derive_methods,
+        doc_comment_parser::doc_comments_parser,
+        lexer::Token,
+        struct_parser::StructDefinition,
+        type_parser::{TypeDefinition, TypeExpr, type_expression_parser},
+    },
+    semantics::ident_mangler::mangle,
+};
+
+// enum Name = { Variant, Variant(T, U), Variant { field: T } };
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDefinition {
+    pub name: String,
+    pub variants: Vec<Spanned<EnumVariant>>,
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<Spanned<Attribute>>,
+    pub span: SS,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant {
+    pub name: String,
+    // the payload of Variant(T, U) is stored in the fields _0 and _1
+    pub fields: Vec<Field>,
+}
+
+impl EnumDefinition {
+    // every variant becomes a struct called Name::Variant and the enum is the union of these
+    // structs, so matching on an enum is a type switch on the go struct of its variant.
+    // #[derive(...)] on the enum derives the methods for the struct of every variant
+    pub fn to_definitions<'src>(
+        &self,
+    ) -> Result<(TypeDefinition, Vec<StructDefinition>), Rich<'src, Token, SS>> {
+        warn_unknown_attributes(&self.attributes, &["derive"], "an enum");
+        let derives = self
+            .attributes
+            .iter()
+            .filter(|(attribute, _)| attribute.name == "derive")
+            .flat_map(|(attribute, _)| attribute.args.clone())
+            .collect::<Vec<_>>();
+
+        let variant_structs = self
+            .variants
+            .iter()
+            .map(|(variant, _)| {
+                let mut variant_struct = StructDefinition {
+                    name: mangle(&[&self.name, &variant.name]),
+                    fields: variant.fields.clone(),
+                    methods: Vec::new(),
+                    generics: None,
+                    doc_comments: Vec::new(),
+                    attributes: Vec::new(),
+                };
+                let path = [self.name.clone(), variant.name.clone()];
+                variant_struct.methods = derive_methods(&variant_struct, &path, &derives)?;
+                Ok(variant_struct)
+            })
+            .collect::<Result<Vec<_>, Rich<'src, Token, SS>>>()?;
+
+        let mut variant_types = self
+            .variants
+            .iter()
+            .map(|(variant, span)| {
+                let path = vec![self.name.clone(), variant.name.clone()];
+                (TypeExpr::RawTypeName(false, path, None), *span)
+            })
+            .collect::<Vec<_>>();
+
+        let type_expression = if variant_types.len() == 1 {
+            variant_types.remove(0)
+        } else {
+            (TypeExpr::Or(variant_types), self.span)
+        };
+
+        let type_definition = TypeDefinition {
+            name: self.name.clone(),
+            type_expression,
+            generics: None,
+            doc_comments: self.doc_comments.clone(),
+            attributes: Vec::new(),
+        };
+
+        Ok((type_definition, variant_structs))
+    }
+}
+
+pub fn enum_definition_parser<'src, I>()
+-> impl Parser<'src, I, EnumDefinition, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+{
+    let field_parser = select_ref! { Token::Ident(identifier) => identifier.to_string() }
+        .then_ignore(just(Token::ControlChar(':')))
+        .then(type_expression_parser())
+        .map(|(identifier, type_expr)| Field::new(identifier, type_expr));
+
+    let tuple_payload = type_expression_parser()
+        .separated_by(just(Token::ControlChar(',')))
+        .allow_trailing()
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')')))
+        .map(|type_exprs| {
+            type_exprs
+                .into_iter()
+                .enumerate()
+                .map(|(i, type_expr)| Field::new(format!("_{i}"), type_expr))
+                .collect::<Vec<_>>()
+        });
+
+    let struct_payload = field_parser
+        .separated_by(just(Token::ControlChar(',')))
+        .allow_trailing()
+        .collect::<Vec<_>>()
+        .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}')));
+
+    let variant = select_ref! { Token::Ident(identifier) => identifier.to_string() }
+        .then(choice((tuple_payload, struct_payload)).or_not())
+        .map_with(|(name, fields), e| {
+            let fields = fields.unwrap_or_default();
+            (EnumVariant { name, fields }, e.span())
+        });
+
+    doc_comments_parser()
+        .then_ignore(just(Token::Enum))
+        .then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+        .then_ignore(just(Token::ControlChar('=')))
+        .then(
+            variant
+                .separated_by(just(Token::ControlChar(',')))
+                .allow_trailing()
+                .at_least(1)
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}'))),
+        )
+        .then_ignore(just(Token::ControlChar(';')))
+        .try_map(|((doc_comments, name), variants), span| {
+            for (i, (variant, variant_span)) in variants.iter().enumerate() {
+                if variants[..i]
+                    .iter()
+                    .any(|(other, _)| other.name == variant.name)
+                {
+                    return Err(Rich::custom(
+                        *variant_span,
+                        format!("{name} already has a variant called {}", variant.name),
+                    ));
+                }
+            }
+
+            Ok(EnumDefinition {
+                name,
+                variants,
+                doc_comments,
+                attributes: Vec::new(),
+                span,
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        lexer::lex_parser,
+        make_input,
+        source_map::FileId,
+        value_parser::{ValueExpr, empty_range},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_enum_definition_parser() {
+        let test_cases = vec![
+            (
+                "enum Dir = { Up, Down };",
+                vec![("Up", vec![]), ("Down", vec![])],
+            ),
+            (
+                "enum Shape = { Circle(Float), Rect { w: Float, h: Float, }, };",
+                vec![("Circle", vec!["_0"]), ("Rect", vec!["w", "h"])],
+            ),
+            (
+                "enum Pair = { Pair(Int, String) };",
+                vec![("Pair", vec!["_0", "_1"])],
+            ),
+        ];
+
+        for (src, expected_variants) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let enum_definition = enum_definition_parser()
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            let variants = enum_definition
+                .variants
+                .iter()
+                .map(|(variant, _)| {
+                    let fields = variant.fields.iter().map(|field| field.name.as_str());
+                    (variant.name.as_str(), fields.collect::<Vec<_>>())
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(variants, expected_variants, "{src}");
+        }
+
+        for invalid in [
+            "enum Empty = {};",
+            "enum Dir = { Up, Down }",
+            "enum Dir { Up, Down };",
+            "enum Dir = { Up, Up };",
+            "enum Shape = { Circle() };",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                enum_definition_parser()
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_enum_to_definitions() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("enum Shape = { Circle(Float), Empty };")
+            .into_result()
+            .unwrap();
+        let enum_definition = enum_definition_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+
+        let (type_definition, variant_structs) = enum_definition.to_definitions().unwrap();
+        assert_eq!(type_definition.name, "Shape");
+        let TypeExpr::Or(variant_types) = type_definition.type_expression.0 else {
+            panic!("an enum with two variants has to be a union")
+        };
+        assert_eq!(
+            variant_types
+                .into_iter()
+                .map(|(type_expr, _)| type_expr)
+                .collect::<Vec<_>>(),
+            vec![
+                TypeExpr::RawTypeName(false, vec!["Shape".into(), "Circle".into()], None),
+                TypeExpr::RawTypeName(false, vec!["Shape".into(), "Empty".into()], None),
+            ]
+        );
+
+        let names = variant_structs
+            .iter()
+            .map(|s| s.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![mangle(&["Shape", "Circle"]), mangle(&["Shape", "Empty"])]
+        );
+        assert_eq!(variant_structs[0].fields[0].name, "_0");
+        assert!(variant_structs[1].fields.is_empty());
+    }
+
+    #[test]
+    fn test_enum_derive() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("enum Shape = { Circle(Float), Empty };")
+            .into_result()
+            .unwrap();
+        let mut enum_definition = enum_definition_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+        enum_definition.attributes = vec![(
+            Attribute {
+                name: "derive".to_string(),
+                args: vec![
+                    ("Show".to_string(), empty_range()),
+                    ("Eq".to_string(), empty_range()),
+                ],
+                key_values: Vec::new(),
+            },
+            empty_range(),
+        )];
+
+        let (_, variant_structs) = enum_definition.to_definitions().unwrap();
+        for variant_struct in &variant_structs {
+            let methods = variant_struct
+                .methods
+                .iter()
+                .map(|method| method.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(methods, vec!["show", "eq"]);
+        }
+
+        let Some(ValueExpr::Block(exprs)) = variant_structs[0]
+            .methods
+            .first()
+            .map(|show| &show.value_expr.0)
+        else {
+            panic!("derived methods have to be blocks")
+        };
+        assert_eq!(
+            exprs[1].0,
+            ValueExpr::InlineGo(
+                "res = ConcDuckString { value: \"Shape::Circle { \" + \"_0: \" + emit_go_to_js(self._0) + \" }\" }".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,794 @@
+use chumsky::{input::BorrowInput, prelude::*};
+
+use crate::parse::{
+    SS, Spanned,
+    attribute_parser::{Attribute, warn_unknown_attributes},
+    function_parser::{FunctionDefintion, Param},
+    lexer::Token,
+    type_parser::{TypeExpr, type_expression_parser},
+    value_parser::{Declaration, ValueExpr},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Abi {
+    C,
+    // the go package the function is in, it has to be imported with use go
+    Go(String),
+}
+
+// extern "C" fn name(params) -> T; or extern "Go" fn package.Name(params) -> T;
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternFunction {
+    pub abi: Abi,
+    pub name: String,
+    pub params: Vec<Param>,
+    // the last param is a go variadic param, it's an array on the duck side
+    pub variadic: bool,
+    pub return_type: Option<Spanned<TypeExpr>>,
+    pub span: SS,
+    // #[link(name = "m")] links a C function against libm
+    pub attributes: Vec<Spanned<Attribute>>,
+}
+
+// the c type a duck type is passed as
+fn c_type(type_expr: &Spanned<TypeExpr>) -> &'static str {
+    match &type_expr.0 {
+        TypeExpr::Int => "long long",
+        TypeExpr::Float => "double",
+        TypeExpr::Bool => "_Bool",
+        TypeExpr::String => "const char*",
+        _ => unreachable!("the parser only accepts c types"),
+    }
+}
+
+fn is_c_type(type_expr: &TypeExpr) -> bool {
+    matches!(
+        type_expr,
+        TypeExpr::Int | TypeExpr::Float | TypeExpr::Bool | TypeExpr::String
+    )
+}
+
+// go_result converts the same types a c function can return
+fn is_go_result_type(type_expr: &TypeExpr) -> bool {
+    is_c_type(type_expr)
+}
+
+fn is_go_arg_type(type_expr: &TypeExpr) -> bool {
+    is_c_type(type_expr) || matches!(type_expr, TypeExpr::Go(_))
+}
+
+// how a duck value is passed to a go function
+fn go_arg(name: &str, type_expr: &Spanned<TypeExpr>) -> String {
+    match &type_expr.0 {
+        TypeExpr::Int => format!("{name}.as_dgo_int()"),
+        TypeExpr::Float => format!("float64({name}.as_dgo_float32())"),
+        TypeExpr::Bool => format!("{name}.as_dgo_bool()"),
+        TypeExpr::String => format!("{name}.as_dgo_string()"),
+        TypeExpr::Go(_) => name.to_string(),
+        _ => unreachable!("the parser only accepts supported go types"),
+    }
+}
+
+// the go type the values of a variadic param are converted to
+fn go_variadic_type(type_expr: &Spanned<TypeExpr>) -> String {
+    match &type_expr.0 {
+        TypeExpr::Int => "int".to_string(),
+        TypeExpr::Float => "float64".to_string(),
+        TypeExpr::Bool => "bool".to_string(),
+        TypeExpr::String => "string".to_string(),
+        TypeExpr::Go(go_type) => go_type.clone(),
+        _ => unreachable!("the parser only accepts supported go types"),
+    }
+}
+
+// the duck array of a variadic param converted to a go slice, that is spread into the call
+fn go_variadic_arg(name: &str, type_expr: &Spanned<TypeExpr>) -> String {
+    let TypeExpr::Array(element_type) = &type_expr.0 else {
+        unreachable!("a variadic param is an array")
+    };
+    let go_type = go_variadic_type(element_type);
+    format!(
+        "func() []{go_type} {{\n\
+         extern_values := make([]{go_type}, 0, len({name}))\n\
+         for _, extern_value := range {name} {{\n\
+         extern_values = append(extern_values, {})\n\
+         }}\n\
+         return extern_values\n\
+         }}()...",
+        go_arg("extern_value", element_type)
+    )
+}
+
+// the duck value of what a go function returned and the default it starts with
+fn go_result(value: &str, type_expr: &Spanned<TypeExpr>) -> (ValueExpr, String) {
+    match &type_expr.0 {
+        TypeExpr::Int => (
+            ValueExpr::Int(0),
+            format!("ConcDuckInt {{ value: int({value}) }}"),
+        ),
+        TypeExpr::Float => (
+            ValueExpr::Float(0.0),
+            format!("ConcDuckFloat {{ value: float32({value}) }}"),
+        ),
+        TypeExpr::Bool => (
+            ValueExpr::Bool(false),
+            format!("ConcDuckBool {{ value: {value} }}"),
+        ),
+        TypeExpr::String => (
+            ValueExpr::String(String::new(), true),
+            format!("ConcDuckString {{ value: {value} }}"),
+        ),
+        _ => unreachable!("the parser only accepts supported go types"),
+    }
+}
+
+// T of a Result<T, go error>, which is returned as (T, error) in go
+fn go_error_result(type_expr: &TypeExpr) -> Option<&Spanned<TypeExpr>> {
+    if let TypeExpr::RawTypeName(_, path, Some(params)) = type_expr
+        && path.last().is_some_and(|name| name == "Result")
+        && let [value_type, (TypeExpr::Go(error), _)] = params.as_slice()
+        && error == "error"
+    {
+        Some(value_type)
+    } else {
+        None
+    }
+}
+
+impl ExternFunction {
+    // the prototype that goes into the cgo preamble
+    pub fn c_declaration(&self) -> String {
+        format!(
+            "{} {}({});",
+            self.return_type.as_ref().map(c_type).unwrap_or("void"),
+            self.name,
+            self.params
+                .iter()
+                .map(|(_, type_expr)| c_type(type_expr))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+
+    // the lines it adds to the cgo preamble, a #cgo LDFLAGS for every library it's linked
+    // against and its prototype
+    pub fn cgo_preamble<'src>(&self) -> Result<Vec<String>, Rich<'src, Token, SS>> {
+        warn_unknown_attributes(&self.attributes, &["link"], "a C function");
+
+        let mut lines = self
+            .attributes
+            .iter()
+            .filter(|(attribute, _)| attribute.name == "link")
+            .map(
+                |(attribute, span)| match (&attribute.args[..], &attribute.key_values[..]) {
+                    ([], [(key, (library, _))]) if key == "name" && is_library_name(library) => {
+                        Ok(format!("#cgo LDFLAGS: -l{library}"))
+                    }
+                    _ => Err(Rich::custom(
+                        *span,
+                        "invalid link attribute, expected #[link(name = \"...\")] with the name \
+                         of a library, like m for libm",
+                    )),
+                },
+            )
+            .collect::<Result<Vec<_>, Rich<'src, Token, SS>>>()?;
+        lines.push(self.c_declaration());
+        Ok(lines)
+    }
+
+    // the first param or return type the wrapper can't convert
+    fn unsupported_type(&self) -> Option<&Spanned<TypeExpr>> {
+        let params = self.params.iter().map(|(_, type_expr)| type_expr);
+        match self.abi {
+            Abi::C => params
+                .chain(self.return_type.as_ref())
+                .find(|(type_expr, _)| !is_c_type(type_expr)),
+            Abi::Go(_) => {
+                let param = params
+                    .enumerate()
+                    .map(|(i, type_expr)| match &type_expr.0 {
+                        TypeExpr::Array(element_type)
+                            if self.variadic && i + 1 == self.params.len() =>
+                        {
+                            element_type.as_ref()
+                        }
+                        _ => type_expr,
+                    })
+                    .find(|(type_expr, _)| !is_go_arg_type(type_expr));
+                if param.is_some() {
+                    return param;
+                }
+
+                let return_type = self.return_type.as_ref()?;
+                let results = match (go_error_result(&return_type.0), &return_type.0) {
+                    (Some(value_type), _) => vec![value_type],
+                    (None, TypeExpr::Tuple(fields)) if !fields.is_empty() => {
+                        fields.iter().collect()
+                    }
+                    _ => vec![return_type],
+                };
+                results
+                    .into_iter()
+                    .find(|(type_expr, _)| !is_go_result_type(type_expr))
+            }
+        }
+    }
+
+    pub fn needs_unsafe(&self) -> bool {
+        self.abi == Abi::C
+            && self
+                .params
+                .iter()
+                .any(|(_, type_expr)| type_expr.0 == TypeExpr::String)
+    }
+
+    pub fn to_function_definition(&self) -> FunctionDefintion {
+        match &self.abi {
+            Abi::C => self.c_function_definition(),
+            Abi::Go(package) => self.go_function_definition(package),
+        }
+    }
+
+    // a duck function that converts its params, calls the c function through cgo and converts
+    // the result back, the same way std wraps go functions
+    fn c_function_definition(&self) -> FunctionDefintion {
+        let mut go_code = Vec::new();
+        let mut args = Vec::new();
+
+        for (name, type_expr) in &self.params {
+            match &type_expr.0 {
+                TypeExpr::String => {
+                    go_code.push(format!("c_{name} := C.CString({name}.as_dgo_string())"));
+                    go_code.push(format!("defer C.free(unsafe.Pointer(c_{name}))"));
+                    args.push(format!("c_{name}"));
+                }
+                TypeExpr::Int => args.push(format!("C.longlong({name}.as_dgo_int())")),
+                TypeExpr::Float => args.push(format!("C.double({name}.as_dgo_float32())")),
+                TypeExpr::Bool => args.push(format!("C._Bool({name}.as_dgo_bool())")),
+                _ => unreachable!("the parser only accepts c types"),
+            }
+        }
+
+        let call = format!("C.{}({})", self.name, args.join(", "));
+        let span = self.span;
+
+        let mut body = Vec::new();
+        match &self.return_type {
+            Some((return_type, _)) => {
+                let (default, result) = match return_type {
+                    TypeExpr::Int => (
+                        ValueExpr::Int(0),
+                        format!("ConcDuckInt {{ value: int({call}) }}"),
+                    ),
+                    TypeExpr::Float => (
+                        ValueExpr::Float(0.0),
+                        format!("ConcDuckFloat {{ value: float32({call}) }}"),
+                    ),
+                    TypeExpr::Bool => (
+                        ValueExpr::Bool(false),
+                        format!("ConcDuckBool {{ value: bool({call}) }}"),
+                    ),
+                    _ => (
+                        ValueExpr::String(String::new(), true),
+                        format!("ConcDuckString {{ value: C.GoString({call}) }}"),
+                    ),
+                };
+
+                body.push((
+                    ValueExpr::VarDecl(Box::new((
+                        Declaration {
+                            name: "extern_result".to_string(),
+                            type_expr: self.return_type.clone(),
+                            initializer: (default, span),
+                        },
+                        span,
+                    ))),
+                    span,
+                ));
+                go_code.push(format!("extern_result = {result}"));
+                body.push((ValueExpr::InlineGo(go_code.join("\n")), span));
+                body.push((
+                    ValueExpr::Return(Some(Box::new((
+                        ValueExpr::RawVariable(false, vec!["extern_result".to_string()]),
+                        span,
+                    )))),
+                    span,
+                ));
+            }
+            None => {
+                go_code.push(call);
+                body.push((ValueExpr::InlineGo(go_code.join("\n")), span));
+            }
+        }
+
+        FunctionDefintion {
+            name: self.name.clone(),
+            return_type: self.return_type.clone(),
+            params: Some(self.params.clone()),
+            value_expr: (ValueExpr::Block(body), span),
+            generics: None,
+            doc_comments: Vec::new(),
+            attributes: Vec::new(),
+            param_defaults: Vec::new(),
+            variadic: false,
+        }
+    }
+
+    // a go function that returns (T, error) is wrapped as returning Result<T, go error>
+    fn go_function_definition(&self, package: &str) -> FunctionDefintion {
+        let args = self
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, (name, type_expr))| {
+                if self.variadic && i + 1 == self.params.len() {
+                    go_variadic_arg(name, type_expr)
+                } else {
+                    go_arg(name, type_expr)
+                }
+            })
+            .collect::<Vec<_>>();
+        let call = format!("{package}.{}({})", self.name, args.join(", "));
+        let span = self.span;
+
+        let var = |name: &str| (ValueExpr::RawVariable(false, vec![name.to_string()]), span);
+        let var_decl = |name: &str, type_expr: Spanned<TypeExpr>, initializer: ValueExpr| {
+            let declaration = Declaration {
+                name: name.to_string(),
+                type_expr: Some(type_expr),
+                initializer: (initializer, span),
+            };
+            (ValueExpr::VarDecl(Box::new((declaration, span))), span)
+        };
+
+        let mut body = Vec::new();
+        let mut return_type = self.return_type.clone();
+        let error_result = self
+            .return_type
+            .as_ref()
+            .and_then(|(return_type_expr, _)| go_error_result(return_type_expr));
+        match (&self.return_type, error_result) {
+            (Some(_), Some(value_type)) => {
+                let (default, result) = go_result("extern_go_value", value_type);
+                body.push(var_decl("extern_value", value_type.clone(), default));
+                body.push(var_decl(
+                    "extern_error",
+                    (
+                        TypeExpr::Or(vec![
+                            (TypeExpr::Go("error".to_string()), span),
+                            (TypeExpr::Tag("none".to_string()), span),
+                        ]),
+                        span,
+                    ),
+                    ValueExpr::Tag("none".to_string()),
+                ));
+                body.push((
+                    ValueExpr::InlineGo(format!(
+                        "extern_go_value, extern_go_error := {call}\n\
+                         if extern_go_error != nil {{\n\
+                         extern_error = extern_go_error\n\
+                         }} else {{\n\
+                         extern_value = {result}\n\
+                         }}"
+                    )),
+                    span,
+                ));
+
+                let std_error =
+                    |name: &str| vec!["std".to_string(), "error".to_string(), name.to_string()];
+                let go_result_call = ValueExpr::FunctionCall {
+                    target: (ValueExpr::RawVariable(true, std_error("go_result")), span).into(),
+                    params: vec![var("extern_value"), var("extern_error")],
+                    type_params: Some(vec![value_type.clone()]),
+                };
+                body.push((
+                    ValueExpr::Return(Some(Box::new((go_result_call, span)))),
+                    span,
+                ));
+
+                // the Result the user wrote might not be imported
+                if let Some((TypeExpr::RawTypeName(is_global, path, _), _)) = &mut return_type {
+                    *is_global = true;
+                    *path = std_error("Result");
+                }
+            }
+            // (A, B) is a go multiple return as well
+            (Some((TypeExpr::Tuple(fields), _)), None) if !fields.is_empty() => {
+                let mut go_values = Vec::new();
+                let mut assignments = Vec::new();
+                let mut results = Vec::new();
+                for (i, field) in fields.iter().enumerate() {
+                    let go_value = format!("extern_go_value_{i}");
+                    let (default, result) = go_result(&go_value, field);
+                    let extern_result = format!("extern_result_{i}");
+                    body.push(var_decl(&extern_result, field.clone(), default));
+                    assignments.push(format!("{extern_result} = {result}"));
+                    results.push(var(&extern_result));
+                    go_values.push(go_value);
+                }
+                body.push((
+                    ValueExpr::InlineGo(format!(
+                        "{} := {call}\n{}",
+                        go_values.join(", "),
+                        assignments.join("\n")
+                    )),
+                    span,
+                ));
+                body.push((
+                    ValueExpr::Return(Some(Box::new((ValueExpr::Tuple(results), span)))),
+                    span,
+                ));
+            }
+            (Some(return_type_expr), None) => {
+                let (default, result) = go_result("extern_go_value", return_type_expr);
+                body.push(var_decl("extern_result", return_type_expr.clone(), default));
+                body.push((
+                    ValueExpr::InlineGo(format!(
+                        "extern_go_value := {call}\nextern_result = {result}"
+                    )),
+                    span,
+                ));
+                body.push((
+                    ValueExpr::Return(Some(Box::new(var("extern_result")))),
+                    span,
+                ));
+            }
+            (None, _) => body.push((ValueExpr::InlineGo(call), span)),
+        }
+
+        FunctionDefintion {
+            name: self.name.clone(),
+            return_type,
+            params: Some(self.params.clone()),
+            value_expr: (ValueExpr::Block(body), span),
+            generics: None,
+            doc_comments: Vec::new(),
+            attributes: Vec::new(),
+            param_defaults: Vec::new(),
+            variadic: self.variadic,
+        }
+    }
+}
+
+pub fn extern_function_parser<'src, I>()
+-> impl Parser<'src, I, ExternFunction, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+{
+    let param_type_parser = just(Token::DotDotDot)
+        .or_not()
+        .then(type_expression_parser())
+        .map_with(|(dots, type_expr), e| match dots {
+            Some(_) => ((TypeExpr::Array(type_expr.into()), e.span()), true),
+            None => (type_expr, false),
+        });
+
+    let param_parser = select_ref! { Token::Ident(identifier) => identifier.to_string() }
+        .then_ignore(just(Token::ControlChar(':')))
+        .then(param_type_parser)
+        .map(|(identifier, (type_expr, is_variadic))| {
+            ((identifier, type_expr) as Param, is_variadic)
+        });
+
+    let identifier = select_ref! { Token::Ident(identifier) => identifier.to_string() };
+
+    // extern is no keyword, just like test
+    select_ref! { Token::Ident(ident) if ident == "extern" => () }
+        .ignore_then(select_ref! { Token::ConstString(abi) => abi.to_string() })
+        .then_ignore(just(Token::Function))
+        .then(
+            identifier
+                .clone()
+                .then(just(Token::ControlChar('.')).ignore_then(identifier).or_not()),
+        )
+        .try_map(|(abi, (first, second)), span| match (abi.as_str(), second) {
+            ("C", None) => Ok((Abi::C, first)),
+            ("Go", Some(name)) => Ok((Abi::Go(first), name)),
+            ("C", Some(_)) => Err(Rich::custom(span, "C functions aren't in a package")),
+            ("Go", None) => Err(Rich::custom(
+                span,
+                "go functions are declared with their package, like extern \"Go\" fn strconv.Itoa",
+            )),
+            _ => Err(Rich::custom(
+                span,
+                format!("unsupported abi \"{abi}\", only \"C\" and \"Go\" are supported"),
+            )),
+        })
+        .then(
+            param_parser
+                .separated_by(just(Token::ControlChar(',')))
+                .allow_trailing()
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')'))),
+        )
+        .then(
+            just(Token::ThinArrow)
+                .ignore_then(type_expression_parser())
+                .or_not(),
+        )
+        .then_ignore(just(Token::ControlChar(';')))
+        .try_map(|(((abi, name), params), return_type), span| {
+            let variadic = params.last().is_some_and(|(_, is_variadic)| *is_variadic);
+            if params[..params.len().saturating_sub(1)]
+                .iter()
+                .any(|(_, is_variadic)| *is_variadic)
+            {
+                return Err(Rich::custom(span, "only the last param can be variadic"));
+            }
+            if variadic && abi == Abi::C {
+                return Err(Rich::custom(span, "variadic C functions aren't supported"));
+            }
+            let extern_function = ExternFunction {
+                abi,
+                name,
+                params: params.into_iter().map(|(param, _)| param).collect(),
+                variadic,
+                return_type,
+                span,
+                attributes: Vec::new(),
+            };
+            if let Some((type_expr, type_span)) = extern_function.unsupported_type() {
+                return Err(Rich::custom(
+                    *type_span,
+                    match extern_function.abi {
+                        Abi::C => format!(
+                            "{type_expr} can't be passed to or returned from a C function, only \
+                             Int, Float, Bool and String are supported"
+                        ),
+                        Abi::Go(_) => format!(
+                            "{type_expr} can't be passed to or returned from a Go function, only \
+                             Int, Float, Bool, String, go types, tuples and Result<T, go error> \
+                             are supported"
+                        ),
+                    },
+                ));
+            }
+            Ok(extern_function)
+        })
+}
+
+// the name goes into a compiler flag, so it can't smuggle in other flags or preamble lines
+fn is_library_name(library: &str) -> bool {
+    !library.is_empty()
+        && !library.starts_with('-')
+        && library
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+'))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_map::FileId, value_parser::empty_range,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_extern_function_parser() {
+        let test_cases = vec![
+            ("extern \"C\" fn abort();", "void abort();"),
+            (
+                "extern \"C\" fn abs(x: Int) -> Int;",
+                "long long abs(long long);",
+            ),
+            (
+                "extern \"C\" fn pow(x: Float, y: Float) -> Float;",
+                "double pow(double, double);",
+            ),
+            (
+                "extern \"C\" fn puts(s: String,) -> Int;",
+                "long long puts(const char*);",
+            ),
+            (
+                "extern \"C\" fn getenv(s: String) -> String;",
+                "const char* getenv(const char*);",
+            ),
+        ];
+
+        for (src, expected) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let extern_function = extern_function_parser()
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            assert_eq!(extern_function.c_declaration(), expected, "{src}");
+        }
+
+        for invalid in [
+            "extern \"C\" fn abort()",
+            "extern \"Rust\" fn abort();",
+            "extern \"C\" fn stdlib.abort();",
+            "extern \"Go\" fn Atoi(s: String) -> Int;",
+            "extern fn abort();",
+            "extern \"C\" fn abort() {}",
+            "extern \"C\" fn printf(values: ...String);",
+            "extern \"Go\" fn path.Join(parts: ...String, last: String) -> String;",
+            "extern \"C\" fn first(values: [Int]) -> Int;",
+            "extern \"C\" fn handle() -> go error;",
+            "extern \"Go\" fn strings.Fields(s: String) -> [String];",
+            "extern \"Go\" fn fmt.Println(values: ...[Int]);",
+            "extern \"Go\" fn os.Open(name: String) -> Result<go os.File, go error>;",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                extern_function_parser()
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_link_attribute() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("extern \"C\" fn sqrt(x: Float) -> Float;")
+            .into_result()
+            .unwrap();
+        let mut extern_function = extern_function_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+        extern_function.attributes.push((
+            Attribute {
+                name: "link".to_string(),
+                args: vec![],
+                key_values: vec![("name".to_string(), ("m -L/tmp".to_string(), empty_range()))],
+            },
+            empty_range(),
+        ));
+
+        let err = extern_function.cgo_preamble().unwrap_err();
+        assert!(err.reason().to_string().contains("invalid link attribute"));
+    }
+
+    #[test]
+    fn test_extern_function_wrapper() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("extern \"C\" fn strlen(s: String) -> Int;")
+            .into_result()
+            .unwrap();
+        let extern_function = extern_function_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+
+        assert!(extern_function.needs_unsafe());
+
+        let function_definition = extern_function.to_function_definition();
+        assert_eq!(function_definition.name, "strlen");
+
+        let ValueExpr::Block(body) = &function_definition.value_expr.0 else {
+            panic!("extern wrapper has to be a block")
+        };
+        let ValueExpr::InlineGo(go_code) = &body[1].0 else {
+            panic!("expected the cgo call")
+        };
+        assert_eq!(
+            go_code,
+            "c_s := C.CString(s.as_dgo_string())\n\
+             defer C.free(unsafe.Pointer(c_s))\n\
+             extern_result = ConcDuckInt { value: int(C.strlen(c_s)) }"
+        );
+    }
+
+    #[test]
+    fn test_go_extern_multiple_returns() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("extern \"Go\" fn math.Frexp(f: Float) -> (Float, Int);")
+            .into_result()
+            .unwrap();
+        let function_definition = extern_function_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap()
+            .to_function_definition();
+
+        let ValueExpr::Block(body) = &function_definition.value_expr.0 else {
+            panic!("extern wrapper has to be a block")
+        };
+        let ValueExpr::InlineGo(go_code) = &body[2].0 else {
+            panic!("expected the go call")
+        };
+        assert_eq!(
+            go_code,
+            "extern_go_value_0, extern_go_value_1 := math.Frexp(float64(f.as_dgo_float32()))\n\
+             extern_result_0 = ConcDuckFloat { value: float32(extern_go_value_0) }\n\
+             extern_result_1 = ConcDuckInt { value: int(extern_go_value_1) }"
+        );
+        assert!(matches!(
+            &body[3].0,
+            ValueExpr::Return(Some(tuple))
+                if matches!(&tuple.0, ValueExpr::Tuple(values) if values.len() == 2)
+        ));
+    }
+
+    #[test]
+    fn test_go_extern_function_wrapper() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("extern \"Go\" fn strconv.Atoi(s: String) -> Result<Int, go error>;")
+            .into_result()
+            .unwrap();
+        let extern_function = extern_function_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+
+        assert_eq!(extern_function.abi, Abi::Go("strconv".to_string()));
+        assert!(!extern_function.needs_unsafe());
+
+        let function_definition = extern_function.to_function_definition();
+        assert_eq!(function_definition.name, "Atoi");
+        assert!(matches!(
+            &function_definition.return_type,
+            Some((TypeExpr::RawTypeName(true, path, Some(params)), _))
+                if *path == ["std", "error", "Result"] && params.len() == 2
+        ));
+
+        let ValueExpr::Block(body) = &function_definition.value_expr.0 else {
+            panic!("extern wrapper has to be a block")
+        };
+        let ValueExpr::InlineGo(go_code) = &body[2].0 else {
+            panic!("expected the go call")
+        };
+        assert_eq!(
+            go_code,
+            "extern_go_value, extern_go_error := strconv.Atoi(s.as_dgo_string())\n\
+             if extern_go_error != nil {\n\
+             extern_error = extern_go_error\n\
+             } else {\n\
+             extern_value = ConcDuckInt { value: int(extern_go_value) }\n\
+             }"
+        );
+        assert!(matches!(
+            &body[3].0,
+            ValueExpr::Return(Some(call))
+                if matches!(&call.0, ValueExpr::FunctionCall { target, .. }
+                    if target.0 == ValueExpr::RawVariable(
+                        true,
+                        vec!["std".into(), "error".into(), "go_result".into()],
+                    ))
+        ));
+    }
+    #[test]
+    fn test_go_extern_variadic() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("extern \"Go\" fn path.Join(parts: ...String) -> String;")
+            .into_result()
+            .unwrap();
+        let extern_function = extern_function_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+        assert!(extern_function.variadic);
+
+        let function_definition = extern_function.to_function_definition();
+        assert!(function_definition.variadic);
+
+        let ValueExpr::Block(body) = &function_definition.value_expr.0 else {
+            panic!("extern wrapper has to be a block")
+        };
+        let ValueExpr::InlineGo(go_code) = &body[1].0 else {
+            panic!("expected the go call")
+        };
+        assert_eq!(
+            go_code,
+            "extern_go_value := path.Join(func() []string {\n\
+             extern_values := make([]string, 0, len(parts))\n\
+             for _, extern_value := range parts {\n\
+             extern_values = append(extern_values, extern_value.as_dgo_string())\n\
+             }\n\
+             return extern_values\n\
+             }()...)\n\
+             extern_result = ConcDuckString { value: extern_go_value }"
+        );
+    }
+}
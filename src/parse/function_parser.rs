@@ -1,21 +1,46 @@
-use chumsky::{input::BorrowInput, prelude::*};
+use chumsky::{
+    input::{BorrowInput, SliceInput},
+    prelude::*,
+};
 
 use crate::{
     parse::{
         SS, Spanned,
+        attribute_parser::Attribute,
+        doc_comment_parser::doc_comments_parser,
         generics_parser::{Generic, generics_parser},
     },
     semantics::type_resolve::FunHeader,
 };
 
 use super::{
-    lexer::Token,
+    lexer::{Token, go_ident},
     type_parser::{TypeExpr, type_expression_parser},
     value_parser::{ValueExpr, value_expr_parser},
 };
 
 pub type Param = (String, Spanned<TypeExpr>);
 
+// extension functions (fn (s: String) shout() -> String {}) are plain functions with the
+// receiver as first parameter, the method name and receiver type are encoded in the name
+pub const EXTENSION_SEP: &str = "__ext__";
+
+// the receiver type is escaped like an identifier, its punctuation is spelled out so that two
+// receivers never end up with the same name
+pub fn extension_fn_name(method: &str, receiver: &TypeExpr) -> String {
+    format!("{method}{EXTENSION_SEP}{}", go_ident(&receiver.to_string()))
+}
+
+pub fn extension_method_name(fn_name: &str) -> Option<&str> {
+    fn_name.split_once(EXTENSION_SEP).map(|(method, _)| method)
+}
+
+// extensions can only be called where they're defined or imported. the mangler marks such a
+// x.shout() with the mangled path of shout, so that only the extensions of that path are tried
+pub fn extension_call_marker(method_path: &str) -> String {
+    format!("{method_path}{EXTENSION_SEP}")
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionDefintion {
@@ -24,6 +49,12 @@ pub struct FunctionDefintion {
     pub params: Option<Vec<Param>>,
     pub value_expr: Spanned<ValueExpr>,
     pub generics: Option<Vec<Spanned<Generic>>>,
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<Spanned<Attribute>>,
+    // defaults of the trailing parameters, evaluated at the call site
+    pub param_defaults: Vec<Spanned<ValueExpr>>,
+    // the last parameter takes any number of values, they're passed as one array
+    pub variadic: bool,
 }
 
 impl FunctionDefintion {
@@ -66,6 +97,10 @@ impl Default for FunctionDefintion {
             params: Some(Default::default()),
             value_expr: ValueExpr::Block(vec![]).into_empty_span(),
             generics: None,
+            doc_comments: Vec::new(),
+            attributes: Vec::new(),
+            param_defaults: Vec::new(),
+            variadic: false,
         }
     }
 }
@@ -81,25 +116,80 @@ pub fn function_definition_parser<'src, I, M>(
     make_input: M,
 ) -> impl Parser<'src, I, FunctionDefintion, extra::Err<Rich<'src, Token, SS>>> + Clone
 where
-    I: BorrowInput<'src, Token = Token, Span = SS>,
+    I: BorrowInput<'src, Token = Token, Span = SS>
+        + SliceInput<'src, Slice = &'src [Spanned<Token>]>,
     M: Fn(SS, &'src [Spanned<Token>]) -> I + Clone + 'static,
 {
+    // xs: ...Int takes any number of Ints, inside of the function xs is an Int[]
+    let param_type_parser = just(Token::DotDotDot)
+        .or_not()
+        .then(type_expression_parser())
+        .map_with(|(dots, type_expr), e| match dots {
+            Some(_) => ((TypeExpr::Array(type_expr.into()), e.span()), true),
+            None => (type_expr, false),
+        });
+
     let param_parser = select_ref! { Token::Ident(identifier) => identifier.to_string() }
         .then_ignore(just(Token::ControlChar(':')))
-        .then(type_expression_parser())
-        .map(|(identifier, type_expr)| (identifier, type_expr) as Param);
+        .then(param_type_parser)
+        .map(|(identifier, (type_expr, is_variadic))| {
+            ((identifier, type_expr) as Param, is_variadic)
+        })
+        .then(
+            just(Token::ControlChar('='))
+                .ignore_then(value_expr_parser(make_input.clone()))
+                .or_not(),
+        );
 
     let params_parser = param_parser
         .separated_by(just(Token::ControlChar(',')))
         .allow_trailing()
-        .collect::<Vec<Param>>()
+        .collect::<Vec<_>>()
+        .try_map(|params, span| {
+            let variadic = params
+                .last()
+                .is_some_and(|((_, is_variadic), _)| *is_variadic);
+            let mut param_defaults = Vec::new();
+            for (i, ((param, is_variadic), default)) in params.iter().enumerate() {
+                if *is_variadic && (i + 1 != params.len() || default.is_some()) {
+                    return Err(Rich::custom(
+                        span,
+                        format!(
+                            "variadic parameter {} has to be the last one and can't have a default",
+                            param.0
+                        ),
+                    ));
+                }
+                match default {
+                    Some(default) => param_defaults.push(default.clone()),
+                    None if !param_defaults.is_empty() => {
+                        return Err(Rich::custom(
+                            span,
+                            format!("parameter {} needs a default value as well", param.0),
+                        ));
+                    }
+                    None => {}
+                }
+            }
+            let params = params
+                .into_iter()
+                .map(|((param, _), _)| param)
+                .collect::<Vec<Param>>();
+            Ok((params, param_defaults, variadic))
+        })
         .or_not();
 
     let return_type_parser = just(Token::ThinArrow).ignore_then(type_expression_parser());
 
-    just(Token::Sus)
-        .or_not()
+    let receiver_parser = select_ref! { Token::Ident(identifier) => identifier.to_string() }
+        .then_ignore(just(Token::ControlChar(':')))
+        .then(type_expression_parser())
+        .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')')));
+
+    doc_comments_parser()
+        .then(just(Token::Sus).or_not())
         .then_ignore(just(Token::Function))
+        .then(receiver_parser.or_not())
         .then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
         .then(generics_parser().or_not())
         .then_ignore(just(Token::ControlChar('(')))
@@ -107,9 +197,39 @@ where
         .then_ignore(just(Token::ControlChar(')')))
         .then(return_type_parser.or_not())
         .then(value_expr_parser(make_input))
-        .map(
-            |(((((has_sus, identifier), generics), params), return_type), mut value_expr)| {
+        .validate(
+            |(
+                (
+                    (((((doc_comments, has_sus), receiver), identifier), generics), params),
+                    return_type,
+                ),
+                mut value_expr,
+            ),
+             e,
+             emitter| {
                 let is_sus = has_sus.is_some();
+                let (mut params, param_defaults, variadic) = match params {
+                    Some((params, param_defaults, variadic)) => {
+                        (Some(params), param_defaults, variadic)
+                    }
+                    None => (None, Vec::new(), false),
+                };
+
+                let identifier = match receiver {
+                    Some(receiver) => {
+                        if let Some(generics) = &generics {
+                            let span = generics.first().map_or(e.span(), |(_, span)| *span);
+                            emitter.emit(Rich::custom(
+                                span,
+                                format!("the extension function {identifier} can't be generic"),
+                            ));
+                        }
+                        let name = extension_fn_name(&identifier, &receiver.1.0);
+                        params.get_or_insert_with(Vec::new).insert(0, receiver);
+                        name
+                    }
+                    None => identifier,
+                };
 
                 if is_sus && return_type.is_some() {
                     panic!("sus function is not allowed to return something");
@@ -153,6 +273,10 @@ where
                     params,
                     value_expr,
                     generics,
+                    doc_comments,
+                    attributes: Vec::new(),
+                    param_defaults,
+                    variadic,
                 }
             },
         )
@@ -160,7 +284,9 @@ where
 
 #[cfg(test)]
 pub mod tests {
-    use crate::parse::{lexer::lex_parser, make_input, value_parser::empty_range};
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_map::FileId, value_parser::empty_range,
+    };
 
     use super::*;
 
@@ -177,11 +303,17 @@ pub mod tests {
             "fn x<TYPE>() -> {x: String} { 5; }",
             "fn x<TYPE, TYPE2>() -> {x: String} { 5; }",
             "fn x<TYPE, TYPE2, TYPE3>() -> {x: String} { 5; }",
+            "fn (s: String) shout() -> String { s }",
+            "fn (d: { name: String }) greet(greeting: String) {}",
+            "fn x(name: String, greeting: String = \"hello\") {}",
+            "fn x(a: Int = 1, b: Int = a,) {}",
+            "fn sum(xs: ...Int) -> Int {}",
+            "fn join(separator: String, parts: ...String) -> String {}",
         ];
 
         for valid_function_definition in valid_function_definitions {
             println!("lexing {valid_function_definition}");
-            let lexer_parse_result = lex_parser("test", "").parse(valid_function_definition);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(valid_function_definition);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -196,11 +328,16 @@ pub mod tests {
             assert_eq!(typedef_parse_result.has_output(), true);
         }
 
-        let invalid_function_definitions = vec![];
+        let invalid_function_definitions = vec![
+            "fn x(a: Int = 1, b: Int) {}",
+            "fn x(a: Int =) {}",
+            "fn x(xs: ...Int, y: Int) {}",
+            "fn x(xs: ...Int = [1]) {}",
+        ];
 
         for invalid_function_definition in invalid_function_definitions {
             println!("lexing {invalid_function_definition}");
-            let lexer_parse_result = lex_parser("test", "").parse(invalid_function_definition);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(invalid_function_definition);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -228,10 +365,15 @@ pub mod tests {
                     generics: Some(vec![(
                         Generic {
                             name: "TYPENAME".to_string(),
+                            constraint: None,
                         },
                         empty_range(),
                     )]),
                     value_expr: ValueExpr::Block(vec![]).into_empty_span(),
+                    doc_comments: Vec::new(),
+                    attributes: Vec::new(),
+                    param_defaults: Vec::new(),
+                    variadic: false,
                 },
             ),
             (
@@ -244,17 +386,23 @@ pub mod tests {
                         (
                             Generic {
                                 name: "TYPENAME".to_string(),
+                                constraint: None,
                             },
                             empty_range(),
                         ),
                         (
                             Generic {
                                 name: "TYPENAME2".to_string(),
+                                constraint: None,
                             },
                             empty_range(),
                         ),
                     ]),
                     value_expr: ValueExpr::Block(vec![]).into_empty_span(),
+                    doc_comments: Vec::new(),
+                    attributes: Vec::new(),
+                    param_defaults: Vec::new(),
+                    variadic: false,
                 },
             ),
             (
@@ -267,29 +415,39 @@ pub mod tests {
                         (
                             Generic {
                                 name: "TYPENAME".to_string(),
+                                constraint: None,
                             },
                             empty_range(),
                         ),
                         (
                             Generic {
                                 name: "TYPENAME2".to_string(),
+                                constraint: None,
                             },
                             empty_range(),
                         ),
                         (
                             Generic {
                                 name: "TYPENAME3".to_string(),
+                                constraint: None,
                             },
                             empty_range(),
                         ),
                     ]),
                     value_expr: ValueExpr::Block(vec![]).into_empty_span(),
+                    doc_comments: Vec::new(),
+                    attributes: Vec::new(),
+                    param_defaults: Vec::new(),
+                    variadic: false,
                 },
             ),
         ];
 
         for (i, (src, expected_fns)) in test_cases.into_iter().enumerate() {
-            let lex_result = lex_parser("test", "").parse(src).into_result().expect(&src);
+            let lex_result = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(&src);
             let parse_result = function_definition_parser(make_input)
                 .parse(make_input(empty_range(), &lex_result));
 
@@ -320,4 +478,137 @@ pub mod tests {
             assert_eq!(output, expected_fns, "{i}: {}", src);
         }
     }
+
+    #[test]
+    fn test_extension_functions() {
+        let test_cases = vec![
+            ("fn (s: String) shout() -> String { s }", "shout", vec!["s"]),
+            (
+                "fn (s: String) repeat(n: Int) -> String { s }",
+                "repeat",
+                vec!["s", "n"],
+            ),
+            ("fn (d: { name: String }) greet() {}", "greet", vec!["d"]),
+        ];
+
+        for (src, expected_method, expected_params) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let fn_def = function_definition_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            assert_eq!(
+                extension_method_name(&fn_def.name),
+                Some(expected_method),
+                "{src}"
+            );
+            assert_eq!(
+                fn_def
+                    .params
+                    .unwrap()
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>(),
+                expected_params,
+                "{src}"
+            );
+        }
+
+        assert_eq!(extension_method_name("shout"), None);
+
+        // Int[] and a type called Int__ would both be Int__ if the punctuation was replaced
+        let array = TypeExpr::Array(TypeExpr::Int.into_empty_span().into());
+        let named = TypeExpr::RawTypeName(false, vec!["Int__".to_string()], None);
+        assert_ne!(
+            extension_fn_name("f", &array),
+            extension_fn_name("f", &named)
+        );
+        assert_eq!(
+            extension_fn_name("f", &array),
+            format!("f{EXTENSION_SEP}Int\u{2E2F}5b\u{2E2F}\u{2E2F}5d\u{2E2F}")
+        );
+
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("fn (s: String) shout<T>() {}")
+            .into_result()
+            .unwrap();
+        let result =
+            function_definition_parser(make_input).parse(make_input(empty_range(), &tokens));
+        assert!(result.has_errors());
+    }
+    #[test]
+    fn test_param_defaults() {
+        let test_cases = vec![
+            ("fn x(a: Int) {}", vec![]),
+            ("fn x(a: Int, b: Int = 2) {}", vec![ValueExpr::Int(2)]),
+            (
+                "fn x(a: Int = 1, b: Bool = true) {}",
+                vec![ValueExpr::Int(1), ValueExpr::Bool(true)],
+            ),
+            (
+                "fn (s: String) pad(n: Int = 4) -> String { s }",
+                vec![ValueExpr::Int(4)],
+            ),
+        ];
+
+        for (src, expected_defaults) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let fn_def = function_definition_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            assert_eq!(
+                fn_def
+                    .param_defaults
+                    .into_iter()
+                    .map(|(default, _)| default)
+                    .collect::<Vec<_>>(),
+                expected_defaults,
+                "{src}"
+            );
+        }
+    }
+    #[test]
+    fn test_variadic_params() {
+        let test_cases = vec![
+            ("fn x(xs: Int[]) {}", false),
+            ("fn sum(xs: ...Int) -> Int {}", true),
+            (
+                "fn join(separator: String, parts: ...String,) -> String {}",
+                true,
+            ),
+            (
+                "fn (s: String) append(parts: ...String) -> String { s }",
+                true,
+            ),
+        ];
+
+        for (src, expected_variadic) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let fn_def = function_definition_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            assert_eq!(fn_def.variadic, expected_variadic, "{src}");
+            assert!(
+                matches!(
+                    fn_def.params.unwrap().last(),
+                    Some((_, (TypeExpr::Array(_), _)))
+                ),
+                "{src}"
+            );
+        }
+    }
 }
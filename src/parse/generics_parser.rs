@@ -1,10 +1,16 @@
 use chumsky::{input::BorrowInput, prelude::*};
 
-use crate::parse::{SS, Spanned, lexer::Token};
+use crate::parse::{
+    SS, Spanned,
+    lexer::Token,
+    type_parser::{TypeExpr, type_expression_parser},
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Generic {
     pub name: String,
+    // <T: duck { name: String }> only accepts type params compatible with the constraint
+    pub constraint: Option<Spanned<TypeExpr>>,
 }
 
 pub fn generics_parser<'src, I>()
@@ -12,13 +18,16 @@ pub fn generics_parser<'src, I>()
 where
     I: BorrowInput<'src, Token = Token, Span = SS>,
 {
-    // '<' <identifier> '>'
+    // '<' <identifier> (':' <type>)? '>'
     just(Token::ControlChar('<'))
         .ignore_then(
-            select_ref! { Token::Ident(identifier) => identifier.clone() }
-                .map(|identifier| Generic {
-                    name: identifier.clone(),
-                })
+            select_ref! { Token::Ident(identifier) => identifier.to_string() }
+                .then(
+                    just(Token::ControlChar(':'))
+                        .ignore_then(type_expression_parser())
+                        .or_not(),
+                )
+                .map(|(name, constraint)| Generic { name, constraint })
                 .map_with(|generic, ctx| (generic, ctx.span()))
                 .separated_by(just(Token::ControlChar(',')))
                 .allow_trailing()
@@ -32,7 +41,8 @@ where
 pub mod tests {
     use super::*;
     use crate::parse::{
-        generics_parser::generics_parser, lexer::lex_parser, make_input, value_parser::empty_range,
+        Field, generics_parser::generics_parser, lexer::lex_parser, make_input, source_map::FileId,
+        type_parser::Duck, value_parser::empty_range,
     };
 
     #[test]
@@ -48,11 +58,14 @@ pub mod tests {
             "<WITH_SPACE_BETWEEN, AND_TRAILING_COMMA_WITH_EVEN_MORE_SPACE,       >",
             "<WITH_SPACE_BETWEEN,
             AND_A_LINE_BREAK_AND_EVEN_MORE_SPACE,       >",
+            "<T: duck { name: String }>",
+            "<T: { x: Int }, U>",
+            "<T, U: String | Int,>",
         ];
 
         for valid_generic_definition in valid_generic_definitions {
             println!("lexing {valid_generic_definition}");
-            let lexer_parse_result = lex_parser("test", "").parse(valid_generic_definition);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(valid_generic_definition);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -66,11 +79,11 @@ pub mod tests {
             assert_eq!(typedef_parse_result.has_output(), true);
         }
 
-        let invalid_generic_definitions = vec!["<>", "<{}>", "<*()>"];
+        let invalid_generic_definitions = vec!["<>", "<{}>", "<*()>", "<T:>", "<T: duck {>"];
 
         for invalid_generic_definition in invalid_generic_definitions {
             println!("lexing {invalid_generic_definition}");
-            let lexer_parse_result = lex_parser("test", "").parse(invalid_generic_definition);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(invalid_generic_definition);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -86,6 +99,61 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_generic_constraints() {
+        let test_cases = vec![
+            ("<T>", vec![("T", None)]),
+            (
+                "<T: duck { name: String }, U>",
+                vec![
+                    (
+                        "T",
+                        Some(TypeExpr::Duck(Duck {
+                            fields: vec![Field::new(
+                                "name".to_string(),
+                                TypeExpr::String.into_empty_span(),
+                            )],
+                        })),
+                    ),
+                    ("U", None),
+                ],
+            ),
+            ("<T: Int>", vec![("T", Some(TypeExpr::Int))]),
+        ];
+
+        for (src, expected_generics) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let generics = generics_parser()
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            let actual_generics = generics
+                .into_iter()
+                .map(|(generic, _)| {
+                    let constraint = generic.constraint.map(|(mut constraint, _)| {
+                        if let TypeExpr::Duck(duck) = &mut constraint {
+                            for field in &mut duck.fields {
+                                field.type_expr.1 = empty_range();
+                            }
+                        }
+                        constraint
+                    });
+                    (generic.name, constraint)
+                })
+                .collect::<Vec<_>>();
+            let expected_generics = expected_generics
+                .into_iter()
+                .map(|(name, constraint)| (name.to_string(), constraint))
+                .collect::<Vec<_>>();
+
+            assert_eq!(actual_generics, expected_generics, "{src}");
+        }
+    }
+
     #[test]
     fn test_detailed_generics_parser() {
         let test_cases = vec![
@@ -93,6 +161,7 @@ pub mod tests {
                 "<TYPENAME>",
                 vec![Generic {
                     name: "TYPENAME".to_string(),
+                    constraint: None,
                 }],
             ),
             (
@@ -100,9 +169,11 @@ pub mod tests {
                 vec![
                     Generic {
                         name: "TYPENAME".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "TYPENAMETWO".to_string(),
+                        constraint: None,
                     },
                 ],
             ),
@@ -111,12 +182,15 @@ pub mod tests {
                 vec![
                     Generic {
                         name: "TYPENAME".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "TYPENAMETWO".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "TYPENAMETHREE".to_string(),
+                        constraint: None,
                     },
                 ],
             ),
@@ -124,6 +198,7 @@ pub mod tests {
                 "<ABCDEFGHIJKLMNOPQRSTUVWXYZ>",
                 vec![Generic {
                     name: "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string(),
+                    constraint: None,
                 }],
             ),
             (
@@ -131,88 +206,117 @@ pub mod tests {
                 vec![
                     Generic {
                         name: "A".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "B".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "C".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "D".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "E".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "F".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "G".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "H".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "I".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "J".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "K".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "L".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "M".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "N".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "O".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "P".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "Q".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "R".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "S".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "T".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "U".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "V".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "W".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "X".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "Y".to_string(),
+                        constraint: None,
                     },
                     Generic {
                         name: "Z".to_string(),
+                        constraint: None,
                     },
                 ],
             ),
         ];
 
         for (i, (src, expected_generics)) in test_cases.into_iter().enumerate() {
-            let lex_result = lex_parser("test", "").parse(src).into_result().expect(&src);
+            let lex_result = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(&src);
             let parse_result = generics_parser().parse(make_input(empty_range(), &lex_result));
 
             assert_eq!(
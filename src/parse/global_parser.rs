@@ -0,0 +1,122 @@
+use chumsky::{
+    input::{BorrowInput, SliceInput},
+    prelude::*,
+};
+
+use crate::parse::{
+    SS, Spanned,
+    lexer::Token,
+    type_parser::{TypeExpr, type_expression_parser},
+    value_parser::{ValueExpr, value_expr_parser},
+};
+
+// let counter: Int = 0; at the top level of a module
+// globals are initialized in the order they are declared, before main runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalVariable {
+    pub name: String,
+    pub type_expr: Option<Spanned<TypeExpr>>,
+    pub initializer: Spanned<ValueExpr>,
+    pub span: SS,
+}
+
+pub fn global_variable_parser<'src, I, M>(
+    make_input: M,
+) -> impl Parser<'src, I, GlobalVariable, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>
+        + SliceInput<'src, Slice = &'src [Spanned<Token>]>,
+    M: Fn(SS, &'src [Spanned<Token>]) -> I + Clone + 'static,
+{
+    just(Token::Let)
+        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+        .then(
+            just(Token::ControlChar(':'))
+                .ignore_then(type_expression_parser())
+                .or_not(),
+        )
+        .then_ignore(just(Token::ControlChar('=')))
+        .then(value_expr_parser(make_input))
+        .then_ignore(just(Token::ControlChar(';')))
+        .map_with(|((name, type_expr), initializer), e| GlobalVariable {
+            name,
+            type_expr,
+            initializer,
+            span: e.span(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        lexer::lex_parser,
+        make_input,
+        source_map::FileId,
+        value_parser::{empty_range, value_expr_into_empty_range},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_global_variable_parser() {
+        let test_cases = vec![
+            ("let counter = 0;", "counter", None, ValueExpr::Int(0)),
+            (
+                "let greeting: String = \"hello\";",
+                "greeting",
+                Some(TypeExpr::String),
+                ValueExpr::String("hello".into(), true),
+            ),
+            (
+                "let doubled: Int = counter * 2;",
+                "doubled",
+                Some(TypeExpr::Int),
+                ValueExpr::Mul(
+                    Box::new(
+                        ValueExpr::RawVariable(false, vec!["counter".into()]).into_empty_span(),
+                    ),
+                    Box::new(ValueExpr::Int(2).into_empty_span()),
+                ),
+            ),
+        ];
+
+        for (src, expected_name, expected_type, expected_initializer) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut global = global_variable_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut global.initializer);
+            assert_eq!(global.name, expected_name, "{src}");
+            assert_eq!(
+                global.type_expr.map(|(type_expr, _)| type_expr),
+                expected_type,
+                "{src}"
+            );
+            assert_eq!(global.initializer.0, expected_initializer, "{src}");
+        }
+
+        for invalid in [
+            "let counter = 0",
+            "let counter;",
+            "let counter: Int;",
+            "let = 0;",
+            "counter = 0;",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                global_variable_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -1,19 +1,29 @@
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display, num::IntErrorKind};
 
-use chumsky::{prelude::*, text::whitespace};
+use chumsky::{
+    prelude::*,
+    text::{Char, whitespace},
+};
 
-use crate::parse::{Context, SS, Spanned, value_parser::empty_range};
+use crate::parse::{
+    Context, SS, Spanned,
+    source_map::{FileId, has_line_break},
+    symbol::Symbol,
+    value_parser::empty_range,
+    warning,
+};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum RawFmtStringContents {
     Char(char),
-    Tokens(Vec<Spanned<Token>>),
+    Tokens(Vec<Spanned<Token>>, Option<String>),
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum FmtStringContents {
     String(String),
-    Tokens(Vec<Spanned<Token>>),
+    // the tokens of an interpolation and its format spec, {x:.2} => ([x], Some(".2"))
+    Tokens(Vec<Spanned<Token>>, Option<String>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -35,16 +45,22 @@ pub enum Token {
     Type,
     Go,
     Struct,
+    Enum,
     Impl,
+    Trait,
+    Pub,
     Duck,
     Function,
     Return,
-    Ident(String),
+    Ident(Symbol),
     ControlChar(char),
-    ConstString(String),
-    FormatStringLiteral(Vec<FmtStringContents>),
-    HtmlString(Vec<HtmlStringContents>),
-    ConstInt(i64),
+    // the payloads are boxed, so that they don't make every other token bigger
+    ConstString(Box<str>),
+    FormatStringLiteral(Box<[FmtStringContents]>),
+    HtmlString(Box<[HtmlStringContents]>),
+    // always positive, -1 is a minus and a 1. whether it fits into an Int is up to the type
+    // checker
+    ConstInt(u64),
     ConstBool(bool),
     CharLiteral(char),
     Equals,
@@ -53,27 +69,53 @@ pub enum Token {
     GreaterThanOrEquals,
     And,
     Or,
+    AndAnd,
+    OrOr,
+    ShiftLeft,
+    // ch <- v and <-ch, like in go a<-1 is a send and not a < -1
+    LeftArrow,
+    // a?.b and a ?? b on optional values
+    QuestionDot,
+    QuestionQuestion,
     Match,
     If,
     TypeOf,
     Else,
     Let,
     While,
+    For,
+    In,
+    Loop,
     Break,
     Continue,
     As,
-    InlineGo(String),
-    InlineTsx(String),
-    InlineDuckx(Vec<Spanned<Token>>),
+    Is,
+    InlineGo(Box<str>),
+    InlineTsx(Box<str>),
+    InlineDuckx(Box<[Spanned<Token>]>),
     Module,
     ScopeRes,
     ThinArrow,
     ThickArrow,
-    Comment(String),
-    DocComment(String),
+    Pipe,
+    DotDot,
+    DotDotEquals,
+    DotDotDot,
+    // whatever the lexer couldn't read, up to the next whitespace or delimiter
+    Error,
+    // the end of the file, see push_eof
+    Eof,
+    Comment(Box<str>),
+    DocComment(Box<str>),
     Sus,
     Component,
     Template,
+    Macro,
+    Comptime,
+    Const,
+    Defer,
+    Select,
+    Recover,
 }
 
 impl Display for Token {
@@ -81,14 +123,21 @@ impl Display for Token {
         let t = match self {
             Token::FormatStringLiteral(s) => &format!("f-string {s:?}"),
             Token::Impl => "impl",
+            Token::Trait => "trait",
+            Token::Pub => "pub",
             Token::ScopeRes => "::",
             Token::ThinArrow => "->",
             Token::ThickArrow => "=>",
+            Token::Pipe => "|>",
+            Token::DotDot => "..",
+            Token::DotDotEquals => "..=",
+            Token::DotDotDot => "...",
             Token::Use => "use",
             Token::Type => "type",
             Token::Go => "go",
             Token::TypeOf => "typeof",
             Token::Struct => "struct",
+            Token::Enum => "enum",
             Token::Duck => "duck",
             Token::Component => "component",
             Token::Template => "template",
@@ -106,13 +155,23 @@ impl Display for Token {
             Token::GreaterThanOrEquals => ">=",
             Token::And => "and",
             Token::Or => "or",
+            Token::AndAnd => "&&",
+            Token::OrOr => "||",
+            Token::ShiftLeft => "<<",
+            Token::LeftArrow => "<-",
+            Token::QuestionDot => "?.",
+            Token::QuestionQuestion => "??",
             Token::If => "if",
             Token::Else => "else",
             Token::Let => "let",
             Token::While => "while",
+            Token::For => "for",
+            Token::In => "in",
+            Token::Loop => "loop",
             Token::Break => "break",
             Token::Continue => "continue",
             Token::As => "as",
+            Token::Is => "is",
             Token::InlineGo(_) => "inline go",
             Token::InlineTsx(_) => "inline tsx",
             Token::InlineDuckx(_) => "inline duckx",
@@ -122,6 +181,14 @@ impl Display for Token {
             Token::DocComment(comment) => &format!("/// {comment}"),
             Token::Comment(comment) => &format!("// {comment}"),
             Token::Sus => "sus",
+            Token::Macro => "macro",
+            Token::Comptime => "comptime",
+            Token::Const => "const",
+            Token::Defer => "defer",
+            Token::Select => "select",
+            Token::Recover => "recover",
+            Token::Error => "invalid token",
+            Token::Eof => "end of file",
         };
         write!(f, "{t}")
     }
@@ -155,6 +222,19 @@ pub fn tokens_in_curly_braces<'a>(
     })
 }
 
+// [<>]?0?\d*(\.\d+)?[xXobe]?, alignment, zero padding, width, precision and radix/exponent
+fn is_valid_format_spec(spec: &str) -> bool {
+    let spec = spec.strip_prefix(['<', '>']).unwrap_or(spec);
+    let spec = spec.strip_suffix(['x', 'X', 'o', 'b', 'e']).unwrap_or(spec);
+    let (width, precision) = match spec.split_once('.') {
+        Some((width, precision)) => (width, Some(precision)),
+        None => (spec, None),
+    };
+
+    width.chars().all(|c| c.is_ascii_digit())
+        && precision.is_none_or(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
 #[derive(Debug, Clone)]
 pub struct HtmlAttribute {
     pub name: String,
@@ -244,14 +324,14 @@ pub fn duckx_parse_html_string<'a>(
                         .ignore_then(duckx_lexer.clone())
                         .map(RawHtmlStringContents::Tokens),
                     special_tag().map(|x| {
-                        RawHtmlStringContents::Sub(Token::HtmlString(vec![
-                            HtmlStringContents::String(x),
-                        ]))
+                        RawHtmlStringContents::Sub(Token::HtmlString(
+                            vec![HtmlStringContents::String(x)].into(),
+                        ))
                     }),
                     opening_self_closing().map(|in_html| {
-                        RawHtmlStringContents::Sub(Token::HtmlString(vec![
-                            HtmlStringContents::String(in_html),
-                        ]))
+                        RawHtmlStringContents::Sub(Token::HtmlString(
+                            vec![HtmlStringContents::String(in_html)].into(),
+                        ))
                     }),
                     opening_tag()
                         .rewind()
@@ -311,15 +391,14 @@ pub fn duckx_parse_html_string<'a>(
                         final_out.push(HtmlStringContents::String(s_buf));
                     }
 
-                    Token::HtmlString(final_out)
+                    Token::HtmlString(final_out.into())
                 },
             )
     })
 }
 
 pub fn duckx_contents_in_curly_braces<'a>(
-    file_name: &'static str,
-    file_contents: &'static str,
+    file_id: FileId,
     lexer: impl Parser<'a, &'a str, Spanned<Token>, extra::Err<Rich<'a, char>>> + Clone + 'a,
 ) -> impl Parser<'a, &'a str, Vec<Spanned<Token>>, extra::Err<Rich<'a, char>>> + Clone {
     recursive(|duckx_lexer| {
@@ -329,7 +408,9 @@ pub fn duckx_contents_in_curly_braces<'a>(
                     just("{").rewind().ignore_then(duckx_lexer.clone()),
                     special_tag().map(|x| {
                         vec![(
-                            Token::HtmlString(vec![HtmlStringContents::String(x.to_string())]),
+                            Token::HtmlString(
+                                vec![HtmlStringContents::String(x.to_string())].into(),
+                            ),
                             empty_range(),
                         )]
                     }),
@@ -388,7 +469,7 @@ pub fn duckx_contents_in_curly_braces<'a>(
                                 final_out.push(HtmlStringContents::String(s_buf));
                             }
 
-                            vec![(Token::HtmlString(final_out), empty_range())]
+                            vec![(Token::HtmlString(final_out.into()), empty_range())]
                             // vec![(
                             //     Token::HtmlString(vec![HtmlStringContents::String(dbg!(format!(
                             //         "{x1}{x2}/>"
@@ -404,12 +485,9 @@ pub fn duckx_contents_in_curly_braces<'a>(
                             vec![(
                                 x,
                                 SS {
-                                    start: e.span().start,
-                                    end: e.span().end,
-                                    context: Context {
-                                        file_name,
-                                        file_contents,
-                                    },
+                                    start: e.span().start as u32,
+                                    end: e.span().end as u32,
+                                    context: Context { file_id },
                                 },
                             )]
                         }),
@@ -434,19 +512,21 @@ pub fn duckx_contents_in_curly_braces<'a>(
 }
 
 pub fn lex_single<'a>(
-    file_name: &'static str,
-    file_contents: &'static str,
+    file_id: FileId,
 ) -> impl Parser<'a, &'a str, Spanned<Token>, extra::Err<Rich<'a, char>>> + Clone {
     recursive(|lexer| {
-        let keyword_or_ident = text::ident().map(|str| match str {
+        let keyword_or_ident = ident().map(|str| match str {
             "module" => Token::Module,
             "use" => Token::Use,
             "typeof" => Token::TypeOf,
             "impl" => Token::Impl,
+            "trait" => Token::Trait,
+            "pub" => Token::Pub,
             "type" => Token::Type,
             "duck" => Token::Duck,
             "go" => Token::Go,
             "struct" => Token::Struct,
+            "enum" => Token::Enum,
             "fn" => Token::Function,
             "return" => Token::Return,
             "component" => Token::Component,
@@ -454,24 +534,32 @@ pub fn lex_single<'a>(
             "if" => Token::If,
             "else" => Token::Else,
             "while" => Token::While,
+            "for" => Token::For,
+            "in" => Token::In,
+            "loop" => Token::Loop,
             "break" => Token::Break,
             "continue" => Token::Continue,
             "as" => Token::As,
+            "is" => Token::Is,
             "match" => Token::Match,
             "sus" => Token::Sus,
             "and" => Token::And,
             "or" => Token::Or,
             "template" => Token::Template,
-            _ => Token::Ident(str.to_string()),
+            "macro" => Token::Macro,
+            "comptime" => Token::Comptime,
+            "const" => Token::Const,
+            "defer" => Token::Defer,
+            "select" => Token::Select,
+            "recover" => Token::Recover,
+            "true" => Token::ConstBool(true),
+            "false" => Token::ConstBool(false),
+            _ => Token::Ident(Symbol::intern(str)),
         });
 
-        let ctrl = one_of("!=:{};,&()-<>.+-*/%|[]@").map(Token::ControlChar);
+        let ctrl = one_of("!=:{};,&()-<>.+-*/%|^~[]@$#?").map(Token::ControlChar);
 
-        let string = string_lexer();
-        let r#bool = choice((
-            just("true").to(Token::ConstBool(true)),
-            just("false").to(Token::ConstBool(false)),
-        ));
+        let string = multi_line_string_lexer().or(string_lexer());
         let r#char = char_lexer();
         let num = num_literal();
 
@@ -479,12 +567,22 @@ pub fn lex_single<'a>(
         let not_equals = just("!=").to(Token::NotEquals);
         let less_than_or_equals = just("<=").to(Token::LessThanOrEquals);
         let greater_than_or_equals = just(">=").to(Token::GreaterThanOrEquals);
-        let and = just("and").to(Token::And);
-        let or = just("or").to(Token::Or);
 
         let scope_res = just("::").to(Token::ScopeRes);
         let thin_arrow = just("->").to(Token::ThinArrow);
         let thick_arrow = just("=>").to(Token::ThickArrow);
+        let pipe = just("|>").to(Token::Pipe);
+        let dot_dot_equals = just("..=").to(Token::DotDotEquals);
+        let dot_dot_dot = just("...").to(Token::DotDotDot);
+        let dot_dot = just("..").to(Token::DotDot);
+        let and_and = just("&&").to(Token::AndAnd);
+        let or_or = just("||").to(Token::OrOr);
+        // there's no >> token, it would split the closing brackets of nested generics like
+        // Vec<Vec<Int>>, the value parser joins two adjacent > instead
+        let shift_left = just("<<").to(Token::ShiftLeft);
+        let left_arrow = just("<-").to(Token::LeftArrow);
+        let question_dot = just("?.").to(Token::QuestionDot);
+        let question_question = just("??").to(Token::QuestionQuestion);
 
         let doc_comment = just("///")
             .ignore_then(
@@ -493,10 +591,7 @@ pub fn lex_single<'a>(
                     .repeated()
                     .collect::<Vec<_>>(),
             )
-            .padded()
-            .map(|comment| {
-                Token::DocComment(comment.iter().collect::<String>().trim().to_string())
-            });
+            .map(|comment| Token::DocComment(comment.iter().collect::<String>().trim().into()));
 
         let comment = just("//")
             .ignore_then(
@@ -505,25 +600,81 @@ pub fn lex_single<'a>(
                     .repeated()
                     .collect::<Vec<_>>(),
             )
-            .padded()
-            .map(|comment| Token::Comment(comment.iter().collect::<String>().trim().to_string()));
+            .map(|comment| Token::Comment(comment.iter().collect::<String>().trim().into()));
+
+        // /** */ is a doc comment, but /**/ and /*** */ are not
+        let block_comment = block_comment_parser().map(|comment| match comment.strip_prefix('*') {
+            Some(doc) if !doc.is_empty() && !doc.starts_with('*') => Token::DocComment(
+                doc.lines()
+                    .map(|line| line.trim().trim_start_matches('*').trim())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .trim()
+                    .into(),
+            ),
+            _ => Token::Comment(comment.trim().into()),
+        });
+
+        // {x:.2} or {n:04}, a : followed by spec characters right before the closing brace
+        let format_spec = just(':')
+            .ignore_then(
+                one_of("<>0123456789.xXobe")
+                    .repeated()
+                    .at_least(1)
+                    .to_slice(),
+            )
+            .then_ignore(just('}'));
+
+        // strings inside of an interpolation are lexed as whole tokens, so braces and quotes in
+        // them don't end the interpolation or the f-string
+        let fmt_interpolation = just('{')
+            .ignore_then(
+                choice((
+                    just('{')
+                        .rewind()
+                        .ignore_then(tokens_in_curly_braces(lexer.clone())),
+                    escaped_quote_string_lexer()
+                        .map_with(move |t, e| {
+                            let span = SS {
+                                start: e.span().start as u32,
+                                end: e.span().end as u32,
+                                context: Context { file_id },
+                            };
+                            vec![(t, span)]
+                        })
+                        .padded(),
+                    any()
+                        .filter(|c| *c != '{' && *c != '}')
+                        .and_is(format_spec.clone().not())
+                        .rewind()
+                        .ignore_then(lexer.clone())
+                        .map(|x| vec![x]),
+                ))
+                .repeated()
+                .collect::<Vec<_>>(),
+            )
+            .then(
+                format_spec
+                    .try_map(|spec: &str, span| {
+                        if is_valid_format_spec(spec) {
+                            Ok(Some(spec.to_string()))
+                        } else {
+                            Err(Rich::custom(span, format!("invalid format spec {spec}")))
+                        }
+                    })
+                    .or(just('}').to(None)),
+            )
+            .map(|(tokens, spec)| {
+                RawFmtStringContents::Tokens(tokens.into_iter().flatten().collect(), spec)
+            });
 
         let fmt_string = just("f")
             .ignore_then(just('"'))
             .ignore_then(
                 choice((
-                    just("{")
-                        .rewind()
-                        .ignore_then(tokens_in_curly_braces(lexer.clone()))
-                        .map(|e| RawFmtStringContents::Tokens(e[1..e.len() - 1].to_vec())),
+                    fmt_interpolation,
                     none_of("\\\"")
-                        .or(choice((
-                            just("\\\\").to('\\'),
-                            just("\\{").to('{'),
-                            just("\\n").to('\n'),
-                            just("\\t").to('\t'),
-                            just("\\\"").to('"'),
-                        )))
+                        .or(escape_sequence("{}"))
                         .map(RawFmtStringContents::Char),
                 ))
                 .repeated()
@@ -536,66 +687,84 @@ pub fn lex_single<'a>(
 
                 for e in x {
                     match e {
-                        RawFmtStringContents::Tokens(t) => {
+                        RawFmtStringContents::Tokens(t, spec) => {
                             if !s.is_empty() {
                                 xx.push(FmtStringContents::String(s.clone()));
                                 s.clear();
                             }
-                            xx.push(FmtStringContents::Tokens(t));
+                            xx.push(FmtStringContents::Tokens(t, spec));
                         }
                         RawFmtStringContents::Char(c) => s.push(c),
                     }
                 }
 
                 if !s.is_empty() {
-                    xx.push(FmtStringContents::String(s.replace("\n", "\\n")));
+                    xx.push(FmtStringContents::String(s));
                 }
 
-                Token::FormatStringLiteral(xx)
+                Token::FormatStringLiteral(xx.into())
             });
 
-        let token = inline_go_parser()
-            .or(inline_tsx_parser())
-            .or(just("duckx")
-                .ignore_then(whitespace().at_least(1))
-                .ignore_then(just("{").rewind())
-                // todo: [TSX] create tsx text parser
-                .ignore_then(duckx_contents_in_curly_braces(
-                    file_name,
-                    file_contents,
-                    lexer.clone(),
-                ))
-                .map(Token::InlineDuckx))
-            .or(doc_comment)
-            .or(comment)
-            .or(fmt_string)
-            .or(thin_arrow)
-            .or(thick_arrow)
-            .or(scope_res)
-            .or(r#bool)
-            .or(equals)
-            .or(not_equals)
-            .or(less_than_or_equals)
-            .or(greater_than_or_equals)
-            .or(and)
-            .or(or)
-            .or(keyword_or_ident)
-            .or(num)
-            .or(ctrl)
-            .or(string)
-            .or(r#char);
+        let duckx = just("duckx")
+            .ignore_then(whitespace().at_least(1))
+            .ignore_then(just("{").rewind())
+            // todo: [TSX] create tsx text parser
+            .ignore_then(duckx_contents_in_curly_braces(file_id, lexer.clone()))
+            .map(|tokens| Token::InlineDuckx(tokens.into()));
+
+        // dispatch on the first character, so that every token only runs the parsers that
+        // can actually start with it instead of backtracking through all of them
+        let word = any()
+            .filter(|c: &char| c.is_ident_start() || *c == '_')
+            .rewind()
+            .ignore_then(choice((
+                inline_go_parser(),
+                inline_tsx_parser(),
+                duckx,
+                fmt_string,
+                keyword_or_ident,
+            )));
+
+        let comments =
+            just('/')
+                .rewind()
+                .ignore_then(choice((doc_comment, comment, block_comment)));
+
+        let operator = one_of("-=:!<>|&.?").rewind().ignore_then(choice((
+            thin_arrow,
+            thick_arrow,
+            pipe,
+            dot_dot_equals,
+            dot_dot_dot,
+            dot_dot,
+            and_and,
+            or_or,
+            shift_left,
+            left_arrow,
+            question_dot,
+            question_question,
+            scope_res,
+            equals,
+            not_equals,
+            less_than_or_equals,
+            greater_than_or_equals,
+        )));
+
+        let token =
+            choice((word, comments, operator, num, ctrl, string, r#char)).recover_with(via_parser(
+                any()
+                    .then(none_of(" \t\r\n{}()[];,\"").repeated())
+                    .to(Token::Error),
+            ));
 
         token
             .map_with(move |t, e| {
                 (
                     t,
                     SS {
-                        start: e.span().start,
-                        end: e.span().end,
-                        context: Context {
-                            file_name,
-                            file_contents,
-                        },
+                        start: e.span().start as u32,
+                        end: e.span().end as u32,
+                        context: Context { file_id },
                     },
                 )
             })
@@ -603,13 +772,137 @@ pub fn lex_single<'a>(
     })
 }
 
+// errors at the end of a file point at this token instead of a span that covers the whole
+// file. only a whole file ends with it, the parsers of single items don't expect one
+pub fn push_eof(tokens: &mut Vec<Spanned<Token>>, file_id: FileId, src_len: usize) {
+    let end = src_len as u32;
+    tokens.push((
+        Token::Eof,
+        SS {
+            start: end,
+            end,
+            context: Context { file_id },
+        },
+    ));
+}
+
+// a line that starts with (, [, - or <- starts a new statement instead of calling, indexing,
+// subtracting from or sending to what the line before ends with. the lexer only sees the line
+// breaks, so the ; that ends the statement before is inserted here. in parens and brackets a
+// line break doesn't end anything. the line could just as well be meant to continue the one
+// before, so the first tokens of these lines are returned to be warned about. a ; at the end of
+// the line before says that a new statement is meant
+pub fn end_statements_at_line_breaks(
+    tokens: Vec<Spanned<Token>>,
+    src: &str,
+) -> (Vec<Spanned<Token>>, Vec<Spanned<Token>>) {
+    let mut out: Vec<Spanned<Token>> = Vec::with_capacity(tokens.len());
+    let mut statement_starts = Vec::new();
+    let mut delimiters = Vec::new();
+
+    for token in tokens {
+        let starts_statement = matches!(
+            token.0,
+            Token::ControlChar('(' | '[' | '-') | Token::LeftArrow
+        );
+        if starts_statement
+            && delimiters.last().is_none_or(|delimiter| *delimiter == '{')
+            && let Some((previous, previous_span)) = out.last()
+            && ends_value(previous)
+            && has_line_break(src, previous_span.end as usize, token.1.start as usize)
+        {
+            let span = SS {
+                start: previous_span.end,
+                end: previous_span.end,
+                context: previous_span.context,
+            };
+            out.push((Token::ControlChar(';'), span));
+            statement_starts.push(token.clone());
+        }
+
+        match token.0 {
+            Token::ControlChar(delimiter @ ('(' | '[' | '{')) => delimiters.push(delimiter),
+            Token::ControlChar(')' | ']' | '}') => {
+                delimiters.pop();
+            }
+            _ => {}
+        }
+        out.push(token);
+    }
+
+    (out, statement_starts)
+}
+
+pub fn warn_statement_starts(statement_starts: &[Spanned<Token>]) {
+    for (token, span) in statement_starts {
+        warning(
+            &span.context.file_name(),
+            &format!("this line starts a new statement with {token}"),
+            (
+                format!(
+                    "it doesn't continue the line before, end that one with a ; or move the \
+                     {token} to its end"
+                ),
+                *span,
+            ),
+            &span.context.file_contents(),
+        );
+    }
+}
+
+fn ends_value(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Ident(_)
+            | Token::ConstString(_)
+            | Token::FormatStringLiteral(_)
+            | Token::HtmlString(_)
+            | Token::ConstInt(_)
+            | Token::ConstBool(_)
+            | Token::CharLiteral(_)
+            | Token::InlineGo(_)
+            | Token::ControlChar(')' | ']' | '}' | '?')
+    )
+}
+
 pub fn lex_parser<'src>(
-    file_name: &'static str,
-    file_contents: &'static str,
+    file_id: FileId,
 ) -> impl Parser<'src, &'src str, Vec<Spanned<Token>>, extra::Err<Rich<'src, char>>> + Clone {
-    lex_single(file_name, file_contents)
-        .repeated()
-        .collect::<Vec<_>>()
+    // #!/usr/bin/env duck run in the first line, so that a file can be executed as a script.
+    // it's skipped instead of stripped, so the spans of the following tokens stay correct
+    let shebang = just("#!")
+        .then(just('[').not())
+        .then(none_of('\n').repeated());
+
+    shebang
+        .or_not()
+        .ignore_then(lex_single(file_id).repeated().collect::<Vec<_>>())
+}
+
+// go strings, runes and comments are copied as a whole, so braces inside of them don't count
+fn go_literal<'src>()
+-> impl Parser<'src, &'src str, &'src str, extra::Err<Rich<'src, char>>> + Clone {
+    let escaped = just('\\').then(any()).to_slice();
+
+    let string = just('"')
+        .then(none_of("\\\"\n").to_slice().or(escaped.clone()).repeated())
+        .then(just('"'));
+    let rune = just('\'')
+        .then(none_of("\\'\n").to_slice().or(escaped).repeated())
+        .then(just('\''));
+    let raw_string = just('`').then(none_of("`").repeated()).then(just('`'));
+    let line_comment = just("//").then(none_of("\n").repeated());
+    let block_comment = just("/*")
+        .then(any().and_is(just("*/").not()).repeated())
+        .then(just("*/"));
+
+    choice((
+        string.to_slice(),
+        rune.to_slice(),
+        raw_string.to_slice(),
+        line_comment.to_slice(),
+        block_comment.to_slice(),
+    ))
 }
 
 fn go_text_parser<'src>()
@@ -617,8 +910,11 @@ fn go_text_parser<'src>()
     recursive(|e| {
         just("{")
             .ignore_then(
-                ((just("{").rewind().ignore_then(e.clone()))
-                    .or(any().filter(|c| *c != '{' && *c != '}').map(String::from)))
+                choice((
+                    just("{").rewind().ignore_then(e.clone()),
+                    go_literal().map(String::from),
+                    any().filter(|c| *c != '{' && *c != '}').map(String::from),
+                ))
                 .repeated()
                 .collect::<Vec<_>>(),
             )
@@ -630,24 +926,164 @@ fn go_text_parser<'src>()
     })
 }
 
+// unicode identifiers, (xid_start | _) xid_continue*
+fn ident<'src>() -> impl Parser<'src, &'src str, &'src str, extra::Err<Rich<'src, char>>> + Clone {
+    any()
+        .filter(|c: &char| c.is_ident_start() || *c == '_')
+        .then(any().filter(|c: &char| c.is_ident_continue()).repeated())
+        .to_slice()
+}
+
+// the chars of xid_start and xid_continue that are neither a go letter (L*) nor a go digit
+// (Nd), the symbols of Other_ID_Start and the numbers (No) of Other_ID_Continue
+const NON_GO_IDENT_CHARS: [char; 16] = [
+    '\u{1885}', '\u{1886}', '\u{2118}', '\u{212E}', '\u{309B}', '\u{309C}', '\u{1369}', '\u{136A}',
+    '\u{136B}', '\u{136C}', '\u{136D}', '\u{136E}', '\u{136F}', '\u{1370}', '\u{1371}', '\u{19DA}',
+];
+
+// ⸯ (U+2E2F) is a go letter, but as pattern syntax it's never part of a duck identifier. the
+// compiler also uses it for the names it introduces, so they can't shadow a user's variable
+pub const GO_IDENT_ESCAPE: char = '\u{2E2F}';
+
+// go only allows letters, digits and _ in identifiers. the rest of xid (letter numbers like Ⅻ,
+// combining marks, connector punctuation) is spelled out as ⸯ{hex}ⸯ, so that an identifier can
+// be used in the go code without clashing with one a user wrote
+pub fn go_ident(ident: &str) -> Cow<'_, str> {
+    let is_go_char = |c: char| {
+        let is_letter = c.is_ident_start() && !c.is_numeric();
+        // xid_start has the letter numbers (Nl), what's left of xid_continue are decimal digits
+        let is_digit = c.is_numeric() && c.is_ident_continue() && !c.is_ident_start();
+        c == '_' || ((is_letter || is_digit) && !NON_GO_IDENT_CHARS.contains(&c))
+    };
+    if ident.chars().all(is_go_char) {
+        return Cow::Borrowed(ident);
+    }
+
+    ident
+        .chars()
+        .map(|c| match c {
+            c if is_go_char(c) => c.to_string(),
+            c => format!("{GO_IDENT_ESCAPE}{:x}{GO_IDENT_ESCAPE}", c as u32),
+        })
+        .collect()
+}
+
+// ${name} inside of inline go refers to a duck variable, f gets its name and returns the go
+// code that replaces it. go strings and comments are left as they are
+pub fn map_go_interpolations(go_code: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let parts = choice((
+        just("${")
+            .ignore_then(ident())
+            .then_ignore(just('}'))
+            .map(|name: &str| (name, true)),
+        go_literal().map(|text| (text, false)),
+        any().to_slice().map(|text| (text, false)),
+    ))
+    .repeated()
+    .collect::<Vec<_>>()
+    .parse(go_code)
+    .into_output()
+    .expect("every char is accepted");
+
+    parts
+        .into_iter()
+        .map(|(text, is_interpolation)| {
+            if is_interpolation {
+                f(text)
+            } else {
+                text.to_string()
+            }
+        })
+        .collect()
+}
+
+// the identifiers keep the spelling of the source until the go code is generated, which is
+// where the ones with chars go doesn't allow are escaped. go strings and comments are left as
+// they are, a name in a message is shown the way it was written
+pub fn escape_go_idents(go_code: &str) -> String {
+    // every ascii identifier is a go identifier already
+    if go_code.is_ascii() {
+        return go_code.to_string();
+    }
+
+    choice((
+        go_literal().map(Cow::Borrowed),
+        any()
+            .filter(|c: &char| c.is_ident_continue())
+            .repeated()
+            .at_least(1)
+            .to_slice()
+            .map(go_ident),
+        any().to_slice().map(Cow::Borrowed),
+    ))
+    .repeated()
+    .collect::<Vec<_>>()
+    .parse(go_code)
+    .into_output()
+    .expect("every char is accepted")
+    .concat()
+}
+
+// /* */ comments nest, so commenting out code that already contains one keeps working
+fn block_comment_parser<'src>()
+-> impl Parser<'src, &'src str, String, extra::Err<Rich<'src, char>>> + Clone {
+    recursive(|block_comment| {
+        just("/*")
+            .ignore_then(
+                block_comment
+                    .map(|inner| format!("/*{inner}*/"))
+                    .or(any().and_is(just("*/").not()).map(String::from))
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .then_ignore(just("*/"))
+            .map(|parts| parts.concat())
+    })
+}
+
+// go { .. } is inline go, a go followed by anything else is the keyword of go f(x)
 fn inline_go_parser<'src>()
 -> impl Parser<'src, &'src str, Token, extra::Err<Rich<'src, char>>> + Clone {
     just("go")
         .ignore_then(whitespace().at_least(1))
         .ignore_then(just("{").rewind())
         .ignore_then(go_text_parser())
-        .map(|x| Token::InlineGo(x[1..x.len() - 1].to_owned()))
+        .map(|x| Token::InlineGo(x[1..x.len() - 1].into()))
 }
 
 fn num_literal<'src>() -> impl Parser<'src, &'src str, Token, extra::Err<Rich<'src, char>>> + Clone
 {
-    just('-')
-        .or_not()
-        .then(text::int(10))
-        .to_slice()
-        .try_map(|s: &str, span| {
-            s.parse::<i64>()
-                .map_err(|_| Rich::custom(span, "Invalid integer"))
+    let decimal = text::int(10).map(|digits| ("", 10, digits));
+
+    // 0x, 0o and 0b take every alphanumeric char after them, so that 0xFG is reported instead of
+    // being lexed as 0 followed by the identifier xFG
+    let prefixed = choice((
+        just("0x").to(("0x", 16)),
+        just("0o").to(("0o", 8)),
+        just("0b").to(("0b", 2)),
+    ))
+    .then(
+        any()
+            .filter(char::is_ascii_alphanumeric)
+            .repeated()
+            .to_slice(),
+    )
+    .map(|((prefix, radix), digits)| (prefix, radix, digits));
+
+    prefixed
+        .or(decimal)
+        .validate(|(prefix, radix, digits), e, emitter| {
+            u64::from_str_radix(digits, radix).unwrap_or_else(|err| {
+                let reason = match err.kind() {
+                    IntErrorKind::PosOverflow => format!(
+                        "integer literal too large, {prefix}{digits} is outside of 0..={}",
+                        u64::MAX
+                    ),
+                    _ => format!("invalid integer literal {prefix}{digits}"),
+                };
+                emitter.emit(Rich::custom(e.span(), reason));
+                0
+            })
         })
         .map(Token::ConstInt)
 }
@@ -659,17 +1095,76 @@ fn inline_tsx_parser<'src>()
         .ignore_then(just("{").rewind())
         // todo: [TSX] create tsx text parser
         .ignore_then(go_text_parser())
-        .map(|x| Token::InlineTsx(x[1..x.len() - 1].to_owned()))
+        .map(|x| Token::InlineTsx(x[1..x.len() - 1].into()))
+}
+
+// the escapes shared by strings, f-strings and chars, besides those a literal can escape the
+// given chars that have a special meaning inside of it
+fn escape_sequence<'src>(
+    special: &'static str,
+) -> impl Parser<'src, &'src str, char, extra::Err<Rich<'src, char>>> + Clone {
+    just('\\').ignore_then(choice((
+        just('\\').to('\\'),
+        just('n').to('\n'),
+        just('t').to('\t'),
+        just('r').to('\r'),
+        just('0').to('\0'),
+        one_of("'\"").or(one_of(special)),
+        hex_escape(),
+        unicode_escape(),
+        any().validate(|c: char, e, emitter| {
+            emitter.emit(Rich::custom(
+                e.span(),
+                format!("unknown escape sequence \\{c}"),
+            ));
+            c
+        }),
+    )))
+}
+
+// \x7F, exactly two hex digits, only ascii because everything above is \u{..}
+fn hex_escape<'src>() -> impl Parser<'src, &'src str, char, extra::Err<Rich<'src, char>>> + Clone {
+    just('x')
+        .ignore_then(none_of("\"'\n").repeated().at_most(2).to_slice())
+        .validate(|digits: &str, e, emitter| {
+            u8::from_str_radix(digits, 16)
+                .ok()
+                .filter(|byte| digits.len() == 2 && byte.is_ascii())
+                .map(char::from)
+                .unwrap_or_else(|| {
+                    emitter.emit(Rich::custom(
+                        e.span(),
+                        format!("invalid hex escape \\x{digits}, expected a value up to \\x7F"),
+                    ));
+                    char::REPLACEMENT_CHARACTER
+                })
+        })
+}
+
+// \u{1F600}, up to six hex digits that have to form a unicode scalar value
+fn unicode_escape<'src>() -> impl Parser<'src, &'src str, char, extra::Err<Rich<'src, char>>> + Clone
+{
+    just("u{")
+        .ignore_then(none_of("}\"'\n").repeated().to_slice())
+        .then_ignore(just('}'))
+        .validate(|digits: &str, e, emitter| {
+            u32::from_str_radix(digits, 16)
+                .ok()
+                .filter(|_| digits.len() <= 6)
+                .and_then(char::from_u32)
+                .unwrap_or_else(|| {
+                    emitter.emit(Rich::custom(
+                        e.span(),
+                        format!("invalid unicode escape \\u{{{digits}}}"),
+                    ));
+                    char::REPLACEMENT_CHARACTER
+                })
+        })
 }
 
 fn char_lexer<'src>() -> impl Parser<'src, &'src str, Token, extra::Err<Rich<'src, char>>> + Clone {
     just("'")
-        .ignore_then(none_of("\\\n\t'").or(choice((
-            just("\\\\").to('\\'),
-            just("\\n").to('\n'),
-            just("\\t").to('\t'),
-            just("\\'").to('\''),
-        ))))
+        .ignore_then(none_of("\\\n\t'").or(escape_sequence("")))
         .then_ignore(just("'"))
         .map(Token::CharLiteral)
 }
@@ -678,17 +1173,58 @@ fn string_lexer<'a>() -> impl Parser<'a, &'a str, Token, extra::Err<Rich<'a, cha
     just('"')
         .ignore_then(
             none_of("\\\n\t\"")
-                .or(choice((
-                    just("\\\\").to('\\'),
-                    just("\\n").to('\n'),
-                    just("\\t").to('\t'),
-                    just("\\\"").to('"'),
-                )))
+                .or(escape_sequence(""))
                 .repeated()
                 .collect::<String>(),
         )
         .then_ignore(just('"'))
-        .map(Token::ConstString)
+        .map(|s| Token::ConstString(s.into()))
+}
+
+// f"{get(\"key\")}", a string inside of an f-string interpolation may use escaped quotes
+fn escaped_quote_string_lexer<'a>()
+-> impl Parser<'a, &'a str, Token, extra::Err<Rich<'a, char>>> + Clone {
+    just("\\\"")
+        .ignore_then(
+            none_of("\\\n\t\"")
+                .or(just("\\\"").not().ignore_then(escape_sequence("")))
+                .repeated()
+                .collect::<String>(),
+        )
+        .then_ignore(just("\\\""))
+        .map(|s| Token::ConstString(s.into()))
+}
+
+// """ strings can span multiple lines, nothing inside of them is escaped and the indentation
+// shared by all of their lines is removed
+fn multi_line_string_lexer<'a>()
+-> impl Parser<'a, &'a str, Token, extra::Err<Rich<'a, char>>> + Clone {
+    just("\"\"\"")
+        .ignore_then(any().and_is(just("\"\"\"").not()).repeated().to_slice())
+        .then_ignore(just("\"\"\""))
+        .map(|contents: &str| Token::ConstString(strip_common_indentation(contents).into()))
+}
+
+fn strip_common_indentation(contents: &str) -> String {
+    // the line breaks after the opening and before the closing quotes are only for the looks
+    let contents = contents.strip_prefix('\n').unwrap_or(contents);
+    let contents = match contents.rfind('\n') {
+        Some(i) if contents[i + 1..].trim().is_empty() => &contents[..i],
+        _ => contents,
+    };
+
+    let indentation = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
+
+    contents
+        .lines()
+        .map(|line| line.chars().skip(indentation).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 pub fn token_empty_range(token_span: &mut Spanned<Token>) {
@@ -696,7 +1232,7 @@ pub fn token_empty_range(token_span: &mut Spanned<Token>) {
     if let Token::FormatStringLiteral(contents) = &mut token_span.0 {
         for content in contents {
             match content {
-                FmtStringContents::Tokens(tokens) => {
+                FmtStringContents::Tokens(tokens, _) => {
                     for token in tokens {
                         token_empty_range(token);
                     }
@@ -752,36 +1288,45 @@ mod tests {
         let test_cases = vec![
             (
                 "duckx {<>{{}}</>}",
-                vec![Token::InlineDuckx(vec![
-                    Token::ControlChar('{').into_empty_span(),
-                    Token::HtmlString(vec![
-                        HtmlStringContents::String("<>".to_string()),
-                        HtmlStringContents::Tokens(vec![
-                            Token::ControlChar('{').into_empty_span(),
-                            Token::ControlChar('{').into_empty_span(),
-                            Token::ControlChar('}').into_empty_span(),
-                            Token::ControlChar('}').into_empty_span(),
-                        ]),
-                        HtmlStringContents::String("</>".to_string()),
-                    ])
-                    .into_empty_span(),
-                    Token::ControlChar('}').into_empty_span(),
-                ])],
+                vec![Token::InlineDuckx(
+                    vec![
+                        Token::ControlChar('{').into_empty_span(),
+                        Token::HtmlString(
+                            vec![
+                                HtmlStringContents::String("<>".to_string()),
+                                HtmlStringContents::Tokens(vec![
+                                    Token::ControlChar('{').into_empty_span(),
+                                    Token::ControlChar('{').into_empty_span(),
+                                    Token::ControlChar('}').into_empty_span(),
+                                    Token::ControlChar('}').into_empty_span(),
+                                ]),
+                                HtmlStringContents::String("</>".to_string()),
+                            ]
+                            .into(),
+                        )
+                        .into_empty_span(),
+                        Token::ControlChar('}').into_empty_span(),
+                    ]
+                    .into(),
+                )],
             ),
             (
                 "duckx {<Counter initial={10} />}",
                 vec![Token::InlineDuckx(
                     vec![
                         Token::ControlChar('{'),
-                        Token::HtmlString(vec![
-                            HtmlStringContents::String("<Counter initial=".to_string()),
-                            HtmlStringContents::Tokens(all_empty(vec![
-                                Token::ControlChar('{'),
-                                Token::ConstInt(10),
-                                Token::ControlChar('}'),
-                            ])),
-                            HtmlStringContents::String(" />".to_string()),
-                        ]),
+                        Token::HtmlString(
+                            vec![
+                                HtmlStringContents::String("<Counter initial=".to_string()),
+                                HtmlStringContents::Tokens(all_empty(vec![
+                                    Token::ControlChar('{'),
+                                    Token::ConstInt(10),
+                                    Token::ControlChar('}'),
+                                ])),
+                                HtmlStringContents::String(" />".to_string()),
+                            ]
+                            .into(),
+                        ),
                         Token::ControlChar('}'),
                     ]
                     .into_iter()
@@ -791,265 +1336,340 @@ mod tests {
             ),
             (
                 "duckx {let hello = <> <!doctype html>{<Counter initial={100}/>} </>;}",
-                vec![Token::InlineDuckx(all_empty(vec![
-                    left_brace(),
-                    Token::Let,
-                    Token::Ident("hello".to_string()),
-                    ctrl('='),
-                    Token::HtmlString(vec![
-                        HtmlStringContents::String("<> <!doctype html>".to_string()),
-                        HtmlStringContents::Tokens(all_empty(vec![
-                            left_brace(),
-                            Token::HtmlString(vec![
-                                HtmlStringContents::String("<Counter initial=".to_string()),
+                vec![Token::InlineDuckx(
+                    all_empty(vec![
+                        left_brace(),
+                        Token::Let,
+                        Token::Ident("hello".into()),
+                        ctrl('='),
+                        Token::HtmlString(
+                            vec![
+                                HtmlStringContents::String("<> <!doctype html>".to_string()),
                                 HtmlStringContents::Tokens(all_empty(vec![
                                     left_brace(),
-                                    Token::ConstInt(100),
+                                    Token::HtmlString(
+                                        vec![
+                                            HtmlStringContents::String(
+                                                "<Counter initial=".to_string(),
+                                            ),
+                                            HtmlStringContents::Tokens(all_empty(vec![
+                                                left_brace(),
+                                                Token::ConstInt(100),
+                                                right_brace(),
+                                            ])),
+                                            HtmlStringContents::String("/>".to_string()),
+                                        ]
+                                        .into(),
+                                    ),
                                     right_brace(),
                                 ])),
-                                HtmlStringContents::String("/>".to_string()),
-                            ]),
-                            right_brace(),
-                        ])),
-                        HtmlStringContents::String(" </>".to_string()),
-                    ]),
-                    ctrl(';'),
-                    right_brace(),
-                ]))],
+                                HtmlStringContents::String(" </>".to_string()),
+                            ]
+                            .into(),
+                        ),
+                        ctrl(';'),
+                        right_brace(),
+                    ])
+                    .into(),
+                )],
             ),
             (
                 "duckx {let hello = <> {ti <span id={props.id} hello={123}></span> tle} <h1> hallo moin  123</h1> abc </>;}",
-                vec![Token::InlineDuckx(all_empty(vec![
-                    left_brace(),
-                    Token::Let,
-                    Token::Ident("hello".to_string()),
-                    ctrl('='),
-                    Token::HtmlString(vec![
-                        HtmlStringContents::String("<> ".to_string()),
-                        HtmlStringContents::Tokens(all_empty(vec![
-                            left_brace(),
-                            Token::Ident("ti".to_string()),
-                            Token::HtmlString(vec![
-                                HtmlStringContents::String("<span id=".to_string()),
-                                HtmlStringContents::Tokens(all_empty(vec![
-                                    left_brace(),
-                                    Token::Ident("props".to_string()),
-                                    ctrl('.'),
-                                    Token::Ident("id".to_string()),
-                                    right_brace(),
-                                ])),
-                                HtmlStringContents::String(" hello=".to_string()),
+                vec![Token::InlineDuckx(
+                    all_empty(vec![
+                        left_brace(),
+                        Token::Let,
+                        Token::Ident("hello".into()),
+                        ctrl('='),
+                        Token::HtmlString(
+                            vec![
+                                HtmlStringContents::String("<> ".to_string()),
                                 HtmlStringContents::Tokens(all_empty(vec![
                                     left_brace(),
-                                    Token::ConstInt(123),
+                                    Token::Ident("ti".into()),
+                                    Token::HtmlString(
+                                        vec![
+                                            HtmlStringContents::String("<span id=".to_string()),
+                                            HtmlStringContents::Tokens(all_empty(vec![
+                                                left_brace(),
+                                                Token::Ident("props".into()),
+                                                ctrl('.'),
+                                                Token::Ident("id".into()),
+                                                right_brace(),
+                                            ])),
+                                            HtmlStringContents::String(" hello=".to_string()),
+                                            HtmlStringContents::Tokens(all_empty(vec![
+                                                left_brace(),
+                                                Token::ConstInt(123),
+                                                right_brace(),
+                                            ])),
+                                            HtmlStringContents::String("></span>".to_string()),
+                                        ]
+                                        .into(),
+                                    ),
+                                    Token::Ident("tle".into()),
                                     right_brace(),
                                 ])),
-                                HtmlStringContents::String("></span>".to_string()),
-                            ]),
-                            Token::Ident("tle".to_string()),
-                            right_brace(),
-                        ])),
-                        HtmlStringContents::String(
-                            " <h1> hallo moin  123</h1> abc </>".to_string(),
+                                HtmlStringContents::String(
+                                    " <h1> hallo moin  123</h1> abc </>".to_string(),
+                                ),
+                            ]
+                            .into(),
                         ),
-                    ]),
-                    ctrl(';'),
-                    right_brace(),
-                ]))],
+                        ctrl(';'),
+                        right_brace(),
+                    ])
+                    .into(),
+                )],
             ),
             (
                 "duckx {let hello = {1};}",
-                vec![Token::InlineDuckx(all_empty(vec![
-                    left_brace(),
-                    Token::Let,
-                    Token::Ident("hello".to_string()),
-                    ctrl('='),
-                    left_brace(),
-                    Token::ConstInt(1),
-                    right_brace(),
-                    ctrl(';'),
-                    right_brace(),
-                ]))],
+                vec![Token::InlineDuckx(
+                    all_empty(vec![
+                        left_brace(),
+                        Token::Let,
+                        Token::Ident("hello".into()),
+                        ctrl('='),
+                        left_brace(),
+                        Token::ConstInt(1),
+                        right_brace(),
+                        ctrl(';'),
+                        right_brace(),
+                    ])
+                    .into(),
+                )],
             ),
             (
                 "f\"{{{1}}}\"",
-                vec![Token::FormatStringLiteral(vec![FmtStringContents::Tokens(
-                    vec![
-                        (Token::ControlChar('{'), empty_range()),
-                        (Token::ControlChar('{'), empty_range()),
-                        (Token::ConstInt(1), empty_range()),
-                        (Token::ControlChar('}'), empty_range()),
-                        (Token::ControlChar('}'), empty_range()),
-                    ],
-                )])],
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![
+                            (Token::ControlChar('{'), empty_range()),
+                            (Token::ControlChar('{'), empty_range()),
+                            (Token::ConstInt(1), empty_range()),
+                            (Token::ControlChar('}'), empty_range()),
+                            (Token::ControlChar('}'), empty_range()),
+                        ],
+                        None,
+                    )]
+                    .into(),
+                )],
             ),
             (
                 "f\"{{{-1}}}\"",
-                vec![Token::FormatStringLiteral(vec![FmtStringContents::Tokens(
-                    vec![
-                        (Token::ControlChar('{'), empty_range()),
-                        (Token::ControlChar('{'), empty_range()),
-                        (Token::ConstInt(-1), empty_range()),
-                        (Token::ControlChar('}'), empty_range()),
-                        (Token::ControlChar('}'), empty_range()),
-                    ],
-                )])],
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![
+                            (Token::ControlChar('{'), empty_range()),
+                            (Token::ControlChar('{'), empty_range()),
+                            (Token::ControlChar('-'), empty_range()),
+                            (Token::ConstInt(1), empty_range()),
+                            (Token::ControlChar('}'), empty_range()),
+                            (Token::ControlChar('}'), empty_range()),
+                        ],
+                        None,
+                    )]
+                    .into(),
+                )],
             ),
             (
                 "type /// hallo ich bin ein dokkommentar",
                 vec![
                     Token::Type,
-                    Token::DocComment("hallo ich bin ein dokkommentar".to_string()),
+                    Token::DocComment("hallo ich bin ein dokkommentar".into()),
                 ],
             ),
             ("component", vec![Token::Component]),
             (
                 "tsx {console.log(\"Hallo, Welt\")}",
-                vec![Token::InlineTsx("console.log(\"Hallo, Welt\")".to_string())],
+                vec![Token::InlineTsx("console.log(\"Hallo, Welt\")".into())],
             ),
             (
                 "tsx {{console.log(\"Hallo, Welt\")}}",
                 vec![Token::InlineTsx(
-                    "{console.log(\"Hallo, Welt\")}".to_string(),
+                    "{console.log(\"Hallo, Welt\")}".to_string().into(),
                 )],
             ),
             (
                 "tsx {<MyComponent name=\"lol\"/>\n<h1>hallo</h1>}",
                 vec![Token::InlineTsx(
-                    "<MyComponent name=\"lol\"/>\n<h1>hallo</h1>".to_string(),
+                    "<MyComponent name=\"lol\"/>\n<h1>hallo</h1>"
+                        .to_string()
+                        .into(),
                 )],
             ),
             (
                 "/// hallo ich bin ein dokkommentar",
                 vec![Token::DocComment(
-                    "hallo ich bin ein dokkommentar".to_string(),
+                    "hallo ich bin ein dokkommentar".to_string().into(),
                 )],
             ),
             (
                 "// hallo ich bin ein kommentar",
-                vec![Token::Comment("hallo ich bin ein kommentar".to_string())],
+                vec![Token::Comment("hallo ich bin ein kommentar".into())],
             ),
-            ("//", vec![Token::Comment("".to_string())]),
-            ("///", vec![Token::DocComment("".to_string())]),
-            ("//    ", vec![Token::Comment("".to_string())]),
-            ("///    ", vec![Token::DocComment("".to_string())]),
+            ("//", vec![Token::Comment("".into())]),
+            ("///", vec![Token::DocComment("".into())]),
+            ("//    ", vec![Token::Comment("".into())]),
+            ("///    ", vec![Token::DocComment("".into())]),
             (
                 "//  leading and trailing whitespace  ",
                 vec![Token::Comment(
-                    "leading and trailing whitespace".to_string(),
+                    "leading and trailing whitespace".to_string().into(),
                 )],
             ),
             (
                 "///  leading and trailing whitespace  ",
                 vec![Token::DocComment(
-                    "leading and trailing whitespace".to_string(),
+                    "leading and trailing whitespace".to_string().into(),
                 )],
             ),
             (
                 "//// this is a doc comment",
-                vec![Token::DocComment("/ this is a doc comment".to_string())],
+                vec![Token::DocComment("/ this is a doc comment".into())],
             ),
             (
                 "// an /// inner doc comment",
-                vec![Token::Comment("an /// inner doc comment".to_string())],
+                vec![Token::Comment("an /// inner doc comment".into())],
             ),
             (
                 "/// a // regular inner comment",
-                vec![Token::DocComment("a // regular inner comment".to_string())],
+                vec![Token::DocComment("a // regular inner comment".into())],
             ),
             (
                 "// a comment with !@#$%^&*()_+-=[]{}|;':\",./<>?",
                 vec![Token::Comment(
-                    "a comment with !@#$%^&*()_+-=[]{}|;':\",./<>?".to_string(),
+                    "a comment with !@#$%^&*()_+-=[]{}|;':\",./<>?"
+                        .to_string()
+                        .into(),
                 )],
             ),
             (
                 "/// a doc comment with !@#$%^&*()_+-=[]{}|;':\",./<>?",
                 vec![Token::DocComment(
-                    "a doc comment with !@#$%^&*()_+-=[]{}|;':\",./<>?".to_string(),
+                    "a doc comment with !@#$%^&*()_+-=[]{}|;':\",./<>?"
+                        .to_string()
+                        .into(),
                 )],
             ),
             (
                 "// Hallo World 🌍",
-                vec![Token::Comment("Hallo World 🌍".to_string())],
+                vec![Token::Comment("Hallo World 🌍".into())],
             ),
             (
                 "/// Hallo World 🌍",
-                vec![Token::DocComment("Hallo World 🌍".to_string())],
+                vec![Token::DocComment("Hallo World 🌍".into())],
             ),
             (
                 "// first line\n// second line",
                 vec![
-                    Token::Comment("first line".to_string()),
-                    Token::Comment("second line".to_string()),
+                    Token::Comment("first line".into()),
+                    Token::Comment("second line".into()),
                 ],
             ),
             (
                 "/// doc line 1\n/// doc line 2\n// regular line 3",
                 vec![
-                    Token::DocComment("doc line 1".to_string()),
-                    Token::DocComment("doc line 2".to_string()),
-                    Token::Comment("regular line 3".to_string()),
+                    Token::DocComment("doc line 1".into()),
+                    Token::DocComment("doc line 2".into()),
+                    Token::Comment("regular line 3".into()),
                 ],
             ),
             (
                 "// a comment with a // nested one",
-                vec![Token::Comment("a comment with a // nested one".to_string())],
+                vec![Token::Comment("a comment with a // nested one".into())],
             ),
             (
                 "/// a doc comment with a // nested regular one",
                 vec![Token::DocComment(
-                    "a doc comment with a // nested regular one".to_string(),
+                    "a doc comment with a // nested regular one"
+                        .to_string()
+                        .into(),
                 )],
             ),
             (
                 "// a comment with a /// nested doc one",
                 vec![Token::Comment(
-                    "a comment with a /// nested doc one".to_string(),
+                    "a comment with a /// nested doc one".to_string().into(),
                 )],
             ),
             (
                 "//\tcomment with a tab before content",
                 vec![Token::Comment(
-                    "comment with a tab before content".to_string(),
+                    "comment with a tab before content".to_string().into(),
                 )],
             ),
             (
                 "///\t doc comment with a tab before content",
                 vec![Token::DocComment(
-                    "doc comment with a tab before content".to_string(),
+                    "doc comment with a tab before content".to_string().into(),
                 )],
             ),
             (
                 "// comment with mixed languages: Привет мир, こんにちは世界",
                 vec![Token::Comment(
-                    "comment with mixed languages: Привет мир, こんにちは世界".to_string(),
+                    "comment with mixed languages: Привет мир, こんにちは世界"
+                        .to_string()
+                        .into(),
                 )],
             ),
             (
                 "//\n///",
+                vec![Token::Comment("".into()), Token::DocComment("".into())],
+            ),
+            ("/* block */", vec![Token::Comment("block".into())]),
+            ("/**/", vec![Token::Comment("".into())]),
+            ("/** doc */", vec![Token::DocComment("doc".into())]),
+            ("/*** not doc */", vec![Token::Comment("** not doc".into())]),
+            (
+                "/**\n * first line\n * second line\n */",
+                vec![Token::DocComment("first line\nsecond line".into())],
+            ),
+            (
+                "/* outer /* inner */ still outer */",
+                vec![Token::Comment("outer /* inner */ still outer".into())],
+            ),
+            (
+                "/* first line\n * second line */",
+                vec![Token::Comment("first line\n * second line".into())],
+            ),
+            (
+                "1 /* a // b */ / 2",
                 vec![
-                    Token::Comment("".to_string()),
-                    Token::DocComment("".to_string()),
+                    Token::ConstInt(1),
+                    Token::Comment("a // b".into()),
+                    Token::ControlChar('/'),
+                    Token::ConstInt(2),
                 ],
             ),
             (
                 "f\"{1}\"",
-                vec![Token::FormatStringLiteral(vec![FmtStringContents::Tokens(
-                    vec![(Token::ConstInt(1), empty_range())],
-                )])],
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![(Token::ConstInt(1), empty_range())],
+                        None,
+                    )]
+                    .into(),
+                )],
             ),
             (
                 "f\"{-1}\"",
-                vec![Token::FormatStringLiteral(vec![FmtStringContents::Tokens(
-                    vec![(Token::ConstInt(-1), empty_range())],
-                )])],
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![
+                            (Token::ControlChar('-'), empty_range()),
+                            (Token::ConstInt(1), empty_range()),
+                        ],
+                        None,
+                    )]
+                    .into(),
+                )],
             ),
             (
                 "type Y = duck {};",
                 vec![
                     Token::Type,
-                    Token::Ident("Y".to_string()),
+                    Token::Ident("Y".into()),
                     Token::ControlChar('='),
                     Token::Duck,
                     Token::ControlChar('{'),
@@ -1060,7 +1680,7 @@ mod tests {
             (
                 "typeY=duck{};",
                 vec![
-                    Token::Ident("typeY".to_string()),
+                    Token::Ident("typeY".into()),
                     Token::ControlChar('='),
                     Token::Duck,
                     Token::ControlChar('{'),
@@ -1072,7 +1692,7 @@ mod tests {
                 "type Y = duck {} & duck {};",
                 vec![
                     Token::Type,
-                    Token::Ident("Y".to_string()),
+                    Token::Ident("Y".into()),
                     Token::ControlChar('='),
                     Token::Duck,
                     Token::ControlChar('{'),
@@ -1097,44 +1717,233 @@ mod tests {
                 "type Y = duck { x: String, y: String };",
                 vec![
                     Token::Type,
-                    Token::Ident("Y".to_string()),
+                    Token::Ident("Y".into()),
                     Token::ControlChar('='),
                     Token::Duck,
                     Token::ControlChar('{'),
-                    Token::Ident("x".to_string()),
+                    Token::Ident("x".into()),
                     Token::ControlChar(':'),
-                    Token::Ident("String".to_string()),
+                    Token::Ident("String".into()),
                     Token::ControlChar(','),
-                    Token::Ident("y".to_string()),
+                    Token::Ident("y".into()),
                     Token::ControlChar(':'),
-                    Token::Ident("String".to_string()),
+                    Token::Ident("String".into()),
                     Token::ControlChar('}'),
                     Token::ControlChar(';'),
                 ],
             ),
             ("()", vec![Token::ControlChar('('), Token::ControlChar(')')]),
             ("->", vec![Token::ThinArrow]),
+            ("|>", vec![Token::Pipe]),
+            ("&&", vec![Token::AndAnd]),
+            ("||", vec![Token::OrOr]),
+            (
+                "& &",
+                vec![Token::ControlChar('&'), Token::ControlChar('&')],
+            ),
+            ("<<", vec![Token::ShiftLeft]),
+            (
+                "ch <- 1",
+                vec![
+                    Token::Ident("ch".into()),
+                    Token::LeftArrow,
+                    Token::ConstInt(1),
+                ],
+            ),
+            ("<-ch", vec![Token::LeftArrow, Token::Ident("ch".into())]),
+            (
+                "a?.b ?? c",
+                vec![
+                    Token::Ident("a".into()),
+                    Token::QuestionDot,
+                    Token::Ident("b".into()),
+                    Token::QuestionQuestion,
+                    Token::Ident("c".into()),
+                ],
+            ),
+            (
+                "Int?",
+                vec![Token::Ident("Int".into()), Token::ControlChar('?')],
+            ),
+            (
+                "0..10",
+                vec![Token::ConstInt(0), Token::DotDot, Token::ConstInt(10)],
+            ),
+            (
+                "a..=b",
+                vec![
+                    Token::Ident("a".into()),
+                    Token::DotDotEquals,
+                    Token::Ident("b".into()),
+                ],
+            ),
+            (
+                "f(...xs)",
+                vec![
+                    Token::Ident("f".into()),
+                    Token::ControlChar('('),
+                    Token::DotDotDot,
+                    Token::Ident("xs".into()),
+                    Token::ControlChar(')'),
+                ],
+            ),
+            (
+                "1.5",
+                vec![
+                    Token::ConstInt(1),
+                    Token::ControlChar('.'),
+                    Token::ConstInt(5),
+                ],
+            ),
+            ("<<=", vec![Token::ShiftLeft, Token::ControlChar('=')]),
+            (">>", vec![Token::ControlChar('>'), Token::ControlChar('>')]),
+            ("^~", vec![Token::ControlChar('^'), Token::ControlChar('~')]),
+            (
+                "a||b|",
+                vec![
+                    Token::Ident("a".into()),
+                    Token::OrOr,
+                    Token::Ident("b".into()),
+                    Token::ControlChar('|'),
+                ],
+            ),
+            (
+                "a |> f",
+                vec![
+                    Token::Ident("a".into()),
+                    Token::Pipe,
+                    Token::Ident("f".into()),
+                ],
+            ),
             ("impl", vec![Token::Impl]),
+            ("trait", vec![Token::Trait]),
+            ("pub", vec![Token::Pub]),
+            ("const", vec![Token::Const]),
+            ("defer", vec![Token::Defer]),
+            ("is", vec![Token::Is]),
+            ("select", vec![Token::Select]),
+            ("recover", vec![Token::Recover]),
             ("fn", vec![Token::Function]),
-            ("\"\"", vec![Token::ConstString(String::from(""))]),
-            ("\"XX\"", vec![Token::ConstString(String::from("XX"))]),
-            ("\"X\\\"X\"", vec![Token::ConstString(String::from("X\"X"))]),
+            ("\"\"", vec![Token::ConstString("".into())]),
+            ("\"XX\"", vec![Token::ConstString("XX".into())]),
+            ("\"X\\\"X\"", vec![Token::ConstString("X\"X".into())]),
             (
                 "\"Hallo ich bin ein String\\n\\n\\nNeue Zeile\"",
-                vec![Token::ConstString(String::from(
-                    "Hallo ich bin ein String\n\n\nNeue Zeile",
-                ))],
+                vec![Token::ConstString(
+                    String::from("Hallo ich bin ein String\n\n\nNeue Zeile").into(),
+                )],
             ),
             ("1", vec![Token::ConstInt(1)]),
-            ("-1", vec![Token::ConstInt(-1)]),
+            ("-1", vec![Token::ControlChar('-'), Token::ConstInt(1)]),
             ("2003", vec![Token::ConstInt(2003)]),
-            ("-2003", vec![Token::ConstInt(-2003)]),
+            (
+                "-2003",
+                vec![Token::ControlChar('-'), Token::ConstInt(2003)],
+            ),
+            ("0xFF", vec![Token::ConstInt(255)]),
+            ("'\\u{41}'", vec![Token::CharLiteral('A')]),
+            ("'\\0'", vec![Token::CharLiteral('\0')]),
+            ("'\\x41'", vec![Token::CharLiteral('A')]),
+            ("'\\\"'", vec![Token::CharLiteral('"')]),
+            (
+                "\"a\\r\\n\\0\\x7F\"",
+                vec![Token::ConstString("a\r\n\0\x7F".into())],
+            ),
+            (
+                "f\"\\r\\x41{x}\"",
+                vec![Token::FormatStringLiteral(
+                    vec![
+                        FmtStringContents::String("\rA".into()),
+                        FmtStringContents::Tokens(
+                            vec![(Token::Ident("x".into()), empty_range())],
+                            None,
+                        ),
+                    ]
+                    .into(),
+                )],
+            ),
+            ("'\\u{1F600}'", vec![Token::CharLiteral('😀')]),
+            ("\"a\\u{e4}b\"", vec![Token::ConstString("aäb".into())]),
+            (
+                "f\"\\u{2764} {x}\"",
+                vec![Token::FormatStringLiteral(
+                    vec![
+                        FmtStringContents::String("❤ ".into()),
+                        FmtStringContents::Tokens(
+                            vec![(Token::Ident("x".into()), empty_range())],
+                            None,
+                        ),
+                    ]
+                    .into(),
+                )],
+            ),
+            ("\"\"\"\"\"\"", vec![Token::ConstString("".into())]),
+            (
+                "\"\"\"a \"quoted\" \\n word\"\"\"",
+                vec![Token::ConstString("a \"quoted\" \\n word".into())],
+            ),
+            (
+                "\"\"\"\n    SELECT *\n\n      FROM t\n    \"\"\"",
+                vec![Token::ConstString("SELECT *\n\n  FROM t".into())],
+            ),
+            (
+                "\"\"\"first\n  second\"\"\" x",
+                vec![
+                    Token::ConstString("first\n  second".into()),
+                    Token::Ident("x".into()),
+                ],
+            ),
+            ("0o17", vec![Token::ConstInt(15)]),
+            ("0b1010", vec![Token::ConstInt(10)]),
+            ("-0x10", vec![Token::ControlChar('-'), Token::ConstInt(16)]),
+            ("0x7FFFFFFFFFFFFFFF", vec![Token::ConstInt(i64::MAX as u64)]),
+            ("9223372036854775808", vec![Token::ConstInt(1 << 63)]),
+            ("0xFFFFFFFFFFFFFFFF", vec![Token::ConstInt(u64::MAX)]),
+            ("18446744073709551615", vec![Token::ConstInt(u64::MAX)]),
+            (
+                "0b1 | 0b10",
+                vec![
+                    Token::ConstInt(1),
+                    Token::ControlChar('|'),
+                    Token::ConstInt(2),
+                ],
+            ),
             ("true", vec![Token::ConstBool(true)]),
             ("false", vec![Token::ConstBool(false)]),
-            ("go { {} }", vec![Token::InlineGo(String::from(" {} "))]),
-            ("go { xx }", vec![Token::InlineGo(String::from(" xx "))]),
-            ("go {}", vec![Token::InlineGo(String::from(""))]),
-            ("go {{}{}{}}", vec![Token::InlineGo(String::from("{}{}{}"))]),
+            ("trueish", vec![Token::Ident("trueish".into())]),
+            ("android", vec![Token::Ident("android".into())]),
+            ("order", vec![Token::Ident("order".into())]),
+            ("go { {} }", vec![Token::InlineGo(" {} ".into())]),
+            ("go { xx }", vec![Token::InlineGo(" xx ".into())]),
+            ("go {}", vec![Token::InlineGo("".into())]),
+            (
+                "go worker()",
+                vec![
+                    Token::Go,
+                    Token::Ident("worker".into()),
+                    Token::ControlChar('('),
+                    Token::ControlChar(')'),
+                ],
+            ),
+            ("go {{}{}{}}", vec![Token::InlineGo("{}{}{}".into())]),
+            (
+                "go { s := \"}\\\"{\" }",
+                vec![Token::InlineGo(" s := \"}\\\"{\" ".into())],
+            ),
+            (
+                "go { r := '}' }",
+                vec![Token::InlineGo(" r := '}' ".into())],
+            ),
+            (
+                "go { s := `{\\` }",
+                vec![Token::InlineGo(" s := `{\\` ".into())],
+            ),
+            (
+                "go { x := 1 // {\n y := x / 2 /* } */ }",
+                vec![Token::InlineGo(
+                    String::from(" x := 1 // {\n y := x / 2 /* } */ ").into(),
+                )],
+            ),
             (
                 "if (true) {}",
                 vec![
@@ -1196,7 +2005,7 @@ mod tests {
                 "let x: {};",
                 vec![
                     Token::Let,
-                    Token::Ident("x".to_string()),
+                    Token::Ident("x".into()),
                     Token::ControlChar(':'),
                     Token::ControlChar('{'),
                     Token::ControlChar('}'),
@@ -1205,30 +2014,36 @@ mod tests {
             ),
             (
                 "f\"FMT {var}\"",
-                vec![Token::FormatStringLiteral(vec![
-                    FmtStringContents::String("FMT ".into()),
-                    FmtStringContents::Tokens(vec![(Token::Ident("var".into()), empty_range())]),
-                ])],
+                vec![Token::FormatStringLiteral(
+                    vec![
+                        FmtStringContents::String("FMT ".into()),
+                        FmtStringContents::Tokens(
+                            vec![(Token::Ident("var".into()), empty_range())],
+                            None,
+                        ),
+                    ]
+                    .into(),
+                )],
             ),
             (
                 // Adjacent strings and f-strings to test greedy tokenizing.
                 "\"a\"f\"b\"'c'",
                 vec![
-                    Token::ConstString("a".to_string()),
-                    Token::FormatStringLiteral(vec![FmtStringContents::String("b".into())]),
+                    Token::ConstString("a".into()),
+                    Token::FormatStringLiteral(vec![FmtStringContents::String("b".into())].into()),
                     Token::CharLiteral('c'),
                 ],
             ),
             (
                 "123testing",
-                vec![Token::ConstInt(123), Token::Ident("testing".to_string())],
+                vec![Token::ConstInt(123), Token::Ident("testing".into())],
             ),
-            ("ifelse", vec![Token::Ident("ifelse".to_string())]),
+            ("ifelse", vec![Token::Ident("ifelse".into())]),
             (
                 "let π = 3;",
                 vec![
                     Token::Let,
-                    Token::Ident("π".to_string()),
+                    Token::Ident("π".into()),
                     Token::ControlChar('='),
                     Token::ConstInt(3),
                     Token::ControlChar(';'),
@@ -1239,9 +2054,10 @@ mod tests {
                 "let π = -3;",
                 vec![
                     Token::Let,
-                    Token::Ident("π".to_string()),
+                    Token::Ident("π".into()),
                     Token::ControlChar('='),
-                    Token::ConstInt(-3),
+                    Token::ControlChar('-'),
+                    Token::ConstInt(3),
                     Token::ControlChar(';'),
                 ],
             ),
@@ -1258,9 +2074,11 @@ mod tests {
                 // todo: divide token
                 "-5 / -2",
                 vec![
-                    Token::ConstInt(-5),
+                    Token::ControlChar('-'),
+                    Token::ConstInt(5),
                     Token::ControlChar('/'),
-                    Token::ConstInt(-2),
+                    Token::ControlChar('-'),
+                    Token::ConstInt(2),
                 ],
             ),
             (
@@ -1268,7 +2086,7 @@ mod tests {
                 vec![
                     Token::Function,
                     Token::ControlChar('('),
-                    Token::Ident("x".to_string()),
+                    Token::Ident("x".into()),
                     Token::ControlChar(')'),
                     Token::ControlChar('{'),
                     Token::If,
@@ -1287,16 +2105,18 @@ mod tests {
                 vec![
                     Token::Function,
                     Token::ControlChar('('),
-                    Token::Ident("x".to_string()),
+                    Token::Ident("x".into()),
                     Token::ControlChar(')'),
                     Token::ControlChar('{'),
                     Token::If,
                     Token::ControlChar('('),
                     Token::ConstBool(true),
                     Token::ControlChar(')'),
-                    Token::ConstInt(-1),
+                    Token::ControlChar('-'),
+                    Token::ConstInt(1),
                     Token::Else,
-                    Token::ConstInt(-0),
+                    Token::ControlChar('-'),
+                    Token::ConstInt(0),
                     Token::ControlChar(';'),
                     Token::ControlChar('}'),
                 ],
@@ -1307,92 +2127,193 @@ mod tests {
                     Token::ConstInt(1),
                     Token::ControlChar('.'),
                     Token::ConstInt(0),
-                    Token::Comment("a float-like thing".to_string()),
+                    Token::Comment("a float-like thing".into()),
                 ],
             ),
             (
                 "-1.0// a float-like thing",
                 vec![
-                    Token::ConstInt(-1),
+                    Token::ControlChar('-'),
+                    Token::ConstInt(1),
                     Token::ControlChar('.'),
                     Token::ConstInt(0),
-                    Token::Comment("a float-like thing".to_string()),
+                    Token::Comment("a float-like thing".into()),
                 ],
             ),
             (
                 "f\" outer {\"inner\"} outer \"",
-                vec![Token::FormatStringLiteral(vec![
-                    FmtStringContents::String(" outer ".into()),
-                    FmtStringContents::Tokens(vec![(
-                        Token::ConstString("inner".to_string()),
-                        empty_range(),
-                    )]),
-                    FmtStringContents::String(" outer ".into()),
-                ])],
+                vec![Token::FormatStringLiteral(
+                    vec![
+                        FmtStringContents::String(" outer ".into()),
+                        FmtStringContents::Tokens(
+                            vec![(Token::ConstString("inner".into()), empty_range())],
+                            None,
+                        ),
+                        FmtStringContents::String(" outer ".into()),
+                    ]
+                    .into(),
+                )],
             ),
-            ("f\"\"", vec![Token::FormatStringLiteral(vec![])]),
+            ("f\"\"", vec![Token::FormatStringLiteral(vec![].into())]),
             (
                 "f\"{}\"",
-                vec![Token::FormatStringLiteral(vec![FmtStringContents::Tokens(
-                    vec![],
-                )])],
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(vec![], None)].into(),
+                )],
             ),
             (
                 "f\"result is {calc(1, 2)}\"",
-                vec![Token::FormatStringLiteral(vec![
-                    FmtStringContents::String("result is ".into()),
-                    FmtStringContents::Tokens(vec![
-                        (Token::Ident("calc".to_string()), empty_range()),
-                        (Token::ControlChar('('), empty_range()),
-                        (Token::ConstInt(1), empty_range()),
-                        (Token::ControlChar(','), empty_range()),
-                        (Token::ConstInt(2), empty_range()),
-                        (Token::ControlChar(')'), empty_range()),
-                    ]),
-                ])],
+                vec![Token::FormatStringLiteral(
+                    vec![
+                        FmtStringContents::String("result is ".into()),
+                        FmtStringContents::Tokens(
+                            vec![
+                                (Token::Ident("calc".into()), empty_range()),
+                                (Token::ControlChar('('), empty_range()),
+                                (Token::ConstInt(1), empty_range()),
+                                (Token::ControlChar(','), empty_range()),
+                                (Token::ConstInt(2), empty_range()),
+                                (Token::ControlChar(')'), empty_range()),
+                            ],
+                            None,
+                        ),
+                    ]
+                    .into(),
+                )],
             ),
             (
                 "f\"outer {f\"inner {y}\"} end\"",
-                vec![Token::FormatStringLiteral(vec![
-                    FmtStringContents::String("outer ".into()),
-                    FmtStringContents::Tokens(vec![(
-                        Token::FormatStringLiteral(vec![
-                            FmtStringContents::String("inner ".into()),
-                            FmtStringContents::Tokens(vec![(
-                                Token::Ident("y".to_string()),
+                vec![Token::FormatStringLiteral(
+                    vec![
+                        FmtStringContents::String("outer ".into()),
+                        FmtStringContents::Tokens(
+                            vec![(
+                                Token::FormatStringLiteral(
+                                    vec![
+                                        FmtStringContents::String("inner ".into()),
+                                        FmtStringContents::Tokens(
+                                            vec![(Token::Ident("y".into()), empty_range())],
+                                            None,
+                                        ),
+                                    ]
+                                    .into(),
+                                ),
                                 empty_range(),
-                            )]),
-                        ]),
-                        empty_range(),
-                    )]),
-                    FmtStringContents::String(" end".into()),
-                ])],
+                            )],
+                            None,
+                        ),
+                        FmtStringContents::String(" end".into()),
+                    ]
+                    .into(),
+                )],
             ),
             (
                 "f\"{1+1}\"",
-                vec![Token::FormatStringLiteral(vec![FmtStringContents::Tokens(
-                    vec![
-                        (Token::ConstInt(1), empty_range()),
-                        (Token::ControlChar('+'), empty_range()),
-                        (Token::ConstInt(1), empty_range()),
-                    ],
-                )])],
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![
+                            (Token::ConstInt(1), empty_range()),
+                            (Token::ControlChar('+'), empty_range()),
+                            (Token::ConstInt(1), empty_range()),
+                        ],
+                        None,
+                    )]
+                    .into(),
+                )],
             ),
             (
                 "f\"{1+1}a\"",
-                vec![Token::FormatStringLiteral(vec![
-                    FmtStringContents::Tokens(vec![
-                        (Token::ConstInt(1), empty_range()),
-                        (Token::ControlChar('+'), empty_range()),
-                        (Token::ConstInt(1), empty_range()),
-                    ]),
-                    FmtStringContents::String("a".into()),
-                ])],
+                vec![Token::FormatStringLiteral(
+                    vec![
+                        FmtStringContents::Tokens(
+                            vec![
+                                (Token::ConstInt(1), empty_range()),
+                                (Token::ControlChar('+'), empty_range()),
+                                (Token::ConstInt(1), empty_range()),
+                            ],
+                            None,
+                        ),
+                        FmtStringContents::String("a".into()),
+                    ]
+                    .into(),
+                )],
+            ),
+            (
+                "f\"{x:.2}\"",
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![(Token::Ident("x".into()), empty_range())],
+                        Some(".2".into()),
+                    )]
+                    .into(),
+                )],
+            ),
+            (
+                "f\"{n :04x}\"",
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![(Token::Ident("n".into()), empty_range())],
+                        Some("04x".into()),
+                    )]
+                    .into(),
+                )],
+            ),
+            (
+                "f\"{Foo::bar}\"",
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![
+                            (Token::Ident("Foo".into()), empty_range()),
+                            (Token::ScopeRes, empty_range()),
+                            (Token::Ident("bar".into()), empty_range()),
+                        ],
+                        None,
+                    )]
+                    .into(),
+                )],
+            ),
+            (
+                "f\"\\{a\\}\"",
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::String("{a}".into())].into(),
+                )],
+            ),
+            (
+                "f\"{get(\\\"}\\\")}\"",
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![
+                            (Token::Ident("get".into()), empty_range()),
+                            (Token::ControlChar('('), empty_range()),
+                            (Token::ConstString("}".into()), empty_range()),
+                            (Token::ControlChar(')'), empty_range()),
+                        ],
+                        None,
+                    )]
+                    .into(),
+                )],
+            ),
+            (
+                "f\"{get(\"{\", '}')}\"",
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![
+                            (Token::Ident("get".into()), empty_range()),
+                            (Token::ControlChar('('), empty_range()),
+                            (Token::ConstString("{".into()), empty_range()),
+                            (Token::ControlChar(','), empty_range()),
+                            (Token::CharLiteral('}'), empty_range()),
+                            (Token::ControlChar(')'), empty_range()),
+                        ],
+                        None,
+                    )]
+                    .into(),
+                )],
             ),
         ];
 
         for (src, expected_tokens) in test_cases {
-            let parse_result = lex_parser("test", src).parse(src);
+            let parse_result = lex_parser(FileId::EMPTY).parse(src);
 
             assert_eq!(parse_result.has_errors(), false, "{}", src);
             assert_eq!(parse_result.has_output(), true, "{}", src);
@@ -1412,20 +2333,244 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_lex_int_literal_errors() {
+        let test_cases = vec![
+            (
+                "0x10000000000000000",
+                "integer literal too large, 0x10000000000000000 is outside of 0..=18446744073709551615",
+            ),
+            (
+                "99999999999999999999",
+                "integer literal too large, 99999999999999999999 is outside of \
+                 0..=18446744073709551615",
+            ),
+            ("0b", "invalid integer literal 0b"),
+            ("0xFG", "invalid integer literal 0xFG"),
+            ("0o78", "invalid integer literal 0o78"),
+        ];
+
+        for (src, expected_reason) in test_cases {
+            let errors = lex_parser(FileId::EMPTY).parse(src).into_errors();
+            assert_eq!(
+                errors
+                    .iter()
+                    .map(|e| e.reason().to_string())
+                    .collect::<Vec<_>>(),
+                vec![expected_reason],
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_lex_invalid_escapes() {
+        let test_cases = vec![
+            ("\"\\q\"", "unknown escape sequence \\q"),
+            ("'\\{'", "unknown escape sequence \\{"),
+            ("\"\\{\"", "unknown escape sequence \\{"),
+            ("f\"\\q\"", "unknown escape sequence \\q"),
+            (
+                "\"\\x80\"",
+                "invalid hex escape \\x80, expected a value up to \\x7F",
+            ),
+            (
+                "'\\x4'",
+                "invalid hex escape \\x4, expected a value up to \\x7F",
+            ),
+            (
+                "\"\\xZZ\"",
+                "invalid hex escape \\xZZ, expected a value up to \\x7F",
+            ),
+        ];
+
+        for (src, expected_reason) in test_cases {
+            let errors = lex_parser(FileId::EMPTY).parse(src).into_errors();
+            assert_eq!(
+                errors
+                    .iter()
+                    .map(|e| e.reason().to_string())
+                    .collect::<Vec<_>>(),
+                vec![expected_reason],
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_map_go_interpolations() {
+        let test_cases = vec![
+            ("fmt.Println(${x})", "fmt.Println(X)"),
+            ("${a} + ${b_1}", "A + B_1"),
+            ("s := \"${x}\" // ${x}", "s := \"${x}\" // ${x}"),
+            ("s := `${x}` /* ${x} */", "s := `${x}` /* ${x} */"),
+            ("$x ${ x }", "$x ${ x }"),
+        ];
+
+        for (src, expected) in test_cases {
+            assert_eq!(
+                map_go_interpolations(src, |name| name.to_uppercase()),
+                expected,
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_escape_go_idents() {
+        let test_cases = vec![
+            ("größe := 1", "größe := 1"),
+            ("e\u{301} := 1", "e\u{2E2F}301\u{2E2F} := 1"),
+            (
+                "a\u{203F}b.x\u{1369}",
+                "a\u{2E2F}203f\u{2E2F}b.x\u{2E2F}1369\u{2E2F}",
+            ),
+            ("zahlⅫ(x٣)", "zahl\u{2E2F}216b\u{2E2F}(x٣)"),
+            ("panic(\"zahlⅫ\") // zahlⅫ", "panic(\"zahlⅫ\") // zahlⅫ"),
+            ("s := `zahlⅫ` + 'Ⅻ'", "s := `zahlⅫ` + 'Ⅻ'"),
+        ];
+
+        for (src, expected) in test_cases {
+            assert_eq!(escape_go_idents(src), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_lex_shebang() {
+        let src = "#!/usr/bin/env duck run\nlet x";
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|(token, span)| (token.clone(), span.start))
+                .collect::<Vec<_>>(),
+            vec![(Token::Let, 24), (Token::Ident("x".into()), 28)]
+        );
+
+        // only the first line is a shebang, and #![...] isn't one
+        for src in ["let x\n#!/bin/duck", "#![cfg]"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            assert!(
+                tokens
+                    .iter()
+                    .any(|(token, _)| *token == Token::ControlChar('#')),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_lex_recovers_from_invalid_chars() {
+        let test_cases = vec![
+            (
+                "let § x",
+                vec![Token::Let, Token::Error, Token::Ident("x".into())],
+                1,
+            ),
+            (
+                "f(1, ¶¶a) ¤",
+                vec![
+                    Token::Ident("f".into()),
+                    ctrl('('),
+                    Token::ConstInt(1),
+                    ctrl(','),
+                    Token::Error,
+                    ctrl(')'),
+                    Token::Error,
+                ],
+                2,
+            ),
+            ("\"open x", vec![Token::Error, Token::Ident("x".into())], 1),
+        ];
+
+        for (src, expected_tokens, expected_errors) in test_cases {
+            let (tokens, errors) = lex_parser(FileId::EMPTY).parse(src).into_output_errors();
+            let tokens = tokens
+                .unwrap()
+                .into_iter()
+                .map(|(t, _)| t)
+                .collect::<Vec<_>>();
+
+            assert_eq!(tokens, expected_tokens, "{src}");
+            assert_eq!(errors.len(), expected_errors, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_lex_invalid_unicode_escapes() {
+        for src in [
+            "'\\u{D800}'",
+            "\"\\u{110000}\"",
+            "\"\\u{zz}\"",
+            "f\"\\u{0000041}\"",
+        ] {
+            let errors = lex_parser(FileId::EMPTY).parse(src).into_errors();
+            assert_eq!(errors.len(), 1, "{src}");
+            assert!(
+                errors[0]
+                    .reason()
+                    .to_string()
+                    .starts_with("invalid unicode escape"),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_lex_spans_after_comments() {
+        let src = "/* a */ let // x\n/* /* b */ */ x";
+        let spans = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .unwrap()
+            .into_iter()
+            .map(|(token, span)| (token, span.start..span.end))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            spans,
+            vec![
+                (Token::Comment("a".into()), 0..7),
+                (Token::Let, 8..11),
+                (Token::Comment("x".into()), 12..16),
+                (Token::Comment("/* b */".into()), 17..30),
+                (Token::Ident("x".into()), 31..32),
+            ]
+        );
+
+        for unterminated in ["/* a", "/* a /* b */"] {
+            assert!(
+                lex_parser(FileId::EMPTY).parse(unterminated).has_errors(),
+                "{unterminated}"
+            );
+        }
+    }
+
     #[test]
     fn test_lex_comment_filtered() {
         let test_cases = vec![
             (
                 "f\"{{{1}}}\" // check",
-                vec![Token::FormatStringLiteral(vec![FmtStringContents::Tokens(
-                    vec![
-                        (Token::ControlChar('{'), empty_range()),
-                        (Token::ControlChar('{'), empty_range()),
-                        (Token::ConstInt(1), empty_range()),
-                        (Token::ControlChar('}'), empty_range()),
-                        (Token::ControlChar('}'), empty_range()),
-                    ],
-                )])],
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![
+                            (Token::ControlChar('{'), empty_range()),
+                            (Token::ControlChar('{'), empty_range()),
+                            (Token::ConstInt(1), empty_range()),
+                            (Token::ControlChar('}'), empty_range()),
+                            (Token::ControlChar('}'), empty_range()),
+                        ],
+                        None,
+                    )]
+                    .into(),
+                )],
             ),
             ("type /// hallo ich bin ein dokkommentar", vec![Token::Type]),
             ("/// hallo ich bin ein dokkommentar", vec![]),
@@ -1458,17 +2603,22 @@ mod tests {
                 vec![],
             ),
             ("//\n///", vec![]),
+            ("/* a */ type /* b /* c */ */", vec![Token::Type]),
             (
                 "f\"{1}\" // check",
-                vec![Token::FormatStringLiteral(vec![FmtStringContents::Tokens(
-                    vec![(Token::ConstInt(1), empty_range())],
-                )])],
+                vec![Token::FormatStringLiteral(
+                    vec![FmtStringContents::Tokens(
+                        vec![(Token::ConstInt(1), empty_range())],
+                        None,
+                    )]
+                    .into(),
+                )],
             ),
             (
                 "type Y = duck {}; // check",
                 vec![
                     Token::Type,
-                    Token::Ident("Y".to_string()),
+                    Token::Ident("Y".into()),
                     Token::ControlChar('='),
                     Token::Duck,
                     Token::ControlChar('{'),
@@ -1479,7 +2629,7 @@ mod tests {
             (
                 "typeY=duck{}; // check",
                 vec![
-                    Token::Ident("typeY".to_string()),
+                    Token::Ident("typeY".into()),
                     Token::ControlChar('='),
                     Token::Duck,
                     Token::ControlChar('{'),
@@ -1491,7 +2641,7 @@ mod tests {
                 "type Y = duck {} & duck {}; // check",
                 vec![
                     Token::Type,
-                    Token::Ident("Y".to_string()),
+                    Token::Ident("Y".into()),
                     Token::ControlChar('='),
                     Token::Duck,
                     Token::ControlChar('{'),
@@ -1507,17 +2657,17 @@ mod tests {
                 "type Y = duck { x: String, y: String }; // check",
                 vec![
                     Token::Type,
-                    Token::Ident("Y".to_string()),
+                    Token::Ident("Y".into()),
                     Token::ControlChar('='),
                     Token::Duck,
                     Token::ControlChar('{'),
-                    Token::Ident("x".to_string()),
+                    Token::Ident("x".into()),
                     Token::ControlChar(':'),
-                    Token::Ident("String".to_string()),
+                    Token::Ident("String".into()),
                     Token::ControlChar(','),
-                    Token::Ident("y".to_string()),
+                    Token::Ident("y".into()),
                     Token::ControlChar(':'),
-                    Token::Ident("String".to_string()),
+                    Token::Ident("String".into()),
                     Token::ControlChar('}'),
                     Token::ControlChar(';'),
                 ],
@@ -1528,37 +2678,28 @@ mod tests {
             ),
             ("-> // check", vec![Token::ThinArrow]),
             ("fn // check", vec![Token::Function]),
-            ("\"\" // check", vec![Token::ConstString(String::from(""))]),
-            (
-                "\"XX\" // check",
-                vec![Token::ConstString(String::from("XX"))],
-            ),
+            ("\"\" // check", vec![Token::ConstString("".into())]),
+            ("\"XX\" // check", vec![Token::ConstString("XX".into())]),
             (
                 "\"X\\\"X\" // check",
-                vec![Token::ConstString(String::from("X\"X"))],
+                vec![Token::ConstString("X\"X".into())],
             ),
             (
                 "\"Hallo ich bin ein String\\n\\n\\nNeue Zeile\" // check",
-                vec![Token::ConstString(String::from(
-                    "Hallo ich bin ein String\n\n\nNeue Zeile",
-                ))],
+                vec![Token::ConstString(
+                    String::from("Hallo ich bin ein String\n\n\nNeue Zeile").into(),
+                )],
             ),
             ("1 // check", vec![Token::ConstInt(1)]),
             ("2003 // check", vec![Token::ConstInt(2003)]),
             ("true // check", vec![Token::ConstBool(true)]),
             ("false // check", vec![Token::ConstBool(false)]),
-            (
-                "go { {} } // check",
-                vec![Token::InlineGo(String::from(" {} "))],
-            ),
-            (
-                "go { xx } // check",
-                vec![Token::InlineGo(String::from(" xx "))],
-            ),
-            ("go {} // check", vec![Token::InlineGo(String::from(""))]),
+            ("go { {} } // check", vec![Token::InlineGo(" {} ".into())]),
+            ("go { xx } // check", vec![Token::InlineGo(" xx ".into())]),
+            ("go {} // check", vec![Token::InlineGo("".into())]),
             (
                 "go {{}{}{}} // check",
-                vec![Token::InlineGo(String::from("{}{}{}"))],
+                vec![Token::InlineGo("{}{}{}".into())],
             ),
             (
                 "if (true) {} // check",
@@ -1621,7 +2762,7 @@ mod tests {
                 "let x: {}; // with comment",
                 vec![
                     Token::Let,
-                    Token::Ident("x".to_string()),
+                    Token::Ident("x".into()),
                     Token::ControlChar(':'),
                     Token::ControlChar('{'),
                     Token::ControlChar('}'),
@@ -1630,17 +2771,58 @@ mod tests {
             ),
             (
                 "f\"FMT {var}\" // check",
-                vec![Token::FormatStringLiteral(vec![
-                    FmtStringContents::String("FMT ".into()),
-                    FmtStringContents::Tokens(vec![(Token::Ident("var".into()), empty_range())]),
-                ])],
+                vec![Token::FormatStringLiteral(
+                    vec![
+                        FmtStringContents::String("FMT ".into()),
+                        FmtStringContents::Tokens(
+                            vec![(Token::Ident("var".into()), empty_range())],
+                            None,
+                        ),
+                    ]
+                    .into(),
+                )],
             ),
             ("sus", vec![Token::Sus]),
             ("sus fn", vec![Token::Sus, Token::Function]),
+            (
+                "for x in xs",
+                vec![
+                    Token::For,
+                    Token::Ident("x".into()),
+                    Token::In,
+                    Token::Ident("xs".into()),
+                ],
+            ),
+            (
+                "loop { break 1; }",
+                vec![
+                    Token::Loop,
+                    Token::ControlChar('{'),
+                    Token::Break,
+                    Token::ConstInt(1),
+                    Token::ControlChar(';'),
+                    Token::ControlChar('}'),
+                ],
+            ),
+            (
+                "enum Shape",
+                vec![Token::Enum, Token::Ident("Shape".into())],
+            ),
+            ("let größe", vec![Token::Let, Token::Ident("größe".into())]),
+            ("_變數1", vec![Token::Ident("_變數1".into())]),
+            ("e\u{301}", vec![Token::Ident("e\u{301}".into())]),
+            ("a\u{203F}b", vec![Token::Ident("a\u{203F}b".into())]),
+            ("e_u301_", vec![Token::Ident("e_u301_".into())]),
+            ("zahlⅫ", vec![Token::Ident("zahlⅫ".into())]),
+            ("x\u{1369}", vec![Token::Ident("x\u{1369}".into())]),
+            ("x٣", vec![Token::Ident("x٣".into())]),
         ];
 
         for (src, expected_tokens) in test_cases {
-            let parse_result = lex("test", src);
+            let parse_result = lex(FileId::EMPTY, src);
+            let (eof, parse_result) = parse_result.split_last().expect(src);
+            assert_eq!(eof.0, Token::Eof, "{}", src);
+            assert_eq!(eof.1.start, src.len() as u32, "{}", src);
 
             let output: Vec<Token> = parse_result
                 .iter()
@@ -0,0 +1,800 @@
+use std::collections::HashMap;
+
+use chumsky::error::Rich;
+
+use crate::parse::{
+    SS, Spanned,
+    lexer::{FmtStringContents, GO_IDENT_ESCAPE, Token},
+    symbol::Symbol,
+};
+
+pub type MacroError = Rich<'static, Token, SS>;
+
+// a macro that (directly or indirectly) expands to itself more often than this is treated
+// as infinite recursion
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternPart {
+    // $name, everything up to the next comma, semicolon or the token after it in the pattern
+    Param(Symbol),
+    // $( ... ) with an optional separator in front of the *, matches zero or more times
+    Repeat(Vec<PatternPart>, Option<Token>),
+    Token(Token),
+}
+
+// macro name($a, $b) { ... } is a macro with a single rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroRule {
+    pub pattern: Vec<PatternPart>,
+    pub body: Vec<Spanned<Token>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroDefinition {
+    pub name: Symbol,
+    pub rules: Vec<MacroRule>,
+    pub span: SS,
+}
+
+// what a parameter matched, repeated parameters have one fragment per repetition
+#[derive(Debug, Clone, PartialEq)]
+enum Fragment {
+    Tokens(Vec<Spanned<Token>>),
+    Repeated(Vec<Fragment>),
+}
+
+#[derive(Debug, Default)]
+struct MacroExpander {
+    macros: HashMap<Symbol, MacroDefinition>,
+    // every expansion gets its own suffix for the identifiers it introduces
+    expansion_count: usize,
+    errors: Vec<MacroError>,
+}
+
+fn is_ctrl(token: Option<&Spanned<Token>>, c: char) -> bool {
+    matches!(token, Some((Token::ControlChar(x), _)) if *x == c)
+}
+
+// returns the index of the token closing the group that is opened at `start`
+fn find_group_end(tokens: &[Spanned<Token>], start: usize) -> Result<usize, MacroError> {
+    let mut depth = 0;
+    for (i, (token, _)) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::ControlChar('(' | '{' | '[') => depth += 1,
+            Token::ControlChar(')' | '}' | ']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Err(Rich::custom(
+        tokens[start].1,
+        "unclosed delimiter in macro, this is never closed",
+    ))
+}
+
+// the separator and * after the group of a $( ... ), `group_end` is the index of its closing
+// parenthesis. returns the separator and the index after the *
+fn parse_repetition_end(
+    tokens: &[Spanned<Token>],
+    group_end: usize,
+) -> Result<(Option<Token>, usize), MacroError> {
+    if is_ctrl(tokens.get(group_end + 1), '*') {
+        return Ok((None, group_end + 2));
+    }
+
+    match tokens.get(group_end + 1) {
+        Some((separator, _)) if is_ctrl(tokens.get(group_end + 2), '*') => {
+            Ok((Some(separator.clone()), group_end + 3))
+        }
+        _ => Err(Rich::custom(
+            tokens[group_end].1,
+            "expected * after $( ... )",
+        )),
+    }
+}
+
+fn parse_pattern(tokens: &[Spanned<Token>]) -> Result<Vec<PatternPart>, MacroError> {
+    let mut pattern = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match (&tokens[i], tokens.get(i + 1)) {
+            ((Token::ControlChar('$'), _), Some((Token::Ident(param), _))) => {
+                pattern.push(PatternPart::Param(*param));
+                i += 2;
+            }
+            ((Token::ControlChar('$'), _), Some((Token::ControlChar('('), _))) => {
+                let group_end = find_group_end(tokens, i + 1)?;
+                let (separator, next) = parse_repetition_end(tokens, group_end)?;
+                pattern.push(PatternPart::Repeat(
+                    parse_pattern(&tokens[i + 2..group_end])?,
+                    separator,
+                ));
+                i = next;
+            }
+            ((Token::ControlChar('$'), span), _) => {
+                return Err(Rich::custom(
+                    *span,
+                    "expected a name or ( after $ in a macro pattern",
+                ));
+            }
+            ((token, _), _) => {
+                pattern.push(PatternPart::Token(token.clone()));
+                i += 1;
+            }
+        }
+    }
+
+    Ok(pattern)
+}
+
+fn pattern_params(pattern: &[PatternPart]) -> Vec<Symbol> {
+    pattern
+        .iter()
+        .flat_map(|part| match part {
+            PatternPart::Param(param) => vec![*param],
+            PatternPart::Repeat(inner, _) => pattern_params(inner),
+            PatternPart::Token(_) => vec![],
+        })
+        .collect()
+}
+
+// the first token a part has to start with, if it's known
+fn first_token(part: Option<&PatternPart>) -> Option<&Token> {
+    match part? {
+        PatternPart::Token(token) => Some(token),
+        PatternPart::Repeat(inner, _) => first_token(inner.first()),
+        PatternPart::Param(_) => None,
+    }
+}
+
+// the end of the fragment a parameter matches when it starts at `start`
+fn fragment_end(tokens: &[Spanned<Token>], start: usize, stop: Option<&Token>) -> usize {
+    let mut depth = 0;
+    for (i, (token, _)) in tokens.iter().enumerate().skip(start) {
+        if depth == 0
+            && (Some(token) == stop
+                || matches!(token, Token::ControlChar(',' | ';' | ')' | '}' | ']')))
+        {
+            return i;
+        }
+        match token {
+            Token::ControlChar('(' | '{' | '[') => depth += 1,
+            Token::ControlChar(')' | '}' | ']') => depth -= 1,
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+// matches the start of `tokens` against the pattern and returns how many tokens it took.
+// `follow` is the token that comes after the pattern, it ends the last parameter
+fn match_pattern(
+    pattern: &[PatternPart],
+    tokens: &[Spanned<Token>],
+    follow: Option<&Token>,
+    bindings: &mut HashMap<Symbol, Fragment>,
+) -> Option<usize> {
+    let mut pos = 0;
+
+    for (i, part) in pattern.iter().enumerate() {
+        let next =
+            first_token(pattern.get(i + 1)).or(if i + 1 == pattern.len() { follow } else { None });
+
+        match part {
+            PatternPart::Token(token) => {
+                if tokens.get(pos).map(|(t, _)| t) != Some(token) {
+                    return None;
+                }
+                pos += 1;
+            }
+            PatternPart::Param(param) => {
+                let end = fragment_end(tokens, pos, next);
+                if end == pos {
+                    return None;
+                }
+                bindings.insert(*param, Fragment::Tokens(tokens[pos..end].to_vec()));
+                pos = end;
+            }
+            PatternPart::Repeat(inner, separator) => {
+                let inner_follow = separator.as_ref().or(next);
+                let mut repetitions = Vec::new();
+
+                loop {
+                    let start = match separator {
+                        Some(separator) if !repetitions.is_empty() => {
+                            if tokens.get(pos).map(|(t, _)| t) != Some(separator) {
+                                break;
+                            }
+                            pos + 1
+                        }
+                        _ => pos,
+                    };
+
+                    let mut repetition = HashMap::new();
+                    match match_pattern(inner, &tokens[start..], inner_follow, &mut repetition) {
+                        Some(len) if len > 0 => {
+                            repetitions.push(repetition);
+                            pos = start + len;
+                        }
+                        _ => break,
+                    }
+                }
+
+                for param in pattern_params(inner) {
+                    let fragments = repetitions
+                        .iter_mut()
+                        .map(|repetition| repetition.remove(&param).unwrap())
+                        .collect();
+                    bindings.insert(param, Fragment::Repeated(fragments));
+                }
+            }
+        }
+    }
+
+    Some(pos)
+}
+
+// macro name($a, $b) { ... } or macro name { ($a) => { ... }, ($a, $b) => { ... } }
+fn parse_definition(
+    tokens: &[Spanned<Token>],
+    start: usize,
+) -> Result<(MacroDefinition, usize), MacroError> {
+    let macro_span = tokens[start].1;
+    let Some((Token::Ident(name), _)) = tokens.get(start + 1) else {
+        return Err(Rich::custom(macro_span, "expected a name after macro"));
+    };
+
+    let parse_rule = |pattern_start: usize| -> Result<(MacroRule, usize), MacroError> {
+        let pattern_end = find_group_end(tokens, pattern_start)?;
+        // the => between pattern and body is optional, a single rule doesn't need it
+        let body_start = match tokens.get(pattern_end + 1) {
+            Some((Token::ThickArrow, _)) => pattern_end + 2,
+            _ => pattern_end + 1,
+        };
+        if !is_ctrl(tokens.get(body_start), '{') {
+            return Err(Rich::custom(
+                macro_span,
+                format!("expected body for macro {name}"),
+            ));
+        }
+
+        let body_end = find_group_end(tokens, body_start)?;
+        let rule = MacroRule {
+            pattern: parse_pattern(&tokens[pattern_start + 1..pattern_end])?,
+            body: tokens[body_start + 1..body_end].to_vec(),
+        };
+        Ok((rule, body_end + 1))
+    };
+
+    let (rules, next) = if is_ctrl(tokens.get(start + 2), '(') {
+        let (rule, next) = parse_rule(start + 2)?;
+        (vec![rule], next)
+    } else if is_ctrl(tokens.get(start + 2), '{') {
+        let rules_end = find_group_end(tokens, start + 2)?;
+        let mut rules = Vec::new();
+        let mut i = start + 3;
+        while i < rules_end {
+            if !is_ctrl(tokens.get(i), '(') {
+                return Err(Rich::custom(
+                    tokens[i].1,
+                    format!("expected a rule like ($a) => {{ ... }} in macro {name}"),
+                ));
+            }
+            let (rule, next) = parse_rule(i)?;
+            rules.push(rule);
+            i = next;
+            if is_ctrl(tokens.get(i), ',') || is_ctrl(tokens.get(i), ';') {
+                i += 1;
+            }
+        }
+        (rules, rules_end + 1)
+    } else {
+        return Err(Rich::custom(
+            macro_span,
+            format!("expected parameter list or rules for macro {name}"),
+        ));
+    };
+
+    Ok((
+        MacroDefinition {
+            name: *name,
+            rules,
+            span: macro_span,
+        },
+        next,
+    ))
+}
+
+// where a definition that couldn't be parsed ends, its pattern and body or the braces around
+// its rules are skipped, up to the end of the file if they're never closed
+fn skip_definition(tokens: &[Spanned<Token>], start: usize) -> usize {
+    let mut i = start + 2;
+    loop {
+        match tokens.get(i) {
+            Some((Token::ControlChar('('), _)) => {
+                i = find_group_end(tokens, i).map_or(tokens.len(), |end| end + 1);
+            }
+            Some((Token::ThickArrow, _)) => i += 1,
+            Some((Token::ControlChar('{'), _)) => {
+                return find_group_end(tokens, i).map_or(tokens.len(), |end| end + 1);
+            }
+            _ => return i.min(tokens.len()),
+        }
+    }
+}
+
+// the bindings that the template introduces are renamed, so that they can't clash with the
+// variables at the call site. that's let bindings, loop variables and the params of lambdas
+fn introduced_idents(body: &[Spanned<Token>]) -> Vec<Symbol> {
+    let mut introduced = Vec::new();
+
+    for (i, (token, _)) in body.iter().enumerate() {
+        match (token, body.get(i + 1)) {
+            (Token::Let | Token::For, Some((Token::Ident(ident), _))) => introduced.push(*ident),
+            // fn(x: Int) { .. } and fn name(x: Int) { .. }, every ident in front of a : inside
+            // of the parens is a param
+            (Token::Function, _) => {
+                let Some(open) =
+                    (i + 1..body.len().min(i + 3)).find(|j| is_ctrl(body.get(*j), '('))
+                else {
+                    continue;
+                };
+                let Ok(close) = find_group_end(body, open) else {
+                    continue;
+                };
+                for j in open + 1..close {
+                    if let (Token::Ident(ident), _) = &body[j]
+                        && (is_ctrl(body.get(j - 1), '(') || is_ctrl(body.get(j - 1), ','))
+                        && is_ctrl(body.get(j + 1), ':')
+                    {
+                        introduced.push(*ident);
+                    }
+                }
+            }
+            // |x, y| x + y. a | after an operand is part of a union type (or an or) instead
+            (Token::ControlChar('|'), _) if !follows_operand(body, i) => {
+                let params = body[i + 1..]
+                    .iter()
+                    .take_while(|(token, _)| !matches!(token, Token::ControlChar('|')))
+                    .collect::<Vec<_>>();
+                if params
+                    .iter()
+                    .all(|(token, _)| matches!(token, Token::Ident(_) | Token::ControlChar(',')))
+                {
+                    introduced.extend(params.iter().filter_map(|(token, _)| match token {
+                        Token::Ident(ident) => Some(*ident),
+                        _ => None,
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    introduced
+}
+
+// whether the token at `i` comes right after something that can be the left side of an operator
+fn follows_operand(body: &[Spanned<Token>], i: usize) -> bool {
+    i.checked_sub(1)
+        .and_then(|before| body.get(before))
+        .is_some_and(|(token, _)| {
+            !matches!(
+                token,
+                Token::ControlChar('(' | '[' | '{' | ',' | '=' | ';' | ':') | Token::Return
+            )
+        })
+}
+
+fn substitute(
+    body: &[Spanned<Token>],
+    args: &HashMap<Symbol, Fragment>,
+    introduced: &[Symbol],
+    suffix: usize,
+) -> Result<Vec<Spanned<Token>>, MacroError> {
+    let mut out = Vec::new();
+    let mut i = 0;
+
+    while i < body.len() {
+        match (&body[i], body.get(i + 1)) {
+            ((Token::ControlChar('$'), span), Some((Token::Ident(param), _))) => {
+                match args.get(param) {
+                    Some(Fragment::Tokens(arg)) => out.extend(arg.iter().cloned()),
+                    Some(Fragment::Repeated(_)) => {
+                        return Err(Rich::custom(
+                            *span,
+                            format!("macro parameter ${param} repeats, use it inside of $( ... )*"),
+                        ));
+                    }
+                    None => {
+                        return Err(Rich::custom(
+                            *span,
+                            format!("unknown macro parameter ${param}"),
+                        ));
+                    }
+                }
+                i += 2;
+                continue;
+            }
+            ((Token::ControlChar('$'), span), Some((Token::ControlChar('('), _))) => {
+                let group_end = find_group_end(body, i + 1)?;
+                let (separator, next) = parse_repetition_end(body, group_end)?;
+                let inner = &body[i + 2..group_end];
+
+                // every repeated parameter used inside decides how often the group repeats
+                let repeated = inner
+                    .windows(2)
+                    .filter_map(|window| match window {
+                        [(Token::ControlChar('$'), _), (Token::Ident(param), _)] => {
+                            match args.get(param) {
+                                Some(Fragment::Repeated(fragments)) => Some((*param, fragments)),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let Some((_, first)) = repeated.first() else {
+                    return Err(Rich::custom(
+                        *span,
+                        "$( ... )* has to use a parameter that repeats",
+                    ));
+                };
+                if repeated
+                    .iter()
+                    .any(|(_, fragments)| fragments.len() != first.len())
+                {
+                    return Err(Rich::custom(
+                        *span,
+                        "the parameters in $( ... )* repeat a different number of times",
+                    ));
+                }
+
+                for n in 0..first.len() {
+                    if n > 0
+                        && let Some(separator) = &separator
+                    {
+                        out.push((separator.clone(), body[group_end].1));
+                    }
+                    let mut args = args.clone();
+                    for (param, fragments) in &repeated {
+                        args.insert(*param, fragments[n].clone());
+                    }
+                    out.extend(substitute(inner, &args, introduced, suffix)?);
+                }
+                i = next;
+                continue;
+            }
+            ((Token::Ident(ident), span), _) if introduced.contains(ident) => {
+                let renamed = Symbol::intern(&format!("{ident}{GO_IDENT_ESCAPE}macro{suffix}"));
+                out.push((Token::Ident(renamed), *span));
+            }
+            ((Token::FormatStringLiteral(contents), span), _) => {
+                let contents = contents
+                    .iter()
+                    .map(|content| match content {
+                        FmtStringContents::Tokens(tokens, spec) => Ok(FmtStringContents::Tokens(
+                            substitute(tokens, args, introduced, suffix)?,
+                            spec.clone(),
+                        )),
+                        other => Ok(other.clone()),
+                    })
+                    .collect::<Result<_, MacroError>>()?;
+                out.push((Token::FormatStringLiteral(contents), *span));
+            }
+            (token, _) => out.push(token.clone()),
+        }
+        i += 1;
+    }
+
+    Ok(out)
+}
+
+impl MacroExpander {
+    // an error anywhere inside of a call gives up on the outermost call it's part of, the
+    // tokens after that call are still expanded
+    fn expand(
+        &mut self,
+        tokens: &[Spanned<Token>],
+        depth: usize,
+    ) -> Result<Vec<Spanned<Token>>, MacroError> {
+        let mut out = Vec::new();
+        let mut i = 0;
+
+        while i < tokens.len() {
+            let macro_def = match &tokens[i] {
+                (Token::Ident(name), _)
+                    if is_ctrl(tokens.get(i + 1), '!') && is_ctrl(tokens.get(i + 2), '(') =>
+                {
+                    self.macros.get(name).cloned()
+                }
+                _ => None,
+            };
+
+            let Some(macro_def) = macro_def else {
+                out.push(tokens[i].clone());
+                i += 1;
+                continue;
+            };
+
+            match self.expand_call(tokens, i, &macro_def, depth) {
+                Ok((expanded, next)) => {
+                    out.extend(expanded);
+                    i = next;
+                }
+                Err(err) if depth == 0 => {
+                    self.errors.push(err);
+                    i = find_group_end(tokens, i + 2).map_or(tokens.len(), |end| end + 1);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(out)
+    }
+
+    // the expansion of the call starting at `start` and the index after its arguments
+    fn expand_call(
+        &mut self,
+        tokens: &[Spanned<Token>],
+        start: usize,
+        macro_def: &MacroDefinition,
+        depth: usize,
+    ) -> Result<(Vec<Spanned<Token>>, usize), MacroError> {
+        let call_span = tokens[start].1;
+        if depth >= MAX_EXPANSION_DEPTH {
+            return Err(Rich::custom(
+                call_span,
+                format!(
+                    "recursion limit of {MAX_EXPANSION_DEPTH} reached while expanding macro {}",
+                    macro_def.name
+                ),
+            ));
+        }
+
+        // arguments are expanded before they are pasted, so their identifiers keep
+        // referring to the call site
+        let args_end = find_group_end(tokens, start + 2)?;
+        let args = self.expand(&tokens[start + 3..args_end], depth + 1)?;
+
+        // the first rule whose pattern matches all of the arguments is used
+        let matched = macro_def.rules.iter().find_map(|rule| {
+            let mut bindings = HashMap::new();
+            (match_pattern(&rule.pattern, &args, None, &mut bindings) == Some(args.len()))
+                .then_some((rule, bindings))
+        });
+        let Some((rule, bindings)) = matched else {
+            return Err(Rich::custom(
+                call_span,
+                format!(
+                    "no rule of macro {} matches these arguments",
+                    macro_def.name
+                ),
+            ));
+        };
+
+        self.expansion_count += 1;
+        let substituted = substitute(
+            &rule.body,
+            &bindings,
+            &introduced_idents(&rule.body),
+            self.expansion_count,
+        )?;
+
+        Ok((self.expand(&substituted, depth + 1)?, args_end + 1))
+    }
+}
+
+// a broken definition or call is left out and its error returned, so that every error in the
+// file is reported at once
+pub fn expand_macros(tokens: Vec<Spanned<Token>>) -> (Vec<Spanned<Token>>, Vec<MacroError>) {
+    if !tokens.iter().any(|(token, _)| *token == Token::Macro) {
+        return (tokens, Vec::new());
+    }
+
+    let mut expander = MacroExpander::default();
+    let mut rest = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        if tokens[i].0 == Token::Macro {
+            match parse_definition(&tokens, i) {
+                Ok((macro_def, next)) => {
+                    expander.macros.insert(macro_def.name, macro_def);
+                    i = next;
+                }
+                Err(err) => {
+                    expander.errors.push(err);
+                    i = skip_definition(&tokens, i);
+                }
+            }
+        } else {
+            rest.push(tokens[i].clone());
+            i += 1;
+        }
+    }
+
+    // the top level keeps going after an error, so this is never an error
+    let expanded = expander.expand(&rest, 0).unwrap_or_default();
+    (expanded, expander.errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        lexer::lex_parser,
+        source_map::{self, FileId},
+    };
+    use chumsky::Parser;
+
+    use super::*;
+
+    fn expand(src: &str) -> Vec<Token> {
+        let (tokens, errors) = expand_with_errors(src);
+        assert!(errors.is_empty(), "{src}: {errors:?}");
+        tokens
+    }
+
+    fn expand_with_errors(src: &str) -> (Vec<Token>, Vec<String>) {
+        let file_id = source_map::add_test_file("macro_expander_test", src);
+        let tokens = lex_parser(file_id).parse(src).into_result().unwrap();
+        let (tokens, errors) = expand_macros(tokens);
+        (
+            tokens.into_iter().map(|x| x.0).collect(),
+            errors.iter().map(|err| err.reason().to_string()).collect(),
+        )
+    }
+
+    fn assert_error(src: &str, expected: &str) {
+        let (_, errors) = expand_with_errors(src);
+        assert_eq!(errors.len(), 1, "{src}: {errors:?}");
+        assert!(errors[0].contains(expected), "{src}: {errors:?}");
+    }
+
+    #[test]
+    fn test_expand_macros() {
+        let test_cases = vec![
+            ("macro m() {} 1", "1"),
+            ("macro twice($x) { $x + $x } twice!(1)", "1 + 1"),
+            (
+                "macro twice($x) { $x + $x } twice!((1, 2))",
+                "(1, 2) + (1, 2)",
+            ),
+            (
+                "macro add($a, $b) { $a + $b } add!(f(1, 2), 3)",
+                "f(1, 2) + 3",
+            ),
+            (
+                "macro twice($x) { $x + $x } macro quad($x) { twice!(twice!($x)) } quad!(a)",
+                "a + a + a + a",
+            ),
+            ("macro show($x) { f\"{$x}\" } show!(a)", "f\"{a}\""),
+            ("not_a_macro!(1)", "not_a_macro!(1)"),
+            (
+                "macro pick { ($a) => { $a }, ($a, $b) => { $b } } pick!(1) pick!(1, 2)",
+                "1 2",
+            ),
+            (
+                "macro sum($($x),*) { 0 $(+ $x)* } sum!(1, 2, 3)",
+                "0 + 1 + 2 + 3",
+            ),
+            ("macro list($($x),*) { [$($x),*] } list!()", "[]"),
+            (
+                "macro sum { () => { 0 } ($x $(, $rest)*) => { $x + sum!($($rest),*) } } \
+                 sum!(a, b)",
+                "a + b + 0",
+            ),
+            (
+                "macro record($name, $($field: $ty),*) { struct $name = { $($field: $ty),* }; } \
+                 record!(Point, x: Int, y: Int)",
+                "struct Point = { x: Int, y: Int };",
+            ),
+        ];
+
+        for (src, expected) in test_cases {
+            let expected = lex_parser(FileId::EMPTY)
+                .parse(expected)
+                .into_result()
+                .unwrap()
+                .into_iter()
+                .map(|x| x.0)
+                .collect::<Vec<_>>();
+            assert_eq!(expand(src), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_hygiene() {
+        // every ident ending in _renamed is expected as the renamed binding of the expansion
+        let test_cases = vec![
+            (
+                "macro swap($a, $b) { let tmp = $a; $a = $b; $b = tmp; } swap!(x, tmp)",
+                "let tmp_renamed = x; x = tmp; tmp = tmp_renamed;",
+            ),
+            (
+                "macro each($xs, $f) { for x in $xs { $f(x); } } each!(x, f)",
+                "for x_renamed in x { f(x_renamed); }",
+            ),
+            (
+                "macro inc_all($xs, $x) { map($xs, |x| x + $x) } inc_all!(xs, x)",
+                "map(xs, |x_renamed| x_renamed + x)",
+            ),
+            (
+                "macro adder($x) { fn(x: Int) -> Int { x + $x } } adder!(x)",
+                "fn(x_renamed: Int) -> Int { x_renamed + x }",
+            ),
+            (
+                "macro either($v) { let y: Int | String = $v; y } either!(y)",
+                "let y_renamed: Int | String = y; y_renamed",
+            ),
+        ];
+
+        for (src, expected) in test_cases {
+            let expected = lex_parser(FileId::EMPTY)
+                .parse(expected)
+                .into_result()
+                .unwrap()
+                .into_iter()
+                .map(|(token, _)| match token {
+                    Token::Ident(ident) if ident.text().ends_with("_renamed") => {
+                        let ident = ident.text().trim_end_matches("_renamed").to_string();
+                        Token::Ident(Symbol::intern(&format!("{ident}{GO_IDENT_ESCAPE}macro1")))
+                    }
+                    token => token,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(expand(src), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        assert_error(
+            "macro forever($x) { forever!($x) } forever!(1)",
+            "recursion limit",
+        );
+    }
+
+    #[test]
+    fn test_no_matching_rule() {
+        assert_error(
+            "macro pick { ($a) => { $a } } pick!(1, 2)",
+            "no rule of macro pick matches these arguments",
+        );
+    }
+
+    #[test]
+    fn test_uneven_repetitions() {
+        assert_error(
+            "macro zip($($a),* ; $($b),*) { $($a + $b),* } zip!(1, 2; 3)",
+            "repeat a different number of times",
+        );
+    }
+
+    #[test]
+    fn test_errors_keep_expanding() {
+        let (tokens, errors) = expand_with_errors(
+            "macro broken { $a } macro twice($x) { $x + $x } \
+             twice!(1) twice!(2, 3) twice!(4) unknown!($y)",
+        );
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors[0].contains("expected a rule"), "{errors:?}");
+        assert!(errors[1].contains("no rule of macro twice"), "{errors:?}");
+
+        let expected = lex_parser(FileId::EMPTY)
+            .parse("1 + 1 4 + 4 unknown!($y)")
+            .into_result()
+            .unwrap()
+            .into_iter()
+            .map(|x| x.0)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, expected);
+    }
+}
@@ -1,20 +1,37 @@
-use std::fmt;
+use std::{fmt, ops::Range, sync::Arc};
 
 use ariadne::{Color, Label, Report, ReportKind, sources};
 use chumsky::{
-    error::Rich,
-    input::{BorrowInput, Input},
+    error::{Rich, RichReason},
+    input::{BorrowInput, Input, SliceInput},
     span::SimpleSpan,
 };
 
-use crate::parse::{lexer::Token, type_parser::TypeExpr};
+use crate::parse::{
+    attribute_parser::Attribute,
+    lexer::Token,
+    source_map::{FileId, SourceText},
+    type_parser::TypeExpr,
+};
 
+pub mod attribute_parser;
+pub mod const_parser;
+pub mod derive;
+pub mod doc_comment_parser;
 pub mod duckx_component_parser;
+pub mod enum_parser;
+pub mod extern_function_parser;
 pub mod function_parser;
 pub mod generics_parser;
+pub mod global_parser;
 pub mod lexer;
+pub mod macro_expander;
 pub mod source_file_parser;
+pub mod source_map;
 pub mod struct_parser;
+pub mod symbol;
+pub mod trait_parser;
+pub mod trivia;
 pub mod tsx_component_parser;
 pub mod type_parser;
 pub mod use_statement_parser;
@@ -22,17 +39,49 @@ pub mod value_parser;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Context {
-    pub file_name: &'static str,
-    pub file_contents: &'static str,
+    pub file_id: FileId,
 }
 
-pub type SS = SimpleSpan<usize, Context>;
+impl Context {
+    pub fn file_name(&self) -> Arc<str> {
+        source_map::file_name(self.file_id)
+    }
+
+    pub fn file_contents(&self) -> Arc<SourceText> {
+        source_map::file_contents(self.file_id)
+    }
+
+    // file:line:column of the given offset, both 1-based, used in runtime error messages
+    pub fn location(&self, offset: u32) -> String {
+        let contents = self.file_contents();
+        let before = &contents[..(offset as usize).min(contents.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+        format!("{}:{line}:{column}", self.file_name())
+    }
+}
+
+// u32 offsets limit a single source file to 4gb, which keeps spans (and therefore every
+// token and ast node) small
+pub type SS = SimpleSpan<u32, Context>;
 pub type Spanned<T> = (T, SS);
 
+const _: () = assert!(std::mem::size_of::<SS>() == 12);
+// literal payloads are boxed slices, a token is a tag plus one fat pointer
+const _: () = assert!(std::mem::size_of::<Token>() <= 24);
+const _: () = assert!(std::mem::size_of::<Spanned<Token>>() <= 40);
+
+fn span_range(span: SS) -> Range<usize> {
+    span.start as usize..span.end as usize
+}
+
 #[derive(Debug, Clone)]
 pub struct Field {
     pub name: String,
     pub type_expr: Spanned<TypeExpr>,
+    // fields of a struct are private without pub, the fields of a duck are always public
+    pub is_pub: bool,
+    pub attributes: Vec<Spanned<Attribute>>,
 }
 
 impl PartialEq for Field {
@@ -43,74 +92,155 @@ impl PartialEq for Field {
 
 impl Field {
     pub fn new(name: String, type_expr: Spanned<TypeExpr>) -> Self {
-        return Self { name, type_expr };
+        return Self {
+            name,
+            type_expr,
+            is_pub: true,
+            attributes: Vec::new(),
+        };
     }
 }
 
+type TokenSlice<'src> = &'src [Spanned<Token>];
+
+// a slice of the input is a slice of the tokens, so a part of it can be parsed on its own
 pub fn make_input<'src>(
     eoi: SS,
-    toks: &'src [Spanned<Token>],
-) -> impl BorrowInput<'src, Token = Token, Span = SS> {
+    toks: TokenSlice<'src>,
+) -> impl BorrowInput<'src, Token = Token, Span = SS> + SliceInput<'src, Slice = TokenSlice<'src>> {
     toks.map(eoi, |(t, s)| (t, s))
 }
 
 pub fn failure_with_occurence(
-    file_name: &'static str,
+    file_name: &str,
     msg: String,
     occured_at: SS,
     labels: impl IntoIterator<Item = (String, SS)>,
     src: &str,
 ) -> ! {
-    Report::build(ReportKind::Error, (file_name, occured_at.into_range()))
-        .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
-        .with_message(&msg)
-        .with_labels(labels.into_iter().map(|label2| {
-            Label::new((file_name, label2.1.into_range()))
-                .with_message(label2.0)
-                .with_color(Color::Yellow)
-        }))
-        .finish()
-        .eprint(sources([(file_name, src)]))
-        .unwrap();
+    let file_name = file_name.to_string();
+    Report::build(
+        ReportKind::Error,
+        (file_name.clone(), span_range(occured_at)),
+    )
+    .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
+    .with_message(&msg)
+    .with_labels(labels.into_iter().map(|label2| {
+        Label::new((file_name.clone(), span_range(label2.1)))
+            .with_message(label2.0)
+            .with_color(Color::Yellow)
+    }))
+    .finish()
+    .eprint(sources([(file_name, src)]))
+    .unwrap();
     panic!("{}", msg)
 }
 
 pub fn failure(
-    file_name: &'static str,
+    file_name: &str,
     msg: String,
     label: (String, SS),
     extra_labels: impl IntoIterator<Item = (String, SS)>,
     src: &str,
 ) -> ! {
-    Report::build(ReportKind::Error, (file_name, label.1.into_range()))
+    report(file_name, &msg, label, extra_labels, src);
+    panic!("{}", msg)
+}
+
+// like failure, but keeps going so that more than one error can be shown
+pub fn report(
+    file_name: &str,
+    msg: &str,
+    label: (String, SS),
+    extra_labels: impl IntoIterator<Item = (String, SS)>,
+    src: &str,
+) {
+    let file_name = file_name.to_string();
+    Report::build(ReportKind::Error, (file_name.clone(), span_range(label.1)))
         .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
-        .with_message(&msg)
+        .with_message(msg)
         .with_label(
-            Label::new((file_name, label.1.into_range()))
+            Label::new((file_name.clone(), span_range(label.1)))
                 .with_message(label.0)
                 .with_color(Color::Red),
         )
         .with_labels(extra_labels.into_iter().map(|label2| {
-            Label::new((file_name, label2.1.into_range()))
+            Label::new((file_name.clone(), span_range(label2.1)))
                 .with_message(label2.0)
                 .with_color(Color::Yellow)
         }))
         .finish()
         .eprint(sources([(file_name, src)]))
         .unwrap();
-    panic!("{}", msg)
+}
+
+// like report, for problems that don't stop the compilation
+pub fn warning(file_name: &str, msg: &str, label: (String, SS), src: &str) {
+    let file_name = file_name.to_string();
+    Report::build(
+        ReportKind::Warning,
+        (file_name.clone(), span_range(label.1)),
+    )
+    .with_config(ariadne::Config::new().with_index_type(ariadne::IndexType::Byte))
+    .with_message(msg)
+    .with_label(
+        Label::new((file_name.clone(), span_range(label.1)))
+            .with_message(label.0)
+            .with_color(Color::Yellow),
+    )
+    .finish()
+    .eprint(sources([(file_name, src)]))
+    .unwrap();
 }
 
 pub fn parse_failure(file_name: &str, err: &Rich<impl fmt::Display, SS>, src: &str) -> ! {
-    failure(
-        file_name.to_string().leak(),
-        err.reason().to_string(),
-        (
-            err.found()
-                .map(|c| c.to_string())
-                .unwrap_or_else(|| "end of input".to_string()),
-            *err.span(),
-        ),
+    parse_report(file_name, err, src);
+    panic!("{}", err.reason())
+}
+
+// the parser skips broken items, so every parse error is shown before giving up
+pub fn parse_failures(file_name: &str, errs: &[Rich<impl fmt::Display, SS>], src: &str) {
+    for err in errs {
+        parse_report(file_name, err, src);
+    }
+
+    if !errs.is_empty() {
+        panic!("{} parse errors", errs.len());
+    }
+}
+
+// the errors of a file and the modules it declares, which were parsed in parallel. each one is
+// shown in the file it's in, sorted by file and position so the output doesn't depend on which
+// module was done first
+pub fn module_parse_failures(errs: &[Rich<impl fmt::Display, SS>]) {
+    let mut errs = errs
+        .iter()
+        .map(|err| (err.span().context.file_name(), err))
+        .collect::<Vec<_>>();
+    errs.sort_by(|(a_name, a), (b_name, b)| {
+        (a_name, a.span().start, a.span().end).cmp(&(b_name, b.span().start, b.span().end))
+    });
+
+    for (file_name, err) in &errs {
+        parse_report(file_name, err, &err.span().context.file_contents());
+    }
+
+    if !errs.is_empty() {
+        panic!("{} parse errors", errs.len());
+    }
+}
+
+pub fn parse_report(file_name: &str, err: &Rich<impl fmt::Display, SS>, src: &str) {
+    // a custom error didn't stop at a token, its message already says what's wrong here
+    let label = match (err.reason(), err.found()) {
+        (RichReason::Custom(_), _) => "here".to_string(),
+        (_, Some(found)) => found.to_string(),
+        (_, None) => "end of input".to_string(),
+    };
+    report(
+        file_name,
+        &err.reason().to_string(),
+        (label, *err.span()),
         err.contexts()
             .map(|(l, s)| (format!("while parsing this {l}"), *s)),
         src,
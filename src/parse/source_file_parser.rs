@@ -1,24 +1,52 @@
-use std::{collections::HashMap, fs::File, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use chumsky::{input::BorrowInput, prelude::*};
+use chumsky::{
+    input::{BorrowInput, SliceInput},
+    prelude::*,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use tree_sitter::{Node, Parser as TSParser};
 
 use crate::{
+    dargo::queries,
+    emit::types::escape_string_for_go,
     parse::{
         Context, SS, Spanned,
+        attribute_parser::{
+            Attribute, Cfg, attribute_parser, cfg_from_attribute, warn_unknown_attributes,
+        },
+        const_parser::{ConstDefinition, const_definition_parser},
+        derive::derives_json,
+        doc_comment_parser::doc_comments_parser,
         duckx_component_parser::{DuckxComponent, duckx_component_parser},
-        function_parser::{FunctionDefintion, LambdaFunctionExpr, function_definition_parser},
-        lexer::{Token, lex_parser},
-        make_input, parse_failure,
+        enum_parser::{EnumDefinition, enum_definition_parser},
+        extern_function_parser::{Abi, ExternFunction, extern_function_parser},
+        failure,
+        function_parser::{
+            EXTENSION_SEP, FunctionDefintion, LambdaFunctionExpr, extension_call_marker,
+            extension_fn_name, function_definition_parser,
+        },
+        generics_parser::Generic,
+        global_parser::{GlobalVariable, global_variable_parser},
+        lexer::{Token, map_go_interpolations},
+        make_input,
+        source_map::{self, FileId},
         struct_parser::{StructDefinition, struct_definition_parser},
+        trait_parser::{
+            ImplBlock, TraitDefinition, TraitImplementation, impl_block_parser,
+            trait_definition_parser,
+        },
         tsx_component_parser::{TsxComponent, tsx_component_parser},
         type_parser::{Duck, TypeDefinition, TypeExpr, type_definition_parser},
         use_statement_parser::{Indicator, UseStatement, use_statement_parser},
-        value_parser::{ValFmtStringContents, ValHtmlStringContents, ValueExpr},
+        value_parser::{ValFmtStringContents, ValHtmlStringContents, ValueExpr, value_expr_parser},
     },
     semantics::ident_mangler::{
         MangleEnv, mangle, mangle_duckx_component, mangle_tsx_component, mangle_type_expression,
-        mangle_value_expr, unmangle,
+        mangle_value_expr, re_export_chain, unmangle,
     },
 };
 
@@ -27,10 +55,52 @@ pub struct SourceFile {
     pub function_definitions: Vec<FunctionDefintion>,
     pub type_definitions: Vec<TypeDefinition>,
     pub struct_definitions: Vec<StructDefinition>,
+    pub const_definitions: Vec<ConstDefinition>,
+    // module level let, emitted as go package vars that are set in an init function
+    pub global_variables: Vec<GlobalVariable>,
     pub use_statements: Vec<UseStatement>,
     pub sub_modules: Vec<(String, SourceFile)>,
     pub tsx_components: Vec<TsxComponent>,
     pub duckx_components: Vec<DuckxComponent>,
+    pub test_cases: Vec<TestCase>,
+    // items behind #[cfg(...)], merged into the file by apply_cfg if all of their cfgs hold
+    pub cfg_items: Vec<(Vec<Spanned<Cfg>>, SourceFile)>,
+    // prototypes of the extern "C" functions and the #cgo directives of their #[link] attributes,
+    // emitted as the cgo preamble
+    pub c_declarations: Vec<String>,
+    // the methods of an impl block are moved into its struct, these are only kept to check
+    // that the struct really implements the trait
+    pub trait_implementations: Vec<TraitImplementation>,
+    pub private_items: Vec<PrivateItem>,
+    pub re_exports: Vec<ReExport>,
+    // the files and directories this module was read from, the ast of the file declaring it is
+    // parsed again once one of them changed
+    pub module_paths: Vec<PathBuf>,
+}
+
+// an item declared without pub, it can only be used in its module and the sub modules of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateItem {
+    pub name: String,
+    pub module: Vec<String>,
+    pub span: SS,
+}
+
+// a name brought into scope by pub use, other modules can use it as a part of this module. flatten
+// sets the target to the path of what the name refers to, which can be another re-export again,
+// so following the targets walks the whole chain up to the item itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReExport {
+    pub name: String,
+    pub target: Vec<String>,
+    pub span: SS,
+}
+
+// test "name" { ... }, only compiled by dargo test, every other build drops them in flatten
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+    pub name: String,
+    pub value_expr: Spanned<ValueExpr>,
 }
 
 #[derive(Debug, Clone)]
@@ -40,8 +110,77 @@ pub enum SourceUnit {
     Component(TsxComponent),
     Template(DuckxComponent),
     Struct(StructDefinition),
+    Enum(EnumDefinition),
+    Const(ConstDefinition),
+    Global(GlobalVariable),
     Use(UseStatement),
     Module(String, SourceFile),
+    Test(TestCase),
+    Cfg(Vec<Spanned<Cfg>>, Box<SourceUnit>),
+    Extern(ExternFunction),
+    Trait(TraitDefinition),
+    Impl(ImplBlock),
+    Private(Box<SourceUnit>, SS),
+    PubUse(UseStatement, SS),
+}
+
+impl SourceUnit {
+    // the doc comments in front of a unit's attributes end up here instead of in the parser of
+    // the declaration, other units have nothing to attach them to
+    fn attach_doc_comments(&mut self, mut doc_comments: Vec<String>) {
+        let attached = match self {
+            SourceUnit::Func(def) => &mut def.doc_comments,
+            SourceUnit::Type(def) => &mut def.doc_comments,
+            SourceUnit::Struct(def) => &mut def.doc_comments,
+            SourceUnit::Enum(def) => &mut def.doc_comments,
+            SourceUnit::Trait(def) => &mut def.doc_comments,
+            SourceUnit::Const(def) => &mut def.doc_comments,
+            _ => return,
+        };
+        doc_comments.append(attached);
+        *attached = doc_comments;
+    }
+
+    // the attributes in front of a unit's doc comments, like the doc comments themselves. the ones
+    // of a unit that can't have attributes are handed back
+    fn attach_attributes(
+        &mut self,
+        mut attributes: Vec<Spanned<Attribute>>,
+    ) -> Vec<Spanned<Attribute>> {
+        let attached = match self {
+            SourceUnit::Func(def) => &mut def.attributes,
+            SourceUnit::Type(def) => &mut def.attributes,
+            SourceUnit::Struct(def) => &mut def.attributes,
+            SourceUnit::Enum(def) => &mut def.attributes,
+            SourceUnit::Extern(def) => &mut def.attributes,
+            _ => return attributes,
+        };
+        attributes.append(attached);
+        *attached = attributes;
+        Vec::new()
+    }
+
+    // the names of the items a unit declares, only these can be pub
+    fn item_names(&self) -> Option<Vec<String>> {
+        let names = match self {
+            SourceUnit::Func(def) => vec![def.name.clone()],
+            SourceUnit::Type(def) => vec![def.name.clone()],
+            SourceUnit::Struct(def) => vec![def.name.clone()],
+            SourceUnit::Trait(def) => vec![def.name.clone()],
+            SourceUnit::Extern(def) => vec![def.name.clone()],
+            SourceUnit::Const(def) => vec![def.name.clone()],
+            SourceUnit::Global(def) => vec![def.name.clone()],
+            SourceUnit::Enum(def) => std::iter::once(def.name.clone())
+                .chain(
+                    def.variants
+                        .iter()
+                        .map(|(variant, _)| mangle(&[&def.name, &variant.name])),
+                )
+                .collect(),
+            _ => return None,
+        };
+        Some(names)
+    }
 }
 
 impl SourceFile {
@@ -51,71 +190,474 @@ impl SourceFile {
         }
     }
 
+    pub fn merge(&mut self, other: SourceFile) {
+        self.function_definitions.extend(other.function_definitions);
+        self.type_definitions.extend(other.type_definitions);
+        self.struct_definitions.extend(other.struct_definitions);
+        self.const_definitions.extend(other.const_definitions);
+        self.global_variables.extend(other.global_variables);
+        for use_statement in &other.use_statements {
+            self.push_use(use_statement);
+        }
+        self.sub_modules.extend(other.sub_modules);
+        self.tsx_components.extend(other.tsx_components);
+        self.duckx_components.extend(other.duckx_components);
+        self.test_cases.extend(other.test_cases);
+        self.cfg_items.extend(other.cfg_items);
+        self.c_declarations.extend(other.c_declarations);
+        self.trait_implementations
+            .extend(other.trait_implementations);
+        self.private_items.extend(other.private_items);
+        self.re_exports.extend(other.re_exports);
+        self.module_paths.extend(other.module_paths);
+    }
+
+    // the module paths of this file and of all modules below it
+    pub fn all_module_paths(&self) -> Vec<&Path> {
+        let mut paths = self
+            .module_paths
+            .iter()
+            .map(PathBuf::as_path)
+            .collect::<Vec<_>>();
+        for (_, sub_module) in &self.sub_modules {
+            paths.extend(sub_module.all_module_paths());
+        }
+        for (_, cfg_item) in &self.cfg_items {
+            paths.extend(cfg_item.all_module_paths());
+        }
+        paths
+    }
+
+    // keeps the cfg items whose cfgs are enabled by the given features and target os and drops
+    // all others, this has to run before flatten, which ignores cfg items
+    pub fn apply_cfg(&mut self, features: &[String], target_os: &str) {
+        for cfg_item in std::mem::take(&mut self.cfg_items) {
+            let (cfgs, mut source_file) = cfg_item;
+            if cfgs
+                .iter()
+                .all(|(cfg, _)| cfg.is_enabled(features, target_os))
+            {
+                source_file.apply_cfg(features, target_os);
+                self.merge(source_file);
+            }
+        }
+
+        for (_, sub_module) in &mut self.sub_modules {
+            sub_module.apply_cfg(features, target_os);
+        }
+    }
+
+    // replaces every use a::b::*; by an import of the names a::b declares, leaving out the ones
+    // the module declares itself or imports by name. the path is looked up in the module of the
+    // use first, then from the root, and paths starting with std in the given std
+    pub fn resolve_wildcard_imports(&mut self, std: Option<&SourceFile>) {
+        fn collect(
+            root: &SourceFile,
+            std: Option<&SourceFile>,
+            module_path: &mut Vec<String>,
+            module: &SourceFile,
+            resolved: &mut Vec<(Vec<String>, usize, Vec<String>)>,
+        ) {
+            let mut shadowed = module.declared_names();
+            for use_statement in &module.use_statements {
+                shadowed.extend(use_statement.imported_names());
+            }
+
+            // the names imported by a wildcard so far, with the module they're imported from
+            let mut wildcard_names: HashMap<String, (Vec<String>, SS)> = HashMap::new();
+            for (index, use_statement) in module.use_statements.iter().enumerate() {
+                let UseStatement::Regular(is_global, indicators) = use_statement else {
+                    continue;
+                };
+                let Some(Indicator::Wildcard(span)) = indicators.last() else {
+                    continue;
+                };
+                let path = indicators
+                    .iter()
+                    .map_while(|indicator| match indicator {
+                        Indicator::Module(name) => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+
+                let target_path = if !is_global
+                    && let Some(first) = path.first()
+                    && module.sub_modules.iter().any(|(name, _)| name == first)
+                {
+                    [module_path.as_slice(), &path].concat()
+                } else {
+                    path.clone()
+                };
+                let target = match (target_path.split_first(), std) {
+                    (Some((first, std_path)), Some(std)) if first == "std" => {
+                        std.module_at(std_path)
+                    }
+                    _ => root.module_at(&target_path),
+                };
+                let Some(target) = target else {
+                    failure(
+                        &span.context.file_name(),
+                        format!("can't find the module {}", path.join("::")),
+                        ("there's no module to import * from".to_string(), *span),
+                        vec![],
+                        &span.context.file_contents(),
+                    )
+                };
+
+                let mut names = Vec::new();
+                for name in target.exported_names() {
+                    if shadowed.contains(&name) {
+                        continue;
+                    }
+                    match wildcard_names.get(&name) {
+                        Some((other_path, _)) if *other_path == target_path => continue,
+                        Some((other_path, other_span)) => failure(
+                            &span.context.file_name(),
+                            format!(
+                                "{name} is imported by both {}::* and {}::*",
+                                other_path.join("::"),
+                                target_path.join("::")
+                            ),
+                            (format!("this imports {name} a second time"), *span),
+                            vec![(
+                                format!("import {name} by name to pick one of them"),
+                                *other_span,
+                            )],
+                            &span.context.file_contents(),
+                        ),
+                        None => {}
+                    }
+                    wildcard_names.insert(name.clone(), (target_path.clone(), *span));
+                    names.push(name);
+                }
+                resolved.push((module_path.clone(), index, names));
+            }
+
+            for (name, sub_module) in &module.sub_modules {
+                module_path.push(name.clone());
+                collect(root, std, module_path, sub_module, resolved);
+                module_path.pop();
+            }
+        }
+
+        let mut resolved = Vec::new();
+        collect(self, std, &mut Vec::new(), self, &mut resolved);
+
+        for (module_path, index, names) in resolved {
+            let mut module = &mut *self;
+            for name in &module_path {
+                module = &mut module
+                    .sub_modules
+                    .iter_mut()
+                    .find(|(sub_module_name, _)| sub_module_name == name)
+                    .unwrap()
+                    .1;
+            }
+            let UseStatement::Regular(_, indicators) = &mut module.use_statements[index] else {
+                unreachable!("only wildcard imports are resolved")
+            };
+            *indicators.last_mut().unwrap() = Indicator::Symbols(names);
+        }
+    }
+
+    // maps the path of every pub use in this file and its sub modules to the path of what it
+    // re-exports and the span of the pub use. a re-export has to lead to a pub item in the end
+    fn resolve_re_exports(&self, with_std: bool) -> HashMap<Vec<String>, (Vec<String>, SS)> {
+        fn collect(
+            module: &SourceFile,
+            module_path: &mut Vec<String>,
+            with_std: bool,
+            re_exports: &mut HashMap<Vec<String>, (Vec<String>, SS)>,
+        ) {
+            let mangle_env = module.mangle_env(&[], with_std, &HashMap::new());
+            for re_export in &module.re_exports {
+                let Some((is_global, import_path)) =
+                    mangle_env.resolve_import(re_export.name.clone())
+                else {
+                    unreachable!("a pub use imports the names it re-exports")
+                };
+                let target = if is_global {
+                    import_path
+                } else {
+                    [module_path.as_slice(), &import_path].concat()
+                };
+                let path = [module_path.as_slice(), &[re_export.name.clone()]].concat();
+                re_exports.insert(path, (target, re_export.span));
+            }
+
+            for (name, sub_module) in &module.sub_modules {
+                module_path.push(name.clone());
+                collect(sub_module, module_path, with_std, re_exports);
+                module_path.pop();
+            }
+        }
+
+        let mut re_exports = HashMap::new();
+        collect(self, &mut Vec::new(), with_std, &mut re_exports);
+
+        let targets = re_exports
+            .iter()
+            .map(|(path, (target, _))| (path.clone(), target.clone()))
+            .collect();
+        for (path, (_, span)) in &re_exports {
+            let Some(chain) = re_export_chain(&targets, path) else {
+                failure(
+                    &span.context.file_name(),
+                    format!("the re-export of {} never reaches an item", path.join("::")),
+                    (
+                        "this pub use is part of a cycle of re-exports".to_string(),
+                        *span,
+                    ),
+                    vec![],
+                    &span.context.file_contents(),
+                )
+            };
+
+            let item = chain.last().unwrap();
+            // std is flattened on its own, so its items can't be looked up here
+            if with_std && item.first().is_some_and(|first| first == "std") {
+                continue;
+            }
+
+            // the pub uses the chain goes through after the first one
+            let via = chain[1..chain.len() - 1]
+                .iter()
+                .filter_map(|step| re_exports.get(step))
+                .map(|(target, span)| (format!("which re-exports {}", target.join("::")), *span))
+                .collect::<Vec<_>>();
+            let (name, module_path) = item.split_last().unwrap();
+            let module = self.module_at(module_path);
+            let message = match module {
+                Some(module) if module.private_items.iter().any(|p| p.name == *name) => {
+                    format!("{} is private and can't be re-exported", item.join("::"))
+                }
+                Some(module) if module.declared_names().contains(name) => continue,
+                _ => format!(
+                    "{} re-exports {}, which doesn't exist",
+                    path.join("::"),
+                    item.join("::")
+                ),
+            };
+            failure(
+                &span.context.file_name(),
+                message,
+                ("re-exported here".to_string(), *span),
+                via,
+                &span.context.file_contents(),
+            )
+        }
+
+        re_exports
+    }
+
+    fn module_at(&self, path: &[String]) -> Option<&SourceFile> {
+        path.iter().try_fold(self, |module, name| {
+            module
+                .sub_modules
+                .iter()
+                .find(|(sub_module_name, _)| sub_module_name == name)
+                .map(|(_, sub_module)| sub_module)
+        })
+    }
+
+    fn declared_names(&self) -> Vec<String> {
+        self.function_definitions
+            .iter()
+            .map(|def| def.name.clone())
+            .chain(self.const_definitions.iter().map(|def| def.name.clone()))
+            .chain(self.global_variables.iter().map(|def| def.name.clone()))
+            .chain(self.type_definitions.iter().map(|def| def.name.clone()))
+            .chain(self.struct_definitions.iter().map(|def| def.name.clone()))
+            .chain(self.sub_modules.iter().map(|(name, _)| name.clone()))
+            .collect()
+    }
+
+    // a wildcard import only brings the pub items and the re-exports of a module into scope
+    fn exported_names(&self) -> Vec<String> {
+        self.declared_names()
+            .into_iter()
+            .filter(|name| !self.private_items.iter().any(|item| item.name == *name))
+            .chain(
+                self.re_exports
+                    .iter()
+                    .map(|re_export| re_export.name.clone()),
+            )
+            .collect()
+    }
+
+    // turns the test cases of this file and all sub modules into functions and returns the
+    // name and the path of the function for every test
+    pub fn tests_into_functions(&mut self) -> Vec<(String, Vec<String>)> {
+        let mut tests = Vec::new();
+
+        for (module_name, sub_module) in &mut self.sub_modules {
+            for (name, mut path) in sub_module.tests_into_functions() {
+                path.insert(0, module_name.clone());
+                tests.push((name, path));
+            }
+        }
+
+        // skips the names the module already declares, a user's duck_test_0 stays untouched
+        let mut taken = self.declared_names();
+        let mut i = 0;
+        for test_case in std::mem::take(&mut self.test_cases) {
+            let function_name = loop {
+                let name = format!("duck_test_{i}");
+                i += 1;
+                if !taken.contains(&name) {
+                    break name;
+                }
+            };
+            taken.push(function_name.clone());
+            self.function_definitions.push(FunctionDefintion {
+                name: function_name.clone(),
+                value_expr: test_case.value_expr,
+                ..Default::default()
+            });
+            tests.push((test_case.name, vec![function_name]));
+        }
+
+        tests
+    }
+
+    // replaces main with a function that runs every test and prints its name
+    pub fn add_test_runner(&mut self, tests: &[(String, Vec<String>)]) {
+        self.function_definitions.retain(|f| f.name != "main");
+
+        let mut value_exprs = Vec::new();
+        for (name, path) in tests {
+            value_exprs.push(
+                ValueExpr::InlineGo(format!(
+                    "fmt.Print(\"{}\")",
+                    escape_string_for_go(&format!("test {name} ... "))
+                ))
+                .into_empty_span(),
+            );
+            value_exprs.push(
+                ValueExpr::FunctionCall {
+                    target: ValueExpr::RawVariable(false, path.clone())
+                        .into_empty_span()
+                        .into(),
+                    params: vec![],
+                    type_params: None,
+                }
+                .into_empty_span(),
+            );
+            value_exprs
+                .push(ValueExpr::InlineGo("fmt.Println(\"ok\")".to_string()).into_empty_span());
+        }
+        value_exprs.push(
+            ValueExpr::InlineGo(format!(
+                "fmt.Println(\"{}\")",
+                escape_string_for_go(&format!("\n{} tests passed", tests.len()))
+            ))
+            .into_empty_span(),
+        );
+
+        self.function_definitions.push(FunctionDefintion {
+            name: "main".to_string(),
+            value_expr: ValueExpr::Block(value_exprs).into_empty_span(),
+            ..Default::default()
+        });
+    }
+
+    // the names a module sees while it's flattened, its own items, sub modules and imports
+    fn mangle_env(
+        &self,
+        global_prefix: &[String],
+        with_std: bool,
+        re_exports: &HashMap<Vec<String>, Vec<String>>,
+    ) -> MangleEnv {
+        MangleEnv {
+            sub_mods: self.sub_modules.iter().map(|x| x.0.clone()).collect(),
+            global_prefix: global_prefix.to_vec(),
+            tsx_components: self.tsx_components.iter().map(|x| x.name.clone()).collect(),
+            duckx_components: self
+                .duckx_components
+                .iter()
+                .map(|x| x.name.clone())
+                .collect(),
+            imports: {
+                let mut imports = HashMap::new();
+                if with_std {
+                    imports.insert("std".into(), (true, vec!["std".into()]));
+                }
+                for u in &self.use_statements {
+                    if let UseStatement::Regular(glob, v) = u {
+                        let pre = v
+                            .iter()
+                            .take_while(|x| matches!(x, Indicator::Module(_)))
+                            .map(|x| {
+                                let Indicator::Module(x) = x else { panic!() };
+                                x.to_string()
+                            })
+                            .collect::<Vec<_>>();
+                        let last = v.last();
+                        if let Some(Indicator::Symbols(sym)) = last {
+                            for s in sym {
+                                let mut path = pre.clone();
+                                path.push(s.clone());
+                                imports.insert(s.clone(), (*glob, path));
+                            }
+                        }
+                    } else if let UseStatement::Alias(glob, path, alias) = u {
+                        imports.insert(alias.clone(), (*glob, path.clone()));
+                    }
+                }
+
+                imports
+            },
+            names: vec![
+                self.function_definitions
+                    .iter()
+                    .map(|x| x.name.clone())
+                    .chain(self.const_definitions.iter().map(|x| x.name.clone()))
+                    .chain(self.global_variables.iter().map(|x| x.name.clone()))
+                    .collect::<Vec<_>>(),
+            ],
+            types: vec![
+                self.type_definitions
+                    .iter()
+                    .map(|x| x.name.clone())
+                    .chain(self.struct_definitions.iter().map(|x| x.name.clone()))
+                    .collect::<Vec<_>>(),
+            ],
+            re_exports: re_exports.clone(),
+        }
+    }
+
     pub fn flatten(&self, global_prefix: &Vec<String>, with_std: bool) -> SourceFile {
         fn flatten0(
             s: &SourceFile,
             global_prefix: &Vec<String>,
             prefix: &Vec<String>,
             with_std: bool,
+            re_exports: &HashMap<Vec<String>, Vec<String>>,
         ) -> SourceFile {
-            let mut mangle_env = MangleEnv {
-                sub_mods: s.sub_modules.iter().map(|x| x.0.clone()).collect(),
-                global_prefix: global_prefix.clone(),
-                tsx_components: s.tsx_components.iter().map(|x| x.name.clone()).collect(),
-                duckx_components: s.duckx_components.iter().map(|x| x.name.clone()).collect(),
-                imports: {
-                    let mut imports = HashMap::new();
-                    if with_std {
-                        imports.insert("std".into(), (true, vec![]));
-                    }
-                    for u in &s.use_statements {
-                        if let UseStatement::Regular(glob, v) = u {
-                            let pre = v
-                                .iter()
-                                .take_while(|x| matches!(x, Indicator::Module(_)))
-                                .map(|x| {
-                                    let Indicator::Module(x) = x else { panic!() };
-                                    x.to_string()
-                                })
-                                .collect::<Vec<_>>();
-                            let last = v.last();
-                            if let Some(Indicator::Symbols(sym)) = last {
-                                for s in sym {
-                                    imports.insert(s.clone(), (*glob, pre.clone()));
-                                }
-                            }
-                        }
-                    }
-
-                    imports
-                },
-                names: vec![
-                    s.function_definitions
-                        .iter()
-                        .map(|x| x.name.clone())
-                        .collect::<Vec<_>>(),
-                ],
-                types: vec![
-                    s.type_definitions
-                        .iter()
-                        .map(|x| x.name.clone())
-                        .chain(s.struct_definitions.iter().map(|x| x.name.clone()))
-                        .collect::<Vec<_>>(),
-                ],
-            };
+            let mut mangle_env = s.mangle_env(global_prefix, with_std, re_exports);
             let mut result = SourceFile::default();
 
             for (name, sub_module) in &s.sub_modules {
                 let mut p = Vec::new();
                 p.extend_from_slice(prefix);
                 p.push(name.to_owned());
-                let src = flatten0(sub_module, global_prefix, &p, with_std);
+                let src = flatten0(sub_module, global_prefix, &p, with_std, re_exports);
 
                 for f in src.function_definitions {
                     mangle_env.insert_ident(f.name[prefix.len()..].to_string());
                     result.function_definitions.push(f);
                 }
 
+                for c in src.const_definitions {
+                    mangle_env.insert_ident(c.name[prefix.len()..].to_string());
+                    result.const_definitions.push(c);
+                }
+
+                for g in src.global_variables {
+                    mangle_env.insert_ident(g.name[prefix.len()..].to_string());
+                    result.global_variables.push(g);
+                }
+
                 for t in src.type_definitions {
                     mangle_env.insert_type(t.name[prefix.len()..].to_string());
                     result.type_definitions.push(t);
@@ -141,6 +683,12 @@ impl SourceFile {
                         result.push_use(u);
                     }
                 }
+
+                result.c_declarations.extend(src.c_declarations);
+                result
+                    .trait_implementations
+                    .extend(src.trait_implementations);
+                result.private_items.extend(src.private_items);
             }
 
             for u in &s.use_statements {
@@ -149,6 +697,11 @@ impl SourceFile {
                 }
             }
 
+            // c names are global, so they aren't mangled
+            result
+                .c_declarations
+                .extend(s.c_declarations.iter().cloned());
+
             for func in &s.function_definitions {
                 let mut f = func.clone();
 
@@ -160,6 +713,13 @@ impl SourceFile {
                 if let Some(return_type) = &mut f.return_type {
                     mangle_type_expression(&mut return_type.0, prefix, &mut mangle_env);
                 }
+                for constraint in generic_constraints(&mut f.generics) {
+                    mangle_type_expression(constraint, prefix, &mut mangle_env);
+                }
+                // the defaults are evaluated at the call site and can't see the other params
+                for default in &mut f.param_defaults {
+                    mangle_value_expr(&mut default.0, global_prefix, prefix, &mut mangle_env);
+                }
                 mangle_env.push_idents();
                 if let Some(params) = &mut f.params {
                     for (name, type_expr) in params {
@@ -172,6 +732,40 @@ impl SourceFile {
                 result.function_definitions.push(f);
             }
 
+            for const_def in &s.const_definitions {
+                let mut const_def = const_def.clone();
+
+                let mut p = Vec::new();
+                p.extend_from_slice(prefix);
+                p.push(const_def.name.clone());
+                const_def.name = mangle(&p);
+
+                if let Some(type_expr) = &mut const_def.type_expr {
+                    mangle_type_expression(&mut type_expr.0, prefix, &mut mangle_env);
+                }
+                result.const_definitions.push(const_def);
+            }
+
+            for global in &s.global_variables {
+                let mut global = global.clone();
+
+                let mut p = Vec::new();
+                p.extend_from_slice(prefix);
+                p.push(global.name.clone());
+                global.name = mangle(&p);
+
+                if let Some(type_expr) = &mut global.type_expr {
+                    mangle_type_expression(&mut type_expr.0, prefix, &mut mangle_env);
+                }
+                mangle_value_expr(
+                    &mut global.initializer.0,
+                    global_prefix,
+                    prefix,
+                    &mut mangle_env,
+                );
+                result.global_variables.push(global);
+            }
+
             for t in &s.type_definitions {
                 let mut ty = t.clone();
 
@@ -180,6 +774,9 @@ impl SourceFile {
                 p.push(ty.name.clone());
                 ty.name = mangle(&p);
                 mangle_type_expression(&mut ty.type_expression.0, prefix, &mut mangle_env);
+                for constraint in generic_constraints(&mut ty.generics) {
+                    mangle_type_expression(constraint, prefix, &mut mangle_env);
+                }
                 result.type_definitions.push(ty);
             }
 
@@ -197,10 +794,17 @@ impl SourceFile {
                     mangle_type_expression(&mut field.type_expr.0, prefix, &mut mangle_env);
                 }
 
+                for constraint in generic_constraints(&mut struct_def.generics) {
+                    mangle_type_expression(constraint, prefix, &mut mangle_env);
+                }
+
                 for func in &mut struct_def.methods {
                     if let Some(return_type) = &mut func.return_type {
                         mangle_type_expression(&mut return_type.0, prefix, &mut mangle_env);
                     }
+                    for constraint in generic_constraints(&mut func.generics) {
+                        mangle_type_expression(constraint, prefix, &mut mangle_env);
+                    }
                     mangle_env.push_idents();
                     if let Some(params) = &mut func.params {
                         for (name, type_expr) in params {
@@ -220,6 +824,28 @@ impl SourceFile {
                 result.struct_definitions.push(struct_def);
             }
 
+            for implementation in &s.trait_implementations {
+                let mut implementation = implementation.clone();
+
+                let mut struct_name = Vec::new();
+                struct_name.extend_from_slice(prefix);
+                struct_name.push(implementation.struct_name.0.clone());
+                implementation.struct_name.0 = mangle(&struct_name);
+
+                mangle_type_expression(&mut implementation.trait_type.0, prefix, &mut mangle_env);
+                result.trait_implementations.push(implementation);
+            }
+
+            for private_item in &s.private_items {
+                let mut name = prefix.clone();
+                name.extend(unmangle(&private_item.name));
+                result.private_items.push(PrivateItem {
+                    name: mangle(&name),
+                    module: prefix.clone(),
+                    span: private_item.span,
+                });
+            }
+
             for c in &s.tsx_components {
                 // todo: mangle components in tsx
                 let mut c = c.clone();
@@ -237,7 +863,17 @@ impl SourceFile {
             result
         }
 
-        let mut r = flatten0(self, global_prefix, &vec![], with_std);
+        let re_exports = self.resolve_re_exports(with_std);
+        let mut r = flatten0(
+            self,
+            global_prefix,
+            &vec![],
+            with_std,
+            &re_exports
+                .iter()
+                .map(|(path, (target, _))| (path.clone(), target.clone()))
+                .collect(),
+        );
 
         let mut mangle_env = MangleEnv {
             sub_mods: Vec::new(),
@@ -249,6 +885,8 @@ impl SourceFile {
                 r.function_definitions
                     .iter()
                     .map(|x| x.name.clone())
+                    .chain(r.const_definitions.iter().map(|x| x.name.clone()))
+                    .chain(r.global_variables.iter().map(|x| x.name.clone()))
                     .collect::<Vec<_>>(),
             ],
             types: vec![
@@ -258,30 +896,73 @@ impl SourceFile {
                     .chain(r.struct_definitions.iter().map(|x| x.name.clone()))
                     .collect::<Vec<_>>(),
             ],
+            re_exports: HashMap::new(),
         };
 
+        for (path, (target, span)) in re_exports {
+            r.re_exports.push(ReExport {
+                name: mangle(&[global_prefix.as_slice(), &path].concat()),
+                target: [global_prefix.as_slice(), &target].concat(),
+                span,
+            });
+        }
+        r.re_exports.sort_by(|a, b| a.name.cmp(&b.name));
+
         for f in &mut r.function_definitions {
             let mut c = global_prefix.clone();
             c.extend(unmangle(&f.name));
             f.name = mangle(&c);
 
-            for t in f.return_type.iter_mut().map(|x| &mut x.0).chain(
-                f.params
-                    .iter_mut()
-                    .flat_map(|x| x.iter_mut().map(|x| &mut x.1.0)),
-            ) {
+            for t in f
+                .return_type
+                .iter_mut()
+                .map(|x| &mut x.0)
+                .chain(
+                    f.params
+                        .iter_mut()
+                        .flat_map(|x| x.iter_mut().map(|x| &mut x.1.0)),
+                )
+                .chain(generic_constraints(&mut f.generics))
+            {
                 append_global_prefix_type_expr(t, &mut mangle_env);
             }
 
+            for default in &mut f.param_defaults {
+                append_global_prefix_value_expr(&mut default.0, &mut mangle_env);
+            }
             append_global_prefix_value_expr(&mut f.value_expr.0, &mut mangle_env);
         }
 
+        for const_def in &mut r.const_definitions {
+            let mut c = global_prefix.clone();
+            c.extend(unmangle(&const_def.name));
+            const_def.name = mangle(&c);
+
+            if let Some(type_expr) = &mut const_def.type_expr {
+                append_global_prefix_type_expr(&mut type_expr.0, &mut mangle_env);
+            }
+        }
+
+        for global in &mut r.global_variables {
+            let mut c = global_prefix.clone();
+            c.extend(unmangle(&global.name));
+            global.name = mangle(&c);
+
+            if let Some(type_expr) = &mut global.type_expr {
+                append_global_prefix_type_expr(&mut type_expr.0, &mut mangle_env);
+            }
+            append_global_prefix_value_expr(&mut global.initializer.0, &mut mangle_env);
+        }
+
         for t in &mut r.type_definitions {
             let mut c = global_prefix.clone();
             c.extend(unmangle(&t.name));
             t.name = mangle(&c);
 
             append_global_prefix_type_expr(&mut t.type_expression.0, &mut mangle_env);
+            for constraint in generic_constraints(&mut t.generics) {
+                append_global_prefix_type_expr(constraint, &mut mangle_env);
+            }
         }
 
         for s in &mut r.struct_definitions {
@@ -289,12 +970,22 @@ impl SourceFile {
             c.extend(unmangle(&s.name));
             s.name = mangle(&c);
 
+            for constraint in generic_constraints(&mut s.generics) {
+                append_global_prefix_type_expr(constraint, &mut mangle_env);
+            }
+
             for m in &mut s.methods {
-                for t in m.return_type.iter_mut().map(|x| &mut x.0).chain(
-                    m.params
-                        .iter_mut()
-                        .flat_map(|x| x.iter_mut().map(|x| &mut x.1.0)),
-                ) {
+                for t in m
+                    .return_type
+                    .iter_mut()
+                    .map(|x| &mut x.0)
+                    .chain(
+                        m.params
+                            .iter_mut()
+                            .flat_map(|x| x.iter_mut().map(|x| &mut x.1.0)),
+                    )
+                    .chain(generic_constraints(&mut m.generics))
+                {
                     append_global_prefix_type_expr(t, &mut mangle_env);
                 }
 
@@ -302,6 +993,24 @@ impl SourceFile {
             }
         }
 
+        for implementation in &mut r.trait_implementations {
+            let mut c = global_prefix.clone();
+            c.extend(unmangle(&implementation.struct_name.0));
+            implementation.struct_name.0 = mangle(&c);
+
+            append_global_prefix_type_expr(&mut implementation.trait_type.0, &mut mangle_env);
+        }
+
+        for private_item in &mut r.private_items {
+            let mut c = global_prefix.clone();
+            c.extend(unmangle(&private_item.name));
+            private_item.name = mangle(&c);
+
+            let mut module = global_prefix.clone();
+            module.append(&mut private_item.module);
+            private_item.module = module;
+        }
+
         for s in &mut r.duckx_components {
             let mut c = global_prefix.clone();
 
@@ -322,6 +1031,17 @@ impl SourceFile {
     }
 }
 
+fn generic_constraints(
+    generics: &mut Option<Vec<Spanned<Generic>>>,
+) -> impl Iterator<Item = &mut TypeExpr> {
+    generics.iter_mut().flatten().filter_map(|(generic, _)| {
+        generic
+            .constraint
+            .as_mut()
+            .map(|constraint| &mut constraint.0)
+    })
+}
+
 fn append_global_prefix_type_expr(type_expr: &mut TypeExpr, mangle_env: &mut MangleEnv) {
     match type_expr {
         TypeExpr::TypeName(_, name, _) => {
@@ -357,9 +1077,13 @@ fn append_global_prefix_type_expr(type_expr: &mut TypeExpr, mangle_env: &mut Man
                 append_global_prefix_type_expr(&mut t.0, mangle_env);
             }
         }
-        TypeExpr::Array(t) => {
+        TypeExpr::Array(t) | TypeExpr::Chan(t) => {
             append_global_prefix_type_expr(&mut t.0, mangle_env);
         }
+        TypeExpr::Map(key, value) => {
+            append_global_prefix_type_expr(&mut key.0, mangle_env);
+            append_global_prefix_type_expr(&mut value.0, mangle_env);
+        }
         _ => {}
     }
 }
@@ -381,11 +1105,44 @@ fn append_global_prefix_value_expr(value_expr: &mut ValueExpr, mangle_env: &mut
         | ValueExpr::Return(None)
         | ValueExpr::Char(..) => {}
         ValueExpr::Continue => {}
-        ValueExpr::Break => {}
-        ValueExpr::ArrayAccess(target, idx) => {
+        ValueExpr::Break(None) => {}
+        ValueExpr::ArrayAccess(target, idx) | ValueExpr::Send(target, idx) => {
             append_global_prefix_value_expr(&mut target.0, mangle_env);
             append_global_prefix_value_expr(&mut idx.0, mangle_env);
         }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            append_global_prefix_value_expr(&mut target.0, mangle_env);
+            for bound in [start, end].into_iter().flatten() {
+                append_global_prefix_value_expr(&mut bound.0, mangle_env);
+            }
+        }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                append_global_prefix_value_expr(&mut arm.case.0, mangle_env);
+                mangle_env.push_idents();
+                if let Some(binding) = &arm.binding {
+                    mangle_env.insert_ident(binding.clone());
+                }
+                append_global_prefix_value_expr(&mut arm.value_expr.0, mangle_env);
+                mangle_env.pop_idents();
+            }
+            if let Some(else_arm) = else_arm {
+                append_global_prefix_value_expr(&mut else_arm.0, mangle_env);
+            }
+        }
+        ValueExpr::Recover {
+            body,
+            binding,
+            handler,
+        } => {
+            append_global_prefix_value_expr(&mut body.0, mangle_env);
+            mangle_env.push_idents();
+            mangle_env.insert_ident(binding.clone());
+            append_global_prefix_value_expr(&mut handler.0, mangle_env);
+            mangle_env.pop_idents();
+        }
         ValueExpr::Match {
             value_expr,
             arms,
@@ -398,6 +1155,9 @@ fn append_global_prefix_value_expr(value_expr: &mut ValueExpr, mangle_env: &mut
                 if let Some(identifier) = &arm.identifier_binding {
                     mangle_env.insert_ident(identifier.clone());
                 }
+                if let Some(condition) = &mut arm.condition {
+                    append_global_prefix_value_expr(&mut condition.0, mangle_env);
+                }
                 append_global_prefix_value_expr(&mut arm.value_expr.0, mangle_env);
                 mangle_env.pop_idents();
             }
@@ -408,13 +1168,16 @@ fn append_global_prefix_value_expr(value_expr: &mut ValueExpr, mangle_env: &mut
                 if let Some(identifier) = &arm.identifier_binding {
                     mangle_env.insert_ident(identifier.clone());
                 }
+                if let Some(condition) = &mut arm.condition {
+                    append_global_prefix_value_expr(&mut condition.0, mangle_env);
+                }
                 append_global_prefix_value_expr(&mut arm.value_expr.0, mangle_env);
                 mangle_env.pop_idents();
             }
         }
         ValueExpr::FormattedString(contents) => {
             for c in contents {
-                if let ValFmtStringContents::Expr(e) = c {
+                if let ValFmtStringContents::Expr(e, _) = c {
                     append_global_prefix_value_expr(&mut e.0, mangle_env);
                 }
             }
@@ -428,7 +1191,27 @@ fn append_global_prefix_value_expr(value_expr: &mut ValueExpr, mangle_env: &mut
                 append_global_prefix_value_expr(&mut expr.0, mangle_env);
             }
         }
+        ValueExpr::Map(ty, entries) => {
+            if let Some(ty) = ty {
+                append_global_prefix_type_expr(&mut ty.0, mangle_env);
+            }
+
+            for (key, value) in entries {
+                append_global_prefix_value_expr(&mut key.0, mangle_env);
+                append_global_prefix_value_expr(&mut value.0, mangle_env);
+            }
+        }
         ValueExpr::InlineGo(t) => {
+            *t = map_go_interpolations(t, |name| {
+                if mangle_env.is_top_level_ident(&name.to_string()) {
+                    let mut v = mangle_env.global_prefix.clone();
+                    v.push(name.to_string());
+                    format!("${{{}}}", mangle(&v))
+                } else {
+                    format!("${{{name}}}")
+                }
+            });
+
             let mut parser = TSParser::new();
             parser
                 .set_language(&tree_sitter_go::LANGUAGE.into())
@@ -533,6 +1316,13 @@ fn append_global_prefix_value_expr(value_expr: &mut ValueExpr, mangle_env: &mut
             type_params,
         } => {
             // TODO: type params
+            if let ValueExpr::FieldAccess { field_name, .. } = &mut target.0
+                && let Some(method_path) = field_name.strip_suffix(EXTENSION_SEP)
+            {
+                let mut path = mangle_env.global_prefix.clone();
+                path.extend(unmangle(method_path));
+                *field_name = extension_call_marker(&mangle(&path));
+            }
             append_global_prefix_value_expr(&mut target.0, mangle_env);
             params
                 .iter_mut()
@@ -574,6 +1364,22 @@ fn append_global_prefix_value_expr(value_expr: &mut ValueExpr, mangle_env: &mut
             append_global_prefix_value_expr(&mut body.0, mangle_env);
             mangle_env.pop_idents();
         }
+        ValueExpr::For {
+            ident,
+            target,
+            body,
+        } => {
+            append_global_prefix_value_expr(&mut target.0, mangle_env);
+            mangle_env.push_idents();
+            mangle_env.insert_ident(ident.clone());
+            append_global_prefix_value_expr(&mut body.0, mangle_env);
+            mangle_env.pop_idents();
+        }
+        ValueExpr::Loop { body, .. } => {
+            mangle_env.push_idents();
+            append_global_prefix_value_expr(&mut body.0, mangle_env);
+            mangle_env.pop_idents();
+        }
         ValueExpr::Tuple(value_exprs) => value_exprs
             .iter_mut()
             .for_each(|value_expr| append_global_prefix_value_expr(&mut value_expr.0, mangle_env)),
@@ -609,10 +1415,11 @@ fn append_global_prefix_value_expr(value_expr: &mut ValueExpr, mangle_env: &mut
                 }
             }
         }
-        ValueExpr::FieldAccess { target_obj, .. } => {
+        ValueExpr::FieldAccess { target_obj, .. }
+        | ValueExpr::OptionalFieldAccess { target_obj, .. } => {
             append_global_prefix_value_expr(&mut target_obj.0, mangle_env);
         }
-        ValueExpr::Return(Some(value_expr)) => {
+        ValueExpr::Return(Some(value_expr)) | ValueExpr::Break(Some(value_expr)) => {
             append_global_prefix_value_expr(&mut value_expr.0, mangle_env)
         }
         ValueExpr::VarAssign(assignment) => {
@@ -640,111 +1447,199 @@ fn append_global_prefix_value_expr(value_expr: &mut ValueExpr, mangle_env: &mut
         | ValueExpr::GreaterThanOrEquals(lhs, rhs)
         | ValueExpr::And(lhs, rhs)
         | ValueExpr::Or(lhs, rhs)
+        | ValueExpr::Update(lhs, rhs)
+        | ValueExpr::Coalesce(lhs, rhs)
         | ValueExpr::Sub(lhs, rhs)
         | ValueExpr::Div(lhs, rhs)
         | ValueExpr::Mod(lhs, rhs)
+        | ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        }
         | ValueExpr::Mul(lhs, rhs) => {
             append_global_prefix_value_expr(&mut lhs.0, mangle_env);
             append_global_prefix_value_expr(&mut rhs.0, mangle_env);
         }
-        ValueExpr::BoolNegate(value_expr) => {
+        ValueExpr::Comptime(..) => {
+            panic!("compiler error: comptime blocks should be evaluated by now")
+        }
+        ValueExpr::BoolNegate(value_expr)
+        | ValueExpr::BitNegate(value_expr)
+        | ValueExpr::TypeOf(value_expr)
+        | ValueExpr::Negate(value_expr)
+        | ValueExpr::Defer(value_expr)
+        | ValueExpr::Spawn(value_expr)
+        | ValueExpr::Receive(value_expr)
+        | ValueExpr::Spread(value_expr)
+        | ValueExpr::Labeled(_, value_expr)
+        | ValueExpr::Propagate(value_expr) => {
             append_global_prefix_value_expr(&mut value_expr.0, mangle_env);
         }
+        ValueExpr::MakeChan {
+            element_type,
+            capacity,
+        } => {
+            append_global_prefix_type_expr(&mut element_type.0, mangle_env);
+            if let Some(capacity) = capacity {
+                append_global_prefix_value_expr(&mut capacity.0, mangle_env);
+            }
+        }
+        ValueExpr::Cast(value_expr, type_expr) => {
+            append_global_prefix_value_expr(&mut value_expr.0, mangle_env);
+            append_global_prefix_type_expr(&mut type_expr.0, mangle_env);
+        }
     }
 }
 
-fn module_descent(name: String, current_dir: PathBuf) -> SourceFile {
-    let joined = current_dir.join(&name);
-    let mod_dir = File::open(&joined);
-    if let Ok(mod_dir) = mod_dir
-        && mod_dir.metadata().unwrap().is_dir()
-    {
-        std::fs::read_dir(&joined)
-            .unwrap()
-            .filter_map(|dir_entry| match dir_entry {
-                Ok(dir_entry)
-                    if dir_entry.metadata().unwrap().is_dir()
-                        || dir_entry.file_name().to_string_lossy().ends_with(".duck") =>
-                {
-                    Some(dir_entry)
-                }
-                _ => None,
-            })
-            .map(|dir_entry| {
-                (
-                    dir_entry.metadata().unwrap().is_file(),
-                    module_descent(
-                        dir_entry
-                            .file_name()
-                            .into_string()
-                            .unwrap()
-                            .split(".duck")
-                            .next()
-                            .unwrap()
-                            .into(),
-                        joined.clone(),
-                    ),
-                )
-            })
-            .fold(SourceFile::default(), |mut acc, (internal, x)| {
-                if internal {
-                    acc.function_definitions.extend(x.function_definitions);
-                    acc.type_definitions.extend(x.type_definitions);
-                    acc.sub_modules.extend(x.sub_modules);
-                    acc.struct_definitions.extend(x.struct_definitions);
-                }
-                acc.use_statements.extend(x.use_statements);
-                acc
-            })
+// the errors of a module, kept until the file that declares it is parsed. modules are parsed in
+// parallel, so they can't be reported right away
+pub type ModuleErrors = Vec<Rich<'static, Token, SS>>;
+
+pub enum ModuleSource {
+    // the file and the directory its own module declarations are looked up in
+    File(PathBuf, PathBuf),
+    Directory(PathBuf),
+}
+
+// module foo; is the file foo.duck, whose own module declarations are looked up in foo/. without
+// a foo.duck all files in the directory foo/ together make up the module. a module declared in an
+// inline module is looked up in the directory of the file too. the doc tests find the files of
+// the modules with this as well
+pub fn find_module(name: &str, current_dir: &Path) -> Option<ModuleSource> {
+    let [module_file, module_dir] = module_candidates(name, current_dir);
+    if module_file.is_file() {
+        Some(ModuleSource::File(module_file, module_dir))
+    } else if module_dir.is_dir() {
+        Some(ModuleSource::Directory(module_dir))
     } else {
-        let src_text = std::fs::read_to_string(format!("{}.duck", joined.to_str().unwrap()))
-            .unwrap_or_else(|_| panic!("{}", joined.to_str().unwrap().to_string()))
-            .leak() as &'static str;
-        let target_path = joined.to_string_lossy();
-        let target_path_leaked = target_path.to_string().leak() as &str;
-        let (lex, lex_errors) = lex_parser(target_path_leaked, src_text)
-            .parse(src_text)
-            .into_output_errors();
+        None
+    }
+}
 
-        lex_errors.into_iter().for_each(|e| {
-            parse_failure(
-                &target_path,
-                &Rich::<&str, SS>::custom(
-                    SS {
-                        start: e.span().start,
-                        end: e.span().end,
-                        context: Context {
-                            file_name: target_path_leaked,
-                            file_contents: src_text,
-                        },
-                    },
-                    "Lex Error",
-                ),
-                src_text,
-            );
-        });
+fn module_descent(name: &str, current_dir: &Path) -> Option<(SourceFile, ModuleErrors)> {
+    let (mut src, errors) = match find_module(name, current_dir)? {
+        ModuleSource::File(file, sub_module_dir) => parse_module_file(&file, sub_module_dir),
+        ModuleSource::Directory(module_dir) => directory_module(&module_dir),
+    };
+    // a foo.duck that's added next to a directory foo/ takes its place, so both are inputs
+    src.module_paths
+        .extend(module_candidates(name, current_dir));
+    Some((src, errors))
+}
 
-        let lex = lex.unwrap();
-        let (parse, parse_errors) = source_file_parser(current_dir.clone(), make_input)
-            .parse(make_input(
-                SS {
-                    start: 0,
-                    end: src_text.len(),
-                    context: Context {
-                        file_name: target_path_leaked,
-                        file_contents: src_text,
-                    },
-                },
-                &lex,
-            ))
-            .into_output_errors();
+fn module_candidates(name: &str, current_dir: &Path) -> [PathBuf; 2] {
+    [
+        current_dir.join(format!("{name}.duck")),
+        current_dir.join(name),
+    ]
+}
 
-        parse_errors.into_iter().for_each(|e| {
-            parse_failure(&target_path, &e, src_text);
-        });
+// a file depends on the modules it declares, so they're parsed before it is. the modules don't
+// depend on each other and are parsed in parallel, each one after its own modules again. the
+// parser then gets them from the ast query
+pub fn prefetch_modules(tokens: &[Spanned<Token>], module_dir: &Path) {
+    let mut names = tokens
+        .windows(3)
+        .filter_map(|window| match window {
+            [
+                (Token::Module, _),
+                (Token::Ident(name), _),
+                (Token::ControlChar(';'), _),
+            ] => Some(name.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
 
-        parse.unwrap()
-    }
+    names.into_par_iter().for_each(|name| {
+        module_descent(&name, module_dir);
+    });
+}
+
+// the .duck files and the directories of a directory module, sorted so that the merged module
+// doesn't depend on the fs order
+pub fn directory_module_entries(module_dir: &Path) -> Vec<PathBuf> {
+    let mut entries = std::fs::read_dir(module_dir)
+        .unwrap()
+        .filter_map(Result::ok)
+        .map(|dir_entry| dir_entry.path())
+        .filter(|path| path.is_dir() || path.extension().is_some_and(|ext| ext == "duck"))
+        .collect::<Vec<_>>();
+    entries.sort();
+    entries
+}
+
+fn directory_module(module_dir: &Path) -> (SourceFile, ModuleErrors) {
+    directory_module_entries(module_dir)
+        .into_par_iter()
+        .map(|path| {
+            if path.is_file() {
+                // the files of a directory module declare their sub modules in the directory
+                let parsed = parse_module_file(&path, module_dir.to_path_buf());
+                (true, path, parsed)
+            } else {
+                let parsed = directory_module(&path);
+                (false, path, parsed)
+            }
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .fold(
+            (SourceFile::default(), Vec::new()),
+            |(mut acc, mut errors), (internal, path, (x, x_errors))| {
+                acc.module_paths.push(path);
+                if internal {
+                    acc.merge(x);
+                } else {
+                    acc.module_paths.extend(x.module_paths);
+                    for use_statement in &x.use_statements {
+                        acc.push_use(use_statement);
+                    }
+                }
+                errors.extend(x_errors);
+                (acc, errors)
+            },
+        )
+}
+
+fn parse_module_file(path: &Path, sub_module_dir: PathBuf) -> (SourceFile, ModuleErrors) {
+    let src_text = source_map::read_source(path)
+        .unwrap_or_else(|err| panic!("couldn't read {}: {err}", path.display()));
+    let target_path = path.to_string_lossy();
+    let file_id = source_map::add_file(&target_path, src_text);
+    let src_text = source_map::file_contents(file_id);
+    queries::ast(file_id, &src_text, || {
+        parse_module_tokens(file_id, &src_text, sub_module_dir)
+    })
+}
+
+fn parse_module_tokens(
+    file_id: FileId,
+    src_text: &str,
+    sub_module_dir: PathBuf,
+) -> (SourceFile, ModuleErrors) {
+    let (lex, mut errors) = crate::lex_partial(file_id, src_text);
+    prefetch_modules(&lex, &sub_module_dir);
+    let (parse, parse_errors) = source_file_parser(sub_module_dir, make_input)
+        .parse(make_input(
+            SS {
+                start: 0,
+                end: src_text.len() as u32,
+                context: Context { file_id },
+            },
+            &lex,
+        ))
+        .into_output_errors();
+    errors.extend(parse_errors.into_iter().map(Rich::into_owned));
+
+    (parse.unwrap_or_default(), errors)
 }
 
 pub fn source_file_parser<'src, I, M>(
@@ -752,69 +1647,388 @@ pub fn source_file_parser<'src, I, M>(
     make_input: M,
 ) -> impl Parser<'src, I, SourceFile, extra::Err<Rich<'src, Token, SS>>>
 where
-    I: BorrowInput<'src, Token = Token, Span = SS>,
+    I: BorrowInput<'src, Token = Token, Span = SS>
+        + SliceInput<'src, Slice = &'src [Spanned<Token>]>,
     M: Fn(SS, &'src [Spanned<Token>]) -> I + Clone + 'static,
 {
     let p = Box::leak(Box::new(p));
-    recursive(|e| {
+    // skips a broken item up to the ; or the block that ends it, so that the items after it are
+    // still parsed and their errors reported too
+    let skipped_block = recursive(|skipped_block| {
         choice((
+            skipped_block,
+            select_ref! { token if !matches!(token, Token::ControlChar('{' | '}')) => () },
+        ))
+        .repeated()
+        .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}')))
+        .ignored()
+    });
+    let skipped_item = select_ref! {
+        token if !matches!(token, Token::ControlChar(';' | '{' | '}') | Token::Eof) => ()
+    }
+    .repeated()
+    .then(choice((
+        just(Token::ControlChar(';')).ignored(),
+        skipped_block.then_ignore(just(Token::ControlChar(';')).or_not()),
+    )))
+    .to(None);
+
+    recursive(|e| {
+        let source_unit = choice((
             use_statement_parser().map(SourceUnit::Use),
             type_definition_parser().map(SourceUnit::Type),
             tsx_component_parser().map(SourceUnit::Component),
             duckx_component_parser(make_input.clone()).map(SourceUnit::Template),
             struct_definition_parser(make_input.clone()).map(SourceUnit::Struct),
-            function_definition_parser(make_input).map(SourceUnit::Func),
+            enum_definition_parser().map(SourceUnit::Enum),
+            const_definition_parser(make_input.clone()).map(SourceUnit::Const),
+            global_variable_parser(make_input.clone()).map(SourceUnit::Global),
+            function_definition_parser(make_input.clone()).map(SourceUnit::Func),
+            extern_function_parser().map(SourceUnit::Extern),
+            trait_definition_parser().map(SourceUnit::Trait),
+            impl_block_parser(make_input.clone()).map(SourceUnit::Impl),
+            // test is no keyword, so that it can still be used as an identifier everywhere else
+            select_ref! { Token::Ident(ident) if ident == "test" => () }
+                .ignore_then(select_ref! { Token::ConstString(name) => name.to_string() })
+                .then(value_expr_parser(make_input))
+                .try_map(|(name, value_expr), span| match value_expr.0 {
+                    ValueExpr::Block(..) => Ok(SourceUnit::Test(TestCase { name, value_expr })),
+                    _ => Err(Rich::custom(span, "the body of a test has to be a block")),
+                }),
             just(Token::Module)
-                .ignore_then(select_ref! { Token::Ident(i) => i.to_owned() })
+                .ignore_then(select_ref! { Token::Ident(i) => i.to_string() })
                 .then(choice((
                     just(Token::ControlChar(';')).to(None),
                     e.clone()
                         .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}')))
                         .map(Some),
                 )))
-                .map(|(name, src)| {
-                    if let Some(src) = src {
-                        SourceUnit::Module(name, src)
+                .validate(|(name, src), e, emitter| {
+                    let src = src.unwrap_or_else(|| match module_descent(&name, p) {
+                        Some((src, errors)) => {
+                            errors.into_iter().for_each(|error| emitter.emit(error));
+                            src
+                        }
+                        None => {
+                            emitter.emit(Rich::custom(
+                                e.span(),
+                                format!(
+                                    "can't find the module {name}, there's neither a {name}.duck \
+                                     nor a directory {name} in {}",
+                                    p.display()
+                                ),
+                            ));
+                            // so that the file is parsed again once the module is there
+                            SourceFile {
+                                module_paths: module_candidates(&name, p).to_vec(),
+                                ..Default::default()
+                            }
+                        }
+                    });
+                    SourceUnit::Module(name, src)
+                }),
+        ));
+
+        doc_comments_parser()
+            .then(attribute_parser().repeated().collect::<Vec<_>>())
+            .then(just(Token::Pub).or_not())
+            .then(source_unit.map_with(|source_unit, e| (source_unit, e.span())))
+            .try_map(
+                |(((doc_comments, attributes), is_pub), (mut source_unit, unit_span)), span| {
+                    source_unit.attach_doc_comments(doc_comments);
+                    let (cfgs, attributes): (Vec<_>, Vec<_>) = attributes
+                        .into_iter()
+                        .partition(|(attribute, _)| attribute.name == "cfg");
+                    let cfgs = cfgs
+                        .into_iter()
+                        .map(cfg_from_attribute)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let ignored = source_unit.attach_attributes(attributes);
+                    warn_unknown_attributes(&ignored, &[], "this declaration");
+                    let source_unit = match (is_pub, source_unit.item_names()) {
+                        (Some(_), None) => match source_unit {
+                            SourceUnit::Use(use_statement)
+                                if !use_statement.imported_names().is_empty() =>
+                            {
+                                SourceUnit::PubUse(use_statement, unit_span)
+                            }
+                            SourceUnit::Use(_) => {
+                                return Err(Rich::custom(
+                                    span,
+                                    "only imported names can be re-exported, like pub use a::{b}; \
+                                     or pub use a::b as c;",
+                                ));
+                            }
+                            _ => {
+                                return Err(Rich::custom(
+                                    span,
+                                    "only functions, types, structs, enums, traits, consts, \
+                                     globals and use statements can be pub",
+                                ));
+                            }
+                        },
+                        (None, Some(_)) => SourceUnit::Private(Box::new(source_unit), unit_span),
+                        _ => source_unit,
+                    };
+
+                    Ok(if cfgs.is_empty() {
+                        source_unit
                     } else {
-                        SourceUnit::Module(name.clone(), module_descent(name.clone(), p.clone()))
+                        SourceUnit::Cfg(cfgs, Box::new(source_unit))
+                    })
+                },
+            )
+            .map(Some)
+            .recover_with(via_parser(skipped_item.clone()))
+            .repeated()
+            .collect::<Vec<_>>()
+            .validate(|source_units, _, emitter| {
+                let mut errors = Vec::new();
+                let source_file = source_file_from_units(
+                    source_units.into_iter().flatten().collect(),
+                    &mut errors,
+                );
+                errors.into_iter().for_each(|err| emitter.emit(err));
+                source_file
+            })
+    })
+    // a module in braces ends with }, only the file itself ends with an eof
+    .then_ignore(just(Token::Eof).or_not())
+}
+
+// imports a go package the generated code needs, unless the file already does
+fn push_go_import(use_statements: &mut Vec<UseStatement>, package: &str) {
+    let import = UseStatement::Go(package.to_string(), None);
+    if !use_statements.contains(&import) {
+        use_statements.push(import);
+    }
+}
+
+// the errors of items that only show up once all units are collected, like a second impl of
+// the same method, are pushed to errors and the item is left out
+fn source_file_from_units<'src>(
+    source_units: Vec<SourceUnit>,
+    errors: &mut Vec<Rich<'src, Token, SS>>,
+) -> SourceFile {
+    let mut function_definitions = Vec::new();
+    let mut type_definitions = Vec::new();
+    let mut struct_definitions = Vec::new();
+    let mut const_definitions = Vec::new();
+    let mut global_variables = Vec::new();
+    let mut use_statements = Vec::new();
+    let mut sub_modules = Vec::new();
+    let mut tsx_components = Vec::new();
+    let mut template_components = Vec::new();
+    let mut test_cases = Vec::new();
+    let mut cfg_items = Vec::new();
+    let mut c_declarations = Vec::new();
+    let mut impl_blocks = Vec::new();
+    let mut private_items = Vec::new();
+    let mut re_exports = Vec::new();
+
+    for source_unit in source_units {
+        use SourceUnit::*;
+        let source_unit = match source_unit {
+            Private(source_unit, span) => {
+                for name in source_unit.item_names().unwrap_or_default() {
+                    private_items.push(PrivateItem {
+                        name,
+                        module: Vec::new(),
+                        span,
+                    });
+                }
+                *source_unit
+            }
+            source_unit => source_unit,
+        };
+
+        match source_unit {
+            Func(def) => {
+                warn_unknown_attributes(&def.attributes, &[], "a function");
+                function_definitions.push(def)
+            }
+            Type(def) => {
+                warn_unknown_attributes(&def.attributes, &[], "a type");
+                type_definitions.push(def)
+            }
+            Struct(mut def) => {
+                if derives_json(&def.attributes) {
+                    push_go_import(&mut use_statements, "encoding/json");
+                }
+                if let Err(err) = def.apply_attributes() {
+                    errors.push(err);
+                }
+                struct_definitions.push(def)
+            }
+            Enum(def) => {
+                if derives_json(&def.attributes) {
+                    push_go_import(&mut use_statements, "encoding/json");
+                }
+                match def.to_definitions() {
+                    Ok((type_definition, variant_structs)) => {
+                        type_definitions.push(type_definition);
+                        struct_definitions.extend(variant_structs);
                     }
-                }),
-        ))
-        .repeated()
-        .collect::<Vec<_>>()
-        .map(|source_units| {
-            let mut function_definitions = Vec::new();
-            let mut type_definitions = Vec::new();
-            let mut struct_definitions = Vec::new();
-            let mut use_statements = Vec::new();
-            let mut sub_modules = Vec::new();
-            let mut tsx_components = Vec::new();
-            let mut template_components = Vec::new();
-
-            for source_unit in source_units {
-                use SourceUnit::*;
-                match source_unit {
-                    Func(def) => function_definitions.push(def),
-                    Type(def) => type_definitions.push(def),
-                    Struct(def) => struct_definitions.push(def),
-                    Use(def) => use_statements.push(def),
-                    Module(name, def) => sub_modules.push((name, def)),
-                    Component(tsx_component) => tsx_components.push(tsx_component),
-                    Template(duckx_component) => template_components.push(duckx_component),
+                    Err(err) => errors.push(err),
+                }
+            }
+            Const(def) => const_definitions.push(def),
+            Global(def) => global_variables.push(def),
+            Use(def) => use_statements.push(def),
+            PubUse(def, span) => {
+                for name in def.imported_names() {
+                    re_exports.push(ReExport {
+                        name,
+                        target: Vec::new(),
+                        span,
+                    });
                 }
+                use_statements.push(def);
             }
+            Module(name, def) => sub_modules.push((name, def)),
+            Component(tsx_component) => tsx_components.push(tsx_component),
+            Template(duckx_component) => template_components.push(duckx_component),
+            Test(test_case) => test_cases.push(test_case),
+            SourceUnit::Cfg(cfgs, source_unit) => {
+                cfg_items.push((cfgs, source_file_from_units(vec![*source_unit], errors)))
+            }
+            Extern(extern_function) => {
+                if extern_function.needs_unsafe() {
+                    push_go_import(&mut use_statements, "unsafe");
+                }
+                if extern_function.abi == Abi::C {
+                    match extern_function.cgo_preamble() {
+                        Ok(lines) => c_declarations.extend(lines),
+                        Err(err) => errors.push(err),
+                    }
+                } else {
+                    warn_unknown_attributes(&extern_function.attributes, &[], "a go function");
+                }
+                function_definitions.push(extern_function.to_function_definition());
+            }
+            Trait(def) => type_definitions.push(def.to_type_definition()),
+            Impl(impl_block) => impl_blocks.push(impl_block),
+            Private(..) => panic!("compiler error: private units are unwrapped above"),
+        }
+    }
 
-            SourceFile {
-                function_definitions,
-                type_definitions,
-                struct_definitions,
-                use_statements,
-                sub_modules,
-                tsx_components,
-                duckx_components: template_components,
+    // go methods have to be declared in the package of their type, so only structs of this file
+    // get the methods of an impl block. every other type gets extension functions with self as
+    // receiver, which can't implement traits
+    let mut trait_implementations = Vec::new();
+    for ImplBlock {
+        trait_type,
+        target: (target, span),
+        methods,
+        associated_functions,
+    } in impl_blocks
+    {
+        // Point::new is resolved like the variant of an enum, as a path starting with a type
+        for mut function in associated_functions {
+            let TypeExpr::RawTypeName(false, path, None) = &target else {
+                errors.push(Rich::custom(
+                    span,
+                    format!(
+                        "{target} can't have the associated function {}, only named types can \
+                         have associated functions",
+                        function.name
+                    ),
+                ));
+                continue;
+            };
+            let mut name = path.clone();
+            name.push(function.name);
+            function.name = mangle(&name);
+            function_definitions.push(function);
+        }
+
+        let struct_def = match &target {
+            TypeExpr::RawTypeName(false, path, None) if path.len() == 1 => struct_definitions
+                .iter_mut()
+                .find(|struct_def| struct_def.name == path[0]),
+            _ => None,
+        };
+
+        let Some(struct_def) = struct_def else {
+            if let Some((trait_type, _)) = trait_type {
+                errors.push(Rich::custom(
+                    span,
+                    format!(
+                        "Can't implement {trait_type} for {target}, {target} isn't a struct of \
+                         this file"
+                    ),
+                ));
+                continue;
             }
-        })
-    })
+
+            for mut method in methods {
+                if method.generics.is_some() {
+                    errors.push(Rich::custom(
+                        span,
+                        format!(
+                            "The method {} of {target} can't be generic, only structs of this \
+                             file can have generic methods",
+                            method.name
+                        ),
+                    ));
+                    continue;
+                }
+
+                method.name = extension_fn_name(&method.name, &target);
+                method
+                    .params
+                    .get_or_insert_with(Vec::new)
+                    .insert(0, ("self".to_string(), (target.clone(), span)));
+                function_definitions.push(method);
+            }
+            continue;
+        };
+
+        for method in methods {
+            if struct_def
+                .methods
+                .iter()
+                .any(|other| other.name == method.name)
+            {
+                errors.push(Rich::custom(
+                    span,
+                    format!(
+                        "{target} already has a method called {}, this implements it a second \
+                         time",
+                        method.name
+                    ),
+                ));
+                continue;
+            }
+            struct_def.methods.push(method);
+        }
+
+        if let Some(trait_type) = trait_type {
+            trait_implementations.push(TraitImplementation {
+                trait_type,
+                struct_name: (struct_def.name.clone(), span),
+            });
+        }
+    }
+
+    SourceFile {
+        function_definitions,
+        type_definitions,
+        struct_definitions,
+        const_definitions,
+        global_variables,
+        use_statements,
+        sub_modules,
+        tsx_components,
+        duckx_components: template_components,
+        test_cases,
+        cfg_items,
+        c_declarations,
+        trait_implementations,
+        private_items,
+        re_exports,
+        module_paths: Vec::new(),
+    }
 }
 
 #[cfg(test)]
@@ -824,11 +2038,14 @@ mod tests {
     use chumsky::Parser;
 
     use crate::parse::{
-        Field,
-        function_parser::FunctionDefintion,
-        lexer::lex_parser,
+        Field, Spanned,
+        attribute_parser::{Attribute, Cfg},
+        doc_comment_parser::strip_comments,
+        function_parser::{FunctionDefintion, extension_fn_name},
+        lexer::{Token, lex_parser, push_eof},
         make_input,
-        source_file_parser::{SourceFile, source_file_parser},
+        source_file_parser::{PrivateItem, SourceFile, TestCase, source_file_parser},
+        source_map::{self, FileId},
         struct_parser::StructDefinition,
         tsx_component_parser::TsxComponent,
         type_parser::{Duck, TypeDefinition, TypeExpr},
@@ -838,6 +2055,18 @@ mod tests {
             type_expr_into_empty_range, value_expr_into_empty_range,
         },
     };
+    use crate::semantics::ident_mangler::mangle;
+
+    fn private_items(names: &[&str]) -> Vec<PrivateItem> {
+        names
+            .iter()
+            .map(|name| PrivateItem {
+                name: name.to_string(),
+                module: Vec::new(),
+                span: empty_range(),
+            })
+            .collect()
+    }
 
     #[test]
     fn do_test() {
@@ -849,6 +2078,7 @@ mod tests {
                         name: "abc".into(),
                         ..Default::default()
                     }],
+                    private_items: private_items(&["abc"]),
                     ..Default::default()
                 },
             ),
@@ -873,14 +2103,8 @@ mod tests {
                         name: "MyComp".to_string(),
                         props_type: TypeExpr::Duck(Duck {
                             fields: vec![
-                                Field {
-                                    name: "x".to_string(),
-                                    type_expr: TypeExpr::String.into_empty_span(),
-                                },
-                                Field {
-                                    name: "y".to_string(),
-                                    type_expr: TypeExpr::Int.into_empty_span(),
-                                },
+                                Field::new("x".to_string(), TypeExpr::String.into_empty_span()),
+                                Field::new("y".to_string(), TypeExpr::Int.into_empty_span()),
                             ],
                         })
                         .into_empty_span(),
@@ -905,6 +2129,7 @@ mod tests {
                             ..Default::default()
                         },
                     ],
+                    private_items: private_items(&["abc", "xyz"]),
                     ..Default::default()
                 },
             ),
@@ -931,7 +2156,10 @@ mod tests {
                         })
                         .into_empty_span(),
                         generics: None,
+                        doc_comments: Vec::new(),
+                        attributes: Vec::new(),
                     }],
+                    private_items: private_items(&["X"]),
                     ..Default::default()
                 },
             ),
@@ -946,7 +2174,10 @@ mod tests {
                         )],
                         methods: vec![],
                         generics: None,
+                        doc_comments: Vec::new(),
+                        attributes: Vec::new(),
                     }],
+                    private_items: private_items(&["X"]),
                     ..Default::default()
                 },
             ),
@@ -1004,6 +2235,7 @@ mod tests {
                                 name: "abc".into(),
                                 ..Default::default()
                             }],
+                            private_items: private_items(&["abc"]),
                             ..Default::default()
                         },
                     )],
@@ -1038,14 +2270,52 @@ mod tests {
                         })
                         .into_empty_span(),
                         generics: None,
+                        doc_comments: Vec::new(),
+                        attributes: Vec::new(),
                     }],
+                    private_items: private_items(&["abc", "X", "xyz"]),
+                    ..Default::default()
+                },
+            ),
+            (
+                "fn test(){}test \"it works\" {}",
+                SourceFile {
+                    function_definitions: vec![FunctionDefintion {
+                        name: "test".into(),
+                        ..Default::default()
+                    }],
+                    test_cases: vec![TestCase {
+                        name: "it works".into(),
+                        value_expr: ValueExpr::Block(vec![]).into_empty_span(),
+                    }],
+                    private_items: private_items(&["test"]),
+                    ..Default::default()
+                },
+            ),
+            (
+                "#[cfg(feature = \"http\")] fn serve(){}",
+                SourceFile {
+                    cfg_items: vec![(
+                        vec![(Cfg::Feature("http".into()), empty_range())],
+                        SourceFile {
+                            function_definitions: vec![FunctionDefintion {
+                                name: "serve".into(),
+                                ..Default::default()
+                            }],
+                            private_items: private_items(&["serve"]),
+                            ..Default::default()
+                        },
+                    )],
                     ..Default::default()
                 },
             ),
         ];
 
         for (src, exp) in test_cases {
-            let lex = lex_parser("test", "").parse(src).into_result().expect(src);
+            let lex = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
             let mut parse = source_file_parser(PathBuf::from("test_files"), make_input)
                 .parse(make_input(empty_range(), &lex))
                 .into_result()
@@ -1060,6 +2330,901 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tests_into_functions() {
+        let test_case = |name: &str| TestCase {
+            name: name.into(),
+            value_expr: ValueExpr::Block(vec![]).into_empty_span(),
+        };
+
+        let mut source_file = SourceFile {
+            function_definitions: vec![
+                FunctionDefintion {
+                    name: "main".into(),
+                    ..Default::default()
+                },
+                FunctionDefintion {
+                    name: "duck_test_0".into(),
+                    ..Default::default()
+                },
+            ],
+            sub_modules: vec![(
+                "sub".into(),
+                SourceFile {
+                    test_cases: vec![test_case("in sub")],
+                    ..Default::default()
+                },
+            )],
+            test_cases: vec![test_case("a"), test_case("say \"hi\"\n")],
+            ..Default::default()
+        };
+
+        let tests = source_file.tests_into_functions();
+        assert_eq!(
+            tests,
+            vec![
+                ("in sub".into(), vec!["sub".into(), "duck_test_0".into()]),
+                ("a".into(), vec!["duck_test_1".into()]),
+                ("say \"hi\"\n".into(), vec!["duck_test_2".into()]),
+            ]
+        );
+        assert!(source_file.test_cases.is_empty());
+        assert!(source_file.sub_modules[0].1.test_cases.is_empty());
+
+        source_file.add_test_runner(&tests);
+        let names = source_file
+            .function_definitions
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["duck_test_0", "duck_test_1", "duck_test_2", "main"]
+        );
+
+        let ValueExpr::Block(runner) = &source_file.function_definitions[3].value_expr.0 else {
+            panic!("the runner is a block");
+        };
+        assert_eq!(
+            runner[6].0,
+            ValueExpr::InlineGo(r#"fmt.Print("test say \"hi\"\n ... ")"#.to_string())
+        );
+
+        // tests never survive flattening, so a normal build doesn't contain them
+        let flattened = SourceFile {
+            test_cases: vec![test_case("a")],
+            ..Default::default()
+        }
+        .flatten(&vec![], false);
+        assert!(flattened.test_cases.is_empty());
+    }
+
+    #[test]
+    fn test_apply_cfg() {
+        let src = r#"
+            fn always() {}
+            #[cfg(feature = "a")] fn only_a() {}
+            #[cfg(feature = "a")] #[cfg(feature = "b")] fn a_and_b() {}
+            #[cfg(target_os = "windows")] fn only_windows() {}
+            module sub {
+                #[cfg(feature = "b")] fn only_b() {}
+            }
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src);
+
+        let test_cases = vec![
+            (vec![], "linux", vec!["always"], vec![]),
+            (vec!["a"], "linux", vec!["always", "only_a"], vec![]),
+            (vec!["b"], "linux", vec!["always"], vec!["only_b"]),
+            (
+                vec!["a", "b"],
+                "linux",
+                vec!["always", "only_a", "a_and_b"],
+                vec!["only_b"],
+            ),
+            (vec![], "windows", vec!["always", "only_windows"], vec![]),
+            (vec!["windows"], "linux", vec!["always"], vec![]),
+        ];
+
+        for (features, target_os, expected, expected_sub) in test_cases {
+            let features = features.into_iter().map(String::from).collect::<Vec<_>>();
+            let mut source_file = parse.clone();
+            source_file.apply_cfg(&features, target_os);
+
+            let names = |source_file: &SourceFile| {
+                source_file
+                    .function_definitions
+                    .iter()
+                    .map(|f| f.name.clone())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(names(&source_file), expected, "{features:?}");
+            assert_eq!(
+                names(&source_file.sub_modules[0].1),
+                expected_sub,
+                "{features:?}"
+            );
+            assert!(source_file.cfg_items.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_impl_blocks() {
+        let src = r#"
+            trait Show = { fn show() -> String; };
+            struct Point = { x: Int } impl { fn len() -> Int { return 1; } };
+            impl Show for Point { fn show() -> String { return "point"; } }
+            impl Point { static fn new() -> Point { return Point { x: 0 }; } }
+            type Vec2 = { x: Int };
+            impl Vec2 { fn x_twice() -> Int { return self.x * 2; } }
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src);
+
+        assert_eq!(parse.type_definitions[0].name, "Show");
+        assert!(matches!(
+            parse.type_definitions[0].type_expression.0,
+            TypeExpr::Duck(..)
+        ));
+
+        let methods = parse.struct_definitions[0]
+            .methods
+            .iter()
+            .map(|method| method.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(methods, vec!["len", "show"]);
+
+        assert_eq!(parse.trait_implementations.len(), 1);
+        assert_eq!(parse.trait_implementations[0].struct_name.0, "Point");
+        let names = parse
+            .function_definitions
+            .iter()
+            .map(|function| function.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                mangle(&["Point", "new"]),
+                extension_fn_name(
+                    "x_twice",
+                    &TypeExpr::RawTypeName(false, vec!["Vec2".into()], None)
+                ),
+            ]
+        );
+        assert!(
+            parse.function_definitions[0]
+                .params
+                .as_ref()
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            parse.function_definitions[1].params.as_ref().unwrap()[0].0,
+            "self"
+        );
+    }
+
+    #[test]
+    fn test_extern_functions() {
+        let src = r#"
+            extern "C" fn abs(x: Int) -> Int;
+            extern "C" fn puts(s: String);
+            extern "C" fn strlen(s: String) -> Int;
+            extern "Go" fn strconv.Itoa(i: Int) -> String;
+            #[link(name = "m")]
+            extern "C" fn sqrt(x: Float) -> Float;
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src);
+
+        assert_eq!(
+            parse.c_declarations,
+            vec![
+                "long long abs(long long);",
+                "void puts(const char*);",
+                "long long strlen(const char*);",
+                "#cgo LDFLAGS: -lm",
+                "double sqrt(double);",
+            ]
+        );
+        assert_eq!(
+            parse.use_statements,
+            vec![UseStatement::Go("unsafe".into(), None)]
+        );
+        assert_eq!(
+            parse
+                .function_definitions
+                .iter()
+                .map(|f| f.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["abs", "puts", "strlen", "Itoa"]
+        );
+    }
+
+    #[test]
+    fn test_enums() {
+        let src = r#"
+            module geo {
+                enum Shape = { Circle(Float), Empty };
+                fn unit() -> Shape { Shape::Circle(1.0) }
+            }
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src)
+            .flatten(&vec![], false);
+
+        let circle = mangle(&["geo", "Shape", "Circle"]);
+        let empty = mangle(&["geo", "Shape", "Empty"]);
+        assert_eq!(
+            parse
+                .struct_definitions
+                .iter()
+                .map(|s| s.name.clone())
+                .collect::<Vec<_>>(),
+            vec![circle.clone(), empty.clone()]
+        );
+
+        assert_eq!(parse.type_definitions[0].name, mangle(&["geo", "Shape"]));
+        let TypeExpr::Or(variants) = &parse.type_definitions[0].type_expression.0 else {
+            panic!("an enum has to be a union of its variants")
+        };
+        assert_eq!(
+            variants.iter().map(|v| v.0.clone()).collect::<Vec<_>>(),
+            vec![
+                TypeExpr::TypeName(true, circle.clone(), None),
+                TypeExpr::TypeName(true, empty, None),
+            ]
+        );
+
+        let unit = &parse.function_definitions[0];
+        let ValueExpr::Block(body) = &unit.value_expr.0 else {
+            panic!("a function body has to be a block")
+        };
+        let ValueExpr::FunctionCall { target, .. } = &body[0].0 else {
+            panic!("expected the construction of a circle")
+        };
+        assert_eq!(target.0, ValueExpr::Variable(true, circle, None));
+    }
+
+    fn parse_with_wildcards(src: &str) -> SourceFile {
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let mut parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src);
+        parse.resolve_wildcard_imports(None);
+        parse
+    }
+
+    #[test]
+    fn test_import_aliases_and_wildcards() {
+        let src = r#"
+            module geometry {
+                module shapes {
+                    pub fn circle() -> Int { 1 }
+                }
+                pub fn area() -> Int { 2 }
+                fn secret() -> Int { 3 }
+            }
+            use geometry::shapes as sh;
+            use geometry::*;
+            fn main() -> Int { sh::circle() + area() }
+        "#;
+        let parse = parse_with_wildcards(src);
+        assert_eq!(
+            parse.use_statements[1],
+            UseStatement::Regular(
+                false,
+                vec![
+                    Indicator::Module("geometry".into()),
+                    Indicator::Symbols(vec!["area".into(), "shapes".into()]),
+                ],
+            )
+        );
+
+        let parse = parse.flatten(&vec![], false);
+        let main = parse
+            .function_definitions
+            .iter()
+            .find(|f| f.name == "main")
+            .unwrap();
+        let ValueExpr::Block(body) = &main.value_expr.0 else {
+            panic!("a function body has to be a block")
+        };
+        let ValueExpr::Add(lhs, rhs) = &body[0].0 else {
+            panic!("expected the sum of both calls")
+        };
+        for (call, path) in [
+            (lhs, ["geometry", "shapes", "circle"].as_slice()),
+            (rhs, ["geometry", "area"].as_slice()),
+        ] {
+            let ValueExpr::FunctionCall { target, .. } = &call.0 else {
+                panic!("expected a call")
+            };
+            assert_eq!(target.0, ValueExpr::Variable(true, mangle(path), None));
+        }
+    }
+
+    #[test]
+    fn test_wildcard_shadowing() {
+        let src = r#"
+            module a {
+                pub fn f() -> Int { 1 }
+                pub fn g() -> Int { 2 }
+            }
+            module b {
+                pub fn f() -> Int { 3 }
+            }
+            use a::*;
+            use b::*;
+            use a::{f};
+            fn g() -> Int { 4 }
+        "#;
+        let parse = parse_with_wildcards(src);
+        assert_eq!(
+            parse.use_statements[..2],
+            [
+                UseStatement::Regular(
+                    false,
+                    vec![Indicator::Module("a".into()), Indicator::Symbols(vec![])],
+                ),
+                UseStatement::Regular(
+                    false,
+                    vec![Indicator::Module("b".into()), Indicator::Symbols(vec![])],
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "f is imported by both a::* and b::*")]
+    fn test_wildcard_collision() {
+        parse_with_wildcards(
+            r#"
+            module a { pub fn f() -> Int { 1 } }
+            module b { pub fn f() -> Int { 2 } }
+            use a::*;
+            use b::*;
+        "#,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "can't find the module nowhere")]
+    fn test_wildcard_unknown_module() {
+        parse_with_wildcards("use nowhere::*;");
+    }
+
+    #[test]
+    fn test_re_exports() {
+        let src = r#"
+            module lib {
+                module inner {
+                    pub fn area() -> Int { 1 }
+                }
+                pub use inner::{area};
+                pub use inner::area as surface;
+            }
+            module facade {
+                pub use ::lib::{surface};
+            }
+            use facade::*;
+            fn main() -> Int { lib::area() + surface() }
+        "#;
+        let parse = parse_with_wildcards(src);
+        let lib = parse.module_at(&["lib".into()]).unwrap();
+        assert_eq!(
+            lib.re_exports
+                .iter()
+                .map(|re_export| re_export.name.as_str())
+                .collect::<Vec<_>>(),
+            ["area", "surface"]
+        );
+
+        let parse = parse.flatten(&vec![], false);
+        assert_eq!(
+            parse
+                .re_exports
+                .iter()
+                .map(|re_export| (re_export.name.clone(), re_export.target.join("::")))
+                .collect::<Vec<_>>(),
+            [
+                (mangle(&["facade", "surface"]), "lib::surface".to_string()),
+                (mangle(&["lib", "area"]), "lib::inner::area".to_string()),
+                (mangle(&["lib", "surface"]), "lib::inner::area".to_string()),
+            ]
+        );
+
+        let main = parse
+            .function_definitions
+            .iter()
+            .find(|f| f.name == "main")
+            .unwrap();
+        let ValueExpr::Block(body) = &main.value_expr.0 else {
+            panic!("a function body has to be a block")
+        };
+        let ValueExpr::Add(lhs, rhs) = &body[0].0 else {
+            panic!("expected the sum of both calls")
+        };
+        for call in [lhs, rhs] {
+            let ValueExpr::FunctionCall { target, .. } = &call.0 else {
+                panic!("expected a call")
+            };
+            assert_eq!(
+                target.0,
+                ValueExpr::Variable(true, mangle(&["lib", "inner", "area"]), None)
+            );
+        }
+    }
+
+    #[test]
+    fn test_builtins() {
+        let src = r#"
+            fn builtins(x: Bool) { assert(x); assert(x, "x must hold"); panic("oh no"); todo(); }
+            fn shadowed_by_variable() { let panic = 1; panic(2); }
+            module m {
+                fn assert(x: Bool) {}
+                fn shadowed_by_item() { assert(true); }
+            }
+        "#;
+        let flat = parse_with_wildcards(src).flatten(&vec![], false);
+
+        let calls = |name: &str| {
+            let function = flat
+                .function_definitions
+                .iter()
+                .find(|function| function.name == name)
+                .expect(name);
+            let ValueExpr::Block(body) = &function.value_expr.0 else {
+                panic!("{name} should have a block")
+            };
+            body.iter()
+                .filter_map(|(expr, _)| match expr {
+                    ValueExpr::FunctionCall { target, params, .. } => match &target.0 {
+                        ValueExpr::Variable(_, target, _) => Some((target.clone(), params.len())),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let std_error = |name: &str| mangle(&["std", "error", name]);
+        assert_eq!(
+            calls("builtins"),
+            vec![
+                (std_error("assert_at"), 3),
+                (std_error("assert_at"), 3),
+                (std_error("panic_at"), 2),
+                (std_error("panic_at"), 2),
+            ]
+        );
+        assert_eq!(
+            calls("shadowed_by_variable"),
+            vec![("panic".to_string(), 1)]
+        );
+        assert_eq!(
+            calls(&mangle(&["m", "shadowed_by_item"])),
+            vec![(mangle(&["m", "assert"]), 1)]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong number of arguments for panic")]
+    fn test_builtin_arity() {
+        let src = "fn main() { panic(); }";
+        let file_id = source_map::add_test_file("builtin_arity_test", src);
+        let lex = lex_parser(file_id).parse(src).into_result().unwrap();
+        source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .unwrap()
+            .flatten(&vec![], false);
+    }
+
+    #[test]
+    fn test_invalid_pub_use() {
+        for src in ["pub use a;", "pub use a::*;", "pub use go \"fmt\";"] {
+            let lex = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+                .parse(make_input(empty_range(), &lex));
+            assert!(parse.has_errors(), "{src}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "a::f is private and can't be re-exported")]
+    fn test_re_export_of_private_item() {
+        parse_with_wildcards("module a { fn f() -> Int { 1 } } pub use a::{f};")
+            .flatten(&vec![], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "f re-exports a::f, which doesn't exist")]
+    fn test_re_export_of_missing_item() {
+        parse_with_wildcards("module a { } pub use a::{f};").flatten(&vec![], false);
+    }
+
+    #[test]
+    #[should_panic(expected = "never reaches an item")]
+    fn test_re_export_cycle() {
+        parse_with_wildcards("module a { pub use ::b::{f}; } module b { pub use ::a::{f}; }")
+            .flatten(&vec![], false);
+    }
+
+    #[test]
+    fn test_eof() {
+        let src = "fn a() {}";
+        let mut lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        push_eof(&mut lex, FileId::EMPTY, src.len());
+        let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src);
+        assert_eq!(parse.function_definitions.len(), 1);
+
+        let src = "fn a() {";
+        let mut lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        push_eof(&mut lex, FileId::EMPTY, src.len());
+        let errors = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_errors();
+        assert_eq!(errors[0].found(), Some(&Token::Eof));
+        assert_eq!(errors[0].span().start, src.len() as u32);
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let src = r#"
+            fn a() { let x = ; }
+            fn b() -> Int { 1 }
+            type T = { x: };
+            const C: Int = { 2 };
+            struct S = { x Int };
+            fn c() {}
+        "#;
+        let mut lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        push_eof(&mut lex, FileId::EMPTY, src.len());
+        let (parse, errors) = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_output_errors();
+
+        // every broken item has its own error and the items around them are still there
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        let parse = parse.expect("a partial source file");
+        let function_names = parse
+            .function_definitions
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(function_names, vec!["b", "c"]);
+        assert_eq!(parse.const_definitions.len(), 1);
+        assert!(parse.type_definitions.is_empty());
+        assert!(parse.struct_definitions.is_empty());
+    }
+
+    #[test]
+    fn test_pub_items() {
+        let src = r#"
+            pub fn public() {}
+            fn private() {}
+            pub struct Point = { x: Int };
+            enum Dir = { Up, Down };
+            module sub {
+                pub type T = { x: Int };
+                trait Show = { fn show() -> String; };
+            }
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src);
+
+        let names = |private_items: &[PrivateItem]| {
+            private_items
+                .iter()
+                .map(|private_item| (private_item.name.clone(), private_item.module.join("::")))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&parse.private_items),
+            vec![
+                ("private".to_string(), String::new()),
+                ("Dir".to_string(), String::new()),
+                (mangle(&["Dir", "Up"]), String::new()),
+                (mangle(&["Dir", "Down"]), String::new()),
+            ]
+        );
+
+        let flattened = parse.flatten(&vec!["lib".to_string()], false);
+        assert_eq!(
+            names(&flattened.private_items),
+            vec![
+                (mangle(&["lib", "sub", "Show"]), "lib::sub".to_string()),
+                (mangle(&["lib", "private"]), "lib".to_string()),
+                (mangle(&["lib", "Dir"]), "lib".to_string()),
+                (mangle(&["lib", "Dir", "Up"]), "lib".to_string()),
+                (mangle(&["lib", "Dir", "Down"]), "lib".to_string()),
+            ]
+        );
+
+        for invalid in ["pub use x;", "pub module sub {}", "pub pub fn a() {}"] {
+            let lex = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                source_file_parser(PathBuf::from("test_files"), make_input)
+                    .parse(make_input(empty_range(), &lex))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_const_definitions() {
+        let src = r#"
+            /// the root
+            const ROOT = 1;
+            module sub {
+                pub const MAX: Int = 1024 * 64;
+            }
+            fn main() { sub::MAX; ROOT; }
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src);
+
+        assert_eq!(
+            parse.const_definitions[0].doc_comments,
+            vec!["the root".to_string()]
+        );
+        let (_, sub) = &parse.sub_modules[0];
+        assert_eq!(sub.const_definitions[0].name, "MAX");
+        assert!(sub.const_definitions[0].doc_comments.is_empty());
+
+        let flattened = parse.flatten(&vec!["lib".to_string()], false);
+        let names = flattened
+            .const_definitions
+            .iter()
+            .map(|const_def| const_def.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![mangle(&["lib", "sub", "MAX"]), mangle(&["lib", "ROOT"])]
+        );
+
+        let ValueExpr::Block(exprs) = &flattened.function_definitions[0].value_expr.0 else {
+            panic!("main has to be a block")
+        };
+        let used = exprs
+            .iter()
+            .map(|expr| match &expr.0 {
+                ValueExpr::Variable(_, name, _) => name.clone(),
+                other => panic!("expected a variable, got {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            used,
+            vec![mangle(&["lib", "sub", "MAX"]), mangle(&["lib", "ROOT"])]
+        );
+    }
+
+    #[test]
+    fn test_global_variables() {
+        let src = r#"
+            let counter = sub::start * 2;
+            module sub {
+                pub let start: Int = 21;
+            }
+            fn main() { counter = counter + 1; }
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &lex))
+            .into_result()
+            .expect(src);
+
+        assert_eq!(parse.global_variables[0].name, "counter");
+        let (_, sub) = &parse.sub_modules[0];
+        assert_eq!(sub.global_variables[0].name, "start");
+        assert!(sub.private_items.is_empty());
+
+        let flattened = parse.flatten(&vec!["lib".to_string()], false);
+        let names = flattened
+            .global_variables
+            .iter()
+            .map(|global| global.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                mangle(&["lib", "sub", "start"]),
+                mangle(&["lib", "counter"])
+            ]
+        );
+
+        let ValueExpr::Mul(start, _) = &flattened.global_variables[1].initializer.0 else {
+            panic!("the initializer of counter has to be a multiplication")
+        };
+        let ValueExpr::Variable(_, start, _) = &start.0 else {
+            panic!("expected a variable, got {:?}", start.0)
+        };
+        assert_eq!(*start, mangle(&["lib", "sub", "start"]));
+
+        let ValueExpr::Block(exprs) = &flattened.function_definitions[0].value_expr.0 else {
+            panic!("main has to be a block")
+        };
+        let ValueExpr::VarAssign(assignment) = &exprs[0].0 else {
+            panic!("expected an assignment, got {:?}", exprs[0].0)
+        };
+        let ValueExpr::Variable(_, target, _) = &assignment.0.target.0 else {
+            panic!("expected a variable, got {:?}", assignment.0.target.0)
+        };
+        assert_eq!(*target, mangle(&["lib", "counter"]));
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let src = r#"
+            /// adds one
+            /// to x
+            fn add_one(x: Int) -> Int { x + 1 }
+
+            /** a point */
+            #[derive(Eq)]
+            struct Point = { x: Int, y: Int } impl {
+                /// the distance to zero
+                fn len() -> Int { 0 }
+            };
+
+            // not a doc comment
+            type Id = Int;
+
+            /// only with the feature
+            #[cfg(feature = "extra")]
+            fn extra() {}
+
+            fn main() {
+                /// not attached to anything
+                let x = 1;
+            }
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let mut parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &strip_comments(&lex)))
+            .into_result()
+            .expect(src);
+        parse.apply_cfg(&["extra".to_string()], "linux");
+
+        let doc_comments = |name: &str| {
+            parse
+                .function_definitions
+                .iter()
+                .find(|f| f.name == name)
+                .unwrap()
+                .doc_comments
+                .clone()
+        };
+        assert_eq!(doc_comments("add_one"), vec!["adds one", "to x"]);
+        assert_eq!(doc_comments("extra"), vec!["only with the feature"]);
+        assert!(doc_comments("main").is_empty());
+
+        let point = &parse.struct_definitions[0];
+        assert_eq!(point.doc_comments, vec!["a point"]);
+        assert_eq!(
+            point
+                .methods
+                .iter()
+                .find(|m| m.name == "len")
+                .unwrap()
+                .doc_comments,
+            vec!["the distance to zero"]
+        );
+        assert!(parse.type_definitions[0].doc_comments.is_empty());
+    }
+
+    #[test]
+    fn test_attributes() {
+        let src = r#"
+            #[inline] #[cold] fn f() {}
+            #[json(rename = "t")] type T = Int;
+            #[serde] #[derive(Eq)] struct S = { #[skip] x: Int };
+            #[cfg(feature = "extra")] #[inline] fn g() {}
+        "#;
+        let lex = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let mut parse = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &strip_comments(&lex)))
+            .into_result()
+            .expect(src);
+        parse.apply_cfg(&["extra".to_string()], "linux");
+
+        let names = |attributes: &Vec<Spanned<Attribute>>| {
+            attributes
+                .iter()
+                .map(|(attribute, _)| attribute.name.clone())
+                .collect::<Vec<_>>()
+        };
+        let function = |name: &str| {
+            parse
+                .function_definitions
+                .iter()
+                .find(|f| f.name == name)
+                .unwrap()
+        };
+        assert_eq!(names(&function("f").attributes), vec!["inline", "cold"]);
+        assert_eq!(names(&function("g").attributes), vec!["inline"]);
+
+        let type_def = &parse.type_definitions[0];
+        assert_eq!(names(&type_def.attributes), vec!["json"]);
+        assert_eq!(type_def.attributes[0].0.key_values[0].1.0, "t".to_string());
+
+        let struct_def = &parse.struct_definitions[0];
+        assert_eq!(names(&struct_def.attributes), vec!["serde", "derive"]);
+        assert_eq!(names(&struct_def.fields[0].attributes), vec!["skip"]);
+        // unknown attributes only warn, derive still does its thing
+        assert!(struct_def.methods.iter().any(|m| m.name == "eq"));
+    }
+
     #[test]
     fn test_mod_structure() {
         let test_cases = vec![
@@ -1310,13 +3475,39 @@ mod tests {
                     ..Default::default()
                 },
             ),
+            (
+                "09.duck",
+                SourceFile {
+                    sub_modules: vec![(
+                        "geometry".into(),
+                        SourceFile {
+                            function_definitions: vec![FunctionDefintion {
+                                name: "area".into(),
+                                ..Default::default()
+                            }],
+                            sub_modules: vec![(
+                                "shapes".into(),
+                                SourceFile {
+                                    function_definitions: vec![FunctionDefintion {
+                                        name: "circle".into(),
+                                        ..Default::default()
+                                    }],
+                                    ..Default::default()
+                                },
+                            )],
+                            ..Default::default()
+                        },
+                    )],
+                    ..Default::default()
+                },
+            ),
         ];
 
         let dir = PathBuf::from("test_files").join("modules");
 
         for (main_file, mut expected) in test_cases {
             let src = std::fs::read_to_string(dir.join(main_file)).unwrap();
-            let lex = lex_parser("test", "").parse(&src).unwrap();
+            let lex = lex_parser(FileId::EMPTY).parse(&src).unwrap();
             let mut got = source_file_parser(dir.clone(), make_input)
                 .parse(make_input(empty_range(), &lex))
                 .unwrap();
@@ -1333,6 +3524,57 @@ mod tests {
 
             assert_eq!(expected, got, "{main_file}");
         }
+
+        let lex = lex_parser(FileId::EMPTY).parse("module missing;").unwrap();
+        assert!(
+            source_file_parser(dir, make_input)
+                .parse(make_input(empty_range(), &lex))
+                .has_errors()
+        );
+    }
+
+    #[test]
+    fn test_module_errors_are_collected() {
+        let dir = tempfile::tempdir().unwrap();
+        let module_dir = dir.path().join("broken");
+        std::fs::create_dir_all(&module_dir).unwrap();
+        std::fs::write(module_dir.join("a.duck"), "fn a( {}").unwrap();
+        std::fs::write(module_dir.join("b.duck"), "fn b() {} fn c( {}").unwrap();
+        std::fs::write(
+            dir.path().join("other.duck"),
+            "struct S = ;\nconst C: String = \"\\q\";",
+        )
+        .unwrap();
+
+        let src = "module broken; module other; fn main() {}";
+        let file_id = source_map::add_test_file("module_errors_test", src);
+        let tokens = crate::lex(file_id, src);
+        let (src_file, errors) = crate::parse_partial(dir.path(), file_id, src, &tokens);
+
+        // every broken module is reported in its own file, in the order of the declarations
+        let mut file_names = errors
+            .iter()
+            .map(|err| err.span().context.file_name().to_string())
+            .collect::<Vec<_>>();
+        file_names.dedup();
+        assert_eq!(
+            file_names,
+            [
+                module_dir.join("a.duck"),
+                module_dir.join("b.duck"),
+                dir.path().join("other.duck"),
+            ]
+            .map(|path| path.to_string_lossy().to_string())
+        );
+        assert_eq!(src_file.function_definitions[0].name, "main");
+        assert_eq!(src_file.sub_modules[0].1.function_definitions[0].name, "b");
+
+        // a module is lexed like any other file, its lexer errors say what's wrong
+        assert!(errors.iter().any(|err| {
+            err.reason()
+                .to_string()
+                .contains("unknown escape sequence \\q")
+        }));
     }
 
     #[test]
@@ -1370,9 +3612,11 @@ mod tests {
             //                 }],
             //                 methods: vec![],
             //                 generics: None,
+            doc_comments: Vec::new(),
             //             })
             //             .into_empty_span(),
             //             generics: None,
+            doc_comments: Vec::new(),
             //         }],
             //         use_statements: vec![UseStatement::Go("fmt".into(), None)],
             //         function_definitions: vec![
@@ -1394,6 +3638,7 @@ mod tests {
             //                 ])
             //                 .into_empty_span(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //             FunctionDefintion {
             //                 name: mangle(&["abc", "im_calling_a_sub_module"]),
@@ -1403,6 +3648,7 @@ mod tests {
             //                 ])
             //                 .into_empty_span(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //             FunctionDefintion {
             //                 name: mangle(&["abc", "lol", "called"]),
@@ -1422,6 +3668,7 @@ mod tests {
             //                 ])
             //                 .into_empty_span(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //         ],
             //         ..Default::default()
@@ -1451,9 +3698,11 @@ mod tests {
             //                                 )
             //                                 .into_empty_span(),
             //                             }],
+            doc_comments: Vec::new(),
             //                         })
             //                         .into_empty_span(),
             //                         generics: None,
+            doc_comments: Vec::new(),
             //                     }],
             //                     function_definitions: vec![FunctionDefintion {
             //                         name: "im_calling_a_sub_module".into(),
@@ -1463,6 +3712,7 @@ mod tests {
             //                         ])
             //                         .into_empty_span(),
             //                         ..Default::default()
+            doc_comments: Vec::new(),
             //                     }],
             //                     sub_modules: vec![(
             //                         "lol".into(),
@@ -1486,6 +3736,7 @@ mod tests {
             //                                     ])
             //                                     .into_empty_span(),
             //                                     ..Default::default()
+            doc_comments: Vec::new(),
             //                                 },
             //                                 FunctionDefintion {
             //                                     name: "called".into(),
@@ -1504,6 +3755,7 @@ mod tests {
             //                                     ])
             //                                     .into_empty_span(),
             //                                     ..Default::default()
+            doc_comments: Vec::new(),
             //                                 },
             //                             ],
             //                             ..SourceFile::default()
@@ -1535,6 +3787,7 @@ mod tests {
             //         function_definitions: vec![FunctionDefintion {
             //             name: "single_my_single_fun".into(),
             //             ..Default::default()
+            doc_comments: Vec::new(),
             //         }],
             //         ..Default::default()
             //     },
@@ -1547,6 +3800,7 @@ mod tests {
             //                     function_definitions: vec![FunctionDefintion {
             //                         name: "my_single_fun".into(),
             //                         ..Default::default()
+            doc_comments: Vec::new(),
             //                     }],
             //                     ..Default::default()
             //                 },
@@ -1563,11 +3817,13 @@ mod tests {
             //                 value_expr: ValueExpr::String("Hello from module".into())
             //                     .into_empty_span_and_block(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //             FunctionDefintion {
             //                 name: "multiple_some_xyz_func".into(),
             //                 value_expr: ValueExpr::Int(1).into_empty_span_and_block(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //         ],
             //         ..Default::default()
@@ -1582,11 +3838,13 @@ mod tests {
             //                         value_expr: ValueExpr::String("Hello from module".into())
             //                             .into_empty_span_and_block(),
             //                         ..Default::default()
+            doc_comments: Vec::new(),
             //                     },
             //                     FunctionDefintion {
             //                         name: "some_xyz_func".into(),
             //                         value_expr: ValueExpr::Int(1).into_empty_span_and_block(),
             //                         ..Default::default()
+            doc_comments: Vec::new(),
             //                     },
             //                 ],
             //                 ..Default::default()
@@ -1603,23 +3861,28 @@ mod tests {
             //                 value_expr: ValueExpr::String("Hello from module".into())
             //                     .into_empty_span_and_block(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //             FunctionDefintion {
             //                 name: "multiple_some_xyz_func".into(),
             //                 value_expr: ValueExpr::Int(1).into_empty_span_and_block(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //             FunctionDefintion {
             //                 name: "nested_hello_from_x".into(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //             FunctionDefintion {
             //                 name: "nested_level1_hello_from_y".into(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //             FunctionDefintion {
             //                 name: "nested_level1_level2_hello_from_z".into(),
             //                 ..Default::default()
+            doc_comments: Vec::new(),
             //             },
             //         ],
             //         ..Default::default()
@@ -1635,11 +3898,13 @@ mod tests {
             //                             value_expr: ValueExpr::String("Hello from module".into())
             //                                 .into_empty_span_and_block(),
             //                             ..Default::default()
+            doc_comments: Vec::new(),
             //                         },
             //                         FunctionDefintion {
             //                             name: "some_xyz_func".into(),
             //                             value_expr: ValueExpr::Int(1).into_empty_span_and_block(),
             //                             ..Default::default()
+            doc_comments: Vec::new(),
             //                         },
             //                     ],
             //                     ..Default::default()
@@ -1651,6 +3916,7 @@ mod tests {
             //                     function_definitions: vec![FunctionDefintion {
             //                         name: "hello_from_x".into(),
             //                         ..Default::default()
+            doc_comments: Vec::new(),
             //                     }],
             //                     sub_modules: vec![(
             //                         "level1".into(),
@@ -1658,6 +3924,7 @@ mod tests {
             //                             function_definitions: vec![FunctionDefintion {
             //                                 name: "hello_from_y".into(),
             //                                 ..Default::default()
+            doc_comments: Vec::new(),
             //                             }],
             //                             sub_modules: vec![(
             //                                 "level2".into(),
@@ -1665,6 +3932,7 @@ mod tests {
             //                                     function_definitions: vec![FunctionDefintion {
             //                                         name: "hello_from_z".into(),
             //                                         ..Default::default()
+            doc_comments: Vec::new(),
             //                                     }],
             //                                     ..Default::default()
             //                                 },
@@ -0,0 +1,224 @@
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use lazy_static::lazy_static;
+use memmap2::Mmap;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+impl FileId {
+    // used for spans that don't point into a real file (tests, generated code)
+    pub const EMPTY: FileId = FileId(u32::MAX);
+}
+
+// spans store their offsets as u32, so no source file may be longer than this
+pub const MAX_FILE_SIZE: u64 = u32::MAX as u64;
+
+// files below this size are read directly, mapping them costs more than it saves
+const MMAP_THRESHOLD: u64 = 64 * 1024;
+
+// the text of a source file, always owned. a &str into a mapping would stop being valid (or
+// fault) as soon as someone else truncates or rewrites the file while it's still in use
+pub type SourceText = String;
+
+// big (mostly generated) files are memory mapped and copied out of the mapping once, instead of
+// being read into a buffer that has to grow on the way. every file that can't be mapped falls
+// back to a plain read
+pub fn read_source(path: &Path) -> io::Result<SourceText> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len > MAX_FILE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            format!("source files can be at most 4 GiB, this one is {len} bytes"),
+        ));
+    }
+
+    if len < MMAP_THRESHOLD {
+        return io::read_to_string(file);
+    }
+
+    // safety: the mapping is dropped before read_source returns, nothing points into it. a file
+    // that's changed by someone else while it's copied can still end up torn, like with a read
+    let Ok(mmap) = (unsafe { Mmap::map(&file) }) else {
+        return std::fs::read_to_string(path);
+    };
+
+    std::str::from_utf8(&mmap)
+        .map(str::to_owned)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[derive(Debug, Clone)]
+struct SourceMapEntry {
+    // files are told apart by their canonical path, so two main.duck in different directories
+    // are two files. sources that aren't on disk (tests) are keyed by the path as it is given
+    key: PathBuf,
+    name: Arc<str>,
+    contents: Arc<SourceText>,
+}
+
+fn file_key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceMapEntry>,
+}
+
+lazy_static! {
+    static ref SOURCE_MAP: RwLock<SourceMap> = RwLock::new(SourceMap::default());
+}
+
+impl SourceMap {
+    // re-adding a file with the same path replaces its contents and keeps the id,
+    // so long running processes (watch mode, lsp) don't grow with every recompile.
+    // the name is what diagnostics show for the file
+    pub fn add_file(&mut self, path: &Path, name: &str, contents: impl Into<SourceText>) -> FileId {
+        let contents = contents.into();
+        assert!(
+            contents.len() as u64 <= MAX_FILE_SIZE,
+            "{name} is {} bytes long, source files can be at most 4 GiB",
+            contents.len()
+        );
+
+        let entry = SourceMapEntry {
+            key: file_key(path),
+            name: name.into(),
+            contents: Arc::new(contents),
+        };
+
+        if let Some(index) = self.files.iter().position(|x| x.key == entry.key) {
+            self.files[index] = entry;
+            return FileId(index as u32);
+        }
+
+        self.files.push(entry);
+        FileId((self.files.len() - 1) as u32)
+    }
+
+    pub fn file_name(&self, file_id: FileId) -> Arc<str> {
+        self.files
+            .get(file_id.0 as usize)
+            .map(|x| x.name.clone())
+            .unwrap_or_else(|| "<empty>".into())
+    }
+
+    pub fn file_contents(&self, file_id: FileId) -> Arc<SourceText> {
+        self.files
+            .get(file_id.0 as usize)
+            .map(|x| x.contents.clone())
+            .unwrap_or_default()
+    }
+}
+
+pub fn add_file(path: &str, contents: impl Into<SourceText>) -> FileId {
+    add_file_as(Path::new(path), path, contents)
+}
+
+// like add_file, for a file whose diagnostics show a shorter name than its path
+pub fn add_file_as(path: &Path, name: &str, contents: impl Into<SourceText>) -> FileId {
+    SOURCE_MAP
+        .write()
+        .expect("source map lock poisoned")
+        .add_file(path, name, contents)
+}
+
+// every call adds a new file, tests that run in parallel would replace each other's sources
+// if they used the same name
+#[cfg(test)]
+pub fn add_test_file(name: &str, contents: impl Into<SourceText>) -> FileId {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT_TEST_FILE: AtomicUsize = AtomicUsize::new(0);
+    let index = NEXT_TEST_FILE.fetch_add(1, Ordering::Relaxed);
+    add_file(&format!("{name}_{index}.duck"), contents)
+}
+
+pub fn file_name(file_id: FileId) -> Arc<str> {
+    SOURCE_MAP
+        .read()
+        .expect("source map lock poisoned")
+        .file_name(file_id)
+}
+
+pub fn file_contents(file_id: FileId) -> Arc<SourceText> {
+    SOURCE_MAP
+        .read()
+        .expect("source map lock poisoned")
+        .file_contents(file_id)
+}
+
+// a statement without a ; ends at the end of its line
+pub fn has_line_break(src: &str, start: usize, end: usize) -> bool {
+    src.get(start..end).is_some_and(|text| text.contains('\n'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map_reuses_ids() {
+        let mut source_map = SourceMap::default();
+
+        let a = source_map.add_file(Path::new("a.duck"), "a.duck", "fn main() {}");
+        let b = source_map.add_file(Path::new("b.duck"), "b.duck", "");
+        assert_ne!(a, b);
+
+        let a_again = source_map.add_file(Path::new("a.duck"), "a.duck", "fn main() { 1 }");
+        assert_eq!(a, a_again);
+        assert_eq!(&*source_map.file_name(a), "a.duck");
+        assert_eq!(&**source_map.file_contents(a), "fn main() { 1 }");
+
+        assert_eq!(&**source_map.file_contents(FileId::EMPTY), "");
+    }
+
+    #[test]
+    fn test_source_map_keys_by_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        for sub_dir in [&a, &b] {
+            std::fs::create_dir(sub_dir).unwrap();
+            std::fs::write(sub_dir.join("main.duck"), "").unwrap();
+        }
+
+        let mut source_map = SourceMap::default();
+        let a_main = source_map.add_file(&a.join("main.duck"), "main.duck", "fn a() {}");
+        let b_main = source_map.add_file(&b.join("main.duck"), "main.duck", "fn b() {}");
+        assert_ne!(a_main, b_main);
+        assert_eq!(&**source_map.file_contents(a_main), "fn a() {}");
+
+        let a_again = source_map.add_file(&b.join("../a/main.duck"), "main.duck", "");
+        assert_eq!(a_main, a_again);
+    }
+
+    #[test]
+    fn test_read_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = "fn main() {}\n".to_string();
+        let big = small.repeat(MMAP_THRESHOLD as usize / small.len() + 1);
+
+        for (file_name, contents) in [("small.duck", &small), ("big.duck", &big)] {
+            let path = dir.path().join(file_name);
+            std::fs::write(&path, contents).unwrap();
+
+            let source = read_source(&path).unwrap();
+            assert_eq!(&source, contents, "{file_name}");
+        }
+
+        std::fs::write(dir.path().join("invalid.duck"), [0xff].repeat(big.len())).unwrap();
+        assert_eq!(
+            read_source(&dir.path().join("invalid.duck"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
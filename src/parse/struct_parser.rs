@@ -1,9 +1,12 @@
 use chumsky::Parser;
-use chumsky::input::BorrowInput;
+use chumsky::input::{BorrowInput, SliceInput};
 use chumsky::prelude::*;
 
 use crate::parse::{
     Field, SS, Spanned,
+    attribute_parser::{Attribute, attribute_parser, warn_unknown_attributes},
+    derive::derive_methods,
+    doc_comment_parser::doc_comments_parser,
     function_parser::{FunctionDefintion, function_definition_parser},
     generics_parser::{Generic, generics_parser},
     type_parser::type_expression_parser,
@@ -17,19 +20,53 @@ pub struct StructDefinition {
     pub fields: Vec<Field>,
     pub methods: Vec<FunctionDefintion>,
     pub generics: Option<Vec<Spanned<Generic>>>,
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<Spanned<Attribute>>,
+}
+
+impl StructDefinition {
+    // adds the methods of every #[derive(...)] and warns about the attributes that mean nothing
+    // here. this runs once all attributes are attached, the ones in front of the doc comments
+    // are parsed with the source unit
+    pub fn apply_attributes<'src>(&mut self) -> Result<(), Rich<'src, Token, SS>> {
+        warn_unknown_attributes(&self.attributes, &["derive"], "a struct");
+        for field in &self.fields {
+            warn_unknown_attributes(&field.attributes, &[], "a field");
+        }
+
+        let derives = self
+            .attributes
+            .iter()
+            .filter(|(attribute, _)| attribute.name == "derive")
+            .flat_map(|(attribute, _)| attribute.args.clone())
+            .collect::<Vec<_>>();
+        let derived = derive_methods(self, std::slice::from_ref(&self.name), &derives)?;
+        self.methods.extend(derived);
+        Ok(())
+    }
 }
 
 pub fn struct_definition_parser<'src, M, I>(
     make_input: M,
 ) -> impl Parser<'src, I, StructDefinition, extra::Err<Rich<'src, Token, SS>>> + Clone
 where
-    I: BorrowInput<'src, Token = Token, Span = SS>,
+    I: BorrowInput<'src, Token = Token, Span = SS>
+        + SliceInput<'src, Slice = &'src [Spanned<Token>]>,
     M: Fn(SS, &'src [Spanned<Token>]) -> I + Clone + 'static,
 {
-    let field_parser = select_ref! { Token::Ident(identifier) => identifier.clone() }
+    let field_parser = attribute_parser()
+        .repeated()
+        .collect::<Vec<_>>()
+        .then(just(Token::Pub).or_not())
+        .then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
         .then_ignore(just(Token::ControlChar(':')))
         .then(type_expression_parser())
-        .map(|(identifier, type_expr)| Field::new(identifier, type_expr));
+        .map(|(((attributes, is_pub), name), type_expr)| Field {
+            name,
+            type_expr,
+            is_pub: is_pub.is_some(),
+            attributes,
+        });
 
     let impl_parser = just(Token::Impl)
         .ignore_then(just(Token::ControlChar('{')))
@@ -43,10 +80,21 @@ where
         .or_not()
         .map(|x| x.or_else(|| Some(vec![])).unwrap());
 
-    just(Token::Struct)
-        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+    let name_and_generics = select_ref! { Token::Ident(identifier) => identifier.to_string() }
         .then(generics_parser().or_not())
-        .then_ignore(just(Token::ControlChar('=')))
+        .then_ignore(just(Token::ControlChar('=')));
+
+    // struct Name<T> = { .. }; or type Name<T> = struct { .. };
+    let header = choice((
+        just(Token::Struct).ignore_then(name_and_generics.clone()),
+        just(Token::Type)
+            .ignore_then(name_and_generics)
+            .then_ignore(just(Token::Struct)),
+    ));
+
+    doc_comments_parser()
+        .then(attribute_parser().repeated().collect::<Vec<_>>())
+        .then(header)
         .then_ignore(just(Token::ControlChar('{')))
         .then(
             field_parser
@@ -58,11 +106,15 @@ where
         .then(impl_parser)
         .then_ignore(just(Token::ControlChar(';')))
         .map(
-            |(((identifier, generics), fields), methods)| StructDefinition {
-                name: identifier,
-                fields,
-                methods,
-                generics,
+            |(((((doc_comments, attributes), identifier), generics), fields), methods)| {
+                StructDefinition {
+                    name: identifier,
+                    fields,
+                    methods,
+                    generics,
+                    doc_comments,
+                    attributes,
+                }
             },
         )
 }
@@ -70,8 +122,8 @@ where
 #[cfg(test)]
 pub mod tests {
     use crate::parse::{
-        generics_parser::Generic, lexer::lex_parser, make_input, type_parser::TypeExpr,
-        value_parser::empty_range,
+        generics_parser::Generic, lexer::lex_parser, make_input, source_map::FileId,
+        type_parser::TypeExpr, value_parser::empty_range,
     };
     use chumsky::Parser;
 
@@ -85,8 +137,8 @@ pub mod tests {
                     .fields
                     .into_iter()
                     .map(|field| Field {
-                        name: field.name,
                         type_expr: strip_spans(field.type_expr),
+                        ..field
                     })
                     .collect(),
             }),
@@ -135,6 +187,7 @@ pub mod tests {
         }
         if let Some(generics) = &mut def.generics {
             for generic in generics {
+                generic.0.constraint = generic.0.constraint.take().map(strip_spans);
                 generic.1 = empty_range();
             }
         }
@@ -143,7 +196,7 @@ pub mod tests {
 
     fn assert_struct_definition(input_str: &str, expected_def: StructDefinition) {
         println!("lexing and parsing struct definition: \"{}\"", input_str);
-        let lexer_parse_result = lex_parser("test", "").parse(input_str);
+        let lexer_parse_result = lex_parser(FileId::EMPTY).parse(input_str);
 
         assert!(
             !lexer_parse_result.has_errors(),
@@ -223,6 +276,8 @@ pub mod tests {
                 ],
                 methods: vec![],
                 generics: None,
+                doc_comments: Vec::new(),
+                attributes: Vec::new(),
             },
         );
 
@@ -233,6 +288,8 @@ pub mod tests {
                 fields: vec![],
                 methods: vec![],
                 generics: None,
+                doc_comments: Vec::new(),
+                attributes: Vec::new(),
             },
         );
 
@@ -246,6 +303,8 @@ pub mod tests {
                 ],
                 methods: vec![],
                 generics: None,
+                doc_comments: Vec::new(),
+                attributes: Vec::new(),
             },
         );
 
@@ -261,9 +320,12 @@ pub mod tests {
                 generics: Some(vec![(
                     Generic {
                         name: "T".to_string(),
+                        constraint: None,
                     },
                     empty_range(),
                 )]),
+                doc_comments: Vec::new(),
+                attributes: Vec::new(),
             },
         );
 
@@ -293,34 +355,158 @@ pub mod tests {
                     (
                         Generic {
                             name: "K".to_string(),
+                            constraint: None,
                         },
                         empty_range(),
                     ),
                     (
                         Generic {
                             name: "V".to_string(),
+                            constraint: None,
                         },
                         empty_range(),
                     ),
                 ]),
+                doc_comments: Vec::new(),
+                attributes: Vec::new(),
+            },
+        );
+
+        assert_struct_definition(
+            "type Pair<T> = struct { a: T, b: T };",
+            StructDefinition {
+                name: "Pair".to_string(),
+                fields: vec![
+                    Field::new(
+                        "a".to_string(),
+                        TypeExpr::RawTypeName(false, vec!["T".to_string()], None).into_empty_span(),
+                    ),
+                    Field::new(
+                        "b".to_string(),
+                        TypeExpr::RawTypeName(false, vec!["T".to_string()], None).into_empty_span(),
+                    ),
+                ],
+                methods: vec![],
+                generics: Some(vec![(
+                    Generic {
+                        name: "T".to_string(),
+                        constraint: None,
+                    },
+                    empty_range(),
+                )]),
+                doc_comments: Vec::new(),
+                attributes: Vec::new(),
+            },
+        );
+
+        assert_struct_definition(
+            "struct Named<T: duck { name: String }> = { inner: T };",
+            StructDefinition {
+                name: "Named".to_string(),
+                fields: vec![Field::new(
+                    "inner".to_string(),
+                    TypeExpr::RawTypeName(false, vec!["T".to_string()], None).into_empty_span(),
+                )],
+                methods: vec![],
+                generics: Some(vec![(
+                    Generic {
+                        name: "T".to_string(),
+                        constraint: Some(
+                            TypeExpr::Duck(crate::parse::type_parser::Duck {
+                                fields: vec![Field::new(
+                                    "name".to_string(),
+                                    TypeExpr::String.into_empty_span(),
+                                )],
+                            })
+                            .into_empty_span(),
+                        ),
+                    },
+                    empty_range(),
+                )]),
+                doc_comments: Vec::new(),
+                attributes: Vec::new(),
             },
         );
 
         let invalid_structs = vec![
             "type MissingBody = ;",
+            "type MissingStruct<T> = { a: T };",
+            "type MissingSemi = struct { x: Int }",
             "type MissingSemi = { x: Int }",
             "type BadField = { x: Int, y };",
             "type BadComma = { x: Int,, y: Bool };",
             "type X = String;",
+            "struct Twice = { pub pub x: Int };",
+            "struct PubAfterName = { x pub: Int };",
         ];
 
         for invalid in invalid_structs {
             println!("testing invalid struct: \"{}\"", invalid);
-            let lexer_parse_result = lex_parser("test", "").parse(invalid);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(invalid);
             let tokens = lexer_parse_result.output().unwrap();
             let parse_result =
                 struct_definition_parser(make_input).parse(make_input(empty_range(), &tokens));
             assert!(parse_result.has_errors());
         }
     }
+
+    #[test]
+    fn test_struct_field_visibility() {
+        let src = "struct Point = { pub x: Int, y: Int, pub z: Int };";
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        let struct_def = struct_definition_parser(make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .expect(src);
+
+        assert_eq!(
+            struct_def
+                .fields
+                .iter()
+                .map(|field| (field.name.as_str(), field.is_pub))
+                .collect::<Vec<_>>(),
+            vec![("x", true), ("y", false), ("z", true)]
+        );
+    }
+
+    #[test]
+    fn test_struct_derive() {
+        let test_cases = vec![
+            ("struct Point = { x: Int };", vec![]),
+            ("#[derive(Show)] struct Point = { x: Int };", vec!["show"]),
+            (
+                "#[derive(Show, Eq)] #[derive(Json)] struct Point = { x: Int };",
+                vec!["show", "eq", "to_json"],
+            ),
+            (
+                "#[derive(Hash)] struct Point = { x: Int } impl { fn len() -> Int { 1 } };",
+                vec!["len", "hash"],
+            ),
+        ];
+
+        for (src, expected_methods) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut struct_def = struct_definition_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+            struct_def.apply_attributes().unwrap();
+
+            assert_eq!(
+                struct_def
+                    .methods
+                    .iter()
+                    .map(|method| method.name.as_str())
+                    .collect::<Vec<_>>(),
+                expected_methods,
+                "{src}"
+            );
+        }
+    }
 }
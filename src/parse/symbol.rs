@@ -0,0 +1,105 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use lazy_static::lazy_static;
+
+// an interned identifier, comparing and copying it doesn't touch the string
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+// the identifiers of every compile of the process. a symbol can be held anywhere, by the
+// memoized tokens or by another thread, so the strings are never freed. only distinct
+// identifiers are stored, which stays small even for a long running process (watch mode, lsp)
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<Arc<str>, Symbol>,
+    strings: Vec<Arc<str>>,
+}
+
+impl Interner {
+    fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(s) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.strings.len() as u32);
+        let s: Arc<str> = s.into();
+        self.strings.push(s.clone());
+        self.symbols.insert(s, symbol);
+        symbol
+    }
+}
+
+lazy_static! {
+    static ref INTERNER: RwLock<Interner> = RwLock::new(Interner::default());
+}
+
+impl Symbol {
+    pub fn intern(s: &str) -> Symbol {
+        if let Some(symbol) = INTERNER
+            .read()
+            .expect("interner lock poisoned")
+            .symbols
+            .get(s)
+        {
+            return *symbol;
+        }
+
+        // another thread could have interned it between the two locks, intern checks again
+        INTERNER.write().expect("interner lock poisoned").intern(s)
+    }
+
+    pub fn text(self) -> Arc<str> {
+        INTERNER.read().expect("interner lock poisoned").strings[self.0 as usize].clone()
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.text())
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        Symbol::intern(s)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.text() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.text() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let a = Symbol::intern("interner_test_a");
+        let b = Symbol::intern("interner_test_b");
+
+        assert_eq!(a, Symbol::intern("interner_test_a"));
+        assert_ne!(a, b);
+        assert_eq!(&*a.text(), "interner_test_a");
+        assert_eq!(b.to_string(), "interner_test_b");
+        assert!(a == "interner_test_a");
+    }
+}
@@ -0,0 +1,366 @@
+use chumsky::{
+    input::{BorrowInput, SliceInput},
+    prelude::*,
+};
+
+use crate::parse::{
+    Field, SS, Spanned,
+    doc_comment_parser::doc_comments_parser,
+    function_parser::{FunctionDefintion, Param, function_definition_parser},
+    generics_parser::{Generic, generics_parser},
+    lexer::Token,
+    type_parser::{Duck, TypeDefinition, TypeExpr, type_expression_parser},
+};
+
+// trait Show = { fn show() -> String; };
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitDefinition {
+    pub name: String,
+    pub generics: Option<Vec<Spanned<Generic>>>,
+    pub methods: Vec<Spanned<TraitMethod>>,
+    pub doc_comments: Vec<String>,
+    pub span: SS,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitMethod {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub return_type: Option<Spanned<TypeExpr>>,
+}
+
+// impl Point { fn norm() -> Int { .. } } or impl Show for Point { fn show() -> String { .. } }
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplBlock {
+    pub trait_type: Option<Spanned<TypeExpr>>,
+    pub target: Spanned<TypeExpr>,
+    pub methods: Vec<FunctionDefintion>,
+    // static fn new() -> Point { .. } has no self and is called as Point::new()
+    pub associated_functions: Vec<FunctionDefintion>,
+}
+
+// the methods of an impl block for a trait are moved into the struct, this is what's left to
+// check that the struct really implements the trait
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitImplementation {
+    pub trait_type: Spanned<TypeExpr>,
+    pub struct_name: Spanned<String>,
+}
+
+impl TraitDefinition {
+    // a trait is the duck of its methods. ducks are go interfaces and structs implement the
+    // getters of their methods, so every struct with these methods can be used as the trait
+    pub fn to_type_definition(&self) -> TypeDefinition {
+        let fields = self
+            .methods
+            .iter()
+            .map(|(method, span)| {
+                let params = method
+                    .params
+                    .iter()
+                    .map(|(name, type_expr)| (Some(name.clone()), type_expr.clone()))
+                    .collect();
+                let fun = TypeExpr::Fun(params, method.return_type.clone().map(Box::new));
+                Field::new(method.name.clone(), (fun, *span))
+            })
+            .collect();
+
+        TypeDefinition {
+            name: self.name.clone(),
+            type_expression: (TypeExpr::Duck(Duck { fields }), self.span),
+            generics: self.generics.clone(),
+            doc_comments: self.doc_comments.clone(),
+            attributes: Vec::new(),
+        }
+    }
+}
+
+pub fn trait_definition_parser<'src, I>()
+-> impl Parser<'src, I, TraitDefinition, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+{
+    let param_parser = select_ref! { Token::Ident(identifier) => identifier.to_string() }
+        .then_ignore(just(Token::ControlChar(':')))
+        .then(type_expression_parser());
+
+    let method = just(Token::Function)
+        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+        .then(
+            param_parser
+                .separated_by(just(Token::ControlChar(',')))
+                .allow_trailing()
+                .collect::<Vec<Param>>()
+                .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')'))),
+        )
+        .then(
+            just(Token::ThinArrow)
+                .ignore_then(type_expression_parser())
+                .or_not(),
+        )
+        .then_ignore(just(Token::ControlChar(';')))
+        .map_with(|((name, params), return_type), e| {
+            let method = TraitMethod {
+                name,
+                params,
+                return_type,
+            };
+            (method, e.span())
+        });
+
+    doc_comments_parser()
+        .then_ignore(just(Token::Trait))
+        .then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+        .then(generics_parser().or_not())
+        .then_ignore(just(Token::ControlChar('=')))
+        .then(
+            method
+                .repeated()
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}'))),
+        )
+        .then_ignore(just(Token::ControlChar(';')))
+        .try_map(|(((doc_comments, name), generics), methods), span| {
+            for (i, (method, method_span)) in methods.iter().enumerate() {
+                if methods[..i]
+                    .iter()
+                    .any(|(other, _)| other.name == method.name)
+                {
+                    return Err(Rich::custom(
+                        *method_span,
+                        format!("{name} already has a method called {}", method.name),
+                    ));
+                }
+            }
+
+            Ok(TraitDefinition {
+                name,
+                generics,
+                methods,
+                doc_comments,
+                span,
+            })
+        })
+}
+
+pub fn impl_block_parser<'src, I, M>(
+    make_input: M,
+) -> impl Parser<'src, I, ImplBlock, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>
+        + SliceInput<'src, Slice = &'src [Spanned<Token>]>,
+    M: Fn(SS, &'src [Spanned<Token>]) -> I + Clone + 'static,
+{
+    // static is no keyword, so that it can still be used as an identifier everywhere else
+    let static_function = doc_comments_parser()
+        .then_ignore(select_ref! { Token::Ident(ident) if ident == "static" => () })
+        .then(function_definition_parser(make_input.clone()))
+        .map(|(mut doc_comments, mut function)| {
+            doc_comments.append(&mut function.doc_comments);
+            function.doc_comments = doc_comments;
+            (true, function)
+        });
+
+    let item = choice((
+        static_function,
+        function_definition_parser(make_input).map(|method| (false, method)),
+    ));
+
+    just(Token::Impl)
+        .ignore_then(choice((
+            type_expression_parser()
+                .then_ignore(just(Token::For))
+                .map(Some)
+                .then(type_expression_parser()),
+            type_expression_parser().map(|target| (None, target)),
+        )))
+        .then(
+            item.repeated()
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}'))),
+        )
+        .map(|((trait_type, target), items)| {
+            let (associated_functions, methods) = items
+                .into_iter()
+                .partition::<Vec<_>, _>(|(is_static, _)| *is_static);
+            ImplBlock {
+                trait_type,
+                target,
+                methods: methods.into_iter().map(|(_, method)| method).collect(),
+                associated_functions: associated_functions
+                    .into_iter()
+                    .map(|(_, function)| function)
+                    .collect(),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_map::FileId, value_parser::empty_range,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_trait_definition_parser() {
+        let test_cases = vec![
+            ("trait Empty = {};", vec![]),
+            (
+                "trait Show = { fn show() -> String; };",
+                vec![("show", 0, true)],
+            ),
+            (
+                "trait Shape<T> = { fn area() -> T; fn scale(by: T, twice: Bool,); fn reset(); };",
+                vec![("area", 0, true), ("scale", 2, false), ("reset", 0, false)],
+            ),
+        ];
+
+        for (src, expected_methods) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let trait_definition = trait_definition_parser()
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            let methods = trait_definition
+                .methods
+                .iter()
+                .map(|(method, _)| {
+                    let has_return_type = method.return_type.is_some();
+                    (method.name.as_str(), method.params.len(), has_return_type)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(methods, expected_methods, "{src}");
+        }
+
+        for invalid in [
+            "trait Show = { fn show() -> String; }",
+            "trait Show { fn show() -> String; };",
+            "trait Show = { fn show() -> String };",
+            "trait Show = { fn show() -> String { \"\" } };",
+            "trait Show = { fn show(); fn show(); };",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                trait_definition_parser()
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_trait_to_type_definition() {
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("trait Show = { fn show(prefix: String) -> String; };")
+            .into_result()
+            .unwrap();
+        let trait_definition = trait_definition_parser()
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+
+        let type_definition = trait_definition.to_type_definition();
+        assert_eq!(type_definition.name, "Show");
+        let TypeExpr::Duck(duck) = type_definition.type_expression.0 else {
+            panic!("a trait has to be a duck")
+        };
+        assert_eq!(duck.fields.len(), 1);
+        assert_eq!(duck.fields[0].name, "show");
+        let TypeExpr::Fun(params, Some(return_type)) = &duck.fields[0].type_expr.0 else {
+            panic!("a trait method has to be a function returning a String")
+        };
+        assert_eq!(params[0].0.as_deref(), Some("prefix"));
+        assert_eq!(params[0].1.0, TypeExpr::String);
+        assert_eq!(return_type.0, TypeExpr::String);
+    }
+
+    #[test]
+    fn test_impl_block_parser() {
+        let test_cases = vec![
+            ("impl Point {}", None, "Point", vec![]),
+            (
+                "impl Point { fn norm() -> Int { return 1; } fn reset() {} }",
+                None,
+                "Point",
+                vec!["norm", "reset"],
+            ),
+            (
+                "impl Point { static fn new() -> Point { return Point {}; } fn reset() {} }",
+                None,
+                "Point",
+                vec!["reset"],
+            ),
+            (
+                "impl { x: Int } { fn x_twice() -> Int { return self.x * 2; } }",
+                None,
+                "{ \"x\": Int }",
+                vec!["x_twice"],
+            ),
+            ("impl Show for Point {}", Some("Show"), "Point", vec![]),
+            (
+                "impl Show for Point { fn show() -> String { return \"point\"; } }",
+                Some("Show"),
+                "Point",
+                vec!["show"],
+            ),
+            (
+                "impl Area<Float> for Sq { fn area() -> Float { return 1.0; } }",
+                Some("Area<Float>"),
+                "Sq",
+                vec!["area"],
+            ),
+        ];
+
+        for (src, expected_trait, expected_type, expected_methods) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let impl_block = impl_block_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            let trait_type = impl_block
+                .trait_type
+                .map(|(trait_type, _)| trait_type.to_string());
+            assert_eq!(trait_type.as_deref(), expected_trait, "{src}");
+            assert_eq!(impl_block.target.0.to_string(), expected_type, "{src}");
+            let methods = impl_block
+                .methods
+                .iter()
+                .map(|method| method.name.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(methods, expected_methods, "{src}");
+        }
+
+        for invalid in [
+            "impl Show Point {}",
+            "impl Show for {}",
+            "impl Show for Point { show() {} }",
+            "impl Point",
+            "impl Point { fn norm() -> Int { return 1; } ",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                impl_block_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::parse::{Context, SS, Spanned, lexer::Token, source_map::FileId};
+
+// everything between two tokens that the parsers don't care about, but a formatter or a doc
+// generator has to keep
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trivia {
+    Whitespace(String),
+    Comment(String),
+    DocComment(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenWithTrivia {
+    pub token: Spanned<Token>,
+    pub leading_trivia: Vec<Spanned<Trivia>>,
+    pub trailing_trivia: Vec<Spanned<Trivia>>,
+}
+
+// takes the tokens of the lexer with their comments still in place. the trivia on the line of a
+// token, up to the first line break, trails it, everything after that leads the next token.
+// the trivia at the end of the file trails the last token
+pub fn attach_trivia(
+    file_id: FileId,
+    tokens: &[Spanned<Token>],
+    src: &str,
+) -> Vec<TokenWithTrivia> {
+    let mut result: Vec<TokenWithTrivia> = Vec::new();
+    let mut pending = Vec::new();
+    let mut pos = 0;
+
+    for (token, span) in tokens {
+        push_whitespace(&mut pending, file_id, src, pos, span.start as usize);
+        pos = span.end as usize;
+
+        match token {
+            Token::Comment(comment) => pending.push((Trivia::Comment(comment.to_string()), *span)),
+            Token::DocComment(comment) => {
+                pending.push((Trivia::DocComment(comment.to_string()), *span))
+            }
+            _ => {
+                if let Some(previous) = result.last_mut() {
+                    let line_end = pending
+                        .iter()
+                        .position(|(trivia, _)| {
+                            matches!(trivia, Trivia::Whitespace(ws) if ws.contains('\n'))
+                        })
+                        .unwrap_or(pending.len());
+                    previous.trailing_trivia.extend(pending.drain(..line_end));
+                }
+
+                result.push(TokenWithTrivia {
+                    token: (token.clone(), *span),
+                    leading_trivia: std::mem::take(&mut pending),
+                    trailing_trivia: Vec::new(),
+                });
+            }
+        }
+    }
+
+    push_whitespace(&mut pending, file_id, src, pos, src.len());
+    if let Some(last) = result.last_mut() {
+        last.trailing_trivia.extend(pending);
+    }
+
+    result
+}
+
+fn push_whitespace(
+    trivia: &mut Vec<Spanned<Trivia>>,
+    file_id: FileId,
+    src: &str,
+    start: usize,
+    end: usize,
+) {
+    if start < end {
+        trivia.push((
+            Trivia::Whitespace(src[start..end].to_string()),
+            SS {
+                start: start as u32,
+                end: end as u32,
+                context: Context { file_id },
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use crate::parse::lexer::lex_parser;
+
+    use super::*;
+
+    fn strip_spans(trivia: &[Spanned<Trivia>]) -> Vec<Trivia> {
+        trivia.iter().map(|(trivia, _)| trivia.clone()).collect()
+    }
+
+    #[test]
+    fn test_attach_trivia() {
+        let ws = |s: &str| Trivia::Whitespace(s.to_string());
+        let comment = |s: &str| Trivia::Comment(s.to_string());
+
+        let test_cases = vec![
+            ("x", vec![(vec![], vec![])]),
+            ("  x  ", vec![(vec![ws("  ")], vec![ws("  ")])]),
+            (
+                "x // one\n\n// two\ny",
+                vec![
+                    (vec![], vec![ws(" "), comment("one")]),
+                    (vec![ws("\n\n"), comment("two"), ws("\n")], vec![]),
+                ],
+            ),
+            (
+                "/// docs\nfn /* inline */ f",
+                vec![
+                    (
+                        vec![Trivia::DocComment("docs".to_string()), ws("\n")],
+                        vec![ws(" "), comment("inline"), ws(" ")],
+                    ),
+                    (vec![], vec![]),
+                ],
+            ),
+        ];
+
+        for (src, expected) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY).parse(src).into_output().unwrap();
+            let output = attach_trivia(FileId::EMPTY, &tokens, src)
+                .iter()
+                .map(|t| {
+                    (
+                        strip_spans(&t.leading_trivia),
+                        strip_spans(&t.trailing_trivia),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(output, expected, "{src}");
+        }
+    }
+}
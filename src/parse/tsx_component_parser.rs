@@ -131,16 +131,16 @@ where
     //   %javascript source
     // }
     just(Token::Component)
-        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.clone() })
+        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
         .then(
-            just(Token::Ident("props".to_string()))
+            just(Token::Ident("props".into()))
                 .ignore_then(just(Token::ControlChar(':')))
                 .ignore_then(type_expression_parser())
                 .or_not()
                 .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')'))),
         )
         .then(
-            select_ref! { Token::InlineTsx(tsx_source) => tsx_source.clone() }
+            select_ref! { Token::InlineTsx(tsx_source) => tsx_source.to_string() }
                 .map_with(|x, e| (x, e.span())),
         )
         .map(|((ident, props_type), tsx_source)| TsxComponent {
@@ -153,7 +153,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::parse::{lexer::lex_parser, make_input, value_parser::empty_range};
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_map::FileId, value_parser::empty_range,
+    };
 
     use super::*;
 
@@ -170,7 +172,7 @@ mod tests {
 
         for (src, expected_ast) in src_and_expected_ast {
             println!("lexing {src}");
-            let lexer_parse_result = lex_parser("test", "").parse(src);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(src);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -213,7 +215,7 @@ mod tests {
         for invalid_component_statement in invalid_component_statements {
             println!("lexing {invalid_component_statement}");
 
-            let lexer_parse_result = lex_parser("test", "").parse(invalid_component_statement);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(invalid_component_statement);
 
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
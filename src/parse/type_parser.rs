@@ -7,6 +7,8 @@ use chumsky::prelude::*;
 use crate::{
     parse::{
         Field, SS, Spanned,
+        attribute_parser::Attribute,
+        doc_comment_parser::doc_comments_parser,
         generics_parser::{Generic, generics_parser},
         value_parser::{TypeParam, empty_range},
     },
@@ -20,6 +22,8 @@ pub struct TypeDefinition {
     pub name: String,
     pub type_expression: Spanned<TypeExpr>,
     pub generics: Option<Vec<Spanned<Generic>>>,
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<Spanned<Attribute>>,
 }
 
 impl Display for TypeExpr {
@@ -30,6 +34,7 @@ impl Display for TypeExpr {
             TypeExpr::TypeOf(identifier) => write!(f, "typeof {identifier}"),
             TypeExpr::Alias(def) => write!(f, "{def:?}"),
             TypeExpr::Any => write!(f, "any"),
+            TypeExpr::Never => write!(f, "never"),
             TypeExpr::InlineGo => write!(f, "inline_go"),
             TypeExpr::Struct(s) => {
                 write!(f, "struct {s}")
@@ -122,6 +127,8 @@ impl Display for TypeExpr {
                 Ok(())
             }
             TypeExpr::Array(inner) => write!(f, "{}[]", inner.0),
+            TypeExpr::Map(key, value) => write!(f, "map<{}, {}>", key.0, value.0),
+            TypeExpr::Chan(element) => write!(f, "chan<{}>", element.0),
         }
     }
 }
@@ -133,12 +140,18 @@ impl Display for Duck {
             if i > 0 {
                 write!(f, ", ")?;
             }
+            if field.name == DUCK_REST {
+                return write!(f, "..{}", field.type_expr.0);
+            }
             write!(f, "\"{}\": {}", field.name, field.type_expr.0)
         })?;
         write!(f, " }}")
     }
 }
 
+// the name of the field that holds R in duck { ..R }
+pub const DUCK_REST: &str = "..";
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Duck {
     pub fields: Vec<Field>,
@@ -153,6 +166,7 @@ pub struct Struct {
 pub enum TypeExpr {
     Html,
     Any,
+    Never,
     InlineGo,
     Struct(String),
     Go(String),
@@ -177,6 +191,10 @@ pub enum TypeExpr {
         Option<Box<Spanned<TypeExpr>>>,           // return type
     ),
     Array(Box<Spanned<TypeExpr>>),
+    // map<K, V>, the keys are stored as native go values
+    Map(Box<Spanned<TypeExpr>>, Box<Spanned<TypeExpr>>),
+    // chan<T>, a go channel of T
+    Chan(Box<Spanned<TypeExpr>>),
     TypeOf(String),
 }
 
@@ -188,11 +206,31 @@ impl TypeExpr {
     pub fn as_go_return_type(&self, type_env: &mut TypeEnv) -> String {
         if self.is_unit() {
             String::new()
+        } else if let Some(types) = self.multi_return_types(type_env) {
+            format!(
+                "({})",
+                types
+                    .iter()
+                    .map(|type_expr| type_expr.as_go_type_annotation(type_env))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
         } else {
             self.as_go_type_annotation(type_env)
         }
     }
 
+    // returned tuples become go multiple return values, so that generated functions compose
+    // with hand written go
+    pub fn multi_return_types(&self, type_env: &TypeEnv) -> Option<Vec<TypeExpr>> {
+        match type_env.try_resolve_type_expr(self) {
+            TypeExpr::Tuple(fields) if !fields.is_empty() => {
+                Some(fields.into_iter().map(|field| field.0).collect())
+            }
+            _ => None,
+        }
+    }
+
     pub fn primitives() -> Vec<TypeExpr> {
         return vec![
             TypeExpr::Int,
@@ -204,25 +242,177 @@ impl TypeExpr {
     }
 }
 
-pub fn type_expression_parser_without_array<'src, I>()
--> impl Parser<'src, I, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>> + Clone
+// the lexer gives -1 as a minus and a 1, for literal types they belong together
+fn int_literal_type_parser<'src, I>()
+-> impl Parser<'src, I, TypeExpr, extra::Err<Rich<'src, Token, SS>>> + Clone
 where
     I: BorrowInput<'src, Token = Token, Span = SS>,
 {
-    recursive(
-        |p: Recursive<dyn Parser<'_, _, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>>>| {
-            let field = select_ref! { Token::Ident(identifier) => identifier.to_string() }
-                .then_ignore(just(Token::ControlChar(':')))
-                .then(p.clone());
+    just(Token::ControlChar('-'))
+        .or_not()
+        .then(select_ref! { Token::ConstInt(int) => *int })
+        .try_map(|(minus, int), span| {
+            let int = match minus {
+                Some(_) => -i128::from(int),
+                None => i128::from(int),
+            };
+            i32::try_from(int).map(TypeExpr::ConstInt).map_err(|_| {
+                Rich::custom(
+                    span,
+                    format!(
+                        "int literal types have to be between {} and {}, {int} isn't",
+                        i32::MIN,
+                        i32::MAX
+                    ),
+                )
+            })
+        })
+}
 
-            let duck_fields = field
-                .clone()
+// fn(x: Int, y: String) -> Bool or fn(Int, String) -> Bool, without an arrow nothing is returned
+fn function_type_parser<'src, I, P>(
+    p: P,
+) -> impl Parser<'src, I, TypeExpr, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+    P: Parser<'src, I, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>> + Clone,
+{
+    let named_param = select_ref! { Token::Ident(identifier) => identifier.to_string() }
+        .then_ignore(just(Token::ControlChar(':')))
+        .then(p.clone())
+        .map(|(name, type_expr)| (Some(name), type_expr));
+
+    let unnamed_param = p.clone().map(|type_expr| (None, type_expr));
+
+    just(Token::Function)
+        .ignore_then(
+            named_param
+                .or(unnamed_param)
                 .separated_by(just(Token::ControlChar(',')))
                 .allow_trailing()
-                .collect::<Vec<(String, Spanned<TypeExpr>)>>();
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')'))),
+        )
+        .then(just(Token::ThinArrow).ignore_then(p).or_not())
+        .map(|(params, return_type)| TypeExpr::Fun(params, return_type.map(Box::new)))
+}
+
+// map<K, V>, only primitives can be used as keys because go compares the keys by value
+fn map_type_parser<'src, I, P>(
+    p: P,
+) -> impl Parser<'src, I, TypeExpr, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+    P: Parser<'src, I, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>> + Clone,
+{
+    select_ref! { Token::Ident(identifier) if *identifier == "map" => () }
+        .ignore_then(just(Token::ControlChar('<')))
+        .ignore_then(p.clone())
+        .then_ignore(just(Token::ControlChar(',')))
+        .then(p)
+        .then_ignore(just(Token::ControlChar(',')).or_not())
+        .then_ignore(just(Token::ControlChar('>')))
+        .validate(|(key, value), _, emitter| {
+            if !key.0.is_hashable() {
+                emitter.emit(Rich::custom(
+                    key.1,
+                    format!(
+                        "map keys have to be Int, Float, Bool, Char or String, {} isn't hashable",
+                        key.0
+                    ),
+                ));
+            }
+            TypeExpr::Map(Box::new(key), Box::new(value))
+        })
+}
 
-            let function_fields = duck_fields.clone();
+// chan<T>, like map it's no keyword, so that chan can still be used as an identifier
+fn chan_type_parser<'src, I, P>(
+    p: P,
+) -> impl Parser<'src, I, TypeExpr, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+    P: Parser<'src, I, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>> + Clone,
+{
+    select_ref! { Token::Ident(identifier) if *identifier == "chan" => () }
+        .ignore_then(p.delimited_by(just(Token::ControlChar('<')), just(Token::ControlChar('>'))))
+        .map(|element| TypeExpr::Chan(Box::new(element)))
+}
 
+// duck { name: T, nickname?: T, ..R }, the fields are sorted by name and an empty duck is any
+fn duck_type_parser<'src, I, P>(
+    p: P,
+) -> impl Parser<'src, I, TypeExpr, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+    P: Parser<'src, I, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>> + Clone,
+{
+    // name?: T is short for name: T?, a duck literal can leave such a field out
+    let field = select_ref! { Token::Ident(identifier) => identifier.to_string() }
+        .then(just(Token::ControlChar('?')).or_not())
+        .then_ignore(just(Token::ControlChar(':')))
+        .then(p)
+        .map(|((name, optional), type_expr)| match optional {
+            Some(_) => {
+                let span = type_expr.1;
+                (name, (optional_of(type_expr), span))
+            }
+            None => (name, type_expr),
+        });
+
+    // ..R in a duck stands for the fields of R, which is bound to the fields of the given duck
+    // that aren't named explicitly
+    let rest = just(Token::DotDot)
+        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+        .map_with(|name, e| {
+            let type_expr = TypeExpr::RawTypeName(false, vec![name], None);
+            (DUCK_REST.to_string(), (type_expr, e.span()))
+        });
+
+    let duck_fields = field
+        .or(rest)
+        .separated_by(just(Token::ControlChar(',')))
+        .allow_trailing()
+        .collect::<Vec<(String, Spanned<TypeExpr>)>>();
+
+    just(Token::Duck)
+        .or_not()
+        .ignore_then(just(Token::ControlChar('{')))
+        .ignore_then(duck_fields.or_not())
+        .then_ignore(just(Token::ControlChar('}')))
+        .validate(|fields, e, emitter| {
+            let rests = fields.iter().flatten().filter(|x| x.0 == DUCK_REST).count();
+            if rests > 1 {
+                emitter.emit(Rich::custom(e.span(), "a duck can only have one ..rest"));
+            }
+            fields
+        })
+        .map(|fields| match fields {
+            Some(mut fields) => {
+                if fields.is_empty() {
+                    return TypeExpr::Any;
+                }
+
+                fields.sort_by_key(|x| x.0.clone());
+                TypeExpr::Duck(Duck {
+                    fields: fields
+                        .iter()
+                        .cloned()
+                        .map(|(name, (type_expr, e))| Field::new(name, (type_expr, e)))
+                        .collect(),
+                })
+            }
+            _ => TypeExpr::Any,
+        })
+}
+
+pub fn type_expression_parser_without_array<'src, I>()
+-> impl Parser<'src, I, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>> + Clone
+where
+    I: BorrowInput<'src, Token = Token, Span = SS>,
+{
+    recursive(
+        |p: Recursive<dyn Parser<'_, _, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>>>| {
             let go_type_identifier: impl Parser<'src, I, String, extra::Err<Rich<'src, Token, SS>>> =
                 select_ref! { Token::Ident(identifier) => identifier.to_string() }
                     .separated_by(just(Token::ControlChar('.')))
@@ -232,67 +422,26 @@ where
                     .map(|str| str.join("."));
 
             let typeof_expr = just(Token::TypeOf)
-                .ignore_then(select_ref! { Token::Ident(identifier) => identifier.clone() })
+                .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
                 .map(TypeExpr::TypeOf);
 
             let go_type = just(Token::Go)
                 .ignore_then(go_type_identifier)
                 .map(TypeExpr::Go);
 
-            let string_literal =
-                select_ref! { Token::ConstString(str) => str.clone() }.map(TypeExpr::ConstString);
+            let string_literal = select_ref! { Token::ConstString(str) => str.to_string() }
+                .map(TypeExpr::ConstString);
 
             let bool_literal =
                 select_ref! { Token::ConstBool(bool) => *bool }.map(TypeExpr::ConstBool);
 
-            let int_literal = select_ref! { Token::ConstInt(int) => *int }
-                .map(|int| TypeExpr::ConstInt(int.try_into().unwrap())); // TODO: unwrap!
+            let int_literal = int_literal_type_parser();
 
             let tag = just(Token::ControlChar('.'))
                 .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
                 .map(TypeExpr::Tag);
 
-            let duck = just(Token::Duck)
-                .or_not()
-                .ignore_then(just(Token::ControlChar('{')))
-                .ignore_then(duck_fields.or_not())
-                .then_ignore(just(Token::ControlChar('}')))
-                .map(|fields| match fields {
-                    Some(mut fields) => {
-                        if fields.is_empty() {
-                            return TypeExpr::Any;
-                        }
-
-                        fields.sort_by_key(|x| x.0.clone());
-                        TypeExpr::Duck(Duck {
-                            fields: fields
-                                .iter()
-                                .cloned()
-                                .map(|(name, (type_expr, e))| Field {
-                                    name,
-                                    type_expr: (type_expr, e),
-                                })
-                                .collect(),
-                        })
-                    }
-                    _ => TypeExpr::Any,
-                });
-
-            let function = just(Token::Function)
-                .ignore_then(just(Token::ControlChar('(')))
-                .ignore_then(function_fields)
-                .then_ignore(just(Token::ControlChar(')')))
-                .then_ignore(just(Token::ThinArrow))
-                .then(p.clone())
-                .map(|(fields, return_type)| {
-                    TypeExpr::Fun(
-                        fields
-                            .iter()
-                            .map(|field| (Some(field.0.clone()), field.1.clone()))
-                            .collect::<Vec<_>>(),
-                        Some(Box::new(return_type.clone())),
-                    )
-                });
+            let duck = duck_type_parser(p.clone());
 
             let tuple = p
                 .clone()
@@ -330,6 +479,7 @@ where
                         "String" => TypeExpr::String,
                         "Char" => TypeExpr::Char,
                         "Html" => TypeExpr::Html,
+                        "Never" => TypeExpr::Never,
                         _ => TypeExpr::RawTypeName(is_global.is_some(), identifier, type_params),
                     },
                 );
@@ -344,9 +494,11 @@ where
                     tag,
                     typeof_expr,
                     go_type,
+                    map_type_parser(p.clone()),
+                    chan_type_parser(p.clone()),
                     type_name,
                     duck,
-                    function,
+                    function_type_parser(p.clone()),
                     tuple,
                 ))
                 .map_with(|x, e| (x, e.span())));
@@ -376,18 +528,6 @@ where
 {
     recursive(
         |p: Recursive<dyn Parser<'_, _, Spanned<TypeExpr>, extra::Err<Rich<'src, Token, SS>>>>| {
-            let field = select_ref! { Token::Ident(identifier) => identifier.to_string() }
-                .then_ignore(just(Token::ControlChar(':')))
-                .then(p.clone());
-
-            let duck_fields = field
-                .clone()
-                .separated_by(just(Token::ControlChar(',')))
-                .allow_trailing()
-                .collect::<Vec<(String, Spanned<TypeExpr>)>>();
-
-            let function_fields = duck_fields.clone();
-
             let go_type_identifier: impl Parser<'src, I, String, extra::Err<Rich<'src, Token, SS>>> =
                 select_ref! { Token::Ident(identifier) => identifier.to_string() }
                     .separated_by(just(Token::ControlChar('.')))
@@ -401,62 +541,21 @@ where
                 .map(TypeExpr::Go);
 
             let typeof_expr = just(Token::TypeOf)
-                .ignore_then(select_ref! { Token::Ident(identifier) => identifier.clone() })
+                .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
                 .map(TypeExpr::TypeOf);
 
-            let duck = just(Token::Duck)
-                .or_not()
-                .ignore_then(just(Token::ControlChar('{')))
-                .ignore_then(duck_fields.or_not())
-                .then_ignore(just(Token::ControlChar('}')))
-                .map(|fields| match fields {
-                    Some(mut fields) => {
-                        if fields.is_empty() {
-                            return TypeExpr::Any;
-                        }
-
-                        fields.sort_by_key(|x| x.0.clone());
-                        TypeExpr::Duck(Duck {
-                            fields: fields
-                                .iter()
-                                .cloned()
-                                .map(|(name, (type_expr, e))| Field {
-                                    name,
-                                    type_expr: (type_expr, e),
-                                })
-                                .collect(),
-                        })
-                    }
-                    _ => TypeExpr::Any,
-                });
+            let duck = duck_type_parser(p.clone());
 
-            let string_literal =
-                select_ref! { Token::ConstString(str) => str.clone() }.map(TypeExpr::ConstString);
+            let string_literal = select_ref! { Token::ConstString(str) => str.to_string() }
+                .map(TypeExpr::ConstString);
             let bool_literal =
                 select_ref! { Token::ConstBool(bool) => *bool }.map(TypeExpr::ConstBool);
-            let int_literal = select_ref! { Token::ConstInt(int) => *int }
-                .map(|int| TypeExpr::ConstInt(int.try_into().unwrap())); // TODO: unwrap!
+            let int_literal = int_literal_type_parser();
 
             let tag = just(Token::ControlChar('.'))
                 .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
                 .map(TypeExpr::Tag);
 
-            let function = just(Token::Function)
-                .ignore_then(just(Token::ControlChar('(')))
-                .ignore_then(function_fields)
-                .then_ignore(just(Token::ControlChar(')')))
-                .then_ignore(just(Token::ThinArrow))
-                .then(p.clone())
-                .map(|(fields, return_type)| {
-                    TypeExpr::Fun(
-                        fields
-                            .iter()
-                            .map(|field| (Some(field.0.clone()), field.1.clone()))
-                            .collect::<Vec<_>>(),
-                        Some(Box::new(return_type.clone())),
-                    )
-                });
-
             let tuple = p
                 .clone()
                 .separated_by(just(Token::ControlChar(',')))
@@ -493,6 +592,7 @@ where
                         "String" => TypeExpr::String,
                         "Char" => TypeExpr::Char,
                         "Html" => TypeExpr::Html,
+                        "Never" => TypeExpr::Never,
                         _ => TypeExpr::RawTypeName(is_global.is_some(), identifier, type_params),
                     },
                 );
@@ -507,24 +607,36 @@ where
                     tag,
                     typeof_expr,
                     go_type,
+                    map_type_parser(p.clone()),
+                    chan_type_parser(p.clone()),
                     type_name,
                     duck,
-                    function,
+                    function_type_parser(p.clone()),
                     tuple,
                 ))
                 .map_with(|x, e| (x, e.span())));
 
+            // T[] and T?, the postfixes apply from left to right, Int?[] is an array of optionals
             let array = term_type_expr
                 .clone()
                 .then(
-                    (just(Token::ControlChar('[')).then(just(Token::ControlChar(']'))))
-                        .repeated()
-                        .collect::<Vec<_>>(),
+                    choice((
+                        just(Token::ControlChar('['))
+                            .then(just(Token::ControlChar(']')))
+                            .to(false),
+                        just(Token::ControlChar('?')).to(true),
+                    ))
+                    .repeated()
+                    .collect::<Vec<_>>(),
                 )
-                .map(|((x, span), is_array)| {
-                    is_array
-                        .iter()
-                        .fold(x, |acc, _| TypeExpr::Array((acc, span).into()))
+                .map(|((x, span), postfixes)| {
+                    postfixes.iter().fold(x, |acc, is_optional| {
+                        if *is_optional {
+                            optional_of((acc, span))
+                        } else {
+                            TypeExpr::Array((acc, span).into())
+                        }
+                    })
                 })
                 .map_with(|x, e| (x, e.span()));
 
@@ -546,6 +658,20 @@ where
     )
 }
 
+// T? is short for T | .none, the same as Opt<T> from std
+pub fn optional_of(type_expr: Spanned<TypeExpr>) -> TypeExpr {
+    let span = type_expr.1;
+    let mut variants = Vec::new();
+    merge_or(&type_expr, &mut variants);
+    if !variants
+        .iter()
+        .any(|variant| matches!(&variant.0, TypeExpr::Tag(tag) if tag == "none"))
+    {
+        variants.push((TypeExpr::Tag("none".to_string()), span));
+    }
+    TypeExpr::Or(variants)
+}
+
 pub fn merge_or(t: &Spanned<TypeExpr>, o: &mut Vec<Spanned<TypeExpr>>) {
     if let TypeExpr::Or(elems) = &t.0 {
         for elem in elems {
@@ -561,17 +687,22 @@ pub fn type_definition_parser<'src, I>()
 where
     I: BorrowInput<'src, Token = Token, Span = SS>,
 {
-    just(Token::Type)
-        .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+    doc_comments_parser()
+        .then_ignore(just(Token::Type))
+        .then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
         .then(generics_parser().or_not())
         .then_ignore(just(Token::ControlChar('=')))
         .then(type_expression_parser())
         .then_ignore(just(Token::ControlChar(';')))
-        .map(|((identifier, generics), type_expression)| TypeDefinition {
-            name: identifier,
-            type_expression,
-            generics,
-        })
+        .map(
+            |(((doc_comments, identifier), generics), type_expression)| TypeDefinition {
+                name: identifier,
+                type_expression,
+                generics,
+                doc_comments,
+                attributes: Vec::new(),
+            },
+        )
 }
 
 #[cfg(test)]
@@ -579,6 +710,7 @@ pub mod tests {
     use crate::parse::{
         lexer::lex_parser,
         make_input,
+        source_map::FileId,
         value_parser::{empty_range, type_expr_into_empty_range},
     };
     use chumsky::Parser;
@@ -594,8 +726,8 @@ pub mod tests {
                     .fields
                     .into_iter()
                     .map(|field| Field {
-                        name: field.name,
                         type_expr: strip_spans(field.type_expr),
+                        ..field
                     })
                     .collect(),
             }),
@@ -621,6 +753,10 @@ pub mod tests {
             TypeExpr::Array(type_expr) => {
                 TypeExpr::Array(Box::new(strip_spans(type_expr.as_ref().clone())))
             }
+            TypeExpr::Map(key, value) => {
+                TypeExpr::Map(Box::new(strip_spans(*key)), Box::new(strip_spans(*value)))
+            }
+            TypeExpr::Chan(element) => TypeExpr::Chan(Box::new(strip_spans(*element))),
             other => other,
         };
         (stripped_expr, empty_range())
@@ -628,7 +764,7 @@ pub mod tests {
 
     fn assert_type_expression(input_str: &str, expected_expr: TypeExpr) {
         println!("lexing and parsing: \"{}\"", input_str);
-        let lexer_parse_result = lex_parser("test", "").parse(input_str);
+        let lexer_parse_result = lex_parser(FileId::EMPTY).parse(input_str);
         assert!(
             !lexer_parse_result.has_errors(),
             "lexing errors for \"{}\": {:?}",
@@ -702,6 +838,8 @@ pub mod tests {
 
         assert_type_expression("555", TypeExpr::ConstInt(555));
 
+        assert_type_expression("-1", TypeExpr::ConstInt(-1));
+
         assert_type_expression(
             "555 | 1000",
             TypeExpr::Or(vec![
@@ -744,6 +882,28 @@ pub mod tests {
             ),
         );
 
+        assert_type_expression(
+            "fn(Int, String) -> Bool",
+            TypeExpr::Fun(
+                vec![
+                    (None, TypeExpr::Int.into_empty_span()),
+                    (None, TypeExpr::String.into_empty_span()),
+                ],
+                Some(Box::new(TypeExpr::Bool.into_empty_span())),
+            ),
+        );
+
+        assert_type_expression(
+            "fn(Int[])",
+            TypeExpr::Fun(
+                vec![(
+                    None,
+                    TypeExpr::Array(TypeExpr::Int.into_empty_span().into()).into_empty_span(),
+                )],
+                None,
+            ),
+        );
+
         assert_type_expression(
             "(String | Bool) | Int",
             TypeExpr::Or(vec![
@@ -1283,6 +1443,7 @@ pub mod tests {
 
         assert_type_expression("{}", TypeExpr::Any);
         assert_type_expression("duck {}", TypeExpr::Any);
+        assert_type_expression("Never", TypeExpr::Never);
 
         assert_type_expression("go fmt", TypeExpr::Go("fmt".to_string()));
         assert_type_expression(
@@ -1300,6 +1461,19 @@ pub mod tests {
             }),
         );
 
+        assert_type_expression(
+            "duck { id: Int, ..R }",
+            TypeExpr::Duck(Duck {
+                fields: vec![
+                    Field::new(
+                        DUCK_REST.to_string(),
+                        TypeExpr::RawTypeName(false, vec!["R".to_string()], None).into_empty_span(),
+                    ),
+                    Field::new("id".to_string(), TypeExpr::Int.into_empty_span()),
+                ],
+            }),
+        );
+
         assert_type_expression(
             "duck { age: Int, active: Bool }",
             TypeExpr::Duck(Duck {
@@ -1474,6 +1648,35 @@ pub mod tests {
                 .into_empty_span(),
             ]),
         );
+
+        assert_type_expression(
+            "Int?",
+            TypeExpr::Or(vec![
+                TypeExpr::Int.into_empty_span(),
+                TypeExpr::Tag("none".to_string()).into_empty_span(),
+            ]),
+        );
+
+        assert_type_expression(
+            "Int?[]",
+            TypeExpr::Array(
+                TypeExpr::Or(vec![
+                    TypeExpr::Int.into_empty_span(),
+                    TypeExpr::Tag("none".to_string()).into_empty_span(),
+                ])
+                .into_empty_span()
+                .into(),
+            ),
+        );
+
+        assert_type_expression(
+            "(Int | .none)? | String",
+            TypeExpr::Or(vec![
+                TypeExpr::Int.into_empty_span(),
+                TypeExpr::Tag("none".to_string()).into_empty_span(),
+                TypeExpr::String.into_empty_span(),
+            ]),
+        );
     }
 
     // TODO(@mvmo): type definition testing
@@ -1507,7 +1710,7 @@ pub mod tests {
 
         for valid_type_definition in valid_type_definitions {
             println!("lexing {valid_type_definition}");
-            let lexer_parse_result = lex_parser("test", "").parse(valid_type_definition);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(valid_type_definition);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -1541,11 +1744,26 @@ pub mod tests {
             "::X",
             "::X::Y",
             "X::Y::Z",
+            "map<String, Int>",
+            "map<Int, { x: String }>",
+            "map<Char, map<String, Int[]>>[]",
+            "chan<Int>",
+            "chan<chan<String>>[]",
+            "chan<{ x: Int }>",
+            "fn(Int, String) -> Bool",
+            "fn(x: Int, String,)",
+            "{ on_click: fn(Int) -> Bool }",
+            "fn() -> fn(Int) -> Int",
+            "duck { name: String, nickname?: String }",
+            "{ parent?: { name: String } }",
+            "duck { ..R }",
+            "duck { id: Int, ..R }",
+            "{ ..R, id: Int, }",
         ];
 
         for valid_type_expression in valid_type_expressions {
             println!("lexing {valid_type_expression}");
-            let lexer_parse_result = lex_parser("test", "").parse(valid_type_expression);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(valid_type_expression);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -1577,11 +1795,20 @@ pub mod tests {
             "::",
             "X:Y:Z:",
             "Y::Y::",
+            "map<{ x: Int }, String>",
+            "map<Int[], String>",
+            "{ ..R, ..S }",
+            "{ ..{ x: Int } }",
+            "fn(Int,, String)",
+            "fn(x:) -> Int",
+            "fn Int -> Int",
+            "{ nickname? String }",
+            "{ nickname: ?String }",
         ];
 
         for invalid_type_expression in invalid_type_expressions {
             println!("lexing {invalid_type_expression}");
-            let lexer_parse_result = lex_parser("test", "").parse(invalid_type_expression);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(invalid_type_expression);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
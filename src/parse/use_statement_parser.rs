@@ -8,15 +8,33 @@ use super::lexer::Token;
 pub enum Indicator {
     Symbols(Vec<String>),
     Module(String),
-    Wildcard,
+    // use a::*; the span is kept for the errors of a collision between two wildcards
+    Wildcard(SS),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum UseStatement {
     Regular(bool, Vec<Indicator>),
+    // use a::b as c;
+    Alias(bool, Vec<String>, String),
     Go(String, Option<String>),
 }
 
+impl UseStatement {
+    // the names a use statement brings into scope, a wildcard only has names once
+    // resolve_wildcard_imports replaced it
+    pub fn imported_names(&self) -> Vec<String> {
+        match self {
+            UseStatement::Regular(_, indicators) => match indicators.last() {
+                Some(Indicator::Symbols(symbols)) => symbols.clone(),
+                _ => Vec::new(),
+            },
+            UseStatement::Alias(_, _, alias) => vec![alias.clone()],
+            UseStatement::Go(..) => Vec::new(),
+        }
+    }
+}
+
 fn regular_use_parser<'src, I>()
 -> impl Parser<'src, I, UseStatement, extra::Err<Rich<'src, Token, SS>>> + Clone
 where
@@ -36,7 +54,8 @@ where
         .then_ignore(just(Token::ControlChar('}')))
         .map(Indicator::Symbols);
 
-    let wildcard_indicator_parser = just(Token::ControlChar('*')).to(Indicator::Wildcard);
+    let wildcard_indicator_parser =
+        just(Token::ControlChar('*')).map_with(|_, e| Indicator::Wildcard(e.span()));
 
     just(Token::Use)
         .ignore_then(just(Token::ScopeRes).or_not().map(|x| x.is_some()))
@@ -48,8 +67,28 @@ where
                 .at_least(1)
                 .collect::<Vec<Indicator>>(),
         )
+        .then(
+            just(Token::As)
+                .ignore_then(select_ref! { Token::Ident(i) => i.to_string() })
+                .or_not(),
+        )
         .then_ignore(just(Token::ControlChar(';')))
-        .map(|(is_global, i)| UseStatement::Regular(is_global, i))
+        .try_map(|((is_global, i), alias), span| {
+            let Some(alias) = alias else {
+                return Ok(UseStatement::Regular(is_global, i));
+            };
+            let path = i
+                .into_iter()
+                .map(|indicator| match indicator {
+                    Indicator::Module(name) => Ok(name),
+                    _ => Err(Rich::custom(
+                        span,
+                        "only a path can be renamed, like use a::b as c;",
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(UseStatement::Alias(is_global, path, alias))
+        })
 }
 
 fn go_use_parser<'src, I>()
@@ -58,10 +97,10 @@ where
     I: BorrowInput<'src, Token = Token, Span = SS>,
 {
     (just(Token::Use).then(just(Token::Go)))
-        .ignore_then(select_ref! { Token::ConstString(s) => s.to_owned() })
+        .ignore_then(select_ref! { Token::ConstString(s) => s.to_string() })
         .then(
             just(Token::As)
-                .ignore_then(select_ref! { Token::Ident(i) => i.to_owned() })
+                .ignore_then(select_ref! { Token::Ident(i) => i.to_string() })
                 .or_not(),
         )
         .then_ignore(just(Token::ControlChar(';')))
@@ -78,7 +117,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::parse::{lexer::lex_parser, make_input, value_parser::empty_range};
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_map::FileId, value_parser::empty_range,
+    };
 
     use super::*;
 
@@ -122,6 +163,32 @@ mod tests {
                 ),
             ),
             ("use go \"fmt\";", UseStatement::Go("fmt".into(), None)),
+            (
+                "use geometry::shapes as sh;",
+                UseStatement::Alias(
+                    false,
+                    vec!["geometry".to_string(), "shapes".to_string()],
+                    "sh".to_string(),
+                ),
+            ),
+            (
+                "use ::std::io::println as say;",
+                UseStatement::Alias(
+                    true,
+                    vec!["std".to_string(), "io".to_string(), "println".to_string()],
+                    "say".to_string(),
+                ),
+            ),
+            (
+                "use geometry::*;",
+                UseStatement::Regular(
+                    false,
+                    vec![
+                        Indicator::Module("geometry".to_string()),
+                        Indicator::Wildcard(empty_range()),
+                    ],
+                ),
+            ),
             (
                 "use go \"fmt\" as FMT;",
                 UseStatement::Go("fmt".into(), Some("FMT".into())),
@@ -130,7 +197,7 @@ mod tests {
 
         for (src, expected_ast) in src_and_expected_ast {
             println!("lexing {src}");
-            let lexer_parse_result = lex_parser("test", "").parse(src);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(src);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -144,10 +211,17 @@ mod tests {
             assert_eq!(use_statement_parse_result.has_errors(), false);
             assert_eq!(use_statement_parse_result.has_output(), true);
 
-            let Some(ast) = use_statement_parse_result.into_output() else {
+            let Some(mut ast) = use_statement_parse_result.into_output() else {
                 unreachable!()
             };
 
+            if let UseStatement::Regular(_, indicators) = &mut ast {
+                for indicator in indicators {
+                    if let Indicator::Wildcard(span) = indicator {
+                        *span = empty_range();
+                    }
+                }
+            }
             assert_eq!(ast, expected_ast);
         }
 
@@ -165,11 +239,14 @@ mod tests {
             "use go::x;",
             "use go::x;",
             "use go as;",
+            "use std::io::{println} as p;",
+            "use geometry::* as g;",
+            "use geometry as;",
         ];
 
         for invalid_use_statement in invalid_use_statements {
             println!("lexing {invalid_use_statement}");
-            let lexer_parse_result = lex_parser("test", "").parse(invalid_use_statement);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(invalid_use_statement);
 
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
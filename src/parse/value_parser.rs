@@ -1,13 +1,20 @@
-use crate::parse::{
-    Context, SS, Spanned,
-    function_parser::{LambdaFunctionExpr, Param},
-    lexer::{FmtStringContents, HtmlStringContents},
-    source_file_parser::SourceFile,
-    type_parser::{type_expression_parser, type_expression_parser_without_array},
+use crate::{
+    parse::{
+        Context, Field, SS, Spanned,
+        function_parser::{LambdaFunctionExpr, Param},
+        lexer::{FmtStringContents, GO_IDENT_ESCAPE, HtmlStringContents},
+        source_file_parser::SourceFile,
+        source_map::{self, FileId},
+        type_parser::{Duck, type_expression_parser, type_expression_parser_without_array},
+    },
+    semantics::ident_mangler::mangle,
 };
 
 use super::{lexer::Token, type_parser::TypeExpr};
-use chumsky::{input::BorrowInput, prelude::*};
+use chumsky::{
+    input::{BorrowInput, SliceInput},
+    prelude::*,
+};
 
 pub type TypeParam = TypeExpr;
 
@@ -17,12 +24,24 @@ pub struct MatchArm {
     pub identifier_binding: Option<String>,
     pub condition: Option<Spanned<ValueExpr>>,
     pub value_expr: Spanned<ValueExpr>,
+    // the fields of a pattern that were written without a type, in a duck pattern their type
+    // in type_case is inferred from the matched value
+    pub untyped_fields: Vec<String>,
+}
+
+// let x = <-ch => .., <-ch => .. or ch <- v => .., the case is a Receive or a Send
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectArm {
+    pub binding: Option<String>,
+    pub case: Spanned<ValueExpr>,
+    pub value_expr: Spanned<ValueExpr>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ValFmtStringContents {
     String(String),
-    Expr(Spanned<ValueExpr>),
+    // the interpolated value and its format spec, see FmtStringContents::Tokens
+    Expr(Spanned<ValueExpr>, Option<String>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -57,7 +76,7 @@ pub enum ValueExpr {
         params: Vec<Spanned<ValueExpr>>,
         type_params: Option<Vec<Spanned<TypeParam>>>,
     },
-    Int(i64),
+    Int(i128),
     String(String, bool),
     Bool(bool),
     Float(f64),
@@ -73,9 +92,21 @@ pub enum ValueExpr {
         condition: Box<Spanned<ValueExpr>>,
         body: Box<Spanned<ValueExpr>>,
     },
+    // for ident in target { body }, the target is an array or a range
+    For {
+        ident: String,
+        target: Box<Spanned<ValueExpr>>,
+        body: Box<Spanned<ValueExpr>>,
+    },
+    // loop { body }, runs until a break, its value is the value of the breaks
+    Loop {
+        body: Box<Spanned<ValueExpr>>,
+        // filled in by the type resolution
+        break_type: Option<TypeExpr>,
+    },
     Tuple(Vec<Spanned<ValueExpr>>),
     Block(Vec<Spanned<ValueExpr>>),
-    Break,
+    Break(Option<Box<Spanned<ValueExpr>>>),
     Continue,
     Duck(Vec<(String, Spanned<ValueExpr>)>),
     HtmlString(Vec<ValHtmlStringContents>),
@@ -89,7 +120,22 @@ pub enum ValueExpr {
         target_obj: Box<Spanned<ValueExpr>>,
         field_name: String,
     },
+    // a?.b, the field of a or .none if a is .none, typeresolve turns it into a match
+    OptionalFieldAccess {
+        target_obj: Box<Spanned<ValueExpr>>,
+        field_name: String,
+    },
+    // a ?? b, a unless it's .none, then b. typeresolve turns it into a match as well
+    Coalesce(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
+    // S { x: 1, ..base } or { x: 1, ..base }, the literal with the given fields and the value the
+    // other fields are copied from. typeresolve spells the copied fields out
+    Update(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
     Array(Option<Spanned<TypeExpr>>, Vec<Spanned<ValueExpr>>),
+    // #{key: value}, the type is only given for #<K, V>{..}
+    Map(
+        Option<Spanned<TypeExpr>>,
+        Vec<(Spanned<ValueExpr>, Spanned<ValueExpr>)>,
+    ),
     Return(Option<Box<Spanned<ValueExpr>>>),
     VarAssign(Box<Spanned<Assignment>>),
     VarDecl(Box<Spanned<Declaration>>),
@@ -98,7 +144,19 @@ pub enum ValueExpr {
     Mul(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
     Div(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
     Mod(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
+    BitAnd(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
+    BitOr(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
+    BitXor(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
+    ShiftLeft(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
+    ShiftRight(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
     BoolNegate(Box<Spanned<ValueExpr>>),
+    BitNegate(Box<Spanned<ValueExpr>>),
+    Negate(Box<Spanned<ValueExpr>>),
+    Range {
+        start: Box<Spanned<ValueExpr>>,
+        end: Box<Spanned<ValueExpr>>,
+        inclusive: bool,
+    },
     Equals(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
     NotEquals(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
     LessThan(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
@@ -110,12 +168,56 @@ pub enum ValueExpr {
     InlineGo(String),
     Lambda(Box<LambdaFunctionExpr>),
     ArrayAccess(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
+    // xs[1..n], s[..5] and xs[2..], the part of an array or a string from start up to end
+    Slice {
+        target: Box<Spanned<ValueExpr>>,
+        start: Option<Box<Spanned<ValueExpr>>>,
+        end: Option<Box<Spanned<ValueExpr>>>,
+        inclusive: bool,
+    },
     Match {
         value_expr: Box<Spanned<ValueExpr>>,
         arms: Vec<MatchArm>,
         else_arm: Option<Box<MatchArm>>,
     },
     FormattedString(Vec<ValFmtStringContents>),
+    Comptime(Box<Spanned<ValueExpr>>),
+    // typeof(x), the name of the type x has at runtime
+    TypeOf(Box<Spanned<ValueExpr>>),
+    // x as T, converts between numbers and chars or widens x to T
+    Cast(Box<Spanned<ValueExpr>>, Spanned<TypeExpr>),
+    // defer f(x), the params are evaluated right away, the call runs when the function returns
+    Defer(Box<Spanned<ValueExpr>>),
+    // go f(x), runs the call in a new goroutine, the params are evaluated right away
+    Spawn(Box<Spanned<ValueExpr>>),
+    // chan<T>() or chan<T>(capacity), a channel without a capacity is unbuffered
+    MakeChan {
+        element_type: Spanned<TypeExpr>,
+        capacity: Option<Box<Spanned<ValueExpr>>>,
+    },
+    // ch <- v, blocks until the value is received or buffered
+    Send(Box<Spanned<ValueExpr>>, Box<Spanned<ValueExpr>>),
+    // <-ch, blocks until there's a value
+    Receive(Box<Spanned<ValueExpr>>),
+    // x?, the value of x or a return of its Err or .none, typeresolve turns it into a match
+    Propagate(Box<Spanned<ValueExpr>>),
+    // f(...xs), passes the array xs as the variadic param, typeresolve unpacks it into the call
+    Spread(Box<Spanned<ValueExpr>>),
+    // @outer while .. {}, break @outer and continue @outer, a label either names a loop or the
+    // loop a break or continue leaves
+    Labeled(String, Box<Spanned<ValueExpr>>),
+    // waits until one of the arms can send or receive, the else arm runs if none can right away
+    Select {
+        arms: Vec<SelectArm>,
+        else_arm: Option<Box<Spanned<ValueExpr>>>,
+    },
+    // recover { body } catch message { handler }, a panic in the body runs the handler with the
+    // message of the panic instead of ending the program
+    Recover {
+        body: Box<Spanned<ValueExpr>>,
+        binding: String,
+        handler: Box<Spanned<ValueExpr>>,
+    },
 }
 
 pub trait IntoBlock {
@@ -150,9 +252,15 @@ impl ValueExpr {
                 condition: _,
                 body: _,
             } => false,
+            ValueExpr::For { .. } => false,
+            ValueExpr::Loop { .. } => false,
+            ValueExpr::Labeled(_, value_expr) => value_expr.0.needs_semicolon(),
             ValueExpr::Block(_) => false,
+            ValueExpr::Comptime(_) => false,
             ValueExpr::InlineGo(_) => false,
             ValueExpr::Match { .. } => false,
+            ValueExpr::Select { .. } => false,
+            ValueExpr::Recover { .. } => false,
             ValueExpr::Add(..)
             | ValueExpr::Mul(..)
             | ValueExpr::Tag(..)
@@ -163,18 +271,34 @@ impl ValueExpr {
             | ValueExpr::Bool(..)
             | ValueExpr::Char(..)
             | ValueExpr::FieldAccess { .. }
+            | ValueExpr::OptionalFieldAccess { .. }
+            | ValueExpr::Update(..)
+            | ValueExpr::Coalesce(..)
             | ValueExpr::Array(..)
+            | ValueExpr::Map(..)
             | ValueExpr::ArrayAccess(..)
+            | ValueExpr::Slice { .. }
             | ValueExpr::Variable(..)
             | ValueExpr::RawVariable(..)
             | ValueExpr::Tuple(..)
-            | ValueExpr::Break
+            | ValueExpr::Break(..)
             | ValueExpr::Continue
             | ValueExpr::Return(..)
+            | ValueExpr::Defer(..)
+            | ValueExpr::Spawn(..)
+            | ValueExpr::MakeChan { .. }
+            | ValueExpr::Send(..)
+            | ValueExpr::Receive(..)
+            | ValueExpr::Spread(..)
+            | ValueExpr::Propagate(..)
             | ValueExpr::Struct { .. }
             | ValueExpr::VarDecl(..)
             | ValueExpr::VarAssign(..)
             | ValueExpr::BoolNegate(..)
+            | ValueExpr::BitNegate(..)
+            | ValueExpr::TypeOf(..)
+            | ValueExpr::Cast(..)
+            | ValueExpr::Negate(..)
             | ValueExpr::Equals(..)
             | ValueExpr::NotEquals(..)
             | ValueExpr::LessThan(..)
@@ -188,6 +312,12 @@ impl ValueExpr {
             | ValueExpr::Sub(..)
             | ValueExpr::Div(..)
             | ValueExpr::Mod(..)
+            | ValueExpr::BitAnd(..)
+            | ValueExpr::BitOr(..)
+            | ValueExpr::BitXor(..)
+            | ValueExpr::ShiftLeft(..)
+            | ValueExpr::ShiftRight(..)
+            | ValueExpr::Range { .. }
             | ValueExpr::FunctionCall { .. } => true,
         }
     }
@@ -197,7 +327,8 @@ pub fn value_expr_parser<'src, I, M>(
     make_input: M,
 ) -> impl Parser<'src, I, Spanned<ValueExpr>, extra::Err<Rich<'src, Token, SS>>> + Clone + 'src
 where
-    I: BorrowInput<'src, Token = Token, Span = SS>,
+    I: BorrowInput<'src, Token = Token, Span = SS>
+        + SliceInput<'src, Slice = &'src [Spanned<Token>]>,
     M: Fn(SS, &'src [Spanned<Token>]) -> I + Clone + 'static,
 {
     let make_input = Box::leak(Box::new(make_input));
@@ -235,6 +366,32 @@ where
                     .ignore_then(type_expression_parser())
                     .boxed();
 
+                // |x, y| x + y, the params are typed any and get their types (and the return
+                // type) from the function type the lambda is passed as. there are no type
+                // annotations here, because | would be ambiguous with union types
+                let shorthand_param_parser =
+                    select_ref! { Token::Ident(identifier) => identifier.to_string() }
+                        .map_with(|identifier, e| (identifier, (TypeExpr::Any, e.span())) as Param);
+
+                let shorthand = shorthand_param_parser
+                    .separated_by(just(Token::ControlChar(',')))
+                    .allow_trailing()
+                    .collect::<Vec<Param>>()
+                    .delimited_by(just(Token::ControlChar('|')), just(Token::ControlChar('|')))
+                    // || is lexed as a single token
+                    .or(just(Token::OrOr).to(Vec::new()))
+                    .then(value_expr_parser.clone())
+                    .map(|(params, value_expr)| {
+                        ValueExpr::Lambda(
+                            LambdaFunctionExpr {
+                                params,
+                                return_type: None,
+                                value_expr,
+                            }
+                            .into(),
+                        )
+                    });
+
                 just(Token::Function)
                     .ignore_then(just(Token::ControlChar('(')))
                     .ignore_then(params_parser)
@@ -257,11 +414,14 @@ where
                             .into(),
                         )
                     })
+                    .or(shorthand)
                     .boxed()
             };
 
-            let params = value_expr_parser
-                .clone()
+            let params = just(Token::DotDotDot)
+                .ignore_then(value_expr_parser.clone())
+                .map_with(|value_expr, e| (ValueExpr::Spread(value_expr.into()), e.span()))
+                .or(value_expr_parser.clone())
                 .separated_by(just(Token::ControlChar(',')))
                 .allow_trailing()
                 .collect::<Vec<_>>()
@@ -277,15 +437,334 @@ where
                 .then(match_arm_condition)
                 .or_not();
 
-            let match_arm = type_expression_parser()
+            let type_pattern_arm = type_expression_parser().map(ArmPattern::Type);
+
+            // everything in front of the => of an arm, if let and while let share it
+            enum ArmPattern {
+                Type(Spanned<TypeExpr>),
+                Values(Vec<ValuePattern>, Spanned<TypeExpr>),
+                // the kind of pattern, the binding of the value is named after it if the arm
+                // doesn't bind it itself
+                Fields(Spanned<TypeExpr>, Vec<(String, FieldPattern)>, &'static str),
+            }
+
+            impl ArmPattern {
+                fn into_arm(
+                    self,
+                    identifier: Option<(String, Option<Spanned<ValueExpr>>)>,
+                    value_expr: Spanned<ValueExpr>,
+                ) -> MatchArm {
+                    match self {
+                        ArmPattern::Type(type_case) => MatchArm {
+                            type_case,
+                            identifier_binding: identifier.clone().map(|x| x.0),
+                            condition: identifier.map(|x| x.1).unwrap_or_else(|| None),
+                            value_expr,
+                            untyped_fields: Vec::new(),
+                        },
+                        ArmPattern::Values(patterns, type_case) => {
+                            values_arm(patterns, type_case, identifier, value_expr)
+                        }
+                        ArmPattern::Fields(type_case, fields, kind) => {
+                            destructure(type_case, fields, identifier, value_expr, kind)
+                        }
+                    }
+                }
+            }
+
+            #[derive(Debug, PartialEq, Clone)]
+            enum FieldPattern {
+                // None if the field has no type annotation
+                Bind(Option<Spanned<TypeExpr>>),
+                // only matches if the field is equal to the literal
+                Literal(Spanned<ValueExpr>, TypeExpr),
+            }
+
+            let sign = just(Token::ControlChar('-'))
+                .or_not()
+                .map(|minus| minus.map_or(1, |_| -1));
+            let pattern_literal = choice((
+                sign.clone()
+                    .then(select_ref! { Token::ConstInt(pre) => *pre })
+                    .then_ignore(just(Token::ControlChar('.')))
+                    .then(select_ref! { Token::ConstInt(frac) => *frac })
+                    .map(|((sign, pre), frac)| {
+                        let float = format!("{pre}.{frac}").parse::<f64>().unwrap();
+                        (ValueExpr::Float(f64::from(sign) * float), TypeExpr::Float)
+                    }),
+                sign.then(select_ref! { Token::ConstInt(i) => *i })
+                    .map(|(sign, i)| {
+                        (
+                            ValueExpr::Int(i128::from(sign) * i128::from(i)),
+                            TypeExpr::Int,
+                        )
+                    }),
+                select_ref! { Token::ConstString(s) => s.to_string() }
+                    .map(|s| (ValueExpr::String(s, true), TypeExpr::String)),
+                select_ref! { Token::ConstBool(b) => *b }
+                    .map(|b| (ValueExpr::Bool(b), TypeExpr::Bool)),
+                select_ref! { Token::CharLiteral(c) => *c }
+                    .map(|c| (ValueExpr::Char(c), TypeExpr::Char)),
+            ))
+            .map_with(|(literal, type_expr), e| ((literal, e.span()), type_expr))
+            .boxed();
+
+            #[derive(Debug, PartialEq, Clone)]
+            enum ValuePattern {
+                Literal(Spanned<ValueExpr>),
+                // start, end and whether the end is included
+                Range(Spanned<ValueExpr>, Spanned<ValueExpr>, bool),
+            }
+
+            // 1 | 2 | 3 => ... and 1..=9 @n => ... compare the matched value instead of checking
+            // its type, the alternatives become the condition of the arm
+            let value_pattern = pattern_literal
+                .clone()
+                .then(
+                    choice((
+                        just(Token::DotDot).to(false),
+                        just(Token::DotDotEquals).to(true),
+                    ))
+                    .then(pattern_literal.clone())
+                    .or_not(),
+                )
+                .try_map(|((start, start_type), end), span| {
+                    let Some((inclusive, (end, end_type))) = end else {
+                        return Ok((ValuePattern::Literal(start), start_type));
+                    };
+
+                    let is_ordered =
+                        matches!(start_type, TypeExpr::Int | TypeExpr::Float | TypeExpr::Char);
+                    if end_type != start_type || !is_ordered {
+                        return Err(Rich::custom(
+                            span,
+                            "a range pattern needs two numbers or two chars of the same type",
+                        ));
+                    }
+
+                    Ok((ValuePattern::Range(start, end, inclusive), start_type))
+                });
+
+            let value_pattern_arm = value_pattern
+                .separated_by(just(Token::ControlChar('|')))
+                .at_least(1)
+                .collect::<Vec<_>>()
+                .try_map(|alternatives, span| {
+                    let type_case = alternatives[0].1.clone();
+                    if alternatives
+                        .iter()
+                        .any(|(_, type_expr)| *type_expr != type_case)
+                    {
+                        return Err(Rich::custom(
+                            span,
+                            "all alternatives of a pattern need to have the same type",
+                        ));
+                    }
+
+                    let patterns = alternatives
+                        .into_iter()
+                        .map(|(pattern, _)| pattern)
+                        .collect::<Vec<_>>();
+                    Ok(ArmPattern::Values(patterns, (type_case, span)))
+                });
+
+            fn values_arm(
+                patterns: Vec<ValuePattern>,
+                type_case: Spanned<TypeExpr>,
+                identifier: Option<(String, Option<Spanned<ValueExpr>>)>,
+                value_expr: Spanned<ValueExpr>,
+            ) -> MatchArm {
+                let span = type_case.1;
+                // named after the arm, so that nested matches don't shadow each other either
+                let (identifier_binding, condition) = identifier.unwrap_or_else(|| {
+                    (format!("value{GO_IDENT_ESCAPE}match{}", span.start), None)
+                });
+
+                let binding = || {
+                    let path = vec![identifier_binding.clone()];
+                    Box::new((ValueExpr::RawVariable(false, path), span))
+                };
+                let matches = patterns
+                    .into_iter()
+                    .map(|pattern| match pattern {
+                        ValuePattern::Literal(literal) => {
+                            (ValueExpr::Equals(binding(), literal.into()), span)
+                        }
+                        ValuePattern::Range(start, end, inclusive) => {
+                            let lower = ValueExpr::GreaterThanOrEquals(binding(), start.into());
+                            let upper = if inclusive {
+                                ValueExpr::LessThanOrEquals(binding(), end.into())
+                            } else {
+                                ValueExpr::LessThan(binding(), end.into())
+                            };
+                            (
+                                ValueExpr::And((lower, span).into(), (upper, span).into()),
+                                span,
+                            )
+                        }
+                    })
+                    .reduce(|lhs, rhs| (ValueExpr::Or(lhs.into(), rhs.into()), span))
+                    .expect("a pattern has at least one alternative");
+
+                let condition = match condition {
+                    Some(condition) => (ValueExpr::And(matches.into(), condition.into()), span),
+                    None => matches,
+                };
+
+                MatchArm {
+                    type_case,
+                    identifier_binding: Some(identifier_binding),
+                    condition: Some(condition),
+                    value_expr,
+                    untyped_fields: Vec::new(),
+                }
+            }
+
+            // { name, age: Int, admin: true } => ... matches everything that has these fields,
+            // fields without a type are typed any here and inferred from the matched value later
+            // on, fields compared to a literal only match if they are equal to it
+            let pattern_fields = select_ref! { Token::Ident(ident) => ident.to_string() }
+                .then(
+                    just(Token::ControlChar(':'))
+                        .ignore_then(
+                            pattern_literal
+                                .clone()
+                                .map(|(value, type_expr)| FieldPattern::Literal(value, type_expr))
+                                .or(type_expression_parser().map(|t| FieldPattern::Bind(Some(t)))),
+                        )
+                        .or_not(),
+                )
+                .map(|(name, pattern)| (name, pattern.unwrap_or(FieldPattern::Bind(None))))
+                .separated_by(just(Token::ControlChar(',')))
+                .at_least(1)
+                .allow_trailing()
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}')))
+                .boxed();
+
+            // the bound fields are declared at the start of the arm and the compared fields are
+            // checked before the condition of the arm
+            fn destructure(
+                type_case: Spanned<TypeExpr>,
+                fields: Vec<(String, FieldPattern)>,
+                identifier: Option<(String, Option<Spanned<ValueExpr>>)>,
+                value_expr: Spanned<ValueExpr>,
+                kind: &str,
+            ) -> MatchArm {
+                let span = type_case.1;
+                // named after the arm like in values_arm, a nested destructuring doesn't shadow it
+                let (identifier_binding, condition) = identifier.unwrap_or_else(|| {
+                    (format!("{kind}{GO_IDENT_ESCAPE}match{}", span.start), None)
+                });
+
+                let field_of_binding = |field_name: String| {
+                    let binding = ValueExpr::RawVariable(false, vec![identifier_binding.clone()]);
+                    let target_obj = Box::new((binding, span));
+                    (
+                        ValueExpr::FieldAccess {
+                            target_obj,
+                            field_name,
+                        },
+                        span,
+                    )
+                };
+
+                let mut exprs = Vec::new();
+                let mut comparisons = Vec::new();
+                let mut untyped_fields = Vec::new();
+                for (name, pattern) in fields {
+                    match pattern {
+                        FieldPattern::Bind(type_expr) => {
+                            if type_expr.is_none() {
+                                untyped_fields.push(name.clone());
+                            }
+                            let declaration = Declaration {
+                                initializer: field_of_binding(name.clone()),
+                                name,
+                                type_expr: None,
+                            };
+                            exprs.push((ValueExpr::VarDecl((declaration, span).into()), span));
+                        }
+                        FieldPattern::Literal(literal, _) => {
+                            let field = field_of_binding(name);
+                            comparisons
+                                .push((ValueExpr::Equals(field.into(), literal.into()), span));
+                        }
+                    }
+                }
+
+                let value_span = value_expr.1;
+                exprs.push(value_expr);
+
+                let condition = comparisons
+                    .into_iter()
+                    .chain(condition)
+                    .reduce(|lhs, rhs| (ValueExpr::And(lhs.into(), rhs.into()), span));
+
+                MatchArm {
+                    type_case,
+                    identifier_binding: Some(identifier_binding),
+                    condition,
+                    value_expr: (ValueExpr::Block(exprs), value_span),
+                    untyped_fields,
+                }
+            }
+
+            let duck_pattern_arm = just(Token::Duck)
+                .or_not()
+                .ignore_then(pattern_fields.clone())
+                .map_with(|fields, e| {
+                    let duck_fields = fields
+                        .iter()
+                        .map(|(name, pattern)| match pattern {
+                            FieldPattern::Bind(Some(type_expr)) => {
+                                Field::new(name.clone(), type_expr.clone())
+                            }
+                            // typed any until the type resolution infers it, see untyped_fields
+                            FieldPattern::Bind(None) => {
+                                Field::new(name.clone(), (TypeExpr::Any, e.span()))
+                            }
+                            FieldPattern::Literal((_, span), type_expr) => {
+                                Field::new(name.clone(), (type_expr.clone(), *span))
+                            }
+                        })
+                        .collect();
+                    let duck = TypeExpr::Duck(Duck {
+                        fields: duck_fields,
+                    });
+                    ArmPattern::Fields((duck, e.span()), fields, "duck")
+                });
+
+            // Point { x, y: 0 } => ... destructures a struct or a variant of an enum
+            let struct_pattern_arm = select_ref! { Token::Ident(ident) => ident.to_string() }
+                .separated_by(just(Token::ScopeRes))
+                .at_least(1)
+                .collect::<Vec<_>>()
+                .map_with(|path, e| (TypeExpr::RawTypeName(false, path, None), e.span()))
+                .then(
+                    pattern_fields.filter(|fields: &Vec<(String, FieldPattern)>| {
+                        // the type of a struct field is already known
+                        fields.iter().all(|(_, pattern)| match pattern {
+                            FieldPattern::Bind(type_expr) => type_expr.is_none(),
+                            FieldPattern::Literal(..) => true,
+                        })
+                    }),
+                )
+                .map(|(type_case, fields)| ArmPattern::Fields(type_case, fields, "struct"));
+
+            let arm_pattern = value_pattern_arm
+                .or(duck_pattern_arm)
+                .or(struct_pattern_arm)
+                .or(type_pattern_arm)
                 .then(match_arm_identifier_binding.clone())
+                .boxed();
+
+            let match_arm = arm_pattern
+                .clone()
                 .then_ignore(just(Token::ThickArrow))
                 .then(value_expr_parser.clone())
-                .map(|((type_expr, identifier), value_expr)| MatchArm {
-                    type_case: type_expr,
-                    identifier_binding: identifier.clone().map(|x| x.0),
-                    condition: identifier.map(|x| x.1).unwrap_or_else(|| None),
-                    value_expr,
+                .map(|((pattern, identifier), value_expr)| {
+                    pattern.into_arm(identifier, value_expr)
                 });
 
             let else_arm = just(Token::Else)
@@ -299,6 +778,7 @@ where
                     identifier_binding: identifier.clone().map(|x| x.0),
                     condition: identifier.map(|x| x.1).unwrap_or_else(|| None),
                     value_expr,
+                    untyped_fields: Vec::new(),
                 });
 
             let r#match = just(Token::Match)
@@ -308,7 +788,12 @@ where
                         .separated_by(just(Token::ControlChar(',')))
                         .allow_trailing()
                         .collect::<Vec<_>>()
-                        .then(else_arm.map(Box::new).or_not())
+                        .then(
+                            else_arm
+                                .map(Box::new)
+                                .then_ignore(just(Token::ControlChar(',')).or_not())
+                                .or_not(),
+                        )
                         .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}'))),
                 )
                 .map(|(value_expr, (arms, else_arm))| ValueExpr::Match {
@@ -319,6 +804,52 @@ where
                 .map_with(|x, e| (x, e.span()))
                 .boxed();
 
+            let select_arm = just(Token::Let)
+                .ignore_then(select_ref! { Token::Ident(identifier) => identifier.to_string() })
+                .then_ignore(just(Token::ControlChar('=')))
+                .or_not()
+                .then(value_expr_parser.clone())
+                .try_map(|(binding, case), span| match (&binding, &case.0) {
+                    (_, ValueExpr::Receive(..)) | (None, ValueExpr::Send(..)) => {
+                        Ok((binding, case))
+                    }
+                    (Some(_), ValueExpr::Send(..)) => Err(Rich::custom(
+                        case.1,
+                        "sending doesn't give a value that could be bound",
+                    )),
+                    _ => Err(Rich::custom(
+                        span,
+                        "a select arm has to receive like <-ch or send like ch <- v",
+                    )),
+                })
+                .then_ignore(just(Token::ThickArrow))
+                .then(value_expr_parser.clone())
+                .map(|((binding, case), value_expr)| SelectArm {
+                    binding,
+                    case,
+                    value_expr,
+                });
+
+            let select = just(Token::Select)
+                .ignore_then(
+                    select_arm
+                        .separated_by(just(Token::ControlChar(',')))
+                        .allow_trailing()
+                        .collect::<Vec<_>>()
+                        .then(
+                            just(Token::Else)
+                                .ignore_then(just(Token::ThickArrow))
+                                .ignore_then(value_expr_parser.clone())
+                                .map(Box::new)
+                                .then_ignore(just(Token::ControlChar(',')).or_not())
+                                .or_not(),
+                        )
+                        .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}'))),
+                )
+                .map(|(arms, else_arm)| ValueExpr::Select { arms, else_arm })
+                .map_with(|x, e| (x, e.span()))
+                .boxed();
+
             let tuple = lambda_parser
                 .clone()
                 .or((just(Token::ControlChar('('))
@@ -350,7 +881,7 @@ where
                         .map_with(|x, e| (x, e.span())),
                 )
                 .then(declare_type)
-                .then(initializer)
+                .then(initializer.clone())
                 .map(|(((ident, _), type_expr), initializer)| {
                     ValueExpr::VarDecl(
                         (
@@ -377,51 +908,154 @@ where
                 )
                 .then_ignore(just(Token::ControlChar('>')));
 
-            let struct_expression = select_ref! { Token::Ident(identifier) => identifier.clone() }
-                .then(struct_type_params_parser.or_not())
-                .then(
-                    select_ref! { Token::Ident(ident) => ident.to_owned() }
-                        .then_ignore(just(Token::ControlChar(':')))
-                        .then(value_expr_parser.clone())
-                        .separated_by(just(Token::ControlChar(',')))
-                        .allow_trailing()
-                        .collect::<Vec<_>>()
-                        .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}'))),
-                )
-                .map(|((identifier, generics), values)| ValueExpr::Struct {
-                    name: identifier,
-                    fields: values,
-                    type_params: generics,
-                })
-                .map_with(|x, e| (x, e.span()))
-                .boxed();
+            #[derive(Debug, PartialEq, Clone)]
+            enum LiteralField {
+                Field(String, Spanned<ValueExpr>),
+                // { x } is short for { x: x }
+                Shorthand(String, SS),
+                // ..base, the fields that aren't given are copied from base
+                Base(Spanned<ValueExpr>),
+            }
 
-            let duck_expression = select_ref! { Token::Ident(ident) => ident.to_owned() }
+            let literal_fields = select_ref! { Token::Ident(ident) => ident.to_string() }
                 .then_ignore(just(Token::ControlChar(':')))
                 .then(value_expr_parser.clone())
+                .map(|(name, value_expr)| LiteralField::Field(name, value_expr))
+                .or(just(Token::DotDot)
+                    .ignore_then(value_expr_parser.clone())
+                    .map(LiteralField::Base))
+                .or(select_ref! { Token::Ident(ident) => ident.to_string() }
+                    .map_with(|name, e| LiteralField::Shorthand(name, e.span())))
                 .separated_by(just(Token::ControlChar(',')))
                 .allow_trailing()
                 .collect::<Vec<_>>()
                 .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}')))
-                .map(|mut x| {
+                .try_map(|literal_fields, span| {
+                    // { x } on its own is a block that evaluates to x
+                    if let [LiteralField::Shorthand(..)] = literal_fields.as_slice() {
+                        return Err(Rich::custom(
+                            span,
+                            "{ x } is a block, a literal with only x is written { x: x }",
+                        ));
+                    }
+
+                    let mut fields = Vec::new();
+                    let mut base = None;
+                    for literal_field in literal_fields {
+                        match literal_field {
+                            LiteralField::Field(name, value_expr) => {
+                                fields.push((name, value_expr))
+                            }
+                            LiteralField::Shorthand(name, span) => {
+                                let variable = ValueExpr::RawVariable(false, vec![name.clone()]);
+                                fields.push((name, (variable, span)))
+                            }
+                            LiteralField::Base(_) if base.is_some() => {
+                                return Err(Rich::custom(span, "only one ..base is allowed"));
+                            }
+                            LiteralField::Base(value_expr) => base = Some(value_expr),
+                        }
+                    }
+                    Ok((fields, base))
+                })
+                .boxed();
+
+            // a literal with ..base is wrapped in an update of base
+            let with_base =
+                |literal: ValueExpr, base: Option<Spanned<ValueExpr>>, span: SS| match base {
+                    Some(base) => ValueExpr::Update((literal, span).into(), base.into()),
+                    None => literal,
+                };
+
+            // Enum::Variant { .. } constructs a variant of an enum, its path is kept mangled
+            let struct_expression =
+                select_ref! { Token::Ident(identifier) => identifier.to_string() }
+                    .separated_by(just(Token::ScopeRes))
+                    .at_least(1)
+                    .collect::<Vec<_>>()
+                    .map(|path| mangle(&path))
+                    .then(struct_type_params_parser.or_not())
+                    .then(literal_fields.clone())
+                    .map_with(move |((identifier, generics), (values, base)), e| {
+                        let literal = ValueExpr::Struct {
+                            name: identifier,
+                            fields: values,
+                            type_params: generics,
+                        };
+                        (with_base(literal, base, e.span()), e.span())
+                    })
+                    .boxed();
+
+            let duck_expression = literal_fields
+                .map_with(move |(mut x, base), e| {
                     x.sort_by_key(|(name, _)| name.clone());
-                    ValueExpr::Duck(x)
+                    (with_base(ValueExpr::Duck(x), base, e.span()), e.span())
                 })
-                .map_with(|x, e| (x, e.span()))
                 .boxed();
 
-            let block_expression = value_expr_parser
-                .clone()
+            let destructuring_names = recursive(|destructuring_names| {
+                select_ref! { Token::Ident(identifier) => identifier.to_string() }
+                    .map(DestructuringName::Name)
+                    .or(destructuring_names.map(DestructuringName::Tuple))
+                    .separated_by(just(Token::ControlChar(',')))
+                    .at_least(2)
+                    .allow_trailing()
+                    .collect::<Vec<_>>()
+                    .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')')))
+            });
+
+            // let (a, b) = x; declares more than one variable, so it's only valid as a statement
+            let destructuring_declaration = just(Token::Let)
+                .ignore_then(destructuring_names)
+                .then(initializer.clone())
+                .map_with(|(names, initializer), e| {
+                    let tuple_name = format!("destructure_{}", e.span().start);
+                    destructure(tuple_name, names, initializer, e.span())
+                })
+                .boxed();
+
+            let block_expression = destructuring_declaration
+                .or(value_expr_parser.clone().map(|expr| vec![expr]))
                 .then(just(Token::ControlChar(';')).or_not())
                 .repeated()
                 .collect::<Vec<_>>()
                 .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}')))
-                .map_with(|mut exprs, e| {
-                    if exprs.len() >= 2 {
-                        for (expr, has_semi) in &exprs[..exprs.len() - 1] {
-                            if expr.0.needs_semicolon() && has_semi.is_none() {
-                                panic!("needs_semi")
-                            }
+                .validate(|statements, e, emitter| {
+                    let mut exprs = Vec::new();
+                    for (statement, has_semi) in statements {
+                        let len = statement.len();
+                        for (i, expr) in statement.into_iter().enumerate() {
+                            let has_semi = if i + 1 == len {
+                                has_semi.clone()
+                            } else {
+                                Some(Token::ControlChar(';'))
+                            };
+                            exprs.push((expr, has_semi));
+                        }
+                    }
+
+                    // the end of a line ends a statement too, only statements on the same line
+                    // have to be separated by a ;. the source is only looked up once a statement
+                    // without a ; shows up
+                    let mut src = None;
+                    for pair in exprs.windows(2) {
+                        let ((expr, has_semi), (next, _)) = (&pair[0], &pair[1]);
+                        if !expr.0.needs_semicolon() || has_semi.is_some() {
+                            continue;
+                        }
+                        let src: &str = src.get_or_insert_with(|| {
+                            source_map::file_contents(expr.1.context.file_id)
+                        });
+                        if !source_map::has_line_break(
+                            src,
+                            expr.1.end as usize,
+                            next.1.start as usize,
+                        ) {
+                            emitter.emit(Rich::custom(
+                                expr.1,
+                                "missing ; after this statement, put it on its own line or end it \
+                                 with a ;",
+                            ));
                         }
                     }
 
@@ -436,13 +1070,151 @@ where
                 })
                 .boxed();
 
+            // catch is no keyword, just like extern
+            let recover = just(Token::Recover)
+                .ignore_then(block_expression.clone())
+                .then_ignore(select_ref! { Token::Ident(ident) if ident == "catch" => () })
+                .then(select_ref! { Token::Ident(binding) => binding.to_string() })
+                .then(block_expression.clone())
+                .map(|((body, binding), handler)| ValueExpr::Recover {
+                    body: Box::new(body),
+                    binding,
+                    handler: Box::new(handler),
+                })
+                .map_with(|x, e| (x, e.span()))
+                .boxed();
+
+            // a token, or a group in parens, brackets or braces with every token inside of it
+            let token_tree = recursive(|token_tree| {
+                let token = select_ref! {
+                    token if !matches!(token, Token::ControlChar('(' | ')' | '[' | ']' | '{' | '}'))
+                        => token.clone()
+                }
+                .map_with(|token, e| vec![(token, e.span())]);
+                let delimited = |open: char, close: char| {
+                    just(Token::ControlChar(open))
+                        .map_with(|token, e| (token, e.span()))
+                        .then(token_tree.clone().repeated().collect::<Vec<Vec<_>>>())
+                        .then(
+                            just(Token::ControlChar(close)).map_with(|token, e| (token, e.span())),
+                        )
+                        .map(|((open, inner), close)| {
+                            let mut tokens = vec![open];
+                            tokens.extend(inner.into_iter().flatten());
+                            tokens.push(close);
+                            tokens
+                        })
+                };
+                choice((
+                    token,
+                    delimited('(', ')'),
+                    delimited('[', ']'),
+                    delimited('{', '}'),
+                ))
+            });
+
+            // the condition of if and while ends at the { of the body. like in rust a struct
+            // literal can only be part of a condition in parens, if ready {} has an empty body.
+            // only the braces of a duck type, like in x is { a: Int }, and of a map literal
+            // belong to the condition. the tokens up to the body are parsed on their own
+            let condition_tokens = choice((
+                select_ref! {
+                    token @ (Token::Is | Token::Duck | Token::ControlChar('#')) => token.clone()
+                }
+                .map_with(|token, e| (token, e.span()))
+                .repeated()
+                .at_least(1)
+                .collect::<Vec<_>>()
+                .then(token_tree.clone())
+                .map(|(mut tokens, tree)| {
+                    tokens.extend(tree);
+                    tokens
+                }),
+                token_tree.filter(|tokens: &Vec<Spanned<Token>>| {
+                    !matches!(tokens[0].0, Token::ControlChar('{' | ';') | Token::Eof)
+                }),
+            ))
+            .repeated()
+            .at_least(1)
+            .to_slice()
+            .map_with({
+                let make_input = make_input.clone();
+                move |tokens, e| {
+                    let span: SS = e.span();
+                    let eoi = SS {
+                        start: span.end,
+                        end: span.end,
+                        context: span.context,
+                    };
+                    make_input(eoi, tokens)
+                }
+            });
             let if_condition = value_expr_parser
                 .clone()
-                .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')')))
+                .nested_in(condition_tokens)
                 .boxed();
             let if_body = block_expression.clone();
+
+            // if let Int @x = (value) {} else {} is a match with a single arm and the else as
+            // its else arm
+            enum IfHead {
+                Condition(Spanned<ValueExpr>),
+                Let(
+                    ArmPattern,
+                    Option<(String, Option<Spanned<ValueExpr>>)>,
+                    Spanned<ValueExpr>,
+                ),
+            }
+
+            impl IfHead {
+                fn into_if(
+                    self,
+                    then: Spanned<ValueExpr>,
+                    r#else: Option<Box<Spanned<ValueExpr>>>,
+                ) -> ValueExpr {
+                    let (pattern, identifier, value_expr) = match self {
+                        IfHead::Condition(condition) => {
+                            return ValueExpr::If {
+                                condition: Box::new(condition),
+                                then: Box::new(then),
+                                r#else,
+                            };
+                        }
+                        IfHead::Let(pattern, identifier, value_expr) => {
+                            (pattern, identifier, value_expr)
+                        }
+                    };
+
+                    let else_value = r#else.map_or((ValueExpr::Block(vec![]), then.1), |x| *x);
+                    ValueExpr::Match {
+                        value_expr: Box::new(value_expr),
+                        arms: vec![pattern.into_arm(identifier, then)],
+                        else_arm: Some(Box::new(MatchArm {
+                            type_case: (TypeExpr::Any, else_value.1),
+                            identifier_binding: None,
+                            condition: None,
+                            value_expr: else_value,
+                            untyped_fields: Vec::new(),
+                        })),
+                    }
+                }
+            }
+
+            let let_pattern_and_value = just(Token::Let)
+                .ignore_then(arm_pattern.clone())
+                .then_ignore(just(Token::ControlChar('=')))
+                .then(if_condition.clone())
+                .boxed();
+
             let if_with_condition_and_body = just(Token::If)
-                .ignore_then(if_condition.clone())
+                .ignore_then(
+                    let_pattern_and_value
+                        .clone()
+                        .map(|((pattern, identifier), value_expr)| {
+                            IfHead::Let(pattern, identifier, value_expr)
+                        })
+                        .or(if_condition.clone().map(IfHead::Condition)),
+                )
                 .then(if_body.clone())
                 .boxed();
 
@@ -453,42 +1225,128 @@ where
                 .then(while_body.clone())
                 .boxed();
 
-            let int = select_ref! { Token::ConstInt(i) => *i }
-                .map(ValueExpr::Int)
-                .map_with(|x, e| (x, e.span()))
+            // while let Int @x = (value) {} runs as long as the value matches, it's a loop that
+            // breaks once the match takes its else arm
+            let while_let = just(Token::While)
+                .ignore_then(let_pattern_and_value.clone())
+                .then(while_body.clone())
+                .map_with(|(((pattern, identifier), value_expr), body), e| {
+                    let span: SS = e.span();
+                    let body_span = body.1;
+                    let matched = (
+                        ValueExpr::Block(vec![body, (ValueExpr::Bool(true), body_span)]),
+                        body_span,
+                    );
+                    let matches = ValueExpr::Match {
+                        value_expr: Box::new(value_expr),
+                        arms: vec![pattern.into_arm(identifier, matched)],
+                        else_arm: Some(Box::new(MatchArm {
+                            type_case: (TypeExpr::Any, span),
+                            identifier_binding: None,
+                            condition: None,
+                            value_expr: (ValueExpr::Bool(false), span),
+                            untyped_fields: Vec::new(),
+                        })),
+                    };
+                    let stop = ValueExpr::If {
+                        condition: Box::new((ValueExpr::BoolNegate((matches, span).into()), span)),
+                        then: Box::new((
+                            ValueExpr::Block(vec![(ValueExpr::Break(None), span)]),
+                            span,
+                        )),
+                        r#else: None,
+                    };
+                    (
+                        ValueExpr::Loop {
+                            body: Box::new((ValueExpr::Block(vec![(stop, span)]), span)),
+                            break_type: None,
+                        },
+                        span,
+                    )
+                })
                 .boxed();
-            let bool_val = select_ref! { Token::ConstBool(b) => *b }
-                .map(ValueExpr::Bool)
-                .map_with(|x, e| (x, e.span()))
+
+            let label = just(Token::ControlChar('@'))
+                .ignore_then(select_ref! { Token::Ident(ident) => ident.to_string() })
                 .boxed();
-            let string_val = select_ref! { Token::ConstString(s) => s.to_owned() }
-                .map(|s| ValueExpr::String(s, true))
-                .map_with(|x, e| (x, e.span()));
+            let with_label = |label: Option<String>, value_expr: Spanned<ValueExpr>| match label {
+                Some(label) => {
+                    let span = value_expr.1;
+                    (ValueExpr::Labeled(label, Box::new(value_expr)), span)
+                }
+                None => value_expr,
+            };
+
+            // xs {} is an empty struct literal, so a loop with an empty body needs parentheses
+            // around its target, for x in (xs) {}
+            let for_in = just(Token::For)
+                .ignore_then(select_ref! { Token::Ident(ident) => ident.to_string() })
+                .then_ignore(just(Token::In))
+                .then(value_expr_parser.clone())
+                .then(block_expression.clone())
+                .map(|((ident, target), body)| ValueExpr::For {
+                    ident,
+                    target: Box::new(target),
+                    body: Box::new(body),
+                })
+                .map_with(|x, e| (x, e.span()))
+                .boxed();
+
+            let labeled_loop = label
+                .clone()
+                .or_not()
+                .then(choice((
+                    while_with_condition_and_body
+                        .clone()
+                        .map(|(cond, body)| ValueExpr::While {
+                            condition: Box::new(cond),
+                            body: Box::new(body),
+                        })
+                        .map_with(|x, e| (x, e.span()))
+                        .or(while_let),
+                    for_in,
+                    just(Token::Loop)
+                        .ignore_then(block_expression.clone())
+                        .map(|body| ValueExpr::Loop {
+                            body: Box::new(body),
+                            break_type: None,
+                        })
+                        .map_with(|x, e| (x, e.span())),
+                )))
+                .map_with(move |(label, r#loop), e| {
+                    let r#loop = with_label(label, r#loop);
+                    (r#loop.0, e.span())
+                })
+                .boxed();
+
+            let int = select_ref! { Token::ConstInt(i) => *i }
+                .map(|i| ValueExpr::Int(i.into()))
+                .map_with(|x, e| (x, e.span()))
+                .boxed();
+            let bool_val = select_ref! { Token::ConstBool(b) => *b }
+                .map(ValueExpr::Bool)
+                .map_with(|x, e| (x, e.span()))
+                .boxed();
+            let string_val = select_ref! { Token::ConstString(s) => s.to_string() }
+                .map(|s| ValueExpr::String(s, true))
+                .map_with(|x, e| (x, e.span()));
             let if_expr = if_with_condition_and_body
                 .clone()
                 .then(
                     just(Token::Else)
                         .ignore_then(if_with_condition_and_body.clone())
                         .repeated()
-                        .collect::<Vec<(Spanned<ValueExpr>, Spanned<ValueExpr>)>>(),
+                        .collect::<Vec<(IfHead, Spanned<ValueExpr>)>>(),
                 )
                 .then(just(Token::Else).ignore_then(if_body.clone()).or_not())
-                .map(|(((condition, then), else_ifs), r#else)| ValueExpr::If {
-                    condition: Box::new(condition),
-                    then: Box::new(then),
-                    r#else: else_ifs.into_iter().rfold(
-                        r#else.map(Box::new),
-                        |acc, (cond, then)| {
-                            Some(Box::new(
-                                ValueExpr::If {
-                                    condition: Box::new(cond),
-                                    then: Box::new(then),
-                                    r#else: Some(acc.unwrap()),
-                                }
-                                .into_empty_span(),
-                            ))
-                        },
-                    ),
+                .map(|(((head, then), else_ifs), r#else)| {
+                    let r#else =
+                        else_ifs
+                            .into_iter()
+                            .rfold(r#else.map(Box::new), |acc, (head, then)| {
+                                Some(Box::new(head.into_if(then, acc).into_empty_span()))
+                            });
+                    head.into_if(then, r#else)
                 })
                 .map_with(|x, e| (x, e.span()))
                 .boxed();
@@ -517,11 +1375,14 @@ where
             enum AtomPostParseUnit {
                 FuncCall(Vec<Spanned<ValueExpr>>, Option<Vec<Spanned<TypeParam>>>),
                 ArrayAccess(Spanned<ValueExpr>),
+                Slice(Option<Spanned<ValueExpr>>, Option<Spanned<ValueExpr>>, bool),
                 FieldAccess(String),
+                OptionalFieldAccess(String),
+                Propagate,
             }
 
             let fmt_string =
-                select_ref! { Token::FormatStringLiteral(elements) => elements.clone() }
+                select_ref! { Token::FormatStringLiteral(elements) => elements.to_vec() }
                     .map({
                         let value_expr_parser = value_expr_parser.clone();
                         let make_input = make_input.clone();
@@ -534,12 +1395,12 @@ where
                                     FmtStringContents::String(s) => {
                                         res.push(ValFmtStringContents::String(s.to_owned()))
                                     }
-                                    FmtStringContents::Tokens(s) => {
+                                    FmtStringContents::Tokens(s, spec) => {
                                         let expr = value_expr_parser
                                             .parse(make_input(empty_range(), s.as_slice()))
                                             .into_result()
                                             .expect("invalid code");
-                                        res.push(ValFmtStringContents::Expr(expr));
+                                        res.push(ValFmtStringContents::Expr(expr, spec.clone()));
                                     }
                                 }
                             }
@@ -549,7 +1410,7 @@ where
                     .map_with(|x, e| (x, e.span()));
 
             let html_string = select_ref! {
-                Token::HtmlString(s) => s.clone()
+                Token::HtmlString(s) => s.to_vec()
             }
             .map({
                 let value_expr_parser = value_expr_parser.clone();
@@ -579,7 +1440,7 @@ where
             .map_with(|x, e| (x, e.span()));
 
             let duckx = select_ref! {
-                Token::InlineDuckx(contents) => contents.clone()
+                Token::InlineDuckx(contents) => contents.to_vec()
             }
             .map({
                 let value_expr_parser = value_expr_parser.clone();
@@ -645,7 +1506,81 @@ where
             })
             .map_with(|x, e| (x, e.span()));
 
-            let atom = just(Token::ControlChar('!'))
+            let map_entries = value_expr_parser
+                .clone()
+                .then_ignore(just(Token::ControlChar(':')))
+                .then(value_expr_parser.clone())
+                .separated_by(just(Token::ControlChar(',')))
+                .allow_trailing()
+                .collect::<Vec<_>>()
+                .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}')));
+
+            let map_type = type_expression_parser()
+                .then_ignore(just(Token::ControlChar(',')))
+                .then(type_expression_parser())
+                .delimited_by(just(Token::ControlChar('<')), just(Token::ControlChar('>')))
+                .validate(|(key, value), e, emitter| {
+                    if !key.0.is_hashable() {
+                        emitter.emit(Rich::custom(
+                            key.1,
+                            format!("{} can't be used as a map key, it isn't hashable", key.0),
+                        ));
+                    }
+                    (TypeExpr::Map(Box::new(key), Box::new(value)), e.span())
+                });
+
+            // #{key: value} and #<K, V>{}, {"a": 1} is a map too as long as its keys are literals
+            let map = just(Token::ControlChar('#'))
+                .ignore_then(map_type.or_not())
+                .then(map_entries)
+                .try_map(|(map_type, entries), span| {
+                    if map_type.is_none() && entries.is_empty() {
+                        return Err(Rich::custom(
+                            span,
+                            "an empty map has to provide its types, like #<String, Int>{}",
+                        ));
+                    }
+                    Ok(ValueExpr::Map(map_type, entries))
+                })
+                .or(choice((string_val.clone(), int.clone(), char_expr.clone()))
+                    .then_ignore(just(Token::ControlChar(':')))
+                    .then(value_expr_parser.clone())
+                    .separated_by(just(Token::ControlChar(',')))
+                    .at_least(1)
+                    .allow_trailing()
+                    .collect::<Vec<_>>()
+                    .delimited_by(just(Token::ControlChar('{')), just(Token::ControlChar('}')))
+                    .map(|entries| ValueExpr::Map(None, entries)))
+                .map_with(|x, e| (x, e.span()));
+
+            // defer f(x) and go f(x) only work on function calls, like in go
+            let call_statement = choice((just(Token::Defer), just(Token::Go)))
+                .then(value_expr_parser.clone())
+                .try_map(|(keyword, call), span| match (keyword, call.0) {
+                    (Token::Defer, call_expr @ ValueExpr::FunctionCall { .. }) => {
+                        Ok((ValueExpr::Defer(Box::new((call_expr, call.1))), span))
+                    }
+                    (Token::Go, call_expr @ ValueExpr::FunctionCall { .. }) => {
+                        Ok((ValueExpr::Spawn(Box::new((call_expr, call.1))), span))
+                    }
+                    (Token::Defer, _) => Err(Rich::custom(
+                        call.1,
+                        "defer needs a function call, like defer file.close()",
+                    )),
+                    _ => Err(Rich::custom(
+                        call.1,
+                        "go needs a function call, like go worker(jobs)",
+                    )),
+                });
+
+            let prefix_op = choice((
+                just(Token::ControlChar('!')),
+                just(Token::ControlChar('~')),
+                just(Token::ControlChar('-')),
+                just(Token::LeftArrow),
+            ));
+
+            let atom = prefix_op
                 .repeated()
                 .collect::<Vec<_>>()
                 .then(
@@ -653,7 +1588,7 @@ where
                         .clone()
                         .delimited_by(just(Token::ControlChar('(')), just(Token::ControlChar(')')))
                         .or(choice((
-                            choice((array.clone(), array_with_type.clone())),
+                            choice((array.clone(), array_with_type.clone(), map)),
                             float_expr,
                             int,
                             fmt_string,
@@ -664,30 +1599,42 @@ where
                             string_val,
                             struct_expression,
                             scope_res_ident.clone(),
-                            r#match,
+                            choice((r#match, select, recover)),
                             if_expr,
                             char_expr,
                             tuple,
                             duck_expression,
+                            just(Token::Comptime)
+                                .ignore_then(block_expression.clone())
+                                .map(|block| ValueExpr::Comptime(Box::new(block)))
+                                .map_with(|x, e| (x, e.span())),
+                            just(Token::TypeOf)
+                                .ignore_then(value_expr_parser.clone().delimited_by(
+                                    just(Token::ControlChar('(')),
+                                    just(Token::ControlChar(')')),
+                                ))
+                                .map(|value_expr| ValueExpr::TypeOf(Box::new(value_expr)))
+                                .map_with(|x, e| (x, e.span())),
                             block_expression,
                             just(Token::Break)
-                                .to(ValueExpr::Break)
-                                .map_with(|x, e| (x, e.span())),
+                                .ignore_then(label.clone().or_not())
+                                .then(value_expr_parser.clone().or_not())
+                                .map_with(|(label, x), e| {
+                                    let r#break = (ValueExpr::Break(x.map(Box::new)), e.span());
+                                    with_label(label, r#break)
+                                }),
                             just(Token::Continue)
-                                .to(ValueExpr::Continue)
-                                .map_with(|x, e| (x, e.span())),
-                            while_with_condition_and_body
-                                .clone()
-                                .map(|(cond, body)| ValueExpr::While {
-                                    condition: Box::new(cond),
-                                    body: Box::new(body),
-                                })
-                                .map_with(|x, e| (x, e.span())),
+                                .ignore_then(label.clone().or_not())
+                                .map_with(|label, e| {
+                                    with_label(label, (ValueExpr::Continue, e.span()))
+                                }),
+                            labeled_loop,
                             just(Token::Return)
                                 .ignore_then(value_expr_parser.clone().or_not())
                                 .map_with(|x: Option<Spanned<ValueExpr>>, e| {
                                     (ValueExpr::Return(x.map(Box::new)), e.span())
                                 }),
+                            call_statement,
                         ))),
                 )
                 .then(
@@ -703,6 +1650,35 @@ where
                                 just(Token::ControlChar(']')),
                             )
                             .map(|x| AtomPostParseUnit::ArrayAccess(x[0].clone())),
+                        // xs[1..n] is an index with a range, see the fold below. these are the
+                        // slices with an open start or end, which aren't ranges on their own
+                        value_expr_parser
+                            .clone()
+                            .or_not()
+                            .then(choice((
+                                just(Token::DotDot).to(false),
+                                just(Token::DotDotEquals).to(true),
+                            )))
+                            .then(value_expr_parser.clone().or_not())
+                            .delimited_by(
+                                just(Token::ControlChar('[')),
+                                just(Token::ControlChar(']')),
+                            )
+                            .validate(|((start, inclusive), end), e, emitter| {
+                                if inclusive && end.is_none() {
+                                    emitter.emit(Rich::custom(
+                                        e.span(),
+                                        "an inclusive slice needs an end, like xs[1..=3]",
+                                    ));
+                                }
+                                if let Some((ValueExpr::Range { .. }, span)) = &start {
+                                    emitter.emit(Rich::custom(
+                                        *span,
+                                        "a slice has one start and one end, ranges don't chain",
+                                    ));
+                                }
+                                AtomPostParseUnit::Slice(start, end, inclusive)
+                            }),
                         just(Token::ControlChar('<'))
                             .ignore_then(
                                 type_expression_parser()
@@ -724,15 +1700,53 @@ where
                                     .or(select_ref! { Token::ConstInt(i) => i.to_string() }),
                             )
                             .map(AtomPostParseUnit::FieldAccess),
+                        just(Token::QuestionDot)
+                            .ignore_then(select_ref! { Token::Ident(s) => s.to_string() })
+                            .map(AtomPostParseUnit::OptionalFieldAccess),
+                        just(Token::ControlChar('?')).to(AtomPostParseUnit::Propagate),
                     ))
                     .repeated()
                     .collect::<Vec<_>>(),
                 )
-                .map(|((neg, target), params)| {
+                .validate(|((neg, target), params), _, emitter| {
                     let target = params.into_iter().fold(target, |acc, x| match x {
+                        AtomPostParseUnit::ArrayAccess((
+                            ValueExpr::Range {
+                                start,
+                                end,
+                                inclusive,
+                            },
+                            _,
+                        )) => ValueExpr::Slice {
+                            target: acc.into(),
+                            start: Some(start),
+                            end: Some(end),
+                            inclusive,
+                        }
+                        .into_empty_span(),
                         AtomPostParseUnit::ArrayAccess(idx_expr) => {
                             ValueExpr::ArrayAccess(acc.into(), idx_expr.into()).into_empty_span()
                         }
+                        AtomPostParseUnit::Slice(start, end, inclusive) => ValueExpr::Slice {
+                            target: acc.into(),
+                            start: start.map(Box::new),
+                            end: end.map(Box::new),
+                            inclusive,
+                        }
+                        .into_empty_span(),
+                        AtomPostParseUnit::FuncCall(params, Some(type_params))
+                            if let ValueExpr::RawVariable(false, path) = &acc.0
+                                && path.len() == 1
+                                && path[0] == "chan" =>
+                        {
+                            match make_chan(type_params, params, acc.1) {
+                                Ok(make_chan) => make_chan.into_empty_span(),
+                                Err(err) => {
+                                    emitter.emit(err);
+                                    acc
+                                }
+                            }
+                        }
                         AtomPostParseUnit::FuncCall(params, type_params) => {
                             ValueExpr::FunctionCall {
                                 target: acc.into(),
@@ -746,11 +1760,42 @@ where
                             field_name,
                         }
                         .into_empty_span(),
+                        AtomPostParseUnit::OptionalFieldAccess(field_name) => {
+                            ValueExpr::OptionalFieldAccess {
+                                target_obj: acc.into(),
+                                field_name,
+                            }
+                            .into_empty_span()
+                        }
+                        AtomPostParseUnit::Propagate => {
+                            ValueExpr::Propagate(acc.into()).into_empty_span()
+                        }
                     });
 
-                    neg.into_iter().fold(target, |acc, _| {
-                        ValueExpr::BoolNegate(acc.into()).into_empty_span()
-                    })
+                    // the innermost prefix is applied first, !~x => !(~x). a minus directly in
+                    // front of a literal is part of it, so -9223372036854775808 is still an Int
+                    neg.into_iter()
+                        .rev()
+                        .fold(target, |acc, op| match (op, acc.0) {
+                            (Token::ControlChar('-'), ValueExpr::Int(i)) => {
+                                (ValueExpr::Int(-i), acc.1)
+                            }
+                            (Token::ControlChar('-'), ValueExpr::Float(f)) => {
+                                (ValueExpr::Float(-f), acc.1)
+                            }
+                            (Token::ControlChar('-'), value_expr) => {
+                                ValueExpr::Negate((value_expr, acc.1).into()).into_empty_span()
+                            }
+                            (Token::ControlChar('~'), value_expr) => {
+                                ValueExpr::BitNegate((value_expr, acc.1).into()).into_empty_span()
+                            }
+                            (Token::LeftArrow, value_expr) => {
+                                ValueExpr::Receive((value_expr, acc.1).into()).into_empty_span()
+                            }
+                            (_, value_expr) => {
+                                ValueExpr::BoolNegate((value_expr, acc.1).into()).into_empty_span()
+                            }
+                        })
                 })
                 .map_with(|x, e| (x.0, e.span()))
                 .boxed();
@@ -812,7 +1857,37 @@ where
                 .map_with(|x, e| (x, e.span()))
                 .boxed();
 
-            let term = pen.clone().or(atom.clone());
+            let send = scope_res_ident
+                .clone()
+                .rewind()
+                .ignore_then(pen.clone().or(atom.clone()))
+                .then_ignore(just(Token::LeftArrow))
+                .then(value_expr_parser.clone())
+                .map_with(|(channel, value_expr), e| {
+                    (
+                        ValueExpr::Send(Box::new(channel), Box::new(value_expr)),
+                        e.span(),
+                    )
+                })
+                .boxed();
+
+            // x as T binds tighter than any binary operator, -x as Float is (-x) as Float
+            let term = pen
+                .clone()
+                .or(atom.clone())
+                .then(
+                    just(Token::As)
+                        .ignore_then(type_expression_parser())
+                        .repeated()
+                        .collect::<Vec<_>>(),
+                )
+                .map(|(init, casts)| {
+                    casts.into_iter().fold(init, |acc, type_expr| {
+                        let span = acc.1.union(type_expr.1);
+                        (ValueExpr::Cast(Box::new(acc), type_expr), span)
+                    })
+                })
+                .boxed();
 
             let prod = term
                 .clone()
@@ -861,7 +1936,120 @@ where
                 })
                 .boxed();
 
-            let relation = add
+            // >> is lexed as two > tokens, they're only a shift if nothing is in between
+            let shift_right = just(Token::ControlChar('>'))
+                .map_with(|_, e| e.span())
+                .then(just(Token::ControlChar('>')).map_with(|_, e| e.span()))
+                .try_map(|(first, second): (SS, SS), span| {
+                    if first.end == second.start {
+                        Ok(Token::ControlChar('>'))
+                    } else {
+                        Err(Rich::custom(
+                            span,
+                            "expected >> without whitespace in between",
+                        ))
+                    }
+                });
+
+            let shift = add
+                .clone()
+                .then(
+                    choice((just(Token::ShiftLeft), shift_right))
+                        .then(add.clone())
+                        .repeated()
+                        .collect::<Vec<_>>(),
+                )
+                .map(|(init, additional)| {
+                    additional.into_iter().fold(init, |acc, (op, x)| {
+                        let span = acc.1.union(x.1);
+                        let new_expr = match op {
+                            Token::ShiftLeft => ValueExpr::ShiftLeft(Box::new(acc), Box::new(x)),
+                            _ => ValueExpr::ShiftRight(Box::new(acc), Box::new(x)),
+                        };
+                        (new_expr, span)
+                    })
+                })
+                .boxed();
+
+            let bit_and = shift
+                .clone()
+                .then(
+                    just(Token::ControlChar('&'))
+                        .ignore_then(shift.clone())
+                        .repeated()
+                        .collect::<Vec<_>>(),
+                )
+                .map(|(init, additional)| {
+                    additional.into_iter().fold(init, |acc, x| {
+                        let span = acc.1.union(x.1);
+                        (ValueExpr::BitAnd(Box::new(acc), Box::new(x)), span)
+                    })
+                })
+                .boxed();
+
+            let bit_xor = bit_and
+                .clone()
+                .then(
+                    just(Token::ControlChar('^'))
+                        .ignore_then(bit_and.clone())
+                        .repeated()
+                        .collect::<Vec<_>>(),
+                )
+                .map(|(init, additional)| {
+                    additional.into_iter().fold(init, |acc, x| {
+                        let span = acc.1.union(x.1);
+                        (ValueExpr::BitXor(Box::new(acc), Box::new(x)), span)
+                    })
+                })
+                .boxed();
+
+            let bit_or = bit_xor
+                .clone()
+                .then(
+                    just(Token::ControlChar('|'))
+                        .ignore_then(bit_xor.clone())
+                        .repeated()
+                        .collect::<Vec<_>>(),
+                )
+                .map(|(init, additional)| {
+                    additional.into_iter().fold(init, |acc, x| {
+                        let span = acc.1.union(x.1);
+                        (ValueExpr::BitOr(Box::new(acc), Box::new(x)), span)
+                    })
+                })
+                .boxed();
+
+            // x is { name: String } checks at runtime whether x has the shape of the type, it's a
+            // match with a single arm that gives true
+            let is_test = bit_or
+                .clone()
+                .then(
+                    just(Token::Is)
+                        .ignore_then(type_expression_parser())
+                        .or_not(),
+                )
+                .map_with(|(value_expr, type_case), e| {
+                    let Some(type_case) = type_case else {
+                        return value_expr;
+                    };
+                    let span: SS = e.span();
+                    let arm = |type_case, value| MatchArm {
+                        type_case,
+                        identifier_binding: None,
+                        condition: None,
+                        value_expr: (ValueExpr::Bool(value), span),
+                        untyped_fields: Vec::new(),
+                    };
+                    let test = ValueExpr::Match {
+                        value_expr: Box::new(value_expr),
+                        arms: vec![arm(type_case, true)],
+                        else_arm: Some(Box::new(arm((TypeExpr::Any, span), false))),
+                    };
+                    (test, span)
+                })
+                .boxed();
+
+            let relation = is_test
                 .clone()
                 .then(
                     choice((
@@ -870,7 +2058,7 @@ where
                         just(Token::ControlChar('>')),
                         just(Token::GreaterThanOrEquals),
                     ))
-                    .then(add.clone())
+                    .then(is_test.clone())
                     .repeated()
                     .collect::<Vec<_>>(),
                 )
@@ -921,7 +2109,7 @@ where
             let and = equality
                 .clone()
                 .then(
-                    just(Token::And)
+                    choice((just(Token::And), just(Token::AndAnd)))
                         .then(equality.clone())
                         .repeated()
                         .collect::<Vec<_>>(),
@@ -937,7 +2125,7 @@ where
             let or = and
                 .clone()
                 .then(
-                    just(Token::Or)
+                    choice((just(Token::Or), just(Token::OrOr)))
                         .then(and.clone())
                         .repeated()
                         .collect::<Vec<_>>(),
@@ -950,19 +2138,197 @@ where
                 })
                 .boxed();
 
-            let inline_go = select_ref! { Token::InlineGo(x) => x.to_owned() }
+            // a ?? b ?? c => a ?? (b ?? c)
+            let coalesce = or
+                .clone()
+                .then(
+                    just(Token::QuestionQuestion)
+                        .ignore_then(or.clone())
+                        .repeated()
+                        .collect::<Vec<_>>(),
+                )
+                .map(|(init, mut additional)| {
+                    let Some(last) = additional.pop() else {
+                        return init;
+                    };
+                    let rhs = additional.into_iter().rev().fold(last, |acc, x| {
+                        let span = x.1.union(acc.1);
+                        (ValueExpr::Coalesce(Box::new(x), Box::new(acc)), span)
+                    });
+                    let span = init.1.union(rhs.1);
+                    (ValueExpr::Coalesce(Box::new(init), Box::new(rhs)), span)
+                })
+                .boxed();
+
+            // 0..10 and 0..=10, ranges don't chain
+            let range = coalesce
+                .clone()
+                .then(
+                    choice((
+                        just(Token::DotDot).to(false),
+                        just(Token::DotDotEquals).to(true),
+                    ))
+                    .then(coalesce.clone())
+                    .or_not(),
+                )
+                .map(|(start, end)| match end {
+                    Some((inclusive, end)) => {
+                        let span = start.1.union(end.1);
+                        let range = ValueExpr::Range {
+                            start: Box::new(start),
+                            end: Box::new(end),
+                            inclusive,
+                        };
+                        (range, span)
+                    }
+                    None => start,
+                })
+                .boxed();
+
+            // a |> f |> g(x) => g(f(a), x)
+            let pipeline = range
+                .clone()
+                .then(
+                    just(Token::Pipe)
+                        .ignore_then(range.clone())
+                        .repeated()
+                        .collect::<Vec<_>>(),
+                )
+                .validate(|(init, stages), _, emitter| {
+                    stages.into_iter().fold(init, |acc, (stage, stage_span)| {
+                        let span = acc.1.union(stage_span);
+                        let call = match stage {
+                            ValueExpr::FunctionCall {
+                                target,
+                                mut params,
+                                type_params,
+                            } => {
+                                params.insert(0, acc);
+                                ValueExpr::FunctionCall {
+                                    target,
+                                    params,
+                                    type_params,
+                                }
+                            }
+                            target @ (ValueExpr::RawVariable(..)
+                            | ValueExpr::FieldAccess { .. }
+                            | ValueExpr::ArrayAccess(..)
+                            | ValueExpr::Lambda(..)) => ValueExpr::FunctionCall {
+                                target: (target, stage_span).into(),
+                                params: vec![acc],
+                                type_params: None,
+                            },
+                            _ => {
+                                emitter.emit(Rich::custom(
+                                    stage_span,
+                                    "can only pipe into functions and function calls",
+                                ));
+                                acc.0
+                            }
+                        };
+                        (call, span)
+                    })
+                })
+                .boxed();
+
+            let inline_go = select_ref! { Token::InlineGo(x) => x.to_string() }
                 .map(ValueExpr::InlineGo)
                 .map_with(|x, e| (x, e.span()))
                 .boxed();
 
-            choice((inline_go, assignment, or, declaration, pen, atom))
-                .labelled("expression")
-                .boxed()
+            choice((
+                inline_go,
+                assignment,
+                send,
+                pipeline,
+                declaration,
+                pen,
+                atom,
+            ))
+            .labelled("expression")
+            .boxed()
         },
     )
 }
 
-#[allow(dead_code)]
+// chan<T>() is go's make(chan T) and chan<T>(n) is make(chan T, n)
+fn make_chan<'src>(
+    mut type_params: Vec<Spanned<TypeExpr>>,
+    mut params: Vec<Spanned<ValueExpr>>,
+    span: SS,
+) -> Result<ValueExpr, Rich<'src, Token, SS>> {
+    if type_params.len() != 1 || params.len() > 1 {
+        return Err(Rich::custom(
+            span,
+            "wrong channel construction, a channel is created with chan<T>() or \
+             chan<T>(capacity)",
+        ));
+    }
+
+    Ok(ValueExpr::MakeChan {
+        element_type: type_params.remove(0),
+        capacity: params.pop().map(Box::new),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DestructuringName {
+    Name(String),
+    Tuple(Vec<DestructuringName>),
+}
+
+// let (a, b) = x; becomes let destructure_n = x; let a = destructure_n.0; let b = destructure_n.1;
+// a nested tuple is destructured from its own variable destructure_n_i the same way
+fn destructure(
+    tuple_name: String,
+    names: Vec<DestructuringName>,
+    initializer: Spanned<ValueExpr>,
+    span: SS,
+) -> Vec<Spanned<ValueExpr>> {
+    let declaration = |name: String, initializer: Spanned<ValueExpr>| {
+        (
+            ValueExpr::VarDecl(Box::new((
+                Declaration {
+                    name,
+                    type_expr: None,
+                    initializer,
+                },
+                span,
+            ))),
+            span,
+        )
+    };
+
+    let mut exprs = vec![declaration(tuple_name.clone(), initializer)];
+    for (i, name) in names.into_iter().enumerate() {
+        let element = (
+            ValueExpr::FieldAccess {
+                target_obj: Box::new((
+                    ValueExpr::RawVariable(false, vec![tuple_name.clone()]),
+                    span,
+                )),
+                field_name: i.to_string(),
+            },
+            span,
+        );
+
+        match name {
+            DestructuringName::Name(name) if name == "_" => {}
+            DestructuringName::Name(name) => exprs.push(declaration(name, element)),
+            DestructuringName::Tuple(names) => {
+                exprs.extend(destructure(
+                    format!("{tuple_name}_{i}"),
+                    names,
+                    element,
+                    span,
+                ));
+            }
+        }
+    }
+
+    exprs
+}
+
 fn empty_tuple() -> ValueExpr {
     ValueExpr::Tuple(Vec::new())
 }
@@ -979,13 +2345,14 @@ pub fn empty_range() -> SS {
         start: 0,
         end: 1,
         context: Context {
-            file_name: "TODO: Empty Range",
-            file_contents: "TODO: PLEASE DONT".to_string().leak(),
+            file_id: FileId::EMPTY,
         },
     }
 }
 
 pub fn source_file_into_empty_range(v: &mut SourceFile) {
+    // like the spans, the paths depend on where the test files are
+    v.module_paths.clear();
     for x in &mut v.function_definitions {
         value_expr_into_empty_range(&mut x.value_expr);
         x.return_type.as_mut().map(type_expr_into_empty_range);
@@ -998,6 +2365,20 @@ pub fn source_file_into_empty_range(v: &mut SourceFile) {
     for x in &mut v.type_definitions {
         type_expr_into_empty_range(&mut x.type_expression);
     }
+    for x in &mut v.const_definitions {
+        x.span = empty_range();
+        value_expr_into_empty_range(&mut x.value_expr);
+        if let Some(type_expr) = &mut x.type_expr {
+            type_expr_into_empty_range(type_expr);
+        }
+    }
+    for x in &mut v.global_variables {
+        x.span = empty_range();
+        value_expr_into_empty_range(&mut x.initializer);
+        if let Some(type_expr) = &mut x.type_expr {
+            type_expr_into_empty_range(type_expr);
+        }
+    }
     for x in &mut v.sub_modules {
         source_file_into_empty_range(&mut x.1);
     }
@@ -1005,6 +2386,25 @@ pub fn source_file_into_empty_range(v: &mut SourceFile) {
     for x in &mut v.tsx_components {
         x.typescript_source.1 = empty_range()
     }
+
+    for x in &mut v.test_cases {
+        value_expr_into_empty_range(&mut x.value_expr);
+    }
+
+    for (cfgs, x) in &mut v.cfg_items {
+        for cfg in cfgs {
+            cfg.1 = empty_range();
+        }
+        source_file_into_empty_range(x);
+    }
+
+    for x in &mut v.private_items {
+        x.span = empty_range();
+    }
+
+    for x in &mut v.re_exports {
+        x.span = empty_range();
+    }
 }
 
 pub fn type_expr_into_empty_range(t: &mut Spanned<TypeExpr>) {
@@ -1015,10 +2415,14 @@ pub fn type_expr_into_empty_range(t: &mut Spanned<TypeExpr>) {
                 type_expr_into_empty_range(&mut f.type_expr);
             }
         }
-        TypeExpr::Array(t) => {
+        TypeExpr::Array(t) | TypeExpr::Chan(t) => {
             t.1 = empty_range();
             type_expr_into_empty_range(t);
         }
+        TypeExpr::Map(key, value) => {
+            type_expr_into_empty_range(key);
+            type_expr_into_empty_range(value);
+        }
         TypeExpr::Tuple(fields) => {
             for f in fields {
                 type_expr_into_empty_range(f);
@@ -1058,6 +2462,14 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
             value_expr_into_empty_range(target);
             value_expr_into_empty_range(idx_expr);
         }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            value_expr_into_empty_range(target);
+            for bound in [start, end].into_iter().flatten() {
+                value_expr_into_empty_range(bound);
+            }
+        }
         ValueExpr::Array(t, elems) => {
             if let Some(t) = t {
                 type_expr_into_empty_range(t);
@@ -1066,6 +2478,15 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
                 value_expr_into_empty_range(elem);
             }
         }
+        ValueExpr::Map(t, entries) => {
+            if let Some(t) = t {
+                type_expr_into_empty_range(t);
+            }
+            for (key, value) in entries {
+                value_expr_into_empty_range(key);
+                value_expr_into_empty_range(value);
+            }
+        }
         ValueExpr::FunctionCall {
             target,
             params,
@@ -1085,7 +2506,18 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
         ValueExpr::Sub(lhs, rhs)
         | ValueExpr::Mod(lhs, rhs)
         | ValueExpr::Div(lhs, rhs)
-        | ValueExpr::Add(lhs, rhs) => {
+        | ValueExpr::Add(lhs, rhs)
+        | ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Send(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        } => {
             value_expr_into_empty_range(lhs);
             value_expr_into_empty_range(rhs);
         }
@@ -1126,7 +2558,9 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
         | ValueExpr::GreaterThan(lhs, rhs)
         | ValueExpr::GreaterThanOrEquals(lhs, rhs)
         | ValueExpr::And(lhs, rhs)
-        | ValueExpr::Or(lhs, rhs) => {
+        | ValueExpr::Or(lhs, rhs)
+        | ValueExpr::Update(lhs, rhs)
+        | ValueExpr::Coalesce(lhs, rhs) => {
             value_expr_into_empty_range(lhs);
             value_expr_into_empty_range(rhs);
         }
@@ -1137,7 +2571,7 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
                 type_expr_into_empty_range(p);
             }
         }
-        ValueExpr::Return(Some(v)) => value_expr_into_empty_range(v),
+        ValueExpr::Return(Some(v)) | ValueExpr::Break(Some(v)) => value_expr_into_empty_range(v),
         ValueExpr::Struct { fields, .. } => {
             for field in fields {
                 value_expr_into_empty_range(&mut field.1);
@@ -1147,7 +2581,12 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
             value_expr_into_empty_range(condition);
             value_expr_into_empty_range(body);
         }
-        ValueExpr::VarDecl(b) => {
+        ValueExpr::For { target, body, .. } => {
+            value_expr_into_empty_range(target);
+            value_expr_into_empty_range(body);
+        }
+        ValueExpr::Loop { body, .. } => value_expr_into_empty_range(body),
+        ValueExpr::VarDecl(b) => {
             b.1 = empty_range();
             value_expr_into_empty_range(&mut b.0.initializer);
             if let Some(type_expr) = &mut b.0.type_expr {
@@ -1159,10 +2598,34 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
             value_expr_into_empty_range(&mut a.0.target);
             value_expr_into_empty_range(&mut a.0.value_expr);
         }
-        ValueExpr::BoolNegate(b) => value_expr_into_empty_range(b),
+        ValueExpr::BoolNegate(b)
+        | ValueExpr::BitNegate(b)
+        | ValueExpr::TypeOf(b)
+        | ValueExpr::Negate(b)
+        | ValueExpr::Receive(b)
+        | ValueExpr::Spread(b)
+        | ValueExpr::Labeled(_, b)
+        | ValueExpr::Propagate(b) => value_expr_into_empty_range(b),
+        ValueExpr::MakeChan {
+            element_type,
+            capacity,
+        } => {
+            type_expr_into_empty_range(element_type);
+            if let Some(capacity) = capacity {
+                value_expr_into_empty_range(capacity);
+            }
+        }
+        ValueExpr::Cast(value_expr, type_expr) => {
+            value_expr_into_empty_range(value_expr);
+            type_expr_into_empty_range(type_expr);
+        }
         ValueExpr::FieldAccess {
             target_obj,
             field_name: _,
+        }
+        | ValueExpr::OptionalFieldAccess {
+            target_obj,
+            field_name: _,
         } => {
             value_expr_into_empty_range(target_obj);
         }
@@ -1183,7 +2646,23 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
             if let Some(arm) = else_arm {
                 type_expr_into_empty_range(&mut arm.type_case);
                 value_expr_into_empty_range(&mut arm.value_expr);
+                if let Some(condition) = &mut arm.condition {
+                    value_expr_into_empty_range(condition);
+                }
+            }
+        }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                value_expr_into_empty_range(&mut arm.case);
+                value_expr_into_empty_range(&mut arm.value_expr);
             }
+            if let Some(else_arm) = else_arm {
+                value_expr_into_empty_range(else_arm);
+            }
+        }
+        ValueExpr::Recover { body, handler, .. } => {
+            value_expr_into_empty_range(body);
+            value_expr_into_empty_range(handler);
         }
         ValueExpr::HtmlString(contents) => {
             for c in contents {
@@ -1192,6 +2671,9 @@ pub fn value_expr_into_empty_range(v: &mut Spanned<ValueExpr>) {
                 }
             }
         }
+        ValueExpr::Comptime(inner) | ValueExpr::Defer(inner) | ValueExpr::Spawn(inner) => {
+            value_expr_into_empty_range(inner)
+        }
         _ => {}
     }
 }
@@ -1203,12 +2685,13 @@ mod tests {
     use crate::parse::{
         Field, Spanned,
         function_parser::LambdaFunctionExpr,
-        lexer::lex_parser,
+        lexer::{end_statements_at_line_breaks, lex_parser},
         make_input,
+        source_map::{self, FileId},
         type_parser::{Duck, TypeExpr},
         value_parser::{
-            Assignment, Declaration, MatchArm, ValHtmlStringContents, empty_duck, empty_range,
-            empty_tuple, type_expr_into_empty_range, value_expr_into_empty_range,
+            Assignment, Declaration, MatchArm, SelectArm, ValHtmlStringContents, empty_duck,
+            empty_range, empty_tuple, type_expr_into_empty_range, value_expr_into_empty_range,
             value_expr_parser,
         },
     };
@@ -1326,6 +2809,49 @@ mod tests {
                     type_params: None,
                 },
             ),
+            (
+                "a |> b",
+                ValueExpr::FunctionCall {
+                    target: var("b"),
+                    params: vec![*var("a")],
+                    type_params: None,
+                },
+            ),
+            (
+                "data |> parse |> validate(1)",
+                ValueExpr::FunctionCall {
+                    target: var("validate"),
+                    params: vec![
+                        ValueExpr::FunctionCall {
+                            target: var("parse"),
+                            params: vec![*var("data")],
+                            type_params: None,
+                        }
+                        .into_empty_span(),
+                        ValueExpr::Int(1).into_empty_span(),
+                    ],
+                    type_params: None,
+                },
+            ),
+            (
+                "1 + 2 |> x.show",
+                ValueExpr::FunctionCall {
+                    target: ValueExpr::FieldAccess {
+                        target_obj: var("x"),
+                        field_name: "show".into(),
+                    }
+                    .into_empty_span()
+                    .into(),
+                    params: vec![
+                        ValueExpr::Add(
+                            ValueExpr::Int(1).into_empty_span().into(),
+                            ValueExpr::Int(2).into_empty_span().into(),
+                        )
+                        .into_empty_span(),
+                    ],
+                    type_params: None,
+                },
+            ),
             (
                 ".Int[]",
                 ValueExpr::Array(Some(TypeExpr::Int.into_empty_span()), vec![]),
@@ -1752,6 +3278,35 @@ mod tests {
                     ),
                 },
             ),
+            (
+                "if x == 1 { 1 } else { 2 }",
+                ValueExpr::If {
+                    condition: ValueExpr::Equals(
+                        var("x"),
+                        ValueExpr::Int(1).into_empty_span().into(),
+                    )
+                    .into_empty_span()
+                    .into(),
+                    then: ValueExpr::Int(1).into_empty_span_and_block().into(),
+                    r#else: Some(ValueExpr::Int(2).into_empty_span_and_block().into()),
+                },
+            ),
+            (
+                "if (true) { 1 } else if (false) { 3 }",
+                ValueExpr::If {
+                    condition: ValueExpr::Bool(true).into_empty_span().into(),
+                    then: ValueExpr::Int(1).into_empty_span_and_block().into(),
+                    r#else: Some(
+                        ValueExpr::If {
+                            condition: ValueExpr::Bool(false).into_empty_span().into(),
+                            then: ValueExpr::Int(3).into_empty_span_and_block().into(),
+                            r#else: None,
+                        }
+                        .into_empty_span()
+                        .into(),
+                    ),
+                },
+            ),
             (
                 "(1,true,2,\"hallo\")",
                 ValueExpr::Tuple(vec![
@@ -1892,6 +3447,35 @@ mod tests {
                     .into_empty_span(),
                 ]),
             ),
+            (
+                "1 + { let a = f(); a + 1 }",
+                ValueExpr::Add(
+                    ValueExpr::Int(1).into_empty_span().into(),
+                    ValueExpr::Block(vec![
+                        ValueExpr::VarDecl(
+                            (
+                                Declaration {
+                                    name: "a".into(),
+                                    initializer: ValueExpr::FunctionCall {
+                                        target: var("f"),
+                                        params: vec![],
+                                        type_params: None,
+                                    }
+                                    .into_empty_span(),
+                                    type_expr: None,
+                                },
+                                empty_range(),
+                            )
+                                .into(),
+                        )
+                        .into_empty_span(),
+                        ValueExpr::Add(var("a"), ValueExpr::Int(1).into_empty_span().into())
+                            .into_empty_span(),
+                    ])
+                    .into_empty_span()
+                    .into(),
+                ),
+            ),
             (
                 "{x();y();}",
                 ValueExpr::Block(vec![
@@ -2023,7 +3607,7 @@ mod tests {
                     .into(),
                     body: ValueExpr::Block(vec![
                         ValueExpr::Int(1).into_empty_span(),
-                        ValueExpr::Break.into_empty_span(),
+                        ValueExpr::Break(None).into_empty_span(),
                         empty_tuple().into_empty_span(),
                     ])
                     .into_empty_span()
@@ -2602,6 +4186,7 @@ mod tests {
                         identifier_binding: Some("i".to_string()),
                         value_expr: *var("i"),
                         condition: None,
+                        untyped_fields: Vec::new(),
                     }],
                     else_arm: None,
                 },
@@ -2618,12 +4203,14 @@ mod tests {
                             identifier_binding: Some("s".to_string()),
                             value_expr: *var("s"),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                         MatchArm {
                             type_case: TypeExpr::Int.into_empty_span(),
                             identifier_binding: Some("i".to_string()),
                             value_expr: *var("i"),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                     ],
                     else_arm: None,
@@ -2641,12 +4228,14 @@ mod tests {
                             identifier_binding: Some("s".to_string()),
                             value_expr: *var("s"),
                             condition: Some(*var("s")),
+                            untyped_fields: Vec::new(),
                         },
                         MatchArm {
                             type_case: TypeExpr::Int.into_empty_span(),
                             identifier_binding: Some("i".to_string()),
                             value_expr: *var("i"),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                     ],
                     else_arm: None,
@@ -2666,12 +4255,14 @@ mod tests {
                             identifier_binding: Some("s".to_string()),
                             value_expr: *var("s"),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                         MatchArm {
                             type_case: TypeExpr::Int.into_empty_span(),
                             identifier_binding: Some("i".to_string()),
                             value_expr: *var("i"),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                         MatchArm {
                             type_case: TypeExpr::RawTypeName(false, vec!["Other".into()], None)
@@ -2682,6 +4273,7 @@ mod tests {
                             ])
                             .into_empty_span(),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                     ],
                     else_arm: None,
@@ -2710,12 +4302,14 @@ mod tests {
                             identifier_binding: Some("s".to_string()),
                             value_expr: *var("s"),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                         MatchArm {
                             type_case: TypeExpr::Int.into_empty_span(),
                             identifier_binding: Some("i".to_string()),
                             value_expr: *var("i"),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                         MatchArm {
                             type_case: TypeExpr::RawTypeName(false, vec!["Other".into()], None)
@@ -2726,6 +4320,7 @@ mod tests {
                             ])
                             .into_empty_span(),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                         MatchArm {
                             type_case: TypeExpr::RawTypeName(false, vec!["Other".into()], None)
@@ -2739,6 +4334,7 @@ mod tests {
                                         identifier_binding: Some("s".to_string()),
                                         value_expr: *var("s"),
                                         condition: None,
+                                        untyped_fields: Vec::new(),
                                     }],
                                     else_arm: None,
                                 }
@@ -2746,6 +4342,7 @@ mod tests {
                             ])
                             .into_empty_span(),
                             condition: None,
+                            untyped_fields: Vec::new(),
                         },
                     ],
                     else_arm: None,
@@ -2754,7 +4351,10 @@ mod tests {
         ];
 
         for (i, (src, expected_tokens)) in test_cases.into_iter().enumerate() {
-            let lex_result = lex_parser("test", "").parse(src).into_result().expect(&src);
+            let lex_result = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(&src);
             dbg!(&lex_result);
             let parse_result =
                 value_expr_parser(make_input).parse(make_input(empty_range(), &lex_result));
@@ -2814,7 +4414,7 @@ mod tests {
         ];
 
         for (input, expected_output) in inputs_and_expected_outputs {
-            let lexer_parse_result = lex_parser("test", "").parse(input);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(input);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -2858,7 +4458,7 @@ mod tests {
 
         for valid_declaration in valid_declarations {
             println!("lexing {valid_declaration}");
-            let lexer_parse_result = lex_parser("test", "").parse(valid_declaration);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(valid_declaration);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -2890,7 +4490,7 @@ mod tests {
         ];
 
         for valid_assignment in valid_assignments {
-            let lexer_parse_result = lex_parser("test", "").parse(valid_assignment);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(valid_assignment);
             assert_eq!(lexer_parse_result.has_errors(), false, "{valid_assignment}");
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -2906,7 +4506,7 @@ mod tests {
         }
     }
 
-    fn int(i: i64) -> Box<Spanned<ValueExpr>> {
+    fn int(i: i128) -> Box<Spanned<ValueExpr>> {
         ValueExpr::Int(i).into_empty_span().into()
     }
 
@@ -3070,7 +4670,7 @@ mod tests {
         ];
 
         for (i, (src, expected_ast)) in test_cases.into_iter().enumerate() {
-            let lex_result = lex_parser("test", "")
+            let lex_result = lex_parser(FileId::EMPTY)
                 .parse(src)
                 .into_result()
                 .unwrap_or_else(|_| panic!("Lexer failed on case {i}: '{src}'"));
@@ -3138,6 +4738,18 @@ mod tests {
                     ValueExpr::Bool(false).into_empty_span().into(),
                 ),
             ),
+            (
+                "true && false || true",
+                or(
+                    and(
+                        ValueExpr::Bool(true).into_empty_span().into(),
+                        ValueExpr::Bool(false).into_empty_span().into(),
+                    )
+                    .into_empty_span()
+                    .into(),
+                    ValueExpr::Bool(true).into_empty_span().into(),
+                ),
+            ),
             (
                 "false or true and true",
                 or(
@@ -3160,7 +4772,131 @@ mod tests {
         ];
 
         for (i, (src, expected_ast)) in test_cases.into_iter().enumerate() {
-            let lex_result = lex_parser("test", "")
+            let lex_result = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .unwrap_or_else(|_| panic!("Lexer failed on case {i}: '{src}'"));
+
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &lex_result));
+            if parse_result.has_errors() {
+                panic!(
+                    "parse failed for {i}: '{src}'. Errors: {:?}",
+                    parse_result.into_errors()
+                );
+            }
+
+            let mut output = parse_result.into_output().unwrap();
+
+            value_expr_into_empty_range(&mut output);
+
+            assert_eq!(output.0, expected_ast, "ast mismatch on {i}: '{src}'");
+        }
+    }
+
+    fn bit_and(lhs: Box<Spanned<ValueExpr>>, rhs: Box<Spanned<ValueExpr>>) -> ValueExpr {
+        ValueExpr::BitAnd(lhs, rhs)
+    }
+
+    fn bit_or(lhs: Box<Spanned<ValueExpr>>, rhs: Box<Spanned<ValueExpr>>) -> ValueExpr {
+        ValueExpr::BitOr(lhs, rhs)
+    }
+
+    fn bit_xor(lhs: Box<Spanned<ValueExpr>>, rhs: Box<Spanned<ValueExpr>>) -> ValueExpr {
+        ValueExpr::BitXor(lhs, rhs)
+    }
+
+    fn shl(lhs: Box<Spanned<ValueExpr>>, rhs: Box<Spanned<ValueExpr>>) -> ValueExpr {
+        ValueExpr::ShiftLeft(lhs, rhs)
+    }
+
+    fn shr(lhs: Box<Spanned<ValueExpr>>, rhs: Box<Spanned<ValueExpr>>) -> ValueExpr {
+        ValueExpr::ShiftRight(lhs, rhs)
+    }
+
+    #[test]
+    fn test_bitwise_expressions() {
+        let test_cases = vec![
+            ("6 & 3", bit_and(int(6), int(3))),
+            ("6 | 3", bit_or(int(6), int(3))),
+            ("6 ^ 3", bit_xor(int(6), int(3))),
+            ("1 << 4", shl(int(1), int(4))),
+            ("16 >> 2", shr(int(16), int(2))),
+            ("~1", ValueExpr::BitNegate(int(1))),
+            (
+                "!~1",
+                ValueExpr::BoolNegate(ValueExpr::BitNegate(int(1)).into_empty_span().into()),
+            ),
+            (
+                "1 + 2 << 3",
+                shl(add(int(1), int(2)).into_empty_span().into(), int(3)),
+            ),
+            (
+                "1 | 2 & 3",
+                bit_or(int(1), bit_and(int(2), int(3)).into_empty_span().into()),
+            ),
+            (
+                "1 ^ 2 | 3",
+                bit_or(bit_xor(int(1), int(2)).into_empty_span().into(), int(3)),
+            ),
+            (
+                "1 & 2 ^ 3",
+                bit_xor(bit_and(int(1), int(2)).into_empty_span().into(), int(3)),
+            ),
+            (
+                "x & 1 == 1",
+                eq(
+                    bit_and(v_var(&["x"]), int(1)).into_empty_span().into(),
+                    int(1),
+                ),
+            ),
+            (
+                "1 < 8 >> 2",
+                lt(int(1), shr(int(8), int(2)).into_empty_span().into()),
+            ),
+            (
+                "8 >> 1 > 2",
+                gt(shr(int(8), int(1)).into_empty_span().into(), int(2)),
+            ),
+            ("typeof(x)", ValueExpr::TypeOf(var("x"))),
+            (
+                "x as Float",
+                ValueExpr::Cast(var("x"), TypeExpr::Float.into_empty_span()),
+            ),
+            (
+                "1 + x as Float",
+                add(
+                    int(1),
+                    ValueExpr::Cast(var("x"), TypeExpr::Float.into_empty_span())
+                        .into_empty_span()
+                        .into(),
+                ),
+            ),
+            (
+                "-x as Int as Char",
+                ValueExpr::Cast(
+                    ValueExpr::Cast(
+                        ValueExpr::Negate(var("x")).into_empty_span().into(),
+                        TypeExpr::Int.into_empty_span(),
+                    )
+                    .into_empty_span()
+                    .into(),
+                    TypeExpr::Char.into_empty_span(),
+                ),
+            ),
+            (
+                "typeof(x) == \"Int\"",
+                eq(
+                    ValueExpr::TypeOf(var("x")).into_empty_span().into(),
+                    ValueExpr::String("Int".into(), true)
+                        .into_empty_span()
+                        .into(),
+                ),
+            ),
+        ];
+
+        for (i, (src, expected_ast)) in test_cases.into_iter().enumerate() {
+            let lex_result = lex_parser(FileId::EMPTY)
                 .parse(src)
                 .into_result()
                 .unwrap_or_else(|_| panic!("Lexer failed on case {i}: '{src}'"));
@@ -3180,5 +4916,1859 @@ mod tests {
 
             assert_eq!(output.0, expected_ast, "ast mismatch on {i}: '{src}'");
         }
+
+        for src in ["8 > > 1", "1 <<", "~", "1 & & 2"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{src}");
+        }
+    }
+
+    #[test]
+    fn test_negation() {
+        let test_cases = vec![
+            ("-5", ValueExpr::Int(-5)),
+            ("-9223372036854775808", ValueExpr::Int(i64::MIN.into())),
+            ("-1.5", ValueExpr::Float(-1.5)),
+            ("--1", ValueExpr::Int(1)),
+            ("-x", ValueExpr::Negate(v_var(&["x"]))),
+            ("x-1", sub(v_var(&["x"]), int(1))),
+            ("2 - -3", sub(int(2), int(-3))),
+            (
+                "-(x + 1)",
+                ValueExpr::Negate(add(v_var(&["x"]), int(1)).into_empty_span().into()),
+            ),
+            (
+                "!-x",
+                ValueExpr::BoolNegate(ValueExpr::Negate(v_var(&["x"])).into_empty_span().into()),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_range_expressions() {
+        let range = |start, end, inclusive| ValueExpr::Range {
+            start,
+            end,
+            inclusive,
+        };
+
+        let test_cases = vec![
+            ("0..10", range(int(0), int(10), false)),
+            ("0..=10", range(int(0), int(10), true)),
+            ("a..b", range(v_var(&["a"]), v_var(&["b"]), false)),
+            (
+                "1 + 1..n * 2",
+                range(
+                    add(int(1), int(1)).into_empty_span().into(),
+                    mul(v_var(&["n"]), int(2)).into_empty_span().into(),
+                    false,
+                ),
+            ),
+            (
+                "0..3 |> f",
+                ValueExpr::FunctionCall {
+                    target: v_var(&["f"]),
+                    params: vec![range(int(0), int(3), false).into_empty_span()],
+                    type_params: None,
+                },
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for src in ["0..1..2", "0..", "..=1"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{src}");
+        }
+    }
+
+    #[test]
+    fn test_slices() {
+        let slice = |target, start, end, inclusive| ValueExpr::Slice {
+            target,
+            start,
+            end,
+            inclusive,
+        };
+
+        let test_cases = vec![
+            (
+                "xs[1..n]",
+                slice(v_var(&["xs"]), Some(int(1)), Some(v_var(&["n"])), false),
+            ),
+            (
+                "xs[1..=3]",
+                slice(v_var(&["xs"]), Some(int(1)), Some(int(3)), true),
+            ),
+            ("s[..5]", slice(v_var(&["s"]), None, Some(int(5)), false)),
+            ("s[2..]", slice(v_var(&["s"]), Some(int(2)), None, false)),
+            ("s[..]", slice(v_var(&["s"]), None, None, false)),
+            (
+                "xs[i + 1..][0]",
+                ValueExpr::ArrayAccess(
+                    slice(
+                        v_var(&["xs"]),
+                        Some(add(v_var(&["i"]), int(1)).into_empty_span().into()),
+                        None,
+                        false,
+                    )
+                    .into_empty_span()
+                    .into(),
+                    int(0),
+                ),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for src in ["xs[1..=]", "xs[..=]", "xs[1..2..3]"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{src}");
+        }
+    }
+
+    #[test]
+    fn test_for_in() {
+        let call_f = |arg| ValueExpr::FunctionCall {
+            target: v_var(&["f"]),
+            params: vec![*arg],
+            type_params: None,
+        };
+        let for_in = |ident: &str, target, body| ValueExpr::For {
+            ident: ident.to_string(),
+            target,
+            body: ValueExpr::Block(body).into_empty_span().into(),
+        };
+
+        let test_cases = vec![
+            (
+                "for x in xs { f(x) }",
+                for_in(
+                    "x",
+                    v_var(&["xs"]),
+                    vec![call_f(v_var(&["x"])).into_empty_span()],
+                ),
+            ),
+            (
+                "for i in 0..n { f(i) }",
+                for_in(
+                    "i",
+                    ValueExpr::Range {
+                        start: int(0),
+                        end: v_var(&["n"]),
+                        inclusive: false,
+                    }
+                    .into_empty_span()
+                    .into(),
+                    vec![call_f(v_var(&["i"])).into_empty_span()],
+                ),
+            ),
+            ("for x in (xs) {}", for_in("x", v_var(&["xs"]), vec![])),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for src in [
+            "for in xs { f(x) }",
+            "for x xs { f(x) }",
+            "for x in { f(x) }",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{src}");
+        }
+    }
+
+    #[test]
+    fn test_loop() {
+        let r#loop = |body| ValueExpr::Loop {
+            body: ValueExpr::Block(body).into_empty_span().into(),
+            break_type: None,
+        };
+        let labeled = |label: &str, value_expr: ValueExpr| {
+            ValueExpr::Labeled(label.to_string(), value_expr.into_empty_span().into())
+        };
+
+        let test_cases = vec![
+            ("loop {}", r#loop(vec![])),
+            (
+                "loop { break; }",
+                r#loop(vec![
+                    ValueExpr::Break(None).into_empty_span(),
+                    empty_tuple().into_empty_span(),
+                ]),
+            ),
+            (
+                "loop { break x + 1 }",
+                r#loop(vec![
+                    ValueExpr::Break(Some(
+                        ValueExpr::Add(v_var(&["x"]), int(1))
+                            .into_empty_span()
+                            .into(),
+                    ))
+                    .into_empty_span(),
+                ]),
+            ),
+            (
+                "@outer loop { break @outer; }",
+                labeled(
+                    "outer",
+                    r#loop(vec![
+                        labeled("outer", ValueExpr::Break(None)).into_empty_span(),
+                        empty_tuple().into_empty_span(),
+                    ]),
+                ),
+            ),
+            (
+                "@outer loop { loop { break @outer 1 } }",
+                labeled(
+                    "outer",
+                    r#loop(vec![
+                        r#loop(vec![
+                            labeled("outer", ValueExpr::Break(Some(int(1)))).into_empty_span(),
+                        ])
+                        .into_empty_span(),
+                    ]),
+                ),
+            ),
+            (
+                "@rows while x { continue @rows; }",
+                labeled(
+                    "rows",
+                    ValueExpr::While {
+                        condition: v_var(&["x"]),
+                        body: ValueExpr::Block(vec![
+                            labeled("rows", ValueExpr::Continue).into_empty_span(),
+                            empty_tuple().into_empty_span(),
+                        ])
+                        .into_empty_span()
+                        .into(),
+                    },
+                ),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in ["@outer { break @outer; }", "break @", "@outer x"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                value_expr_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_defer() {
+        let test_cases = vec![
+            (
+                "defer file.close()",
+                ValueExpr::FunctionCall {
+                    target: ValueExpr::FieldAccess {
+                        target_obj: v_var(&["file"]),
+                        field_name: "close".to_string(),
+                    }
+                    .into_empty_span()
+                    .into(),
+                    params: vec![],
+                    type_params: None,
+                },
+            ),
+            (
+                "defer log(x + 1)",
+                ValueExpr::FunctionCall {
+                    target: v_var(&["log"]),
+                    params: vec![ValueExpr::Add(v_var(&["x"]), int(1)).into_empty_span()],
+                    type_params: None,
+                },
+            ),
+        ];
+
+        for (src, expected_call) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(
+                output.0,
+                ValueExpr::Defer(expected_call.into_empty_span().into()),
+                "{src}"
+            );
+        }
+
+        for invalid in ["defer", "defer x", "defer 1 + 2", "defer { f() }"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_spawn() {
+        let test_cases = vec![
+            (
+                "go worker(jobs, 2)",
+                ValueExpr::Spawn(
+                    ValueExpr::FunctionCall {
+                        target: v_var(&["worker"]),
+                        params: vec![*v_var(&["jobs"]), *int(2)],
+                        type_params: None,
+                    }
+                    .into_empty_span()
+                    .into(),
+                ),
+            ),
+            // go with a block after it stays inline go
+            (
+                "go { worker() }",
+                ValueExpr::InlineGo(" worker() ".to_string()),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in ["go", "go worker", "go 1 + 2"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_channels() {
+        let make_chan = |capacity: Option<Box<Spanned<ValueExpr>>>| ValueExpr::MakeChan {
+            element_type: TypeExpr::Int.into_empty_span(),
+            capacity,
+        };
+
+        let test_cases = vec![
+            ("chan<Int>()", make_chan(None)),
+            ("chan<Int>(16)", make_chan(Some(int(16)))),
+            ("ch <- 1", ValueExpr::Send(v_var(&["ch"]), int(1))),
+            (
+                "results <- x + 1",
+                ValueExpr::Send(
+                    v_var(&["results"]),
+                    add(v_var(&["x"]), int(1)).into_empty_span().into(),
+                ),
+            ),
+            ("<-ch", ValueExpr::Receive(v_var(&["ch"]))),
+            (
+                "<-workers.done",
+                ValueExpr::Receive(
+                    ValueExpr::FieldAccess {
+                        target_obj: v_var(&["workers"]),
+                        field_name: "done".to_string(),
+                    }
+                    .into_empty_span()
+                    .into(),
+                ),
+            ),
+            (
+                "out <- <-input",
+                ValueExpr::Send(
+                    v_var(&["out"]),
+                    ValueExpr::Receive(v_var(&["input"]))
+                        .into_empty_span()
+                        .into(),
+                ),
+            ),
+            // with a space it's still a comparison with a negative number
+            (
+                "a < -1",
+                ValueExpr::LessThan(v_var(&["a"]), ValueExpr::Int(-1).into_empty_span().into()),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in ["ch <-", "<-", "chan<>()"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_optionals() {
+        let optional_field = |target_obj: Box<Spanned<ValueExpr>>, field_name: &str| {
+            ValueExpr::OptionalFieldAccess {
+                target_obj,
+                field_name: field_name.to_string(),
+            }
+        };
+
+        let test_cases = vec![
+            ("user?.name", optional_field(v_var(&["user"]), "name")),
+            (
+                "user?.address?.city",
+                optional_field(
+                    optional_field(v_var(&["user"]), "address")
+                        .into_empty_span()
+                        .into(),
+                    "city",
+                ),
+            ),
+            (
+                "user?.greet(1)",
+                ValueExpr::FunctionCall {
+                    target: optional_field(v_var(&["user"]), "greet")
+                        .into_empty_span()
+                        .into(),
+                    params: vec![*int(1)],
+                    type_params: None,
+                },
+            ),
+            ("x ?? 0", ValueExpr::Coalesce(v_var(&["x"]), int(0))),
+            (
+                "a ?? b ?? c",
+                ValueExpr::Coalesce(
+                    v_var(&["a"]),
+                    ValueExpr::Coalesce(v_var(&["b"]), v_var(&["c"]))
+                        .into_empty_span()
+                        .into(),
+                ),
+            ),
+            // ?? binds weaker than arithmetic, x ?? 1 + 2 => x ?? (1 + 2)
+            (
+                "x ?? 1 + 2",
+                ValueExpr::Coalesce(v_var(&["x"]), add(int(1), int(2)).into_empty_span().into()),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in ["x ??", "x?.", "x?.1"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_propagate() {
+        let propagate =
+            |value_expr: ValueExpr| ValueExpr::Propagate(value_expr.into_empty_span().into());
+        let call = |name: &str, params: Vec<Spanned<ValueExpr>>| ValueExpr::FunctionCall {
+            target: v_var(&[name]),
+            params,
+            type_params: None,
+        };
+
+        let test_cases = vec![
+            ("x?", ValueExpr::Propagate(v_var(&["x"]))),
+            ("parse(s)?", propagate(call("parse", vec![*v_var(&["s"])]))),
+            // the postfix binds tighter than the prefixes, -x? => -(x?)
+            (
+                "-x?",
+                ValueExpr::Negate(propagate(*v_var(&["x"])).into_empty_span().into()),
+            ),
+            (
+                "a? + b?",
+                add(
+                    propagate(*v_var(&["a"])).into_empty_span().into(),
+                    propagate(*v_var(&["b"])).into_empty_span().into(),
+                ),
+            ),
+            (
+                "(x?).len()",
+                ValueExpr::FunctionCall {
+                    target: ValueExpr::FieldAccess {
+                        target_obj: propagate(*v_var(&["x"])).into_empty_span().into(),
+                        field_name: "len".to_string(),
+                    }
+                    .into_empty_span()
+                    .into(),
+                    params: vec![],
+                    type_params: None,
+                },
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in ["?", "?x"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_spread() {
+        let spread = |name: &str| ValueExpr::Spread(v_var(&[name])).into_empty_span();
+        let call = |params: Vec<Spanned<ValueExpr>>| ValueExpr::FunctionCall {
+            target: v_var(&["sum"]),
+            params,
+            type_params: None,
+        };
+
+        let test_cases = vec![
+            ("sum(...xs)", call(vec![spread("xs")])),
+            ("sum(1, ...xs,)", call(vec![*int(1), spread("xs")])),
+            (
+                "sum(...[1, 2])",
+                call(vec![
+                    ValueExpr::Spread(
+                        ValueExpr::Array(None, vec![*int(1), *int(2)])
+                            .into_empty_span()
+                            .into(),
+                    )
+                    .into_empty_span(),
+                ]),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in ["...xs", "sum(...)", "x = ...xs"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_update() {
+        let update = |literal: ValueExpr, base: &str| {
+            ValueExpr::Update(literal.into_empty_span().into(), v_var(&[base]))
+        };
+
+        let test_cases = vec![
+            (
+                "S { x: 1, ..base }",
+                update(
+                    ValueExpr::Struct {
+                        name: "S".to_string(),
+                        fields: vec![("x".to_string(), *int(1))],
+                        type_params: None,
+                    },
+                    "base",
+                ),
+            ),
+            (
+                "S { ..base }",
+                update(
+                    ValueExpr::Struct {
+                        name: "S".to_string(),
+                        fields: vec![],
+                        type_params: None,
+                    },
+                    "base",
+                ),
+            ),
+            (
+                "{ ..a, extra: 2, b: 1, }",
+                update(
+                    ValueExpr::Duck(vec![
+                        ("b".to_string(), *int(1)),
+                        ("extra".to_string(), *int(2)),
+                    ]),
+                    "a",
+                ),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in ["S { ..a, ..b }", "{ ..a ..b }", "S { .. }"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_field_shorthand() {
+        let field = |name: &str| (name.to_string(), *v_var(&[name]));
+
+        let test_cases = vec![
+            ("{ x, y }", ValueExpr::Duck(vec![field("x"), field("y")])),
+            (
+                "{ y, x: 1, }",
+                ValueExpr::Duck(vec![("x".to_string(), *int(1)), field("y")]),
+            ),
+            (
+                "Point { x, y }",
+                ValueExpr::Struct {
+                    name: "Point".to_string(),
+                    fields: vec![field("x"), field("y")],
+                    type_params: None,
+                },
+            ),
+            (
+                "Point { x, ..origin }",
+                ValueExpr::Update(
+                    ValueExpr::Struct {
+                        name: "Point".to_string(),
+                        fields: vec![field("x")],
+                        type_params: None,
+                    }
+                    .into_empty_span()
+                    .into(),
+                    v_var(&["origin"]),
+                ),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        // a single name in braces stays a block
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("{ x }")
+            .into_result()
+            .unwrap();
+        let output = value_expr_parser(make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+        assert!(!matches!(output.0, ValueExpr::Duck(..)));
+    }
+
+    #[test]
+    fn test_recover() {
+        let block = |exprs: Vec<Spanned<ValueExpr>>| ValueExpr::Block(exprs).into_empty_span();
+        let call = |name: &str, params: Vec<Spanned<ValueExpr>>| {
+            ValueExpr::FunctionCall {
+                target: v_var(&[name]),
+                params,
+                type_params: None,
+            }
+            .into_empty_span()
+        };
+
+        let test_cases = vec![
+            (
+                "recover { risky() } catch message { log(message) }",
+                ValueExpr::Recover {
+                    body: block(vec![call("risky", vec![])]).into(),
+                    binding: "message".to_string(),
+                    handler: block(vec![call("log", vec![*v_var(&["message"])])]).into(),
+                },
+            ),
+            (
+                "recover {} catch e {}",
+                ValueExpr::Recover {
+                    body: block(vec![]).into(),
+                    binding: "e".to_string(),
+                    handler: block(vec![]).into(),
+                },
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in [
+            "recover { risky() }",
+            "recover { risky() } catch { log() }",
+            "recover risky() catch e {}",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let test_cases = [
+            ("f(1, 2,)", "f(1, 2)"),
+            ("f<Int, String,>(1)", "f<Int, String>(1)"),
+            ("|a, b,| a + b", "|a, b| a + b"),
+            (
+                "fn(a: Int, b: Int,) -> Int { a + b }",
+                "fn(a: Int, b: Int) -> Int { a + b }",
+            ),
+            ("{ x: 1, y: 2, }", "{ x: 1, y: 2 }"),
+            ("[1, 2,]", "[1, 2]"),
+            ("(1, 2,)", "(1, 2)"),
+            (
+                "match (x) { Int => 1, String => 2, }",
+                "match (x) { Int => 1, String => 2 }",
+            ),
+            (
+                "match (x) { Int => 1, else => 2, }",
+                "match (x) { Int => 1, else => 2 }",
+            ),
+            (
+                "select { <-done => {}, else => {}, }",
+                "select { <-done => {}, else => {} }",
+            ),
+        ];
+
+        let parse = |src: &str| {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+            value_expr_into_empty_range(&mut output);
+            output.0
+        };
+
+        for (with_comma, without_comma) in test_cases {
+            assert_eq!(parse(with_comma), parse(without_comma), "{with_comma}");
+        }
+    }
+
+    #[test]
+    fn test_optional_semicolons() {
+        let parse = |name: &str, src: &str| {
+            let file_id = source_map::add_test_file(name, src);
+            let tokens = lex_parser(file_id).parse(src).into_result().expect(src);
+            let (tokens, _) = end_statements_at_line_breaks(tokens, src);
+            value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_output_errors()
+        };
+
+        let (with_semicolons, errors) = parse(
+            "with_semicolons",
+            "{ let x = 1; let y = x + 1; print(y); y }",
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        let (mut with_semicolons, mut without_semicolons) = (
+            with_semicolons.unwrap(),
+            parse(
+                "without_semicolons",
+                "{\n    let x = 1\n    let y = x + 1\n    print(y)\n    y\n}",
+            )
+            .0
+            .unwrap(),
+        );
+        value_expr_into_empty_range(&mut with_semicolons);
+        value_expr_into_empty_range(&mut without_semicolons);
+        assert_eq!(with_semicolons.0, without_semicolons.0);
+
+        // on the same line the ; is still needed
+        let (_, errors) = parse("same_line", "{ let x = 1 let y = 2; y }");
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(errors[0].reason().to_string().contains("missing ;"));
+
+        // a line starting with -, ( or [ doesn't continue the line before, except in parens
+        for (src, expected) in [
+            ("{\n    let x = a\n    -1\n}", "{ let x = a; -1 }"),
+            ("{\n    f(x)\n    (a, b)\n}", "{ f(x); (a, b) }"),
+            ("{\n    f(x)\n    [a, b]\n}", "{ f(x); [a, b] }"),
+            (
+                "{\n    let x = (a\n    -1)\n    x\n}",
+                "{ let x = (a - 1); x }",
+            ),
+            ("{\n    f(x,\n    (a, b))\n}", "{ f(x, (a, b)) }"),
+            // a line that starts with . or a binary operator continues the line before
+            (
+                "{\n    let x = a\n        + 1\n    x\n}",
+                "{ let x = a + 1; x }",
+            ),
+            (
+                "{\n    let x = a\n        * b\n    x\n}",
+                "{ let x = a * b; x }",
+            ),
+            ("{\n    xs\n        .len()\n}", "{ xs.len() }"),
+            ("{\n    a\n        == b\n}", "{ a == b }"),
+        ] {
+            let (mut parsed, errors) = parse("line_breaks", src);
+            assert!(errors.is_empty(), "{src}: {errors:?}");
+            let (mut expected_expr, errors) = parse("line_breaks_expected", expected);
+            assert!(errors.is_empty(), "{expected}: {errors:?}");
+            let (parsed, expected_expr) =
+                (parsed.as_mut().unwrap(), expected_expr.as_mut().unwrap());
+            value_expr_into_empty_range(parsed);
+            value_expr_into_empty_range(expected_expr);
+            assert_eq!(parsed.0, expected_expr.0, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_statement_starts_are_returned() {
+        let statement_starts = |src: &str| {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let (_, statement_starts) = end_statements_at_line_breaks(tokens, src);
+            statement_starts
+                .into_iter()
+                .map(|(token, span)| (token, span.start))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            statement_starts("{\n    let x = a\n    -1\n}"),
+            vec![(Token::ControlChar('-'), 20)]
+        );
+        assert_eq!(
+            statement_starts("{\n    f(x)\n    (a, b)\n    <- c\n}"),
+            vec![(Token::ControlChar('('), 15), (Token::LeftArrow, 26)]
+        );
+
+        // a ; says that a new statement is meant, continued lines and lines in parens start none
+        for src in [
+            "{\n    let x = a;\n    -1\n}",
+            "{\n    f(x);\n    (a, b)\n}",
+            "{\n    let x = a\n        + 1\n}",
+            "{\n    xs\n        .len()\n}",
+            "{\n    let x = (a\n    -1)\n}",
+        ] {
+            assert_eq!(statement_starts(src), vec![], "{src}");
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let block = |exprs: Vec<Spanned<ValueExpr>>| ValueExpr::Block(exprs).into_empty_span();
+        let receive = |channel: &str| ValueExpr::Receive(v_var(&[channel])).into_empty_span();
+
+        let test_cases = vec![
+            (
+                "select { let msg = <-messages => { msg; }, <-done => {} }",
+                ValueExpr::Select {
+                    arms: vec![
+                        SelectArm {
+                            binding: Some("msg".to_string()),
+                            case: receive("messages"),
+                            value_expr: block(vec![*v_var(&["msg"])]),
+                        },
+                        SelectArm {
+                            binding: None,
+                            case: receive("done"),
+                            value_expr: block(vec![]),
+                        },
+                    ],
+                    else_arm: None,
+                },
+            ),
+            (
+                "select { results <- 1 => {}, else => {} }",
+                ValueExpr::Select {
+                    arms: vec![SelectArm {
+                        binding: None,
+                        case: ValueExpr::Send(v_var(&["results"]), int(1)).into_empty_span(),
+                        value_expr: block(vec![]),
+                    }],
+                    else_arm: Some(block(vec![]).into()),
+                },
+            ),
+            (
+                "select { else => {} }",
+                ValueExpr::Select {
+                    arms: vec![],
+                    else_arm: Some(block(vec![]).into()),
+                },
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in [
+            "select { x => {} }",
+            "select { let x = ch <- 1 => {} }",
+            "select { <-ch }",
+            "select { else => {}, <-ch => {} }",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_make_chan_arity() {
+        for src in ["chan<Int>(1, 2)", "chan<Int, String>()"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let errors = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_errors();
+            assert_eq!(errors.len(), 1, "{src}");
+            assert!(
+                errors[0]
+                    .reason()
+                    .to_string()
+                    .contains("wrong channel construction"),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_pipeline_errors() {
+        for src in ["a |> 1", "a |> \"x\"", "a |> b |> (1, 2)", "a |>"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let parse_result =
+                value_expr_parser(make_input).parse(make_input(empty_range(), &tokens));
+            assert!(parse_result.has_errors(), "{src}");
+        }
+    }
+
+    #[test]
+    fn test_duck_pattern_match() {
+        let test_cases = vec![
+            (
+                "match (x) { duck { name } => name }",
+                vec![(vec![("name", TypeExpr::Any)], "duckⸯmatch12")],
+            ),
+            (
+                "match (x) { duck { name, age: Int } @p => p, duck { id, } => id, }",
+                vec![
+                    (vec![("name", TypeExpr::Any), ("age", TypeExpr::Int)], "p"),
+                    (vec![("id", TypeExpr::Any)], "duckⸯmatch45"),
+                ],
+            ),
+            (
+                "match (x) { duck { id: Int } @d => d, duck { id } => id }",
+                vec![
+                    (vec![("id", TypeExpr::Int)], "d"),
+                    (vec![("id", TypeExpr::Any)], "duckⸯmatch38"),
+                ],
+            ),
+            (
+                "match (x) { String @s => s, duck { id } => id }",
+                vec![(vec![], "s"), (vec![("id", TypeExpr::Any)], "duckⸯmatch28")],
+            ),
+        ];
+
+        for (src, expected_arms) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let (ValueExpr::Match { arms, .. }, _) = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src)
+            else {
+                panic!("{src} should be a match")
+            };
+
+            assert_eq!(arms.len(), expected_arms.len(), "{src}");
+            for (arm, (expected_fields, expected_binding)) in arms.iter().zip(expected_arms) {
+                let fields = match &arm.type_case.0 {
+                    TypeExpr::Duck(duck) => duck
+                        .fields
+                        .iter()
+                        .map(|field| (field.name.as_str(), field.type_expr.0.clone()))
+                        .collect::<Vec<_>>(),
+                    _ => vec![],
+                };
+                assert_eq!(fields, expected_fields, "{src}");
+                assert_eq!(
+                    arm.identifier_binding.as_deref(),
+                    Some(expected_binding),
+                    "{src}"
+                );
+            }
+        }
+
+        // an explicit Any is kept, only the fields without a type are inferred later on
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("match (x) { duck { id: Any, name } => name }")
+            .into_result()
+            .unwrap();
+        let (ValueExpr::Match { arms, .. }, _) = value_expr_parser(make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap()
+        else {
+            panic!("should be a match")
+        };
+        assert_eq!(arms[0].untyped_fields, vec!["name".to_string()]);
+        let ValueExpr::Block(exprs) = &arms[0].value_expr.0 else {
+            panic!("a duck pattern arm is a block")
+        };
+        // both fields are bound, also the typed one
+        assert_eq!(exprs.len(), 3);
+
+        for invalid in [
+            "match (x) { duck {} => 1 }",
+            "match (x) { duck { 1 } => 1 }",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                value_expr_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_value_pattern_match() {
+        let var = |name: &str| {
+            Box::new(ValueExpr::RawVariable(false, vec![name.to_string()]).into_empty_span())
+        };
+        let lit = |value_expr: ValueExpr| Box::new(value_expr.into_empty_span());
+        let both = |lhs: ValueExpr, rhs: ValueExpr| {
+            ValueExpr::And(lhs.into_empty_span().into(), rhs.into_empty_span().into())
+        };
+        let either = |lhs: ValueExpr, rhs: ValueExpr| {
+            ValueExpr::Or(lhs.into_empty_span().into(), rhs.into_empty_span().into())
+        };
+
+        let test_cases = vec![
+            (
+                "match (x) { 1 | 2 | -3 => 0, else => 1 }",
+                TypeExpr::Int,
+                "value\u{2E2F}match12",
+                either(
+                    either(
+                        ValueExpr::Equals(var("value\u{2E2F}match12"), lit(ValueExpr::Int(1))),
+                        ValueExpr::Equals(var("value\u{2E2F}match12"), lit(ValueExpr::Int(2))),
+                    ),
+                    ValueExpr::Equals(var("value\u{2E2F}match12"), lit(ValueExpr::Int(-3))),
+                ),
+            ),
+            (
+                "match (x) { 1..=9 @n => n, else => 0 }",
+                TypeExpr::Int,
+                "n",
+                both(
+                    ValueExpr::GreaterThanOrEquals(var("n"), lit(ValueExpr::Int(1))),
+                    ValueExpr::LessThanOrEquals(var("n"), lit(ValueExpr::Int(9))),
+                ),
+            ),
+            (
+                "match (x) { 0.5..1.5 @f if f != 1.0 => f, else => 0.0 }",
+                TypeExpr::Float,
+                "f",
+                both(
+                    both(
+                        ValueExpr::GreaterThanOrEquals(var("f"), lit(ValueExpr::Float(0.5))),
+                        ValueExpr::LessThan(var("f"), lit(ValueExpr::Float(1.5))),
+                    ),
+                    ValueExpr::NotEquals(var("f"), lit(ValueExpr::Float(1.0))),
+                ),
+            ),
+            (
+                "match (x) { \"a\" | \"b\" @s => s, else => \"\" }",
+                TypeExpr::String,
+                "s",
+                either(
+                    ValueExpr::Equals(var("s"), lit(ValueExpr::String("a".into(), true))),
+                    ValueExpr::Equals(var("s"), lit(ValueExpr::String("b".into(), true))),
+                ),
+            ),
+        ];
+
+        for (src, expected_type, expected_binding, expected_condition) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut value_expr = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+            value_expr_into_empty_range(&mut value_expr);
+            let ValueExpr::Match { arms, .. } = value_expr.0 else {
+                panic!("{src} should be a match")
+            };
+
+            assert_eq!(arms[0].type_case.0, expected_type, "{src}");
+            assert_eq!(
+                arms[0].identifier_binding.as_deref(),
+                Some(expected_binding),
+                "{src}"
+            );
+            assert_eq!(
+                arms[0].condition,
+                Some(expected_condition.into_empty_span()),
+                "{src}"
+            );
+        }
+
+        for invalid in [
+            "match (x) { 1..2 | \"a\" => 0 }",
+            "match (x) { 1..'z' => 0 }",
+            "match (x) { \"a\"..\"z\" => 0 }",
+            "match (x) { 1 | => 0 }",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                value_expr_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_destructuring_match() {
+        fn conjuncts(condition: &ValueExpr) -> usize {
+            match condition {
+                ValueExpr::And(lhs, rhs) => conjuncts(&lhs.0) + conjuncts(&rhs.0),
+                _ => 1,
+            }
+        }
+
+        let test_cases = vec![
+            (
+                "match (p) { { x: 0, y } => y, else => 0 }",
+                "duckⸯmatch12",
+                vec!["y"],
+                1,
+            ),
+            (
+                "match (p) { duck { x: \"a\", y, z } => y }",
+                "duckⸯmatch12",
+                vec!["y", "z"],
+                1,
+            ),
+            (
+                "match (p) { Point { x, y } @p => x + y }",
+                "p",
+                vec!["x", "y"],
+                0,
+            ),
+            (
+                "match (s) { Shape::Rect { w: 1.5, h } => h }",
+                "structⸯmatch12",
+                vec!["h"],
+                1,
+            ),
+            (
+                "match (p) { Point { x: true, y: 'a' } @p if p.z => 1 }",
+                "p",
+                vec![],
+                3,
+            ),
+        ];
+
+        for (src, expected_binding, expected_bound_fields, expected_conjuncts) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let (ValueExpr::Match { arms, .. }, _) = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src)
+            else {
+                panic!("{src} should be a match")
+            };
+
+            let arm = &arms[0];
+            assert_eq!(
+                arm.identifier_binding.as_deref(),
+                Some(expected_binding),
+                "{src}"
+            );
+            assert_eq!(
+                arm.condition
+                    .as_ref()
+                    .map_or(0, |condition| conjuncts(&condition.0)),
+                expected_conjuncts,
+                "{src}"
+            );
+
+            let ValueExpr::Block(exprs) = &arm.value_expr.0 else {
+                panic!("{src} should bind its fields in a block")
+            };
+            let bound_fields = exprs
+                .iter()
+                .filter_map(|(expr, _)| match expr {
+                    ValueExpr::VarDecl(declaration) => Some(declaration.0.name.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(bound_fields, expected_bound_fields, "{src}");
+        }
+
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("match (s) { Shape::Rect { w, h } => w * h }")
+            .into_result()
+            .unwrap();
+        let (ValueExpr::Match { arms, .. }, _) = value_expr_parser(make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap()
+        else {
+            panic!("should be a match")
+        };
+        assert_eq!(
+            arms[0].type_case.0,
+            TypeExpr::RawTypeName(false, vec!["Shape".into(), "Rect".into()], None)
+        );
+
+        for invalid in [
+            "match (p) { Point { x: Int } => 1 }",
+            "match (p) { Point {} => 1 }",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                value_expr_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_condition_ends_at_body() {
+        let parse = |src: &str| {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut value_expr = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+            value_expr_into_empty_range(&mut value_expr);
+            value_expr.0
+        };
+        let ready = ValueExpr::RawVariable(false, vec!["ready".into()]);
+
+        let ValueExpr::If { condition, .. } = parse("if ready {}") else {
+            panic!("if ready {{}} should be an if")
+        };
+        assert_eq!(condition.0, ready);
+
+        let ValueExpr::If {
+            condition, r#else, ..
+        } = parse("if ready {} else { 1 }")
+        else {
+            panic!("if ready {{}} else {{ 1 }} should be an if")
+        };
+        assert_eq!(condition.0, ready);
+        assert!(r#else.is_some());
+
+        let ValueExpr::While { condition, .. } = parse("while ready {}") else {
+            panic!("while ready {{}} should be a while")
+        };
+        assert_eq!(condition.0, ready);
+
+        // a struct literal only belongs to the condition in parens, the braces of a duck type
+        // always do
+        let ValueExpr::If { condition, .. } = parse("if p == (Point { x: 1 }) { 1 }") else {
+            panic!("the struct literal in parens should be part of the condition")
+        };
+        let ValueExpr::Equals(_, rhs) = &condition.0 else {
+            panic!("the condition should be a comparison")
+        };
+        assert!(matches!(rhs.0, ValueExpr::Struct { .. }));
+        assert!(matches!(
+            parse("if x is duck { a: Int } { 1 }"),
+            ValueExpr::Match { .. }
+        ));
+        assert!(matches!(
+            parse("if x is { a: Int } { 1 }"),
+            ValueExpr::Match { .. }
+        ));
+
+        // without parens the braces of the struct literal are the body, x: 1 isn't a statement
+        let src = "if p == Point { x: 1 } { 1 }";
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse(src)
+            .into_result()
+            .expect(src);
+        assert!(
+            value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .has_errors()
+        );
+    }
+
+    #[test]
+    fn test_if_let() {
+        let test_cases = vec![
+            ("if let Int @x = (y) { x }", TypeExpr::Int, Some("x"), false),
+            (
+                "if let Point { x, y: 0 } = (p) { x } else { 0 }",
+                TypeExpr::RawTypeName(false, vec!["Point".into()], None),
+                Some("structⸯmatch7"),
+                true,
+            ),
+            (
+                "if let .none = (opt) { 1 } else { 2 }",
+                TypeExpr::Tag("none".into()),
+                None,
+                true,
+            ),
+            (
+                "if (a) { 1 } else if let 1..=9 @n = (b) { n } else { 0 }",
+                TypeExpr::Int,
+                Some("n"),
+                true,
+            ),
+        ];
+
+        for (src, expected_type, expected_binding, has_else) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut value_expr = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+            if let ValueExpr::If { r#else, .. } = value_expr.0 {
+                value_expr = *r#else.expect(src);
+            }
+            let ValueExpr::Match { arms, else_arm, .. } = value_expr.0 else {
+                panic!("{src} should be a match")
+            };
+
+            assert_eq!(arms.len(), 1, "{src}");
+            assert_eq!(arms[0].type_case.0, expected_type, "{src}");
+            assert_eq!(
+                arms[0].identifier_binding.as_deref(),
+                expected_binding,
+                "{src}"
+            );
+            let else_arm = else_arm.expect(src);
+            assert_eq!(else_arm.type_case.0, TypeExpr::Any, "{src}");
+            assert_eq!(
+                else_arm.value_expr.0 != ValueExpr::Block(vec![]),
+                has_else,
+                "{src}"
+            );
+        }
+
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("while let Int @x = (next()) { x; }")
+            .into_result()
+            .unwrap();
+        let (ValueExpr::Loop { body, .. }, _) = value_expr_parser(make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap()
+        else {
+            panic!("while let should be a loop")
+        };
+        let ValueExpr::Block(exprs) = &body.0 else {
+            panic!("the body of the loop should be a block")
+        };
+        let ValueExpr::If { condition, .. } = &exprs[0].0 else {
+            panic!("the loop should start by checking the match")
+        };
+        assert!(matches!(
+            &condition.0,
+            ValueExpr::BoolNegate(matches) if matches!(matches.0, ValueExpr::Match { .. })
+        ));
+
+        for invalid in [
+            "if let Int @x { x }",
+            "if let Int @x (y) { x }",
+            "while let = (y) {}",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                value_expr_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_is() {
+        let parse = |src: &str| {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src)
+                .0
+        };
+
+        let test_cases = vec![
+            ("x is Int", TypeExpr::Int),
+            (
+                "x is { name: String }",
+                TypeExpr::Duck(Duck {
+                    fields: vec![Field::new(
+                        "name".into(),
+                        TypeExpr::String.into_empty_span(),
+                    )],
+                }),
+            ),
+            ("x is .none", TypeExpr::Tag("none".into())),
+        ];
+
+        for (src, expected_type) in test_cases {
+            let ValueExpr::Match { arms, else_arm, .. } = parse(src) else {
+                panic!("{src} should be a match")
+            };
+            assert_eq!(arms.len(), 1, "{src}");
+            let mut type_case = arms[0].type_case.clone();
+            type_expr_into_empty_range(&mut type_case);
+            assert_eq!(type_case.0, expected_type, "{src}");
+            assert_eq!(arms[0].identifier_binding, None, "{src}");
+            assert_eq!(else_arm.expect(src).type_case.0, TypeExpr::Any, "{src}");
+        }
+
+        assert!(matches!(
+            parse("x is Int && y"),
+            ValueExpr::And(lhs, _) if matches!(lhs.0, ValueExpr::Match { .. })
+        ));
+        // the narrowing of x happens in the type resolution, the parser keeps the if
+        assert!(matches!(
+            parse("if (x is Int) { x } else { 0 }"),
+            ValueExpr::If { condition, .. } if matches!(condition.0, ValueExpr::Match { .. })
+        ));
+    }
+
+    #[test]
+    fn test_shorthand_lambda() {
+        let test_cases = vec![
+            ("|x| x + 1", vec!["x"]),
+            ("|| 5", vec![]),
+            ("|a, b| a", vec!["a", "b"]),
+            ("|s| { s; }", vec!["s"]),
+        ];
+
+        for (src, expected_params) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let (ValueExpr::Lambda(lambda_expr), _) = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src)
+            else {
+                panic!("{src} should be a lambda")
+            };
+
+            assert_eq!(
+                lambda_expr
+                    .params
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>(),
+                expected_params,
+                "{src}"
+            );
+            assert!(
+                lambda_expr
+                    .params
+                    .iter()
+                    .all(|(_, (type_expr, _))| *type_expr == TypeExpr::Any),
+                "{src}"
+            );
+            assert_eq!(lambda_expr.return_type, None, "{src}");
+        }
+
+        let tokens = lex_parser(FileId::EMPTY)
+            .parse("map(xs, |x| x * 2)")
+            .into_result()
+            .unwrap();
+        let (ValueExpr::FunctionCall { params, .. }, _) = value_expr_parser(make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap()
+        else {
+            panic!("should be a function call")
+        };
+        assert!(matches!(params[1].0, ValueExpr::Lambda(..)));
+
+        for invalid in ["|x x", "|1| 1", "|x: Int| x"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                value_expr_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_destructuring_declaration() {
+        let test_cases = vec![
+            ("{ let (q, r) = divmod(7, 2); q }", vec!["q", "r"]),
+            ("{ let (a, _) = x; a }", vec!["a"]),
+            ("{ let (a, b, c,) = x; a }", vec!["a", "b", "c"]),
+        ];
+
+        for (src, expected_names) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let (ValueExpr::Block(exprs), _) = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src)
+            else {
+                panic!("{src} should be a block")
+            };
+
+            let names = exprs
+                .iter()
+                .filter_map(|(expr, _)| match expr {
+                    ValueExpr::VarDecl(declaration) => Some(declaration.0.name.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            assert!(names[0].starts_with("destructure_"), "{src}");
+            assert_eq!(names[1..], expected_names, "{src}");
+        }
+
+        let src = "{ let (a, (b, _), c) = x; a }";
+        let tokens = lex_parser(FileId::EMPTY).parse(src).into_result().unwrap();
+        let (ValueExpr::Block(exprs), _) = value_expr_parser(make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap()
+        else {
+            panic!("{src} should be a block")
+        };
+        let names = exprs
+            .iter()
+            .filter_map(|(expr, _)| match expr {
+                ValueExpr::VarDecl(declaration) => Some(declaration.0.name.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let nested_tuple = format!("{}_1", names[0]);
+        assert_eq!(
+            names[1..],
+            ["a".to_string(), nested_tuple, "b".into(), "c".into()]
+        );
+
+        for invalid in [
+            "{ let (a) = x; }",
+            "{ let (a, 1) = x; }",
+            "{ let (a, b); }",
+            "{ let (a, (b)) = x; }",
+        ] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                value_expr_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_map_literals() {
+        let string = |s: &str| ValueExpr::String(s.into(), true).into_empty_span();
+        let int = |i| ValueExpr::Int(i).into_empty_span();
+        let map_type = |key: TypeExpr, value: TypeExpr| {
+            TypeExpr::Map(key.into_empty_span().into(), value.into_empty_span().into())
+                .into_empty_span()
+        };
+
+        let test_cases = vec![
+            (
+                "#{\"a\": 1}",
+                ValueExpr::Map(None, vec![(string("a"), int(1))]),
+            ),
+            (
+                "{\"a\": 1, \"b\": 2,}",
+                ValueExpr::Map(None, vec![(string("a"), int(1)), (string("b"), int(2))]),
+            ),
+            (
+                "#<String, Int>{}",
+                ValueExpr::Map(Some(map_type(TypeExpr::String, TypeExpr::Int)), vec![]),
+            ),
+            (
+                "#{x: y}",
+                ValueExpr::Map(None, vec![(*v_var(&["x"]), *v_var(&["y"]))]),
+            ),
+        ];
+
+        for (src, expected_ast) in test_cases {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(src)
+                .into_result()
+                .expect(src);
+            let mut output = value_expr_parser(make_input)
+                .parse(make_input(empty_range(), &tokens))
+                .into_result()
+                .expect(src);
+
+            value_expr_into_empty_range(&mut output);
+            assert_eq!(output.0, expected_ast, "{src}");
+        }
+
+        for invalid in ["#{}", "#{\"a\" 1}", "#<{ x: Int }, Int>{}"] {
+            let tokens = lex_parser(FileId::EMPTY)
+                .parse(invalid)
+                .into_result()
+                .expect(invalid);
+            assert!(
+                value_expr_parser(make_input)
+                    .parse(make_input(empty_range(), &tokens))
+                    .has_errors(),
+                "{invalid}"
+            );
+        }
     }
 }
@@ -0,0 +1,1228 @@
+use std::collections::HashMap;
+
+use crate::parse::{
+    SS, Spanned,
+    const_parser::ConstDefinition,
+    failure,
+    function_parser::FunctionDefintion,
+    source_file_parser::SourceFile,
+    type_parser::TypeExpr,
+    value_parser::{
+        Assignment, Declaration, MatchArm, ValFmtStringContents, ValHtmlStringContents, ValueExpr,
+    },
+};
+
+// guards against comptime code that never terminates
+const MAX_STEPS: usize = 1_000_000;
+const MAX_CALL_DEPTH: usize = 256;
+
+type EvalResult<T> = Result<T, (String, SS)>;
+
+enum Flow {
+    Value(ValueExpr),
+    Return(ValueExpr),
+    Break(ValueExpr),
+    Continue,
+}
+
+pub struct ConstEvaluator<'a> {
+    functions: &'a [FunctionDefintion],
+    // the consts of the module, visible in every scope and every called function
+    consts: &'a HashMap<String, ValueExpr>,
+    scopes: Vec<HashMap<String, ValueExpr>>,
+    call_depth: usize,
+    steps: usize,
+}
+
+fn unit() -> ValueExpr {
+    ValueExpr::Tuple(vec![])
+}
+
+fn is_const_value(value_expr: &ValueExpr) -> bool {
+    match value_expr {
+        ValueExpr::Int(..)
+        | ValueExpr::Float(..)
+        | ValueExpr::Bool(..)
+        | ValueExpr::Char(..)
+        | ValueExpr::String(..) => true,
+        ValueExpr::Tuple(fields) | ValueExpr::Array(_, fields) => {
+            fields.iter().all(|field| is_const_value(&field.0))
+        }
+        _ => false,
+    }
+}
+
+// literals are wider than an Int until the type checker has seen them
+fn as_int(i: i128, span: SS) -> EvalResult<i64> {
+    i64::try_from(i).map_err(|_| (format!("{i} doesn't fit into an Int"), span))
+}
+
+// arrays are printed by go at runtime, so they can't be formatted at compile time
+fn display_const_value(value_expr: &ValueExpr) -> Option<String> {
+    Some(match value_expr {
+        ValueExpr::Int(i) => i.to_string(),
+        ValueExpr::Float(f) => f.to_string(),
+        ValueExpr::Bool(b) => b.to_string(),
+        ValueExpr::Char(c) => c.to_string(),
+        ValueExpr::String(s, _) => s.clone(),
+        ValueExpr::Tuple(fields) => format!(
+            "({})",
+            fields
+                .iter()
+                .map(|field| display_const_value(&field.0))
+                .collect::<Option<Vec<_>>>()?
+                .join(", ")
+        ),
+        _ => return None,
+    })
+}
+
+// the functions of std::string that don't need go, so they can be called at compile time
+fn call_std_string(name: &str, params: &[ValueExpr], span: SS) -> EvalResult<ValueExpr> {
+    Ok(match (name, params) {
+        ("to_upper", [ValueExpr::String(s, _)]) => ValueExpr::String(s.to_uppercase(), true),
+        ("to_lower", [ValueExpr::String(s, _)]) => ValueExpr::String(s.to_lowercase(), true),
+        ("from_int", [ValueExpr::Int(i)]) => ValueExpr::String(i.to_string(), true),
+        ("from_bool", [ValueExpr::Bool(b)]) => ValueExpr::String(b.to_string(), true),
+        // at runtime the error of strconv.Atoi is ignored and to_int returns 0, a comptime
+        // block would bake that 0 into the program, so it's reported instead
+        ("to_int", [ValueExpr::String(s, _)]) => match s.parse::<i64>() {
+            Ok(i) => ValueExpr::Int(i.into()),
+            Err(_) => return Err((format!("std::string::to_int: {s:?} is not an Int"), span)),
+        },
+        _ => {
+            return Err((
+                format!("std::string::{name} can't be called at compile time"),
+                span,
+            ));
+        }
+    })
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(functions: &'a [FunctionDefintion], consts: &'a HashMap<String, ValueExpr>) -> Self {
+        Self {
+            functions,
+            consts,
+            scopes: vec![HashMap::new()],
+            call_depth: 0,
+            steps: 0,
+        }
+    }
+
+    pub fn eval(&mut self, value_expr: &Spanned<ValueExpr>) -> EvalResult<ValueExpr> {
+        match self.eval_flow(value_expr)? {
+            Flow::Value(value) | Flow::Return(value) => Ok(value),
+            Flow::Break(..) | Flow::Continue => Err((
+                "break and continue are only allowed inside of loops".to_string(),
+                value_expr.1,
+            )),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&ValueExpr> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.consts.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut ValueExpr> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn eval_int_op(
+        &mut self,
+        lhs: &Spanned<ValueExpr>,
+        rhs: &Spanned<ValueExpr>,
+        span: SS,
+        int_op: fn(i64, i64) -> Option<i64>,
+        float_op: fn(f64, f64) -> f64,
+    ) -> EvalResult<ValueExpr> {
+        match (self.eval(lhs)?, self.eval(rhs)?) {
+            (ValueExpr::Int(l), ValueExpr::Int(r)) => int_op(as_int(l, lhs.1)?, as_int(r, rhs.1)?)
+                .map(|i| ValueExpr::Int(i.into()))
+                .ok_or(("integer overflow or division by zero".to_string(), span)),
+            (ValueExpr::Float(l), ValueExpr::Float(r)) => Ok(ValueExpr::Float(float_op(l, r))),
+            _ => Err(("arithmetic is only allowed on numbers".to_string(), span)),
+        }
+    }
+
+    fn eval_bit_op(
+        &mut self,
+        lhs: &Spanned<ValueExpr>,
+        rhs: &Spanned<ValueExpr>,
+        span: SS,
+        op: fn(i64, i64) -> Option<i64>,
+    ) -> EvalResult<ValueExpr> {
+        let (l, r) = (self.eval_int(lhs)?, self.eval_int(rhs)?);
+        op(l, r)
+            .map(|i| ValueExpr::Int(i.into()))
+            .ok_or(("shift amount out of range".to_string(), span))
+    }
+
+    fn eval_compare(
+        &mut self,
+        lhs: &Spanned<ValueExpr>,
+        rhs: &Spanned<ValueExpr>,
+        span: SS,
+        op: fn(std::cmp::Ordering) -> bool,
+    ) -> EvalResult<ValueExpr> {
+        let ordering = match (self.eval(lhs)?, self.eval(rhs)?) {
+            (ValueExpr::Int(l), ValueExpr::Int(r)) => l.partial_cmp(&r),
+            (ValueExpr::Float(l), ValueExpr::Float(r)) => l.partial_cmp(&r),
+            (ValueExpr::Char(l), ValueExpr::Char(r)) => l.partial_cmp(&r),
+            (ValueExpr::String(l, _), ValueExpr::String(r, _)) => l.partial_cmp(&r),
+            _ => None,
+        };
+
+        ordering
+            .map(|ordering| ValueExpr::Bool(op(ordering)))
+            .ok_or(("these values can't be compared".to_string(), span))
+    }
+
+    fn eval_bool(&mut self, value_expr: &Spanned<ValueExpr>) -> EvalResult<bool> {
+        match self.eval(value_expr)? {
+            ValueExpr::Bool(b) => Ok(b),
+            _ => Err(("expected a bool".to_string(), value_expr.1)),
+        }
+    }
+
+    fn eval_int(&mut self, value_expr: &Spanned<ValueExpr>) -> EvalResult<i64> {
+        match self.eval(value_expr)? {
+            ValueExpr::Int(i) => as_int(i, value_expr.1),
+            _ => Err(("expected an int".to_string(), value_expr.1)),
+        }
+    }
+
+    // the array a variable holds, for the methods and assignments that change it in place
+    fn variable_mut(&mut self, target: &Spanned<ValueExpr>) -> EvalResult<&mut ValueExpr> {
+        let ValueExpr::RawVariable(false, path) = &target.0 else {
+            return Err((
+                "only arrays in variables can be changed at compile time".to_string(),
+                target.1,
+            ));
+        };
+        let name = path.join("::");
+        self.lookup_mut(&name)
+            .ok_or((format!("{name} is not declared"), target.1))
+    }
+
+    fn call_array_method(
+        &mut self,
+        target_obj: &Spanned<ValueExpr>,
+        method_name: &str,
+        params: Vec<ValueExpr>,
+        span: SS,
+    ) -> EvalResult<ValueExpr> {
+        match (method_name, &params[..]) {
+            ("len", []) => match self.eval(target_obj)? {
+                ValueExpr::Array(_, elements) => Ok(ValueExpr::Int(elements.len() as i128)),
+                _ => Err((
+                    "only arrays have a length at compile time".to_string(),
+                    span,
+                )),
+            },
+            ("push", [_]) => {
+                let ValueExpr::Array(_, elements) = self.variable_mut(target_obj)? else {
+                    return Err(("only arrays can be pushed to".to_string(), target_obj.1));
+                };
+                elements.extend(params.into_iter().map(|param| (param, span)));
+                Ok(unit())
+            }
+            _ => Err((
+                format!("{method_name} can't be called at compile time"),
+                span,
+            )),
+        }
+    }
+
+    // runs the body of a for loop for one value, Some is the flow that ends the loop
+    fn eval_for_body(
+        &mut self,
+        ident: &str,
+        value: ValueExpr,
+        body: &Spanned<ValueExpr>,
+    ) -> EvalResult<Option<Flow>> {
+        self.scopes
+            .push(HashMap::from([(ident.to_string(), value)]));
+        let flow = self.eval_flow(body);
+        self.scopes.pop();
+        Ok(match flow? {
+            Flow::Break(..) => Some(Flow::Value(unit())),
+            Flow::Value(..) | Flow::Continue => None,
+            flow @ Flow::Return(..) => Some(flow),
+        })
+    }
+
+    fn call(
+        &mut self,
+        function: &FunctionDefintion,
+        params: Vec<ValueExpr>,
+        span: SS,
+    ) -> EvalResult<ValueExpr> {
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err((
+                format!("call depth limit of {MAX_CALL_DEPTH} reached at compile time"),
+                span,
+            ));
+        }
+
+        let param_names = function.params.clone().unwrap_or_default();
+        if param_names.len() != params.len() {
+            return Err((
+                format!(
+                    "{} takes {} arguments, but {} were given",
+                    function.name,
+                    param_names.len(),
+                    params.len()
+                ),
+                span,
+            ));
+        }
+
+        // functions only see their own parameters, not the scopes of the caller
+        let caller_scopes = std::mem::replace(
+            &mut self.scopes,
+            vec![
+                param_names
+                    .into_iter()
+                    .map(|param| param.0)
+                    .zip(params)
+                    .collect(),
+            ],
+        );
+        self.call_depth += 1;
+        let result = self.eval(&function.value_expr);
+        self.call_depth -= 1;
+        self.scopes = caller_scopes;
+
+        result
+    }
+
+    fn eval_flow(&mut self, value_expr: &Spanned<ValueExpr>) -> EvalResult<Flow> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err((
+                format!("step limit of {MAX_STEPS} reached at compile time"),
+                value_expr.1,
+            ));
+        }
+
+        let span = value_expr.1;
+        let value = match &value_expr.0 {
+            ValueExpr::Int(..)
+            | ValueExpr::Float(..)
+            | ValueExpr::Bool(..)
+            | ValueExpr::Char(..)
+            | ValueExpr::String(..) => value_expr.0.clone(),
+            ValueExpr::Comptime(inner) => self.eval(inner)?,
+            ValueExpr::RawVariable(false, path) if path.len() == 1 => self
+                .lookup(&path[0])
+                .cloned()
+                .ok_or((format!("{} is not known at compile time", path[0]), span))?,
+            ValueExpr::Tuple(fields) => {
+                let mut values = Vec::new();
+                for field in fields {
+                    values.push((self.eval(field)?, field.1));
+                }
+                ValueExpr::Tuple(values)
+            }
+            ValueExpr::Array(content_type, elements) => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push((self.eval(element)?, element.1));
+                }
+                ValueExpr::Array(content_type.clone(), values)
+            }
+            ValueExpr::ArrayAccess(target, idx) => {
+                let ValueExpr::Array(_, elements) = self.eval(target)? else {
+                    return Err((
+                        "only arrays can be indexed at compile time".to_string(),
+                        target.1,
+                    ));
+                };
+                let idx = self.eval_int(idx)?;
+                element_at(&elements, idx, span)?.0.clone()
+            }
+            ValueExpr::FieldAccess {
+                target_obj,
+                field_name,
+            } => {
+                let ValueExpr::Tuple(fields) = self.eval(target_obj)? else {
+                    return Err((
+                        "only tuple fields can be accessed at compile time".to_string(),
+                        span,
+                    ));
+                };
+
+                field_name
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|idx| fields.get(idx))
+                    .map(|field| field.0.clone())
+                    .ok_or((format!("tuple has no field {field_name}"), span))?
+            }
+            ValueExpr::FormattedString(contents) => {
+                let mut s = String::new();
+                for content in contents {
+                    match content {
+                        ValFmtStringContents::String(string) => s.push_str(string),
+                        ValFmtStringContents::Expr(expr, None) => {
+                            let value = self.eval(expr)?;
+                            let Some(display) = display_const_value(&value) else {
+                                return Err((
+                                    "arrays can't be formatted at compile time".to_string(),
+                                    expr.1,
+                                ));
+                            };
+                            s.push_str(&display)
+                        }
+                        ValFmtStringContents::Expr(expr, Some(_)) => {
+                            return Err((
+                                "format specs are not supported at compile time".to_string(),
+                                expr.1,
+                            ));
+                        }
+                    }
+                }
+                ValueExpr::String(s, true)
+            }
+            ValueExpr::Block(exprs) => {
+                self.scopes.push(HashMap::new());
+                let mut last = unit();
+                for expr in exprs {
+                    match self.eval_flow(expr) {
+                        Ok(Flow::Value(value)) => last = value,
+                        other => {
+                            self.scopes.pop();
+                            return other;
+                        }
+                    }
+                }
+                self.scopes.pop();
+                last
+            }
+            ValueExpr::VarDecl(decl) => {
+                let Declaration {
+                    name, initializer, ..
+                } = &decl.0;
+                let value = self.eval(initializer)?;
+                self.scopes
+                    .last_mut()
+                    .expect("there is always at least one scope")
+                    .insert(name.clone(), value);
+                unit()
+            }
+            ValueExpr::VarAssign(assignment) => {
+                let Assignment { target, value_expr } = &assignment.0;
+                if let ValueExpr::ArrayAccess(array, idx) = &target.0 {
+                    let idx = self.eval_int(idx)?;
+                    let value = self.eval(value_expr)?;
+                    let ValueExpr::Array(_, elements) = self.variable_mut(array)? else {
+                        return Err(("only arrays can be indexed".to_string(), array.1));
+                    };
+                    let len = elements.len();
+                    let Some(element) = usize::try_from(idx).ok().and_then(|i| elements.get_mut(i))
+                    else {
+                        return Err((
+                            format!("index {idx} is out of bounds for an array of length {len}"),
+                            target.1,
+                        ));
+                    };
+                    element.0 = value;
+                    return Ok(Flow::Value(unit()));
+                }
+
+                let (ValueExpr::RawVariable(false, path), _) = target else {
+                    return Err((
+                        "only variables can be assigned at compile time".to_string(),
+                        target.1,
+                    ));
+                };
+
+                let value = self.eval(value_expr)?;
+                let Some(slot) = self.lookup_mut(&path.join("::")) else {
+                    return Err((format!("{} is not declared", path.join("::")), target.1));
+                };
+                *slot = value;
+                unit()
+            }
+            ValueExpr::If {
+                condition,
+                then,
+                r#else,
+            } => {
+                if self.eval_bool(condition)? {
+                    return self.eval_flow(then);
+                } else if let Some(r#else) = r#else {
+                    return self.eval_flow(r#else);
+                }
+                unit()
+            }
+            ValueExpr::While { condition, body } => {
+                while self.eval_bool(condition)? {
+                    match self.eval_flow(body)? {
+                        Flow::Break(..) => break,
+                        Flow::Value(..) | Flow::Continue => {}
+                        flow @ Flow::Return(..) => return Ok(flow),
+                    }
+                }
+                unit()
+            }
+            ValueExpr::For {
+                ident,
+                target,
+                body,
+            } => {
+                let ValueExpr::Range {
+                    start,
+                    end,
+                    inclusive,
+                } = &target.0
+                else {
+                    let ValueExpr::Array(_, elements) = self.eval(target)? else {
+                        return Err((
+                            "only ranges and arrays can be iterated at compile time".to_string(),
+                            target.1,
+                        ));
+                    };
+                    for (element, _) in elements {
+                        if let Some(flow) = self.eval_for_body(ident, element, body)? {
+                            return Ok(flow);
+                        }
+                    }
+                    return Ok(Flow::Value(unit()));
+                };
+
+                let (start, end) = (self.eval_int(start)?, self.eval_int(end)?);
+                for i in start..=end {
+                    if i == end && !inclusive {
+                        break;
+                    }
+
+                    if let Some(flow) = self.eval_for_body(ident, ValueExpr::Int(i.into()), body)? {
+                        return Ok(flow);
+                    }
+                }
+                unit()
+            }
+            ValueExpr::Loop { body, .. } => loop {
+                match self.eval_flow(body)? {
+                    Flow::Break(value) => break value,
+                    Flow::Value(..) | Flow::Continue => {}
+                    flow @ Flow::Return(..) => return Ok(flow),
+                }
+            },
+            ValueExpr::Break(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => unit(),
+                };
+                return Ok(Flow::Break(value));
+            }
+            ValueExpr::Continue => return Ok(Flow::Continue),
+            ValueExpr::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => unit(),
+                };
+                return Ok(Flow::Return(value));
+            }
+            ValueExpr::Add(lhs, rhs) => match (self.eval(lhs)?, self.eval(rhs)?) {
+                (ValueExpr::String(l, _), ValueExpr::String(r, _)) => {
+                    ValueExpr::String(l + &r, true)
+                }
+                (ValueExpr::Int(l), ValueExpr::Int(r)) => ValueExpr::Int(
+                    as_int(l, lhs.1)?
+                        .checked_add(as_int(r, rhs.1)?)
+                        .ok_or(("integer overflow".to_string(), span))?
+                        .into(),
+                ),
+                (ValueExpr::Float(l), ValueExpr::Float(r)) => ValueExpr::Float(l + r),
+                _ => return Err(("these values can't be added".to_string(), span)),
+            },
+            ValueExpr::Sub(lhs, rhs) => {
+                self.eval_int_op(lhs, rhs, span, i64::checked_sub, |l, r| l - r)?
+            }
+            ValueExpr::Mul(lhs, rhs) => {
+                self.eval_int_op(lhs, rhs, span, i64::checked_mul, |l, r| l * r)?
+            }
+            ValueExpr::Div(lhs, rhs) => {
+                self.eval_int_op(lhs, rhs, span, i64::checked_div, |l, r| l / r)?
+            }
+            ValueExpr::Mod(lhs, rhs) => {
+                self.eval_int_op(lhs, rhs, span, i64::checked_rem, |l, r| l % r)?
+            }
+            ValueExpr::Equals(lhs, rhs) | ValueExpr::NotEquals(lhs, rhs) => {
+                let equal = strip_spans(self.eval(lhs)?) == strip_spans(self.eval(rhs)?);
+                ValueExpr::Bool(equal == matches!(value_expr.0, ValueExpr::Equals(..)))
+            }
+            ValueExpr::LessThan(lhs, rhs) => self.eval_compare(lhs, rhs, span, |o| o.is_lt())?,
+            ValueExpr::LessThanOrEquals(lhs, rhs) => {
+                self.eval_compare(lhs, rhs, span, |o| o.is_le())?
+            }
+            ValueExpr::GreaterThan(lhs, rhs) => self.eval_compare(lhs, rhs, span, |o| o.is_gt())?,
+            ValueExpr::GreaterThanOrEquals(lhs, rhs) => {
+                self.eval_compare(lhs, rhs, span, |o| o.is_ge())?
+            }
+            ValueExpr::And(lhs, rhs) => {
+                ValueExpr::Bool(self.eval_bool(lhs)? && self.eval_bool(rhs)?)
+            }
+            ValueExpr::Or(lhs, rhs) => {
+                ValueExpr::Bool(self.eval_bool(lhs)? || self.eval_bool(rhs)?)
+            }
+            ValueExpr::BitAnd(lhs, rhs) => self.eval_bit_op(lhs, rhs, span, |l, r| Some(l & r))?,
+            ValueExpr::BitOr(lhs, rhs) => self.eval_bit_op(lhs, rhs, span, |l, r| Some(l | r))?,
+            ValueExpr::BitXor(lhs, rhs) => self.eval_bit_op(lhs, rhs, span, |l, r| Some(l ^ r))?,
+            ValueExpr::ShiftLeft(lhs, rhs) => self.eval_bit_op(lhs, rhs, span, |l, r| {
+                u32::try_from(r).ok().and_then(|r| l.checked_shl(r))
+            })?,
+            ValueExpr::ShiftRight(lhs, rhs) => self.eval_bit_op(lhs, rhs, span, |l, r| {
+                u32::try_from(r).ok().and_then(|r| l.checked_shr(r))
+            })?,
+            ValueExpr::BoolNegate(inner) => ValueExpr::Bool(!self.eval_bool(inner)?),
+            ValueExpr::BitNegate(inner) => ValueExpr::Int((!self.eval_int(inner)?).into()),
+            ValueExpr::Negate(inner) => match self.eval(inner)? {
+                ValueExpr::Int(i) => ValueExpr::Int(
+                    as_int(i, inner.1)?
+                        .checked_neg()
+                        .ok_or(("integer overflow".to_string(), span))?
+                        .into(),
+                ),
+                ValueExpr::Float(f) => ValueExpr::Float(-f),
+                _ => return Err(("only numbers can be negated".to_string(), span)),
+            },
+            ValueExpr::FunctionCall {
+                target,
+                params,
+                type_params: None,
+            } => {
+                let mut values = Vec::new();
+                for param in params {
+                    values.push(self.eval(param)?);
+                }
+
+                match &target.0 {
+                    ValueExpr::FieldAccess {
+                        target_obj,
+                        field_name,
+                    } => self.call_array_method(target_obj, field_name, values, span)?,
+                    ValueExpr::RawVariable(false, path)
+                        if path.len() == 3 && path[0] == "std" && path[1] == "string" =>
+                    {
+                        call_std_string(&path[2], &values, span)?
+                    }
+                    _ => {
+                        let function = match &target.0 {
+                            ValueExpr::RawVariable(false, path) if path.len() == 1 => {
+                                self.functions.iter().find(|function| {
+                                    function.name == path[0] && function.generics.is_none()
+                                })
+                            }
+                            _ => None,
+                        };
+                        let Some(function) = function else {
+                            return Err((
+                                "only non generic functions of this module and std::string can \
+                                 be called at compile time"
+                                    .to_string(),
+                                target.1,
+                            ));
+                        };
+                        self.call(function, values, span)?
+                    }
+                }
+            }
+            _ => {
+                return Err((
+                    "this expression can't be evaluated at compile time".to_string(),
+                    span,
+                ));
+            }
+        };
+
+        Ok(Flow::Value(value))
+    }
+}
+
+fn element_at(
+    elements: &[Spanned<ValueExpr>],
+    idx: i64,
+    span: SS,
+) -> EvalResult<&Spanned<ValueExpr>> {
+    usize::try_from(idx)
+        .ok()
+        .and_then(|i| elements.get(i))
+        .ok_or((
+            format!(
+                "index {idx} is out of bounds for an array of length {}",
+                elements.len()
+            ),
+            span,
+        ))
+}
+
+// values are compared structurally, the spans of tuple fields and array elements don't matter
+fn strip_spans(value_expr: ValueExpr) -> ValueExpr {
+    let strip_all = |fields: Vec<Spanned<ValueExpr>>| {
+        fields
+            .into_iter()
+            .map(|field| strip_spans(field.0).into_empty_span())
+            .collect()
+    };
+    match value_expr {
+        ValueExpr::Tuple(fields) => ValueExpr::Tuple(strip_all(fields)),
+        ValueExpr::Array(_, elements) => ValueExpr::Array(None, strip_all(elements)),
+        other => other,
+    }
+}
+
+// the type of a value a const can have, consts are emitted as go consts and these are the
+// only duck types backed by a go constant
+fn const_value_type(value_expr: &ValueExpr) -> Option<TypeExpr> {
+    match value_expr {
+        ValueExpr::Int(..) => Some(TypeExpr::Int),
+        ValueExpr::Float(..) => Some(TypeExpr::Float),
+        ValueExpr::Bool(..) => Some(TypeExpr::Bool),
+        ValueExpr::Char(..) => Some(TypeExpr::Char),
+        ValueExpr::String(..) => Some(TypeExpr::String),
+        _ => None,
+    }
+}
+
+fn evaluate_const(
+    const_def: &mut ConstDefinition,
+    functions: &[FunctionDefintion],
+    consts: &HashMap<String, ValueExpr>,
+) {
+    let span = const_def.value_expr.1;
+    let (value, value_type) = ConstEvaluator::new(functions, consts)
+        .eval(&const_def.value_expr)
+        .and_then(|value| match const_value_type(&value) {
+            Some(value_type) => {
+                if let ValueExpr::Int(i) = value {
+                    as_int(i, span)?;
+                }
+                Ok((value, value_type))
+            }
+            None => Err((
+                "consts have to be an Int, Float, Bool, Char or String".to_string(),
+                span,
+            )),
+        })
+        .unwrap_or_else(|(msg, at)| {
+            failure(
+                &at.context.file_name(),
+                format!("can't evaluate the const {}: {msg}", const_def.name),
+                (msg, at),
+                vec![("in this const".to_string(), span)],
+                &at.context.file_contents(),
+            )
+        });
+
+    if let Some((declared_type, declared_span)) = &const_def.type_expr
+        && *declared_type != value_type
+    {
+        failure(
+            &span.context.file_name(),
+            format!(
+                "the const {} is declared as {declared_type}, but has the type {value_type}",
+                const_def.name
+            ),
+            (format!("this has the type {value_type}"), span),
+            vec![(format!("declared as {declared_type} here"), *declared_span)],
+            &span.context.file_contents(),
+        )
+    }
+
+    let type_span = const_def.type_expr.as_ref().map_or(span, |(_, span)| *span);
+    const_def.type_expr = Some((value_type, type_span));
+    const_def.value_expr.0 = value;
+}
+
+// MAX | MIN => .. compares the matched value with the values of the consts, like the literal
+// pattern 65536 | 0 => .., patterns of other names stay type cases
+fn replace_const_pattern(arm: &mut MatchArm, consts: &HashMap<String, ValueExpr>) {
+    let span = arm.type_case.1;
+    let alternatives = match &arm.type_case.0 {
+        TypeExpr::Or(variants) => variants.iter().collect::<Vec<_>>(),
+        _ => vec![&arm.type_case],
+    };
+    let Some(values) = alternatives
+        .into_iter()
+        .map(|(type_expr, _)| match type_expr {
+            TypeExpr::RawTypeName(false, path, None) if path.len() == 1 => consts.get(&path[0]),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+    else {
+        return;
+    };
+
+    let type_case = const_value_type(values[0]).expect("consts are evaluated to literals");
+    if values
+        .iter()
+        .any(|value| const_value_type(value).as_ref() != Some(&type_case))
+    {
+        failure(
+            &span.context.file_name(),
+            "all alternatives of a pattern need to have the same type".to_string(),
+            ("these consts have different types".to_string(), span),
+            vec![],
+            &span.context.file_contents(),
+        )
+    }
+
+    let binding = arm
+        .identifier_binding
+        .get_or_insert_with(|| "value_match".to_string())
+        .clone();
+    let matches = values
+        .into_iter()
+        .map(|value| {
+            let binding = ValueExpr::RawVariable(false, vec![binding.clone()]);
+            let equals = ValueExpr::Equals((binding, span).into(), (value.clone(), span).into());
+            (equals, span)
+        })
+        .reduce(|lhs, rhs| (ValueExpr::Or(lhs.into(), rhs.into()), span))
+        .expect("a pattern has at least one alternative");
+
+    arm.condition = Some(match arm.condition.take() {
+        Some(condition) => (ValueExpr::And(matches.into(), condition.into()), span),
+        None => matches,
+    });
+    arm.type_case = (type_case, span);
+}
+
+// comptime blocks and consts evaluate to values only, generating items (functions, types)
+// at compile time is not supported
+pub fn evaluate_comptime(src_file: &mut SourceFile) {
+    let functions = src_file.function_definitions.clone();
+
+    // a const can use the consts declared before it and the functions of its module
+    let mut consts = HashMap::new();
+    for const_def in src_file.const_definitions.iter_mut() {
+        evaluate_const(const_def, &functions, &consts);
+        consts.insert(const_def.name.clone(), const_def.value_expr.0.clone());
+    }
+
+    for function in src_file.function_definitions.iter_mut() {
+        evaluate_comptime_value_expr(&mut function.value_expr, &functions, &consts);
+    }
+
+    for global in src_file.global_variables.iter_mut() {
+        evaluate_comptime_value_expr(&mut global.initializer, &functions, &consts);
+    }
+
+    for struct_def in src_file.struct_definitions.iter_mut() {
+        for method in struct_def.methods.iter_mut() {
+            evaluate_comptime_value_expr(&mut method.value_expr, &functions, &consts);
+        }
+    }
+
+    for (_, sub_module) in src_file.sub_modules.iter_mut() {
+        evaluate_comptime(sub_module);
+    }
+}
+
+fn evaluate_comptime_value_expr(
+    value_expr: &mut Spanned<ValueExpr>,
+    functions: &[FunctionDefintion],
+    consts: &HashMap<String, ValueExpr>,
+) {
+    let span = value_expr.1;
+    match &mut value_expr.0 {
+        ValueExpr::Comptime(inner) => {
+            evaluate_comptime_value_expr(inner, functions, consts);
+            let value = ConstEvaluator::new(functions, consts)
+                .eval(inner)
+                .and_then(|value| {
+                    if is_const_value(&value) {
+                        Ok(value)
+                    } else {
+                        Err((
+                            "comptime blocks have to result in a literal value".to_string(),
+                            span,
+                        ))
+                    }
+                })
+                .unwrap_or_else(|(msg, at)| {
+                    failure(
+                        &at.context.file_name(),
+                        format!("can't evaluate comptime block: {msg}"),
+                        (msg, at),
+                        vec![("in this comptime block".to_string(), span)],
+                        &at.context.file_contents(),
+                    )
+                });
+            value_expr.0 = value;
+        }
+        ValueExpr::HtmlString(contents) => {
+            for c in contents {
+                if let ValHtmlStringContents::Expr(e) = c {
+                    evaluate_comptime_value_expr(e, functions, consts);
+                }
+            }
+        }
+        ValueExpr::FormattedString(contents) => {
+            for c in contents {
+                if let ValFmtStringContents::Expr(e, _) = c {
+                    evaluate_comptime_value_expr(e, functions, consts);
+                }
+            }
+        }
+        ValueExpr::FunctionCall { target, params, .. } => {
+            evaluate_comptime_value_expr(target, functions, consts);
+            for param in params {
+                evaluate_comptime_value_expr(param, functions, consts);
+            }
+        }
+        ValueExpr::If {
+            condition,
+            then,
+            r#else,
+        } => {
+            evaluate_comptime_value_expr(condition, functions, consts);
+            evaluate_comptime_value_expr(then, functions, consts);
+            if let Some(r#else) = r#else {
+                evaluate_comptime_value_expr(r#else, functions, consts);
+            }
+        }
+        ValueExpr::While { condition, body }
+        | ValueExpr::For {
+            target: condition,
+            body,
+            ..
+        } => {
+            evaluate_comptime_value_expr(condition, functions, consts);
+            evaluate_comptime_value_expr(body, functions, consts);
+        }
+        ValueExpr::Tuple(exprs) | ValueExpr::Block(exprs) | ValueExpr::Array(_, exprs) => {
+            for expr in exprs {
+                evaluate_comptime_value_expr(expr, functions, consts);
+            }
+        }
+        ValueExpr::Duck(fields) | ValueExpr::Struct { fields, .. } => {
+            for (_, expr) in fields {
+                evaluate_comptime_value_expr(expr, functions, consts);
+            }
+        }
+        ValueExpr::Map(_, entries) => {
+            for (key, value) in entries {
+                evaluate_comptime_value_expr(key, functions, consts);
+                evaluate_comptime_value_expr(value, functions, consts);
+            }
+        }
+        ValueExpr::FieldAccess { target_obj, .. }
+        | ValueExpr::OptionalFieldAccess { target_obj, .. } => {
+            evaluate_comptime_value_expr(target_obj, functions, consts);
+        }
+        ValueExpr::Return(Some(expr))
+        | ValueExpr::Break(Some(expr))
+        | ValueExpr::Loop { body: expr, .. }
+        | ValueExpr::BoolNegate(expr)
+        | ValueExpr::BitNegate(expr)
+        | ValueExpr::TypeOf(expr)
+        | ValueExpr::Cast(expr, _)
+        | ValueExpr::Negate(expr)
+        | ValueExpr::Defer(expr)
+        | ValueExpr::Spawn(expr)
+        | ValueExpr::Receive(expr)
+        | ValueExpr::Spread(expr)
+        | ValueExpr::Labeled(_, expr)
+        | ValueExpr::Propagate(expr)
+        | ValueExpr::MakeChan {
+            capacity: Some(expr),
+            ..
+        } => {
+            evaluate_comptime_value_expr(expr, functions, consts);
+        }
+        ValueExpr::VarAssign(assignment) => {
+            evaluate_comptime_value_expr(&mut assignment.0.target, functions, consts);
+            evaluate_comptime_value_expr(&mut assignment.0.value_expr, functions, consts);
+        }
+        ValueExpr::VarDecl(decl) => {
+            evaluate_comptime_value_expr(&mut decl.0.initializer, functions, consts);
+        }
+        ValueExpr::Add(lhs, rhs)
+        | ValueExpr::Sub(lhs, rhs)
+        | ValueExpr::Mul(lhs, rhs)
+        | ValueExpr::Div(lhs, rhs)
+        | ValueExpr::Mod(lhs, rhs)
+        | ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        }
+        | ValueExpr::Equals(lhs, rhs)
+        | ValueExpr::NotEquals(lhs, rhs)
+        | ValueExpr::LessThan(lhs, rhs)
+        | ValueExpr::LessThanOrEquals(lhs, rhs)
+        | ValueExpr::GreaterThan(lhs, rhs)
+        | ValueExpr::GreaterThanOrEquals(lhs, rhs)
+        | ValueExpr::And(lhs, rhs)
+        | ValueExpr::Or(lhs, rhs)
+        | ValueExpr::Update(lhs, rhs)
+        | ValueExpr::Coalesce(lhs, rhs)
+        | ValueExpr::ArrayAccess(lhs, rhs)
+        | ValueExpr::Send(lhs, rhs) => {
+            evaluate_comptime_value_expr(lhs, functions, consts);
+            evaluate_comptime_value_expr(rhs, functions, consts);
+        }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            evaluate_comptime_value_expr(target, functions, consts);
+            for bound in [start, end].into_iter().flatten() {
+                evaluate_comptime_value_expr(bound, functions, consts);
+            }
+        }
+        ValueExpr::Lambda(lambda) => {
+            evaluate_comptime_value_expr(&mut lambda.value_expr, functions, consts);
+        }
+        ValueExpr::Match {
+            value_expr,
+            arms,
+            else_arm,
+        } => {
+            evaluate_comptime_value_expr(value_expr, functions, consts);
+            for arm in arms {
+                replace_const_pattern(arm, consts);
+                if let Some(condition) = &mut arm.condition {
+                    evaluate_comptime_value_expr(condition, functions, consts);
+                }
+                evaluate_comptime_value_expr(&mut arm.value_expr, functions, consts);
+            }
+            if let Some(arm) = else_arm {
+                if let Some(condition) = &mut arm.condition {
+                    evaluate_comptime_value_expr(condition, functions, consts);
+                }
+                evaluate_comptime_value_expr(&mut arm.value_expr, functions, consts);
+            }
+        }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                evaluate_comptime_value_expr(&mut arm.case, functions, consts);
+                evaluate_comptime_value_expr(&mut arm.value_expr, functions, consts);
+            }
+            if let Some(else_arm) = else_arm {
+                evaluate_comptime_value_expr(else_arm, functions, consts);
+            }
+        }
+        ValueExpr::Recover { body, handler, .. } => {
+            evaluate_comptime_value_expr(body, functions, consts);
+            evaluate_comptime_value_expr(handler, functions, consts);
+        }
+        ValueExpr::Int(..)
+        | ValueExpr::String(..)
+        | ValueExpr::Bool(..)
+        | ValueExpr::Float(..)
+        | ValueExpr::Char(..)
+        | ValueExpr::RawVariable(..)
+        | ValueExpr::Variable(..)
+        | ValueExpr::Break(None)
+        | ValueExpr::Continue
+        | ValueExpr::Return(None)
+        | ValueExpr::Tag(..)
+        | ValueExpr::MakeChan { capacity: None, .. }
+        | ValueExpr::InlineGo(..) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chumsky::Parser;
+
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_file_parser::source_file_parser, source_map::FileId,
+        value_parser::empty_range,
+    };
+
+    use super::*;
+
+    fn main_body(src: &str) -> ValueExpr {
+        let tokens = lex_parser(FileId::EMPTY).parse(src).into_result().unwrap();
+        let mut src_file = source_file_parser("".into(), make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+
+        evaluate_comptime(&mut src_file);
+
+        let main = src_file
+            .function_definitions
+            .into_iter()
+            .find(|function| function.name == "main")
+            .unwrap();
+        let ValueExpr::Block(mut exprs) = main.value_expr.0 else {
+            panic!("main has to be a block")
+        };
+        exprs.remove(0).0
+    }
+
+    #[test]
+    fn test_evaluate_comptime() {
+        let test_cases = vec![
+            ("fn main() { comptime { 1 + 2 * 3 } }", ValueExpr::Int(7)),
+            (
+                "fn main() { comptime { 7 % 4 == 3 } }",
+                ValueExpr::Bool(true),
+            ),
+            (
+                "fn main() { comptime { \"a\" + \"b\" } }",
+                ValueExpr::String("ab".into(), true),
+            ),
+            (
+                "fn main() { comptime { let x = 5; f\"x is {x}\" } }",
+                ValueExpr::String("x is 5".into(), true),
+            ),
+            (
+                "fn main() { comptime { let i = 0; let s = 0; while (i < 5) { i = i + 1; s = s + i; } s } }",
+                ValueExpr::Int(15),
+            ),
+            (
+                "fn fib(n: Int) -> Int { if (n < 2) { return n; } fib(n - 1) + fib(n - 2) } fn main() { comptime { fib(10) } }",
+                ValueExpr::Int(55),
+            ),
+            (
+                "fn main() { comptime { if (true) { 1 } else { 2 } } }",
+                ValueExpr::Int(1),
+            ),
+            (
+                "fn main() { comptime { let i = 1; loop { if (i > 50) { break i; } i = i * 2; } } }",
+                ValueExpr::Int(64),
+            ),
+            (
+                "fn squares(n: Int) -> Int[] { let a = .Int[]; for i in 0..n { a.push(i * i); } a } fn main() { comptime { squares(4)[3] } }",
+                ValueExpr::Int(9),
+            ),
+            (
+                "fn main() { comptime { let a = [1, 2, 3]; a[0] = 10; let s = 0; for x in a { s = s + x; } s } }",
+                ValueExpr::Int(15),
+            ),
+            (
+                "fn main() { comptime { std::string::to_upper(\"quack\") + std::string::from_int(2) } }",
+                ValueExpr::String("QUACK2".into(), true),
+            ),
+        ];
+
+        for (src, expected) in test_cases {
+            assert_eq!(main_body(src), expected, "{src}");
+        }
+    }
+
+    fn evaluated_consts(src: &str) -> Vec<(String, TypeExpr, ValueExpr)> {
+        let tokens = lex_parser(FileId::EMPTY).parse(src).into_result().unwrap();
+        let mut src_file = source_file_parser("".into(), make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .unwrap();
+
+        evaluate_comptime(&mut src_file);
+
+        src_file
+            .const_definitions
+            .into_iter()
+            .map(|const_def| {
+                let (type_expr, _) = const_def.type_expr.unwrap();
+                (const_def.name, type_expr, const_def.value_expr.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate_consts() {
+        let test_cases = vec![
+            (
+                "const MAX = 1024 * 64;",
+                vec![("MAX", TypeExpr::Int, ValueExpr::Int(65536))],
+            ),
+            (
+                "const A: String = \"a\" + \"b\"; const B = A + \"c\";",
+                vec![
+                    ("A", TypeExpr::String, ValueExpr::String("ab".into(), true)),
+                    ("B", TypeExpr::String, ValueExpr::String("abc".into(), true)),
+                ],
+            ),
+            (
+                "fn double(x: Int) -> Int { x * 2 } const X = double(21); const Y = X > 40;",
+                vec![
+                    ("X", TypeExpr::Int, ValueExpr::Int(42)),
+                    ("Y", TypeExpr::Bool, ValueExpr::Bool(true)),
+                ],
+            ),
+            (
+                "const HALF: Float = 1.0 / 2.0; const C = 'c';",
+                vec![
+                    ("HALF", TypeExpr::Float, ValueExpr::Float(0.5)),
+                    ("C", TypeExpr::Char, ValueExpr::Char('c')),
+                ],
+            ),
+            (
+                "fn table() -> Int[] { [1, 2, 3] } const N = table().len(); const S = std::string::from_int(N) + \"!\";",
+                vec![
+                    ("N", TypeExpr::Int, ValueExpr::Int(3)),
+                    ("S", TypeExpr::String, ValueExpr::String("3!".into(), true)),
+                ],
+            ),
+        ];
+
+        for (src, expected) in test_cases {
+            let expected = expected
+                .into_iter()
+                .map(|(name, type_expr, value)| (name.to_string(), type_expr, value))
+                .collect::<Vec<_>>();
+            assert_eq!(evaluated_consts(src), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_consts_in_comptime_and_patterns() {
+        assert_eq!(
+            main_body("const MAX = 10; fn main() { comptime { MAX * 2 } }"),
+            ValueExpr::Int(20)
+        );
+
+        let ValueExpr::Match { arms, .. } =
+            main_body("const LOW = 1; const HIGH = 9; fn main() { match 5 { LOW | HIGH => 0 } }")
+        else {
+            panic!("main has to start with the match")
+        };
+        assert_eq!(arms[0].type_case.0, TypeExpr::Int);
+        assert!(arms[0].condition.is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "the const X is declared as String, but has the type Int")]
+    fn test_const_with_wrong_type() {
+        evaluated_consts("const X: String = 1 + 2;");
+    }
+
+    #[test]
+    #[should_panic(expected = "can't evaluate the const X")]
+    fn test_const_without_literal_value() {
+        evaluated_consts("const X = [1, 2];");
+    }
+
+    #[test]
+    #[should_panic(expected = "index 2 is out of bounds for an array of length 2")]
+    fn test_const_index_out_of_bounds() {
+        evaluated_consts("const X = [1, 2][2];");
+    }
+
+    #[test]
+    #[should_panic(expected = "std::string::to_int: \"12a\" is not an Int")]
+    fn test_comptime_to_int_of_invalid_string() {
+        evaluated_consts("const X = std::string::to_int(\"12a\");");
+    }
+}
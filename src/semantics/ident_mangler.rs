@@ -3,8 +3,11 @@ use std::collections::HashMap;
 use tree_sitter::{Node, Parser};
 
 use crate::parse::{
+    SS, Spanned,
     duckx_component_parser::DuckxComponent,
-    function_parser::LambdaFunctionExpr,
+    failure,
+    function_parser::{LambdaFunctionExpr, extension_call_marker, extension_method_name},
+    lexer::map_go_interpolations,
     tsx_component_parser::{Edit, TsxComponent, TsxSourceUnit, do_edits},
     type_parser::{Duck, TypeExpr},
     value_parser::{ValFmtStringContents, ValHtmlStringContents, ValueExpr},
@@ -12,6 +15,8 @@ use crate::parse::{
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MangleEnv {
+    // an imported name and the path of what it stands for, use a::b::{c} maps c to a::b::c and
+    // use a::b as d maps d to a::b. the bool is set for global paths
     pub imports: HashMap<String, (bool, Vec<String>)>,
     pub sub_mods: Vec<String>,
     pub global_prefix: Vec<String>,
@@ -19,6 +24,9 @@ pub struct MangleEnv {
     pub types: Vec<Vec<String>>,
     pub tsx_components: Vec<String>,
     pub duckx_components: Vec<String>,
+    // the path of every pub use and the path of what it re-exports, relative to the root of the
+    // flattened file
+    pub re_exports: HashMap<Vec<String>, Vec<String>>,
 }
 
 pub const MANGLE_SEP: &str = "_____";
@@ -34,7 +42,43 @@ pub fn unmangle(s: &str) -> Vec<String> {
     s.split(MANGLE_SEP).map(String::from).collect()
 }
 
+// the paths a path goes through when its re-exports are followed, starting with the path itself
+// and ending with the path of the item. None if the re-exports form a cycle
+pub fn re_export_chain(
+    re_exports: &HashMap<Vec<String>, Vec<String>>,
+    path: &[String],
+) -> Option<Vec<Vec<String>>> {
+    let mut chain = vec![path.to_vec()];
+    loop {
+        let current = chain.last().unwrap();
+        // a path can go through a re-exported module, a::b::c goes through the pub use of a::b
+        let Some(next) = (1..=current.len()).rev().find_map(|len| {
+            re_exports.get(&current[..len]).map(|target| {
+                target
+                    .iter()
+                    .chain(&current[len..])
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+        }) else {
+            return Some(chain);
+        };
+        if chain.contains(&next) {
+            return None;
+        }
+        chain.push(next);
+    }
+}
+
 impl MangleEnv {
+    // cycles are reported before the mangling starts, a path in one is left as it is
+    fn follow_re_exports(&self, path: Vec<String>) -> Vec<String> {
+        match re_export_chain(&self.re_exports, &path) {
+            Some(mut chain) => chain.pop().unwrap(),
+            None => path,
+        }
+    }
+
     pub fn mangle_component(&self, prefix: &[String], comp: &str) -> Option<Vec<String>> {
         let is_global = comp.starts_with("::");
         let ident = if is_global && comp.len() >= 3 {
@@ -59,9 +103,9 @@ impl MangleEnv {
             }
 
             res.extend(import_path);
-            res.extend(ident);
+            res.extend(ident.into_iter().skip(1));
 
-            return Some(res);
+            return Some(self.follow_re_exports(res));
         }
 
         if self.tsx_components.contains(ident.first().unwrap()) {
@@ -72,7 +116,7 @@ impl MangleEnv {
                 x.extend_from_slice(prefix);
             }
             x.extend_from_slice(&ident);
-            return Some(x);
+            return Some(self.follow_re_exports(x));
         }
 
         None
@@ -91,26 +135,25 @@ impl MangleEnv {
     }
 
     pub fn resolve_import(&self, mut sym: String) -> Option<(bool, Vec<String>)> {
-        let mut result = None;
+        let mut result: Option<(bool, Vec<String>)> = None;
 
         if self.sub_mods.contains(&sym) {
-            return Some((false, vec![]));
+            return Some((false, vec![sym]));
         }
 
+        // the path of an import can start with an imported name again
         while let Some((is_glob, import_path)) = self.imports.get(&sym) {
-            result = result
-                .map(|(g, p)| {
-                    (g || *is_glob, {
-                        let mut import_path = import_path.to_owned();
-                        import_path.extend(p);
-                        import_path
-                    })
-                })
-                .or(Some((*is_glob, import_path.to_owned())));
-            if import_path.is_empty() {
-                break;
+            result = Some(match result {
+                Some((g, p)) => (
+                    g || *is_glob,
+                    import_path.iter().chain(&p[1..]).cloned().collect(),
+                ),
+                None => (*is_glob, import_path.to_owned()),
+            });
+            match import_path.first() {
+                Some(first) if *first != sym => sym = first.clone(),
+                _ => break,
             }
-            sym = import_path.first().unwrap().clone();
         }
 
         result
@@ -136,9 +179,9 @@ impl MangleEnv {
             }
 
             res.extend(import_path);
-            res.extend_from_slice(ident);
+            res.extend_from_slice(&ident[1..]);
 
-            return Some(res);
+            return Some(self.follow_re_exports(res));
         }
 
         if self.is_top_level_type(ident.first()?) {
@@ -149,7 +192,7 @@ impl MangleEnv {
                 x.extend_from_slice(prefix);
             }
             x.extend_from_slice(ident);
-            return Some(x);
+            return Some(self.follow_re_exports(x));
         }
 
         None
@@ -177,21 +220,48 @@ impl MangleEnv {
             }
 
             res.extend(import_path);
-            res.extend_from_slice(ident);
+            res.extend_from_slice(&ident[1..]);
 
-            return Some(res);
+            return Some(self.follow_re_exports(res));
         }
 
         if self.is_top_level_ident(ident.first()?) {
             let mut x = Vec::new();
             x.extend_from_slice(prefix);
             x.extend_from_slice(ident);
-            return Some(x);
+            return Some(self.follow_re_exports(x));
         }
 
         None
     }
 
+    // the path of the extension method .name(..) where it's defined in this module or imported
+    pub fn extension_path(&self, prefix: &[String], name: &str) -> Option<Vec<String>> {
+        let is_defined = self.names[0]
+            .iter()
+            .any(|defined| extension_method_name(defined) == Some(name));
+        if is_defined {
+            let mut path = prefix.to_vec();
+            path.push(name.to_string());
+            return Some(path);
+        }
+
+        let (is_glob, import_path) = self.resolve_import(name.to_string())?;
+        let mut path = if is_glob {
+            self.global_prefix.clone()
+        } else {
+            prefix.to_vec()
+        };
+        path.extend(import_path);
+        Some(self.follow_re_exports(path))
+    }
+
+    // whether an item, import or local variable of this name can be used here
+    pub fn is_visible(&self, ident: &String) -> bool {
+        self.names.iter().any(|names| names.contains(ident))
+            || self.resolve_import(ident.clone()).is_some()
+    }
+
     pub fn is_top_level_type(&self, ident: &String) -> bool {
         for i in 1..self.types.len() {
             if self.types[i].contains(ident) {
@@ -255,6 +325,55 @@ impl MangleEnv {
     }
 }
 
+// assert(cond, msg?), panic(msg) and todo() are calls into std::error with the source location
+// of the call appended, panic_at never returns. they're only builtins where no item, import or
+// variable of that name is visible
+const BUILTINS: [&str; 3] = ["assert", "panic", "todo"];
+
+fn builtin_call(name: &str, mut params: Vec<Spanned<ValueExpr>>, span: SS) -> ValueExpr {
+    let (target, arity, default_message) = match name {
+        "assert" => ("assert_at", 1..=2, Some("assertion failed")),
+        "panic" => ("panic_at", 1..=1, None),
+        "todo" => ("panic_at", 0..=0, Some("not yet implemented")),
+        _ => unreachable!(),
+    };
+
+    if !arity.contains(&params.len()) {
+        failure(
+            &span.context.file_name(),
+            format!("wrong number of arguments for {name}"),
+            (
+                format!(
+                    "{name} takes {} to {} arguments",
+                    arity.start(),
+                    arity.end()
+                ),
+                span,
+            ),
+            vec![],
+            &span.context.file_contents(),
+        );
+    }
+
+    let string = |s: String| (ValueExpr::String(s, true), span);
+    if let Some(default_message) = default_message
+        && params.len() == *arity.start()
+    {
+        params.push(string(default_message.to_string()));
+    }
+    params.push(string(span.context.location(span.start)));
+
+    ValueExpr::FunctionCall {
+        target: (
+            ValueExpr::RawVariable(true, vec!["std".into(), "error".into(), target.into()]),
+            span,
+        )
+            .into(),
+        params,
+        type_params: None,
+    }
+}
+
 pub fn mangle_type_expression(
     type_expr: &mut TypeExpr,
     prefix: &Vec<String>,
@@ -302,9 +421,13 @@ pub fn mangle_type_expression(
                 mangle_type_expression(&mut t.0, prefix, mangle_env);
             }
         }
-        TypeExpr::Array(t) => {
+        TypeExpr::Array(t) | TypeExpr::Chan(t) => {
             mangle_type_expression(&mut t.0, prefix, mangle_env);
         }
+        TypeExpr::Map(key, value) => {
+            mangle_type_expression(&mut key.0, prefix, mangle_env);
+            mangle_type_expression(&mut value.0, prefix, mangle_env);
+        }
         _ => {}
     }
 }
@@ -369,11 +492,44 @@ pub fn mangle_value_expr(
         | ValueExpr::Tag(..)
         | ValueExpr::Char(..) => {}
         ValueExpr::Continue => {}
-        ValueExpr::Break => {}
-        ValueExpr::ArrayAccess(target, idx) => {
+        ValueExpr::Break(None) => {}
+        ValueExpr::ArrayAccess(target, idx) | ValueExpr::Send(target, idx) => {
             mangle_value_expr(&mut target.0, global_prefix, prefix, mangle_env);
             mangle_value_expr(&mut idx.0, global_prefix, prefix, mangle_env);
         }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            mangle_value_expr(&mut target.0, global_prefix, prefix, mangle_env);
+            for bound in [start, end].into_iter().flatten() {
+                mangle_value_expr(&mut bound.0, global_prefix, prefix, mangle_env);
+            }
+        }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                mangle_value_expr(&mut arm.case.0, global_prefix, prefix, mangle_env);
+                mangle_env.push_idents();
+                if let Some(binding) = &arm.binding {
+                    mangle_env.insert_ident(binding.clone());
+                }
+                mangle_value_expr(&mut arm.value_expr.0, global_prefix, prefix, mangle_env);
+                mangle_env.pop_idents();
+            }
+            if let Some(else_arm) = else_arm {
+                mangle_value_expr(&mut else_arm.0, global_prefix, prefix, mangle_env);
+            }
+        }
+        ValueExpr::Recover {
+            body,
+            binding,
+            handler,
+        } => {
+            mangle_value_expr(&mut body.0, global_prefix, prefix, mangle_env);
+            mangle_env.push_idents();
+            mangle_env.insert_ident(binding.clone());
+            mangle_value_expr(&mut handler.0, global_prefix, prefix, mangle_env);
+            mangle_env.pop_idents();
+        }
         ValueExpr::Match {
             value_expr,
             arms,
@@ -386,6 +542,9 @@ pub fn mangle_value_expr(
                 if let Some(identifier) = &arm.identifier_binding {
                     mangle_env.insert_ident(identifier.clone());
                 }
+                if let Some(condition) = &mut arm.condition {
+                    mangle_value_expr(&mut condition.0, global_prefix, prefix, mangle_env);
+                }
                 mangle_value_expr(&mut arm.value_expr.0, global_prefix, prefix, mangle_env);
                 mangle_env.pop_idents();
             }
@@ -396,13 +555,16 @@ pub fn mangle_value_expr(
                 if let Some(identifier) = &arm.identifier_binding {
                     mangle_env.insert_ident(identifier.clone());
                 }
+                if let Some(condition) = &mut arm.condition {
+                    mangle_value_expr(&mut condition.0, global_prefix, prefix, mangle_env);
+                }
                 mangle_value_expr(&mut arm.value_expr.0, global_prefix, prefix, mangle_env);
                 mangle_env.pop_idents();
             }
         }
         ValueExpr::FormattedString(contents) => {
             for c in contents {
-                if let ValFmtStringContents::Expr(e) = c {
+                if let ValFmtStringContents::Expr(e, _) = c {
                     mangle_value_expr(&mut e.0, global_prefix, prefix, mangle_env);
                 }
             }
@@ -416,7 +578,27 @@ pub fn mangle_value_expr(
                 mangle_value_expr(&mut expr.0, global_prefix, prefix, mangle_env);
             }
         }
+        ValueExpr::Map(ty, entries) => {
+            if let Some(ty) = ty {
+                mangle_type_expression(&mut ty.0, prefix, mangle_env);
+            }
+
+            for (key, value) in entries {
+                mangle_value_expr(&mut key.0, global_prefix, prefix, mangle_env);
+                mangle_value_expr(&mut value.0, global_prefix, prefix, mangle_env);
+            }
+        }
         ValueExpr::InlineGo(t) => {
+            // ${name} is mangled like any other duck variable, the tree-sitter pass below only
+            // guesses which go identifiers refer to duck ones
+            *t = map_go_interpolations(t, |name| {
+                let mangled = mangle_env
+                    .mangle_ident(false, prefix, &[name.to_string()])
+                    .map(|path| mangle(&path))
+                    .unwrap_or_else(|| name.to_string());
+                format!("${{{mangled}}}")
+            });
+
             let mut parser = Parser::new();
             parser
                 .set_language(&tree_sitter_go::LANGUAGE.into())
@@ -521,6 +703,24 @@ pub fn mangle_value_expr(
             type_params,
         } => {
             // TODO: type params
+            if type_params.is_none()
+                && let ValueExpr::RawVariable(false, path) = &target.0
+                && let [name] = path.as_slice()
+                && BUILTINS.contains(&name.as_str())
+                && !mangle_env.is_visible(name)
+            {
+                let name = name.clone();
+                let span = target.1;
+                *value_expr = builtin_call(&name, std::mem::take(params), span);
+                mangle_value_expr(value_expr, global_prefix, prefix, mangle_env);
+                return;
+            }
+
+            if let ValueExpr::FieldAccess { field_name, .. } = &mut target.0
+                && let Some(path) = mangle_env.extension_path(prefix, field_name)
+            {
+                *field_name = extension_call_marker(&mangle(&path));
+            }
             mangle_value_expr(&mut target.0, global_prefix, prefix, mangle_env);
             params.iter_mut().for_each(|param| {
                 mangle_value_expr(&mut param.0, global_prefix, prefix, mangle_env)
@@ -534,6 +734,13 @@ pub fn mangle_value_expr(
         ValueExpr::RawVariable(is_global, path) => {
             if let Some(mangled) = mangle_env.mangle_ident(*is_global, prefix, path) {
                 *path = mangled;
+            } else if path.len() > 1
+                && let Some(mangled) = mangle_env.mangle_type(*is_global, prefix, path)
+            {
+                // Enum::Variant, the type resolution turns it into a struct of the variant
+                let mut m = mangle_env.global_prefix.clone();
+                m.extend(mangled);
+                *path = m;
             }
             *value_expr = ValueExpr::Variable(true, mangle(path), None);
         }
@@ -560,6 +767,22 @@ pub fn mangle_value_expr(
             mangle_value_expr(&mut body.0, global_prefix, prefix, mangle_env);
             mangle_env.pop_idents();
         }
+        ValueExpr::For {
+            ident,
+            target,
+            body,
+        } => {
+            mangle_value_expr(&mut target.0, global_prefix, prefix, mangle_env);
+            mangle_env.push_idents();
+            mangle_env.insert_ident(ident.clone());
+            mangle_value_expr(&mut body.0, global_prefix, prefix, mangle_env);
+            mangle_env.pop_idents();
+        }
+        ValueExpr::Loop { body, .. } => {
+            mangle_env.push_idents();
+            mangle_value_expr(&mut body.0, global_prefix, prefix, mangle_env);
+            mangle_env.pop_idents();
+        }
         ValueExpr::Tuple(value_exprs) => value_exprs.iter_mut().for_each(|value_expr| {
             mangle_value_expr(&mut value_expr.0, global_prefix, prefix, mangle_env)
         }),
@@ -578,8 +801,8 @@ pub fn mangle_value_expr(
             fields,
             type_params,
         } => {
-            if let Some(mangled) = mangle_env.mangle_type(false, prefix, std::slice::from_ref(name))
-            {
+            // Enum::Variant { .. } is parsed with the mangled path as its name
+            if let Some(mangled) = mangle_env.mangle_type(false, prefix, &unmangle(name)) {
                 let mut m = mangle_env.global_prefix.clone();
                 m.extend(mangled);
                 *name = mangle(&m);
@@ -595,10 +818,11 @@ pub fn mangle_value_expr(
                 }
             }
         }
-        ValueExpr::FieldAccess { target_obj, .. } => {
+        ValueExpr::FieldAccess { target_obj, .. }
+        | ValueExpr::OptionalFieldAccess { target_obj, .. } => {
             mangle_value_expr(&mut target_obj.0, global_prefix, prefix, mangle_env);
         }
-        ValueExpr::Return(Some(value_expr)) => {
+        ValueExpr::Return(Some(value_expr)) | ValueExpr::Break(Some(value_expr)) => {
             mangle_value_expr(&mut value_expr.0, global_prefix, prefix, mangle_env)
         }
         ValueExpr::VarAssign(assignment) => {
@@ -637,6 +861,16 @@ pub fn mangle_value_expr(
         ValueExpr::Mul(lhs, rhs)
         | ValueExpr::Sub(lhs, rhs)
         | ValueExpr::Mod(lhs, rhs)
+        | ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        }
         | ValueExpr::Div(lhs, rhs) => {
             mangle_value_expr(&mut lhs.0, global_prefix, prefix, mangle_env);
             mangle_value_expr(&mut rhs.0, global_prefix, prefix, mangle_env);
@@ -648,12 +882,39 @@ pub fn mangle_value_expr(
         | ValueExpr::GreaterThan(lhs, rhs)
         | ValueExpr::GreaterThanOrEquals(lhs, rhs)
         | ValueExpr::And(lhs, rhs)
-        | ValueExpr::Or(lhs, rhs) => {
+        | ValueExpr::Or(lhs, rhs)
+        | ValueExpr::Update(lhs, rhs)
+        | ValueExpr::Coalesce(lhs, rhs) => {
             mangle_value_expr(&mut lhs.0, global_prefix, prefix, mangle_env);
             mangle_value_expr(&mut rhs.0, global_prefix, prefix, mangle_env);
         }
-        ValueExpr::BoolNegate(value_expr) => {
+        ValueExpr::Comptime(..) => {
+            panic!("compiler error: comptime blocks should be evaluated by now")
+        }
+        ValueExpr::BoolNegate(value_expr)
+        | ValueExpr::BitNegate(value_expr)
+        | ValueExpr::TypeOf(value_expr)
+        | ValueExpr::Negate(value_expr)
+        | ValueExpr::Defer(value_expr)
+        | ValueExpr::Spawn(value_expr)
+        | ValueExpr::Receive(value_expr)
+        | ValueExpr::Spread(value_expr)
+        | ValueExpr::Labeled(_, value_expr)
+        | ValueExpr::Propagate(value_expr) => {
+            mangle_value_expr(&mut value_expr.0, global_prefix, prefix, mangle_env);
+        }
+        ValueExpr::MakeChan {
+            element_type,
+            capacity,
+        } => {
+            mangle_type_expression(&mut element_type.0, prefix, mangle_env);
+            if let Some(capacity) = capacity {
+                mangle_value_expr(&mut capacity.0, global_prefix, prefix, mangle_env);
+            }
+        }
+        ValueExpr::Cast(value_expr, type_expr) => {
             mangle_value_expr(&mut value_expr.0, global_prefix, prefix, mangle_env);
+            mangle_type_expression(&mut type_expr.0, prefix, mangle_env);
         }
     }
 }
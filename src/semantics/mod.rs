@@ -1,3 +1,6 @@
+pub mod const_eval;
 pub mod ident_mangler;
+pub mod recursive_types;
 pub mod type_resolve;
 pub mod typechecker;
+pub mod visibility;
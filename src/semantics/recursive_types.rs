@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    parse::{
+        SS, failure,
+        source_file_parser::SourceFile,
+        struct_parser::StructDefinition,
+        type_parser::{Duck, TypeDefinition, TypeExpr},
+    },
+    semantics::{ident_mangler::unmangle, visibility::for_each_type_name},
+};
+
+fn display_name(name: &str) -> String {
+    unmangle(name).join("::")
+}
+
+fn recursive_type_failure(msg: String, label: String, span: SS) -> ! {
+    failure(
+        &span.context.file_name(),
+        msg,
+        (label, span),
+        vec![],
+        &span.context.file_contents(),
+    )
+}
+
+// structs and enums can refer to themselves, the go backend keeps their values behind a pointer.
+// a type alias is replaced by what it stands for, so one that refers to itself would never end
+struct TypeGraph<'a> {
+    aliases: HashMap<&'a str, &'a TypeDefinition>,
+    structs: HashMap<&'a str, &'a StructDefinition>,
+}
+
+impl TypeGraph<'_> {
+    fn check_alias(&self, name: &str, path: &mut Vec<String>, done: &mut HashSet<String>) {
+        if done.contains(name) {
+            return;
+        }
+
+        let mut uses = Vec::new();
+        for_each_type_name(&self.aliases[name].type_expression, &mut |used, span| {
+            if self.aliases.contains_key(used) {
+                uses.push((used.to_string(), span));
+            }
+        });
+
+        path.push(name.to_string());
+        for (used, span) in uses {
+            if let Some(start) = path.iter().position(|alias| *alias == used) {
+                let cycle = path[start..]
+                    .iter()
+                    .chain([&used])
+                    .map(|alias| display_name(alias))
+                    .collect::<Vec<_>>();
+                recursive_type_failure(
+                    format!(
+                        "the type {} refers to itself, only structs and enums can be recursive",
+                        cycle[0]
+                    ),
+                    if cycle.len() == 2 {
+                        format!("{} is used inside of its own definition", cycle[0])
+                    } else {
+                        format!("this closes the cycle {}", cycle.join(" -> "))
+                    },
+                    span,
+                );
+            }
+            self.check_alias(&used, path, done);
+        }
+        path.pop();
+        done.insert(name.to_string());
+    }
+
+    // the structs every value of the type holds. a value of an or type only holds what all of its
+    // variants hold, arrays, maps, channels and functions can always be empty
+    fn contained_structs(&self, type_expr: &TypeExpr) -> HashSet<String> {
+        match type_expr {
+            TypeExpr::TypeName(_, name, _) | TypeExpr::Struct(name)
+                if self.structs.contains_key(name.as_str()) =>
+            {
+                HashSet::from([name.clone()])
+            }
+            TypeExpr::TypeName(_, name, _) => self
+                .aliases
+                .get(name.as_str())
+                .map(|alias| self.contained_structs(&alias.type_expression.0))
+                .unwrap_or_default(),
+            TypeExpr::Tuple(type_exprs) => type_exprs
+                .iter()
+                .flat_map(|type_expr| self.contained_structs(&type_expr.0))
+                .collect(),
+            TypeExpr::Duck(Duck { fields }) => fields
+                .iter()
+                .flat_map(|field| self.contained_structs(&field.type_expr.0))
+                .collect(),
+            TypeExpr::Or(variants) => variants
+                .iter()
+                .map(|variant| self.contained_structs(&variant.0))
+                .reduce(|all, held| all.intersection(&held).cloned().collect())
+                .unwrap_or_default(),
+            _ => HashSet::new(),
+        }
+    }
+
+    // path holds the structs on the way here and the field each of them continues with
+    fn check_struct(&self, name: &str, path: &mut Vec<(String, SS)>, done: &mut HashSet<String>) {
+        if done.contains(name) {
+            return;
+        }
+
+        for field in &self.structs[name].fields {
+            let mut held = self
+                .contained_structs(&field.type_expr.0)
+                .into_iter()
+                .collect::<Vec<_>>();
+            held.sort();
+
+            for held in held {
+                path.push((name.to_string(), field.type_expr.1));
+                if let Some(start) = path.iter().position(|(name, _)| *name == held) {
+                    let (start_name, span) = &path[start];
+                    let next = path
+                        .get(start + 1)
+                        .map_or(start_name.as_str(), |(name, _)| name.as_str());
+                    recursive_type_failure(
+                        format!(
+                            "the struct {} always contains itself, so its values would be \
+                             infinitely large",
+                            display_name(start_name)
+                        ),
+                        if next == start_name {
+                            format!(
+                                "this always holds a {}, make it optional with ? or use an array",
+                                display_name(start_name)
+                            )
+                        } else {
+                            format!(
+                                "this always holds a {}, which contains a {}",
+                                display_name(next),
+                                display_name(start_name)
+                            )
+                        },
+                        *span,
+                    );
+                }
+                self.check_struct(&held, path, done);
+                path.pop();
+            }
+        }
+        done.insert(name.to_string());
+    }
+}
+
+pub fn check_recursive_types(source_file: &SourceFile) {
+    let graph = TypeGraph {
+        aliases: source_file
+            .type_definitions
+            .iter()
+            .map(|type_definition| (type_definition.name.as_str(), type_definition))
+            .collect(),
+        structs: source_file
+            .struct_definitions
+            .iter()
+            .map(|struct_definition| (struct_definition.name.as_str(), struct_definition))
+            .collect(),
+    };
+
+    // sorted, so the same cycle is always reported at the same place
+    let mut aliases = graph.aliases.keys().copied().collect::<Vec<_>>();
+    aliases.sort();
+    let mut done = HashSet::new();
+    for alias in aliases {
+        graph.check_alias(alias, &mut Vec::new(), &mut done);
+    }
+
+    let mut structs = graph.structs.keys().copied().collect::<Vec<_>>();
+    structs.sort();
+    let mut done = HashSet::new();
+    for struct_name in structs {
+        graph.check_struct(struct_name, &mut Vec::new(), &mut done);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chumsky::Parser;
+
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_file_parser::source_file_parser, source_map,
+        value_parser::empty_range,
+    };
+
+    use super::*;
+
+    fn check(src: &str) {
+        let file_id = source_map::add_test_file("recursive_types_test", src);
+        let tokens = lex_parser(file_id).parse(src).into_result().expect(src);
+        let source_file = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .expect(src);
+        check_recursive_types(&source_file.flatten(&vec![], false));
+    }
+
+    #[test]
+    fn test_finite_recursive_types() {
+        let test_cases = vec![
+            "struct Node = { value: Int, next: Node? };",
+            "struct Tree = { children: Tree[] };",
+            "enum List = { Nil, Cons(Int, List) };",
+            "enum Json = { Null, Number(Int), Array(Json[]), Object(map<String, Json>) };",
+            "struct A = { b: B? }; struct B = { a: A };",
+            "type Pair = (Int, Int); type Pairs = { first: Pair, second: Pair };",
+            "struct Visitor = { visit: fn(Visitor) -> Int };",
+        ];
+
+        for src in test_cases {
+            check(src);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "the type Json refers to itself")]
+    fn test_recursive_alias() {
+        check("type Json = Int | String | Json[];");
+    }
+
+    #[test]
+    #[should_panic(expected = "the type A refers to itself")]
+    fn test_alias_cycle() {
+        check("type A = { b: B }; type B = (Int, A);");
+    }
+
+    #[test]
+    #[should_panic(expected = "the struct Node always contains itself")]
+    fn test_infinitely_large_struct() {
+        check("struct Node = { value: Int, next: Node };");
+    }
+
+    #[test]
+    #[should_panic(expected = "the struct A always contains itself")]
+    fn test_infinitely_large_struct_cycle() {
+        check("struct A = { b: (Int, B) }; struct B = { a: A | { a: A } };");
+    }
+
+    #[test]
+    #[should_panic(expected = "the struct List::Cons always contains itself")]
+    fn test_enum_without_base_case() {
+        check("enum List = { Cons(Int, List) };");
+    }
+}
@@ -4,23 +4,37 @@ use std::{
 };
 
 use chumsky::container::Container;
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 
 use crate::{
+    emit::types::primitive_native_type_name,
     parse::{
         SS, Spanned,
         duckx_component_parser::DuckxComponent,
-        function_parser::{FunctionDefintion, LambdaFunctionExpr},
+        failure,
+        function_parser::{
+            EXTENSION_SEP, FunctionDefintion, LambdaFunctionExpr, extension_method_name,
+        },
+        generics_parser::Generic,
+        global_parser::GlobalVariable,
+        lexer::{GO_IDENT_ESCAPE, map_go_interpolations},
         source_file_parser::SourceFile,
         struct_parser::StructDefinition,
+        trait_parser::TraitImplementation,
         tsx_component_parser::{
             Edit, TsxComponent, TsxComponentDependencies, TsxSourceUnit, do_edits,
         },
-        type_parser::{Duck, TypeDefinition, TypeExpr},
+        type_parser::{DUCK_REST, Duck, TypeDefinition, TypeExpr, merge_or},
         value_parser::{
-            Assignment, Declaration, ValFmtStringContents, ValHtmlStringContents, ValueExpr,
+            Assignment, Declaration, MatchArm, ValFmtStringContents, ValHtmlStringContents,
+            ValueExpr,
         },
     },
-    semantics::ident_mangler::mangle,
+    semantics::{
+        ident_mangler::{MANGLE_SEP, mangle, unmangle},
+        typechecker::check_type_compatability,
+        visibility::for_each_value_expr,
+    },
     tags::Tag,
 };
 
@@ -110,6 +124,14 @@ pub struct FunHeader {
     pub return_type: Option<Spanned<TypeExpr>>,
 }
 
+// collects the breaks of an enclosing loop, only loop { .. } can break with a value
+#[derive(Debug, Clone, Default)]
+pub struct LoopBreaks {
+    pub can_carry_value: bool,
+    pub value_types: Vec<Spanned<TypeExpr>>,
+    pub has_plain_break: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct TypeEnv {
     pub identifier_types: Vec<HashMap<String, TypeExpr>>,
@@ -126,6 +148,13 @@ pub struct TypeEnv {
     pub generic_structs_generated: Vec<StructDefinition>,
     pub generic_methods_generated: HashMap<String, Vec<FunctionDefintion>>,
     pub prevent_struct_generation: HashSet<String>,
+    pub loop_breaks: Vec<LoopBreaks>,
+    // the labels of the loops around the current expression and the index of their breaks
+    pub loop_labels: Vec<(String, usize)>,
+    // the mangled names of all consts, their uses are emitted as the go const in a duck value
+    pub const_names: HashSet<String>,
+    // the module of the function that's being resolved, the names of its locals are looked up in it
+    pub module: Vec<String>,
 }
 
 impl Default for TypeEnv {
@@ -144,6 +173,10 @@ impl Default for TypeEnv {
             generic_structs_generated: Vec::new(),
             generic_methods_generated: HashMap::new(),
             prevent_struct_generation: HashSet::new(),
+            loop_breaks: Vec::new(),
+            loop_labels: Vec::new(),
+            const_names: HashSet::new(),
+            module: Vec::new(),
         }
     }
 }
@@ -222,6 +255,59 @@ impl TypeEnv {
             .unwrap_or_else(|| panic!("Could not find struct {name}"))
     }
 
+    // the fields of an enum variant that is constructed like a function or a constant, that are
+    // Variant(T, U) with the fields _0 and _1 and Variant without any fields
+    pub fn variant_payload_fields(&self, name: &str) -> Option<Vec<String>> {
+        if self.get_identifier_type(name.to_string()).is_some() {
+            return None;
+        }
+
+        let def = self
+            .struct_definitions
+            .iter()
+            .find(|x| x.name.as_str() == name)?;
+        let is_payload = def
+            .fields
+            .iter()
+            .enumerate()
+            .all(|(i, field)| field.name == format!("_{i}"));
+        is_payload.then(|| def.fields.iter().map(|field| field.name.clone()).collect())
+    }
+
+    // the default values a call of the function needs to fill up the given number of params
+    pub fn missing_param_defaults(
+        &self,
+        name: &str,
+        given_params: usize,
+    ) -> Option<Vec<Spanned<ValueExpr>>> {
+        let def = self
+            .function_definitions
+            .iter()
+            .find(|x| x.name.as_str() == name)?;
+        let param_count = def.params.as_ref().map_or(0, Vec::len);
+        let missing = param_count.checked_sub(given_params)?;
+        (missing > 0 && missing <= def.param_defaults.len())
+            .then(|| def.param_defaults[def.param_defaults.len() - missing..].to_vec())
+    }
+
+    // the number of params in front of the variadic one and the type of its values, the type
+    // is left to the inference for generic functions
+    pub fn variadic_param(&self, name: &str) -> Option<(usize, Option<Spanned<TypeExpr>>)> {
+        let def = self
+            .function_definitions
+            .iter()
+            .find(|x| x.name.as_str() == name && x.variadic)?;
+        let params = def.params.as_ref()?;
+        let (_, (TypeExpr::Array(element_type), _)) = params.last()? else {
+            return None;
+        };
+        let element_type = def
+            .generics
+            .is_none()
+            .then(|| element_type.as_ref().clone());
+        Some((params.len() - 1, element_type))
+    }
+
     pub fn get_struct_def_mut<'a>(&'a mut self, name: &str) -> &'a mut StructDefinition {
         self.struct_definitions
             .iter_mut()
@@ -470,9 +556,24 @@ fn resolve_all_aliases_type_expr(expr: &mut TypeExpr, env: &mut TypeEnv) {
             fields.sort_by_key(|x| x.name.clone());
             for field in fields {
                 resolve_all_aliases_type_expr(&mut field.type_expr.0, env);
+                // ..B of a type definition B spreads the duck B stands for
+                while field.name == DUCK_REST
+                    && let TypeExpr::Alias(def) = &field.type_expr.0
+                    && def.generics.is_none()
+                {
+                    field.type_expr.0 = def.type_expression.0.clone();
+                    resolve_all_aliases_type_expr(&mut field.type_expr.0, env);
+                }
             }
+            // { x: Int, ..B } with a duck B gets the fields of B, anything else can't be spread
+            spread_duck_rest(expr);
+            check_duck_rest_spread(expr);
+        }
+        TypeExpr::Array(d) | TypeExpr::Chan(d) => resolve_all_aliases_type_expr(&mut d.0, env),
+        TypeExpr::Map(key, value) => {
+            resolve_all_aliases_type_expr(&mut key.0, env);
+            resolve_all_aliases_type_expr(&mut value.0, env);
         }
-        TypeExpr::Array(d) => resolve_all_aliases_type_expr(&mut d.0, env),
         TypeExpr::Fun(params, return_type) => {
             if let Some(r) = return_type {
                 resolve_all_aliases_type_expr(&mut r.0, env);
@@ -538,13 +639,18 @@ fn instantiate_generics_type_expr(expr: &mut TypeExpr, type_env: &mut TypeEnv) {
         TypeExpr::Alias(alias) => {
             instantiate_generics_type_expr(&mut alias.type_expression.0, type_env);
         }
-        TypeExpr::Array(t) => {
+        TypeExpr::Array(t) | TypeExpr::Chan(t) => {
             instantiate_generics_type_expr(&mut t.0, type_env);
         }
+        TypeExpr::Map(key, value) => {
+            instantiate_generics_type_expr(&mut key.0, type_env);
+            instantiate_generics_type_expr(&mut value.0, type_env);
+        }
         TypeExpr::Duck(d) => {
             for f in &mut d.fields {
                 instantiate_generics_type_expr(&mut f.type_expr.0, type_env);
             }
+            spread_duck_rest(expr);
         }
         TypeExpr::Fun(params, ret) => {
             for p in params {
@@ -589,6 +695,13 @@ fn instantiate_generics_type_expr(expr: &mut TypeExpr, type_env: &mut TypeEnv) {
                         type_env
                             .prevent_struct_generation
                             .push(mangled_name.clone());
+                        if let Some(generics) = &def.generics {
+                            check_generic_constraints(
+                                generics,
+                                new_type_params.as_deref().unwrap_or(&[]),
+                                type_env,
+                            );
+                        }
                         let generics_instance = def
                             .generics
                             .as_ref()
@@ -650,6 +763,7 @@ fn instantiate_generics_type_expr(expr: &mut TypeExpr, type_env: &mut TypeEnv) {
             }
         }
         TypeExpr::Any
+        | TypeExpr::Never
         | TypeExpr::Bool
         | TypeExpr::Char
         | TypeExpr::ConstBool(..)
@@ -674,6 +788,16 @@ fn replace_generics_in_value_expr(expr: &mut ValueExpr, set_params: &HashMap<Str
         | ValueExpr::Div(lhs, rhs)
         | ValueExpr::Sub(lhs, rhs)
         | ValueExpr::Mod(lhs, rhs)
+        | ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        }
         | ValueExpr::Equals(lhs, rhs)
         | ValueExpr::NotEquals(lhs, rhs)
         | ValueExpr::LessThan(lhs, rhs)
@@ -681,7 +805,9 @@ fn replace_generics_in_value_expr(expr: &mut ValueExpr, set_params: &HashMap<Str
         | ValueExpr::GreaterThan(lhs, rhs)
         | ValueExpr::GreaterThanOrEquals(lhs, rhs)
         | ValueExpr::And(lhs, rhs)
-        | ValueExpr::Or(lhs, rhs) => {
+        | ValueExpr::Or(lhs, rhs)
+        | ValueExpr::Update(lhs, rhs)
+        | ValueExpr::Coalesce(lhs, rhs) => {
             replace_generics_in_value_expr(&mut lhs.0, set_params);
             replace_generics_in_value_expr(&mut rhs.0, set_params);
         }
@@ -692,8 +818,34 @@ fn replace_generics_in_value_expr(expr: &mut ValueExpr, set_params: &HashMap<Str
                 }
             }
         }
-        ValueExpr::BoolNegate(e) | ValueExpr::Return(Some(e)) => {
-            replace_generics_in_value_expr(&mut e.0, set_params)
+        ValueExpr::Comptime(..) => {
+            panic!("compiler error: comptime blocks should be evaluated by now")
+        }
+        ValueExpr::BoolNegate(e)
+        | ValueExpr::BitNegate(e)
+        | ValueExpr::TypeOf(e)
+        | ValueExpr::Negate(e)
+        | ValueExpr::Return(Some(e))
+        | ValueExpr::Break(Some(e))
+        | ValueExpr::Loop { body: e, .. }
+        | ValueExpr::Defer(e)
+        | ValueExpr::Spawn(e)
+        | ValueExpr::Receive(e)
+        | ValueExpr::Spread(e)
+        | ValueExpr::Labeled(_, e)
+        | ValueExpr::Propagate(e) => replace_generics_in_value_expr(&mut e.0, set_params),
+        ValueExpr::MakeChan {
+            element_type,
+            capacity,
+        } => {
+            replace_generics_in_type_expr(&mut element_type.0, set_params);
+            if let Some(capacity) = capacity {
+                replace_generics_in_value_expr(&mut capacity.0, set_params);
+            }
+        }
+        ValueExpr::Cast(value_expr, type_expr) => {
+            replace_generics_in_value_expr(&mut value_expr.0, set_params);
+            replace_generics_in_type_expr(&mut type_expr.0, set_params);
         }
         ValueExpr::Array(t, exprs) => {
             if let Some(t) = t {
@@ -704,10 +856,28 @@ fn replace_generics_in_value_expr(expr: &mut ValueExpr, set_params: &HashMap<Str
                 replace_generics_in_value_expr(&mut e.0, set_params);
             }
         }
-        ValueExpr::ArrayAccess(target, index) => {
+        ValueExpr::Map(t, entries) => {
+            if let Some(t) = t {
+                replace_generics_in_type_expr(&mut t.0, set_params);
+            }
+
+            for (key, value) in entries {
+                replace_generics_in_value_expr(&mut key.0, set_params);
+                replace_generics_in_value_expr(&mut value.0, set_params);
+            }
+        }
+        ValueExpr::ArrayAccess(target, index) | ValueExpr::Send(target, index) => {
             replace_generics_in_value_expr(&mut target.0, set_params);
             replace_generics_in_value_expr(&mut index.0, set_params);
         }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            replace_generics_in_value_expr(&mut target.0, set_params);
+            for bound in [start, end].into_iter().flatten() {
+                replace_generics_in_value_expr(&mut bound.0, set_params);
+            }
+        }
         ValueExpr::Block(exprs) => {
             for e in exprs {
                 replace_generics_in_value_expr(&mut e.0, set_params);
@@ -721,12 +891,16 @@ fn replace_generics_in_value_expr(expr: &mut ValueExpr, set_params: &HashMap<Str
         ValueExpr::FieldAccess {
             target_obj,
             field_name: _,
+        }
+        | ValueExpr::OptionalFieldAccess {
+            target_obj,
+            field_name: _,
         } => {
             replace_generics_in_value_expr(&mut target_obj.0, set_params);
         }
         ValueExpr::FormattedString(contents) => {
             for c in contents {
-                if let ValFmtStringContents::Expr(e) = c {
+                if let ValFmtStringContents::Expr(e, _) = c {
                     replace_generics_in_value_expr(&mut e.0, set_params);
                 }
             }
@@ -794,7 +968,12 @@ fn replace_generics_in_value_expr(expr: &mut ValueExpr, set_params: &HashMap<Str
                 replace_generics_in_value_expr(&mut f.0, set_params);
             }
         }
-        ValueExpr::While { condition, body } => {
+        ValueExpr::While { condition, body }
+        | ValueExpr::For {
+            target: condition,
+            body,
+            ..
+        } => {
             replace_generics_in_value_expr(&mut condition.0, set_params);
             replace_generics_in_value_expr(&mut body.0, set_params);
         }
@@ -816,16 +995,35 @@ fn replace_generics_in_value_expr(expr: &mut ValueExpr, set_params: &HashMap<Str
             replace_generics_in_value_expr(&mut value_expr.0, set_params);
             for arm in arms {
                 replace_generics_in_type_expr(&mut arm.type_case.0, set_params);
+                if let Some(condition) = &mut arm.condition {
+                    replace_generics_in_value_expr(&mut condition.0, set_params);
+                }
                 replace_generics_in_value_expr(&mut arm.value_expr.0, set_params);
             }
 
             if let Some(arm) = else_arm {
                 replace_generics_in_type_expr(&mut arm.type_case.0, set_params);
+                if let Some(condition) = &mut arm.condition {
+                    replace_generics_in_value_expr(&mut condition.0, set_params);
+                }
                 replace_generics_in_value_expr(&mut arm.value_expr.0, set_params);
             }
         }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                replace_generics_in_value_expr(&mut arm.case.0, set_params);
+                replace_generics_in_value_expr(&mut arm.value_expr.0, set_params);
+            }
+            if let Some(else_arm) = else_arm {
+                replace_generics_in_value_expr(&mut else_arm.0, set_params);
+            }
+        }
+        ValueExpr::Recover { body, handler, .. } => {
+            replace_generics_in_value_expr(&mut body.0, set_params);
+            replace_generics_in_value_expr(&mut handler.0, set_params);
+        }
         ValueExpr::Bool(..)
-        | ValueExpr::Break
+        | ValueExpr::Break(None)
         | ValueExpr::Char(..)
         | ValueExpr::String(..)
         | ValueExpr::Continue
@@ -846,13 +1044,18 @@ fn replace_generics_in_type_expr(expr: &mut TypeExpr, set_params: &HashMap<Strin
         TypeExpr::Alias(alias) => {
             replace_generics_in_type_expr(&mut alias.type_expression.0, set_params);
         }
-        TypeExpr::Array(t) => {
+        TypeExpr::Array(t) | TypeExpr::Chan(t) => {
             replace_generics_in_type_expr(&mut t.0, set_params);
         }
+        TypeExpr::Map(key, value) => {
+            replace_generics_in_type_expr(&mut key.0, set_params);
+            replace_generics_in_type_expr(&mut value.0, set_params);
+        }
         TypeExpr::Duck(d) => {
             for f in &mut d.fields {
                 replace_generics_in_type_expr(&mut f.type_expr.0, set_params);
             }
+            spread_duck_rest(expr);
         }
         TypeExpr::Fun(params, ret) => {
             for p in params {
@@ -883,6 +1086,7 @@ fn replace_generics_in_type_expr(expr: &mut TypeExpr, set_params: &HashMap<Strin
             }
         }
         TypeExpr::Any
+        | TypeExpr::Never
         | TypeExpr::Bool
         | TypeExpr::Char
         | TypeExpr::ConstBool(..)
@@ -900,6 +1104,79 @@ fn replace_generics_in_type_expr(expr: &mut TypeExpr, set_params: &HashMap<Strin
     }
 }
 
+// { id: Int, ..R } with R = { name: String } is { id: Int, name: String }, an empty R leaves
+// just the named fields
+fn spread_duck_rest(expr: &mut TypeExpr) {
+    let TypeExpr::Duck(Duck { fields }) = expr else {
+        return;
+    };
+    let Some(rest_index) = fields.iter().position(|field| field.name == DUCK_REST) else {
+        return;
+    };
+
+    let rest_fields = match &fields[rest_index].type_expr.0 {
+        TypeExpr::Duck(rest) => rest.fields.clone(),
+        TypeExpr::Any => Vec::new(),
+        _ => return,
+    };
+    fields.remove(rest_index);
+    for field in rest_fields {
+        if !fields.iter().any(|x| x.name == field.name) {
+            fields.push(field);
+        }
+    }
+
+    if fields.is_empty() {
+        *expr = TypeExpr::Any;
+    } else {
+        fields.sort_by_key(|field| field.name.clone());
+    }
+}
+
+// a ..R that is still there after the generics are replaced or the aliases are resolved was
+// bound to something that has no fields
+fn check_duck_rest_spread(expr: &TypeExpr) {
+    match expr {
+        TypeExpr::Duck(Duck { fields }) => {
+            for field in fields {
+                let (type_expr, span) = &field.type_expr;
+                if field.name == DUCK_REST {
+                    failure(
+                        &span.context.file_name(),
+                        format!("..{type_expr} has to be a duck"),
+                        (
+                            format!(
+                                "this is a {type_expr}, only the fields of a duck can be spread"
+                            ),
+                            *span,
+                        ),
+                        vec![],
+                        &span.context.file_contents(),
+                    )
+                }
+                check_duck_rest_spread(type_expr);
+            }
+        }
+        TypeExpr::Array(t) | TypeExpr::Chan(t) => check_duck_rest_spread(&t.0),
+        TypeExpr::Map(key, value) => {
+            check_duck_rest_spread(&key.0);
+            check_duck_rest_spread(&value.0);
+        }
+        TypeExpr::Tuple(types) | TypeExpr::Or(types) => {
+            types.iter().for_each(|(t, _)| check_duck_rest_spread(t));
+        }
+        TypeExpr::Fun(params, return_type) => {
+            params
+                .iter()
+                .for_each(|(_, (t, _))| check_duck_rest_spread(t));
+            if let Some(return_type) = return_type {
+                check_duck_rest_spread(&return_type.0);
+            }
+        }
+        _ => {}
+    }
+}
+
 fn resolve_by_string(
     s: &str,
     mut type_params: Option<Vec<(TypeExpr, SS)>>,
@@ -911,6 +1188,7 @@ fn resolve_by_string(
         if let Some(generics) = &def.generics
             && let Some(ref user_generics) = type_params
         {
+            check_generic_constraints(generics, user_generics, type_env);
             for (generic_name, val_to_set) in generics
                 .iter()
                 .map(|x| &x.0.name)
@@ -964,11 +1242,134 @@ fn mangle_generics_name(
     })
 }
 
+// calling foo<T> generates a copy of foo with T replaced, that is called foo_T
+fn instantiate_function_call(
+    var_name: &mut String,
+    type_params: &mut Option<Vec<Spanned<TypeExpr>>>,
+    type_env: &mut TypeEnv,
+) {
+    let mangled_name = mangle_generics_name(
+        var_name.as_str(),
+        type_params.as_ref().unwrap_or(&vec![]).as_slice(),
+        type_env,
+    );
+    let fn_def = type_env
+        .function_definitions
+        .iter()
+        .find(|x| x.name.as_str() == var_name.as_str());
+    if let Some(fn_def) = fn_def {
+        if !type_env.prevent_struct_generation.contains(&mangled_name) {
+            type_env
+                .prevent_struct_generation
+                .push(mangled_name.clone());
+
+            let generics_instance = fn_def
+                .generics
+                .as_ref()
+                .unwrap_or(&vec![])
+                .iter()
+                .map(|x| &x.0.name)
+                .zip(type_params.as_ref().unwrap_or(&vec![]).iter().map(|x| &x.0))
+                .fold(HashMap::new(), |mut acc, (param_name, param_inst)| {
+                    acc.insert(param_name.clone(), param_inst.clone());
+                    acc
+                });
+
+            let mut cloned_def = fn_def.clone();
+            cloned_def.name = mangled_name.clone();
+            if let Some(generics) = &cloned_def.generics {
+                check_generic_constraints(
+                    generics,
+                    type_params.as_deref().unwrap_or(&[]),
+                    type_env,
+                );
+            }
+            cloned_def.generics = None;
+
+            for t in cloned_def
+                .params
+                .iter_mut()
+                .flat_map(|x| x.iter_mut().map(|x| &mut x.1.0))
+                .chain(cloned_def.return_type.iter_mut().map(|x| &mut x.0))
+            {
+                replace_generics_in_type_expr(t, &generics_instance);
+                check_duck_rest_spread(t);
+                instantiate_generics_type_expr(t, type_env);
+            }
+
+            replace_generics_in_value_expr(&mut cloned_def.value_expr.0, &generics_instance);
+            instantiate_generics_value_expr(&mut cloned_def.value_expr.0, type_env);
+
+            for m in type_env.identifier_types.iter_mut() {
+                m.insert(
+                    cloned_def.name.clone(),
+                    TypeExpr::Fun(
+                        cloned_def
+                            .params
+                            .clone()
+                            .map(|x| x.iter().map(|x| (Some(x.0.clone()), x.1.clone())).collect())
+                            .unwrap_or_default(),
+                        cloned_def.return_type.clone().map(Box::new),
+                    ),
+                );
+            }
+
+            type_env.generic_fns_generated.push(cloned_def);
+            *var_name = mangled_name;
+            *type_params = None;
+        } else {
+            *var_name = mangled_name;
+            *type_params = None;
+        }
+    }
+}
+
+// <T: duck { name: String }> can only be instantiated with a type that is compatible with the
+// constraint, which is checked once per instance
+fn check_generic_constraints(
+    generics: &[Spanned<Generic>],
+    type_params: &[Spanned<TypeExpr>],
+    type_env: &mut TypeEnv,
+) {
+    for ((generic, _), type_param) in generics.iter().zip(type_params) {
+        let Some(constraint) = &generic.constraint else {
+            continue;
+        };
+
+        let mut constraint = constraint.clone();
+        instantiate_generics_type_expr(&mut constraint.0, type_env);
+        resolve_all_aliases_type_expr(&mut constraint.0, type_env);
+
+        let mut type_param = type_param.clone();
+        instantiate_generics_type_expr(&mut type_param.0, type_env);
+        resolve_all_aliases_type_expr(&mut type_param.0, type_env);
+
+        check_type_compatability(&constraint, &type_param, type_env);
+    }
+}
+
+fn is_generic_function(name: &str, type_env: &TypeEnv) -> bool {
+    type_env
+        .function_definitions
+        .iter()
+        .any(|fn_def| fn_def.name == name && fn_def.generics.is_some())
+}
+
 fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv) {
     match expr {
         ValueExpr::Add(lhs, rhs)
         | ValueExpr::Mul(lhs, rhs)
         | ValueExpr::Mod(lhs, rhs)
+        | ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        }
         | ValueExpr::Sub(lhs, rhs)
         | ValueExpr::Div(lhs, rhs)
         | ValueExpr::Equals(lhs, rhs)
@@ -978,7 +1379,9 @@ fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv)
         | ValueExpr::GreaterThan(lhs, rhs)
         | ValueExpr::GreaterThanOrEquals(lhs, rhs)
         | ValueExpr::And(lhs, rhs)
-        | ValueExpr::Or(lhs, rhs) => {
+        | ValueExpr::Or(lhs, rhs)
+        | ValueExpr::Update(lhs, rhs)
+        | ValueExpr::Coalesce(lhs, rhs) => {
             instantiate_generics_value_expr(&mut lhs.0, type_env);
             instantiate_generics_value_expr(&mut rhs.0, type_env);
         }
@@ -989,8 +1392,34 @@ fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv)
                 }
             }
         }
-        ValueExpr::BoolNegate(e) | ValueExpr::Return(Some(e)) => {
-            instantiate_generics_value_expr(&mut e.0, type_env)
+        ValueExpr::Comptime(..) => {
+            panic!("compiler error: comptime blocks should be evaluated by now")
+        }
+        ValueExpr::BoolNegate(e)
+        | ValueExpr::BitNegate(e)
+        | ValueExpr::TypeOf(e)
+        | ValueExpr::Negate(e)
+        | ValueExpr::Return(Some(e))
+        | ValueExpr::Break(Some(e))
+        | ValueExpr::Loop { body: e, .. }
+        | ValueExpr::Defer(e)
+        | ValueExpr::Spawn(e)
+        | ValueExpr::Receive(e)
+        | ValueExpr::Spread(e)
+        | ValueExpr::Labeled(_, e)
+        | ValueExpr::Propagate(e) => instantiate_generics_value_expr(&mut e.0, type_env),
+        ValueExpr::MakeChan {
+            element_type,
+            capacity,
+        } => {
+            instantiate_generics_type_expr(&mut element_type.0, type_env);
+            if let Some(capacity) = capacity {
+                instantiate_generics_value_expr(&mut capacity.0, type_env);
+            }
+        }
+        ValueExpr::Cast(value_expr, type_expr) => {
+            instantiate_generics_value_expr(&mut value_expr.0, type_env);
+            instantiate_generics_type_expr(&mut type_expr.0, type_env);
         }
         ValueExpr::Array(t, exprs) => {
             if let Some(t) = t {
@@ -1001,10 +1430,28 @@ fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv)
                 instantiate_generics_value_expr(&mut e.0, type_env);
             }
         }
-        ValueExpr::ArrayAccess(target, index) => {
+        ValueExpr::Map(t, entries) => {
+            if let Some(t) = t {
+                instantiate_generics_type_expr(&mut t.0, type_env);
+            }
+
+            for (key, value) in entries {
+                instantiate_generics_value_expr(&mut key.0, type_env);
+                instantiate_generics_value_expr(&mut value.0, type_env);
+            }
+        }
+        ValueExpr::ArrayAccess(target, index) | ValueExpr::Send(target, index) => {
             instantiate_generics_value_expr(&mut target.0, type_env);
             instantiate_generics_value_expr(&mut index.0, type_env);
         }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            instantiate_generics_value_expr(&mut target.0, type_env);
+            for bound in [start, end].into_iter().flatten() {
+                instantiate_generics_value_expr(&mut bound.0, type_env);
+            }
+        }
         ValueExpr::Block(exprs) => {
             for e in exprs {
                 instantiate_generics_value_expr(&mut e.0, type_env);
@@ -1018,12 +1465,16 @@ fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv)
         ValueExpr::FieldAccess {
             target_obj,
             field_name: _,
+        }
+        | ValueExpr::OptionalFieldAccess {
+            target_obj,
+            field_name: _,
         } => {
             instantiate_generics_value_expr(&mut target_obj.0, type_env);
         }
         ValueExpr::FormattedString(contents) => {
             for c in contents {
-                if let ValFmtStringContents::Expr(e) = c {
+                if let ValFmtStringContents::Expr(e, _) = c {
                     instantiate_generics_value_expr(&mut e.0, type_env);
                 }
             }
@@ -1041,80 +1492,12 @@ fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv)
                 instantiate_generics_value_expr(&mut p.0, type_env);
             }
 
-            if let ValueExpr::Variable(_, var_name, _) = &mut target.0 {
-                let mangled_name = mangle_generics_name(
-                    var_name.as_str(),
-                    type_params.as_ref().unwrap_or(&vec![]).as_slice(),
-                    type_env,
-                );
-                let fn_def = type_env
-                    .function_definitions
-                    .iter()
-                    .find(|x| x.name.as_str() == var_name.as_str());
-                if let Some(fn_def) = fn_def {
-                    if !type_env.prevent_struct_generation.contains(&mangled_name) {
-                        type_env
-                            .prevent_struct_generation
-                            .push(mangled_name.clone());
-
-                        let generics_instance = fn_def
-                            .generics
-                            .as_ref()
-                            .unwrap_or(&vec![])
-                            .iter()
-                            .map(|x| &x.0.name)
-                            .zip(type_params.as_ref().unwrap_or(&vec![]).iter().map(|x| &x.0))
-                            .fold(HashMap::new(), |mut acc, (param_name, param_inst)| {
-                                acc.insert(param_name.clone(), param_inst.clone());
-                                acc
-                            });
-
-                        let mut cloned_def = fn_def.clone();
-                        cloned_def.name = mangled_name.clone();
-                        cloned_def.generics = None;
-
-                        for t in cloned_def
-                            .params
-                            .iter_mut()
-                            .flat_map(|x| x.iter_mut().map(|x| &mut x.1.0))
-                            .chain(cloned_def.return_type.iter_mut().map(|x| &mut x.0))
-                        {
-                            replace_generics_in_type_expr(t, &generics_instance);
-                            instantiate_generics_type_expr(t, type_env);
-                        }
-
-                        replace_generics_in_value_expr(
-                            &mut cloned_def.value_expr.0,
-                            &generics_instance,
-                        );
-                        instantiate_generics_value_expr(&mut cloned_def.value_expr.0, type_env);
-
-                        for m in type_env.identifier_types.iter_mut() {
-                            m.insert(
-                                cloned_def.name.clone(),
-                                TypeExpr::Fun(
-                                    cloned_def
-                                        .params
-                                        .clone()
-                                        .map(|x| {
-                                            x.iter()
-                                                .map(|x| (Some(x.0.clone()), x.1.clone()))
-                                                .collect()
-                                        })
-                                        .unwrap_or_default(),
-                                    cloned_def.return_type.clone().map(Box::new),
-                                ),
-                            );
-                        }
-
-                        type_env.generic_fns_generated.push(cloned_def);
-                        *var_name = mangled_name;
-                        *type_params = None;
-                    } else {
-                        *var_name = mangled_name;
-                        *type_params = None;
-                    }
-                }
+            if let ValueExpr::Variable(_, var_name, _) = &mut target.0
+                && (type_params.is_some() || !is_generic_function(var_name, type_env))
+            {
+                // generic calls without type params are instantiated by typeresolve_value_expr,
+                // because the type params are inferred from the types of the arguments
+                instantiate_function_call(var_name, type_params, type_env);
             }
         }
         ValueExpr::If {
@@ -1168,6 +1551,13 @@ fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv)
                     type_env
                         .prevent_struct_generation
                         .push(mangled_name.clone());
+                    if let Some(generics) = &def.generics {
+                        check_generic_constraints(
+                            generics,
+                            type_params.as_deref().unwrap_or(&[]),
+                            type_env,
+                        );
+                    }
                     let generics_instance = def
                         .generics
                         .as_ref()
@@ -1229,7 +1619,12 @@ fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv)
                 instantiate_generics_value_expr(&mut f.0, type_env);
             }
         }
-        ValueExpr::While { condition, body } => {
+        ValueExpr::While { condition, body }
+        | ValueExpr::For {
+            target: condition,
+            body,
+            ..
+        } => {
             instantiate_generics_value_expr(&mut condition.0, type_env);
             instantiate_generics_value_expr(&mut body.0, type_env);
         }
@@ -1252,16 +1647,35 @@ fn instantiate_generics_value_expr(expr: &mut ValueExpr, type_env: &mut TypeEnv)
             instantiate_generics_value_expr(&mut value_expr.0, type_env);
             for arm in arms {
                 instantiate_generics_type_expr(&mut arm.type_case.0, type_env);
+                if let Some(condition) = &mut arm.condition {
+                    instantiate_generics_value_expr(&mut condition.0, type_env);
+                }
                 instantiate_generics_value_expr(&mut arm.value_expr.0, type_env);
             }
 
             if let Some(arm) = else_arm {
                 instantiate_generics_type_expr(&mut arm.type_case.0, type_env);
+                if let Some(condition) = &mut arm.condition {
+                    instantiate_generics_value_expr(&mut condition.0, type_env);
+                }
+                instantiate_generics_value_expr(&mut arm.value_expr.0, type_env);
+            }
+        }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                instantiate_generics_value_expr(&mut arm.case.0, type_env);
                 instantiate_generics_value_expr(&mut arm.value_expr.0, type_env);
             }
+            if let Some(else_arm) = else_arm {
+                instantiate_generics_value_expr(&mut else_arm.0, type_env);
+            }
+        }
+        ValueExpr::Recover { body, handler, .. } => {
+            instantiate_generics_value_expr(&mut body.0, type_env);
+            instantiate_generics_value_expr(&mut handler.0, type_env);
         }
         ValueExpr::Bool(..)
-        | ValueExpr::Break
+        | ValueExpr::Break(None)
         | ValueExpr::Char(..)
         | ValueExpr::String(..)
         | ValueExpr::Continue
@@ -1292,6 +1706,15 @@ fn sort_fields_value_expr(expr: &mut ValueExpr) {
                 sort_fields_value_expr(&mut expr.0);
             }
         }
+        ValueExpr::Map(ty, entries) => {
+            if let Some(ty) = ty {
+                sort_fields_type_expr(&mut ty.0);
+            }
+            for (key, value) in entries {
+                sort_fields_value_expr(&mut key.0);
+                sort_fields_value_expr(&mut value.0);
+            }
+        }
         ValueExpr::VarDecl(d) => {
             let Declaration {
                 name: _,
@@ -1321,6 +1744,16 @@ fn sort_fields_value_expr(expr: &mut ValueExpr) {
         ValueExpr::Add(lhs, rhs)
         | ValueExpr::Mul(lhs, rhs)
         | ValueExpr::Mod(lhs, rhs)
+        | ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        }
         | ValueExpr::Sub(lhs, rhs)
         | ValueExpr::Div(lhs, rhs)
         | ValueExpr::Equals(lhs, rhs)
@@ -1330,20 +1763,57 @@ fn sort_fields_value_expr(expr: &mut ValueExpr) {
         | ValueExpr::GreaterThan(lhs, rhs)
         | ValueExpr::GreaterThanOrEquals(lhs, rhs)
         | ValueExpr::And(lhs, rhs)
-        | ValueExpr::Or(lhs, rhs) => {
+        | ValueExpr::Or(lhs, rhs)
+        | ValueExpr::Update(lhs, rhs)
+        | ValueExpr::Coalesce(lhs, rhs) => {
             sort_fields_value_expr(&mut lhs.0);
             sort_fields_value_expr(&mut rhs.0);
         }
-        ValueExpr::ArrayAccess(target, idx) => {
+        ValueExpr::ArrayAccess(target, idx) | ValueExpr::Send(target, idx) => {
             sort_fields_value_expr(&mut target.0);
             sort_fields_value_expr(&mut idx.0);
         }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            sort_fields_value_expr(&mut target.0);
+            for bound in [start, end].into_iter().flatten() {
+                sort_fields_value_expr(&mut bound.0);
+            }
+        }
         ValueExpr::Block(exprs) => {
             for expr in exprs {
                 sort_fields_value_expr(&mut expr.0);
             }
         }
-        ValueExpr::BoolNegate(e) => sort_fields_value_expr(&mut e.0),
+        ValueExpr::Comptime(..) => {
+            panic!("compiler error: comptime blocks should be evaluated by now")
+        }
+        ValueExpr::BoolNegate(e)
+        | ValueExpr::BitNegate(e)
+        | ValueExpr::TypeOf(e)
+        | ValueExpr::Negate(e)
+        | ValueExpr::Break(Some(e))
+        | ValueExpr::Loop { body: e, .. }
+        | ValueExpr::Defer(e)
+        | ValueExpr::Spawn(e)
+        | ValueExpr::Receive(e)
+        | ValueExpr::Spread(e)
+        | ValueExpr::Labeled(_, e)
+        | ValueExpr::Propagate(e) => sort_fields_value_expr(&mut e.0),
+        ValueExpr::MakeChan {
+            element_type,
+            capacity,
+        } => {
+            sort_fields_type_expr(&mut element_type.0);
+            if let Some(capacity) = capacity {
+                sort_fields_value_expr(&mut capacity.0);
+            }
+        }
+        ValueExpr::Cast(value_expr, type_expr) => {
+            sort_fields_value_expr(&mut value_expr.0);
+            sort_fields_type_expr(&mut type_expr.0);
+        }
         ValueExpr::Duck(init) => {
             for i in init {
                 sort_fields_value_expr(&mut i.1.0);
@@ -1352,12 +1822,16 @@ fn sort_fields_value_expr(expr: &mut ValueExpr) {
         ValueExpr::FieldAccess {
             target_obj,
             field_name: _,
+        }
+        | ValueExpr::OptionalFieldAccess {
+            target_obj,
+            field_name: _,
         } => {
             sort_fields_value_expr(&mut target_obj.0);
         }
         ValueExpr::FormattedString(content) => {
             for c in content {
-                if let ValFmtStringContents::Expr(e) = c {
+                if let ValFmtStringContents::Expr(e, _) = c {
                     sort_fields_value_expr(&mut e.0);
                 }
             }
@@ -1392,13 +1866,32 @@ fn sort_fields_value_expr(expr: &mut ValueExpr) {
             sort_fields_value_expr(&mut value_expr.0);
             for arm in arms {
                 sort_fields_type_expr(&mut arm.type_case.0);
+                if let Some(condition) = &mut arm.condition {
+                    sort_fields_value_expr(&mut condition.0);
+                }
                 sort_fields_value_expr(&mut arm.value_expr.0);
             }
 
             if let Some(arm) = else_arm {
                 sort_fields_type_expr(&mut arm.type_case.0);
+                if let Some(condition) = &mut arm.condition {
+                    sort_fields_value_expr(&mut condition.0);
+                }
+                sort_fields_value_expr(&mut arm.value_expr.0);
+            }
+        }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                sort_fields_value_expr(&mut arm.case.0);
                 sort_fields_value_expr(&mut arm.value_expr.0);
             }
+            if let Some(else_arm) = else_arm {
+                sort_fields_value_expr(&mut else_arm.0);
+            }
+        }
+        ValueExpr::Recover { body, handler, .. } => {
+            sort_fields_value_expr(&mut body.0);
+            sort_fields_value_expr(&mut handler.0);
         }
         ValueExpr::Return(r) => {
             if let Some(r) = r {
@@ -1420,11 +1913,16 @@ fn sort_fields_value_expr(expr: &mut ValueExpr) {
             sort_fields_value_expr(&mut target.0);
             sort_fields_value_expr(&mut value_expr.0);
         }
-        ValueExpr::While { condition, body } => {
+        ValueExpr::While { condition, body }
+        | ValueExpr::For {
+            target: condition,
+            body,
+            ..
+        } => {
             sort_fields_value_expr(&mut condition.0);
             sort_fields_value_expr(&mut body.0);
         }
-        ValueExpr::Break
+        ValueExpr::Break(None)
         | ValueExpr::InlineGo(..)
         | ValueExpr::Int(..)
         | ValueExpr::Variable(..)
@@ -1452,7 +1950,11 @@ fn sort_fields_type_expr(expr: &mut TypeExpr) {
                 sort_fields_type_expr(&mut field.type_expr.0);
             }
         }
-        TypeExpr::Array(d) => sort_fields_type_expr(&mut d.0),
+        TypeExpr::Array(d) | TypeExpr::Chan(d) => sort_fields_type_expr(&mut d.0),
+        TypeExpr::Map(key, value) => {
+            sort_fields_type_expr(&mut key.0);
+            sort_fields_type_expr(&mut value.0);
+        }
         TypeExpr::Fun(params, r) => {
             if let Some(r) = r {
                 sort_fields_type_expr(&mut r.0);
@@ -1472,6 +1974,7 @@ fn sort_fields_type_expr(expr: &mut TypeExpr) {
             }
         }
         TypeExpr::Any
+        | TypeExpr::Never
         | TypeExpr::Bool
         | TypeExpr::ConstBool(_)
         | TypeExpr::Char
@@ -1541,15 +2044,52 @@ pub fn typeresolve_struct_def(def: &mut StructDefinition, type_env: &mut TypeEnv
     type_env.all_types.insert(0, ty_expr);
 }
 
+// resolves the aliases in the params and the return type and gives the type of the function
+fn resolve_function_signature(
+    function_definition: &mut FunctionDefintion,
+    type_env: &mut TypeEnv,
+) -> TypeExpr {
+    if let Some(params) = function_definition.params.as_mut() {
+        for (_, p) in params {
+            instantiate_generics_type_expr(&mut p.0, type_env);
+            resolve_all_aliases_type_expr(&mut p.0, type_env);
+        }
+    }
+
+    if let Some(r) = function_definition.return_type.as_mut() {
+        instantiate_generics_type_expr(&mut r.0, type_env);
+        resolve_all_aliases_type_expr(&mut r.0, type_env);
+    }
+
+    TypeExpr::Fun(
+        function_definition
+            .params
+            .as_ref()
+            .unwrap_or(&Vec::new())
+            .iter()
+            .map(|(identifier, type_expr)| (Some(identifier.clone()), type_expr.clone()))
+            .collect::<Vec<_>>(),
+        function_definition
+            .return_type
+            .as_ref()
+            .map(|spanned_type_expr| {
+                Box::new((
+                    type_env.insert_type(spanned_type_expr.0.clone()),
+                    spanned_type_expr.1,
+                ))
+            }),
+    )
+}
+
 pub fn typeresolve_source_file(source_file: &mut SourceFile, type_env: &mut TypeEnv) {
     type_env.push_type_aliases();
 
     println!("{} sort fields", Tag::TypeResolve);
 
-    // Step 1: Sort fields
+    // Step 1: Sort fields, every item on its own so they're sorted in parallel
     source_file
         .type_definitions
-        .iter_mut()
+        .par_iter_mut()
         .for_each(|type_definition| {
             sort_fields_type_expr(&mut type_definition.type_expression.0);
         });
@@ -1557,21 +2097,31 @@ pub fn typeresolve_source_file(source_file: &mut SourceFile, type_env: &mut Type
     // todo: check if we'd rather sort after generic generation
     source_file
         .function_definitions
-        .iter_mut()
+        .par_iter_mut()
         .for_each(|function_definition| {
             sort_fields_value_expr(&mut function_definition.value_expr.0);
         });
 
+    source_file
+        .global_variables
+        .par_iter_mut()
+        .for_each(|global| {
+            if let Some(type_expr) = &mut global.type_expr {
+                sort_fields_type_expr(&mut type_expr.0);
+            }
+            sort_fields_value_expr(&mut global.initializer.0);
+        });
+
     source_file
         .tsx_components
-        .iter_mut()
+        .par_iter_mut()
         .for_each(|function_definition| {
             sort_fields_type_expr(&mut function_definition.props_type.0);
         });
 
     source_file
         .duckx_components
-        .iter_mut()
+        .par_iter_mut()
         .for_each(|function_definition| {
             sort_fields_type_expr(&mut function_definition.props_type.0);
             sort_fields_value_expr(&mut function_definition.value_expr.0);
@@ -1637,6 +2187,16 @@ pub fn typeresolve_source_file(source_file: &mut SourceFile, type_env: &mut Type
             );
         });
 
+    // the values of consts are literals by now, so their type is already known
+    for const_def in &source_file.const_definitions {
+        let (type_expr, _) = const_def
+            .type_expr
+            .clone()
+            .expect("compiler error: consts should be evaluated by now");
+        type_env.const_names.insert(const_def.name.clone());
+        type_env.insert_identifier_type(const_def.name.clone(), type_expr);
+    }
+
     for fn_def in &source_file.function_definitions {
         type_env.function_definitions.push(fn_def.clone());
     }
@@ -1671,6 +2231,13 @@ pub fn typeresolve_source_file(source_file: &mut SourceFile, type_env: &mut Type
         instantiate_generics_value_expr(&mut x.value_expr.0, type_env);
     });
 
+    source_file.global_variables.iter_mut().for_each(|global| {
+        if let Some(type_expr) = &mut global.type_expr {
+            instantiate_generics_type_expr(&mut type_expr.0, type_env);
+        }
+        instantiate_generics_value_expr(&mut global.initializer.0, type_env);
+    });
+
     for func in &type_env.generic_fns_generated {
         source_file.function_definitions.push(func.clone());
     }
@@ -1684,39 +2251,21 @@ pub fn typeresolve_source_file(source_file: &mut SourceFile, type_env: &mut Type
                 return;
             }
 
-            if let Some(params) = function_definition.params.as_mut() {
-                for (_, p) in params {
-                    instantiate_generics_type_expr(&mut p.0, type_env);
-                    resolve_all_aliases_type_expr(&mut p.0, type_env);
-                }
-            }
-
-            if let Some(r) = function_definition.return_type.as_mut() {
-                instantiate_generics_type_expr(&mut r.0, type_env);
-                resolve_all_aliases_type_expr(&mut r.0, type_env);
-            }
+            let fn_type_expr = resolve_function_signature(function_definition, type_env);
+            type_env.insert_identifier_type(function_definition.name.clone(), fn_type_expr);
+        });
 
-            let fn_type_expr = TypeExpr::Fun(
-                function_definition
-                    .params
-                    .as_ref()
-                    .unwrap_or(&Vec::new())
-                    .iter()
-                    .map(|(identifier, type_expr)| (Some(identifier.clone()), type_expr.clone()))
-                    .collect::<Vec<_>>(),
-                function_definition
-                    .return_type
-                    .as_ref()
-                    .map(|spanned_type_expr| {
-                        Box::new((
-                            type_env.insert_type(spanned_type_expr.0.clone()),
-                            spanned_type_expr.1,
-                        ))
-                    }),
-            );
-
-            type_env.insert_identifier_type(function_definition.name.clone(), fn_type_expr);
-        });
+    // globals are initialized in the order they're declared in, so an initializer only sees the
+    // globals before it. functions are resolved afterwards and can use all of them
+    let global_names = source_file
+        .global_variables
+        .iter()
+        .map(|global| global.name.clone())
+        .collect::<Vec<_>>();
+    for (i, global) in source_file.global_variables.iter_mut().enumerate() {
+        check_initialized_before(global, &global_names[i..]);
+        typeresolve_global_variable(global, type_env);
+    }
     println!("{} typeresolve functions", Tag::TypeResolve);
     println!("{} final resolve of all functions", Tag::TypeResolve);
 
@@ -1758,14 +2307,16 @@ pub fn typeresolve_source_file(source_file: &mut SourceFile, type_env: &mut Type
 
     type_env.generic_methods_generated = cloned;
 
-    let mut cloned = type_env.generic_fns_generated.clone();
-
-    for m in cloned.iter_mut() {
-        typeresolve_function_definition(m, type_env);
+    // resolving a generated function can generate further ones, when it calls generic functions
+    // without type params
+    let mut index = 0;
+    while index < type_env.generic_fns_generated.len() {
+        let mut generic_fn = type_env.generic_fns_generated[index].clone();
+        typeresolve_function_definition(&mut generic_fn, type_env);
+        type_env.generic_fns_generated[index] = generic_fn;
+        index += 1;
     }
 
-    type_env.generic_fns_generated = cloned;
-
     // Step 5: Final resolve of all functions
     source_file
         .struct_definitions
@@ -1789,6 +2340,1111 @@ pub fn typeresolve_source_file(source_file: &mut SourceFile, type_env: &mut Type
 
     type_env.struct_definitions = source_file.struct_definitions.clone();
     type_env.function_definitions = source_file.function_definitions.clone();
+
+    for implementation in &source_file.trait_implementations {
+        check_trait_implementation(implementation, type_env);
+    }
+}
+
+// the methods of impl Show for Point are already part of Point, what's left is checking that
+// Point is compatible with the duck of the trait
+fn check_trait_implementation(implementation: &TraitImplementation, type_env: &mut TypeEnv) {
+    let (struct_name, span) = &implementation.struct_name;
+
+    // generic structs are only checked through their instances
+    if type_env.get_struct_def(struct_name).generics.is_some() {
+        return;
+    }
+
+    let mut trait_type = implementation.trait_type.clone();
+    instantiate_generics_type_expr(&mut trait_type.0, type_env);
+    resolve_all_aliases_type_expr(&mut trait_type.0, type_env);
+
+    if !matches!(trait_type.0, TypeExpr::Duck(..)) {
+        failure(
+            &span.context.file_name(),
+            format!("{} isn't a trait", implementation.trait_type.0),
+            (
+                format!(
+                    "only traits can be implemented, but this is {}",
+                    trait_type.0
+                ),
+                implementation.trait_type.1,
+            ),
+            vec![],
+            &span.context.file_contents(),
+        )
+    }
+
+    check_type_compatability(
+        &trait_type,
+        &(TypeExpr::Struct(struct_name.clone()), *span),
+        type_env,
+    );
+}
+
+// map(xs, |x| x + 1) is map<Int, Int>(xs, |x| x + 1), the type params are taken from the
+// types of the arguments. lambdas are looked at last, so their params can be typed by the
+// type params the other arguments gave
+fn infer_type_params(
+    fn_name: &str,
+    args: &[Spanned<ValueExpr>],
+    call_span: SS,
+    type_env: &mut TypeEnv,
+) -> Vec<Spanned<TypeExpr>> {
+    let fn_def = type_env
+        .function_definitions
+        .iter()
+        .find(|fn_def| fn_def.name == fn_name)
+        .cloned()
+        .expect("type params are only inferred for generic functions");
+    let generics = fn_def.generics.clone().unwrap_or_default();
+    let generic_names = generics
+        .iter()
+        .map(|(generic, _)| generic.name.clone())
+        .collect::<Vec<_>>();
+    let params = fn_def.params.clone().unwrap_or_default();
+
+    let (lambdas, others): (Vec<_>, Vec<_>) = args
+        .iter()
+        .zip(params.iter())
+        .partition(|((arg, _), _)| matches!(arg, ValueExpr::Lambda(..)));
+
+    let mut inferred = HashMap::new();
+    for ((arg, _), (_, (param_type, _))) in others.into_iter().chain(lambdas) {
+        let mut arg = arg.clone();
+
+        if let ValueExpr::Lambda(..) = arg {
+            let mut expected = param_type.clone();
+            replace_generics_in_type_expr(&mut expected, &inferred);
+            if let TypeExpr::Fun(expected_params, expected_return_type) = &mut expected
+                && !expected_params
+                    .iter()
+                    .any(|(_, (t, _))| mentions_generics(t, &generic_names))
+            {
+                // a return type that isn't known yet is taken from the body of the lambda
+                if expected_return_type
+                    .as_ref()
+                    .is_some_and(|t| mentions_generics(&t.0, &generic_names))
+                {
+                    *expected_return_type = None;
+                }
+                infer_lambda_signature(&mut arg, &expected, type_env);
+            }
+        }
+
+        typeresolve_value_expr(&mut arg, type_env);
+        let arg_type = match &arg {
+            ValueExpr::Lambda(lambda_expr) if lambda_expr.return_type.is_none() => TypeExpr::Fun(
+                lambda_expr
+                    .params
+                    .iter()
+                    .map(|(name, type_expr)| (Some(name.clone()), type_expr.clone()))
+                    .collect(),
+                Some(Box::new((
+                    TypeExpr::from_value_expr(&lambda_expr.value_expr.0, type_env),
+                    lambda_expr.value_expr.1,
+                ))),
+            ),
+            _ => TypeExpr::from_value_expr(&arg, type_env),
+        };
+        infer_generics(
+            param_type,
+            &arg_type,
+            &generic_names,
+            &mut inferred,
+            type_env,
+        );
+    }
+
+    generics
+        .iter()
+        .map(|(generic, span)| match inferred.get(&generic.name) {
+            Some(type_expr) => (type_expr.clone(), *span),
+            None => {
+                let fn_name = fn_name.replace(MANGLE_SEP, "::");
+                failure(
+                    &call_span.context.file_name(),
+                    format!("Can't infer the type of {} for {fn_name}", generic.name),
+                    (
+                        format!(
+                            "{} isn't known from the arguments, call it like {fn_name}<..>(..)",
+                            generic.name
+                        ),
+                        call_span,
+                    ),
+                    vec![],
+                    &call_span.context.file_contents(),
+                )
+            }
+        })
+        .collect()
+}
+
+// binds the generics in expected to the matching parts of given, if they aren't bound yet
+fn infer_generics(
+    expected: &TypeExpr,
+    given: &TypeExpr,
+    generics: &[String],
+    inferred: &mut HashMap<String, TypeExpr>,
+    type_env: &mut TypeEnv,
+) {
+    if let TypeExpr::TypeName(_, name, None) = expected
+        && generics.contains(name)
+    {
+        if !inferred.contains_key(name) && *given != TypeExpr::Any {
+            inferred.insert(name.clone(), given.unconst());
+        }
+        return;
+    }
+
+    if inferred.len() == generics.len() {
+        return;
+    }
+
+    let given = object_as_duck(&type_env.try_resolve_type_expr(given), None, type_env);
+    match (expected, &given) {
+        (TypeExpr::Array(expected), TypeExpr::Array(given))
+        | (TypeExpr::Chan(expected), TypeExpr::Chan(given)) => {
+            infer_generics(&expected.0, &given.0, generics, inferred, type_env);
+        }
+        (TypeExpr::Map(expected_key, expected_value), TypeExpr::Map(given_key, given_value)) => {
+            infer_generics(&expected_key.0, &given_key.0, generics, inferred, type_env);
+            infer_generics(
+                &expected_value.0,
+                &given_value.0,
+                generics,
+                inferred,
+                type_env,
+            );
+        }
+        (TypeExpr::Tuple(expected), TypeExpr::Tuple(given)) => {
+            for (expected, given) in expected.iter().zip(given) {
+                infer_generics(&expected.0, &given.0, generics, inferred, type_env);
+            }
+        }
+        (
+            TypeExpr::Fun(expected_params, expected_return),
+            TypeExpr::Fun(given_params, given_return),
+        ) => {
+            for ((_, expected), (_, given)) in expected_params.iter().zip(given_params) {
+                infer_generics(&expected.0, &given.0, generics, inferred, type_env);
+            }
+            if let Some(expected_return) = expected_return
+                && let Some(given_return) = given_return
+            {
+                infer_generics(
+                    &expected_return.0,
+                    &given_return.0,
+                    generics,
+                    inferred,
+                    type_env,
+                );
+            }
+        }
+        (TypeExpr::Duck(expected), TypeExpr::Duck(given)) => {
+            for field in &expected.fields {
+                if field.name == DUCK_REST {
+                    infer_duck_rest(&field.type_expr.0, expected, given, generics, inferred);
+                } else if let Some(given_field) = given.fields.iter().find(|x| x.name == field.name)
+                {
+                    let (expected, given) = (&field.type_expr.0, &given_field.type_expr.0);
+                    infer_generics(expected, given, generics, inferred, type_env);
+                }
+            }
+        }
+        (TypeExpr::TypeName(_, name, Some(type_params)), _) => {
+            // List<T> is matched by the fields it has with T in them
+            let (resolved, type_params) =
+                resolve_by_string(name, Some(type_params.clone()), type_env);
+            if let Some(resolved) = resolved {
+                let expected = object_as_duck(&resolved, type_params.as_ref(), type_env);
+                infer_generics(&expected, &given, generics, inferred, type_env);
+            }
+        }
+        _ => {}
+    }
+}
+
+// the ..R of an expected duck is bound to the fields of the given duck that the expected one
+// doesn't name
+fn infer_duck_rest(
+    rest: &TypeExpr,
+    expected: &Duck,
+    given: &Duck,
+    generics: &[String],
+    inferred: &mut HashMap<String, TypeExpr>,
+) {
+    let TypeExpr::TypeName(_, name, None) = rest else {
+        return;
+    };
+    if !generics.contains(name) || inferred.contains_key(name) {
+        return;
+    }
+
+    let fields = given
+        .fields
+        .iter()
+        .filter(|field| !expected.fields.iter().any(|x| x.name == field.name))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut rest_type = if fields.is_empty() {
+        TypeExpr::Any
+    } else {
+        TypeExpr::Duck(Duck { fields })
+    };
+    widen_literal_types(&mut rest_type);
+    inferred.insert(name.clone(), rest_type);
+}
+
+// structs are compared by their fields when inferring generics, a generic struct gets the type
+// params replaced in its fields
+fn object_as_duck(
+    type_expr: &TypeExpr,
+    type_params: Option<&Vec<Spanned<TypeExpr>>>,
+    type_env: &TypeEnv,
+) -> TypeExpr {
+    let TypeExpr::Struct(struct_name) = type_expr else {
+        return type_expr.clone();
+    };
+
+    let struct_def = type_env.get_struct_def(struct_name);
+    let generics_instance = struct_def
+        .generics
+        .iter()
+        .flatten()
+        .map(|(generic, _)| generic.name.clone())
+        .zip(type_params.into_iter().flatten().map(|(t, _)| t.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut fields = struct_def.fields.clone();
+    for field in &mut fields {
+        replace_generics_in_type_expr(&mut field.type_expr.0, &generics_instance);
+    }
+    TypeExpr::Duck(Duck { fields })
+}
+
+fn mentions_generics(type_expr: &TypeExpr, generics: &[String]) -> bool {
+    match type_expr {
+        TypeExpr::TypeName(_, name, type_params) => {
+            generics.contains(name)
+                || type_params
+                    .iter()
+                    .flatten()
+                    .any(|(t, _)| mentions_generics(t, generics))
+        }
+        TypeExpr::Array(t) | TypeExpr::Chan(t) => mentions_generics(&t.0, generics),
+        TypeExpr::Map(key, value) => {
+            mentions_generics(&key.0, generics) || mentions_generics(&value.0, generics)
+        }
+        TypeExpr::Tuple(types) | TypeExpr::Or(types) => {
+            types.iter().any(|(t, _)| mentions_generics(t, generics))
+        }
+        TypeExpr::Fun(params, return_type) => {
+            params
+                .iter()
+                .any(|(_, (t, _))| mentions_generics(t, generics))
+                || return_type
+                    .as_ref()
+                    .is_some_and(|t| mentions_generics(&t.0, generics))
+        }
+        TypeExpr::Duck(Duck { fields }) => fields
+            .iter()
+            .any(|field| mentions_generics(&field.type_expr.0, generics)),
+        _ => false,
+    }
+}
+
+// params of shorthand lambdas (|x| x + 1) are typed any by the parser and have no return type,
+// both are taken from the function type the lambda is used as
+fn infer_lambda_signature(value_expr: &mut ValueExpr, expected: &TypeExpr, type_env: &mut TypeEnv) {
+    let ValueExpr::Lambda(lambda_expr) = value_expr else {
+        return;
+    };
+
+    let mut expected = expected.clone();
+    resolve_all_aliases_type_expr(&mut expected, type_env);
+    let TypeExpr::Fun(expected_params, expected_return_type) = expected else {
+        return;
+    };
+
+    if lambda_expr.params.len() != expected_params.len() {
+        return;
+    }
+
+    let is_shorthand = lambda_expr.return_type.is_none()
+        && lambda_expr
+            .params
+            .iter()
+            .all(|(_, (type_expr, _))| *type_expr == TypeExpr::Any);
+
+    if !is_shorthand {
+        return;
+    }
+
+    for ((_, param), (_, expected_param)) in
+        lambda_expr.params.iter_mut().zip(expected_params.iter())
+    {
+        param.0 = expected_param.0.clone();
+    }
+
+    lambda_expr.return_type = expected_return_type.map(|return_type| *return_type);
+}
+
+// a field of the expected duck that can be .none, like nickname in
+// { name: String, nickname?: String }, can be left out of a duck literal and is .none then.
+// other values without the field are copied into the expected duck when they're emitted
+fn fill_omitted_optional_fields(
+    value_expr: &mut ValueExpr,
+    expected: &TypeExpr,
+    type_env: &mut TypeEnv,
+) {
+    let ValueExpr::Duck(fields) = value_expr else {
+        return;
+    };
+
+    let mut expected = expected.clone();
+    resolve_all_aliases_type_expr(&mut expected, type_env);
+    let TypeExpr::Duck(Duck {
+        fields: expected_fields,
+    }) = expected
+    else {
+        return;
+    };
+
+    for expected_field in expected_fields {
+        match fields
+            .iter_mut()
+            .find(|(name, _)| *name == expected_field.name)
+        {
+            Some((_, field_value)) => fill_omitted_optional_fields(
+                &mut field_value.0,
+                &expected_field.type_expr.0,
+                type_env,
+            ),
+            None if expected_field.type_expr.0.is_optional() => {
+                fields.push((
+                    expected_field.name,
+                    ValueExpr::Tag("none".to_string()).into_empty_span(),
+                ));
+            }
+            None => {}
+        }
+    }
+}
+
+// fields of duck patterns in match arms without a type annotation get the type of the field in
+// the first variant of the matched value that has all fields of the pattern
+fn infer_duck_pattern_fields(
+    duck: &mut Duck,
+    untyped_fields: &[String],
+    match_on_type: &TypeExpr,
+    type_env: &mut TypeEnv,
+) {
+    if untyped_fields.is_empty() {
+        return;
+    }
+
+    let variants = match match_on_type {
+        TypeExpr::Or(variants) => variants.iter().map(|x| x.0.clone()).collect::<Vec<_>>(),
+        other => vec![other.clone()],
+    };
+
+    let variant_fields = variants
+        .into_iter()
+        .map(|variant| match type_env.try_resolve_type_expr(&variant) {
+            TypeExpr::Struct(struct_name) => type_env.get_struct_def(&struct_name).fields.clone(),
+            TypeExpr::Duck(Duck { fields }) => fields,
+            _ => vec![],
+        })
+        .find(|variant_fields| {
+            duck.fields
+                .iter()
+                .all(|field| variant_fields.iter().any(|x| x.name == field.name))
+        });
+
+    for field in duck.fields.iter_mut() {
+        if !untyped_fields.contains(&field.name) {
+            continue;
+        }
+
+        let Some(variant_field) = variant_fields
+            .iter()
+            .flatten()
+            .find(|x| x.name == field.name)
+        else {
+            let span = field.type_expr.1;
+            failure(
+                &span.context.file_name(),
+                format!(
+                    "can't infer the type of field {} in duck pattern",
+                    field.name
+                ),
+                (
+                    format!(
+                        "no variant of {} has all fields of this pattern, add a type annotation",
+                        match_on_type.as_clean_user_faced_type_name()
+                    ),
+                    span,
+                ),
+                vec![],
+                &span.context.file_contents(),
+            );
+        };
+
+        let mut type_expr = variant_field.type_expr.0.clone();
+        resolve_all_aliases_type_expr(&mut type_expr, type_env);
+        field.type_expr.0 = type_expr;
+    }
+}
+
+// a loop has the type of the values its breaks carry, or unit if they carry none
+// a labeled break leaves the loop with the label, any other break the innermost loop
+fn typeresolve_break(
+    value_expr: &mut Option<Box<Spanned<ValueExpr>>>,
+    breaks_index: Option<usize>,
+    type_env: &mut TypeEnv,
+) {
+    let Some(value_expr) = value_expr else {
+        if let Some(breaks_index) = breaks_index {
+            type_env.loop_breaks[breaks_index].has_plain_break = true;
+        }
+        return;
+    };
+
+    typeresolve_value_expr(&mut value_expr.0, type_env);
+    let type_expr = TypeExpr::from_value_expr(&value_expr.0, type_env).unconst();
+    match breaks_index.map(|breaks_index| &mut type_env.loop_breaks[breaks_index]) {
+        Some(breaks) if breaks.can_carry_value => {
+            breaks.value_types.push((type_expr, value_expr.1));
+        }
+        _ => {
+            let span = value_expr.1;
+            failure(
+                &span.context.file_name(),
+                "break with a value outside of loop".to_string(),
+                (
+                    "while and for loops can't break with a value".to_string(),
+                    span,
+                ),
+                vec![],
+                &span.context.file_contents(),
+            );
+        }
+    }
+}
+
+fn loop_break_type(breaks: LoopBreaks, type_env: &mut TypeEnv) -> TypeExpr {
+    let mut variants = breaks.value_types;
+    if breaks.has_plain_break
+        && let Some((_, span)) = variants.first()
+    {
+        failure(
+            &span.context.file_name(),
+            "loop has breaks with and without a value".to_string(),
+            (
+                "this break carries a value, so every break of the loop needs one".to_string(),
+                *span,
+            ),
+            vec![],
+            &span.context.file_contents(),
+        );
+    }
+
+    variants.sort_by_key(|variant| variant.0.as_clean_go_type_name(type_env));
+    variants.dedup_by_key(|variant| variant.0.as_clean_go_type_name(type_env));
+
+    match variants.len() {
+        0 => TypeExpr::Tuple(vec![]),
+        1 => variants.remove(0).0,
+        _ => TypeExpr::Or(variants),
+    }
+}
+
+pub const ARRAY_METHODS: [&str; 2] = ["len", "push"];
+pub const MAP_METHODS: [&str; 3] = ["len", "has", "remove"];
+pub const CHAN_METHODS: [&str; 2] = ["len", "close"];
+
+// returns the type of the parameter if target_obj.field_name(..) is a builtin method of an array,
+// a map or a channel, that's the element type of an array or channel and the key type of a map
+fn builtin_method_param_type(
+    target_obj: &Spanned<ValueExpr>,
+    field_name: &str,
+    type_env: &mut TypeEnv,
+) -> Option<TypeExpr> {
+    if !ARRAY_METHODS.contains(&field_name)
+        && !MAP_METHODS.contains(&field_name)
+        && !CHAN_METHODS.contains(&field_name)
+    {
+        return None;
+    }
+
+    let mut target_obj = target_obj.0.clone();
+    typeresolve_value_expr(&mut target_obj, type_env);
+    match TypeExpr::from_value_expr_resolved_type_name(&target_obj, type_env) {
+        TypeExpr::Array(element_type) if ARRAY_METHODS.contains(&field_name) => {
+            Some(element_type.0)
+        }
+        TypeExpr::Map(key_type, _) if MAP_METHODS.contains(&field_name) => Some(key_type.0),
+        TypeExpr::Chan(element_type) if CHAN_METHODS.contains(&field_name) => Some(element_type.0),
+        _ => None,
+    }
+}
+
+// whether a value of target can be the receiver of an extension function of type receiver. a
+// duck receiver takes every target with fields of the same names and types
+fn receiver_accepts(receiver: &TypeExpr, target: &TypeExpr, type_env: &mut TypeEnv) -> bool {
+    match receiver {
+        TypeExpr::Duck(Duck { fields }) => {
+            let target_fields = match target {
+                TypeExpr::Duck(Duck { fields }) => fields.clone(),
+                TypeExpr::Struct(struct_name) => {
+                    type_env.get_struct_def(struct_name).fields.clone()
+                }
+                _ => return false,
+            };
+
+            fields.iter().all(|receiver_field| {
+                target_fields
+                    .iter()
+                    .find(|field| field.name == receiver_field.name)
+                    .is_some_and(|field| {
+                        let mut field_type = field.type_expr.0.clone();
+                        resolve_all_aliases_type_expr(&mut field_type, type_env);
+                        receiver_accepts(&receiver_field.type_expr.0, &field_type, type_env)
+                    })
+            })
+        }
+        receiver if receiver.is_string() => target.is_string(),
+        receiver => receiver == target,
+    }
+}
+
+// returns the name and type of the extension function that handles target_obj.method(..), where
+// method_path is the mangled path the mangler found for the method. members of the target always
+// win over extension functions
+fn find_extension_function(
+    target_obj: &Spanned<ValueExpr>,
+    method_path: &str,
+    type_env: &mut TypeEnv,
+) -> Option<(String, TypeExpr)> {
+    let candidates = type_env
+        .function_definitions
+        .iter()
+        .filter(|function_definition| {
+            extension_method_name(&function_definition.name) == Some(method_path)
+        })
+        .map(|function_definition| function_definition.name.clone())
+        .collect::<Vec<_>>();
+
+    if candidates.is_empty() {
+        return None;
+    }
+
+    let field_name = unmangle(method_path).pop().unwrap_or_default();
+    let mut target_obj = target_obj.0.clone();
+    typeresolve_value_expr(&mut target_obj, type_env);
+    let target_type = TypeExpr::from_value_expr_resolved_type_name(&target_obj, type_env);
+
+    let is_member = match &target_type {
+        TypeExpr::Struct(struct_name) => {
+            let struct_def = type_env.get_struct_def(struct_name);
+            struct_def
+                .fields
+                .iter()
+                .any(|field| field.name == field_name)
+                || struct_def
+                    .methods
+                    .iter()
+                    .any(|method| method.name == field_name)
+                || type_env.has_generic_method(struct_name, &field_name)
+        }
+        TypeExpr::Duck(Duck { fields }) => fields.iter().any(|field| field.name == field_name),
+        _ => false,
+    };
+
+    if is_member {
+        return None;
+    }
+
+    candidates.into_iter().find_map(|name| {
+        let extension_type = type_env.get_identifier_type(name.clone())?;
+        let TypeExpr::Fun(extension_params, _) = &extension_type else {
+            return None;
+        };
+        let (_, (receiver, _)) = extension_params.first()?;
+
+        receiver_accepts(receiver, &target_type, type_env).then_some((name, extension_type))
+    })
+}
+
+// a + b -> a.add(b) and a[i] -> a.index(i) if a is a struct with a method of that name,
+// everything else keeps the go operator. lhs has to be typeresolved already
+fn operator_method_call(
+    lhs: &Spanned<ValueExpr>,
+    method_name: &str,
+    rhs: &Spanned<ValueExpr>,
+    type_env: &mut TypeEnv,
+) -> Option<ValueExpr> {
+    let TypeExpr::Struct(struct_name) =
+        TypeExpr::from_value_expr_resolved_type_name(&lhs.0, type_env)
+    else {
+        return None;
+    };
+
+    let has_method = type_env
+        .get_struct_def(&struct_name)
+        .methods
+        .iter()
+        .any(|method| method.name == method_name);
+
+    has_method.then(|| ValueExpr::FunctionCall {
+        target: (
+            ValueExpr::FieldAccess {
+                target_obj: lhs.clone().into(),
+                field_name: method_name.to_string(),
+            },
+            lhs.1,
+        )
+            .into(),
+        params: vec![rhs.clone()],
+        type_params: None,
+    })
+}
+
+// a?.b and a ?? b are a match on a, every variant but .none is bound and given to some_body
+fn optional_match(
+    target: Spanned<ValueExpr>,
+    operator: &str,
+    some_body: impl Fn(Spanned<ValueExpr>) -> Spanned<ValueExpr>,
+    none_body: Spanned<ValueExpr>,
+    type_env: &mut TypeEnv,
+) -> ValueExpr {
+    unwrap_match(
+        target,
+        &format!("{operator} needs an optional value"),
+        ".none",
+        is_none_variant,
+        some_body,
+        |_| none_body.clone(),
+        type_env,
+    )
+}
+
+// x? is a match on x that returns every .none or Err variant and unwraps the rest
+fn propagate_match(target: Spanned<ValueExpr>, type_env: &mut TypeEnv) -> ValueExpr {
+    unwrap_match(
+        target,
+        "? needs a Result or an optional value",
+        ".none or an Err",
+        |variant| is_none_variant(variant) || is_err_variant(variant),
+        |binding| binding,
+        |binding| {
+            let span = binding.1;
+            (ValueExpr::Return(Some(binding.into())), span)
+        },
+        type_env,
+    )
+}
+
+// the variables that x is T narrows where the condition holds, or where it doesn't if holds is
+// false. both sides of an && narrow, and !(x is T) narrows x in the else branch
+fn narrowed_variables(
+    condition: &ValueExpr,
+    holds: bool,
+) -> Vec<(Spanned<ValueExpr>, Spanned<TypeExpr>)> {
+    match condition {
+        ValueExpr::And(lhs, rhs) if holds => {
+            let mut narrowed = narrowed_variables(&lhs.0, holds);
+            narrowed.extend(narrowed_variables(&rhs.0, holds));
+            narrowed
+        }
+        ValueExpr::Or(lhs, rhs) if !holds => {
+            let mut narrowed = narrowed_variables(&lhs.0, holds);
+            narrowed.extend(narrowed_variables(&rhs.0, holds));
+            narrowed
+        }
+        ValueExpr::BoolNegate(inner) => narrowed_variables(&inner.0, !holds),
+        // the right side of an && that its left side narrowed already
+        ValueExpr::Match {
+            arms,
+            else_arm: Some(else_arm),
+            ..
+        } if arms.is_empty() => narrowed_variables(&else_arm.value_expr.0, holds),
+        // x is T is a match on x with a single arm that gives true
+        ValueExpr::Match {
+            value_expr,
+            arms,
+            else_arm: Some(else_arm),
+        } if holds && matches!(value_expr.0, ValueExpr::Variable(..)) => {
+            let [arm] = arms.as_slice() else {
+                return Vec::new();
+            };
+            let is_plain = |arm: &MatchArm, value: bool| {
+                arm.identifier_binding.is_none()
+                    && arm.condition.is_none()
+                    && arm.value_expr.0 == ValueExpr::Bool(value)
+            };
+            if !is_plain(arm, true) || !is_plain(else_arm, false) {
+                return Vec::new();
+            }
+            vec![(value_expr.as_ref().clone(), arm.type_case.clone())]
+        }
+        _ => Vec::new(),
+    }
+}
+
+// the body is wrapped in a match on x with only an else arm of type T that binds x again, so x
+// is a T in there. a variable the body assigns to stays as it is, the assignment has to reach it
+fn narrow_variables(
+    body: &mut Spanned<ValueExpr>,
+    narrowed: Vec<(Spanned<ValueExpr>, Spanned<TypeExpr>)>,
+) {
+    for (variable, type_case) in narrowed {
+        let ValueExpr::Variable(_, name, _) = &variable.0 else {
+            continue;
+        };
+        let name = name.clone();
+
+        let mut is_assigned = false;
+        for_each_value_expr(body, &mut |value_expr| {
+            if let ValueExpr::VarAssign(assignment) = &value_expr.0
+                && let ValueExpr::Variable(_, target, _) = &assignment.0.target.0
+                && *target == name
+            {
+                is_assigned = true;
+            }
+        });
+        if is_assigned {
+            continue;
+        }
+
+        let span = body.1;
+        let inner = std::mem::replace(&mut body.0, ValueExpr::Block(Vec::new()));
+        body.0 = ValueExpr::Match {
+            value_expr: Box::new(variable),
+            arms: Vec::new(),
+            else_arm: Some(Box::new(MatchArm {
+                type_case,
+                identifier_binding: Some(name),
+                condition: None,
+                value_expr: (inner, span),
+                untyped_fields: Vec::new(),
+            })),
+        };
+    }
+}
+
+fn is_none_variant(variant: &TypeExpr) -> bool {
+    matches!(variant, TypeExpr::Tag(tag) if tag == "none")
+}
+
+// std::error::Err<E> is instantiated as std::error::Err_E
+fn is_err_variant(variant: &TypeExpr) -> bool {
+    let err = mangle(&["std", "error", "Err"]);
+    matches!(variant, TypeExpr::Struct(name)
+        if *name == err || name.strip_prefix(&err).is_some_and(|rest| rest.starts_with('_')))
+}
+
+fn unwrap_match(
+    mut target: Spanned<ValueExpr>,
+    message: &str,
+    missing: &str,
+    is_missing: impl Fn(&TypeExpr) -> bool,
+    present_body: impl Fn(Spanned<ValueExpr>) -> Spanned<ValueExpr>,
+    missing_body: impl Fn(Spanned<ValueExpr>) -> Spanned<ValueExpr>,
+    type_env: &mut TypeEnv,
+) -> ValueExpr {
+    typeresolve_value_expr(&mut target.0, type_env);
+    let target_type = TypeExpr::from_value_expr_resolved_type_name(&target.0, type_env);
+    let span = target.1;
+
+    let mut variants = Vec::new();
+    merge_or(&(target_type.clone(), span), &mut variants);
+    if !variants.iter().any(|variant| is_missing(&variant.0)) {
+        failure(
+            &span.context.file_name(),
+            message.to_string(),
+            (
+                format!("this is a {target_type}, it can't be {missing}"),
+                span,
+            ),
+            vec![],
+            &span.context.file_contents(),
+        );
+    }
+
+    let mut present_variants: Vec<Spanned<TypeExpr>> = Vec::new();
+    let mut missing_variants: Vec<Spanned<TypeExpr>> = Vec::new();
+    for variant in variants {
+        let same_kind = if is_missing(&variant.0) {
+            &mut missing_variants
+        } else {
+            &mut present_variants
+        };
+        if !same_kind.iter().any(|(x, _)| *x == variant.0) {
+            same_kind.push(variant);
+        }
+    }
+
+    // the missing arms need their types even if no .none is written anywhere
+    for (missing_type, _) in &missing_variants {
+        type_env.insert_type(missing_type.clone());
+    }
+
+    let binding = format!("optional{MANGLE_SEP}value");
+    let bound = || (ValueExpr::RawVariable(false, vec![binding.clone()]), span);
+    let arm = |type_case: Spanned<TypeExpr>, value_expr: Spanned<ValueExpr>| MatchArm {
+        type_case,
+        identifier_binding: Some(binding.clone()),
+        condition: None,
+        value_expr,
+        untyped_fields: Vec::new(),
+    };
+
+    let single_present_type = match present_variants.as_slice() {
+        [(present_type, _)] => Some(present_type.clone()),
+        _ => None,
+    };
+    let mut arms = present_variants
+        .into_iter()
+        .map(|variant| arm(variant, present_body(bound())))
+        .collect::<Vec<_>>();
+    for variant in missing_variants {
+        let mut value_expr = missing_body(bound());
+        if let Some(present_type) = &single_present_type {
+            replace_if_const(present_type, &mut value_expr.0);
+        }
+        arms.push(arm(variant, value_expr));
+    }
+
+    ValueExpr::Match {
+        value_expr: Box::new(target),
+        arms,
+        else_arm: None,
+    }
+}
+
+// S { x: 1, ..base } -> S { x: 1, y: base.y }, every field the literal needs but doesn't give
+// has to be a field of base. base is read once per field, so it has to be a variable or a field
+fn spell_out_update(literal: &mut ValueExpr, mut base: Spanned<ValueExpr>, type_env: &mut TypeEnv) {
+    let span = base.1;
+    let fail = |msg: String, label: String| -> ! {
+        failure(
+            &span.context.file_name(),
+            msg,
+            (label, span),
+            vec![],
+            &span.context.file_contents(),
+        )
+    };
+
+    let mut place = &base.0;
+    while let ValueExpr::FieldAccess { target_obj, .. } = place {
+        place = &target_obj.0;
+    }
+    if !matches!(place, ValueExpr::Variable(..) | ValueExpr::RawVariable(..)) {
+        fail(
+            "..base has to be a variable".to_string(),
+            "assign this to a variable first, it would be evaluated once per field".to_string(),
+        );
+    }
+
+    typeresolve_value_expr(&mut base.0, type_env);
+    let base_type = TypeExpr::from_value_expr_resolved_type_name(&base.0, type_env);
+    let base_fields = match &base_type {
+        TypeExpr::Struct(name) => type_env
+            .get_struct_def(name)
+            .fields
+            .iter()
+            .map(|field| field.name.clone())
+            .collect::<Vec<_>>(),
+        TypeExpr::Duck(duck) => duck.fields.iter().map(|field| field.name.clone()).collect(),
+        _ => fail(
+            "..base has to be a struct or a duck".to_string(),
+            format!("this is a {base_type}"),
+        ),
+    };
+
+    let (needed_fields, fields) = match literal {
+        ValueExpr::Struct { name, fields, .. } => {
+            let Some(struct_def) = type_env.struct_definitions.iter().find(|x| x.name == *name)
+            else {
+                fail(
+                    format!("unknown struct {}", name.replace(MANGLE_SEP, "::")),
+                    "..base can only fill the fields of a struct".to_string(),
+                );
+            };
+            let needed_fields = struct_def
+                .fields
+                .iter()
+                .map(|field| field.name.clone())
+                .collect::<Vec<_>>();
+            (needed_fields, fields)
+        }
+        ValueExpr::Duck(fields) => (base_fields.clone(), fields),
+        _ => panic!("compiler error: only struct and duck literals have a ..base"),
+    };
+
+    for field_name in needed_fields {
+        if fields.iter().any(|(name, _)| *name == field_name) {
+            continue;
+        }
+        if !base_fields.contains(&field_name) {
+            fail(
+                format!("..base doesn't have the field {field_name}"),
+                format!("this is a {base_type}, {field_name} has to be given explicitly"),
+            );
+        }
+        let field_access = ValueExpr::FieldAccess {
+            target_obj: base.clone().into(),
+            field_name: field_name.clone(),
+        };
+        fields.push((field_name, (field_access, span)));
+    }
+
+    if let ValueExpr::Duck(fields) = literal {
+        fields.sort_by_key(|(name, _)| name.clone());
+    }
+}
+
+// a + b -> a.add(b), a == b -> a.eq(b) and a != b -> !a.eq(b)
+fn typeresolve_operator(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
+    let is_not_equals = matches!(value_expr, ValueExpr::NotEquals(..));
+    let (method_name, lhs, rhs) = match value_expr {
+        ValueExpr::Add(lhs, rhs) => ("add", lhs, rhs),
+        ValueExpr::Sub(lhs, rhs) => ("sub", lhs, rhs),
+        ValueExpr::Mul(lhs, rhs) => ("mul", lhs, rhs),
+        ValueExpr::Div(lhs, rhs) => ("div", lhs, rhs),
+        ValueExpr::Mod(lhs, rhs) => ("mod", lhs, rhs),
+        ValueExpr::Equals(lhs, rhs) | ValueExpr::NotEquals(lhs, rhs) => ("eq", lhs, rhs),
+        _ => panic!("compiler error: only arithmetic operators and == can be overloaded"),
+    };
+
+    typeresolve_value_expr(&mut lhs.0, type_env);
+    let span = lhs.1;
+    let Some(method_call) = operator_method_call(lhs, method_name, rhs, type_env) else {
+        typeresolve_value_expr(&mut rhs.0, type_env);
+        return;
+    };
+
+    *value_expr = if is_not_equals {
+        ValueExpr::BoolNegate((method_call, span).into())
+    } else {
+        method_call
+    };
+    typeresolve_value_expr(value_expr, type_env);
+}
+
+// the mangled name a local name stands for in the module of the current function, when it isn't
+// hidden by another local
+fn module_binding(name: &str, type_env: &TypeEnv) -> String {
+    let mut path = type_env.module.clone();
+    path.push(name.to_string());
+    mangle(&path)
+}
+
+// every use of a const is emitted as the go const, so no variable can hide a const. binding is
+// the mangled name the variable would hide
+fn check_not_const(binding: &str, span: SS, type_env: &TypeEnv) {
+    if type_env.const_names.contains(binding) {
+        failure(
+            &span.context.file_name(),
+            format!(
+                "{} is a const and can't be redeclared",
+                unmangle(binding).join("::")
+            ),
+            (
+                "a variable can't have the name of a const".to_string(),
+                span,
+            ),
+            vec![],
+            &span.context.file_contents(),
+        )
+    }
+}
+
+// consts of the root module aren't prefixed, so a local of another module can have the name of
+// one. such a local is emitted under a name of its own, its uses would be taken for the const
+fn local_name(name: &str, type_env: &TypeEnv) -> String {
+    if type_env.const_names.contains(name) {
+        format!("{name}{GO_IDENT_ESCAPE}local")
+    } else {
+        name.to_string()
+    }
+}
+
+// the renamed local a variable refers to, if it has the name of a const of the root module
+fn shadowing_local(name: &str, type_env: &TypeEnv) -> Option<String> {
+    let local = local_name(name, type_env);
+    (local != name && type_env.get_identifier_type(local.clone()).is_some()).then_some(local)
+}
+
+fn check_initialized_before(global: &GlobalVariable, uninitialized: &[String]) {
+    for_each_value_expr(&global.initializer, &mut |value_expr| {
+        if let ValueExpr::Variable(_, name, _) = &value_expr.0
+            && uninitialized.contains(name)
+        {
+            failure(
+                &value_expr.1.context.file_name(),
+                format!(
+                    "the global {} is used before it's initialized",
+                    unmangle(name).join("::")
+                ),
+                (
+                    "globals are initialized in the order they're declared in".to_string(),
+                    value_expr.1,
+                ),
+                vec![(
+                    format!("this initializes {}", unmangle(&global.name).join("::")),
+                    global.span,
+                )],
+                &value_expr.1.context.file_contents(),
+            )
+        }
+    });
+}
+
+fn typeresolve_global_variable(global: &mut GlobalVariable, type_env: &mut TypeEnv) {
+    check_not_const(&global.name, global.span, type_env);
+
+    if let Some(type_expr) = &mut global.type_expr {
+        resolve_all_aliases_type_expr(&mut type_expr.0, type_env);
+        infer_lambda_signature(&mut global.initializer.0, &type_expr.0, type_env);
+        fill_omitted_optional_fields(&mut global.initializer.0, &type_expr.0, type_env);
+        typeresolve_value_expr(&mut global.initializer.0, type_env);
+        check_type_compatability(
+            type_expr,
+            &(
+                TypeExpr::from_value_expr(&global.initializer.0, type_env),
+                global.initializer.1,
+            ),
+            type_env,
+        );
+    } else {
+        typeresolve_value_expr(&mut global.initializer.0, type_env);
+        let mut type_expr = TypeExpr::from_value_expr(&global.initializer.0, type_env);
+        widen_literal_types(&mut type_expr);
+        global.type_expr = Some((type_expr, global.initializer.1));
+    }
+
+    let (type_expr, _) = global.type_expr.clone().expect("the type was just set");
+    type_env.insert_identifier_type(global.name.clone(), type_expr);
+}
+
+// let x = "a"; gives x the type String instead of the type of the literal, so that another
+// string can be assigned to it later. the same goes for the fields of a duck literal
+fn widen_literal_types(type_expr: &mut TypeExpr) {
+    match type_expr {
+        TypeExpr::Duck(Duck { fields }) => fields
+            .iter_mut()
+            .for_each(|field| widen_literal_types(&mut field.type_expr.0)),
+        TypeExpr::Tuple(elements) => elements
+            .iter_mut()
+            .for_each(|element| widen_literal_types(&mut element.0)),
+        TypeExpr::Array(element) => widen_literal_types(&mut element.0),
+        _ => *type_expr = type_expr.unconst(),
+    }
 }
 
 pub fn replace_if_const(def_type: &TypeExpr, v: &mut ValueExpr) {
@@ -1817,16 +3473,30 @@ fn typeresolve_function_definition(
         *return_type = type_env.insert_type(return_type.clone());
     }
 
+    // methods are named without their module, they're in the module of their struct
+    let owner = match type_env.get_identifier_type("self".to_string()) {
+        Some(TypeExpr::Struct(struct_name)) if !function_definition.name.contains(MANGLE_SEP) => {
+            struct_name
+        }
+        _ => function_definition.name.clone(),
+    };
+    let mut module = unmangle(&owner);
+    module.pop();
+    let outer_module = std::mem::replace(&mut type_env.module, module);
+
     type_env.push_identifier_types();
 
-    if let Some(params) = function_definition.params.clone().as_mut() {
+    if let Some(params) = function_definition.params.as_mut() {
         for p in params {
+            check_not_const(&module_binding(&p.0, type_env), p.1.1, type_env);
+            p.0 = local_name(&p.0, type_env);
             type_env.insert_identifier_type(p.0.clone(), p.1.0.clone());
         }
     }
 
     typeresolve_value_expr(&mut function_definition.value_expr.0, type_env);
     type_env.pop_identifier_types();
+    type_env.module = outer_module;
 }
 
 fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
@@ -1840,6 +3510,7 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
         }
         ValueExpr::RawVariable(_, path) => {
             let ident = mangle(path);
+            let ident = shadowing_local(&ident, type_env).unwrap_or(ident);
             let mut type_expr = type_env
                 .get_identifier_type(ident.clone())
                 .unwrap_or_else(|| panic!("Couldn't resolve type of identifier {ident}"));
@@ -1847,16 +3518,29 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
             *value_expr = ValueExpr::Variable(true, ident, Some(type_expr));
         }
         ValueExpr::VarDecl(declaration) => {
+            let span = declaration.1;
             let declaration = &mut declaration.0;
+            check_not_const(&module_binding(&declaration.name, type_env), span, type_env);
+            declaration.name = local_name(&declaration.name, type_env);
 
             // Resolve the type expression on the declaration
             if let Some(type_expr) = &mut declaration.type_expr {
                 resolve_all_aliases_type_expr(&mut type_expr.0, type_env);
+                infer_lambda_signature(&mut declaration.initializer.0, &type_expr.0, type_env);
+                fill_omitted_optional_fields(
+                    &mut declaration.initializer.0,
+                    &type_expr.0,
+                    type_env,
+                );
                 type_env.insert_identifier_type(declaration.name.clone(), type_expr.0.clone());
             } else {
-                let type_expr = TypeExpr::from_value_expr(&declaration.initializer.0, type_env);
+                // the initializer has to be resolved before its type can be inferred
+                typeresolve_value_expr(&mut declaration.initializer.0, type_env);
+                let mut type_expr = TypeExpr::from_value_expr(&declaration.initializer.0, type_env);
+                widen_literal_types(&mut type_expr);
                 declaration.type_expr = Some((type_expr.clone(), declaration.initializer.1));
                 type_env.insert_identifier_type(declaration.name.clone(), type_expr);
+                return;
             }
 
             typeresolve_value_expr(&mut declaration.initializer.0, type_env);
@@ -1864,7 +3548,7 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
         ValueExpr::FormattedString(contents) => {
             for c in contents {
                 match c {
-                    ValFmtStringContents::Expr(e) => typeresolve_value_expr(&mut e.0, type_env),
+                    ValFmtStringContents::Expr(e, _) => typeresolve_value_expr(&mut e.0, type_env),
                     ValFmtStringContents::String(s) => {
                         type_env.insert_type(TypeExpr::ConstString(s.clone()));
                     }
@@ -1873,8 +3557,50 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
         }
         ValueExpr::ArrayAccess(target, idx) => {
             typeresolve_value_expr(&mut target.0, type_env);
+            if let Some(method_call) = operator_method_call(target, "index", idx, type_env) {
+                *value_expr = method_call;
+                typeresolve_value_expr(value_expr, type_env);
+                return;
+            }
             typeresolve_value_expr(&mut idx.0, type_env);
         }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            typeresolve_value_expr(&mut target.0, type_env);
+            for bound in [start, end].into_iter().flatten() {
+                typeresolve_value_expr(&mut bound.0, type_env);
+            }
+        }
+        ValueExpr::MakeChan {
+            element_type,
+            capacity,
+        } => {
+            resolve_all_aliases_type_expr(&mut element_type.0, type_env);
+            if let Some(capacity) = capacity {
+                typeresolve_value_expr(&mut capacity.0, type_env);
+            }
+            type_env.insert_type(TypeExpr::Chan(element_type.clone().into()));
+        }
+        ValueExpr::Cast(value_expr, type_expr) => {
+            typeresolve_value_expr(&mut value_expr.0, type_env);
+            resolve_all_aliases_type_expr(&mut type_expr.0, type_env);
+            type_env.insert_type(type_expr.0.clone());
+        }
+        ValueExpr::Send(channel, value) => {
+            typeresolve_value_expr(&mut channel.0, type_env);
+            if let TypeExpr::Chan(element_type) =
+                TypeExpr::from_value_expr_resolved_type_name(&channel.0, type_env)
+            {
+                replace_if_const(&element_type.0, &mut value.0);
+            }
+            typeresolve_value_expr(&mut value.0, type_env);
+        }
+        ValueExpr::Receive(channel) => {
+            typeresolve_value_expr(&mut channel.0, type_env);
+            let ty = TypeExpr::from_value_expr(value_expr as &ValueExpr, type_env);
+            type_env.insert_type(ty);
+        }
         ValueExpr::Array(ty, exprs) => {
             if let Some(ty) = ty {
                 resolve_all_aliases_type_expr(&mut ty.0, type_env);
@@ -1889,7 +3615,33 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
             let ty = TypeExpr::from_value_expr(value_expr as &ValueExpr, type_env);
             type_env.insert_type(ty);
         }
-        ValueExpr::InlineGo(..) => {}
+        ValueExpr::Map(ty, entries) => {
+            if let Some(ty) = ty {
+                resolve_all_aliases_type_expr(&mut ty.0, type_env);
+            }
+
+            for (key, value) in entries {
+                if let Some((TypeExpr::Map(key_type, value_type), _)) = ty {
+                    replace_if_const(&key_type.0, &mut key.0);
+                    replace_if_const(&value_type.0, &mut value.0);
+                }
+                typeresolve_value_expr(&mut key.0, type_env);
+                typeresolve_value_expr(&mut value.0, type_env);
+            }
+            let ty = TypeExpr::from_value_expr(value_expr as &ValueExpr, type_env);
+            type_env.insert_type(ty);
+        }
+        ValueExpr::InlineGo(go_code) => {
+            // primitives are wrapped in go structs, ${x} hands their native value to inline go
+            *go_code = map_go_interpolations(go_code, |name| {
+                match type_env.get_identifier_type(name.to_string()) {
+                    Some(type_expr) if type_expr.is_primitive() => {
+                        format!("{name}.as_dgo_{}()", primitive_native_type_name(&type_expr))
+                    }
+                    _ => name.to_string(),
+                }
+            });
+        }
         ValueExpr::Lambda(b) => {
             let LambdaFunctionExpr {
                 params,
@@ -1908,7 +3660,12 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
                 resolve_all_aliases_type_expr(&mut return_type.0, type_env);
             }
 
+            // a break in the lambda can't leave a loop around it
+            let loop_breaks = std::mem::take(&mut type_env.loop_breaks);
+            let loop_labels = std::mem::take(&mut type_env.loop_labels);
             typeresolve_value_expr(&mut value_expr.0, type_env);
+            type_env.loop_breaks = loop_breaks;
+            type_env.loop_labels = loop_labels;
             type_env.pop_identifier_types();
         }
         ValueExpr::FunctionCall {
@@ -1916,6 +3673,171 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
             params,
             type_params,
         } => {
+            // a?.f(x) only calls f if a isn't .none
+            if let ValueExpr::OptionalFieldAccess {
+                target_obj,
+                field_name,
+            } = &target.0
+            {
+                let span = target.1;
+                let (field_name, params, type_params) = (
+                    field_name.clone(),
+                    std::mem::take(params),
+                    type_params.take(),
+                );
+                *value_expr = optional_match(
+                    target_obj.as_ref().clone(),
+                    "?.",
+                    |binding| {
+                        let method = ValueExpr::FieldAccess {
+                            target_obj: binding.into(),
+                            field_name: field_name.clone(),
+                        };
+                        let call = ValueExpr::FunctionCall {
+                            target: (method, span).into(),
+                            params: params.clone(),
+                            type_params: type_params.clone(),
+                        };
+                        (call, span)
+                    },
+                    (ValueExpr::Tag("none".to_string()), span),
+                    type_env,
+                );
+                typeresolve_value_expr(value_expr, type_env);
+                return;
+            }
+
+            // sum(1, 2, 3) -> sum([1, 2, 3]) and sum(...xs) -> sum(xs)
+            if let ValueExpr::Variable(_, name, _) = &target.0
+                && let Some((fixed_params, element_type)) = type_env.variadic_param(name)
+                && params.len() >= fixed_params
+            {
+                let mut values = params.split_off(fixed_params);
+                let array = match values.pop() {
+                    Some((ValueExpr::Spread(array), _)) if values.is_empty() => *array,
+                    value => {
+                        values.extend(value);
+                        (ValueExpr::Array(element_type, values), target.1)
+                    }
+                };
+                params.push(array);
+            }
+
+            // greet("duck") -> greet("duck", "hello") with the defaults of the missing params
+            if let ValueExpr::Variable(_, name, _) = &target.0
+                && let Some(defaults) = type_env.missing_param_defaults(name, params.len())
+            {
+                params.extend(defaults);
+            }
+
+            if let ValueExpr::Variable(_, name, None) = &target.0
+                && let Some(field_names) = type_env.variant_payload_fields(name)
+            {
+                if field_names.len() != params.len() {
+                    let span = target.1;
+                    failure(
+                        &span.context.file_name(),
+                        format!(
+                            "wrong number of values for {}",
+                            name.replace(MANGLE_SEP, "::")
+                        ),
+                        (
+                            format!("this variant takes {} values", field_names.len()),
+                            span,
+                        ),
+                        vec![],
+                        &span.context.file_contents(),
+                    );
+                }
+
+                // Shape::Circle(1.0) -> Shape::Circle { _0: 1.0 }
+                *value_expr = ValueExpr::Struct {
+                    name: name.clone(),
+                    fields: field_names
+                        .into_iter()
+                        .zip(std::mem::take(params))
+                        .collect(),
+                    type_params: None,
+                };
+                typeresolve_value_expr(value_expr, type_env);
+                return;
+            }
+
+            if let ValueExpr::FieldAccess {
+                target_obj,
+                field_name,
+            } = &mut target.0
+                && let Some(method_path) = field_name.strip_suffix(EXTENSION_SEP)
+            {
+                let method_path = method_path.to_string();
+                if type_params.is_none()
+                    && let Some((extension_name, extension_type)) =
+                        find_extension_function(target_obj, &method_path, type_env)
+                {
+                    // x.shout() -> shout__ext__String(x), extensions are plain go functions
+                    let mut extension_params = vec![target_obj.as_ref().clone()];
+                    extension_params.append(params);
+                    let span = target.1;
+                    *value_expr = ValueExpr::FunctionCall {
+                        target: (
+                            ValueExpr::Variable(true, extension_name, Some(extension_type)),
+                            span,
+                        )
+                            .into(),
+                        params: extension_params,
+                        type_params: None,
+                    };
+                    typeresolve_value_expr(value_expr, type_env);
+                    return;
+                }
+
+                // no extension takes the target, so it's a call of its own method
+                *field_name = unmangle(&method_path).pop().unwrap_or_default();
+            }
+
+            if type_params.is_none()
+                && let ValueExpr::FieldAccess {
+                    target_obj,
+                    field_name,
+                } = &mut target.0
+                && let Some(param_type) =
+                    builtin_method_param_type(target_obj, field_name, type_env)
+            {
+                // xs.push(x) or m.has(k) are builtins and not calls of a go method
+                typeresolve_value_expr(&mut target_obj.0, type_env);
+                for (param, _) in params {
+                    replace_if_const(&param_type, param);
+                    typeresolve_value_expr(param, type_env);
+                }
+                return;
+            }
+
+            if type_params.is_none()
+                && let ValueExpr::Variable(_, name, _) = &mut target.0
+                && is_generic_function(name, type_env)
+            {
+                *type_params = Some(infer_type_params(name, params, target.1, type_env));
+                instantiate_function_call(name, type_params, type_env);
+
+                // the instance is only typeresolved with the other generated functions, but its
+                // type is needed right away
+                if let Some(index) = type_env
+                    .generic_fns_generated
+                    .iter()
+                    .position(|fn_def| fn_def.name == *name)
+                {
+                    let mut fn_def = type_env.generic_fns_generated[index].clone();
+                    let fn_type_expr = resolve_function_signature(&mut fn_def, type_env);
+                    for identifier_types in type_env.identifier_types.iter_mut() {
+                        identifier_types.insert(fn_def.name.clone(), fn_type_expr.clone());
+                    }
+                    type_env.generic_fns_generated[index] = fn_def;
+                }
+
+                typeresolve_value_expr(value_expr, type_env);
+                return;
+            }
+
             typeresolve_value_expr(&mut target.0, type_env);
 
             let header: FunHeader;
@@ -1939,6 +3861,8 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
                     fields: _,
                     methods,
                     generics,
+                    doc_comments: _,
+                    attributes: Vec::new(),
                 } = type_env.get_struct_def(struct_name.as_str()).clone();
 
                 assert!(generics.is_none());
@@ -2060,10 +3984,28 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
                 .zip(header.params.iter())
                 .for_each(|(param, param_def)| {
                     replace_if_const(&param_def.0, &mut param.0);
+                    infer_lambda_signature(&mut param.0, &param_def.0, type_env);
+                    fill_omitted_optional_fields(&mut param.0, &param_def.0, type_env);
                     typeresolve_value_expr(&mut param.0, type_env);
                 });
         }
+        ValueExpr::Variable(_, identifier, None)
+            if type_env
+                .variant_payload_fields(identifier)
+                .is_some_and(|field_names| field_names.is_empty()) =>
+        {
+            // Shape::Empty -> Shape::Empty {}
+            *value_expr = ValueExpr::Struct {
+                name: identifier.clone(),
+                fields: Vec::new(),
+                type_params: None,
+            };
+            typeresolve_value_expr(value_expr, type_env);
+        }
         ValueExpr::Variable(_, identifier, type_expr_opt) => {
+            if let Some(local) = shadowing_local(identifier, type_env) {
+                *identifier = local;
+            }
             // if let Some(type_expr) = type_expr_opt {
             //     resolve_all_aliases_type_expr(type_expr, type_env);
             //     return;
@@ -2080,10 +4022,12 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
             r#else,
         } => {
             typeresolve_value_expr(&mut condition.0, type_env);
+            narrow_variables(then, narrowed_variables(&condition.0, true));
             type_env.push_identifier_types();
             typeresolve_value_expr(&mut then.0, type_env);
             type_env.pop_identifier_types();
             if let Some(r#else) = r#else {
+                narrow_variables(r#else, narrowed_variables(&condition.0, false));
                 type_env.push_identifier_types();
                 typeresolve_value_expr(&mut r#else.0, type_env);
                 type_env.pop_identifier_types();
@@ -2091,9 +4035,54 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
         }
         ValueExpr::While { condition, body } => {
             typeresolve_value_expr(&mut condition.0, type_env);
+            narrow_variables(body, narrowed_variables(&condition.0, true));
+            type_env.push_identifier_types();
+            type_env.loop_breaks.push(LoopBreaks::default());
+            typeresolve_value_expr(&mut body.0, type_env);
+            type_env.loop_breaks.pop();
+            type_env.pop_identifier_types();
+        }
+        ValueExpr::For {
+            ident,
+            target,
+            body,
+        } => {
+            typeresolve_value_expr(&mut target.0, type_env);
+            // anything else than an array, a map or a channel is reported by the type checker
+            let target_type = TypeExpr::from_value_expr_resolved_type_name(&target.0, type_env);
+            let item_type = match target_type {
+                // ranging over a channel receives until it's closed
+                TypeExpr::Array(item_type) | TypeExpr::Chan(item_type) => item_type.0,
+                // iterating a map gives its entries as (key, value) tuples
+                TypeExpr::Map(key_type, value_type) => {
+                    type_env.insert_type(TypeExpr::Tuple(vec![*key_type, *value_type]))
+                }
+                other => other,
+            };
+
+            type_env.push_identifier_types();
+            type_env.insert_identifier_type(ident.clone(), item_type);
+            type_env.loop_breaks.push(LoopBreaks::default());
+            typeresolve_value_expr(&mut body.0, type_env);
+            type_env.loop_breaks.pop();
+            type_env.pop_identifier_types();
+        }
+        ValueExpr::Loop { body, break_type } => {
             type_env.push_identifier_types();
+            type_env.loop_breaks.push(LoopBreaks {
+                can_carry_value: true,
+                ..LoopBreaks::default()
+            });
             typeresolve_value_expr(&mut body.0, type_env);
+            let breaks = type_env
+                .loop_breaks
+                .pop()
+                .expect("compiler error: the breaks of this loop were pushed above");
             type_env.pop_identifier_types();
+
+            let type_expr = loop_break_type(breaks, type_env);
+            type_env.insert_type(type_expr.clone());
+            *break_type = Some(type_expr);
         }
         ValueExpr::Tuple(value_exprs) => {
             value_exprs
@@ -2140,36 +4129,183 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
             let ty = TypeExpr::from_value_expr(value_expr as &ValueExpr, type_env);
             type_env.insert_type(ty);
         }
-        ValueExpr::FieldAccess { target_obj, .. } => {
+        ValueExpr::FieldAccess {
+            target_obj,
+            field_name,
+        } => {
             typeresolve_value_expr(&mut target_obj.0, type_env);
+
+            // the payload of an enum variant like Circle(Float) is read as c.0, but stored in _0
+            if field_name.parse::<usize>().is_ok()
+                && let TypeExpr::Struct(struct_name) =
+                    TypeExpr::from_value_expr_resolved_type_name(&target_obj.0, type_env)
+                && type_env
+                    .get_struct_def(&struct_name)
+                    .fields
+                    .iter()
+                    .any(|field| field.name == format!("_{field_name}"))
+            {
+                *field_name = format!("_{field_name}");
+            }
+        }
+        ValueExpr::OptionalFieldAccess {
+            target_obj,
+            field_name,
+        } => {
+            let span = target_obj.1;
+            let field_name = field_name.clone();
+            *value_expr = optional_match(
+                target_obj.as_ref().clone(),
+                "?.",
+                |binding| {
+                    let field_access = ValueExpr::FieldAccess {
+                        target_obj: binding.into(),
+                        field_name: field_name.clone(),
+                    };
+                    (field_access, span)
+                },
+                (ValueExpr::Tag("none".to_string()), span),
+                type_env,
+            );
+            typeresolve_value_expr(value_expr, type_env);
+        }
+        ValueExpr::Coalesce(lhs, rhs) => {
+            let (lhs, rhs) = (lhs.as_ref().clone(), rhs.as_ref().clone());
+            *value_expr = optional_match(lhs, "??", |binding| binding, rhs, type_env);
+            typeresolve_value_expr(value_expr, type_env);
+        }
+        ValueExpr::Propagate(target) => {
+            *value_expr = propagate_match(target.as_ref().clone(), type_env);
+            typeresolve_value_expr(value_expr, type_env);
+        }
+        ValueExpr::Update(literal, base) => {
+            let (mut literal, base) = (literal.as_ref().clone(), base.as_ref().clone());
+            spell_out_update(&mut literal.0, base, type_env);
+            *value_expr = literal.0;
+            typeresolve_value_expr(value_expr, type_env);
+        }
+        ValueExpr::Spread(array) => {
+            let span = array.1;
+            failure(
+                &span.context.file_name(),
+                "unexpected spread".to_string(),
+                (
+                    "only an array can be spread, as the only value of a variadic param"
+                        .to_string(),
+                    span,
+                ),
+                vec![],
+                &span.context.file_contents(),
+            );
         }
         ValueExpr::Return(Some(value_expr)) => typeresolve_value_expr(&mut value_expr.0, type_env),
+        ValueExpr::Break(value_expr) => {
+            let breaks_index = type_env.loop_breaks.len().checked_sub(1);
+            typeresolve_break(value_expr, breaks_index, type_env);
+        }
+        ValueExpr::Labeled(label, labeled) => {
+            if !matches!(labeled.0, ValueExpr::Break(..) | ValueExpr::Continue) {
+                let breaks_index = type_env.loop_breaks.len();
+                type_env.loop_labels.push((label.clone(), breaks_index));
+                typeresolve_value_expr(&mut labeled.0, type_env);
+                type_env.loop_labels.pop();
+                return;
+            }
+
+            let Some(breaks_index) = type_env
+                .loop_labels
+                .iter()
+                .rev()
+                .find(|(name, _)| name == label)
+                .map(|(_, breaks_index)| *breaks_index)
+            else {
+                let span = labeled.1;
+                failure(
+                    &span.context.file_name(),
+                    format!("unknown label @{label}"),
+                    (
+                        format!("there's no loop labeled @{label} around this"),
+                        span,
+                    ),
+                    vec![],
+                    &span.context.file_contents(),
+                );
+            };
+            if let ValueExpr::Break(value_expr) = &mut labeled.0 {
+                typeresolve_break(value_expr, Some(breaks_index), type_env);
+            }
+        }
         ValueExpr::VarAssign(assignment) => {
-            typeresolve_value_expr(&mut assignment.0.target.0, type_env);
+            // p[i] = x can't be a call of index, so only arrays and maps can be assigned with []
+            if let ValueExpr::ArrayAccess(target, idx) = &mut assignment.0.target.0 {
+                typeresolve_value_expr(&mut target.0, type_env);
+                typeresolve_value_expr(&mut idx.0, type_env);
+            } else {
+                typeresolve_value_expr(&mut assignment.0.target.0, type_env);
+            }
+            if let (ValueExpr::Variable(_, name, _), span) = &assignment.0.target
+                && type_env.const_names.contains(name)
+            {
+                failure(
+                    &span.context.file_name(),
+                    format!("can't assign to the const {}", unmangle(name).join("::")),
+                    ("consts can't be changed".to_string(), *span),
+                    vec![],
+                    &span.context.file_contents(),
+                )
+            }
             let target_type = TypeExpr::from_value_expr(&assignment.0.target.0, type_env);
             replace_if_const(&target_type, &mut assignment.0.value_expr.0);
             typeresolve_value_expr(&mut assignment.0.value_expr.0, type_env);
         }
-        ValueExpr::Add(lhs, rhs)
-        | ValueExpr::Sub(lhs, rhs)
-        | ValueExpr::Div(lhs, rhs)
-        | ValueExpr::Mul(lhs, rhs)
-        | ValueExpr::Mod(lhs, rhs) => {
+        ValueExpr::Add(..)
+        | ValueExpr::Sub(..)
+        | ValueExpr::Div(..)
+        | ValueExpr::Mul(..)
+        | ValueExpr::Mod(..)
+        | ValueExpr::Equals(..)
+        | ValueExpr::NotEquals(..) => typeresolve_operator(value_expr, type_env),
+        ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        } => {
             typeresolve_value_expr(&mut lhs.0, type_env);
             typeresolve_value_expr(&mut rhs.0, type_env);
         }
-        ValueExpr::Equals(lhs, rhs)
-        | ValueExpr::NotEquals(lhs, rhs)
-        | ValueExpr::LessThan(lhs, rhs)
+        ValueExpr::LessThan(lhs, rhs)
         | ValueExpr::LessThanOrEquals(lhs, rhs)
         | ValueExpr::GreaterThan(lhs, rhs)
-        | ValueExpr::GreaterThanOrEquals(lhs, rhs)
-        | ValueExpr::And(lhs, rhs)
-        | ValueExpr::Or(lhs, rhs) => {
+        | ValueExpr::GreaterThanOrEquals(lhs, rhs) => {
             typeresolve_value_expr(&mut lhs.0, type_env);
             typeresolve_value_expr(&mut rhs.0, type_env);
         }
-        ValueExpr::BoolNegate(value_expr) => {
+        // the right side only runs if the left side holds for &&, or doesn't hold for ||, so
+        // x is T && x.name > "a" can use x as a T on the right
+        ValueExpr::And(lhs, rhs) => {
+            typeresolve_value_expr(&mut lhs.0, type_env);
+            narrow_variables(rhs, narrowed_variables(&lhs.0, true));
+            typeresolve_value_expr(&mut rhs.0, type_env);
+        }
+        ValueExpr::Or(lhs, rhs) => {
+            typeresolve_value_expr(&mut lhs.0, type_env);
+            narrow_variables(rhs, narrowed_variables(&lhs.0, false));
+            typeresolve_value_expr(&mut rhs.0, type_env);
+        }
+        ValueExpr::Comptime(..) => {
+            panic!("compiler error: comptime blocks should be evaluated by now")
+        }
+        ValueExpr::BoolNegate(value_expr)
+        | ValueExpr::BitNegate(value_expr)
+        | ValueExpr::TypeOf(value_expr)
+        | ValueExpr::Negate(value_expr)
+        | ValueExpr::Defer(value_expr)
+        | ValueExpr::Spawn(value_expr) => {
             typeresolve_value_expr(&mut value_expr.0, type_env);
         }
         ValueExpr::Match {
@@ -2178,11 +4314,24 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
             else_arm,
         } => {
             typeresolve_value_expr(&mut value_expr.0, type_env);
+
+            let match_on_type =
+                TypeExpr::from_value_expr_resolved_type_name(&value_expr.0, type_env);
+            arms.iter_mut().for_each(|arm| {
+                if let TypeExpr::Duck(duck) = &mut arm.type_case.0 {
+                    infer_duck_pattern_fields(duck, &arm.untyped_fields, &match_on_type, type_env);
+                    type_env.insert_type(arm.type_case.0.clone());
+                }
+            });
+
             arms.iter_mut().for_each(|arm| {
                 type_env.push_identifier_types();
                 if let Some(identifier) = &arm.identifier_binding {
                     type_env.insert_identifier_type(identifier.clone(), arm.type_case.0.clone());
                 }
+                if let Some(condition) = &mut arm.condition {
+                    typeresolve_value_expr(&mut condition.0, type_env);
+                }
                 typeresolve_value_expr(&mut arm.value_expr.0, type_env);
                 type_env.pop_identifier_types();
             });
@@ -2192,10 +4341,39 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
                 if let Some(identifier) = &arm.identifier_binding {
                     type_env.insert_identifier_type(identifier.clone(), arm.type_case.0.clone());
                 }
+                if let Some(condition) = &mut arm.condition {
+                    typeresolve_value_expr(&mut condition.0, type_env);
+                }
                 typeresolve_value_expr(&mut arm.value_expr.0, type_env);
                 type_env.pop_identifier_types();
             }
         }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                typeresolve_value_expr(&mut arm.case.0, type_env);
+                type_env.push_identifier_types();
+                if let Some(binding) = &arm.binding {
+                    let received_type = TypeExpr::from_value_expr(&arm.case.0, type_env);
+                    type_env.insert_identifier_type(binding.clone(), received_type);
+                }
+                typeresolve_value_expr(&mut arm.value_expr.0, type_env);
+                type_env.pop_identifier_types();
+            }
+            if let Some(else_arm) = else_arm {
+                typeresolve_value_expr(&mut else_arm.0, type_env);
+            }
+        }
+        ValueExpr::Recover {
+            body,
+            binding,
+            handler,
+        } => {
+            typeresolve_value_expr(&mut body.0, type_env);
+            type_env.push_identifier_types();
+            type_env.insert_identifier_type(binding.clone(), TypeExpr::String);
+            typeresolve_value_expr(&mut handler.0, type_env);
+            type_env.pop_identifier_types();
+        }
         ValueExpr::String(str, _) => {
             type_env.insert_type(TypeExpr::ConstString(str.clone()));
         }
@@ -2206,7 +4384,6 @@ fn typeresolve_value_expr(value_expr: &mut ValueExpr, type_env: &mut TypeEnv) {
         | ValueExpr::Bool(..)
         | ValueExpr::Char(..)
         | ValueExpr::Float(..)
-        | ValueExpr::Break
         | ValueExpr::Return(None)
         | ValueExpr::Continue => {}
     }
@@ -2235,7 +4412,7 @@ fn resolve_implicit_function_return_type(
             }
             ValueExpr::FormattedString(contents) => {
                 for c in contents {
-                    if let ValFmtStringContents::Expr(e) = c {
+                    if let ValFmtStringContents::Expr(e, _) = c {
                         flatten_returns(&e.0, return_types_found, type_env);
                     }
                 }
@@ -2245,6 +4422,12 @@ fn resolve_implicit_function_return_type(
                     flatten_returns(&expr.0, return_types_found, type_env);
                 }
             }
+            ValueExpr::Map(_, entries) => {
+                for (key, value) in entries {
+                    flatten_returns(&key.0, return_types_found, type_env);
+                    flatten_returns(&value.0, return_types_found, type_env);
+                }
+            }
             ValueExpr::If {
                 condition,
                 then,
@@ -2256,7 +4439,12 @@ fn resolve_implicit_function_return_type(
                     flatten_returns(&r#else.as_ref().0, return_types_found, type_env);
                 });
             }
-            ValueExpr::While { condition, body } => {
+            ValueExpr::While { condition, body }
+            | ValueExpr::For {
+                target: condition,
+                body,
+                ..
+            } => {
                 flatten_returns(&condition.as_ref().0, return_types_found, type_env);
                 flatten_returns(&body.as_ref().0, return_types_found, type_env)
             }
@@ -2279,15 +4467,53 @@ fn resolve_implicit_function_return_type(
             ValueExpr::VarDecl(declaration) => {
                 flatten_returns(&declaration.0.initializer.0, return_types_found, type_env);
             }
+            ValueExpr::Select { arms, else_arm } => {
+                for arm in arms {
+                    flatten_returns(&arm.value_expr.0, return_types_found, type_env);
+                }
+                if let Some(else_arm) = else_arm {
+                    flatten_returns(&else_arm.0, return_types_found, type_env);
+                }
+            }
+            ValueExpr::Recover { body, handler, .. } => {
+                flatten_returns(&body.0, return_types_found, type_env);
+                flatten_returns(&handler.0, return_types_found, type_env);
+            }
             ValueExpr::Add(left, right)
             | ValueExpr::Sub(left, right)
             | ValueExpr::Mod(left, right)
+            | ValueExpr::BitAnd(left, right)
+            | ValueExpr::BitOr(left, right)
+            | ValueExpr::BitXor(left, right)
+            | ValueExpr::ShiftLeft(left, right)
+            | ValueExpr::ShiftRight(left, right)
+            | ValueExpr::Send(left, right)
+            | ValueExpr::Range {
+                start: left,
+                end: right,
+                ..
+            }
             | ValueExpr::Div(left, right)
             | ValueExpr::Mul(left, right) => {
                 flatten_returns(&left.as_ref().0, return_types_found, type_env);
                 flatten_returns(&right.as_ref().0, return_types_found, type_env);
             }
-            ValueExpr::BoolNegate(value_expr) => {
+            ValueExpr::Comptime(..) => {
+                panic!("compiler error: comptime blocks should be evaluated by now")
+            }
+            ValueExpr::BoolNegate(value_expr)
+            | ValueExpr::BitNegate(value_expr)
+            | ValueExpr::TypeOf(value_expr)
+            | ValueExpr::Cast(value_expr, _)
+            | ValueExpr::Negate(value_expr)
+            | ValueExpr::Receive(value_expr)
+            | ValueExpr::Spread(value_expr)
+            | ValueExpr::Labeled(_, value_expr)
+            | ValueExpr::Propagate(value_expr)
+            | ValueExpr::Break(Some(value_expr))
+            | ValueExpr::Loop {
+                body: value_expr, ..
+            } => {
                 flatten_returns(&value_expr.as_ref().0, return_types_found, type_env);
             }
             ValueExpr::Equals(lhs, rhs)
@@ -2297,11 +4523,16 @@ fn resolve_implicit_function_return_type(
             | ValueExpr::GreaterThan(lhs, rhs)
             | ValueExpr::GreaterThanOrEquals(lhs, rhs)
             | ValueExpr::And(lhs, rhs)
-            | ValueExpr::Or(lhs, rhs) => {
+            | ValueExpr::Or(lhs, rhs)
+            | ValueExpr::Update(lhs, rhs)
+            | ValueExpr::Coalesce(lhs, rhs) => {
                 flatten_returns(&lhs.as_ref().0, return_types_found, type_env);
                 flatten_returns(&rhs.as_ref().0, return_types_found, type_env);
             }
             ValueExpr::FunctionCall { .. }
+            | ValueExpr::Defer(..)
+            | ValueExpr::Spawn(..)
+            | ValueExpr::MakeChan { .. }
             | ValueExpr::Int(..)
             | ValueExpr::InlineGo(..)
             | ValueExpr::String(..)
@@ -2309,17 +4540,19 @@ fn resolve_implicit_function_return_type(
             | ValueExpr::Float(..)
             | ValueExpr::Char(..)
             | ValueExpr::Tuple(..)
-            | ValueExpr::Break
+            | ValueExpr::Break(None)
             | ValueExpr::Continue
             | ValueExpr::Duck(..)
             | ValueExpr::Struct { .. }
             | ValueExpr::FieldAccess { .. }
+            | ValueExpr::OptionalFieldAccess { .. }
             | ValueExpr::Lambda(..)
             | ValueExpr::Variable(..)
             | ValueExpr::RawVariable(..)
             | ValueExpr::Tag(..)
             | ValueExpr::Match { .. }
-            | ValueExpr::ArrayAccess(..) => {}
+            | ValueExpr::ArrayAccess(..)
+            | ValueExpr::Slice { .. } => {}
         }
     }
 
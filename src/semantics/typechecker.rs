@@ -5,13 +5,14 @@ use chumsky::container::Seq;
 use colored::Colorize;
 
 use crate::parse::struct_parser::StructDefinition;
-use crate::parse::type_parser::{Duck, TypeExpr};
+use crate::parse::type_parser::{Duck, TypeExpr, merge_or};
 use crate::parse::{Field, SS, failure_with_occurence};
 use crate::parse::{
     Spanned, failure,
     value_parser::{ValFmtStringContents, ValueExpr},
 };
 use crate::semantics::type_resolve::TypeEnv;
+use crate::semantics::visibility::for_each_value_expr;
 
 impl TypeExpr {
     pub fn as_clean_user_faced_type_name(&self) -> String {
@@ -48,13 +49,41 @@ impl TypeExpr {
             ValueExpr::RawVariable(_x, p) => panic!("{}", p.join(" ").leak()),
             ValueExpr::FormattedString(contents) => {
                 for c in contents {
-                    if let ValFmtStringContents::Expr(e) = c {
-                        require(
+                    match c {
+                        ValFmtStringContents::Expr(e, None) => require(
                             // matches!(TypeExpr::from_value_expr(&e.0, type_env), TypeExpr::String) ||
                             // matches!(TypeExpr::from_value_expr(&e.0, type_env), TypeExpr::ConstString(..)),
                             TypeExpr::from_value_expr(&e.0, type_env).is_string(),
                             "Needs to be string".into(),
-                        );
+                        ),
+                        ValFmtStringContents::Expr(e, Some(spec)) => {
+                            let type_expr = TypeExpr::from_value_expr(&e.0, type_env).unconst();
+
+                            require(
+                                type_expr.is_number()
+                                    || type_expr.is_string()
+                                    || type_expr.is_char()
+                                    || type_expr.is_bool(),
+                                format!(
+                                    "Format specs are only allowed for primitives. You've used {}.",
+                                    type_expr.as_go_type_annotation(type_env)
+                                ),
+                            );
+
+                            // x, X, o and b print the digits of an int, e the exponent of a float
+                            if spec.ends_with(['x', 'X', 'o', 'b']) {
+                                require(
+                                    type_expr.is_int(),
+                                    format!("The format spec {spec} is only allowed for ints."),
+                                );
+                            } else if spec.ends_with('e') {
+                                require(
+                                    type_expr.is_float(),
+                                    format!("The format spec {spec} is only allowed for floats."),
+                                );
+                            }
+                        }
+                        ValFmtStringContents::String(_) => {}
                     }
                 }
                 TypeExpr::String
@@ -63,6 +92,11 @@ impl TypeExpr {
                 let target_type = TypeExpr::from_value_expr(&target.0, type_env);
                 let idx_type = TypeExpr::from_value_expr(&idx.0, type_env);
 
+                if let TypeExpr::Map(key_type, value_type) = target_type {
+                    check_type_compatability(&key_type, &(idx_type, idx.1), type_env);
+                    return value_type.0;
+                }
+
                 require(target_type.is_array(), "Needs to be array".into());
                 require(idx_type.is_int(), "Needs to be int".into());
 
@@ -72,6 +106,83 @@ impl TypeExpr {
 
                 array_type.0.clone()
             }
+            ValueExpr::Slice {
+                target,
+                start,
+                end,
+                inclusive,
+            } => {
+                let target_type = TypeExpr::from_value_expr_resolved_type_name(&target.0, type_env);
+                require(
+                    target_type.is_array() || target_type.is_string(),
+                    format!(
+                        "Only arrays and strings can be sliced. You've used {}.",
+                        target_type.as_go_type_annotation(type_env)
+                    ),
+                );
+
+                for bound in [start, end].into_iter().flatten() {
+                    let bound_type = TypeExpr::from_value_expr(&bound.0, type_env);
+                    require(
+                        bound_type.unconst().is_int(),
+                        format!(
+                            "Slice bounds need to be ints. You've used {}.",
+                            bound_type.as_go_type_annotation(type_env)
+                        ),
+                    );
+                }
+
+                // the bounds that are literals can be checked here, the rest panics at runtime
+                let start_value = start.as_ref().and_then(|start| const_slice_bound(&start.0));
+                let end_value = end
+                    .as_ref()
+                    .and_then(|end| const_slice_bound(&end.0))
+                    .map(|end| if *inclusive { end + 1 } else { end });
+                let length = match (&target.0, &target_type) {
+                    (ValueExpr::Array(_, elems), _)
+                        if !elems
+                            .iter()
+                            .any(|elem| matches!(elem.0, ValueExpr::Spread(..))) =>
+                    {
+                        Some(elems.len() as i128)
+                    }
+                    (_, TypeExpr::ConstString(s)) => Some(s.len() as i128),
+                    _ => None,
+                };
+
+                fn slice_bound_failure(bound: &Spanned<ValueExpr>, msg: String) -> ! {
+                    failure(
+                        &bound.1.context.file_name(),
+                        "Slice out of bounds".to_string(),
+                        (msg, bound.1),
+                        vec![],
+                        &bound.1.context.file_contents(),
+                    )
+                }
+                for (bound, value) in [(start, start_value), (end, end_value)] {
+                    if let (Some(bound), Some(value)) = (bound, value) {
+                        if value < 0 {
+                            slice_bound_failure(bound, "slice bounds can't be negative".into());
+                        }
+                        if let Some(length) = length
+                            && value > length
+                        {
+                            slice_bound_failure(
+                                bound,
+                                format!("this is past the end, the length is only {length}"),
+                            );
+                        }
+                    }
+                }
+                if let (Some(start_value), Some(end_value), Some(end)) =
+                    (start_value, end_value, end)
+                    && start_value > end_value
+                {
+                    slice_bound_failure(end, "the slice ends before it starts".into());
+                }
+
+                target_type.unconst()
+            }
             ValueExpr::Array(optional_type_support, value_exprs) => {
                 if let Some(type_support) = optional_type_support {
                     for value_expr in value_exprs {
@@ -126,6 +237,59 @@ impl TypeExpr {
                     .expect("we've checked that variants is exactly of len 1");
                 return TypeExpr::Array(Box::new(first_type.clone()));
             }
+            ValueExpr::Map(optional_type_support, entries) => {
+                if let Some(type_support) = optional_type_support {
+                    let TypeExpr::Map(key_type, value_type) = &type_support.0 else {
+                        panic!("compiler error: a typed map literal needs a map type")
+                    };
+
+                    for (key, value) in entries {
+                        let given_key_type = (TypeExpr::from_value_expr(&key.0, type_env), key.1);
+                        check_type_compatability(key_type, &given_key_type, type_env);
+                        let given_value_type =
+                            (TypeExpr::from_value_expr(&value.0, type_env), value.1);
+                        check_type_compatability(value_type, &given_value_type, type_env);
+                    }
+
+                    return type_support.0.clone();
+                }
+
+                let (first_key, first_value) = entries
+                    .first()
+                    .expect("compiler error: an empty map literal has to provide its type");
+                let key_type = (
+                    TypeExpr::from_value_expr(&first_key.0, type_env).unconst(),
+                    first_key.1,
+                );
+                if !key_type.0.is_hashable() {
+                    failure(
+                        &first_key.1.context.file_name(),
+                        "Unhashable map key".to_string(),
+                        (
+                            format!("{} can't be used as a map key", key_type.0),
+                            first_key.1,
+                        ),
+                        vec![(
+                            "map keys have to be Int, Float, Bool, Char or String".to_string(),
+                            first_key.1,
+                        )],
+                        &first_key.1.context.file_contents(),
+                    );
+                }
+                let value_type = (
+                    TypeExpr::from_value_expr(&first_value.0, type_env).unconst(),
+                    first_value.1,
+                );
+
+                for (key, value) in entries.iter().skip(1) {
+                    let given_key_type = (TypeExpr::from_value_expr(&key.0, type_env), key.1);
+                    check_type_compatability(&key_type, &given_key_type, type_env);
+                    let given_value_type = (TypeExpr::from_value_expr(&value.0, type_env), value.1);
+                    check_type_compatability(&value_type, &given_value_type, type_env);
+                }
+
+                return TypeExpr::Map(Box::new(key_type), Box::new(value_type));
+            }
             ValueExpr::Lambda(lambda_expr) => TypeExpr::Fun(
                 lambda_expr
                     .params
@@ -135,7 +299,17 @@ impl TypeExpr {
                 lambda_expr.return_type.clone().map(Box::new),
             ),
             ValueExpr::InlineGo(..) => TypeExpr::InlineGo,
-            ValueExpr::Int(..) => TypeExpr::Int,
+            ValueExpr::Int(i) => {
+                require(
+                    i64::try_from(*i).is_ok(),
+                    format!(
+                        "The integer literal {i} doesn't fit into an Int. Ints have to be between {} and {}.",
+                        i64::MIN,
+                        i64::MAX
+                    ),
+                );
+                TypeExpr::Int
+            }
             ValueExpr::Bool(..) => TypeExpr::Bool,
             ValueExpr::Char(..) => TypeExpr::Char,
             ValueExpr::Float(..) => TypeExpr::Float,
@@ -146,8 +320,13 @@ impl TypeExpr {
                     TypeExpr::String
                 }
             }
-            ValueExpr::Break => TypeExpr::Tuple(vec![]),
+            ValueExpr::Break(Some(value_expr)) => {
+                let _value_type_expr = TypeExpr::from_value_expr(&value_expr.0, type_env);
+                TypeExpr::Tuple(vec![])
+            }
+            ValueExpr::Break(None) => TypeExpr::Tuple(vec![]),
             ValueExpr::Continue => TypeExpr::Tuple(vec![]),
+            ValueExpr::Labeled(_, value_expr) => TypeExpr::from_value_expr(&value_expr.0, type_env),
             ValueExpr::Return(Some(value_expr)) => {
                 TypeExpr::from_value_expr(&value_expr.0, type_env)
             }
@@ -255,9 +434,9 @@ impl TypeExpr {
                 let right_type_expr: TypeExpr = TypeExpr::from_value_expr(&right.0, type_env);
 
                 require(
-                    left_type_expr.is_number(),
+                    left_type_expr.is_number() || left_type_expr.is_string(),
                     format!(
-                        "Addition '+' is only allowed for numbers. You've used {} + {}.",
+                        "Addition '+' only works for numbers and strings. You've used {} + {}.",
                         left_type_expr.as_go_type_annotation(type_env),
                         right_type_expr.as_go_type_annotation(type_env)
                     ),
@@ -269,6 +448,11 @@ impl TypeExpr {
                     type_env,
                 );
 
+                // concatenating two const strings doesn't give a const string
+                if left_type_expr.is_string() {
+                    return TypeExpr::String;
+                }
+
                 left_type_expr
             }
             ValueExpr::Sub(left, right) => {
@@ -357,6 +541,18 @@ impl TypeExpr {
                 let left_type_expr: TypeExpr = TypeExpr::from_value_expr(&left.0, type_env);
                 let right_type_expr: TypeExpr = TypeExpr::from_value_expr(&right.0, type_env);
 
+                if left_type_expr.is_string() {
+                    require(
+                        right_type_expr.unconst().is_int(),
+                        format!(
+                            "Strings can only be repeated by an Int. You've used {} * {}.",
+                            left_type_expr.as_go_type_annotation(type_env),
+                            right_type_expr.as_go_type_annotation(type_env)
+                        ),
+                    );
+                    return TypeExpr::String;
+                }
+
                 require(
                     left_type_expr.is_number(),
                     format!(
@@ -379,6 +575,40 @@ impl TypeExpr {
                 params,
                 type_params: _,
             } => {
+                if let ValueExpr::FieldAccess {
+                    target_obj,
+                    field_name,
+                } = &target.0
+                    && let Some((param_type, return_type)) = builtin_method_type(
+                        &TypeExpr::from_value_expr_resolved_type_name(&target_obj.0, type_env),
+                        field_name,
+                    )
+                {
+                    let expected_params = usize::from(param_type.is_some());
+                    if params.len() != expected_params {
+                        failure(
+                            &target.1.context.file_name(),
+                            format!("wrong number of arguments for {field_name}"),
+                            (
+                                format!("{field_name} takes {expected_params} arguments"),
+                                target.1,
+                            ),
+                            vec![],
+                            &target.1.context.file_contents(),
+                        );
+                    }
+
+                    if let Some(param_type) = param_type {
+                        let param = &params[0];
+                        check_type_compatability(
+                            &param_type,
+                            &(TypeExpr::from_value_expr(&param.0, type_env), param.1),
+                            type_env,
+                        );
+                    }
+                    return return_type;
+                }
+
                 // todo: type_params
                 let in_param_types = params
                     .iter()
@@ -406,7 +636,7 @@ impl TypeExpr {
                 }
 
                 failure(
-                    target.as_ref().1.context.file_name,
+                    &target.as_ref().1.context.file_name(),
                     "Tried to invoke a non-function value".to_string(),
                     (
                         "This is the value you tried to invoke as a function.".to_string(),
@@ -430,7 +660,7 @@ impl TypeExpr {
                             target.as_ref().1,
                         ),
                     ],
-                    target.as_ref().1.context.file_contents,
+                    &target.as_ref().1.context.file_contents(),
                 )
             }
             ValueExpr::Block(value_exprs) => {
@@ -460,6 +690,127 @@ impl TypeExpr {
                     })
                     .clone()
             }
+            ValueExpr::Comptime(..) => {
+                panic!("compiler error: comptime blocks should be evaluated by now")
+            }
+            ValueExpr::OptionalFieldAccess { .. }
+            | ValueExpr::Coalesce(..)
+            | ValueExpr::Propagate(..) => {
+                panic!("compiler error: ?., ?? and ? should be turned into a match by now")
+            }
+            ValueExpr::Spread(..) => {
+                panic!("compiler error: a spread should be unpacked into its call by now")
+            }
+            ValueExpr::Update(..) => {
+                panic!("compiler error: ..base should be spelled out by now")
+            }
+            ValueExpr::BitAnd(left, right)
+            | ValueExpr::BitOr(left, right)
+            | ValueExpr::BitXor(left, right)
+            | ValueExpr::ShiftLeft(left, right)
+            | ValueExpr::ShiftRight(left, right) => {
+                let left_type_expr: TypeExpr = TypeExpr::from_value_expr(&left.0, type_env);
+                let right_type_expr: TypeExpr = TypeExpr::from_value_expr(&right.0, type_env);
+
+                require(
+                    left_type_expr.unconst().is_int() && right_type_expr.unconst().is_int(),
+                    format!(
+                        "Bitwise operators are only allowed for ints. You've used {} and {}.",
+                        left_type_expr.as_go_type_annotation(type_env),
+                        right_type_expr.as_go_type_annotation(type_env)
+                    ),
+                );
+
+                TypeExpr::Int
+            }
+            ValueExpr::Range { start, end, .. } => {
+                let start_type_expr: TypeExpr = TypeExpr::from_value_expr(&start.0, type_env);
+                let end_type_expr: TypeExpr = TypeExpr::from_value_expr(&end.0, type_env);
+
+                require(
+                    start_type_expr.unconst().is_int() && end_type_expr.unconst().is_int(),
+                    format!(
+                        "Ranges are only allowed between ints. You've used {}..{}.",
+                        start_type_expr.as_go_type_annotation(type_env),
+                        end_type_expr.as_go_type_annotation(type_env)
+                    ),
+                );
+
+                TypeExpr::Array(Box::new(TypeExpr::Int.into_empty_span()))
+            }
+            ValueExpr::BitNegate(int_expr) => {
+                check_type_compatability(
+                    &(TypeExpr::from_value_expr(&int_expr.0, type_env), int_expr.1),
+                    &TypeExpr::Int.into_empty_span(),
+                    type_env,
+                );
+                TypeExpr::Int
+            }
+            ValueExpr::TypeOf(value_expr) => {
+                TypeExpr::from_value_expr(&value_expr.0, type_env);
+                TypeExpr::String
+            }
+            ValueExpr::Cast(value_expr, type_expr) => {
+                check_cast(value_expr, type_expr, type_env);
+                type_expr.0.clone()
+            }
+            ValueExpr::Negate(number_expr) => {
+                let type_expr = TypeExpr::from_value_expr(&number_expr.0, type_env).unconst();
+                require(
+                    type_expr.is_number(),
+                    format!(
+                        "Only numbers can be negated. You've used {}.",
+                        type_expr.as_go_type_annotation(type_env)
+                    ),
+                );
+                type_expr
+            }
+            ValueExpr::Defer(call) | ValueExpr::Spawn(call) => {
+                TypeExpr::from_value_expr(&call.0, type_env);
+                TypeExpr::Tuple(vec![])
+            }
+            ValueExpr::MakeChan {
+                element_type,
+                capacity,
+            } => {
+                if let Some(capacity) = capacity {
+                    check_type_compatability(
+                        &TypeExpr::Int.into_empty_span(),
+                        &(TypeExpr::from_value_expr(&capacity.0, type_env), capacity.1),
+                        type_env,
+                    );
+                }
+                TypeExpr::Chan(element_type.clone().into())
+            }
+            ValueExpr::Send(channel, value) => {
+                let element_type = channel_element_type(channel, "send to", type_env);
+                check_type_compatability(
+                    &element_type,
+                    &(TypeExpr::from_value_expr(&value.0, type_env), value.1),
+                    type_env,
+                );
+                TypeExpr::Tuple(vec![])
+            }
+            ValueExpr::Receive(channel) => {
+                channel_element_type(channel, "receive from", type_env).0
+            }
+            ValueExpr::Select { arms, else_arm } => {
+                for arm in arms {
+                    TypeExpr::from_value_expr(&arm.case.0, type_env);
+                    TypeExpr::from_value_expr(&arm.value_expr.0, type_env);
+                }
+                if let Some(else_arm) = else_arm {
+                    TypeExpr::from_value_expr(&else_arm.0, type_env);
+                }
+                TypeExpr::Tuple(vec![])
+            }
+            ValueExpr::Recover { body, handler, .. } => {
+                check_recover_control_flow(body);
+                check_recover_control_flow(handler);
+                TypeExpr::from_value_expr(&body.0, type_env);
+                TypeExpr::from_value_expr(&handler.0, type_env);
+                TypeExpr::Tuple(vec![])
+            }
             ValueExpr::BoolNegate(bool_expr) => {
                 check_type_compatability(
                     &(
@@ -483,16 +834,35 @@ impl TypeExpr {
                     type_env,
                 );
 
-                let _then_type_expr = TypeExpr::from_value_expr(&then.0, type_env);
-                if let Some(r#else) = r#else {
-                    let _else_type_expr = TypeExpr::from_value_expr(&r#else.0, type_env);
-                }
-
-                // let x: TypeExpression = combine_types(vec![else_type_expr, then]);
+                let then_type_expr = TypeExpr::from_value_expr(&then.0, type_env);
+                let Some(r#else) = r#else else {
+                    // without an else there's no value if the condition isn't met
+                    if then_type_expr == TypeExpr::Never {
+                        return then_type_expr;
+                    }
+                    return TypeExpr::Tuple(vec![]);
+                };
+                let else_type_expr = TypeExpr::from_value_expr(&r#else.0, type_env);
 
-                // todo!("combine then and else, then return combined type");
+                // a branch that never finishes doesn't contribute to the type
+                if then_type_expr == TypeExpr::Never {
+                    return else_type_expr;
+                }
+                if else_type_expr == TypeExpr::Never || then_type_expr == else_type_expr {
+                    return then_type_expr;
+                }
+                if then_type_expr.is_unit() || else_type_expr.is_unit() {
+                    return TypeExpr::Tuple(vec![]);
+                }
 
-                _then_type_expr
+                // if (x) { "a" } else { "b" } is a String, not the const string "a"
+                let if_type_expr = then_type_expr.unconst();
+                check_type_compatability(
+                    &(if_type_expr.clone(), then.1),
+                    &(else_type_expr, r#else.1),
+                    type_env,
+                );
+                if_type_expr
             }
             ValueExpr::FieldAccess {
                 target_obj,
@@ -532,6 +902,31 @@ impl TypeExpr {
 
                 return TypeExpr::Tuple(vec![]);
             }
+            ValueExpr::For { target, body, .. } => {
+                let target_type_expr =
+                    TypeExpr::from_value_expr_resolved_type_name(&target.0, type_env);
+                require(
+                    matches!(
+                        target_type_expr,
+                        TypeExpr::Array(..) | TypeExpr::Map(..) | TypeExpr::Chan(..)
+                    ),
+                    format!(
+                        "For loops can only iterate over arrays, maps, channels and ranges, not {}.",
+                        target_type_expr.as_go_type_annotation(type_env)
+                    ),
+                );
+
+                let _body_type_expr = TypeExpr::from_value_expr(&body.0, type_env);
+
+                return TypeExpr::Tuple(vec![]);
+            }
+            ValueExpr::Loop { body, break_type } => {
+                let _body_type_expr = TypeExpr::from_value_expr(&body.0, type_env);
+
+                return break_type
+                    .clone()
+                    .expect("compiler error: the type of a loop should be resolved by now");
+            }
             // TODO: Match Expressions need to be type resolved just as the function defs
             ValueExpr::Match {
                 value_expr,
@@ -544,19 +939,45 @@ impl TypeExpr {
                 }
 
                 let mut arm_types = Vec::new();
+                let mut has_never_arm = false;
                 for arm in &arms {
+                    if let Some(condition) = &arm.condition {
+                        let condition_type_expr = TypeExpr::from_value_expr(&condition.0, type_env);
+                        check_type_compatability(
+                            &(condition_type_expr, condition.1),
+                            &TypeExpr::Bool.into_empty_span(),
+                            type_env,
+                        );
+                    }
+
                     let arm_type = TypeExpr::from_value_expr(&arm.value_expr.0, type_env);
-                    if !arm_types.iter().any(|(x, _)| x == &arm_type) {
-                        arm_types.push((arm_type, arm.value_expr.1));
+                    // an arm that returns from the function doesn't give the match a value, x?
+                    // leaves only the unwrapped variants
+                    if arm_type == TypeExpr::Never
+                        || matches!(arm.value_expr.0, ValueExpr::Return(..))
+                    {
+                        has_never_arm = true;
+                        continue;
+                    }
+                    // unions of the arms are flattened, Int? and .none arms make an Int?
+                    let mut variants = Vec::new();
+                    merge_or(&(arm_type, arm.value_expr.1), &mut variants);
+                    for variant in variants {
+                        if !arm_types.iter().any(|(x, _)| *x == variant.0) {
+                            arm_types.push(variant);
+                        }
                     }
                 }
 
                 if else_arm.is_none() {
-                    let possible_types: Vec<Spanned<TypeExpr>> =
-                        match &TypeExpr::from_value_expr(&value_expr.0, type_env) {
-                            TypeExpr::Or(types) => types.clone(),
-                            other => vec![(other.clone(), value_expr.1)],
-                        };
+                    let mut possible_types: Vec<Spanned<TypeExpr>> = Vec::new();
+                    merge_or(
+                        &(
+                            TypeExpr::from_value_expr(&value_expr.0, type_env),
+                            value_expr.1,
+                        ),
+                        &mut possible_types,
+                    );
 
                     let mut covered_types = Vec::new();
                     for arm in &arms {
@@ -571,17 +992,51 @@ impl TypeExpr {
                         }
                     }
 
-                    possible_types.iter().for_each(|possible_type| {
-                        let is_covered = covered_types.iter().any(|(x, _)| *x == possible_type.0);
-                        if !is_covered {
-                            panic!("missing {possible_type:?} match arm");
-                        }
-                    });
+                    let missing_types = possible_types
+                        .iter()
+                        .filter(|possible_type| {
+                            let resolved_type = type_env.try_resolve_type_expr(&possible_type.0);
+                            !covered_types.iter().any(|(x, _)| match x {
+                                // duck patterns cover every variant that has all of their fields
+                                TypeExpr::Duck(duck) if *x != possible_type.0 => {
+                                    !resolved_type.is_tuple()
+                                        && duck.fields.iter().all(|field| {
+                                            resolved_type
+                                                .has_field_by_name(field.name.clone(), type_env)
+                                        })
+                                }
+                                x => *x == possible_type.0,
+                            })
+                        })
+                        .map(|(missing_type, _)| missing_type.as_clean_user_faced_type_name())
+                        .collect::<Vec<_>>();
+
+                    // every variant of the matched union needs an arm, unless there's an else arm
+                    if !missing_types.is_empty() {
+                        failure(
+                            &value_expr.1.context.file_name(),
+                            "Non exhaustive match".to_string(),
+                            (
+                                format!(
+                                    "this can also be {}, add {} or an else arm",
+                                    missing_types.join(" or "),
+                                    if missing_types.len() == 1 {
+                                        "an arm for it"
+                                    } else {
+                                        "arms for them"
+                                    }
+                                ),
+                                value_expr.1,
+                            ),
+                            vec![],
+                            &value_expr.1.context.file_contents(),
+                        )
+                    }
                 }
 
-                
-
-                if arm_types.is_empty() {
+                if arm_types.is_empty() && has_never_arm {
+                    TypeExpr::Never
+                } else if arm_types.is_empty() {
                     TypeExpr::Tuple(vec![])
                 } else if arm_types.len() == 1 {
                     arm_types.first().cloned().unwrap().0
@@ -646,6 +1101,8 @@ impl TypeExpr {
                     fields: _,
                     methods,
                     generics: _,
+                    doc_comments: _,
+                    attributes: Vec::new(),
                 } = type_env.get_struct_def(r#struct.as_str());
 
                 methods.iter().any(|f| f.name.as_str() == name.as_str())
@@ -667,6 +1124,8 @@ impl TypeExpr {
                     fields,
                     methods: _,
                     generics: _,
+                    doc_comments: _,
+                    attributes: Vec::new(),
                 } = type_env.get_struct_def(r#struct.as_str());
 
                 fields.iter().any(|f| f.name.as_str() == name.as_str())
@@ -688,6 +1147,8 @@ impl TypeExpr {
                     fields,
                     methods,
                     generics: _,
+                    doc_comments: _,
+                    attributes: Vec::new(),
                 } = type_env.get_struct_def(r#struct.as_str());
 
                 fields
@@ -763,6 +1224,16 @@ impl TypeExpr {
         return matches!(*self, TypeExpr::Fun(..));
     }
 
+    // a duck field of an optional type, like nickname in { nickname?: String }, can be left out
+    pub fn is_optional(&self) -> bool {
+        let TypeExpr::Or(variants) = self else {
+            return false;
+        };
+        return variants
+            .iter()
+            .any(|variant| matches!(&variant.0, TypeExpr::Tag(tag) if tag == "none"));
+    }
+
     pub fn is_char(&self) -> bool {
         return *self == TypeExpr::Char;
     }
@@ -784,6 +1255,15 @@ impl TypeExpr {
         return matches!(*self, TypeExpr::Array(..));
     }
 
+    pub fn is_map(&self) -> bool {
+        return matches!(*self, TypeExpr::Map(..));
+    }
+
+    // go can only use values as map keys that it can compare, so only the primitives are allowed
+    pub fn is_hashable(&self) -> bool {
+        return self.is_primitive();
+    }
+
     pub fn is_int(&self) -> bool {
         return *self == TypeExpr::Int;
     }
@@ -829,6 +1309,165 @@ impl TypeExpr {
     }
 }
 
+// the body and the handler of recover run in a go function of their own, so return and ? can't
+// leave them and break and continue only work for the loops inside of them
+fn check_recover_control_flow(recover_part: &Spanned<ValueExpr>) {
+    let is_loop = |value_expr: &ValueExpr| {
+        matches!(
+            value_expr,
+            ValueExpr::While { .. } | ValueExpr::For { .. } | ValueExpr::Loop { .. }
+        )
+    };
+    let contains = |outer: &SS, inner: &SS| outer.start <= inner.start && inner.end <= outer.end;
+
+    // (label, span) of the loops and the spans of the lambdas inside, they are visited before
+    // the expressions nested in them
+    let mut loops: Vec<(Option<String>, SS)> = Vec::new();
+    let mut lambdas: Vec<SS> = Vec::new();
+    let mut escape = None;
+
+    for_each_value_expr(recover_part, &mut |(value_expr, span)| {
+        if escape.is_some() || lambdas.iter().any(|lambda| contains(lambda, span)) {
+            return;
+        }
+
+        match value_expr {
+            value_expr if is_loop(value_expr) => loops.push((None, *span)),
+            ValueExpr::Labeled(label, labeled) if is_loop(&labeled.0) => {
+                loops.push((Some(label.clone()), labeled.1))
+            }
+            ValueExpr::Lambda(..) => lambdas.push(*span),
+            ValueExpr::Return(..) => escape = Some(("return", *span)),
+            ValueExpr::Propagate(..) => escape = Some(("?", *span)),
+            ValueExpr::Break(..) | ValueExpr::Continue
+                if !loops.iter().any(|(_, loop_span)| contains(loop_span, span)) =>
+            {
+                let keyword = if matches!(value_expr, ValueExpr::Continue) {
+                    "continue"
+                } else {
+                    "break"
+                };
+                escape = Some((keyword, *span));
+            }
+            ValueExpr::Labeled(label, labeled)
+                if matches!(labeled.0, ValueExpr::Break(..) | ValueExpr::Continue)
+                    && !loops.iter().any(|(loop_label, loop_span)| {
+                        loop_label.as_ref() == Some(label) && contains(loop_span, span)
+                    }) =>
+            {
+                escape = Some(("a labeled break or continue", *span));
+            }
+            _ => {}
+        }
+    });
+
+    if let Some((keyword, span)) = escape {
+        failure(
+            &span.context.file_name(),
+            format!("{keyword} can't leave a recover block"),
+            (
+                "recover and catch run in a function of their own, only loops inside of them can \
+                 be left with break and continue"
+                    .to_string(),
+                span,
+            ),
+            vec![],
+            &span.context.file_contents(),
+        );
+    }
+}
+
+// the element type of the channel that's sent to or received from
+fn channel_element_type(
+    channel: &Spanned<ValueExpr>,
+    action: &str,
+    type_env: &mut TypeEnv,
+) -> Spanned<TypeExpr> {
+    let channel_type = TypeExpr::from_value_expr_resolved_type_name(&channel.0, type_env);
+    let TypeExpr::Chan(element_type) = channel_type else {
+        failure(
+            &channel.1.context.file_name(),
+            format!("can only {action} a channel"),
+            (format!("this is {channel_type}, not a channel"), channel.1),
+            vec![],
+            &channel.1.context.file_contents(),
+        );
+    };
+
+    *element_type
+}
+
+// the parameter and return type of the builtin methods of arrays, maps and channels
+fn builtin_method_type(
+    target_type: &TypeExpr,
+    method_name: &str,
+) -> Option<(Option<Spanned<TypeExpr>>, TypeExpr)> {
+    match (target_type, method_name) {
+        (TypeExpr::Array(..) | TypeExpr::Map(..) | TypeExpr::Chan(..), "len") => {
+            Some((None, TypeExpr::Int))
+        }
+        (TypeExpr::Chan(..), "close") => Some((None, TypeExpr::Tuple(vec![]))),
+        (TypeExpr::Array(element_type), "push") => {
+            Some((Some(element_type.as_ref().clone()), TypeExpr::Tuple(vec![])))
+        }
+        (TypeExpr::Map(key_type, _), "has") => {
+            Some((Some(key_type.as_ref().clone()), TypeExpr::Bool))
+        }
+        (TypeExpr::Map(key_type, _), "remove") => {
+            Some((Some(key_type.as_ref().clone()), TypeExpr::Tuple(vec![])))
+        }
+        _ => None,
+    }
+}
+
+// numbers, chars and bools are converted by x as T, every other type can only be widened, like a
+// struct to a duck with some of its fields
+fn check_cast(value_expr: &Spanned<ValueExpr>, target: &Spanned<TypeExpr>, type_env: &mut TypeEnv) {
+    let source = TypeExpr::from_value_expr_resolved_type_name(&value_expr.0, type_env).unconst();
+    let target_type = type_env.try_resolve_type_expr(&target.0);
+
+    let hint = match (&source, &target_type) {
+        (TypeExpr::Int | TypeExpr::Float | TypeExpr::Char | TypeExpr::Bool, TypeExpr::Int)
+        | (TypeExpr::Int | TypeExpr::Float, TypeExpr::Float)
+        | (TypeExpr::Int | TypeExpr::Char, TypeExpr::Char) => return,
+        (source, target_type) if source == target_type => return,
+        (TypeExpr::String, TypeExpr::Int | TypeExpr::Float) => format!(
+            "a String can't be cast to {target_type}, std::string::parse_{} parses it and gives \
+             .none if it isn't a number",
+            target_type.as_clean_user_faced_type_name().to_lowercase()
+        ),
+        (_, TypeExpr::String) => format!(
+            "a {source} can't be cast to String, use a format string like f\"{{x}}\" instead"
+        ),
+        (_, TypeExpr::Int | TypeExpr::Float | TypeExpr::Char | TypeExpr::Bool) => format!(
+            "a {source} can't be cast to {target_type}, only numbers, chars and bools can be \
+             converted"
+        ),
+        _ => {
+            check_type_compatability(target, &(source, value_expr.1), type_env);
+            return;
+        }
+    };
+
+    let span = value_expr.1.union(target.1);
+    failure(
+        &span.context.file_name(),
+        "invalid cast".to_string(),
+        (hint, span),
+        vec![],
+        &span.context.file_contents(),
+    );
+}
+
+// the value of a slice bound if it's an int literal
+fn const_slice_bound(value_expr: &ValueExpr) -> Option<i128> {
+    match value_expr {
+        ValueExpr::Int(i) => Some(*i),
+        ValueExpr::Negate(value_expr) => const_slice_bound(&value_expr.0).map(|i| -i),
+        _ => None,
+    }
+}
+
 fn require(condition: bool, fail_message: String) {
     if !condition {
         println!("TypeError: {fail_message}");
@@ -901,7 +1540,7 @@ fn require_subset_of_variant_type(
             for other_member in other_members {
                 if !is_non_variant_type_in_variant(other_member, variant_members, type_env) {
                     failure(
-                        variant_type.1.context.file_name,
+                        &variant_type.1.context.file_name(),
                         "Incompatible Variant Types".to_string(),
                         (
                             format!(
@@ -917,7 +1556,7 @@ fn require_subset_of_variant_type(
                             ),
                             variant_type.1,
                         )],
-                        variant_type.1.context.file_contents,
+                        &variant_type.1.context.file_contents(),
                     );
                 }
             }
@@ -925,7 +1564,7 @@ fn require_subset_of_variant_type(
         _ => {
             if !is_non_variant_type_in_variant(other, variant_members, type_env) {
                 failure(
-                    other.1.context.file_name,
+                    &other.1.context.file_name(),
                     "Incompatible Types".to_string(),
                     (
                         format!(
@@ -941,20 +1580,26 @@ fn require_subset_of_variant_type(
                         ),
                         variant_type.1,
                     )],
-                    other.1.context.file_contents,
+                    &other.1.context.file_contents(),
                 );
             }
         }
     }
 }
 
-fn check_type_compatability(
+pub fn check_type_compatability(
     required_type: &Spanned<TypeExpr>,
     given_type: &Spanned<TypeExpr>,
     type_env: &mut TypeEnv,
 ) {
     let mut given_type = given_type.clone();
     given_type.0 = type_env.try_resolve_type_expr(&given_type.0);
+
+    // never can be used as every type, the code that would use the value doesn't run
+    if given_type.0 == TypeExpr::Never {
+        return;
+    }
+
     let fail_requirement = |explain_required: String, explain_given: String| {
         let (smaller, larger) = if required_type.1.start <= given_type.1.start {
             (required_type.1, given_type.1)
@@ -970,14 +1615,14 @@ fn check_type_compatability(
         };
 
         failure_with_occurence(
-            given_type.1.context.file_name,
+            &given_type.1.context.file_name(),
             "Incompatible Types".to_string(),
             given_type.1,
             vec![
                 (explain_required.to_string(), required_type.1),
                 (explain_given.to_string(), given_type.1),
             ],
-            given_type.1.context.file_contents,
+            &given_type.1.context.file_contents(),
         )
     };
 
@@ -1001,6 +1646,13 @@ fn check_type_compatability(
         TypeExpr::TypeOf(..) => panic!("typeof should have been replaced"),
         TypeExpr::Alias(..) => panic!("alias should have been replaced"),
         TypeExpr::Any => return,
+        TypeExpr::Never => fail_requirement(
+            "nothing can be passed where never is required".to_string(),
+            format!(
+                "but you've provided {}",
+                format!("{}", given_type.0).bright_yellow()
+            ),
+        ),
         TypeExpr::InlineGo => todo!("should inline go be typechecked?"),
         TypeExpr::Go(_) => return,
         TypeExpr::Tag(required_identifier) => {
@@ -1061,6 +1713,16 @@ fn check_type_compatability(
                         .iter()
                         .find(|field| field.name == required_field.name);
 
+                    // an optional field can be missing, it's filled with .none when the value
+                    // is copied into the required duck
+                    if companion_field.is_none()
+                        && type_env
+                            .try_resolve_type_expr(&required_field.type_expr.0)
+                            .is_optional()
+                    {
+                        continue;
+                    }
+
                     if companion_field.is_none() {
                         fail_requirement(
                             format!(
@@ -1114,7 +1776,7 @@ fn check_type_compatability(
                             &companion_method.type_expr(),
                             type_env,
                         );
-                        return;
+                        continue;
                     }
 
                     let companion_field = struct_def
@@ -1122,6 +1784,14 @@ fn check_type_compatability(
                         .iter()
                         .find(|field| field.name == required_field.name);
 
+                    if companion_field.is_none()
+                        && type_env
+                            .try_resolve_type_expr(&required_field.type_expr.0)
+                            .is_optional()
+                    {
+                        continue;
+                    }
+
                     if companion_field.is_none() {
                         fail_requirement(
                             format!(
@@ -1368,8 +2038,31 @@ fn check_type_compatability(
                     .expect("we've just handled is_none");
 
                 check_type_compatability(required_return_type, given_return_type, type_env);
+            } else if let Some(given_return_type) = given_return_type
+                && !given_return_type.0.is_unit()
+            {
+                // go only accepts func(x) for func(x) if neither of them returns something
+                fail_requirement(
+                    "this requires a function that doesn't return anything".to_string(),
+                    format!("this function returns {}", given_return_type.0),
+                )
             }
         }
+        TypeExpr::Map(key_type, value_type) => {
+            let TypeExpr::Map(given_key_type, given_value_type) = &given_type.0 else {
+                fail_requirement(
+                    format!(
+                        "this requires a map from {} to {}",
+                        key_type.0, value_type.0
+                    ),
+                    "this is not a map".to_string(),
+                );
+                return;
+            };
+
+            check_type_compatability(key_type, given_key_type, type_env);
+            check_type_compatability(value_type, given_value_type, type_env);
+        }
         TypeExpr::Array(content_type) => {
             if !given_type.0.is_array() {
                 fail_requirement(
@@ -1384,22 +2077,42 @@ fn check_type_compatability(
 
             check_type_compatability(content_type, &given_content_type, type_env);
         }
+        TypeExpr::Chan(element_type) => {
+            let TypeExpr::Chan(given_element_type) = &given_type.0 else {
+                fail_requirement(
+                    format!("this requires a channel of {}", element_type.0),
+                    "this is not a channel".to_string(),
+                );
+                return;
+            };
+
+            // go channels can't be converted, so the element types have to match both ways
+            check_type_compatability(element_type, given_element_type, type_env);
+            check_type_compatability(given_element_type, element_type, type_env);
+        }
         TypeExpr::RawTypeName(..) | TypeExpr::TypeName(..) | TypeExpr::TypeNameInternal(..) => {}
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::path::PathBuf;
+
     use crate::{
         parse::{
             SS,
             function_parser::FunctionDefintion,
             lexer::lex_parser,
             make_input,
-            source_file_parser::SourceFile,
+            source_file_parser::{SourceFile, source_file_parser},
+            source_map::{self, FileId},
             value_parser::{empty_range, type_expr_into_empty_range, value_expr_parser},
         },
-        semantics::type_resolve::{TypesSummary, typeresolve_source_file},
+        semantics::{
+            const_eval::evaluate_comptime,
+            ident_mangler::mangle,
+            type_resolve::{TypesSummary, typeresolve_source_file},
+        },
     };
     use chumsky::prelude::*;
 
@@ -1423,6 +2136,17 @@ mod test {
             ("0.1 + 0.4", TypeExpr::Float),
             ("0 + 0.4", TypeExpr::Int),
             ("0.4 + 0", TypeExpr::Float),
+            ("\"Hallo\" + \", Welt\"", TypeExpr::String),
+            ("\"quak\" * 3", TypeExpr::String),
+            ("\"a\" < \"b\"", TypeExpr::Bool),
+            ("\"quak\"[1..3]", TypeExpr::String),
+            (
+                "{ let xs: Int[] = [1, 2, 3]; xs[1..] }",
+                TypeExpr::Array(TypeExpr::Int.into_empty_span().into()),
+            ),
+            ("if (true) { 1 } else { 2 }", TypeExpr::Int),
+            ("if (true) { \"a\" } else { \"b\" }", TypeExpr::String),
+            ("if (true) { 1 }", TypeExpr::Tuple(vec![])),
             (
                 "(0, 2)",
                 TypeExpr::Tuple(vec![
@@ -1462,11 +2186,46 @@ mod test {
             ("{ let x: Int = 5; 5 }", TypeExpr::Int),
             ("{ let x: Int = 5; x }", TypeExpr::Int),
             ("{ let x: Int = 5; x * x }", TypeExpr::Int),
+            ("{ let s = \"a\"; s }", TypeExpr::String),
+            ("{ let x = 4 + 4; x }", TypeExpr::Int),
+            (
+                "{ let d = { name: \"Ada\", age: 3 }; d }",
+                TypeExpr::Duck(Duck {
+                    fields: vec![
+                        Field::new("age".to_string(), TypeExpr::Int.into_empty_span()),
+                        Field::new("name".to_string(), TypeExpr::String.into_empty_span()),
+                    ],
+                }),
+            ),
+            (
+                "{ let pair = (1, \"a\"); pair }",
+                TypeExpr::Tuple(vec![
+                    TypeExpr::Int.into_empty_span(),
+                    TypeExpr::String.into_empty_span(),
+                ]),
+            ),
+            ("{ let xs: Int[] = [1, 2]; xs.len() }", TypeExpr::Int),
+            (
+                "{ let xs: Int[] = [1, 2]; xs.push(3) }",
+                TypeExpr::Tuple(vec![]),
+            ),
+            (
+                "{ let m: map<String, Int> = #{\"a\": 1}; m[\"a\"] }",
+                TypeExpr::Int,
+            ),
+            (
+                "{ let m: map<String, Int> = #{\"a\": 1}; m.has(\"b\") }",
+                TypeExpr::Bool,
+            ),
+            (
+                "{ let m: map<String, Int> = #<String, Int>{}; m.len() }",
+                TypeExpr::Int,
+            ),
         ];
 
         for (src, expected_type_expr) in src_and_expected_type_vec {
             println!("lexing {src}");
-            let lexer_parse_result = lex_parser("test", "").parse(src);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(src);
             assert_eq!(lexer_parse_result.has_errors(), false);
             assert_eq!(lexer_parse_result.has_output(), true);
 
@@ -1488,6 +2247,10 @@ mod test {
                     return_type: None,
                     value_expr: value_expr,
                     generics: None,
+                    doc_comments: Vec::new(),
+                    attributes: Vec::new(),
+                    param_defaults: Vec::new(),
+                    variadic: false,
                 }],
                 ..Default::default()
             };
@@ -1625,7 +2388,7 @@ mod test {
         ];
 
         for (src, summary_check_fun) in src_and_summary_check_funs {
-            let lexer_parse_result = lex_parser("test", "").parse(src);
+            let lexer_parse_result = lex_parser(FileId::EMPTY).parse(src);
             assert_eq!(lexer_parse_result.has_errors(), false, "Couldn't lex {src}");
             assert_eq!(lexer_parse_result.has_output(), true, "Couldn't lex {src}");
 
@@ -1656,6 +2419,10 @@ mod test {
                     return_type: None,
                     value_expr: value_expr,
                     generics: None,
+                    doc_comments: Vec::new(),
+                    attributes: Vec::new(),
+                    param_defaults: Vec::new(),
+                    variadic: false,
                 }],
                 ..Default::default()
             };
@@ -1860,12 +2627,76 @@ mod test {
         );
     }
 
+    fn typeresolve_src(src: &str) -> SourceFile {
+        let file_id = source_map::add_test_file("typechecker_test", src);
+        let tokens = lex_parser(file_id).parse(src).into_result().expect(src);
+        let mut source_file = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .expect(src);
+        evaluate_comptime(&mut source_file);
+        let mut source_file = source_file.flatten(&vec![], false);
+        typeresolve_source_file(&mut source_file, &mut TypeEnv::default());
+        source_file
+    }
+
+    #[test]
+    fn test_local_with_the_name_of_a_const_of_another_module() {
+        let source_file = typeresolve_src(
+            "const N = 1; module a { pub fn f(N: Int) -> Int { let M = N; return M; } \
+             pub fn g() -> Int { let N = 2; return N; } } fn main() { a::f(1); a::g(); }",
+        );
+
+        for name in [mangle(&["a", "f"]), mangle(&["a", "g"])] {
+            let function = source_file
+                .function_definitions
+                .iter()
+                .find(|function| function.name == name)
+                .unwrap();
+            let mut names = function
+                .params
+                .iter()
+                .flatten()
+                .map(|param| param.0.clone())
+                .collect::<Vec<_>>();
+            for_each_value_expr(
+                &function.value_expr,
+                &mut |(value_expr, _)| match value_expr {
+                    ValueExpr::Variable(_, name, _) => names.push(name.clone()),
+                    ValueExpr::VarDecl(declaration) => names.push(declaration.0.name.clone()),
+                    _ => {}
+                },
+            );
+
+            // the local is renamed, a use of N would be emitted as the go const N otherwise
+            assert!(names.contains(&"Nⸯlocal".to_string()), "{names:?}");
+            assert!(!names.contains(&"N".to_string()), "{names:?}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "N is a const and can't be redeclared")]
+    fn test_local_hides_const() {
+        typeresolve_src("const N = 1; fn main() { let N = 2; }");
+    }
+
+    #[test]
+    #[should_panic(expected = "a::N is a const and can't be redeclared")]
+    fn test_local_hides_const_of_its_module() {
+        typeresolve_src("module a { const N = 1; fn f() { let N = 2; } }");
+    }
+
+    #[test]
+    #[should_panic(expected = "a::N is a const and can't be redeclared")]
+    fn test_param_hides_const_of_its_module() {
+        typeresolve_src("module a { const N = 1; fn f(N: Int) {} }");
+    }
+
     fn empty_spanned<T>(item: T) -> Spanned<T> {
         use crate::parse::Context as SourceFileContext;
 
         let context = SourceFileContext {
-            file_name: "test",
-            file_contents: "",
+            file_id: FileId::EMPTY,
         };
 
         return (
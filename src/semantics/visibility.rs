@@ -0,0 +1,614 @@
+use std::collections::HashMap;
+
+use crate::{
+    parse::{
+        SS, Spanned, failure,
+        function_parser::FunctionDefintion,
+        source_file_parser::{PrivateItem, SourceFile},
+        struct_parser::StructDefinition,
+        type_parser::{Duck, TypeExpr},
+        value_parser::{ValFmtStringContents, ValHtmlStringContents, ValueExpr},
+    },
+    semantics::{ident_mangler::unmangle, type_resolve::TypeEnv},
+};
+
+// the module of a flattened item, that is its mangled name without the last part
+fn module_of(name: &str) -> Vec<String> {
+    let mut module = unmangle(name);
+    module.pop();
+    module
+}
+
+fn module_display(module: &[String]) -> String {
+    if module.is_empty() {
+        "the root module".to_string()
+    } else {
+        format!("the module {}", module.join("::"))
+    }
+}
+
+// a report only shows a single file, so a declaration in another file can't be labeled
+fn declaration_label(span: SS, declaration_span: SS) -> Vec<(String, SS)> {
+    if span.context == declaration_span.context {
+        vec![("declared here without pub".to_string(), declaration_span)]
+    } else {
+        vec![]
+    }
+}
+
+fn private_item_failure(private_item: &PrivateItem, span: SS) -> ! {
+    let name = unmangle(&private_item.name).join("::");
+    failure(
+        &span.context.file_name(),
+        format!("{name} is private"),
+        (
+            format!(
+                "{name} isn't pub, so it can only be used in {} and its sub modules",
+                module_display(&private_item.module)
+            ),
+            span,
+        ),
+        declaration_label(span, private_item.span),
+        &span.context.file_contents(),
+    )
+}
+
+fn private_field_failure(
+    struct_name: &str,
+    field_name: &str,
+    module: &[String],
+    declaration_span: SS,
+    span: SS,
+) -> ! {
+    let struct_name = unmangle(struct_name).join("::");
+    failure(
+        &span.context.file_name(),
+        format!("the field {field_name} of {struct_name} is private"),
+        (
+            format!(
+                "{field_name} isn't pub, so it can only be used in {} and its sub modules",
+                module_display(module)
+            ),
+            span,
+        ),
+        declaration_label(span, declaration_span),
+        &span.context.file_contents(),
+    )
+}
+
+// calls f for the value expression and every value expression nested in it
+pub(crate) fn for_each_value_expr(
+    value_expr: &Spanned<ValueExpr>,
+    f: &mut impl FnMut(&Spanned<ValueExpr>),
+) {
+    f(value_expr);
+
+    match &value_expr.0 {
+        ValueExpr::FunctionCall { target, params, .. } => {
+            for_each_value_expr(target, f);
+            for param in params {
+                for_each_value_expr(param, f);
+            }
+        }
+        ValueExpr::If {
+            condition,
+            then,
+            r#else,
+        } => {
+            for_each_value_expr(condition, f);
+            for_each_value_expr(then, f);
+            if let Some(r#else) = r#else {
+                for_each_value_expr(r#else, f);
+            }
+        }
+        ValueExpr::While { condition, body } => {
+            for_each_value_expr(condition, f);
+            for_each_value_expr(body, f);
+        }
+        ValueExpr::For { target, body, .. } => {
+            for_each_value_expr(target, f);
+            for_each_value_expr(body, f);
+        }
+        ValueExpr::Loop { body, .. } => for_each_value_expr(body, f),
+        ValueExpr::Tuple(value_exprs)
+        | ValueExpr::Block(value_exprs)
+        | ValueExpr::Array(_, value_exprs) => {
+            for value_expr in value_exprs {
+                for_each_value_expr(value_expr, f);
+            }
+        }
+        ValueExpr::Duck(fields) | ValueExpr::Struct { fields, .. } => {
+            for (_, value_expr) in fields {
+                for_each_value_expr(value_expr, f);
+            }
+        }
+        ValueExpr::Map(_, entries) => {
+            for (key, value) in entries {
+                for_each_value_expr(key, f);
+                for_each_value_expr(value, f);
+            }
+        }
+        ValueExpr::HtmlString(contents) => {
+            for content in contents {
+                if let ValHtmlStringContents::Expr(value_expr) = content {
+                    for_each_value_expr(value_expr, f);
+                }
+            }
+        }
+        ValueExpr::FormattedString(contents) => {
+            for content in contents {
+                if let ValFmtStringContents::Expr(value_expr, _) = content {
+                    for_each_value_expr(value_expr, f);
+                }
+            }
+        }
+        ValueExpr::FieldAccess { target_obj, .. }
+        | ValueExpr::OptionalFieldAccess { target_obj, .. } => for_each_value_expr(target_obj, f),
+        ValueExpr::VarAssign(assignment) => {
+            for_each_value_expr(&assignment.0.target, f);
+            for_each_value_expr(&assignment.0.value_expr, f);
+        }
+        ValueExpr::VarDecl(declaration) => for_each_value_expr(&declaration.0.initializer, f),
+        ValueExpr::Add(lhs, rhs)
+        | ValueExpr::Sub(lhs, rhs)
+        | ValueExpr::Mul(lhs, rhs)
+        | ValueExpr::Div(lhs, rhs)
+        | ValueExpr::Mod(lhs, rhs)
+        | ValueExpr::BitAnd(lhs, rhs)
+        | ValueExpr::BitOr(lhs, rhs)
+        | ValueExpr::BitXor(lhs, rhs)
+        | ValueExpr::ShiftLeft(lhs, rhs)
+        | ValueExpr::ShiftRight(lhs, rhs)
+        | ValueExpr::Equals(lhs, rhs)
+        | ValueExpr::NotEquals(lhs, rhs)
+        | ValueExpr::LessThan(lhs, rhs)
+        | ValueExpr::LessThanOrEquals(lhs, rhs)
+        | ValueExpr::GreaterThan(lhs, rhs)
+        | ValueExpr::GreaterThanOrEquals(lhs, rhs)
+        | ValueExpr::And(lhs, rhs)
+        | ValueExpr::Or(lhs, rhs)
+        | ValueExpr::Update(lhs, rhs)
+        | ValueExpr::Coalesce(lhs, rhs)
+        | ValueExpr::ArrayAccess(lhs, rhs)
+        | ValueExpr::Send(lhs, rhs)
+        | ValueExpr::Range {
+            start: lhs,
+            end: rhs,
+            ..
+        } => {
+            for_each_value_expr(lhs, f);
+            for_each_value_expr(rhs, f);
+        }
+        ValueExpr::Slice {
+            target, start, end, ..
+        } => {
+            for_each_value_expr(target, f);
+            for bound in [start, end].into_iter().flatten() {
+                for_each_value_expr(bound, f);
+            }
+        }
+        ValueExpr::BoolNegate(value_expr)
+        | ValueExpr::BitNegate(value_expr)
+        | ValueExpr::TypeOf(value_expr)
+        | ValueExpr::Cast(value_expr, _)
+        | ValueExpr::Negate(value_expr)
+        | ValueExpr::Comptime(value_expr)
+        | ValueExpr::Defer(value_expr)
+        | ValueExpr::Spawn(value_expr)
+        | ValueExpr::Receive(value_expr)
+        | ValueExpr::Spread(value_expr)
+        | ValueExpr::Labeled(_, value_expr)
+        | ValueExpr::Propagate(value_expr)
+        | ValueExpr::MakeChan {
+            capacity: Some(value_expr),
+            ..
+        }
+        | ValueExpr::Return(Some(value_expr))
+        | ValueExpr::Break(Some(value_expr)) => for_each_value_expr(value_expr, f),
+        ValueExpr::Lambda(lambda) => for_each_value_expr(&lambda.value_expr, f),
+        ValueExpr::Match {
+            value_expr,
+            arms,
+            else_arm,
+        } => {
+            for_each_value_expr(value_expr, f);
+            for arm in arms.iter().chain(else_arm.as_deref()) {
+                if let Some(condition) = &arm.condition {
+                    for_each_value_expr(condition, f);
+                }
+                for_each_value_expr(&arm.value_expr, f);
+            }
+        }
+        ValueExpr::Select { arms, else_arm } => {
+            for arm in arms {
+                for_each_value_expr(&arm.case, f);
+                for_each_value_expr(&arm.value_expr, f);
+            }
+            if let Some(else_arm) = else_arm {
+                for_each_value_expr(else_arm, f);
+            }
+        }
+        ValueExpr::Recover { body, handler, .. } => {
+            for_each_value_expr(body, f);
+            for_each_value_expr(handler, f);
+        }
+        ValueExpr::Int(..)
+        | ValueExpr::String(..)
+        | ValueExpr::Bool(..)
+        | ValueExpr::Float(..)
+        | ValueExpr::Char(..)
+        | ValueExpr::RawVariable(..)
+        | ValueExpr::Variable(..)
+        | ValueExpr::Tag(..)
+        | ValueExpr::InlineGo(..)
+        | ValueExpr::Return(None)
+        | ValueExpr::Break(None)
+        | ValueExpr::MakeChan { capacity: None, .. }
+        | ValueExpr::Continue => {}
+    }
+}
+
+// calls f with the name and the span of every named type in the type expression
+pub(crate) fn for_each_type_name(type_expr: &Spanned<TypeExpr>, f: &mut impl FnMut(&str, SS)) {
+    match &type_expr.0 {
+        TypeExpr::TypeName(_, name, type_params) => {
+            f(name, type_expr.1);
+            for type_param in type_params.iter().flatten() {
+                for_each_type_name(type_param, f);
+            }
+        }
+        TypeExpr::Struct(name) => f(name, type_expr.1),
+        TypeExpr::Duck(Duck { fields }) => {
+            for field in fields {
+                for_each_type_name(&field.type_expr, f);
+            }
+        }
+        TypeExpr::Tuple(type_exprs) | TypeExpr::Or(type_exprs) => {
+            for type_expr in type_exprs {
+                for_each_type_name(type_expr, f);
+            }
+        }
+        TypeExpr::Fun(params, return_type) => {
+            for (_, param) in params {
+                for_each_type_name(param, f);
+            }
+            if let Some(return_type) = return_type {
+                for_each_type_name(return_type, f);
+            }
+        }
+        TypeExpr::Array(type_expr) | TypeExpr::Chan(type_expr) => for_each_type_name(type_expr, f),
+        TypeExpr::Map(key, value) => {
+            for_each_type_name(key, f);
+            for_each_type_name(value, f);
+        }
+        _ => {}
+    }
+}
+
+struct ItemVisibility<'a> {
+    private_items: HashMap<&'a str, &'a PrivateItem>,
+    struct_definitions: HashMap<&'a str, &'a StructDefinition>,
+}
+
+impl ItemVisibility<'_> {
+    // a private item can be used in the module it's declared in and all sub modules of it
+    fn check_name(&self, name: &str, span: SS, module: &[String]) {
+        if let Some(private_item) = self.private_items.get(name)
+            && !module.starts_with(&private_item.module)
+        {
+            private_item_failure(private_item, span);
+        }
+    }
+
+    fn check_type_expr(&self, type_expr: &Spanned<TypeExpr>, module: &[String]) {
+        for_each_type_name(type_expr, &mut |name, span| {
+            self.check_name(name, span, module)
+        });
+    }
+
+    fn check_type_params(&self, type_params: &Option<Vec<Spanned<TypeExpr>>>, module: &[String]) {
+        for type_param in type_params.iter().flatten() {
+            self.check_type_expr(type_param, module);
+        }
+    }
+
+    fn check_value_expr(&self, value_expr: &Spanned<ValueExpr>, module: &[String]) {
+        for_each_value_expr(value_expr, &mut |(value_expr, span)| match value_expr {
+            ValueExpr::Variable(_, name, _) => self.check_name(name, *span, module),
+            ValueExpr::FunctionCall { type_params, .. } => {
+                self.check_type_params(type_params, module)
+            }
+            ValueExpr::Struct {
+                name,
+                fields,
+                type_params,
+            } => {
+                self.check_name(name, *span, module);
+                self.check_type_params(type_params, module);
+                self.check_struct_fields(name, fields, module);
+            }
+            ValueExpr::VarDecl(declaration) => {
+                if let Some(type_expr) = &declaration.0.type_expr {
+                    self.check_type_expr(type_expr, module);
+                }
+            }
+            ValueExpr::Array(Some(type_expr), _)
+            | ValueExpr::Map(Some(type_expr), _)
+            | ValueExpr::MakeChan {
+                element_type: type_expr,
+                ..
+            }
+            | ValueExpr::Cast(_, type_expr) => self.check_type_expr(type_expr, module),
+            ValueExpr::Lambda(lambda) => {
+                for (_, type_expr) in &lambda.params {
+                    self.check_type_expr(type_expr, module);
+                }
+                if let Some(return_type) = &lambda.return_type {
+                    self.check_type_expr(return_type, module);
+                }
+            }
+            ValueExpr::Match { arms, else_arm, .. } => {
+                for arm in arms.iter().chain(else_arm.as_deref()) {
+                    self.check_type_expr(&arm.type_case, module);
+                }
+            }
+            _ => {}
+        });
+    }
+
+    // Point { x: 1 } sets every field, so all of them have to be visible
+    fn check_struct_fields(
+        &self,
+        struct_name: &str,
+        fields: &[(String, Spanned<ValueExpr>)],
+        module: &[String],
+    ) {
+        let Some(struct_definition) = self.struct_definitions.get(struct_name) else {
+            return;
+        };
+        let struct_module = module_of(struct_name);
+        if module.starts_with(&struct_module) {
+            return;
+        }
+
+        for (field_name, (_, span)) in fields {
+            if let Some(field) = struct_definition
+                .fields
+                .iter()
+                .find(|field| field.name == *field_name && !field.is_pub)
+            {
+                private_field_failure(
+                    struct_name,
+                    field_name,
+                    &struct_module,
+                    field.type_expr.1,
+                    *span,
+                );
+            }
+        }
+    }
+
+    fn check_function(&self, function_definition: &FunctionDefintion, module: &[String]) {
+        for (_, type_expr) in function_definition.params.iter().flatten() {
+            self.check_type_expr(type_expr, module);
+        }
+        if let Some(return_type) = &function_definition.return_type {
+            self.check_type_expr(return_type, module);
+        }
+        for default in &function_definition.param_defaults {
+            self.check_value_expr(default, module);
+        }
+        self.check_value_expr(&function_definition.value_expr, module);
+    }
+}
+
+// rejects every use of a private item outside of the module it's declared in, this runs on the
+// flattened source file before the type resolution
+pub fn check_item_visibility(source_file: &SourceFile) {
+    let visibility = ItemVisibility {
+        private_items: source_file
+            .private_items
+            .iter()
+            .map(|private_item| (private_item.name.as_str(), private_item))
+            .collect(),
+        struct_definitions: source_file
+            .struct_definitions
+            .iter()
+            .map(|struct_definition| (struct_definition.name.as_str(), struct_definition))
+            .collect(),
+    };
+
+    for function_definition in &source_file.function_definitions {
+        let module = module_of(&function_definition.name);
+        visibility.check_function(function_definition, &module);
+    }
+
+    for type_definition in &source_file.type_definitions {
+        let module = module_of(&type_definition.name);
+        visibility.check_type_expr(&type_definition.type_expression, &module);
+    }
+
+    for global in &source_file.global_variables {
+        let module = module_of(&global.name);
+        if let Some(type_expr) = &global.type_expr {
+            visibility.check_type_expr(type_expr, &module);
+        }
+        visibility.check_value_expr(&global.initializer, &module);
+    }
+
+    for struct_definition in &source_file.struct_definitions {
+        let module = module_of(&struct_definition.name);
+        for field in &struct_definition.fields {
+            visibility.check_type_expr(&field.type_expr, &module);
+        }
+        for method in &struct_definition.methods {
+            visibility.check_function(method, &module);
+        }
+    }
+
+    for duckx_component in &source_file.duckx_components {
+        let module = module_of(&duckx_component.name);
+        visibility.check_type_expr(&duckx_component.props_type, &module);
+        visibility.check_value_expr(&duckx_component.value_expr, &module);
+    }
+}
+
+// generic instances are named after their struct and their type params, which can contain the
+// mangle separator as well, so the module is taken from the generic struct
+fn struct_module(struct_name: &str, type_env: &TypeEnv) -> Vec<String> {
+    let generic_struct = type_env
+        .struct_definitions
+        .iter()
+        .filter(|struct_definition| {
+            struct_definition.generics.is_some()
+                && struct_name.starts_with(&format!("{}_", struct_definition.name))
+        })
+        .max_by_key(|struct_definition| struct_definition.name.len());
+    module_of(generic_struct.map_or(struct_name, |struct_definition| {
+        struct_definition.name.as_str()
+    }))
+}
+
+fn check_field_access(value_expr: &Spanned<ValueExpr>, module: &[String], type_env: &mut TypeEnv) {
+    let ValueExpr::FieldAccess {
+        target_obj,
+        field_name,
+    } = &value_expr.0
+    else {
+        return;
+    };
+    let TypeExpr::Struct(struct_name) =
+        TypeExpr::from_value_expr_resolved_type_name(&target_obj.0, type_env)
+    else {
+        return;
+    };
+
+    // methods are always public, only fields can be private
+    let Some(field) = type_env
+        .get_struct_def(&struct_name)
+        .fields
+        .iter()
+        .find(|field| field.name == *field_name)
+    else {
+        return;
+    };
+
+    let struct_module = struct_module(&struct_name, type_env);
+    if !field.is_pub && !module.starts_with(&struct_module) {
+        private_field_failure(
+            &struct_name,
+            field_name,
+            &struct_module,
+            field.type_expr.1,
+            value_expr.1,
+        );
+    }
+}
+
+// the type of a field access is only known after the type resolution, so private fields are
+// checked afterwards. generic functions are checked through their instances
+pub fn check_field_visibility(source_file: &SourceFile, type_env: &mut TypeEnv) {
+    for function_definition in &source_file.function_definitions {
+        if function_definition.generics.is_some() {
+            continue;
+        }
+        let module = module_of(&function_definition.name);
+        for_each_value_expr(&function_definition.value_expr, &mut |value_expr| {
+            check_field_access(value_expr, &module, type_env)
+        });
+    }
+
+    for global in &source_file.global_variables {
+        let module = module_of(&global.name);
+        for_each_value_expr(&global.initializer, &mut |value_expr| {
+            check_field_access(value_expr, &module, type_env)
+        });
+    }
+
+    for struct_definition in &source_file.struct_definitions {
+        if struct_definition.generics.is_some() {
+            continue;
+        }
+        let module = struct_module(&struct_definition.name, type_env);
+        for method in &struct_definition.methods {
+            if method.generics.is_some() {
+                continue;
+            }
+            for_each_value_expr(&method.value_expr, &mut |value_expr| {
+                check_field_access(value_expr, &module, type_env)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chumsky::Parser;
+
+    use crate::parse::{
+        lexer::lex_parser, make_input, source_file_parser::source_file_parser, source_map,
+        value_parser::empty_range,
+    };
+
+    use super::*;
+
+    fn check(src: &str) {
+        let file_id = source_map::add_test_file("visibility_test", src);
+        let tokens = lex_parser(file_id).parse(src).into_result().expect(src);
+        let source_file = source_file_parser(PathBuf::from("test_files"), make_input)
+            .parse(make_input(empty_range(), &tokens))
+            .into_result()
+            .expect(src);
+        check_item_visibility(&source_file.flatten(&vec![], false));
+    }
+
+    #[test]
+    fn test_visible_items() {
+        let test_cases = vec![
+            "module a { pub fn f() {} } fn main() { a::f(); }",
+            "module a { fn g() {} pub fn f() { g(); } } fn main() { a::f(); }",
+            "fn helper() {} module a { pub fn f() { ::helper(); } }",
+            "module a { fn g() {} module b { fn f() { ::a::g(); } } }",
+            "module a { pub type T = { x: Int }; } fn f(t: a::T) {}",
+            "module a { pub struct P = { pub x: Int }; } fn f() -> a::P { return a::P { x: 1 }; }",
+            "module a { struct P = { x: Int }; fn make() -> P { return P { x: 1 }; } }",
+            "module a { pub let n = 1; } let m = a::n; fn main() { a::n; }",
+        ];
+
+        for src in test_cases {
+            check(src);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "a::g is private")]
+    fn test_private_function() {
+        check("module a { fn g() {} } fn main() { a::g(); }");
+    }
+
+    #[test]
+    #[should_panic(expected = "a::g is private")]
+    fn test_private_function_in_sibling_module() {
+        check("module a { fn g() {} } module b { fn f() { ::a::g(); } }");
+    }
+
+    #[test]
+    #[should_panic(expected = "a::T is private")]
+    fn test_private_type() {
+        check("module a { type T = { x: Int }; } fn f(t: a::T) {}");
+    }
+
+    #[test]
+    #[should_panic(expected = "a::n is private")]
+    fn test_private_global() {
+        check("module a { let n = 1; } let m = a::n;");
+    }
+
+    #[test]
+    #[should_panic(expected = "the field y of a::P is private")]
+    fn test_private_field() {
+        check("module a { pub struct P = { y: Int }; } fn f() -> a::P { return a::P { y: 2 }; }");
+    }
+}
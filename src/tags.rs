@@ -17,6 +17,7 @@ pub enum Tag {
     Git,
     Build,
     Run,
+    Test,
     Dependency,
     Setup,
     IO,
@@ -69,6 +70,17 @@ impl Display for Tag {
                     })
                     .bright_white()
             ),
+            Self::Test => write!(
+                f,
+                "{}",
+                " test "
+                    .on_color(Color::TrueColor {
+                        r: 20,
+                        g: 80,
+                        b: 80
+                    })
+                    .bright_white()
+            ),
             Self::Dependency => write!(
                 f,
                 "{}",